@@ -0,0 +1,380 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Activity Queries
+//!
+//! Filter, sort and grouping definitions used to query the local activity store.
+//! Queries are built from MCP tool arguments and executed by
+//! [`Database::query_activities`](crate::database::Database::query_activities).
+
+use crate::constants::limits::DEFAULT_ACTIVITIES_LIMIT;
+use crate::models::{Activity, SportType};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Field used to order query results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivitySortField {
+    #[default]
+    StartDate,
+    Distance,
+    Duration,
+    ElevationGain,
+    HeartRate,
+}
+
+impl ActivitySortField {
+    /// Parse a sort field from a tool argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "date" | "start_date" => Some(Self::StartDate),
+            "distance" => Some(Self::Distance),
+            "duration" | "time" => Some(Self::Duration),
+            "elevation" | "elevation_gain" => Some(Self::ElevationGain),
+            "heart_rate" | "average_heart_rate" | "hr" => Some(Self::HeartRate),
+            _ => None,
+        }
+    }
+
+    /// Column in the `activities` table backing this field
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::StartDate => "start_date",
+            Self::Distance => "distance_meters",
+            Self::Duration => "duration_seconds",
+            Self::ElevationGain => "elevation_gain",
+            Self::HeartRate => "average_heart_rate",
+        }
+    }
+}
+
+/// Sort direction for query results
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    /// Parse a sort order from a tool argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "asc" | "ascending" => Some(Self::Asc),
+            "desc" | "descending" => Some(Self::Desc),
+            _ => None,
+        }
+    }
+
+    /// SQL keyword for this order
+    pub fn sql(&self) -> &'static str {
+        match self {
+            Self::Asc => "ASC",
+            Self::Desc => "DESC",
+        }
+    }
+}
+
+/// Bucket used to aggregate matching activities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityGroupBy {
+    Week,
+    Month,
+    Year,
+    Sport,
+}
+
+impl ActivityGroupBy {
+    /// Parse a grouping from a tool argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "year" => Some(Self::Year),
+            "sport" | "sport_type" => Some(Self::Sport),
+            _ => None,
+        }
+    }
+
+    /// Group key for an activity, using the user's timezone for calendar buckets
+    pub fn key(&self, activity: &Activity, timezone: Tz) -> String {
+        let local = activity.start_date.with_timezone(&timezone);
+        match self {
+            Self::Week => {
+                let week = local.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Self::Month => format!("{}-{:02}", local.year(), local.month()),
+            Self::Year => local.year().to_string(),
+            Self::Sport => activity.sport_type.key(),
+        }
+    }
+}
+
+/// Aggregated totals for one group of activities
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityGroup {
+    pub key: String,
+    pub activity_count: usize,
+    pub total_distance_km: f64,
+    pub total_duration_hours: f64,
+    pub total_elevation_gain_m: f64,
+    pub average_heart_rate: Option<f64>,
+    /// Longest activity by distance in this group
+    pub longest_activity_id: Option<String>,
+}
+
+/// Filters, ordering and paging for an activity store query
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ActivityQuery {
+    pub sport_type: Option<SportType>,
+    /// Inclusive lower bound on the activity start time
+    pub start_date: Option<DateTime<Utc>>,
+    /// Inclusive upper bound on the activity start time
+    pub end_date: Option<DateTime<Utc>>,
+    pub min_distance_meters: Option<f64>,
+    pub max_distance_meters: Option<f64>,
+    pub min_duration_seconds: Option<u64>,
+    pub max_duration_seconds: Option<u64>,
+    pub min_elevation_gain: Option<f64>,
+    pub max_elevation_gain: Option<f64>,
+    /// Case-insensitive substring match on the city
+    pub city: Option<String>,
+    /// Case-insensitive substring match on the region
+    pub region: Option<String>,
+    /// Case-insensitive substring match on the trail name
+    pub trail_name: Option<String>,
+    /// Only activities with (true) or without (false) heart rate data
+    pub has_heart_rate: Option<bool>,
+    pub sort_by: ActivitySortField,
+    pub sort_order: SortOrder,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+impl ActivityQuery {
+    /// Build a query from `query_activities` tool arguments.
+    /// Distances are given in kilometers and durations in minutes; the limit
+    /// defaults to `DEFAULT_ACTIVITIES_LIMIT` results. Plain dates are days in
+    /// `timezone`.
+    pub fn from_args(args: &Value, timezone: Tz) -> Result<Self> {
+        let sort_by = match args["sort_by"].as_str() {
+            Some(value) => ActivitySortField::parse(value)
+                .ok_or_else(|| anyhow!("Unknown sort_by '{}'. Use date, distance, duration, elevation or heart_rate", value))?,
+            None => ActivitySortField::default(),
+        };
+        let sort_order = match args["sort_order"].as_str() {
+            Some(value) => SortOrder::parse(value)
+                .ok_or_else(|| anyhow!("Unknown sort_order '{}'. Use 'asc' or 'desc'", value))?,
+            None => SortOrder::default(),
+        };
+
+        let start_date = args["start_date"]
+            .as_str()
+            .map(|value| parse_date_bound(value, false, timezone))
+            .transpose()?;
+        let end_date = args["end_date"]
+            .as_str()
+            .map(|value| parse_date_bound(value, true, timezone))
+            .transpose()?;
+        if let (Some(start), Some(end)) = (start_date, end_date) {
+            if start > end {
+                return Err(anyhow!("start_date must be before end_date"));
+            }
+        }
+
+        Ok(Self {
+            sport_type: args["sport_type"].as_str().map(SportType::from_name),
+            start_date,
+            end_date,
+            min_distance_meters: args["min_distance_km"].as_f64().map(|km| km * 1000.0),
+            max_distance_meters: args["max_distance_km"].as_f64().map(|km| km * 1000.0),
+            min_duration_seconds: args["min_duration_minutes"].as_f64().map(|m| (m * 60.0) as u64),
+            max_duration_seconds: args["max_duration_minutes"].as_f64().map(|m| (m * 60.0) as u64),
+            min_elevation_gain: args["min_elevation_m"].as_f64(),
+            max_elevation_gain: args["max_elevation_m"].as_f64(),
+            city: non_empty(&args["city"]),
+            region: non_empty(&args["region"]),
+            trail_name: non_empty(&args["trail_name"]),
+            has_heart_rate: args["has_heart_rate"].as_bool(),
+            sort_by,
+            sort_order,
+            limit: Some(args["limit"].as_u64().map_or(DEFAULT_ACTIVITIES_LIMIT, |n| n as usize)),
+            offset: args["offset"].as_u64().map(|n| n as usize),
+        })
+    }
+
+    /// Copy of this query without limit and offset, for counting and grouping
+    pub fn unpaged(&self) -> Self {
+        Self {
+            limit: None,
+            offset: None,
+            ..self.clone()
+        }
+    }
+}
+
+/// Aggregate activities into groups ordered by key
+pub fn group_activities(activities: &[Activity], group_by: ActivityGroupBy, timezone: Tz) -> Vec<ActivityGroup> {
+    let mut buckets: BTreeMap<String, Vec<&Activity>> = BTreeMap::new();
+    for activity in activities {
+        buckets.entry(group_by.key(activity, timezone)).or_default().push(activity);
+    }
+
+    buckets
+        .into_iter()
+        .map(|(key, activities)| {
+            let heart_rates: Vec<f64> = activities
+                .iter()
+                .filter_map(|a| a.average_heart_rate.map(f64::from))
+                .collect();
+            let longest = activities
+                .iter()
+                .filter(|a| a.distance_meters.is_some())
+                .max_by(|a, b| a.distance_meters.partial_cmp(&b.distance_meters).unwrap_or(std::cmp::Ordering::Equal));
+
+            ActivityGroup {
+                key,
+                activity_count: activities.len(),
+                total_distance_km: activities.iter().filter_map(|a| a.distance_meters).sum::<f64>() / 1000.0,
+                total_duration_hours: activities.iter().map(|a| a.duration_seconds as f64).sum::<f64>() / 3600.0,
+                total_elevation_gain_m: activities.iter().filter_map(|a| a.elevation_gain).sum(),
+                average_heart_rate: if heart_rates.is_empty() {
+                    None
+                } else {
+                    Some(heart_rates.iter().sum::<f64>() / heart_rates.len() as f64)
+                },
+                longest_activity_id: longest.map(|a| a.id.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Parse an RFC 3339 timestamp or a `YYYY-MM-DD` date in `timezone`.
+/// Plain dates cover the whole day, so an end bound runs to 23:59:59.
+fn parse_date_bound(value: &str, end_of_day: bool, timezone: Tz) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow!("Invalid date '{}'. Use YYYY-MM-DD or RFC 3339", value))?;
+    let time = if end_of_day {
+        date.and_hms_opt(23, 59, 59)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }
+    .ok_or_else(|| anyhow!("Invalid date '{}'", value))?;
+
+    timezone
+        .from_local_datetime(&time)
+        .earliest()
+        .map(|local| local.with_timezone(&Utc))
+        .ok_or_else(|| anyhow!("Invalid local date '{}'", value))
+}
+
+fn non_empty(value: &Value) -> Option<String> {
+    value.as_str().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Timelike;
+    use serde_json::json;
+
+    fn activity(id: &str, sport_type: SportType, date: (i32, u32, u32), distance_km: f64) -> Activity {
+        Activity {
+            id: id.to_string(),
+            sport_type,
+            start_date: Utc.with_ymd_and_hms(date.0, date.1, date.2, 8, 0, 0).unwrap(),
+            distance_meters: Some(distance_km * 1000.0),
+            ..Activity::default()
+        }
+    }
+
+    #[test]
+    fn test_from_args_parses_filters() {
+        let query = ActivityQuery::from_args(&json!({
+            "sport_type": "run",
+            "start_date": "2024-01-01",
+            "end_date": "2024-12-31",
+            "min_distance_km": 10.0,
+            "max_duration_minutes": 90,
+            "city": "  Montreal ",
+            "has_heart_rate": true,
+            "sort_by": "distance",
+            "sort_order": "desc",
+            "limit": 1
+        }), chrono_tz::America::Toronto)
+        .unwrap();
+
+        assert_eq!(query.sport_type, Some(SportType::Run));
+        assert_eq!(query.min_distance_meters, Some(10_000.0));
+        assert_eq!(query.max_duration_seconds, Some(5400));
+        assert_eq!(query.city.as_deref(), Some("Montreal"));
+        assert_eq!(query.has_heart_rate, Some(true));
+        assert_eq!(query.sort_by, ActivitySortField::Distance);
+        assert_eq!(query.limit, Some(1));
+
+        // Plain dates are whole days in the user's timezone
+        let start = query.start_date.unwrap();
+        let end = query.end_date.unwrap();
+        assert_eq!(start, Utc.with_ymd_and_hms(2024, 1, 1, 5, 0, 0).unwrap());
+        assert_eq!((end.month(), end.day(), end.hour()), (1, 1, 4));
+        assert_eq!(end.year(), 2025);
+    }
+
+    #[test]
+    fn test_from_args_rejects_invalid_values() {
+        assert_eq!(ActivityQuery::from_args(&json!({}), Tz::UTC).unwrap().limit, Some(DEFAULT_ACTIVITIES_LIMIT));
+        assert!(ActivityQuery::from_args(&json!({ "sort_by": "cadence" }), Tz::UTC).is_err());
+        assert!(ActivityQuery::from_args(&json!({ "sort_order": "sideways" }), Tz::UTC).is_err());
+        assert!(ActivityQuery::from_args(&json!({ "start_date": "last tuesday" }), Tz::UTC).is_err());
+        assert!(ActivityQuery::from_args(&json!({ "start_date": "2024-06-01", "end_date": "2024-01-01" }), Tz::UTC).is_err());
+    }
+
+    #[test]
+    fn test_group_activities_by_month_and_sport() {
+        let activities = vec![
+            activity("1", SportType::Run, (2024, 3, 2), 10.0),
+            activity("2", SportType::Run, (2024, 3, 20), 21.1),
+            activity("3", SportType::Ride, (2024, 4, 5), 40.0),
+        ];
+
+        let by_month = group_activities(&activities, ActivityGroupBy::Month, Tz::UTC);
+        assert_eq!(by_month.len(), 2);
+        assert_eq!(by_month[0].key, "2024-03");
+        assert_eq!(by_month[0].activity_count, 2);
+        assert!((by_month[0].total_distance_km - 31.1).abs() < 1e-9);
+        assert_eq!(by_month[0].longest_activity_id.as_deref(), Some("2"));
+
+        let by_sport = group_activities(&activities, ActivityGroupBy::Sport, Tz::UTC);
+        let keys: Vec<&str> = by_sport.iter().map(|g| g.key.as_str()).collect();
+        assert_eq!(keys, vec!["ride", "run"]);
+    }
+
+    #[test]
+    fn test_calendar_groups_follow_the_users_timezone() {
+        // Evening of March 31st in Los Angeles is already April in UTC
+        let activities = vec![Activity {
+            start_date: Utc.with_ymd_and_hms(2024, 4, 1, 3, 0, 0).unwrap(),
+            ..activity("1", SportType::Run, (2024, 4, 1), 10.0)
+        }];
+
+        let utc = group_activities(&activities, ActivityGroupBy::Month, Tz::UTC);
+        assert_eq!(utc[0].key, "2024-04");
+        let local = group_activities(&activities, ActivityGroupBy::Month, chrono_tz::America::Los_Angeles);
+        assert_eq!(local[0].key, "2024-03");
+    }
+}
@@ -7,9 +7,18 @@
 //! # Authentication and Session Management
 //!
 //! This module provides JWT-based authentication and session management
-//! for the multi-tenant Pierre MCP Server.
-
-use crate::models::{User, UserSession, AuthRequest, AuthResponse};
+//! for the multi-tenant Pierre MCP Server, and the long-lived API keys that
+//! MCP clients can use instead of a JWT.
+//!
+//! Every JWT carries a `jti` naming its server-side session, so a token can be
+//! revoked before it expires. When refresh tokens are enabled, login also
+//! returns a refresh token that is exchanged for a new JWT and a new refresh
+//! token, each usable once.
+
+use crate::constants::api_keys::{KEY_PREFIX, SECRET_LENGTH, VISIBLE_PREFIX_LENGTH};
+use crate::constants::limits::REFRESH_TOKEN_LENGTH;
+use crate::database::Database;
+use crate::models::{ApiKeyScope, User, UserSession, AuthRequest, AuthResponse};
 use anyhow::Result;
 use chrono::{Utc, Duration};
 use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation, Algorithm};
@@ -29,6 +38,8 @@ pub struct Claims {
     pub exp: i64,
     /// Available fitness providers
     pub providers: Vec<String>,
+    /// Token ID, the ID of the session the token was issued for
+    pub jti: String,
 }
 
 /// Authentication manager for JWT tokens and user sessions
@@ -36,6 +47,7 @@ pub struct Claims {
 pub struct AuthManager {
     jwt_secret: Vec<u8>,
     token_expiry_hours: i64,
+    enable_refresh_tokens: bool,
 }
 
 impl AuthManager {
@@ -44,13 +56,34 @@ impl AuthManager {
         Self {
             jwt_secret,
             token_expiry_hours,
+            enable_refresh_tokens: false,
         }
     }
 
+    /// Issue refresh tokens at login, exchangeable for new JWTs
+    pub fn with_refresh_tokens(mut self, enabled: bool) -> Self {
+        self.enable_refresh_tokens = enabled;
+        self
+    }
+
+    pub fn refresh_tokens_enabled(&self) -> bool {
+        self.enable_refresh_tokens
+    }
+
+    /// How long issued JWTs stay valid
+    pub fn token_lifetime(&self) -> Duration {
+        Duration::hours(self.token_expiry_hours)
+    }
+
     /// Generate a JWT token for a user
     pub fn generate_token(&self, user: &User) -> Result<String> {
+        self.generate_token_with_id(user, Uuid::new_v4())
+    }
+
+    /// Generate a JWT token for a user with the given token ID
+    pub fn generate_token_with_id(&self, user: &User, jti: Uuid) -> Result<String> {
         let now = Utc::now();
-        let expiry = now + Duration::hours(self.token_expiry_hours);
+        let expiry = now + self.token_lifetime();
         
         let claims = Claims {
             sub: user.id.to_string(),
@@ -58,6 +91,7 @@ impl AuthManager {
             iat: now.timestamp(),
             exp: expiry.timestamp(),
             providers: user.available_providers(),
+            jti: jti.to_string(),
         };
 
         let token = encode(
@@ -83,10 +117,21 @@ impl AuthManager {
         Ok(token_data.claims)
     }
 
+    /// Validate a JWT token and check that its session hasn't been revoked
+    pub async fn validate_active_token(&self, token: &str, database: &Database) -> Result<Claims> {
+        let claims = self.validate_token(token)?;
+        let jti = Uuid::parse_str(&claims.jti).map_err(|_| anyhow::anyhow!("Invalid token ID"))?;
+        if database.is_token_revoked(jti).await? {
+            return Err(anyhow::anyhow!("Token has been revoked"));
+        }
+
+        Ok(claims)
+    }
+
     /// Create a user session from a valid user
     pub fn create_session(&self, user: &User) -> Result<UserSession> {
         let jwt_token = self.generate_token(user)?;
-        let expires_at = Utc::now() + Duration::hours(self.token_expiry_hours);
+        let expires_at = Utc::now() + self.token_lifetime();
 
         Ok(UserSession {
             user_id: user.id,
@@ -125,25 +170,6 @@ impl AuthManager {
         }
     }
 
-    /// Refresh a token if it's still valid
-    pub fn refresh_token(&self, old_token: &str, user: &User) -> Result<String> {
-        // First validate the old token (even if expired, we want to check signature)
-        let mut validation = Validation::new(Algorithm::HS256);
-        validation.validate_exp = false; // Allow expired tokens for refresh
-        
-        let _token_data = decode::<Claims>(
-            old_token,
-            &DecodingKey::from_secret(&self.jwt_secret),
-            &validation,
-        )?;
-
-        // Wait to ensure different timestamp
-        std::thread::sleep(std::time::Duration::from_millis(1));
-        
-        // Generate new token
-        self.generate_token(user)
-    }
-
     /// Extract user ID from token without full validation
     /// Used for database lookups when token might be expired
     pub fn extract_user_id(&self, token: &str) -> Result<Uuid> {
@@ -172,27 +198,142 @@ pub fn generate_jwt_secret() -> [u8; 64] {
     secret
 }
 
+/// Generate a new API key. Returns the full key, shown to the user once, and
+/// its visible prefix.
+pub fn generate_api_key() -> (String, String) {
+    use rand::{distributions::Alphanumeric, Rng};
+
+    let random = |length: usize| -> String {
+        rand::thread_rng().sample_iter(&Alphanumeric).take(length).map(char::from).collect()
+    };
+    let prefix = format!("{}{}", KEY_PREFIX, random(VISIBLE_PREFIX_LENGTH));
+    let key = format!("{}_{}", prefix, random(SECRET_LENGTH));
+    (key, prefix)
+}
+
+/// SHA-256 hash of a full API key, as stored in the database
+///
+/// Keys are long random strings, so a fast hash is enough and lets keys be
+/// looked up by hash directly.
+pub fn hash_api_key(key: &str) -> String {
+    sha256_hex(key)
+}
+
+/// Generate a new refresh token
+pub fn generate_refresh_token() -> String {
+    use rand::{distributions::Alphanumeric, Rng};
+
+    rand::thread_rng().sample_iter(&Alphanumeric).take(REFRESH_TOKEN_LENGTH).map(char::from).collect()
+}
+
+/// SHA-256 hash of a refresh token, as stored in the database
+pub fn hash_refresh_token(token: &str) -> String {
+    sha256_hex(token)
+}
+
+/// Generate an unguessable, URL-safe calendar feed token
+pub fn generate_calendar_token() -> String {
+    random_url_safe_token()
+}
+
+/// Generate an opaque OAuth `state` value. It carries nothing about the user:
+/// the callback looks the user up from the stored state.
+pub fn generate_oauth_state() -> String {
+    random_url_safe_token()
+}
+
+fn random_url_safe_token() -> String {
+    use base64::{Engine, engine::general_purpose};
+    use ring::rand::{SecureRandom, SystemRandom};
+
+    let mut bytes = [0u8; 32];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .expect("Failed to generate random token");
+    general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// SHA-256 hash of a calendar feed token, as stored in the database
+pub fn hash_calendar_token(token: &str) -> String {
+    sha256_hex(token)
+}
+
+fn sha256_hex(value: &str) -> String {
+    use sha2::{Digest, Sha256};
+
+    Sha256::digest(value.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// The authenticated user of a request and what the credential allows
+#[derive(Debug, Clone)]
+pub struct AuthContext {
+    pub user_id: Uuid,
+    /// Scopes of the API key; JWT logins get full access
+    pub scopes: Vec<ApiKeyScope>,
+    /// The API key used, if the request didn't use a JWT
+    pub api_key_id: Option<Uuid>,
+}
+
+impl AuthContext {
+    /// Whether the credential grants `scope`. Admin grants everything.
+    pub fn allows(&self, scope: ApiKeyScope) -> bool {
+        self.scopes.contains(&ApiKeyScope::Admin) || self.scopes.contains(&scope)
+    }
+}
+
 /// Middleware for MCP protocol authentication
+#[derive(Clone)]
 pub struct McpAuthMiddleware {
     auth_manager: AuthManager,
+    database: Database,
 }
 
 impl McpAuthMiddleware {
     /// Create new MCP auth middleware
-    pub fn new(auth_manager: AuthManager) -> Self {
-        Self { auth_manager }
+    pub fn new(auth_manager: AuthManager, database: Database) -> Self {
+        Self { auth_manager, database }
     }
 
-    /// Authenticate MCP request and extract user context
-    pub fn authenticate_request(&self, auth_header: Option<&str>) -> Result<Uuid> {
+    /// Authenticate MCP request and extract user context. The bearer token is
+    /// either a JWT from login or an API key.
+    pub async fn authenticate_request(&self, auth_header: Option<&str>) -> Result<AuthContext> {
         let token = auth_header
             .and_then(|h| h.strip_prefix("Bearer "))
             .ok_or_else(|| anyhow::anyhow!("Missing or invalid authorization header"))?;
 
-        let claims = self.auth_manager.validate_token(token)?;
+        if token.starts_with(KEY_PREFIX) {
+            return self.authenticate_api_key(token).await;
+        }
+
+        let claims = self.auth_manager.validate_active_token(token, &self.database).await?;
         let user_id = Uuid::parse_str(&claims.sub)?;
         
-        Ok(user_id)
+        Ok(AuthContext {
+            user_id,
+            scopes: vec![ApiKeyScope::Admin],
+            api_key_id: None,
+        })
+    }
+
+    async fn authenticate_api_key(&self, key: &str) -> Result<AuthContext> {
+        let api_key = self.database.get_api_key_by_hash(&hash_api_key(key)).await?
+            .ok_or_else(|| anyhow::anyhow!("Invalid API key"))?;
+        if api_key.revoked_at.is_some() {
+            return Err(anyhow::anyhow!("API key {} has been revoked", api_key.key_prefix));
+        }
+        if !api_key.is_active() {
+            return Err(anyhow::anyhow!("API key {} has expired", api_key.key_prefix));
+        }
+
+        self.database.touch_api_key(api_key.id).await?;
+        Ok(AuthContext {
+            user_id: api_key.user_id,
+            scopes: api_key.scopes,
+            api_key_id: Some(api_key.id),
+        })
     }
 
     /// Check if user has access to specific provider
@@ -276,21 +417,17 @@ mod tests {
     }
 
     #[test]
-    fn test_refresh_token() {
+    fn test_tokens_have_unique_ids() {
         let auth_manager = create_auth_manager();
         let user = create_test_user();
 
-        let original_token = auth_manager.generate_token(&user).unwrap();
-        let refreshed_token = auth_manager.refresh_token(&original_token, &user).unwrap();
+        let first = auth_manager.validate_token(&auth_manager.generate_token(&user).unwrap()).unwrap();
+        let second = auth_manager.validate_token(&auth_manager.generate_token(&user).unwrap()).unwrap();
+        assert_ne!(first.jti, second.jti);
 
-        // Both tokens should be valid (tokens might be identical if generated within same second)
-        
-        let original_claims = auth_manager.validate_token(&original_token).unwrap();
-        let refreshed_claims = auth_manager.validate_token(&refreshed_token).unwrap();
-        
-        assert_eq!(original_claims.sub, refreshed_claims.sub);
-        assert_eq!(original_claims.email, refreshed_claims.email);
-        // Note: expiry times might be the same if generated within the same second
+        let jti = Uuid::new_v4();
+        let token = auth_manager.generate_token_with_id(&user, jti).unwrap();
+        assert_eq!(auth_manager.validate_token(&token).unwrap().jti, jti.to_string());
     }
 
     #[test]
@@ -304,41 +441,139 @@ mod tests {
         assert_eq!(extracted_id, user.id);
     }
 
-    #[test]
-    fn test_mcp_auth_middleware() {
+    async fn create_test_database() -> Database {
+        let encryption_key = crate::database::generate_encryption_key().to_vec();
+        Database::new("sqlite::memory:", encryption_key).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_mcp_auth_middleware() {
         let auth_manager = create_auth_manager();
         let user = create_test_user();
-        let middleware = McpAuthMiddleware::new(auth_manager);
+        let middleware = McpAuthMiddleware::new(auth_manager, create_test_database().await);
 
         let token = middleware.auth_manager.generate_token(&user).unwrap();
         let auth_header = format!("Bearer {}", token);
 
-        let user_id = middleware.authenticate_request(Some(&auth_header)).unwrap();
-        assert_eq!(user_id, user.id);
+        let context = middleware.authenticate_request(Some(&auth_header)).await.unwrap();
+        assert_eq!(context.user_id, user.id);
+        assert!(context.allows(ApiKeyScope::Admin));
+        assert!(context.api_key_id.is_none());
     }
 
-    #[test]
-    fn test_mcp_auth_middleware_invalid_header() {
+    #[tokio::test]
+    async fn test_mcp_auth_middleware_invalid_header() {
         let auth_manager = create_auth_manager();
-        let middleware = McpAuthMiddleware::new(auth_manager);
+        let middleware = McpAuthMiddleware::new(auth_manager, create_test_database().await);
 
         // Test missing header
-        let result = middleware.authenticate_request(None);
+        let result = middleware.authenticate_request(None).await;
         assert!(result.is_err());
 
         // Test invalid format
-        let result = middleware.authenticate_request(Some("Invalid header"));
+        let result = middleware.authenticate_request(Some("Invalid header")).await;
         assert!(result.is_err());
     }
 
-    #[test]
-    fn test_provider_access_check() {
+    #[tokio::test]
+    async fn test_mcp_auth_middleware_api_key() {
+        let database = create_test_database().await;
+        let user = create_test_user();
+        let user_id = database.create_user(&user).await.unwrap();
+        let middleware = McpAuthMiddleware::new(create_auth_manager(), database.clone());
+
+        let (key, key_prefix) = generate_api_key();
+        assert!(key.starts_with(&key_prefix));
+        assert!(key_prefix.starts_with(KEY_PREFIX));
+        let api_key = crate::models::ApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name: "agent".to_string(),
+            key_prefix,
+            scopes: vec![ApiKeyScope::ActivitiesRead],
+            created_at: Utc::now(),
+            expires_at: None,
+            last_used_at: None,
+            revoked_at: None,
+        };
+        database.create_api_key(&api_key, &hash_api_key(&key)).await.unwrap();
+
+        let context = middleware.authenticate_request(Some(&format!("Bearer {}", key))).await.unwrap();
+        assert_eq!(context.user_id, user_id);
+        assert_eq!(context.api_key_id, Some(api_key.id));
+        assert!(context.allows(ApiKeyScope::ActivitiesRead));
+        assert!(!context.allows(ApiKeyScope::GoalsWrite));
+        assert!(database.get_api_keys(user_id).await.unwrap()[0].last_used_at.is_some());
+
+        // A key with a wrong secret doesn't authenticate
+        let (other_key, _) = generate_api_key();
+        assert!(middleware.authenticate_request(Some(&format!("Bearer {}", other_key))).await.is_err());
+
+        // Nor does a revoked key
+        assert!(database.revoke_api_key(user_id, api_key.id).await.unwrap());
+        let error = middleware.authenticate_request(Some(&format!("Bearer {}", key))).await.unwrap_err();
+        assert!(error.to_string().contains("revoked"));
+    }
+
+    #[tokio::test]
+    async fn test_expired_api_key_rejected() {
+        let database = create_test_database().await;
+        let user_id = database.create_user(&create_test_user()).await.unwrap();
+        let middleware = McpAuthMiddleware::new(create_auth_manager(), database.clone());
+
+        let (key, key_prefix) = generate_api_key();
+        let api_key = crate::models::ApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name: "expired".to_string(),
+            key_prefix,
+            scopes: vec![ApiKeyScope::Admin],
+            created_at: Utc::now() - Duration::days(31),
+            expires_at: Some(Utc::now() - Duration::days(1)),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        database.create_api_key(&api_key, &hash_api_key(&key)).await.unwrap();
+
+        let error = middleware.authenticate_request(Some(&format!("Bearer {}", key))).await.unwrap_err();
+        assert!(error.to_string().contains("expired"));
+    }
+
+    #[tokio::test]
+    async fn test_revoked_token_rejected() {
+        let database = create_test_database().await;
+        let user = create_test_user();
+        let user_id = database.create_user(&user).await.unwrap();
+        let auth_manager = create_auth_manager();
+        let middleware = McpAuthMiddleware::new(auth_manager.clone(), database.clone());
+
+        let session = crate::models::AuthSession {
+            id: Uuid::new_v4(),
+            user_id,
+            family_id: Uuid::new_v4(),
+            created_at: Utc::now(),
+            expires_at: Utc::now() + auth_manager.token_lifetime(),
+            revoked_at: None,
+            replaced_by: None,
+        };
+        database.create_session(&session, None).await.unwrap();
+        let auth_header = format!("Bearer {}", auth_manager.generate_token_with_id(&user, session.id).unwrap());
+        assert!(middleware.authenticate_request(Some(&auth_header)).await.is_ok());
+
+        // The token is still unexpired, but its session is gone
+        assert_eq!(database.revoke_session_family(session.family_id).await.unwrap(), 1);
+        let error = middleware.authenticate_request(Some(&auth_header)).await.unwrap_err();
+        assert!(error.to_string().contains("revoked"));
+    }
+
+    #[tokio::test]
+    async fn test_provider_access_check() {
         let auth_manager = create_auth_manager();
         let user = create_test_user();
         
         // User has no providers initially
         let token = auth_manager.generate_token(&user).unwrap();
-        let middleware = McpAuthMiddleware::new(auth_manager);
+        let middleware = McpAuthMiddleware::new(auth_manager, create_test_database().await);
         
         let has_strava = middleware.check_provider_access(&token, "strava").unwrap();
         assert!(!has_strava);
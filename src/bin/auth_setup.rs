@@ -138,6 +138,7 @@ async fn setup_strava_auth(client_id: String, client_secret: String, port: u16)
             api_key: None,
             redirect_uri: None,
             scopes: Some(oauth::STRAVA_DEFAULT_SCOPES.split(',').map(|s| s.to_string()).collect()),
+            directory: None,
         });
         
         config.save(None)?;
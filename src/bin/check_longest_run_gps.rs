@@ -36,73 +36,43 @@ async fn main() -> Result<()> {
     let _init_response: Value = serde_json::from_str(&line)?;
     println!("✅ MCP connection initialized");
     
-    // Get activities to find the longest 2025 run
-    println!("\n📊 Retrieving activities...");
-    
-    let mut all_activities: Vec<Value> = Vec::new();
-    let mut page = 1;
-    let limit = 50;
-    
-    // Get first few pages to find the longest run
-    while page <= 3 {
-        let offset = (page - 1) * limit;
-        let activities_request = json!({
-            "jsonrpc": "2.0",
-            "method": "tools/call",
-            "params": {
-                "name": "get_activities",
-                "arguments": {
-                    "provider": "strava",
-                    "limit": limit,
-                    "offset": offset
-                }
-            },
-            "id": page + 1
-        });
-        
-        writeln!(stream, "{}", activities_request)?;
-        let mut line = String::new();
-        reader.read_line(&mut line)?;
-        let response: Value = serde_json::from_str(&line)?;
-        
-        if let Some(result) = response.get("result") {
-            if let Some(activities) = result.as_array() {
-                if activities.is_empty() {
-                    break;
-                }
-                all_activities.extend(activities.clone());
-                println!("📄 Retrieved page {} with {} activities", page, activities.len());
-                page += 1;
-            } else {
-                break;
-            }
-        } else {
-            println!("❌ Error retrieving activities: {:?}", response);
-            return Ok(());
-        }
-    }
+    // Query the longest 2025 run directly from the server's activity store
+    println!("\n📊 Querying longest 2025 run...");
     
-    // Find 2025 runs
-    let mut runs_2025 = Vec::new();
-    for activity in &all_activities {
-        if let (Some(sport_type), Some(start_date)) = 
-            (activity.get("sport_type"), activity.get("start_date")) {
-            if sport_type == "run" && start_date.as_str().unwrap_or("").starts_with("2025") {
-                runs_2025.push(activity);
+    let query_request = json!({
+        "jsonrpc": "2.0",
+        "method": "tools/call",
+        "params": {
+            "name": "query_activities",
+            "arguments": {
+                "provider": "strava",
+                "sport_type": "run",
+                "start_date": "2025-01-01",
+                "end_date": "2025-12-31",
+                "sort_by": "distance",
+                "sort_order": "desc",
+                "limit": 1
             }
-        }
-    }
+        },
+        "id": 2
+    });
+    
+    writeln!(stream, "{}", query_request)?;
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let response: Value = serde_json::from_str(&line)?;
+    
+    let Some(result) = response.get("result") else {
+        println!("❌ Error querying activities: {:?}", response);
+        return Ok(());
+    };
     
-    println!("\n🏃 Found {} runs in 2025", runs_2025.len());
+    println!("\n🏃 Found {} runs in 2025", result["total_matches"]);
     
-    // Find the longest run
-    let longest_run = runs_2025.iter()
-        .max_by(|a, b| {
-            let dist_a = a.get("distance_meters").and_then(|d| d.as_f64()).unwrap_or(0.0);
-            let dist_b = b.get("distance_meters").and_then(|d| d.as_f64()).unwrap_or(0.0);
-            dist_a.partial_cmp(&dist_b).unwrap()
-        })
-        .unwrap();
+    let Some(longest_run) = result["activities"].get(0) else {
+        println!("❌ No runs found in 2025");
+        return Ok(());
+    };
     
     let distance_km = longest_run.get("distance_meters")
         .and_then(|d| d.as_f64())
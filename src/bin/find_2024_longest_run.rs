@@ -16,8 +16,8 @@
 //! to find their longest run in 2024. It showcases:
 //!
 //! - MCP protocol communication (JSON-RPC over TCP)
-//! - Paginated data retrieval from fitness providers
-//! - Data filtering and analysis
+//! - Server-side filtering, sorting and grouping with `query_activities`
+//! - Data analysis
 //! - Error handling and connection management
 //! - Performance optimization for large datasets
 //!
@@ -50,8 +50,8 @@
 //!
 //! ## Key Features Demonstrated
 //!
-//! - **Pagination**: Efficiently retrieves large datasets in chunks
-//! - **Filtering**: Processes activities by year and sport type
+//! - **Querying**: Lets the server's activity store do the heavy lifting
+//! - **Filtering**: Selects activities by date range and sport type
 //! - **Analysis**: Calculates metrics like pace and duration
 //! - **Error Handling**: Graceful handling of network and data errors
 //! - **Data Presentation**: User-friendly output formatting
@@ -66,10 +66,8 @@ use tokio::net::TcpStream;
 /// This function demonstrates a complete MCP client workflow:
 /// 1. Establishes connection to the MCP server
 /// 2. Initializes the MCP protocol session
-/// 3. Retrieves activities data through pagination
-/// 4. Filters activities by year and sport type
-/// 5. Analyzes data to find the longest run
-/// 6. Presents results in a user-friendly format
+/// 3. Queries 2024 runs sorted by distance, with yearly totals
+/// 4. Presents results in a user-friendly format
 ///
 /// # Returns
 ///
@@ -113,146 +111,99 @@ async fn main() -> Result<()> {
     reader.read_line(&mut line).await?;
     println!("✅ Connected to MCP server");
     
-    // Step 3: Retrieve activities data using pagination
-    // Since we're looking for 2024 data, we need to go back in history.
-    // Strava returns activities in reverse chronological order (newest first),
-    // so we need to paginate through multiple pages to find 2024 activities.
-    let mut all_activities = Vec::new();
-    
-    // Paginate through multiple pages to get historical data
-    // Each page returns up to 200 activities with an offset
-    for page in 1..=3 {
-        let activities_request = serde_json::json!({
-            "jsonrpc": "2.0",           // JSON-RPC 2.0 protocol
-            "method": "tools/call",     // MCP tool call method
-            "params": {
-                "name": "get_activities",   // Tool name from server capabilities
-                "arguments": {
-                    "provider": "strava",       // Which fitness provider to use
-                    "limit": 200,               // Max activities per request
-                    "offset": (page - 1) * 200 // Pagination offset
-                }
-            },
-            "id": page + 1              // Unique ID for this request
-        });
-        
-        writer.write_all(format!("{}\n", activities_request).as_bytes()).await?;
-        line.clear();
-        reader.read_line(&mut line).await?;
-        
-        let response: Value = serde_json::from_str(&line)?;
-        
-        if let Some(activities) = response["result"].as_array() {
-            if activities.is_empty() {
-                break; // No more activities
+    // Step 3: Query the server's activity store
+    // The server syncs new Strava activities into its local store, then filters
+    // and sorts there, so the client receives only the 2024 runs it needs.
+    // Grouping by year returns the 2024 totals alongside the longest runs.
+    let query_request = serde_json::json!({
+        "jsonrpc": "2.0",               // JSON-RPC 2.0 protocol
+        "method": "tools/call",         // MCP tool call method
+        "params": {
+            "name": "query_activities", // Tool name from server capabilities
+            "arguments": {
+                "provider": "strava",       // Which fitness provider to sync
+                "sport_type": "run",
+                "start_date": "2024-01-01",
+                "end_date": "2024-12-31",
+                "sort_by": "distance",      // Longest runs first
+                "sort_order": "desc",
+                "limit": 6,                 // Longest run plus five for context
+                "group_by": "year"          // Totals for all matching runs
             }
-            for activity in activities {
-                all_activities.push(activity.clone());
-            }
-            println!("📄 Got page {}: {} activities (total: {})", page, activities.len(), all_activities.len());
-        } else {
-            println!("❌ Failed to get page {}", page);
-            break;
-        }
-    }
+        },
+        "id": 2                         // Unique ID for this request
+    });
     
-    if !all_activities.is_empty() {
-        println!("📊 Analyzing {} activities...", all_activities.len());
-        
-        let mut longest_run_2024: Option<&Value> = None;
-        let mut longest_distance_2024 = 0.0;
-        let mut total_runs_2024 = 0;
-        let mut total_run_distance_2024 = 0.0;
+    writer.write_all(format!("{}\n", query_request).as_bytes()).await?;
+    line.clear();
+    reader.read_line(&mut line).await?;
+    
+    let response: Value = serde_json::from_str(&line)?;
+    
+    let Some(runs) = response["result"]["activities"].as_array() else {
+        println!("❌ Failed to query activities: {}", line.trim());
+        return Ok(());
+    };
+    
+    // Step 4: Summarize the 2024 totals from the year group
+    let totals = &response["result"]["groups"][0];
+    println!("\n🏃 2024 Run Statistics:");
+    println!("   Total runs in 2024: {}", totals["activity_count"].as_u64().unwrap_or(0));
+    println!("   Total run distance in 2024: {:.2} km", totals["total_distance_km"].as_f64().unwrap_or(0.0));
+    
+    // Step 5: Present the longest run, already first in the sorted results
+    if let Some(run) = runs.first() {
+        let longest_distance_2024 = run["distance_meters"].as_f64().unwrap_or(0.0);
+        println!("\n🏆 LONGEST RUN IN 2024:");
+        println!("   Distance: {:.2} km", longest_distance_2024 / 1000.0);
         
-        for activity in &all_activities {
-            // Check if it's from 2024
-            if let Some(date_str) = activity["start_date"].as_str() {
-                if date_str.starts_with("2024") {
-                    // Check if it's a run
-                    if let Some(sport_type) = activity["sport_type"].as_str() {
-                        if sport_type == "run" {
-                            total_runs_2024 += 1;
-                            
-                            if let Some(distance_meters) = activity["distance_meters"].as_f64() {
-                                total_run_distance_2024 += distance_meters;
-                                
-                                if distance_meters > longest_distance_2024 {
-                                    longest_distance_2024 = distance_meters;
-                                    longest_run_2024 = Some(activity);
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        if let Some(name) = run["name"].as_str() {
+            println!("   Name: {}", name);
         }
         
-        println!("\n🏃 2024 Run Statistics:");
-        println!("   Total runs in 2024: {}", total_runs_2024);
-        println!("   Total run distance in 2024: {:.2} km", total_run_distance_2024 / 1000.0);
+        if let Some(date) = run["start_date"].as_str() {
+            println!("   Date: {}", date);
+        }
         
-        if let Some(run) = longest_run_2024 {
-            println!("\n🏆 LONGEST RUN IN 2024:");
-            println!("   Distance: {:.2} km", longest_distance_2024 / 1000.0);
-            
-            if let Some(name) = run["name"].as_str() {
-                println!("   Name: {}", name);
-            }
-            
-            if let Some(date) = run["start_date"].as_str() {
-                println!("   Date: {}", date);
-            }
-            
-            if let Some(duration) = run["duration_seconds"].as_u64() {
-                let hours = duration / 3600;
-                let minutes = (duration % 3600) / 60;
-                let seconds = duration % 60;
-                println!("   Duration: {}h {}m {}s", hours, minutes, seconds);
-                
-                // Calculate pace
-                if longest_distance_2024 > 0.0 {
-                    let pace_per_km = duration as f64 / (longest_distance_2024 / 1000.0);
-                    let pace_minutes = (pace_per_km / 60.0) as u64;
-                    let pace_seconds = (pace_per_km % 60.0) as u64;
-                    println!("   Pace: {}:{:02} min/km", pace_minutes, pace_seconds);
-                }
-            }
+        if let Some(duration) = run["duration_seconds"].as_u64() {
+            let hours = duration / 3600;
+            let minutes = (duration % 3600) / 60;
+            let seconds = duration % 60;
+            println!("   Duration: {}h {}m {}s", hours, minutes, seconds);
             
-            if let Some(elevation) = run["elevation_gain"].as_f64() {
-                println!("   Elevation gain: {:.0} m", elevation);
+            // Calculate pace
+            if longest_distance_2024 > 0.0 {
+                let pace_per_km = duration as f64 / (longest_distance_2024 / 1000.0);
+                let pace_minutes = (pace_per_km / 60.0) as u64;
+                let pace_seconds = (pace_per_km % 60.0) as u64;
+                println!("   Pace: {}:{:02} min/km", pace_minutes, pace_seconds);
             }
-            
-            if let Some(avg_hr) = run["average_heart_rate"].as_u64() {
-                println!("   Average heart rate: {} bpm", avg_hr);
-            }
-        } else {
-            println!("\n❌ No runs found in 2024 activities");
         }
         
-        // Also show some 2024 runs for context
-        println!("\n📋 Other 2024 runs:");
-        let mut run_count = 0;
-        for activity in &all_activities {
-            if let Some(date_str) = activity["start_date"].as_str() {
-                if date_str.starts_with("2024") {
-                    if let Some(sport_type) = activity["sport_type"].as_str() {
-                        if sport_type == "run" && run_count < 5 {
-                            if let Some(distance_meters) = activity["distance_meters"].as_f64() {
-                                if let Some(name) = activity["name"].as_str() {
-                                    println!("   {:.2} km - {} ({})", 
-                                        distance_meters / 1000.0, 
-                                        name, 
-                                        &date_str[0..10]);
-                                    run_count += 1;
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+        if let Some(elevation) = run["elevation_gain"].as_f64() {
+            println!("   Elevation gain: {:.0} m", elevation);
+        }
+        
+        if let Some(avg_hr) = run["average_heart_rate"].as_u64() {
+            println!("   Average heart rate: {} bpm", avg_hr);
         }
     } else {
-        println!("❌ Failed to get activities");
+        println!("\n❌ No runs found in 2024 activities");
+    }
+    
+    // Also show the next longest 2024 runs for context
+    println!("\n📋 Other 2024 runs:");
+    for run in runs.iter().skip(1) {
+        if let (Some(distance_meters), Some(name), Some(date_str)) = (
+            run["distance_meters"].as_f64(),
+            run["name"].as_str(),
+            run["start_date"].as_str(),
+        ) {
+            println!("   {:.2} km - {} ({})", 
+                distance_meters / 1000.0, 
+                name, 
+                &date_str[0..10]);
+        }
     }
     
     Ok(())
@@ -5,10 +5,10 @@
 // except according to those terms.
 
 use anyhow::Result;
-use chrono::NaiveDate;
+use chrono::Utc;
+use pierre_mcp_server::intelligence::{StreakCriteria, StreakEngine, StreakPeriod};
 use pierre_mcp_server::models::{Activity, SportType};
 use serde_json::{json, Value};
-use std::collections::HashMap;
 use std::io::{BufRead, BufReader, Write};
 use std::net::TcpStream;
 
@@ -125,174 +125,55 @@ fn main() -> Result<()> {
     
     println!("📊 Total activities retrieved: {}", all_activities.len());
     
-    // Filter for runs with 10km+ distance
-    let qualifying_runs: Vec<&Activity> = all_activities
-        .iter()
-        .filter(|activity| {
-            // Check if it's a run
-            let is_run = matches!(activity.sport_type, SportType::Run) || 
-                         matches!(activity.sport_type, SportType::Other(ref s) if s.to_lowercase().contains("run"));
-            
-            // Check if distance is 10km or more
-            let is_10k_plus = activity.distance_meters
-                .map(|d| d >= 10000.0)
-                .unwrap_or(false);
-            
-            is_run && is_10k_plus
-        })
-        .collect();
+    // Streak detection is handled by the intelligence streak engine
+    let criteria = StreakCriteria {
+        period: StreakPeriod::Day,
+        sport_type: Some(SportType::Run),
+        min_distance_meters: Some(10_000.0),
+        ..StreakCriteria::default()
+    };
+    let report = StreakEngine::new(criteria).analyze(&all_activities, Utc::now());
     
-    println!("🏃 Found {} runs with 10km+ distance", qualifying_runs.len());
+    println!("📅 Found {} unique days with 10km+ runs", report.qualifying_periods);
     
-    if qualifying_runs.is_empty() {
+    let Some(longest_streak) = report.longest_streak else {
         println!("❌ No runs with 10km+ distance found");
         return Ok(());
-    }
-    
-    // Group runs by date (only keep the longest run per day)
-    let mut runs_by_date: HashMap<NaiveDate, &Activity> = HashMap::new();
-    
-    for run in &qualifying_runs {
-        let date = run.start_date.date_naive();
-        
-        // Keep only the longest run for each day
-        if let Some(existing_run) = runs_by_date.get(&date) {
-            let existing_distance = existing_run.distance_meters.unwrap_or(0.0);
-            let current_distance = run.distance_meters.unwrap_or(0.0);
-            
-            if current_distance > existing_distance {
-                runs_by_date.insert(date, run);
-            }
-        } else {
-            runs_by_date.insert(date, run);
-        }
-    }
-    
-    println!("📅 Found {} unique days with 10km+ runs", runs_by_date.len());
-    
-    // Convert to sorted vector by date
-    let mut daily_runs: Vec<(NaiveDate, &Activity)> = runs_by_date.into_iter().collect();
-    daily_runs.sort_by_key(|(date, _)| *date);
-    
-    // Find longest consecutive streak
-    let mut longest_streak = 0;
-    let mut current_streak = 0;
-    let mut longest_streak_start: Option<NaiveDate> = None;
-    let mut longest_streak_end: Option<NaiveDate> = None;
-    let mut current_streak_start: Option<NaiveDate> = None;
-    let mut longest_streak_activities: Vec<&Activity> = Vec::new();
-    
-    for i in 0..daily_runs.len() {
-        let (current_date, _current_activity) = daily_runs[i];
-        
-        if i == 0 {
-            // First run starts a streak
-            current_streak = 1;
-            current_streak_start = Some(current_date);
-        } else {
-            let (prev_date, _) = daily_runs[i - 1];
-            
-            // Check if current date is consecutive to previous date
-            if current_date == prev_date + chrono::Duration::days(1) {
-                // Continue streak
-                current_streak += 1;
-            } else {
-                // Check if current streak is the longest
-                if current_streak > longest_streak {
-                    longest_streak = current_streak;
-                    longest_streak_start = current_streak_start;
-                    longest_streak_end = Some(prev_date);
-                    
-                    // Collect activities from the longest streak
-                    longest_streak_activities.clear();
-                    let start_idx = i - current_streak;
-                    for j in start_idx..i {
-                        longest_streak_activities.push(daily_runs[j].1);
-                    }
-                }
-                
-                // Start new streak
-                current_streak = 1;
-                current_streak_start = Some(current_date);
-            }
-        }
-    }
-    
-    // Check the final streak
-    if current_streak > longest_streak {
-        longest_streak = current_streak;
-        longest_streak_start = current_streak_start;
-        longest_streak_end = Some(daily_runs.last().unwrap().0);
-        
-        // Collect activities from the final streak
-        longest_streak_activities.clear();
-        let start_idx = daily_runs.len() - current_streak;
-        for j in start_idx..daily_runs.len() {
-            longest_streak_activities.push(daily_runs[j].1);
-        }
-    }
+    };
     
     // Display results
     println!("\n🏆 LONGEST CONSECUTIVE 10KM+ RUNNING STREAK:");
-    println!("   📈 Streak Length: {} days", longest_streak);
-    
-    if let (Some(start), Some(end)) = (longest_streak_start, longest_streak_end) {
-        println!("   📅 Period: {} to {}", start.format("%Y-%m-%d"), end.format("%Y-%m-%d"));
-        
-        // Calculate total distance and time for the streak
-        let total_distance: f64 = longest_streak_activities
-            .iter()
-            .map(|a| a.distance_meters.unwrap_or(0.0))
-            .sum();
-        
-        let total_time: u64 = longest_streak_activities
-            .iter()
-            .map(|a| a.duration_seconds)
-            .sum();
-        
-        println!("   📏 Total Distance: {:.2} km", total_distance / 1000.0);
-        println!("   ⏱️  Total Time: {:.2} hours", total_time as f64 / 3600.0);
-        println!("   📊 Average Distance: {:.2} km/day", (total_distance / 1000.0) / longest_streak as f64);
-        
-        println!("\n📋 Streak Details:");
-        for (i, activity) in longest_streak_activities.iter().enumerate() {
-            let distance_km = activity.distance_meters.unwrap_or(0.0) / 1000.0;
-            let duration_hours = activity.duration_seconds as f64 / 3600.0;
-            
-            println!("   Day {}: {} - {:.2}km in {:.2}h - \"{}\"", 
+    println!("   📈 Streak Length: {} days", longest_streak.length);
+    println!("   📅 Period: {} to {}", longest_streak.start.format("%Y-%m-%d"), longest_streak.end.format("%Y-%m-%d"));
+    println!("   📏 Total Distance: {:.2} km", longest_streak.total_distance_km);
+    println!("   ⏱️  Total Time: {:.2} hours", longest_streak.total_duration_hours);
+    println!("   📊 Average Distance: {:.2} km/day", longest_streak.total_distance_km / longest_streak.length as f64);
+    
+    println!("\n📋 Streak Details:");
+    for (i, activity_id) in longest_streak.activity_ids.iter().enumerate() {
+        if let Some(activity) = all_activities.iter().find(|a| &a.id == activity_id) {
+            println!("   {}: {} - {:.2}km in {:.2}h - \"{}\"", 
                 i + 1,
                 activity.start_date.format("%Y-%m-%d"),
-                distance_km,
-                duration_hours,
+                activity.distance_meters.unwrap_or(0.0) / 1000.0,
+                activity.duration_seconds as f64 / 3600.0,
                 activity.name
             );
         }
     }
     
-    if longest_streak == 0 {
-        println!("❌ No consecutive days with 10km+ runs found");
-    } else if longest_streak == 1 {
+    if longest_streak.length == 1 {
         println!("ℹ️  Maximum streak is 1 day (no consecutive days found)");
     }
     
-    // Show some additional statistics
-    println!("\n📊 Additional Statistics:");
-    println!("   🏃 Total 10km+ runs: {}", qualifying_runs.len());
-    println!("   📅 Total days with 10km+ runs: {}", daily_runs.len());
-    
-    // Find the longest single run
-    if let Some(longest_run) = qualifying_runs.iter().max_by(|a, b| {
-        let dist_a = a.distance_meters.unwrap_or(0.0);
-        let dist_b = b.distance_meters.unwrap_or(0.0);
-        dist_a.partial_cmp(&dist_b).unwrap_or(std::cmp::Ordering::Equal)
-    }) {
-        println!("   🎯 Longest single run: {:.2}km - \"{}\"", 
-            longest_run.distance_meters.unwrap_or(0.0) / 1000.0,
-            longest_run.name
-        );
+    if let Some(current_streak) = &report.current_streak {
+        println!("\n🔥 Current streak: {} days (since {})", current_streak.length, current_streak.start.format("%Y-%m-%d"));
+    }
+    for alert in &report.alerts {
+        println!("   ⚠️  {}", alert.message);
     }
     
     println!("\n✅ Analysis completed successfully!");
     
     Ok(())
-}
\ No newline at end of file
+}
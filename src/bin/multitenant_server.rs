@@ -17,10 +17,16 @@ use pierre_mcp_server::{
     database::{Database, generate_encryption_key},
     mcp::multitenant::MultiTenantMcpServer,
     config::environment::ServerConfig,
+    cors::CorsPolicy,
     logging,
     health::HealthChecker,
+    tls,
 };
+use std::convert::Infallible;
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use tokio_rustls::TlsAcceptor;
+use warp::Filter;
 use tracing::{info, error};
 
 #[derive(Parser)]
@@ -113,22 +119,33 @@ async fn main() -> Result<()> {
         info!("Database initialized successfully");
 
         // Initialize authentication manager
-        let auth_manager = AuthManager::new(jwt_secret.to_vec(), config.auth.jwt_expiry_hours as i64);
+        let auth_manager = AuthManager::new(jwt_secret.to_vec(), config.auth.jwt_expiry_hours as i64)
+            .with_refresh_tokens(config.auth.enable_refresh_tokens);
         info!("Authentication manager initialized");
 
         // Initialize health checker
         let health_checker = HealthChecker::new(database.clone());
         info!("Health checker initialized");
 
+        // Load the TLS certificate, if enabled, for both listeners
+        let tls_acceptor = tls::acceptor(&config.security.tls)?;
+
         // Create and run multi-tenant server with health checks
-        let server = MultiTenantMcpServer::new(database, auth_manager);
+        let mut server = MultiTenantMcpServer::new(database, auth_manager)
+            .with_oauth_config(config.oauth.clone())
+            .with_rate_limit(config.security.rate_limit.clone())
+            .with_bind_address(config.bind_address)
+            .with_cors(CorsPolicy::new(&config.security.cors_origins, config.security.cors_allow_credentials)?);
+        if let Some(acceptor) = tls_acceptor.clone() {
+            server = server.with_tls(acceptor);
+        }
         
         info!("🚀 Multi-tenant MCP server starting on ports {} (MCP) and {} (HTTP)", 
               config.mcp_port, config.http_port);
         info!("📊 Ready to serve fitness data with user authentication!");
         
         // Run server with health check integration
-        if let Err(e) = run_production_server(server, config, health_checker).await {
+        if let Err(e) = run_production_server(server, config, health_checker, tls_acceptor).await {
             error!("Server error: {}", e);
             return Err(e);
         }
@@ -142,14 +159,17 @@ async fn run_production_server(
     server: MultiTenantMcpServer,
     config: ServerConfig,
     health_checker: HealthChecker,
+    tls_acceptor: Option<TlsAcceptor>,
 ) -> Result<()> {
     
     // Setup HTTP routes with health checks  
-    let routes = pierre_mcp_server::health::middleware::routes(health_checker);
+    let routes = pierre_mcp_server::health::middleware::routes(health_checker)
+        .recover(|_: warp::Rejection| async {
+            Ok::<_, Infallible>(warp::reply::with_status("Not Found", warp::http::StatusCode::NOT_FOUND))
+        });
     
     // Run HTTP server and MCP server concurrently
-    let http_server = warp::serve(routes)
-        .run(([0, 0, 0, 0], config.http_port));
+    let http_server = tls::serve_http(routes, SocketAddr::new(config.bind_address, config.http_port), tls_acceptor);
     
     let mcp_server = server.run(config.mcp_port);
     
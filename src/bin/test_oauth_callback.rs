@@ -10,7 +10,9 @@ use anyhow::Result;
 use pierre_mcp_server::routes::{AuthRoutes, OAuthRoutes, RegisterRequest, LoginRequest};
 use pierre_mcp_server::database::Database;
 use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::config::environment::OAuthConfig;
 use pierre_mcp_server::database::generate_encryption_key;
+use std::sync::Arc;
 use uuid::Uuid;
 
 #[tokio::main]
@@ -25,7 +27,8 @@ async fn main() -> Result<()> {
     let auth_manager = AuthManager::new(vec![0u8; 64], 24);
     
     let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    // Strava credentials come from STRAVA_CLIENT_ID and STRAVA_CLIENT_SECRET
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager.clone(), Arc::new(OAuthConfig::from_env()?));
     
     println!("✅ Test environment initialized");
     
@@ -49,11 +52,12 @@ async fn main() -> Result<()> {
         password: test_password.to_string(),
     };
     
-    let _login_response = auth_routes.login(login_request).await?;
+    let login_response = auth_routes.login(login_request).await?;
+    let auth_header = format!("Bearer {}", login_response.jwt_token);
     println!("✅ User logged in, JWT token generated");
     
     // 4. Test OAuth authorization URL generation
-    let auth_url_response = oauth_routes.get_auth_url(user_id, "strava").await?;
+    let auth_url_response = oauth_routes.get_auth_url(Some(&auth_header), "strava").await?;
     println!("✅ Strava OAuth authorization URL generated:");
     println!("   URL: {}", auth_url_response.authorization_url);
     println!("   State: {}", auth_url_response.state);
@@ -126,8 +130,8 @@ async fn main() -> Result<()> {
     }
     
     // Test OAuth URL endpoint
-    let oauth_url = format!("http://localhost:8081/oauth/auth/strava/{}", user_id);
-    match client.get(&oauth_url).send().await {
+    let oauth_url = "http://localhost:8081/oauth/auth/strava";
+    match client.get(oauth_url).header("authorization", &auth_header).send().await {
         Ok(response) => {
             if response.status().is_success() {
                 let _oauth_data: serde_json::Value = response.json().await?;
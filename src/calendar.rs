@@ -0,0 +1,264 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! iCalendar (RFC 5545) feed of planned workouts, goal deadlines and milestone checkpoints
+
+use crate::database::Database;
+use crate::intelligence::{milestone_schedule, Goal, GoalStatus, Milestone, TrainingPlan};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use uuid::Uuid;
+
+const PRODUCT_ID: &str = "-//Pierre//Pierre MCP Server//EN";
+const UID_DOMAIN: &str = "pierre-mcp-server";
+
+/// Longest content line in octets before it is folded
+const MAX_LINE_OCTETS: usize = 75;
+
+/// An all-day calendar entry
+#[derive(Debug, Clone)]
+pub struct CalendarEvent {
+    pub uid: String,
+    pub date: NaiveDate,
+    pub summary: String,
+    pub description: String,
+    pub category: &'static str,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CalendarFeed {
+    pub events: Vec<CalendarEvent>,
+}
+
+impl CalendarFeed {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add every workout of a plan, including the race itself
+    pub fn add_training_plan(&mut self, plan: &TrainingPlan) {
+        for week in &plan.weeks {
+            for (index, workout) in week.workouts.iter().enumerate() {
+                let workout_type = serde_json::to_value(workout.workout_type)
+                    .ok()
+                    .and_then(|value| value.as_str().map(|name| name.replace('_', " ")))
+                    .unwrap_or_default();
+                self.events.push(CalendarEvent {
+                    uid: format!("plan-{}-{}-{}@{}", plan.id, workout.date, index, UID_DOMAIN),
+                    date: workout.date,
+                    summary: format!("{} {} ({} min)", capitalize(&workout_type), workout.sport, workout.duration_minutes),
+                    description: format!("Week {} ({:?} phase): {}", week.week_number, week.phase, workout.description),
+                    category: "WORKOUT",
+                });
+            }
+        }
+    }
+
+    /// Add a goal's target date and, while it is active, its milestone checkpoints.
+    /// Reached milestones are placed on the day they were achieved.
+    pub fn add_goal(&mut self, goal: &Goal, milestones: &[Milestone]) {
+        if goal.status == GoalStatus::Cancelled {
+            return;
+        }
+
+        let summary = match goal.status {
+            GoalStatus::Completed => format!("Goal completed: {}", goal.title),
+            _ => format!("Goal deadline: {}", goal.title),
+        };
+        let mut description = format!("Target {} ({})", goal.target_value, goal.goal_type.kind());
+        if !goal.description.is_empty() {
+            description = format!("{}. {}", goal.description, description);
+        }
+        self.events.push(CalendarEvent {
+            uid: format!("goal-{}@{}", goal.id, UID_DOMAIN),
+            date: goal.target_date.date_naive(),
+            summary,
+            description,
+            category: "GOAL",
+        });
+
+        for (index, (name, due)) in milestone_schedule(goal).into_iter().enumerate() {
+            let reached = milestones.iter().find(|m| m.name == name && m.achieved);
+            let (date, summary) = match reached {
+                Some(milestone) => (
+                    milestone.achieved_date.unwrap_or(due).date_naive(),
+                    format!("Milestone reached: {} - {}", name, goal.title),
+                ),
+                None if goal.status == GoalStatus::Active => {
+                    (due.date_naive(), format!("Milestone checkpoint: {} - {}", name, goal.title))
+                }
+                None => continue,
+            };
+            self.events.push(CalendarEvent {
+                uid: format!("goal-{}-milestone-{}@{}", goal.id, index + 1, UID_DOMAIN),
+                date,
+                summary,
+                description: format!("{} of the way to '{}'", name, goal.title),
+                category: "MILESTONE",
+            });
+        }
+    }
+
+    /// Render the feed as an RFC 5545 VCALENDAR
+    pub fn to_ics(&self, now: DateTime<Utc>) -> String {
+        let stamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let mut events = self.events.clone();
+        events.sort_by(|a, b| a.date.cmp(&b.date).then_with(|| a.uid.cmp(&b.uid)));
+
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            format!("PRODID:{}", PRODUCT_ID),
+            "CALSCALE:GREGORIAN".to_string(),
+            "METHOD:PUBLISH".to_string(),
+            "X-WR-CALNAME:Pierre training".to_string(),
+        ];
+        for event in &events {
+            lines.push("BEGIN:VEVENT".to_string());
+            lines.push(format!("UID:{}", event.uid));
+            lines.push(format!("DTSTAMP:{}", stamp));
+            lines.push(format!("DTSTART;VALUE=DATE:{}", event.date.format("%Y%m%d")));
+            lines.push(format!("DTEND;VALUE=DATE:{}", (event.date + Duration::days(1)).format("%Y%m%d")));
+            lines.push(format!("SUMMARY:{}", escape_text(&event.summary)));
+            lines.push(format!("DESCRIPTION:{}", escape_text(&event.description)));
+            lines.push(format!("CATEGORIES:{}", event.category));
+            lines.push("TRANSP:TRANSPARENT".to_string());
+            lines.push("END:VEVENT".to_string());
+        }
+        lines.push("END:VCALENDAR".to_string());
+
+        lines.iter().map(|line| fold_line(line)).collect()
+    }
+}
+
+/// Build the calendar of all plans and goals stored for a user
+pub async fn build_user_calendar(database: &Database, user_id: Uuid) -> Result<String> {
+    let mut feed = CalendarFeed::new();
+
+    for plan in database.get_user_training_plans(user_id).await? {
+        feed.add_training_plan(&plan);
+    }
+    for goal in database.get_user_goals(user_id).await? {
+        let milestones = database.get_goal_milestones(&goal.id).await?;
+        feed.add_goal(&goal, &milestones);
+    }
+
+    Ok(feed.to_ics(Utc::now()))
+}
+
+/// Escape a TEXT property value
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Terminate a content line with CRLF, folding it so no line exceeds 75 octets
+fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut line_octets = 0;
+    for c in line.chars() {
+        if line_octets + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            // The leading space counts towards the continuation line
+            line_octets = 1;
+        }
+        folded.push(c);
+        line_octets += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intelligence::{GoalType, TimeFrame};
+    use chrono::TimeZone;
+
+    fn goal(status: GoalStatus) -> Goal {
+        Goal {
+            id: "g1".to_string(),
+            user_id: "u1".to_string(),
+            title: "Run 100km, fast".to_string(),
+            description: String::new(),
+            goal_type: GoalType::Distance { sport: "run".to_string(), timeframe: TimeFrame::Month },
+            target_value: 100.0,
+            target_date: Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap(),
+            current_value: 30.0,
+            created_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            updated_at: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_goal_events() {
+        let milestones = vec![Milestone {
+            name: "First Quarter".to_string(),
+            target_value: 25.0,
+            achieved_date: Some(Utc.with_ymd_and_hms(2024, 1, 20, 8, 0, 0).unwrap()),
+            achieved: true,
+        }];
+
+        let mut feed = CalendarFeed::new();
+        feed.add_goal(&goal(GoalStatus::Active), &milestones);
+        assert_eq!(feed.events.len(), 4);
+        assert_eq!(feed.events[0].date, NaiveDate::from_ymd_opt(2024, 5, 1).unwrap());
+        assert_eq!(feed.events[1].date, NaiveDate::from_ymd_opt(2024, 1, 20).unwrap());
+        assert!(feed.events[1].summary.starts_with("Milestone reached"));
+        // Halfway through January 1 to May 1
+        assert_eq!(feed.events[2].date, NaiveDate::from_ymd_opt(2024, 3, 1).unwrap());
+        assert!(feed.events[2].summary.starts_with("Milestone checkpoint"));
+
+        // Paused goals keep their deadline and reached milestones only
+        let mut feed = CalendarFeed::new();
+        feed.add_goal(&goal(GoalStatus::Paused), &milestones);
+        assert_eq!(feed.events.len(), 2);
+
+        let mut feed = CalendarFeed::new();
+        feed.add_goal(&goal(GoalStatus::Cancelled), &milestones);
+        assert!(feed.events.is_empty());
+    }
+
+    #[test]
+    fn test_ics_format() {
+        let mut feed = CalendarFeed::new();
+        feed.add_goal(&goal(GoalStatus::Active), &[]);
+        feed.events[0].description = "A very long description; with commas, semicolons and a\nnewline that goes well past the seventy-five octet limit".to_string();
+
+        let ics = feed.to_ics(Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap());
+        assert!(ics.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(ics.ends_with("END:VCALENDAR\r\n"));
+        assert_eq!(ics.matches("BEGIN:VEVENT").count(), 4);
+        assert!(ics.contains("DTSTAMP:20240102T030405Z\r\n"));
+        assert!(ics.contains("DTSTART;VALUE=DATE:20240501\r\nDTEND;VALUE=DATE:20240502\r\n"));
+        assert!(ics.contains("SUMMARY:Goal deadline: Run 100km\\, fast\r\n"));
+
+        // Every physical line fits in 75 octets and unfolds to the escaped text
+        assert!(ics.split("\r\n").all(|line| line.len() <= MAX_LINE_OCTETS));
+        let unfolded = ics.replace("\r\n ", "");
+        assert!(unfolded.contains("DESCRIPTION:A very long description\\; with commas\\, semicolons and a\\nnewline"));
+    }
+
+    #[test]
+    fn test_fold_line_keeps_characters_whole() {
+        let line = format!("SUMMARY:{}", "é".repeat(60));
+        let folded = fold_line(&line);
+        assert!(folded.split("\r\n").all(|part| part.len() <= MAX_LINE_OCTETS));
+        assert_eq!(folded.replace("\r\n ", "").trim_end(), line);
+    }
+}
@@ -33,6 +33,9 @@ pub struct ProviderConfig {
     pub api_key: Option<String>,
     pub redirect_uri: Option<String>,
     pub scopes: Option<Vec<String>>,
+    /// Directory of activity files, for the `directory` auth type
+    #[serde(default)]
+    pub directory: Option<String>,
 }
 
 impl Config {
@@ -71,6 +74,7 @@ impl Config {
                     api_key: None,
                     redirect_uri: std::env::var("STRAVA_REDIRECT_URI").ok(),
                     scopes: Some(vec!["read".to_string(), "activity:read_all".to_string()]),
+                    directory: None,
                 });
             }
             
@@ -88,6 +92,22 @@ impl Config {
                     api_key: None,
                     redirect_uri: std::env::var("FITBIT_REDIRECT_URI").ok(),
                     scopes: Some(vec!["activity".to_string(), "profile".to_string()]),
+                    directory: None,
+                });
+            }
+            
+            // Watch a directory of GPX/TCX/FIT files for devices without an API
+            if let Ok(directory) = std::env::var("IMPORT_DIRECTORY") {
+                config.providers.insert("file".to_string(), ProviderConfig {
+                    auth_type: "directory".to_string(),
+                    client_id: None,
+                    client_secret: None,
+                    access_token: None,
+                    refresh_token: None,
+                    api_key: None,
+                    redirect_uri: None,
+                    scopes: None,
+                    directory: Some(directory),
                 });
             }
             
@@ -133,6 +153,7 @@ mod tests {
             api_key: None,
             redirect_uri: Some("http://localhost:8081/oauth/callback".to_string()),
             scopes: Some(vec!["read".to_string(), "activity:read_all".to_string()]),
+            directory: None,
         }
     }
 
@@ -149,6 +170,7 @@ mod tests {
             api_key: Some("test_api_key".to_string()),
             redirect_uri: None,
             scopes: None,
+            directory: None,
         });
         
         Config { providers }
@@ -269,6 +291,7 @@ api_key = "test_key_123"
             api_key: None,
             redirect_uri: None,
             scopes: None,
+            directory: None,
         });
         
         // Verify the Strava provider was created 
@@ -374,6 +397,7 @@ api_key = "test_key_123"
             api_key: Some("my_secret_key".to_string()),
             redirect_uri: None,
             scopes: None,
+            directory: None,
         };
         
         assert_eq!(api_key_config.auth_type, "api_key");
@@ -393,6 +417,7 @@ api_key = "test_key_123"
             api_key: None,
             redirect_uri: Some("http://localhost:8081/oauth/callback".to_string()),
             scopes: Some(vec!["read".to_string()]),
+            directory: None,
         };
         
         assert_eq!(oauth2_config.auth_type, "oauth2");
@@ -9,12 +9,18 @@
 use anyhow::{Result, Context};
 use serde::{Deserialize, Serialize};
 use std::env;
+use std::net::IpAddr;
 use std::path::PathBuf;
 use tracing::{info, warn};
 use crate::constants::{env_config, oauth, defaults, limits};
+use crate::cors::CorsPolicy;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerConfig {
+    /// Deployment environment; `production` refuses placeholder credentials
+    pub environment: String,
+    /// Address the MCP and HTTP listeners bind to
+    pub bind_address: IpAddr,
     /// MCP server port
     pub mcp_port: u16,
     /// HTTP API port  
@@ -65,15 +71,19 @@ pub struct AuthConfig {
     pub enable_refresh_tokens: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// OAuth client credentials of every provider. This is the only place they
+/// are read from the environment.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OAuthConfig {
     /// Strava OAuth configuration
     pub strava: OAuthProviderConfig,
     /// Fitbit OAuth configuration  
     pub fitbit: OAuthProviderConfig,
+    /// Garmin Connect OAuth configuration
+    pub garmin: OAuthProviderConfig,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct OAuthProviderConfig {
     /// OAuth client ID
     pub client_id: Option<String>,
@@ -89,8 +99,10 @@ pub struct OAuthProviderConfig {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SecurityConfig {
-    /// CORS allowed origins
+    /// CORS allowed origins: exact, `scheme://*.domain` wildcard subdomains, or `*`
     pub cors_origins: Vec<String>,
+    /// Let allowed origins send cookies and authorization headers
+    pub cors_allow_credentials: bool,
     /// Rate limiting configuration
     pub rate_limit: RateLimitConfig,
     /// TLS configuration
@@ -128,6 +140,9 @@ impl ServerConfig {
         }
 
         let config = ServerConfig {
+            environment: env_var_or("ENVIRONMENT", "development")?,
+            bind_address: env_var_or("BIND_ADDRESS", defaults::DEFAULT_BIND_ADDRESS)?.parse()
+                .context("Invalid BIND_ADDRESS value")?,
             mcp_port: env_config::mcp_port(),
             http_port: env_config::http_port(),
             log_level: env_config::log_level(),
@@ -155,27 +170,12 @@ impl ServerConfig {
                     .context("Invalid ENABLE_REFRESH_TOKENS value")?,
             },
             
-            oauth: OAuthConfig {
-                strava: OAuthProviderConfig {
-                    client_id: env_config::strava_client_id(),
-                    client_secret: env_config::strava_client_secret(),
-                    redirect_uri: Some(env_config::strava_redirect_uri()),
-                    scopes: parse_scopes(oauth::STRAVA_DEFAULT_SCOPES),
-                    enabled: env_var_or("STRAVA_ENABLED", "true")?.parse()
-                        .context("Invalid STRAVA_ENABLED value")?,
-                },
-                fitbit: OAuthProviderConfig {
-                    client_id: env::var("FITBIT_CLIENT_ID").ok(),
-                    client_secret: env::var("FITBIT_CLIENT_SECRET").ok(),
-                    redirect_uri: env::var("FITBIT_REDIRECT_URI").ok(),
-                    scopes: parse_scopes(oauth::FITBIT_DEFAULT_SCOPES),
-                    enabled: env_var_or("FITBIT_ENABLED", "true")?.parse()
-                        .context("Invalid FITBIT_ENABLED value")?,
-                },
-            },
+            oauth: OAuthConfig::from_env()?,
             
             security: SecurityConfig {
                 cors_origins: parse_origins(&env_var_or("CORS_ORIGINS", "*")?),
+                cors_allow_credentials: env_var_or("CORS_ALLOW_CREDENTIALS", "false")?.parse()
+                    .context("Invalid CORS_ALLOW_CREDENTIALS value")?,
                 rate_limit: RateLimitConfig {
                     enabled: env_var_or("RATE_LIMIT_ENABLED", "true")?.parse()
                         .context("Invalid RATE_LIMIT_ENABLED value")?,
@@ -211,52 +211,160 @@ impl ServerConfig {
         }
 
         // OAuth validation
-        if self.oauth.strava.enabled {
-            if self.oauth.strava.client_id.is_none() || self.oauth.strava.client_secret.is_none() {
-                warn!("Strava OAuth is enabled but missing client_id or client_secret");
+        for (name, provider) in self.oauth.providers() {
+            if !provider.enabled {
+                continue;
             }
-        }
-
-        if self.oauth.fitbit.enabled {
-            if self.oauth.fitbit.client_id.is_none() || self.oauth.fitbit.client_secret.is_none() {
-                warn!("Fitbit OAuth is enabled but missing client_id or client_secret");
+            if provider.credentials().is_none() {
+                warn!("{} OAuth is enabled but missing client_id or client_secret", name);
+            }
+            if provider.has_placeholder_credentials() {
+                if self.is_production() {
+                    return Err(anyhow::anyhow!(
+                        "{} OAuth client credentials are placeholders; set the real {}_CLIENT_ID and {}_CLIENT_SECRET",
+                        name,
+                        name.to_uppercase(),
+                        name.to_uppercase()
+                    ));
+                }
+                warn!("{} OAuth client credentials look like placeholders", name);
             }
         }
 
         // TLS validation
         if self.security.tls.enabled {
-            if self.security.tls.cert_path.is_none() || self.security.tls.key_path.is_none() {
+            let (Some(cert_path), Some(key_path)) = (&self.security.tls.cert_path, &self.security.tls.key_path) else {
                 return Err(anyhow::anyhow!("TLS is enabled but cert_path or key_path is missing"));
+            };
+            for path in [cert_path, key_path] {
+                if !path.is_file() {
+                    return Err(anyhow::anyhow!("TLS file not found: {}", path.display()));
+                }
             }
         }
 
+        // CORS validation
+        CorsPolicy::new(&self.security.cors_origins, self.security.cors_allow_credentials)
+            .context("Invalid CORS_ORIGINS")?;
+
+        // Rate limit validation
+        let rate_limit = &self.security.rate_limit;
+        if rate_limit.enabled && (rate_limit.requests_per_window == 0 || rate_limit.window_seconds == 0) {
+            return Err(anyhow::anyhow!("RATE_LIMIT_REQUESTS and RATE_LIMIT_WINDOW must be positive when rate limiting is enabled"));
+        }
+
         Ok(())
     }
 
+    /// Whether this is a production deployment, whatever the case of `ENVIRONMENT`
+    pub fn is_production(&self) -> bool {
+        ["production", "prod"].iter().any(|name| self.environment.eq_ignore_ascii_case(name))
+    }
+
     /// Get a summary of the configuration for logging (without secrets)
     pub fn summary(&self) -> String {
         format!(
             "Pierre MCP Server Configuration:\n\
+             - Environment: {}\n\
+             - Bind Address: {}\n\
              - MCP Port: {}\n\
              - HTTP Port: {}\n\
              - Log Level: {}\n\
              - Database: {}\n\
              - Strava OAuth: {}\n\
              - Fitbit OAuth: {}\n\
+             - Garmin OAuth: {}\n\
              - TLS: {}\n\
              - Rate Limiting: {}",
+            self.environment,
+            self.bind_address,
             self.mcp_port,
             self.http_port,
             self.log_level,
             if self.database.url.starts_with("sqlite:") { "SQLite" } else { "External DB" },
-            if self.oauth.strava.enabled && self.oauth.strava.client_id.is_some() { "Enabled" } else { "Disabled" },
-            if self.oauth.fitbit.enabled && self.oauth.fitbit.client_id.is_some() { "Enabled" } else { "Disabled" },
+            if self.oauth.strava.is_configured() { "Enabled" } else { "Disabled" },
+            if self.oauth.fitbit.is_configured() { "Enabled" } else { "Disabled" },
+            if self.oauth.garmin.is_configured() { "Enabled" } else { "Disabled" },
             if self.security.tls.enabled { "Enabled" } else { "Disabled" },
             if self.security.rate_limit.enabled { "Enabled" } else { "Disabled" }
         )
     }
 }
 
+impl OAuthConfig {
+    /// Load every provider's credentials from `{PROVIDER}_CLIENT_ID` and
+    /// `{PROVIDER}_CLIENT_SECRET`, or from the files named by the same
+    /// variables with a `_FILE` suffix
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            strava: OAuthProviderConfig::from_env("STRAVA", env_config::strava_redirect_uri(), oauth::STRAVA_DEFAULT_SCOPES)?,
+            fitbit: OAuthProviderConfig::from_env("FITBIT", env_config::fitbit_redirect_uri(), oauth::FITBIT_DEFAULT_SCOPES)?,
+            garmin: OAuthProviderConfig::from_env("GARMIN", env_config::garmin_redirect_uri(), oauth::GARMIN_DEFAULT_SCOPES)?,
+        })
+    }
+
+    /// Configuration of a provider by name
+    pub fn provider(&self, name: &str) -> Option<&OAuthProviderConfig> {
+        self.providers().into_iter().find(|(provider, _)| *provider == name).map(|(_, config)| config)
+    }
+
+    /// Client ID and secret of an enabled provider
+    pub fn credentials(&self, provider: &str) -> Option<(String, String)> {
+        self.provider(provider).filter(|config| config.enabled).and_then(OAuthProviderConfig::credentials)
+    }
+
+    fn providers(&self) -> [(&'static str, &OAuthProviderConfig); 3] {
+        [("strava", &self.strava), ("fitbit", &self.fitbit), ("garmin", &self.garmin)]
+    }
+}
+
+impl OAuthProviderConfig {
+    fn from_env(prefix: &str, redirect_uri: String, scopes: &str) -> Result<Self> {
+        let enabled_var = format!("{}_ENABLED", prefix);
+        Ok(Self {
+            client_id: secret_from_env(&format!("{}_CLIENT_ID", prefix))?,
+            client_secret: secret_from_env(&format!("{}_CLIENT_SECRET", prefix))?,
+            redirect_uri: Some(redirect_uri),
+            scopes: parse_scopes(scopes),
+            enabled: env_var_or(&enabled_var, "true")?.parse()
+                .with_context(|| format!("Invalid {} value", enabled_var))?,
+        })
+    }
+
+    /// Client ID and secret, when both are set
+    pub fn credentials(&self) -> Option<(String, String)> {
+        self.client_id.clone().zip(self.client_secret.clone())
+    }
+
+    /// Whether users can connect this provider
+    pub fn is_configured(&self) -> bool {
+        self.enabled && self.credentials().is_some()
+    }
+
+    /// Whether the client ID or secret is still a dummy value from an example config
+    pub fn has_placeholder_credentials(&self) -> bool {
+        [&self.client_id, &self.client_secret].into_iter().flatten().any(|value| {
+            let value = value.to_lowercase();
+            oauth::PLACEHOLDER_CREDENTIAL_MARKERS.iter().any(|marker| value.contains(marker))
+        })
+    }
+}
+
+/// Read a secret from `key`, or from the file named by `{key}_FILE` as
+/// mounted by Docker and Kubernetes secrets. Setting both is an error.
+fn secret_from_env(key: &str) -> Result<Option<String>> {
+    let file_key = format!("{}_FILE", key);
+    let value = match (env::var(key).ok(), env::var(&file_key).ok()) {
+        (Some(_), Some(_)) => return Err(anyhow::anyhow!("Only one of {} and {} may be set", key, file_key)),
+        (Some(value), None) => value,
+        (None, Some(path)) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {} from {}", key, path))?,
+        (None, None) => return Ok(None),
+    };
+    let value = value.trim();
+    Ok((!value.is_empty()).then(|| value.to_string()))
+}
+
 /// Get environment variable or default value
 fn env_var_or(key: &str, default: &str) -> Result<String> {
     Ok(env::var(key).unwrap_or_else(|_| default.to_string()))
@@ -315,6 +423,8 @@ mod tests {
     fn test_config_validation() {
         // Test port conflict
         let mut config = ServerConfig {
+            environment: "development".to_string(),
+            bind_address: defaults::DEFAULT_BIND_ADDRESS.parse().unwrap(),
             mcp_port: env_config::mcp_port(),
             http_port: env_config::mcp_port(),  // Same as MCP port - should fail validation
             log_level: env_config::log_level(),
@@ -334,24 +444,10 @@ mod tests {
                 jwt_expiry_hours: limits::JWT_EXPIRY_HOURS as u64,
                 enable_refresh_tokens: false,
             },
-            oauth: OAuthConfig {
-                strava: OAuthProviderConfig {
-                    client_id: None,
-                    client_secret: None,
-                    redirect_uri: None,
-                    scopes: vec![],
-                    enabled: false,
-                },
-                fitbit: OAuthProviderConfig {
-                    client_id: None,
-                    client_secret: None,
-                    redirect_uri: None,
-                    scopes: vec![],
-                    enabled: false,
-                },
-            },
+            oauth: OAuthConfig::default(),
             security: SecurityConfig {
                 cors_origins: vec!["*".to_string()],
+                cors_allow_credentials: false,
                 rate_limit: RateLimitConfig {
                     enabled: false,
                     requests_per_window: limits::DEFAULT_RATE_LIMIT_REQUESTS,
@@ -370,5 +466,46 @@ mod tests {
         // Fix port conflict
         config.http_port = env_config::http_port();
         assert!(config.validate().is_ok());
+
+        // Credentials can't be sent to any origin
+        config.security.cors_allow_credentials = true;
+        assert!(config.validate().is_err());
+        config.security.cors_origins = vec!["https://*.example.com".to_string()];
+        assert!(config.validate().is_ok());
+
+        // Placeholder credentials are only tolerated outside production
+        config.oauth.strava = OAuthProviderConfig {
+            client_id: Some("12345".to_string()),
+            client_secret: Some("your_strava_client_secret_here".to_string()),
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        config.environment = "production".to_string();
+        assert!(config.validate().is_err());
+        for environment in ["Production", "PROD", "prod"] {
+            config.environment = environment.to_string();
+            assert!(config.validate().is_err(), "{}", environment);
+        }
+        config.oauth.strava.client_secret = Some("0123456789abcdef".to_string());
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn test_secret_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("client_secret");
+        std::fs::write(&path, "file_secret\n").unwrap();
+
+        env::set_var("PIERRE_TEST_SECRET_FILE", &path);
+        assert_eq!(secret_from_env("PIERRE_TEST_SECRET").unwrap().as_deref(), Some("file_secret"));
+
+        env::set_var("PIERRE_TEST_SECRET", "env_secret");
+        assert!(secret_from_env("PIERRE_TEST_SECRET").is_err());
+
+        env::remove_var("PIERRE_TEST_SECRET_FILE");
+        assert_eq!(secret_from_env("PIERRE_TEST_SECRET").unwrap().as_deref(), Some("env_secret"));
+        env::remove_var("PIERRE_TEST_SECRET");
+        assert_eq!(secret_from_env("PIERRE_TEST_SECRET").unwrap(), None);
     }
 }
\ No newline at end of file
@@ -92,14 +92,10 @@ pub mod env_config {
             .unwrap_or(24)
     }
     
-    /// Get Strava client ID from environment
-    pub fn strava_client_id() -> Option<String> {
-        env::var("STRAVA_CLIENT_ID").ok()
-    }
-    
-    /// Get Strava client secret from environment
-    pub fn strava_client_secret() -> Option<String> {
-        env::var("STRAVA_CLIENT_SECRET").ok()
+    /// Get the externally reachable base URL of the HTTP server, used in feed links
+    pub fn public_base_url() -> String {
+        env::var("PUBLIC_BASE_URL")
+            .unwrap_or_else(|_| format!("http://localhost:{}", http_port()))
     }
     
     /// Get Strava redirect URI from environment or default
@@ -137,6 +133,36 @@ pub mod env_config {
             .unwrap_or_else(|_| "https://www.strava.com/oauth/token".to_string())
     }
     
+    /// Get Fitbit redirect URI from environment or default
+    pub fn fitbit_redirect_uri() -> String {
+        env::var("FITBIT_REDIRECT_URI")
+            .unwrap_or_else(|_| "http://localhost:8081/oauth/callback/fitbit".to_string())
+    }
+    
+    /// Get Garmin Connect redirect URI from environment or default
+    pub fn garmin_redirect_uri() -> String {
+        env::var("GARMIN_REDIRECT_URI")
+            .unwrap_or_else(|_| "http://localhost:8081/oauth/callback/garmin".to_string())
+    }
+    
+    /// Get Garmin Health API base URL from environment or default
+    pub fn garmin_api_base() -> String {
+        env::var("GARMIN_API_BASE")
+            .unwrap_or_else(|_| "https://apis.garmin.com/wellness-api/rest".to_string())
+    }
+    
+    /// Get Garmin Connect auth URL from environment or default
+    pub fn garmin_auth_url() -> String {
+        env::var("GARMIN_AUTH_URL")
+            .unwrap_or_else(|_| "https://connect.garmin.com/oauth2Confirm".to_string())
+    }
+    
+    /// Get Garmin Connect token URL from environment or default
+    pub fn garmin_token_url() -> String {
+        env::var("GARMIN_TOKEN_URL")
+            .unwrap_or_else(|_| "https://diauth.garmin.com/di-oauth2-service/oauth/token".to_string())
+    }
+    
     /// Get max activities fetch limit from environment or default
     pub fn max_activities_fetch() -> usize {
         env::var("MAX_ACTIVITIES_FETCH")
@@ -168,6 +194,12 @@ pub mod errors {
     /// Unauthorized (custom error code)
     pub const ERROR_UNAUTHORIZED: i32 = -32000;
     
+    /// Authenticated, but the API key lacks the tool's scope (custom error code)
+    pub const ERROR_FORBIDDEN: i32 = -32001;
+    
+    /// Too many requests; the error data says when to retry (custom error code)
+    pub const ERROR_RATE_LIMITED: i32 = -32002;
+    
     /// Common error messages
     pub const MSG_METHOD_NOT_FOUND: &str = "Method not found";
     pub const MSG_INVALID_PARAMS: &str = "Invalid parameters";
@@ -188,6 +220,11 @@ pub mod endpoints {
     pub const FITBIT_API_BASE: &str = "https://api.fitbit.com";
     pub const FITBIT_AUTH_URL: &str = "https://www.fitbit.com/oauth2/authorize";
     pub const FITBIT_TOKEN_URL: &str = "https://api.fitbit.com/oauth2/token";
+    
+    /// Garmin Health and Activity API
+    pub const GARMIN_API_BASE: &str = "https://apis.garmin.com/wellness-api/rest";
+    pub const GARMIN_AUTH_URL: &str = "https://connect.garmin.com/oauth2Confirm";
+    pub const GARMIN_TOKEN_URL: &str = "https://diauth.garmin.com/di-oauth2-service/oauth/token";
 }
 
 /// HTTP routes and paths
@@ -196,6 +233,8 @@ pub mod routes {
     pub const AUTH_BASE: &str = "auth";
     pub const AUTH_REGISTER: &str = "register";
     pub const AUTH_LOGIN: &str = "login";
+    pub const AUTH_REFRESH: &str = "refresh";
+    pub const AUTH_LOGOUT: &str = "logout";
     
     /// OAuth routes
     pub const OAUTH_BASE: &str = "oauth";
@@ -212,14 +251,62 @@ pub mod limits {
     pub const MAX_ACTIVITIES_FETCH: usize = 100;
     pub const DEFAULT_ACTIVITIES_LIMIT: usize = 20;
     
+    /// Activity store sync
+    pub const ACTIVITY_SYNC_PAGE_SIZE: usize = 100;
+    pub const ACTIVITY_SYNC_MAX_PAGES: usize = 10;
+    
+    /// Authenticated provider instances are dropped after this long unused
+    pub const PROVIDER_CACHE_IDLE_SECS: u64 = 30 * 60;
+    
+    /// Activity file import
+    pub const MAX_IMPORT_FILE_BYTES: u64 = 25 * 1024 * 1024;
+    pub const IMPORT_WATCH_INTERVAL_SECS: u64 = 30;
+    pub const MAX_IMPORT_ARCHIVE_BYTES: u64 = 1024 * 1024 * 1024;
+    /// Decompressed bytes and entries read from one archive, nested ZIPs included
+    pub const MAX_IMPORT_ARCHIVE_EXPANDED_BYTES: u64 = 4 * 1024 * 1024 * 1024;
+    pub const MAX_IMPORT_ARCHIVE_ENTRIES: usize = 100_000;
+    /// Archive activities from another provider starting this close to a stored
+    /// one, with durations within the tolerance, are treated as the same activity
+    pub const ARCHIVE_DUPLICATE_WINDOW_SECS: i64 = 60;
+    pub const ARCHIVE_DUPLICATE_DURATION_TOLERANCE: f64 = 0.1;
+    
+    /// Garmin Health API pull requests cover at most one day of uploads
+    pub const GARMIN_PULL_WINDOW_SECS: i64 = 86_400;
+    pub const GARMIN_DEFAULT_LOOKBACK_DAYS: i64 = 30;
+    /// Garmin ping notifications are small; pushed records are ignored
+    pub const MAX_GARMIN_WEBHOOK_BYTES: u64 = 1024 * 1024;
+
+    /// Strava API quota shared by every user of the server
+    pub const STRAVA_SHORT_WINDOW_SECS: i64 = 15 * 60;
+    /// Percentage of each Strava window kept back so one burst can't use it all
+    pub const STRAVA_QUOTA_RESERVE_PERCENT: u32 = 5;
+    /// Requests wait for the next 15-minute window if it opens this soon; otherwise they are shed
+    pub const STRAVA_QUOTA_MAX_WAIT_SECS: u64 = 30;
+
+    /// Weather and location lookups per period report
+    pub const REPORT_MAX_ENRICHMENT_LOOKUPS: usize = 25;
+    
     /// Authentication
+    pub const OAUTH_STATE_EXPIRY_MINUTES: i64 = 10;
+    pub const TOKEN_REFRESH_MARGIN_MINUTES: i64 = 5;
     pub const MIN_PASSWORD_LENGTH: usize = 8;
     pub const JWT_EXPIRY_HOURS: i64 = 24;
+    /// Refresh tokens last this long unless used or revoked first
+    pub const REFRESH_TOKEN_EXPIRY_DAYS: i64 = 30;
+    /// Random characters in a refresh token
+    pub const REFRESH_TOKEN_LENGTH: usize = 64;
     pub const AUTH_THREAD_SLEEP_MS: u64 = 1;
     
+    /// How often TLS certificate files are checked for changes
+    pub const TLS_RELOAD_INTERVAL_SECS: u64 = 60;
+    /// Connections that don't finish the TLS handshake in time are dropped
+    pub const TLS_HANDSHAKE_TIMEOUT_SECS: u64 = 10;
+    
     /// Rate limiting defaults
     pub const DEFAULT_RATE_LIMIT_REQUESTS: u32 = 100;
     pub const DEFAULT_RATE_LIMIT_WINDOW_SECS: u64 = 60;
+    /// Expensive tools get this fraction of the normal request allowance
+    pub const EXPENSIVE_TOOL_LIMIT_DIVISOR: u32 = 10;
     
     /// Backup defaults
     pub const DEFAULT_BACKUP_INTERVAL_SECS: u64 = 21600; // 6 hours
@@ -238,13 +325,35 @@ pub mod limits {
     pub const METERS_PER_MILE: f64 = 1609.34;
 }
 
+/// API key format
+pub mod api_keys {
+    /// Marks a bearer token as an API key rather than a JWT
+    pub const KEY_PREFIX: &str = "pierre_";
+    /// Random characters after `KEY_PREFIX` that are stored and shown in listings
+    pub const VISIBLE_PREFIX_LENGTH: usize = 8;
+    /// Random characters of the secret part, only ever stored hashed
+    pub const SECRET_LENGTH: usize = 40;
+    /// Longest lifetime a key can be created with
+    pub const MAX_EXPIRY_DAYS: i64 = 3650;
+}
+
 /// OAuth scopes and provider defaults
 pub mod oauth {
+    /// Providers users connect through an OAuth flow
+    pub const OAUTH_PROVIDERS: &[&str] = &["strava", "fitbit", "garmin"];
+    
     /// Default OAuth scopes for Strava
     pub const STRAVA_DEFAULT_SCOPES: &str = "read,activity:read_all";
     
     /// Default OAuth scopes for Fitbit  
     pub const FITBIT_DEFAULT_SCOPES: &str = "activity,profile";
+    
+    /// Permissions granted by Garmin Connect when the user consents
+    pub const GARMIN_DEFAULT_SCOPES: &str = "ACTIVITY_EXPORT HEALTH_EXPORT";
+    
+    /// Fragments of the dummy client credentials in example configs, which
+    /// must be replaced before running in production
+    pub const PLACEHOLDER_CREDENTIAL_MARKERS: &[&str] = &["your_", "your-", "changeme", "change_me", "replace_me", "placeholder"];
 }
 
 /// User and application defaults
@@ -252,6 +361,9 @@ pub mod defaults {
     /// Default backup directory
     pub const DEFAULT_BACKUP_DIR: &str = "./backups";
     
+    /// Default address the MCP and HTTP listeners bind to
+    pub const DEFAULT_BIND_ADDRESS: &str = "127.0.0.1";
+    
     /// Default fitness level for new users
     pub const DEFAULT_FITNESS_LEVEL: &str = "beginner";
     
@@ -267,12 +379,14 @@ pub mod database {
     pub const TABLE_GOALS: &str = "goals";
     pub const TABLE_GOAL_MILESTONES: &str = "goal_milestones";
     pub const TABLE_ANALYTICS_INSIGHTS: &str = "analytics_insights";
+    pub const TABLE_ACTIVITIES: &str = "activities";
     
     /// Index names
     pub const INDEX_USERS_EMAIL: &str = "idx_users_email";
     pub const INDEX_GOALS_USER_ID: &str = "idx_goals_user_id";
     pub const INDEX_MILESTONES_GOAL_ID: &str = "idx_goal_milestones_goal_id";
     pub const INDEX_INSIGHTS_USER_ID: &str = "idx_analytics_insights_user_id";
+    pub const INDEX_ACTIVITIES_USER_START: &str = "idx_activities_user_start";
     
     /// Column defaults
     pub const DEFAULT_USER_ACTIVE: bool = true;
@@ -295,6 +409,9 @@ pub mod status {
     pub const GOAL_TYPE_PERFORMANCE: &str = "performance";
     pub const GOAL_TYPE_CUSTOM: &str = "custom";
     
+    /// Insight types
+    pub const INSIGHT_TYPE_GOAL_ADJUSTMENT: &str = "goal_adjustment";
+    
     /// Trend directions
     pub const TREND_IMPROVING: &str = "improving";
     pub const TREND_DECLINING: &str = "declining";
@@ -321,6 +438,8 @@ pub mod tools {
     pub const GET_ATHLETE: &str = "get_athlete";
     pub const GET_STATS: &str = "get_stats";
     pub const GET_ACTIVITY_INTELLIGENCE: &str = "get_activity_intelligence";
+    pub const QUERY_ACTIVITIES: &str = "query_activities";
+    pub const EXPORT_ACTIVITIES: &str = "export_activities";
     
     /// Connection management
     pub const CONNECT_STRAVA: &str = "connect_strava";
@@ -334,18 +453,31 @@ pub mod tools {
     pub const ANALYZE_PERFORMANCE_TRENDS: &str = "analyze_performance_trends";
     pub const COMPARE_ACTIVITIES: &str = "compare_activities";
     pub const DETECT_PATTERNS: &str = "detect_patterns";
+    pub const FIND_STREAKS: &str = "find_streaks";
+    pub const GENERATE_PERIOD_REPORT: &str = "generate_period_report";
     
     /// Goal management
     pub const SET_GOAL: &str = "set_goal";
     pub const TRACK_PROGRESS: &str = "track_progress";
     pub const SUGGEST_GOALS: &str = "suggest_goals";
     pub const ANALYZE_GOAL_FEASIBILITY: &str = "analyze_goal_feasibility";
+    pub const LIST_GOALS: &str = "list_goals";
+    pub const UPDATE_GOAL: &str = "update_goal";
+    pub const PAUSE_GOAL: &str = "pause_goal";
+    pub const COMPLETE_GOAL: &str = "complete_goal";
+    pub const DELETE_GOAL: &str = "delete_goal";
     
     /// Advanced analytics
     pub const GENERATE_RECOMMENDATIONS: &str = "generate_recommendations";
     pub const CALCULATE_FITNESS_SCORE: &str = "calculate_fitness_score";
     pub const PREDICT_PERFORMANCE: &str = "predict_performance";
     pub const ANALYZE_TRAINING_LOAD: &str = "analyze_training_load";
+    pub const GENERATE_TRAINING_PLAN: &str = "generate_training_plan";
+    pub const GET_TRAINING_PLAN: &str = "get_training_plan";
+    pub const EXPORT_WORKOUT: &str = "export_workout";
+    pub const GET_CALENDAR_FEED: &str = "get_calendar_feed";
+    pub const REVOKE_CALENDAR_FEED: &str = "revoke_calendar_feed";
+    pub const UPDATE_PROFILE: &str = "update_profile";
 }
 
 /// Common JSON field names
@@ -374,6 +506,7 @@ pub mod json_fields {
     pub const GOAL_ID: &str = "goal_id";
     pub const TIMEFRAME: &str = "timeframe";
     pub const METRIC: &str = "metric";
+    pub const TIMEZONE: &str = "timezone";
 }
 
 /// User-facing messages
@@ -394,6 +527,20 @@ pub mod messages {
     pub const GOAL_CREATED: &str = "Goal successfully created";
     pub const GOAL_NOT_FOUND: &str = "Goal not found";
     pub const GOAL_UPDATED: &str = "Goal updated successfully";
+    pub const GOAL_DELETED: &str = "Goal deleted successfully";
+
+    /// Training plan messages
+    pub const TRAINING_PLAN_CREATED: &str = "Training plan created";
+    pub const TRAINING_PLAN_NOT_FOUND: &str = "Training plan not found";
+
+    /// Profile messages
+    pub const PROFILE_UPDATED: &str = "Profile updated";
+    
+    /// Calendar feed messages
+    pub const CALENDAR_FEED_REVOKED: &str = "Calendar feed revoked";
+    pub const CALENDAR_FEED_NOT_FOUND: &str = "No calendar feed to revoke";
+    pub const CALENDAR_FEED_URL_NOT_SHOWN: &str =
+        "The feed URL is only shown when it is created; use rotate_token to get a new one";
     
     /// Analysis messages
     pub const INSUFFICIENT_DATA: &str = "Insufficient data for analysis";
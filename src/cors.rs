@@ -0,0 +1,258 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # CORS
+//!
+//! Cross-origin access to the HTTP API for browser clients such as the web
+//! dashboard. Allowed origins are exact (`https://app.example.com`), wildcard
+//! subdomains (`https://*.example.com`, which doesn't match the bare domain)
+//! or `*` for any origin. Requests from other origins are served without CORS
+//! headers, so browsers don't expose the responses, and their preflights are
+//! refused.
+
+use anyhow::Result;
+use std::convert::Infallible;
+use std::sync::Arc;
+use warp::http::header::{self, HeaderMap, HeaderValue};
+use warp::http::{Method, StatusCode};
+use warp::reply::Response;
+use warp::{Filter, Reply};
+
+const ALLOWED_METHODS: [Method; 4] = [Method::GET, Method::POST, Method::DELETE, Method::OPTIONS];
+const ALLOWED_HEADERS: [&str; 2] = ["authorization", "content-type"];
+/// Response headers scripts may read besides the CORS-safelisted ones
+const EXPOSED_HEADERS: &str = "retry-after, content-disposition";
+const PREFLIGHT_MAX_AGE_SECS: u32 = 600;
+
+/// One entry of the allowed origins list
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OriginPattern {
+    Any,
+    Exact(String),
+    /// `scheme://*.domain[:port]`, matching any subdomain of the domain
+    Subdomain { scheme: String, domain: String, port: Option<u16> },
+}
+
+impl OriginPattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        let pattern = pattern.trim().trim_end_matches('/').to_lowercase();
+        if pattern == "*" {
+            return Ok(Self::Any);
+        }
+
+        let (scheme, host, port) = split_origin(&pattern)
+            .ok_or_else(|| anyhow::anyhow!("Invalid CORS origin '{}': expected scheme://host[:port]", pattern))?;
+        match host.strip_prefix("*.") {
+            Some(domain) if !domain.is_empty() && !domain.contains('*') => Ok(Self::Subdomain {
+                scheme: scheme.to_string(),
+                domain: domain.to_string(),
+                port,
+            }),
+            None if !host.contains('*') => Ok(Self::Exact(pattern)),
+            _ => Err(anyhow::anyhow!("Invalid CORS origin '{}': only a leading '*.' wildcard is supported", pattern)),
+        }
+    }
+
+    fn matches(&self, origin: &str) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Exact(allowed) => allowed == origin,
+            Self::Subdomain { scheme, domain, port } => split_origin(origin).is_some_and(|(origin_scheme, host, origin_port)| {
+                origin_scheme == scheme
+                    && origin_port == *port
+                    && host.strip_suffix(domain.as_str()).is_some_and(|subdomain| subdomain.len() > 1 && subdomain.ends_with('.'))
+            }),
+        }
+    }
+}
+
+/// Split `scheme://host[:port]` into its parts
+fn split_origin(origin: &str) -> Option<(&str, &str, Option<u16>)> {
+    let (scheme, authority) = origin.split_once("://")?;
+    if scheme.is_empty() || authority.is_empty() || authority.contains(['/', '?', '#', '@']) {
+        return None;
+    }
+    // The last colon of a bracketed IPv6 host without a port isn't a port separator
+    match authority.rsplit_once(':').filter(|_| !authority.ends_with(']')) {
+        Some((host, port)) if !host.is_empty() => Some((scheme, host, Some(port.parse().ok()?))),
+        Some(_) => None,
+        None => Some((scheme, authority, None)),
+    }
+}
+
+/// Which origins may call the HTTP API, and whether they may send credentials
+#[derive(Debug, Clone)]
+pub struct CorsPolicy {
+    origins: Vec<OriginPattern>,
+    allow_credentials: bool,
+}
+
+impl CorsPolicy {
+    /// Build a policy from the configured origins. Credentials can't be
+    /// combined with `*`, which would let any site act as the user.
+    pub fn new(origins: &[String], allow_credentials: bool) -> Result<Self> {
+        let origins = origins.iter().map(|origin| OriginPattern::parse(origin)).collect::<Result<Vec<_>>>()?;
+        if allow_credentials && origins.contains(&OriginPattern::Any) {
+            return Err(anyhow::anyhow!("CORS credentials can't be allowed for any origin ('*'); list the allowed origins"));
+        }
+        Ok(Self { origins, allow_credentials })
+    }
+
+    /// Any origin, without credentials
+    pub fn allow_any() -> Self {
+        Self {
+            origins: vec![OriginPattern::Any],
+            allow_credentials: false,
+        }
+    }
+
+    /// Whether requests from an origin may read responses
+    pub fn allows(&self, origin: &str) -> bool {
+        let origin = origin.to_lowercase();
+        self.origins.iter().any(|pattern| pattern.matches(&origin))
+    }
+
+    /// Add the CORS response headers for a request from `origin`
+    fn decorate(&self, origin: &str, headers: &mut HeaderMap) {
+        let Ok(origin_value) = HeaderValue::from_str(origin) else {
+            return;
+        };
+        // Echoing the origin makes the response vary with it, unless any origin gets `*`
+        if self.allow_credentials || !self.origins.contains(&OriginPattern::Any) {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, origin_value);
+            headers.append(header::VARY, HeaderValue::from_static("origin"));
+        } else {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+        }
+        if self.allow_credentials {
+            headers.insert(header::ACCESS_CONTROL_ALLOW_CREDENTIALS, HeaderValue::from_static("true"));
+        }
+    }
+
+    /// Answer a preflight request, refusing it unless the origin, method and
+    /// headers are all allowed
+    fn preflight(&self, request_headers: &HeaderMap) -> Response {
+        let origin = request_headers.get(header::ORIGIN).and_then(|origin| origin.to_str().ok());
+        let method_allowed = request_headers
+            .get(header::ACCESS_CONTROL_REQUEST_METHOD)
+            .and_then(|method| Method::from_bytes(method.as_bytes()).ok())
+            .is_some_and(|method| ALLOWED_METHODS.contains(&method));
+        let headers_allowed = request_headers
+            .get_all(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .map(|name| name.trim().to_lowercase())
+            .all(|name| name.is_empty() || ALLOWED_HEADERS.contains(&name.as_str()));
+
+        let Some(origin) = origin.filter(|origin| self.allows(origin) && method_allowed && headers_allowed) else {
+            return StatusCode::FORBIDDEN.into_response();
+        };
+
+        let mut response = StatusCode::NO_CONTENT.into_response();
+        let headers = response.headers_mut();
+        self.decorate(origin, headers);
+        let methods = ALLOWED_METHODS.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+        headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_str(&methods).expect("method names are valid header values"));
+        headers.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static("authorization, content-type"));
+        headers.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(PREFLIGHT_MAX_AGE_SECS));
+        response
+    }
+
+    /// Answer preflight requests and add CORS headers to the responses of `routes`
+    pub fn apply<F, R>(self, routes: F) -> impl Filter<Extract = (Response,), Error = Infallible> + Clone
+    where
+        F: Filter<Extract = (R,), Error = Infallible> + Clone + Send + Sync + 'static,
+        R: Reply,
+    {
+        let policy = Arc::new(self);
+
+        let preflight = warp::options()
+            .and(warp::header::headers_cloned())
+            .and_then({
+                let policy = policy.clone();
+                move |headers: HeaderMap| {
+                    let policy = policy.clone();
+                    async move {
+                        // A plain OPTIONS request isn't a preflight and goes to the routes
+                        if !headers.contains_key(header::ACCESS_CONTROL_REQUEST_METHOD) {
+                            return Err(warp::reject::not_found());
+                        }
+                        Ok(policy.preflight(&headers))
+                    }
+                }
+            });
+
+        let decorated = warp::header::headers_cloned()
+            .and(routes)
+            .map(move |request_headers: HeaderMap, reply: R| {
+                let mut response = reply.into_response();
+                let origin = request_headers.get(header::ORIGIN).and_then(|origin| origin.to_str().ok());
+                if let Some(origin) = origin.filter(|origin| policy.allows(origin)) {
+                    policy.decorate(origin, response.headers_mut());
+                    response
+                        .headers_mut()
+                        .insert(header::ACCESS_CONTROL_EXPOSE_HEADERS, HeaderValue::from_static(EXPOSED_HEADERS));
+                }
+                response
+            });
+
+        preflight.or(decorated).unify()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(origins: &[&str], allow_credentials: bool) -> CorsPolicy {
+        let origins: Vec<String> = origins.iter().map(|origin| origin.to_string()).collect();
+        CorsPolicy::new(&origins, allow_credentials).unwrap()
+    }
+
+    #[test]
+    fn test_exact_origins() {
+        let policy = policy(&["https://app.example.com", "http://localhost:3000/"], false);
+        assert!(policy.allows("https://app.example.com"));
+        assert!(policy.allows("HTTPS://App.Example.com"));
+        assert!(policy.allows("http://localhost:3000"));
+        assert!(!policy.allows("http://localhost:3001"));
+        assert!(!policy.allows("http://app.example.com"));
+        assert!(!policy.allows("https://app.example.com.evil.com"));
+    }
+
+    #[test]
+    fn test_wildcard_subdomains() {
+        let policy = policy(&["https://*.example.com"], false);
+        assert!(policy.allows("https://app.example.com"));
+        assert!(policy.allows("https://eu.app.example.com"));
+        assert!(!policy.allows("https://example.com"));
+        assert!(!policy.allows("https://evilexample.com"));
+        assert!(!policy.allows("https://app.example.com:8443"));
+        assert!(!policy.allows("http://app.example.com"));
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_rejected() {
+        for pattern in ["example.com", "https://app.*.com", "https://*", "https://example.com/path"] {
+            assert!(CorsPolicy::new(&[pattern.to_string()], false).is_err(), "{} should be rejected", pattern);
+        }
+        assert!(CorsPolicy::new(&["*".to_string()], true).is_err());
+    }
+
+    #[test]
+    fn test_credentials_echo_the_origin() {
+        let mut headers = HeaderMap::new();
+        policy(&["https://*.example.com"], true).decorate("https://app.example.com", &mut headers);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "https://app.example.com");
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_CREDENTIALS], "true");
+
+        let mut headers = HeaderMap::new();
+        CorsPolicy::allow_any().decorate("https://app.example.com", &mut headers);
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_CREDENTIALS));
+    }
+}
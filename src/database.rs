@@ -9,10 +9,16 @@
 //! This module provides database functionality for the multi-tenant Pierre MCP Server.
 //! It handles user storage, token encryption, and secure data access patterns.
 
-use crate::models::{User, EncryptedToken, DecryptedToken};
+use crate::activity_query::ActivityQuery;
+use crate::intelligence::{
+    FitnessLevel, Goal, GoalStatus, GoalType, Milestone, TimeAvailability, TimeFrame, TrainingPlan,
+    UserFitnessProfile, UserPreferences,
+};
+use crate::models::{Activity, ActivityDetails, ApiKey, ApiKeyScope, AuthSession, User, EncryptedToken, DecryptedToken, OAuthState, ProviderConnection};
+use crate::workouts::WorkoutThresholds;
 use anyhow::Result;
-use chrono::{DateTime, Utc};
-use sqlx::{Pool, Sqlite, SqlitePool, Row};
+use chrono::{DateTime, SecondsFormat, Utc};
+use sqlx::{Pool, QueryBuilder, Sqlite, SqlitePool, Row};
 use uuid::Uuid;
 
 /// Database manager for user and token storage
@@ -55,16 +61,6 @@ impl Database {
                 email TEXT UNIQUE NOT NULL,
                 display_name TEXT,
                 password_hash TEXT NOT NULL,
-                strava_access_token TEXT,
-                strava_refresh_token TEXT,
-                strava_expires_at TEXT,
-                strava_scope TEXT,
-                strava_nonce TEXT,
-                fitbit_access_token TEXT,
-                fitbit_refresh_token TEXT,
-                fitbit_expires_at TEXT,
-                fitbit_scope TEXT,
-                fitbit_nonce TEXT,
                 created_at TEXT NOT NULL,
                 last_active TEXT NOT NULL,
                 is_active BOOLEAN NOT NULL DEFAULT 1
@@ -74,6 +70,50 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // Create provider_connections table, one row per linked provider account
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS provider_connections (
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                access_token TEXT NOT NULL,
+                refresh_token TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                scope TEXT NOT NULL,
+                nonce TEXT NOT NULL,
+                external_athlete_id TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, provider),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create oauth_states table for authorizations awaiting their provider callback.
+        // No foreign key: rows are short-lived and only ever read back by state.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS oauth_states (
+                state TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                code_verifier TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Tokens of databases created when each provider had its own users columns
+        for provider in ["strava", "fitbit", "garmin"] {
+            self.move_provider_columns(provider).await?;
+        }
+
         // Create index on email for fast lookups
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_users_email ON users(email)")
             .execute(&self.pool)
@@ -116,6 +156,7 @@ impl Database {
                 description TEXT,
                 goal_type TEXT NOT NULL, -- 'distance', 'time', 'frequency', 'performance', 'custom'
                 sport_type TEXT,
+                goal_params TEXT, -- JSON serialized GoalType
                 target_value REAL NOT NULL,
                 target_date TEXT NOT NULL,
                 current_value REAL DEFAULT 0,
@@ -129,6 +170,16 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // Goals created before typed goal parameters were stored
+        self.add_column_if_missing("goals", "goal_params", "TEXT").await?;
+
+        // Thresholds that structured workout targets are relative to
+        self.add_column_if_missing("user_profiles", "ftp_watts", "REAL").await?;
+        self.add_column_if_missing("user_profiles", "threshold_pace_seconds_per_km", "REAL").await?;
+
+        // IANA timezone the user's training days and times are read in
+        self.add_column_if_missing("user_profiles", "timezone", "TEXT").await?;
+
         // Create goal_milestones table
         sqlx::query(
             r#"
@@ -168,6 +219,139 @@ impl Database {
         .execute(&self.pool)
         .await?;
 
+        // Create activities table as the local store for synced provider activities
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS activities (
+                id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                name TEXT NOT NULL,
+                sport_type TEXT NOT NULL,
+                start_date TEXT NOT NULL, -- RFC 3339 UTC, second precision
+                duration_seconds INTEGER NOT NULL,
+                distance_meters REAL,
+                elevation_gain REAL,
+                average_heart_rate INTEGER,
+                city TEXT,
+                region TEXT,
+                trail_name TEXT,
+                data TEXT NOT NULL, -- JSON serialized Activity
+                synced_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, provider, id),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create activity_sync_state table; where syncing older provider history resumes
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS activity_sync_state (
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                backfill_offset INTEGER, -- NULL once the full history is stored
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, provider),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create activity_streams table; samples, laps and device of imported activities as JSON
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS activity_streams (
+                activity_id TEXT NOT NULL,
+                user_id TEXT NOT NULL,
+                provider TEXT NOT NULL,
+                data TEXT NOT NULL, -- JSON serialized streams, laps and device
+                created_at TEXT NOT NULL,
+                PRIMARY KEY (user_id, provider, activity_id),
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create training_plans table; the full plan is kept as JSON
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS training_plans (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                name TEXT,
+                sport_type TEXT NOT NULL,
+                event_date TEXT NOT NULL,
+                plan_data TEXT NOT NULL, -- JSON serialized TrainingPlan
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create calendar_tokens table; one revocable feed token per user, stored as a hash
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS calendar_tokens (
+                user_id TEXT PRIMARY KEY,
+                token_hash TEXT NOT NULL UNIQUE,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create api_keys table; only a hash of each key's secret is kept
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS api_keys (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                key_prefix TEXT NOT NULL UNIQUE,
+                key_hash TEXT NOT NULL UNIQUE,
+                scopes TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                last_used_at TEXT,
+                revoked_at TEXT,
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
+        // Create sessions table; one row per issued JWT, keyed by its jti.
+        // Only a hash of the refresh token is kept.
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                user_id TEXT NOT NULL,
+                family_id TEXT NOT NULL,
+                refresh_token_hash TEXT UNIQUE,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL,
+                revoked_at TEXT,
+                replaced_by TEXT,
+                FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+
         // Create indexes for performance
         sqlx::query("CREATE INDEX IF NOT EXISTS idx_goals_user_id ON goals(user_id)")
             .execute(&self.pool)
@@ -181,6 +365,78 @@ impl Database {
             .execute(&self.pool)
             .await?;
 
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_activities_user_start ON activities(user_id, start_date)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_training_plans_user_id ON training_plans(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_api_keys_user_id ON api_keys(user_id)")
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_sessions_family_id ON sessions(family_id)")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Whether a table has the given column
+    async fn column_exists(&self, table: &str, column: &str) -> Result<bool> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(columns
+            .iter()
+            .any(|row| row.try_get::<String, _>("name").is_ok_and(|name| name == column)))
+    }
+
+    /// Add a column to an existing table unless it is already present
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        if !self.column_exists(table, column).await? {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(&self.pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Move a provider's `users` token columns into `provider_connections` and drop them
+    async fn move_provider_columns(&self, provider: &str) -> Result<()> {
+        if !self.column_exists("users", &format!("{}_access_token", provider)).await? {
+            return Ok(());
+        }
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(&format!(
+            r#"
+            INSERT OR IGNORE INTO provider_connections
+                (user_id, provider, access_token, refresh_token, expires_at, scope, nonce, created_at, updated_at)
+            SELECT id, ?1, {p}_access_token, {p}_refresh_token, {p}_expires_at, {p}_scope, {p}_nonce,
+                   last_active, last_active
+            FROM users
+            WHERE {p}_access_token IS NOT NULL AND {p}_refresh_token IS NOT NULL
+              AND {p}_expires_at IS NOT NULL AND {p}_scope IS NOT NULL AND {p}_nonce IS NOT NULL
+            "#,
+            p = provider
+        ))
+        .bind(provider)
+        .execute(&mut *tx)
+        .await?;
+
+        for column in ["access_token", "refresh_token", "expires_at", "scope", "nonce"] {
+            sqlx::query(&format!("ALTER TABLE users DROP COLUMN {}_{}", provider, column))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        tracing::info!("Moved {} tokens into provider_connections", provider);
         Ok(())
     }
 
@@ -213,7 +469,7 @@ impl Database {
             .await?;
 
         match row {
-            Some(row) => Ok(Some(self.row_to_user(row)?)),
+            Some(row) => Ok(Some(self.with_provider_connections(self.row_to_user(row)?).await?)),
             None => Ok(None),
         }
     }
@@ -226,7 +482,7 @@ impl Database {
             .await?;
 
         match row {
-            Some(row) => Ok(Some(self.row_to_user(row)?)),
+            Some(row) => Ok(Some(self.with_provider_connections(self.row_to_user(row)?).await?)),
             None => Ok(None),
         }
     }
@@ -239,10 +495,11 @@ impl Database {
         }
     }
 
-    /// Update user's Strava token
-    pub async fn update_strava_token(
+    /// Store a user's tokens for a provider, replacing any earlier ones
+    pub async fn update_provider_token(
         &self,
         user_id: Uuid,
+        provider: &str,
         access_token: &str,
         refresh_token: &str,
         expires_at: DateTime<Utc>,
@@ -255,150 +512,135 @@ impl Database {
             scope,
             &self.encryption_key,
         )?;
+        let now = Utc::now().to_rfc3339();
 
         sqlx::query(
             r#"
-            UPDATE users 
-            SET strava_access_token = ?1, strava_refresh_token = ?2, strava_expires_at = ?3, 
-                strava_scope = ?4, strava_nonce = ?5, last_active = ?6
-            WHERE id = ?7
+            INSERT INTO provider_connections
+                (user_id, provider, access_token, refresh_token, expires_at, scope, nonce, created_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?8)
+            ON CONFLICT(user_id, provider) DO UPDATE SET
+                access_token = excluded.access_token,
+                refresh_token = excluded.refresh_token,
+                expires_at = excluded.expires_at,
+                scope = excluded.scope,
+                nonce = excluded.nonce,
+                updated_at = excluded.updated_at
             "#,
         )
+        .bind(user_id.to_string())
+        .bind(provider)
         .bind(&encrypted_token.access_token)
         .bind(&encrypted_token.refresh_token)
         .bind(encrypted_token.expires_at.to_rfc3339())
         .bind(&encrypted_token.scope)
         .bind(&encrypted_token.nonce)
-        .bind(Utc::now().to_rfc3339())
-        .bind(user_id.to_string())
+        .bind(&now)
         .execute(&self.pool)
         .await?;
 
-        Ok(())
-    }
-
-    /// Get decrypted Strava token for user
-    pub async fn get_strava_token(&self, user_id: Uuid) -> Result<Option<DecryptedToken>> {
-        let row = sqlx::query(
-            r#"
-            SELECT strava_access_token, strava_refresh_token, strava_expires_at, 
-                   strava_scope, strava_nonce 
-            FROM users WHERE id = ?1
-            "#,
-        )
-        .bind(user_id.to_string())
-        .fetch_optional(&self.pool)
-        .await?;
-
-        match row {
-            Some(row) => {
-                let access_token: Option<String> = row.try_get("strava_access_token")?;
-                let refresh_token: Option<String> = row.try_get("strava_refresh_token")?;
-                let expires_at: Option<String> = row.try_get("strava_expires_at")?;
-                let scope: Option<String> = row.try_get("strava_scope")?;
-                let nonce: Option<String> = row.try_get("strava_nonce")?;
-
-                if let (Some(access), Some(refresh), Some(expires), Some(scope), Some(nonce)) =
-                    (access_token, refresh_token, expires_at, scope, nonce)
-                {
-                    let encrypted_token = EncryptedToken {
-                        access_token: access,
-                        refresh_token: refresh,
-                        expires_at: DateTime::parse_from_rfc3339(&expires)?.with_timezone(&Utc),
-                        scope,
-                        nonce,
-                    };
-
-                    let decrypted = encrypted_token.decrypt(&self.encryption_key)?;
-                    Ok(Some(decrypted))
-                } else {
-                    Ok(None)
-                }
-            }
-            None => Ok(None),
-        }
+        self.update_last_active(user_id).await
     }
 
-    /// Update user's Fitbit token
-    pub async fn update_fitbit_token(
+    /// Record the user's athlete or user id at a connected provider
+    pub async fn update_provider_athlete_id(
         &self,
         user_id: Uuid,
-        access_token: &str,
-        refresh_token: &str,
-        expires_at: DateTime<Utc>,
-        scope: String,
+        provider: &str,
+        external_athlete_id: &str,
     ) -> Result<()> {
-        let encrypted_token = EncryptedToken::new(
-            access_token,
-            refresh_token,
-            expires_at,
-            scope,
-            &self.encryption_key,
-        )?;
-
         sqlx::query(
-            r#"
-            UPDATE users 
-            SET fitbit_access_token = ?1, fitbit_refresh_token = ?2, fitbit_expires_at = ?3, 
-                fitbit_scope = ?4, fitbit_nonce = ?5, last_active = ?6
-            WHERE id = ?7
-            "#,
+            "UPDATE provider_connections SET external_athlete_id = ?1 WHERE user_id = ?2 AND provider = ?3",
         )
-        .bind(&encrypted_token.access_token)
-        .bind(&encrypted_token.refresh_token)
-        .bind(encrypted_token.expires_at.to_rfc3339())
-        .bind(&encrypted_token.scope)
-        .bind(&encrypted_token.nonce)
-        .bind(Utc::now().to_rfc3339())
+        .bind(external_athlete_id)
         .bind(user_id.to_string())
+        .bind(provider)
         .execute(&self.pool)
         .await?;
 
         Ok(())
     }
 
-    /// Get decrypted Fitbit token for user
-    pub async fn get_fitbit_token(&self, user_id: Uuid) -> Result<Option<DecryptedToken>> {
+    /// Users connected to a provider under the given athlete or user id
+    pub async fn get_users_by_provider_athlete_id(&self, provider: &str, external_athlete_id: &str) -> Result<Vec<Uuid>> {
+        let rows = sqlx::query(
+            "SELECT user_id FROM provider_connections WHERE provider = ?1 AND external_athlete_id = ?2",
+        )
+        .bind(provider)
+        .bind(external_athlete_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter()
+            .map(|row| Ok(Uuid::parse_str(&row.try_get::<String, _>("user_id")?)?))
+            .collect()
+    }
+
+    /// Get decrypted token for a user's provider connection
+    pub async fn get_provider_token(&self, user_id: Uuid, provider: &str) -> Result<Option<DecryptedToken>> {
         let row = sqlx::query(
             r#"
-            SELECT fitbit_access_token, fitbit_refresh_token, fitbit_expires_at, 
-                   fitbit_scope, fitbit_nonce 
-            FROM users WHERE id = ?1
+            SELECT * FROM provider_connections WHERE user_id = ?1 AND provider = ?2
             "#,
         )
         .bind(user_id.to_string())
+        .bind(provider)
         .fetch_optional(&self.pool)
         .await?;
 
         match row {
             Some(row) => {
-                let access_token: Option<String> = row.try_get("fitbit_access_token")?;
-                let refresh_token: Option<String> = row.try_get("fitbit_refresh_token")?;
-                let expires_at: Option<String> = row.try_get("fitbit_expires_at")?;
-                let scope: Option<String> = row.try_get("fitbit_scope")?;
-                let nonce: Option<String> = row.try_get("fitbit_nonce")?;
-
-                if let (Some(access), Some(refresh), Some(expires), Some(scope), Some(nonce)) =
-                    (access_token, refresh_token, expires_at, scope, nonce)
-                {
-                    let encrypted_token = EncryptedToken {
-                        access_token: access,
-                        refresh_token: refresh,
-                        expires_at: DateTime::parse_from_rfc3339(&expires)?.with_timezone(&Utc),
-                        scope,
-                        nonce,
-                    };
-
-                    let decrypted = encrypted_token.decrypt(&self.encryption_key)?;
-                    Ok(Some(decrypted))
-                } else {
-                    Ok(None)
-                }
+                let connection = Self::row_to_provider_connection(&row)?;
+                Ok(Some(connection.token.decrypt(&self.encryption_key)?))
             }
             None => Ok(None),
         }
     }
 
+    /// Get all of a user's provider connections, tokens still encrypted
+    pub async fn get_provider_connections(&self, user_id: Uuid) -> Result<Vec<ProviderConnection>> {
+        let rows = sqlx::query(
+            "SELECT * FROM provider_connections WHERE user_id = ?1 ORDER BY created_at, provider",
+        )
+        .bind(user_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        rows.iter().map(Self::row_to_provider_connection).collect()
+    }
+
+    /// Remove a user's provider connection, returning whether there was one
+    pub async fn delete_provider_connection(&self, user_id: Uuid, provider: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM provider_connections WHERE user_id = ?1 AND provider = ?2")
+            .bind(user_id.to_string())
+            .bind(provider)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Convert database row to ProviderConnection model
+    fn row_to_provider_connection(row: &sqlx::sqlite::SqliteRow) -> Result<ProviderConnection> {
+        let expires_at: String = row.try_get("expires_at")?;
+        let created_at: String = row.try_get("created_at")?;
+        let updated_at: String = row.try_get("updated_at")?;
+
+        Ok(ProviderConnection {
+            provider: row.try_get("provider")?,
+            token: EncryptedToken {
+                access_token: row.try_get("access_token")?,
+                refresh_token: row.try_get("refresh_token")?,
+                expires_at: DateTime::parse_from_rfc3339(&expires_at)?.with_timezone(&Utc),
+                scope: row.try_get("scope")?,
+                nonce: row.try_get("nonce")?,
+            },
+            external_athlete_id: row.try_get("external_athlete_id")?,
+            connected_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+            updated_at: DateTime::parse_from_rfc3339(&updated_at)?.with_timezone(&Utc),
+        })
+    }
+
     /// Update user's last active timestamp
     pub async fn update_last_active(&self, user_id: Uuid) -> Result<()> {
         sqlx::query("UPDATE users SET last_active = ?1 WHERE id = ?2")
@@ -420,6 +662,64 @@ impl Database {
         Ok(count)
     }
 
+    /// Store a pending OAuth authorization, dropping any that have expired
+    pub async fn store_oauth_state(&self, oauth_state: &OAuthState) -> Result<()> {
+        let now = Utc::now().to_rfc3339();
+
+        sqlx::query("DELETE FROM oauth_states WHERE expires_at <= ?1")
+            .bind(&now)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO oauth_states (state, user_id, provider, code_verifier, expires_at, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+            "#,
+        )
+        .bind(&oauth_state.state)
+        .bind(oauth_state.user_id.to_string())
+        .bind(&oauth_state.provider)
+        .bind(&oauth_state.code_verifier)
+        .bind(oauth_state.expires_at.to_rfc3339())
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove and return a pending OAuth authorization, so each state is accepted once.
+    /// Expired states are removed too but not returned.
+    pub async fn consume_oauth_state(&self, state: &str) -> Result<Option<OAuthState>> {
+        let row = sqlx::query("DELETE FROM oauth_states WHERE state = ?1 RETURNING *")
+            .bind(state)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let user_id: String = row.try_get("user_id")?;
+        let expires_at: String = row.try_get("expires_at")?;
+        let oauth_state = OAuthState {
+            state: row.try_get("state")?,
+            user_id: Uuid::parse_str(&user_id)?,
+            provider: row.try_get("provider")?,
+            code_verifier: row.try_get("code_verifier")?,
+            expires_at: DateTime::parse_from_rfc3339(&expires_at)?.with_timezone(&Utc),
+        };
+
+        Ok((oauth_state.expires_at > Utc::now()).then_some(oauth_state))
+    }
+
+    /// Attach the user's provider connections, which live in their own table
+    async fn with_provider_connections(&self, mut user: User) -> Result<User> {
+        user.provider_connections = self.get_provider_connections(user.id).await?;
+        Ok(user)
+    }
+
     /// Convert database row to User model
     fn row_to_user(&self, row: sqlx::sqlite::SqliteRow) -> Result<User> {
         let id_str: String = row.try_get("id")?;
@@ -437,106 +737,72 @@ impl Database {
         
         let is_active: bool = row.try_get("is_active")?;
 
-        // Build encrypted tokens if they exist
-        let strava_token = self.build_encrypted_token(&row, "strava")?;
-        let fitbit_token = self.build_encrypted_token(&row, "fitbit")?;
-
         Ok(User {
             id,
             email,
             display_name,
             password_hash,
-            strava_token,
-            fitbit_token,
+            provider_connections: Vec::new(),
             created_at,
             last_active,
             is_active,
         })
     }
 
-    /// Build encrypted token from database row
-    fn build_encrypted_token(
-        &self,
-        row: &sqlx::sqlite::SqliteRow,
-        provider: &str,
-    ) -> Result<Option<EncryptedToken>> {
-        let access_token: Option<String> = match provider {
-            "strava" => row.try_get("strava_access_token")?,
-            "fitbit" => row.try_get("fitbit_access_token")?,
-            _ => None,
-        };
-        let refresh_token: Option<String> = match provider {
-            "strava" => row.try_get("strava_refresh_token")?,
-            "fitbit" => row.try_get("fitbit_refresh_token")?,
-            _ => None,
-        };
-        let expires_at: Option<String> = match provider {
-            "strava" => row.try_get("strava_expires_at")?,
-            "fitbit" => row.try_get("fitbit_expires_at")?,
-            _ => None,
-        };
-        let scope: Option<String> = match provider {
-            "strava" => row.try_get("strava_scope")?,
-            "fitbit" => row.try_get("fitbit_scope")?,
-            _ => None,
-        };
-        let nonce: Option<String> = match provider {
-            "strava" => row.try_get("strava_nonce")?,
-            "fitbit" => row.try_get("fitbit_nonce")?,
-            _ => None,
-        };
-
-        if let (Some(access), Some(refresh), Some(expires), Some(scope), Some(nonce)) =
-            (access_token, refresh_token, expires_at, scope, nonce)
-        {
-            Ok(Some(EncryptedToken {
-                access_token: access,
-                refresh_token: refresh,
-                expires_at: DateTime::parse_from_rfc3339(&expires)?.with_timezone(&Utc),
-                scope,
-                nonce,
-            }))
-        } else {
-            Ok(None)
-        }
-    }
-
     // === ANALYTICS METHODS ===
 
-    /// Create or update user fitness profile
+    /// Create or update user fitness profile. Only fields present in
+    /// `profile_data` are written; fields it omits keep their stored value
     pub async fn upsert_user_profile(&self, user_id: Uuid, profile_data: serde_json::Value) -> Result<()> {
         let now = Utc::now().to_rfc3339();
-        
-        sqlx::query(
-            r#"
-            INSERT OR REPLACE INTO user_profiles (
+        sqlx::query(r#"
+            INSERT INTO user_profiles (
                 user_id, age, gender, weight_kg, height_cm, fitness_level,
                 primary_sports, training_history_months, preferred_units,
                 training_focus, injury_history, hours_per_week, preferred_days,
-                preferred_duration_minutes, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, 
-                     COALESCE((SELECT created_at FROM user_profiles WHERE user_id = ?1), ?15), ?16)
-            "#,
-        )
+                preferred_duration_minutes, created_at, updated_at, ftp_watts,
+                threshold_pace_seconds_per_km, timezone
+            ) VALUES (?1, ?2, ?3, ?4, ?5, COALESCE(?6, 'beginner'), COALESCE(?7, '[]'), COALESCE(?8, 0),
+                     COALESCE(?9, 'metric'), COALESCE(?10, '[]'), COALESCE(?11, '[]'), COALESCE(?12, 0),
+                     COALESCE(?13, '[]'), ?14, ?15, ?15, ?16, ?17, ?18)
+            ON CONFLICT(user_id) DO UPDATE SET
+                age = COALESCE(?2, age),
+                gender = COALESCE(?3, gender),
+                weight_kg = COALESCE(?4, weight_kg),
+                height_cm = COALESCE(?5, height_cm),
+                fitness_level = COALESCE(?6, fitness_level),
+                primary_sports = COALESCE(?7, primary_sports),
+                training_history_months = COALESCE(?8, training_history_months),
+                preferred_units = COALESCE(?9, preferred_units),
+                training_focus = COALESCE(?10, training_focus),
+                injury_history = COALESCE(?11, injury_history),
+                hours_per_week = COALESCE(?12, hours_per_week),
+                preferred_days = COALESCE(?13, preferred_days),
+                preferred_duration_minutes = COALESCE(?14, preferred_duration_minutes),
+                updated_at = ?15,
+                ftp_watts = COALESCE(?16, ftp_watts),
+                threshold_pace_seconds_per_km = COALESCE(?17, threshold_pace_seconds_per_km),
+                timezone = COALESCE(?18, timezone)
+            "#)
         .bind(user_id.to_string())
         .bind(profile_data.get("age").and_then(|v| v.as_i64()))
         .bind(profile_data.get("gender").and_then(|v| v.as_str()))
         .bind(profile_data.get("weight_kg").and_then(|v| v.as_f64()))
         .bind(profile_data.get("height_cm").and_then(|v| v.as_f64()))
-        .bind(profile_data.get("fitness_level").and_then(|v| v.as_str()).unwrap_or("beginner"))
-        .bind(profile_data.get("primary_sports").map(|v| v.to_string()).unwrap_or_else(|| "[]".to_string()))
-        .bind(profile_data.get("training_history_months").and_then(|v| v.as_i64()).unwrap_or(0))
-        .bind(profile_data.get("preferred_units").and_then(|v| v.as_str()).unwrap_or("metric"))
-        .bind(profile_data.get("training_focus").map(|v| v.to_string()).unwrap_or_else(|| "[]".to_string()))
-        .bind(profile_data.get("injury_history").map(|v| v.to_string()).unwrap_or_else(|| "[]".to_string()))
-        .bind(profile_data.get("hours_per_week").and_then(|v| v.as_f64()).unwrap_or(0.0))
-        .bind(profile_data.get("preferred_days").map(|v| v.to_string()).unwrap_or_else(|| "[]".to_string()))
+        .bind(profile_data.get("fitness_level").and_then(|v| v.as_str()))
+        .bind(profile_data.get("primary_sports").map(|v| v.to_string()))
+        .bind(profile_data.get("training_history_months").and_then(|v| v.as_i64()))
+        .bind(profile_data.get("preferred_units").and_then(|v| v.as_str()))
+        .bind(profile_data.get("training_focus").map(|v| v.to_string()))
+        .bind(profile_data.get("injury_history").map(|v| v.to_string()))
+        .bind(profile_data.get("hours_per_week").and_then(|v| v.as_f64()))
+        .bind(profile_data.get("preferred_days").map(|v| v.to_string()))
         .bind(profile_data.get("preferred_duration_minutes").and_then(|v| v.as_i64()))
-        .bind(&now) // for created_at when inserting new record
-        .bind(&now) // for updated_at
-        .execute(&self.pool)
-        .await?;
-
+        .bind(&now)
+        .bind(profile_data.get("ftp_watts").and_then(|v| v.as_f64()))
+        .bind(profile_data.get("threshold_pace_seconds_per_km").and_then(|v| v.as_f64()))
+        .bind(profile_data.get("timezone").and_then(|v| v.as_str()))
+        .execute(&self.pool).await?;
         Ok(())
     }
 
@@ -578,71 +844,183 @@ impl Database {
         }
     }
 
-    /// Create a new goal
-    pub async fn create_goal(&self, user_id: Uuid, goal_data: serde_json::Value) -> Result<String> {
-        let goal_id = uuid::Uuid::new_v4().to_string();
-        let now = Utc::now().to_rfc3339();
+    /// Get the FTP and threshold pace that structured workout targets are based on
+    pub async fn get_workout_thresholds(&self, user_id: Uuid) -> Result<WorkoutThresholds> {
+        let row = sqlx::query("SELECT ftp_watts, threshold_pace_seconds_per_km FROM user_profiles WHERE user_id = ?1")
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(WorkoutThresholds {
+                ftp_watts: row.try_get("ftp_watts")?,
+                threshold_pace_seconds_per_km: row.try_get("threshold_pace_seconds_per_km")?,
+            }),
+            None => Ok(WorkoutThresholds::default()),
+        }
+    }
+
+    /// Get the IANA timezone stored on the user's profile, if any
+    pub async fn get_user_timezone(&self, user_id: Uuid) -> Result<Option<String>> {
+        let timezone = sqlx::query_scalar::<_, Option<String>>("SELECT timezone FROM user_profiles WHERE user_id = ?1")
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(timezone.flatten())
+    }
+
+    /// Get the full fitness profile used by the analytics engines
+    pub async fn get_user_fitness_profile(&self, user_id: Uuid) -> Result<Option<UserFitnessProfile>> {
+        let row = sqlx::query("SELECT * FROM user_profiles WHERE user_id = ?1")
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let json_list = |column: &str| -> Vec<String> {
+            row.try_get::<Option<String>, _>(column)
+                .ok()
+                .flatten()
+                .and_then(|value| serde_json::from_str(&value).ok())
+                .unwrap_or_default()
+        };
+        let fitness_level: String = row.try_get("fitness_level")?;
+
+        Ok(Some(UserFitnessProfile {
+            user_id: user_id.to_string(),
+            age: row.try_get::<Option<i64>, _>("age")?.map(|age| age as i32),
+            gender: row.try_get("gender")?,
+            weight: row.try_get("weight_kg")?,
+            height: row.try_get("height_cm")?,
+            fitness_level: FitnessLevel::parse(&fitness_level).unwrap_or(FitnessLevel::Beginner),
+            primary_sports: json_list("primary_sports"),
+            training_history_months: row.try_get::<Option<i64>, _>("training_history_months")?.unwrap_or(0) as i32,
+            preferences: UserPreferences {
+                preferred_units: row
+                    .try_get::<Option<String>, _>("preferred_units")?
+                    .unwrap_or_else(|| "metric".to_string()),
+                training_focus: json_list("training_focus"),
+                injury_history: json_list("injury_history"),
+                time_availability: TimeAvailability {
+                    hours_per_week: row.try_get::<Option<f64>, _>("hours_per_week")?.unwrap_or(0.0),
+                    preferred_days: json_list("preferred_days"),
+                    preferred_duration_minutes: row
+                        .try_get::<Option<i64>, _>("preferred_duration_minutes")?
+                        .map(|minutes| minutes as i32),
+                },
+            },
+        }))
+    }
+
+    /// Store a new goal after validating it
+    pub async fn create_goal(&self, goal: &Goal) -> Result<String> {
+        goal.validate()?;
 
         sqlx::query(
             r#"
             INSERT INTO goals (
-                id, user_id, title, description, goal_type, sport_type,
+                id, user_id, title, description, goal_type, sport_type, goal_params,
                 target_value, target_date, current_value, status, created_at, updated_at
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
             "#,
         )
-        .bind(&goal_id)
-        .bind(user_id.to_string())
-        .bind(goal_data.get("title").and_then(|v| v.as_str()).unwrap_or("Untitled Goal"))
-        .bind(goal_data.get("description").and_then(|v| v.as_str()).unwrap_or(""))
-        .bind(goal_data.get("goal_type").and_then(|v| v.as_str()).unwrap_or("custom"))
-        .bind(goal_data.get("sport_type").and_then(|v| v.as_str()))
-        .bind(goal_data.get("target_value").and_then(|v| v.as_f64()).unwrap_or(0.0))
-        .bind(goal_data.get("target_date").and_then(|v| v.as_str()).unwrap_or(&now))
-        .bind(goal_data.get("current_value").and_then(|v| v.as_f64()).unwrap_or(0.0))
-        .bind("active")
-        .bind(&now)
-        .bind(&now)
+        .bind(&goal.id)
+        .bind(&goal.user_id)
+        .bind(&goal.title)
+        .bind(&goal.description)
+        .bind(goal.goal_type.kind())
+        .bind(goal.goal_type.sport())
+        .bind(serde_json::to_string(&goal.goal_type)?)
+        .bind(goal.target_value)
+        .bind(goal.target_date.to_rfc3339())
+        .bind(goal.current_value)
+        .bind(goal.status.as_str())
+        .bind(goal.created_at.to_rfc3339())
+        .bind(goal.updated_at.to_rfc3339())
         .execute(&self.pool)
         .await?;
 
-        Ok(goal_id)
+        Ok(goal.id.clone())
+    }
+
+    /// Get a single goal owned by the user
+    pub async fn get_goal(&self, user_id: Uuid, goal_id: &str) -> Result<Option<Goal>> {
+        let row = sqlx::query("SELECT * FROM goals WHERE id = ?1 AND user_id = ?2")
+            .bind(goal_id)
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.as_ref().map(row_to_goal).transpose()
     }
 
-    /// Get user goals
-    pub async fn get_user_goals(&self, user_id: Uuid) -> Result<Vec<serde_json::Value>> {
+    /// Get user goals, newest first
+    pub async fn get_user_goals(&self, user_id: Uuid) -> Result<Vec<Goal>> {
         let rows = sqlx::query("SELECT * FROM goals WHERE user_id = ?1 ORDER BY created_at DESC")
             .bind(user_id.to_string())
             .fetch_all(&self.pool)
             .await?;
 
-        let mut goals = Vec::new();
-        for row in rows {
-            let mut goal = serde_json::Map::new();
-            
-            if let Ok(id) = row.try_get::<String, _>("id") {
-                goal.insert("id".to_string(), serde_json::Value::String(id));
-            }
-            if let Ok(title) = row.try_get::<String, _>("title") {
-                goal.insert("title".to_string(), serde_json::Value::String(title));
-            }
-            if let Ok(goal_type) = row.try_get::<String, _>("goal_type") {
-                goal.insert("goal_type".to_string(), serde_json::Value::String(goal_type));
-            }
-            if let Ok(target_value) = row.try_get::<f64, _>("target_value") {
-                goal.insert("target_value".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(target_value).unwrap_or_else(|| 0.into())));
-            }
-            if let Ok(current_value) = row.try_get::<f64, _>("current_value") {
-                goal.insert("current_value".to_string(), serde_json::Value::Number(serde_json::Number::from_f64(current_value).unwrap_or_else(|| 0.into())));
-            }
-            if let Ok(status) = row.try_get::<String, _>("status") {
-                goal.insert("status".to_string(), serde_json::Value::String(status));
-            }
+        rows.iter().map(row_to_goal).collect()
+    }
 
-            goals.push(serde_json::Value::Object(goal));
-        }
+    /// Replace the editable fields of a goal. Returns false if the goal does not exist.
+    pub async fn update_goal(&self, goal: &Goal) -> Result<bool> {
+        goal.validate_fields()?;
+
+        let result = sqlx::query(
+            r#"
+            UPDATE goals SET
+                title = ?1, description = ?2, goal_type = ?3, sport_type = ?4, goal_params = ?5,
+                target_value = ?6, target_date = ?7, current_value = ?8, status = ?9, updated_at = ?10
+            WHERE id = ?11 AND user_id = ?12
+            "#,
+        )
+        .bind(&goal.title)
+        .bind(&goal.description)
+        .bind(goal.goal_type.kind())
+        .bind(goal.goal_type.sport())
+        .bind(serde_json::to_string(&goal.goal_type)?)
+        .bind(goal.target_value)
+        .bind(goal.target_date.to_rfc3339())
+        .bind(goal.current_value)
+        .bind(goal.status.as_str())
+        .bind(goal.updated_at.to_rfc3339())
+        .bind(&goal.id)
+        .bind(&goal.user_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Change a goal's status. Returns false if the goal does not exist.
+    pub async fn update_goal_status(&self, user_id: Uuid, goal_id: &str, status: GoalStatus) -> Result<bool> {
+        let result = sqlx::query("UPDATE goals SET status = ?1, updated_at = ?2 WHERE id = ?3 AND user_id = ?4")
+            .bind(status.as_str())
+            .bind(Utc::now().to_rfc3339())
+            .bind(goal_id)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
 
-        Ok(goals)
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Delete a goal; its milestones are removed by the foreign key cascade.
+    /// Returns false if the goal does not exist.
+    pub async fn delete_goal(&self, user_id: Uuid, goal_id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM goals WHERE id = ?1 AND user_id = ?2")
+            .bind(goal_id)
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
     }
 
     /// Update goal progress
@@ -659,6 +1037,100 @@ impl Database {
         Ok(())
     }
 
+    /// Get a goal's milestones in target order
+    pub async fn get_goal_milestones(&self, goal_id: &str) -> Result<Vec<Milestone>> {
+        let rows = sqlx::query("SELECT * FROM goal_milestones WHERE goal_id = ?1 ORDER BY created_at, rowid")
+            .bind(goal_id)
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                Ok(Milestone {
+                    name: row.try_get("name")?,
+                    target_value: row.try_get("target_value")?,
+                    achieved: row.try_get::<Option<bool>, _>("achieved")?.unwrap_or(false),
+                    achieved_date: row
+                        .try_get::<Option<String>, _>("achieved_date")?
+                        .map(|date| Goal::parse_date(&date))
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
+    /// Store a goal's milestones, matched by name. A milestone keeps its first
+    /// achieved date for as long as it stays achieved at the same target.
+    /// Returns the milestones achieved by this call.
+    pub async fn save_goal_milestones(&self, goal_id: &str, milestones: &[Milestone]) -> Result<Vec<Milestone>> {
+        let now = Utc::now().to_rfc3339();
+        let mut newly_achieved = Vec::new();
+        let mut tx = self.pool.begin().await?;
+
+        for milestone in milestones {
+            let existing = sqlx::query(
+                "SELECT id, target_value, achieved, achieved_date FROM goal_milestones WHERE goal_id = ?1 AND name = ?2",
+            )
+            .bind(goal_id)
+            .bind(&milestone.name)
+            .fetch_optional(&mut *tx)
+            .await?;
+
+            let achieved_date = milestone.achieved_date.map(|date| date.to_rfc3339());
+
+            match existing {
+                Some(row) => {
+                    let id: String = row.try_get("id")?;
+                    let was_achieved = row.try_get::<Option<bool>, _>("achieved")?.unwrap_or(false)
+                        && row.try_get::<f64, _>("target_value")? == milestone.target_value;
+
+                    let achieved_date = match (was_achieved, milestone.achieved) {
+                        (true, true) => row.try_get::<Option<String>, _>("achieved_date")?,
+                        (false, true) => {
+                            newly_achieved.push(milestone.clone());
+                            achieved_date
+                        }
+                        (_, false) => None,
+                    };
+
+                    sqlx::query(
+                        "UPDATE goal_milestones SET target_value = ?1, achieved = ?2, achieved_date = ?3 WHERE id = ?4",
+                    )
+                    .bind(milestone.target_value)
+                    .bind(milestone.achieved)
+                    .bind(achieved_date)
+                    .bind(&id)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+                None => {
+                    if milestone.achieved {
+                        newly_achieved.push(milestone.clone());
+                    }
+
+                    sqlx::query(
+                        r#"
+                        INSERT INTO goal_milestones (id, goal_id, name, target_value, achieved, achieved_date, created_at)
+                        VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                        "#,
+                    )
+                    .bind(Uuid::new_v4().to_string())
+                    .bind(goal_id)
+                    .bind(&milestone.name)
+                    .bind(milestone.target_value)
+                    .bind(milestone.achieved)
+                    .bind(achieved_date)
+                    .bind(&now)
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        tx.commit().await?;
+        Ok(newly_achieved)
+    }
+
     /// Store analytics insight
     pub async fn store_insight(&self, user_id: Uuid, insight_data: serde_json::Value) -> Result<String> {
         let insight_id = uuid::Uuid::new_v4().to_string();
@@ -714,12 +1186,631 @@ impl Database {
             if let Ok(description) = row.try_get::<String, _>("description") {
                 insight.insert("description".to_string(), serde_json::Value::String(description));
             }
+            if let Ok(Some(metadata)) = row.try_get::<Option<String>, _>("metadata") {
+                if let Ok(metadata) = serde_json::from_str(&metadata) {
+                    insight.insert("metadata".to_string(), metadata);
+                }
+            }
+            if let Ok(created_at) = row.try_get::<String, _>("created_at") {
+                insight.insert("created_at".to_string(), serde_json::Value::String(created_at));
+            }
 
             insights.push(serde_json::Value::Object(insight));
         }
 
         Ok(insights)
     }
+
+    // === TRAINING PLAN METHODS ===
+
+    /// Store a generated training plan
+    pub async fn create_training_plan(&self, plan: &TrainingPlan) -> Result<String> {
+        sqlx::query(
+            r#"
+            INSERT INTO training_plans (id, user_id, name, sport_type, event_date, plan_data, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(&plan.id)
+        .bind(&plan.user_id)
+        .bind(&plan.event.name)
+        .bind(&plan.event.sport)
+        .bind(plan.event.event_date.to_string())
+        .bind(serde_json::to_string(plan)?)
+        .bind(plan.created_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(plan.id.clone())
+    }
+
+    /// Get one of the user's training plans
+    pub async fn get_training_plan(&self, user_id: Uuid, plan_id: &str) -> Result<Option<TrainingPlan>> {
+        let row = sqlx::query("SELECT plan_data FROM training_plans WHERE id = ?1 AND user_id = ?2")
+            .bind(plan_id)
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| Ok(serde_json::from_str(&row.try_get::<String, _>("plan_data")?)?))
+            .transpose()
+    }
+
+    /// Get all training plans for a user, newest first
+    pub async fn get_user_training_plans(&self, user_id: Uuid) -> Result<Vec<TrainingPlan>> {
+        let rows = sqlx::query("SELECT plan_data FROM training_plans WHERE user_id = ?1 ORDER BY created_at DESC")
+            .bind(user_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(&row.try_get::<String, _>("plan_data")?)?))
+            .collect()
+    }
+
+    // === CALENDAR FEED METHODS ===
+
+    /// Whether the user has a calendar feed token
+    pub async fn has_calendar_token(&self, user_id: Uuid) -> Result<bool> {
+        let row = sqlx::query("SELECT 1 FROM calendar_tokens WHERE user_id = ?1")
+            .bind(user_id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.is_some())
+    }
+
+    /// Store the hash of the user's new calendar feed token, invalidating any old feed URL
+    pub async fn set_calendar_token(&self, user_id: Uuid, token_hash: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO calendar_tokens (user_id, token_hash, created_at)
+            VALUES (?1, ?2, ?3)
+            ON CONFLICT(user_id) DO UPDATE SET token_hash = excluded.token_hash, created_at = excluded.created_at
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(token_hash)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Revoke the user's calendar feed token. Returns false if there was none.
+    pub async fn revoke_calendar_token(&self, user_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM calendar_tokens WHERE user_id = ?1")
+            .bind(user_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Look up the user a calendar feed token belongs to by the token's hash
+    pub async fn get_user_by_calendar_token_hash(&self, token_hash: &str) -> Result<Option<Uuid>> {
+        let row = sqlx::query("SELECT user_id FROM calendar_tokens WHERE token_hash = ?1")
+            .bind(token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| Ok(Uuid::parse_str(&row.try_get::<String, _>("user_id")?)?))
+            .transpose()
+    }
+
+    // === API KEY METHODS ===
+
+    /// Store a new API key with the hash of its full key
+    pub async fn create_api_key(&self, api_key: &ApiKey, key_hash: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO api_keys (id, user_id, name, key_prefix, key_hash, scopes, created_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            "#,
+        )
+        .bind(api_key.id.to_string())
+        .bind(api_key.user_id.to_string())
+        .bind(&api_key.name)
+        .bind(&api_key.key_prefix)
+        .bind(key_hash)
+        .bind(api_key.scopes.iter().map(ApiKeyScope::as_str).collect::<Vec<_>>().join(","))
+        .bind(api_key.created_at.to_rfc3339())
+        .bind(api_key.expires_at.map(|expires_at| expires_at.to_rfc3339()))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// All of a user's API keys, including revoked and expired ones, newest first
+    pub async fn get_api_keys(&self, user_id: Uuid) -> Result<Vec<ApiKey>> {
+        let rows = sqlx::query("SELECT * FROM api_keys WHERE user_id = ?1 ORDER BY created_at DESC")
+            .bind(user_id.to_string())
+            .fetch_all(&self.pool)
+            .await?;
+
+        rows.into_iter().map(|row| self.row_to_api_key(row)).collect()
+    }
+
+    /// Look up an API key by the hash of the full key
+    pub async fn get_api_key_by_hash(&self, key_hash: &str) -> Result<Option<ApiKey>> {
+        let row = sqlx::query("SELECT * FROM api_keys WHERE key_hash = ?1")
+            .bind(key_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| self.row_to_api_key(row)).transpose()
+    }
+
+    /// Record that an API key was just used
+    pub async fn touch_api_key(&self, id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE api_keys SET last_used_at = ?1 WHERE id = ?2")
+            .bind(Utc::now().to_rfc3339())
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Revoke one of a user's API keys. Returns false if the user has no such active key.
+    pub async fn revoke_api_key(&self, user_id: Uuid, id: Uuid) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE api_keys SET revoked_at = ?1 WHERE id = ?2 AND user_id = ?3 AND revoked_at IS NULL",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(id.to_string())
+        .bind(user_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    fn row_to_api_key(&self, row: sqlx::sqlite::SqliteRow) -> Result<ApiKey> {
+        let parse_time = |value: Option<String>| -> Result<Option<DateTime<Utc>>> {
+            value
+                .map(|value| Ok(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc)))
+                .transpose()
+        };
+        let id: String = row.try_get("id")?;
+        let user_id: String = row.try_get("user_id")?;
+        let scopes: String = row.try_get("scopes")?;
+        let created_at: String = row.try_get("created_at")?;
+
+        Ok(ApiKey {
+            id: Uuid::parse_str(&id)?,
+            user_id: Uuid::parse_str(&user_id)?,
+            name: row.try_get("name")?,
+            key_prefix: row.try_get("key_prefix")?,
+            scopes: scopes.split(',').filter_map(ApiKeyScope::parse).collect(),
+            created_at: DateTime::parse_from_rfc3339(&created_at)?.with_timezone(&Utc),
+            expires_at: parse_time(row.try_get("expires_at")?)?,
+            last_used_at: parse_time(row.try_get("last_used_at")?)?,
+            revoked_at: parse_time(row.try_get("revoked_at")?)?,
+        })
+    }
+
+    // === SESSION METHODS ===
+
+    /// Store a session, with the hash of its refresh token if one was issued.
+    /// Sessions that expired a day or more ago are dropped.
+    pub async fn create_session(&self, session: &AuthSession, refresh_token_hash: Option<&str>) -> Result<()> {
+        sqlx::query("DELETE FROM sessions WHERE expires_at <= ?1")
+            .bind((Utc::now() - chrono::Duration::days(1)).to_rfc3339())
+            .execute(&self.pool)
+            .await?;
+
+        self.insert_session(&self.pool, session, refresh_token_hash).await
+    }
+
+    async fn insert_session<'e, E>(&self, executor: E, session: &AuthSession, refresh_token_hash: Option<&str>) -> Result<()>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, user_id, family_id, refresh_token_hash, created_at, expires_at, revoked_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            "#,
+        )
+        .bind(session.id.to_string())
+        .bind(session.user_id.to_string())
+        .bind(session.family_id.to_string())
+        .bind(refresh_token_hash)
+        .bind(session.created_at.to_rfc3339())
+        .bind(session.expires_at.to_rfc3339())
+        .bind(session.revoked_at.map(|revoked_at| revoked_at.to_rfc3339()))
+        .execute(executor)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Look up a session by the jti of its JWT
+    pub async fn get_session(&self, id: Uuid) -> Result<Option<AuthSession>> {
+        let row = sqlx::query("SELECT * FROM sessions WHERE id = ?1")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| self.row_to_session(row)).transpose()
+    }
+
+    /// Look up a session by the hash of its refresh token
+    pub async fn get_session_by_refresh_hash(&self, refresh_token_hash: &str) -> Result<Option<AuthSession>> {
+        let row = sqlx::query("SELECT * FROM sessions WHERE refresh_token_hash = ?1")
+            .bind(refresh_token_hash)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        row.map(|row| self.row_to_session(row)).transpose()
+    }
+
+    /// Replace a session with its successor from a refresh. Returns false,
+    /// storing nothing, if the session was already revoked or replaced.
+    pub async fn rotate_session(&self, id: Uuid, next: &AuthSession, refresh_token_hash: &str) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+        let result = sqlx::query(
+            "UPDATE sessions SET revoked_at = ?1, replaced_by = ?2 WHERE id = ?3 AND revoked_at IS NULL",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(next.id.to_string())
+        .bind(id.to_string())
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        self.insert_session(&mut *tx, next, Some(refresh_token_hash)).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Revoke every session of a login. Returns how many were still active.
+    pub async fn revoke_session_family(&self, family_id: Uuid) -> Result<u64> {
+        let result = sqlx::query("UPDATE sessions SET revoked_at = ?1 WHERE family_id = ?2 AND revoked_at IS NULL")
+            .bind(Utc::now().to_rfc3339())
+            .bind(family_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Whether the JWT with this jti belongs to a revoked session. JWTs
+    /// without a stored session are not considered revoked.
+    pub async fn is_token_revoked(&self, jti: Uuid) -> Result<bool> {
+        Ok(self.get_session(jti).await?.is_some_and(|session| session.revoked_at.is_some()))
+    }
+
+    fn row_to_session(&self, row: sqlx::sqlite::SqliteRow) -> Result<AuthSession> {
+        let parse_time = |value: String| -> Result<DateTime<Utc>> {
+            Ok(DateTime::parse_from_rfc3339(&value)?.with_timezone(&Utc))
+        };
+        let id: String = row.try_get("id")?;
+        let user_id: String = row.try_get("user_id")?;
+        let family_id: String = row.try_get("family_id")?;
+        let revoked_at: Option<String> = row.try_get("revoked_at")?;
+        let replaced_by: Option<String> = row.try_get("replaced_by")?;
+
+        Ok(AuthSession {
+            id: Uuid::parse_str(&id)?,
+            user_id: Uuid::parse_str(&user_id)?,
+            family_id: Uuid::parse_str(&family_id)?,
+            created_at: parse_time(row.try_get("created_at")?)?,
+            expires_at: parse_time(row.try_get("expires_at")?)?,
+            revoked_at: revoked_at.map(parse_time).transpose()?,
+            replaced_by: replaced_by.map(|id| Uuid::parse_str(&id)).transpose()?,
+        })
+    }
+
+    // === ACTIVITY STORE METHODS ===
+
+    /// Insert or refresh activities synced from a provider. Returns the number of rows written.
+    pub async fn upsert_activities(&self, user_id: Uuid, activities: &[Activity]) -> Result<usize> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.pool.begin().await?;
+
+        for activity in activities {
+            sqlx::query(
+                r#"
+                INSERT OR REPLACE INTO activities (
+                    id, user_id, provider, name, sport_type, start_date, duration_seconds,
+                    distance_meters, elevation_gain, average_heart_rate, city, region,
+                    trail_name, data, synced_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)
+                "#,
+            )
+            .bind(&activity.id)
+            .bind(user_id.to_string())
+            .bind(&activity.provider)
+            .bind(&activity.name)
+            .bind(activity.sport_type.key())
+            .bind(activity_timestamp(&activity.start_date))
+            .bind(activity.duration_seconds as i64)
+            .bind(activity.distance_meters)
+            .bind(activity.elevation_gain)
+            .bind(activity.average_heart_rate.map(i64::from))
+            .bind(&activity.city)
+            .bind(&activity.region)
+            .bind(&activity.trail_name)
+            .bind(serde_json::to_string(activity)?)
+            .bind(&now)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(activities.len())
+    }
+
+    /// Store an activity together with its streams, laps and device info
+    pub async fn store_activity_details(&self, user_id: Uuid, details: &ActivityDetails) -> Result<()> {
+        self.upsert_activities(user_id, std::slice::from_ref(&details.activity)).await?;
+
+        let data = serde_json::json!({
+            "streams": details.streams,
+            "laps": details.laps,
+            "device": details.device,
+        });
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO activity_streams (activity_id, user_id, provider, data, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            "#,
+        )
+        .bind(&details.activity.id)
+        .bind(user_id.to_string())
+        .bind(&details.activity.provider)
+        .bind(data.to_string())
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Get a stored activity with its streams, laps and device info, if any were recorded
+    pub async fn get_activity_details(
+        &self,
+        user_id: Uuid,
+        provider: &str,
+        activity_id: &str,
+    ) -> Result<Option<ActivityDetails>> {
+        let row = sqlx::query(
+            r#"
+            SELECT a.data AS activity, s.data AS details
+            FROM activities a
+            JOIN activity_streams s
+              ON s.user_id = a.user_id AND s.provider = a.provider AND s.activity_id = a.id
+            WHERE a.user_id = ?1 AND a.provider = ?2 AND a.id = ?3
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(provider)
+        .bind(activity_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let mut details: serde_json::Value = serde_json::from_str(&row.try_get::<String, _>("details")?)?;
+        details["activity"] = serde_json::from_str(&row.try_get::<String, _>("activity")?)?;
+        Ok(Some(serde_json::from_value(details)?))
+    }
+
+    /// Query stored activities, optionally restricted to one provider
+    pub async fn query_activities(
+        &self,
+        user_id: Uuid,
+        provider: Option<&str>,
+        query: &ActivityQuery,
+    ) -> Result<Vec<Activity>> {
+        let mut builder = QueryBuilder::new("SELECT data FROM activities");
+        push_activity_filters(&mut builder, user_id, provider, query);
+
+        // Activities missing the sort field always go last
+        builder.push(format!(
+            " ORDER BY {column} IS NULL, {column} {order}, start_date DESC",
+            column = query.sort_by.column(),
+            order = query.sort_order.sql(),
+        ));
+        if query.limit.is_some() || query.offset.is_some() {
+            builder
+                .push(" LIMIT ")
+                .push_bind(query.limit.map_or(-1, |limit| limit as i64))
+                .push(" OFFSET ")
+                .push_bind(query.offset.unwrap_or(0) as i64);
+        }
+
+        let rows = builder.build().fetch_all(&self.pool).await?;
+        rows.iter()
+            .map(|row| Ok(serde_json::from_str(&row.try_get::<String, _>("data")?)?))
+            .collect()
+    }
+
+    /// Count stored activities matching a query's filters (paging is ignored)
+    pub async fn count_activities(
+        &self,
+        user_id: Uuid,
+        provider: Option<&str>,
+        query: &ActivityQuery,
+    ) -> Result<i64> {
+        let mut builder = QueryBuilder::new("SELECT COUNT(*) AS count FROM activities");
+        push_activity_filters(&mut builder, user_id, provider, query);
+
+        let row = builder.build().fetch_one(&self.pool).await?;
+        Ok(row.try_get("count")?)
+    }
+
+    /// Start time of the most recent stored activity for a provider
+    pub async fn get_latest_activity_date(&self, user_id: Uuid, provider: &str) -> Result<Option<DateTime<Utc>>> {
+        let latest: Option<String> = sqlx::query_scalar(
+            "SELECT MAX(start_date) FROM activities WHERE user_id = ?1 AND provider = ?2",
+        )
+        .bind(user_id.to_string())
+        .bind(provider)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(latest
+            .map(|date| DateTime::parse_from_rfc3339(&date).map(|d| d.with_timezone(&Utc)))
+            .transpose()?)
+    }
+
+    /// Provider offset that syncing older history resumes from, counted from the
+    /// newest activity. `None` once the provider's full history is stored.
+    pub async fn get_sync_backfill_offset(&self, user_id: Uuid, provider: &str) -> Result<Option<usize>> {
+        let row = sqlx::query("SELECT backfill_offset FROM activity_sync_state WHERE user_id = ?1 AND provider = ?2")
+            .bind(user_id.to_string())
+            .bind(provider)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(row.try_get::<Option<i64>, _>("backfill_offset")?.map(|offset| offset as usize)),
+            // Nothing recorded yet, so history is walked from the newest activity
+            None => Ok(Some(0)),
+        }
+    }
+
+    /// Record where syncing older history resumes from
+    pub async fn set_sync_backfill_offset(&self, user_id: Uuid, provider: &str, offset: Option<usize>) -> Result<()> {
+        sqlx::query(
+            "INSERT OR REPLACE INTO activity_sync_state (user_id, provider, backfill_offset, updated_at) VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(user_id.to_string())
+        .bind(provider)
+        .bind(offset.map(|offset| offset as i64))
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove all stored activities for a provider, e.g. after disconnecting it
+    pub async fn delete_provider_activities(&self, user_id: Uuid, provider: &str) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM activities WHERE user_id = ?1 AND provider = ?2")
+            .bind(user_id.to_string())
+            .bind(provider)
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("DELETE FROM activity_sync_state WHERE user_id = ?1 AND provider = ?2")
+            .bind(user_id.to_string())
+            .bind(provider)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Build a goal from a `goals` row, falling back to the kind and sport columns
+/// for rows stored before `goal_params` existed
+fn row_to_goal(row: &sqlx::sqlite::SqliteRow) -> Result<Goal> {
+    let kind: String = row.try_get("goal_type")?;
+    let sport = row.try_get::<Option<String>, _>("sport_type")?.unwrap_or_default();
+
+    let goal_type = match row.try_get::<Option<String>, _>("goal_params")? {
+        Some(params) => serde_json::from_str(&params)?,
+        None => match kind.as_str() {
+            "distance" => GoalType::Distance { sport, timeframe: TimeFrame::Month },
+            "time" => GoalType::Time { sport, distance: 0.0 },
+            "frequency" => GoalType::Frequency { sport, sessions_per_week: 0 },
+            "performance" => GoalType::Performance { metric: sport, improvement_percent: 0.0 },
+            _ => GoalType::Custom { metric: kind.clone(), unit: String::new() },
+        },
+    };
+
+    let status: String = row.try_get("status")?;
+
+    Ok(Goal {
+        id: row.try_get("id")?,
+        user_id: row.try_get("user_id")?,
+        title: row.try_get("title")?,
+        description: row.try_get::<Option<String>, _>("description")?.unwrap_or_default(),
+        goal_type,
+        target_value: row.try_get("target_value")?,
+        target_date: Goal::parse_date(&row.try_get::<String, _>("target_date")?)?,
+        current_value: row.try_get::<Option<f64>, _>("current_value")?.unwrap_or(0.0),
+        created_at: Goal::parse_date(&row.try_get::<String, _>("created_at")?)?,
+        updated_at: Goal::parse_date(&row.try_get::<String, _>("updated_at")?)?,
+        status: GoalStatus::parse(&status).unwrap_or(GoalStatus::Active),
+    })
+}
+
+/// Format a timestamp the way `activities.start_date` stores it, so string comparison orders correctly
+fn activity_timestamp(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Append the `WHERE` clause for an activity query
+fn push_activity_filters<'a>(
+    builder: &mut QueryBuilder<'a, Sqlite>,
+    user_id: Uuid,
+    provider: Option<&'a str>,
+    query: &'a ActivityQuery,
+) {
+    builder.push(" WHERE user_id = ").push_bind(user_id.to_string());
+
+    if let Some(provider) = provider {
+        builder.push(" AND provider = ").push_bind(provider);
+    }
+    if let Some(sport_type) = &query.sport_type {
+        builder.push(" AND sport_type = ").push_bind(sport_type.key());
+    }
+    if let Some(start) = &query.start_date {
+        builder.push(" AND start_date >= ").push_bind(activity_timestamp(start));
+    }
+    if let Some(end) = &query.end_date {
+        builder.push(" AND start_date <= ").push_bind(activity_timestamp(end));
+    }
+    if let Some(min) = query.min_distance_meters {
+        builder.push(" AND distance_meters >= ").push_bind(min);
+    }
+    if let Some(max) = query.max_distance_meters {
+        builder.push(" AND distance_meters <= ").push_bind(max);
+    }
+    if let Some(min) = query.min_duration_seconds {
+        builder.push(" AND duration_seconds >= ").push_bind(min as i64);
+    }
+    if let Some(max) = query.max_duration_seconds {
+        builder.push(" AND duration_seconds <= ").push_bind(max as i64);
+    }
+    if let Some(min) = query.min_elevation_gain {
+        builder.push(" AND elevation_gain >= ").push_bind(min);
+    }
+    if let Some(max) = query.max_elevation_gain {
+        builder.push(" AND elevation_gain <= ").push_bind(max);
+    }
+    for (column, value) in [("city", &query.city), ("region", &query.region), ("trail_name", &query.trail_name)] {
+        if let Some(value) = value {
+            builder
+                .push(format!(" AND LOWER({column}) LIKE "))
+                .push_bind(format!("%{}%", escape_like(&value.to_lowercase())))
+                .push(" ESCAPE '\\'");
+        }
+    }
+    match query.has_heart_rate {
+        Some(true) => {
+            builder.push(" AND average_heart_rate IS NOT NULL");
+        }
+        Some(false) => {
+            builder.push(" AND average_heart_rate IS NULL");
+        }
+        None => {}
+    }
+}
+
+/// Escape LIKE wildcards so user input only matches literally
+fn escape_like(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
 }
 
 /// Generate a random encryption key for token storage
@@ -732,6 +1823,7 @@ pub fn generate_encryption_key() -> [u8; 32] {
     key
 }
 
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -779,7 +1871,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_strava_token_storage() {
+    async fn test_provider_token_storage() {
         let db = create_test_db().await;
         
         let user = User::new(
@@ -792,8 +1884,9 @@ mod tests {
         let expires_at = Utc::now() + chrono::Duration::hours(6);
         
         // Store token
-        db.update_strava_token(
+        db.update_provider_token(
             user_id,
+            "strava",
             "access_token_123",
             "refresh_token_456",
             expires_at,
@@ -801,7 +1894,7 @@ mod tests {
         ).await.unwrap();
         
         // Retrieve token
-        let token = db.get_strava_token(user_id).await.unwrap().unwrap();
+        let token = db.get_provider_token(user_id, "strava").await.unwrap().unwrap();
         assert_eq!(token.access_token, "access_token_123");
         assert_eq!(token.refresh_token, "refresh_token_456");
         assert_eq!(token.scope, "read,activity:read_all");
@@ -809,35 +1902,153 @@ mod tests {
         // Check token expiry is close to what we set
         let diff = (token.expires_at - expires_at).num_seconds().abs();
         assert!(diff < 2); // Within 2 seconds
+        
+        // Other providers are unaffected
+        assert!(db.get_provider_token(user_id, "fitbit").await.unwrap().is_none());
     }
 
     #[tokio::test]
-    async fn test_fitbit_token_storage() {
+    async fn test_provider_connections() {
         let db = create_test_db().await;
         
         let user = User::new(
-            "fitbit@example.com".to_string(),
+            "connections@example.com".to_string(),
             "hashed_password".to_string(),
             None
         );
         let user_id = db.create_user(&user).await.unwrap();
         
         let expires_at = Utc::now() + chrono::Duration::hours(8);
+        db.update_provider_token(user_id, "fitbit", "fitbit_access", "fitbit_refresh", expires_at, "activity profile".to_string()).await.unwrap();
+        db.update_provider_token(user_id, "garmin", "garmin_access", "garmin_refresh", expires_at, "ACTIVITY_EXPORT".to_string()).await.unwrap();
+        db.update_provider_athlete_id(user_id, "garmin", "garmin-user-1").await.unwrap();
+        // An expired provider is connected but not available
+        db.update_provider_token(user_id, "strava", "old", "old", Utc::now() - chrono::Duration::hours(1), "read".to_string()).await.unwrap();
         
-        // Store token
-        db.update_fitbit_token(
+        // Reconnecting replaces the tokens but keeps the athlete id
+        db.update_provider_token(user_id, "garmin", "garmin_access_2", "garmin_refresh_2", expires_at, "ACTIVITY_EXPORT".to_string()).await.unwrap();
+        let token = db.get_provider_token(user_id, "garmin").await.unwrap().unwrap();
+        assert_eq!(token.access_token, "garmin_access_2");
+        
+        let user = db.get_user(user_id).await.unwrap().unwrap();
+        assert_eq!(user.provider_connections.len(), 3);
+        let garmin = user.provider_connections.iter().find(|c| c.provider == "garmin").unwrap();
+        assert_eq!(garmin.external_athlete_id.as_deref(), Some("garmin-user-1"));
+        assert_eq!(db.get_users_by_provider_athlete_id("garmin", "garmin-user-1").await.unwrap(), vec![user_id]);
+        assert!(db.get_users_by_provider_athlete_id("fitbit", "garmin-user-1").await.unwrap().is_empty());
+        let mut available = user.available_providers();
+        available.sort();
+        assert_eq!(available, vec!["fitbit".to_string(), "garmin".to_string()]);
+        assert!(!user.has_provider_access("strava"));
+        
+        assert!(db.delete_provider_connection(user_id, "fitbit").await.unwrap());
+        assert!(!db.delete_provider_connection(user_id, "fitbit").await.unwrap());
+        assert!(db.get_provider_token(user_id, "fitbit").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oauth_state_single_use() {
+        let db = create_test_db().await;
+        let user_id = Uuid::new_v4();
+
+        let oauth_state = OAuthState {
+            state: format!("{}:{}", user_id, Uuid::new_v4()),
             user_id,
-            "fitbit_access_789",
-            "fitbit_refresh_101112",
-            expires_at,
-            "activity heartrate profile".to_string(),
-        ).await.unwrap();
+            provider: "strava".to_string(),
+            code_verifier: "verifier".to_string(),
+            expires_at: Utc::now() + chrono::Duration::minutes(10),
+        };
+        db.store_oauth_state(&oauth_state).await.unwrap();
+
+        let consumed = db.consume_oauth_state(&oauth_state.state).await.unwrap().unwrap();
+        assert_eq!(consumed.user_id, user_id);
+        assert_eq!(consumed.provider, "strava");
+        assert_eq!(consumed.code_verifier, "verifier");
+
+        // Replaying the same state fails
+        assert!(db.consume_oauth_state(&oauth_state.state).await.unwrap().is_none());
+        assert!(db.consume_oauth_state("unknown").await.unwrap().is_none());
+
+        // Expired states are rejected
+        let expired = OAuthState {
+            state: format!("{}:{}", user_id, Uuid::new_v4()),
+            expires_at: Utc::now() - chrono::Duration::minutes(1),
+            ..oauth_state
+        };
+        db.store_oauth_state(&expired).await.unwrap();
+        assert!(db.consume_oauth_state(&expired.state).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_provider_columns_migration() {
+        let dir = tempfile::tempdir().unwrap();
+        let database_url = format!("sqlite:{}", dir.path().join("legacy.db").display());
+        let encryption_key = generate_encryption_key().to_vec();
+        let user_id = Uuid::new_v4();
+        let expires_at = Utc::now() + chrono::Duration::hours(6);
         
-        // Retrieve token
-        let token = db.get_fitbit_token(user_id).await.unwrap().unwrap();
-        assert_eq!(token.access_token, "fitbit_access_789");
-        assert_eq!(token.refresh_token, "fitbit_refresh_101112");
-        assert_eq!(token.scope, "activity heartrate profile");
+        // A users table from before provider_connections existed
+        {
+            let pool = SqlitePool::connect(&format!("{database_url}?mode=rwc")).await.unwrap();
+            sqlx::query(
+                r#"
+                CREATE TABLE users (
+                    id TEXT PRIMARY KEY,
+                    email TEXT UNIQUE NOT NULL,
+                    display_name TEXT,
+                    password_hash TEXT NOT NULL,
+                    strava_access_token TEXT,
+                    strava_refresh_token TEXT,
+                    strava_expires_at TEXT,
+                    strava_scope TEXT,
+                    strava_nonce TEXT,
+                    fitbit_access_token TEXT,
+                    fitbit_refresh_token TEXT,
+                    fitbit_expires_at TEXT,
+                    fitbit_scope TEXT,
+                    fitbit_nonce TEXT,
+                    created_at TEXT NOT NULL,
+                    last_active TEXT NOT NULL,
+                    is_active BOOLEAN NOT NULL DEFAULT 1
+                )
+                "#,
+            )
+            .execute(&pool)
+            .await
+            .unwrap();
+            
+            let token = EncryptedToken::new("legacy_access", "legacy_refresh", expires_at, "read".to_string(), &encryption_key).unwrap();
+            sqlx::query(
+                r#"
+                INSERT INTO users (id, email, password_hash, strava_access_token, strava_refresh_token,
+                                   strava_expires_at, strava_scope, strava_nonce, created_at, last_active)
+                VALUES (?1, 'legacy@example.com', 'hash', ?2, ?3, ?4, ?5, ?6, ?7, ?7)
+                "#,
+            )
+            .bind(user_id.to_string())
+            .bind(&token.access_token)
+            .bind(&token.refresh_token)
+            .bind(token.expires_at.to_rfc3339())
+            .bind(&token.scope)
+            .bind(&token.nonce)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&pool)
+            .await
+            .unwrap();
+            pool.close().await;
+        }
+        
+        let db = Database::new(&database_url, encryption_key).await.unwrap();
+        
+        let token = db.get_provider_token(user_id, "strava").await.unwrap().unwrap();
+        assert_eq!(token.access_token, "legacy_access");
+        assert_eq!(token.refresh_token, "legacy_refresh");
+        assert!(db.get_provider_token(user_id, "fitbit").await.unwrap().is_none());
+        assert!(!db.column_exists("users", "strava_access_token").await.unwrap());
+        assert!(!db.column_exists("users", "fitbit_nonce").await.unwrap());
+        
+        let user = db.get_user(user_id).await.unwrap().unwrap();
+        assert_eq!(user.available_providers(), vec!["strava".to_string()]);
     }
 
     #[tokio::test]
@@ -859,4 +2070,329 @@ mod tests {
         let updated_user = db.get_user(user_id).await.unwrap().unwrap();
         assert!(updated_user.last_active > initial_active);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_activity_store_query() {
+        use crate::activity_query::ActivitySortField;
+        use crate::models::SportType;
+
+        let db = create_test_db().await;
+        let user = User::new("store@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+
+        let base = Utc::now() - chrono::Duration::days(30);
+        let activities = vec![
+            Activity {
+                id: "run_short".to_string(),
+                start_date: base,
+                distance_meters: Some(5000.0),
+                provider: "strava".to_string(),
+                ..Activity::default()
+            },
+            Activity {
+                id: "run_long".to_string(),
+                start_date: base + chrono::Duration::days(2),
+                distance_meters: Some(21100.0),
+                average_heart_rate: None,
+                city: Some("Montréal".to_string()),
+                provider: "strava".to_string(),
+                ..Activity::default()
+            },
+            Activity {
+                id: "ride".to_string(),
+                sport_type: SportType::Ride,
+                start_date: base + chrono::Duration::days(4),
+                distance_meters: Some(40000.0),
+                provider: "strava".to_string(),
+                ..Activity::default()
+            },
+        ];
+        assert_eq!(db.upsert_activities(user_id, &activities).await.unwrap(), 3);
+        // Re-syncing the same activities must not create duplicates
+        db.upsert_activities(user_id, &activities).await.unwrap();
+
+        let all = db.query_activities(user_id, None, &ActivityQuery::default()).await.unwrap();
+        let ids: Vec<&str> = all.iter().map(|a| a.id.as_str()).collect();
+        assert_eq!(ids, vec!["ride", "run_long", "run_short"]);
+
+        let longest_run = ActivityQuery {
+            sport_type: Some(SportType::Run),
+            sort_by: ActivitySortField::Distance,
+            limit: Some(1),
+            ..ActivityQuery::default()
+        };
+        let result = db.query_activities(user_id, Some("strava"), &longest_run).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, "run_long");
+        assert_eq!(result[0].city.as_deref(), Some("Montréal"));
+        assert_eq!(db.count_activities(user_id, Some("strava"), &longest_run).await.unwrap(), 2);
+
+        let filtered = ActivityQuery {
+            start_date: Some(base + chrono::Duration::days(1)),
+            has_heart_rate: Some(true),
+            ..ActivityQuery::default()
+        };
+        let result = db.query_activities(user_id, None, &filtered).await.unwrap();
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].id, "ride");
+
+        // Place names match as plain substrings, wildcards included
+        let city = |value: &str| ActivityQuery {
+            city: Some(value.to_string()),
+            ..ActivityQuery::default()
+        };
+        assert_eq!(db.count_activities(user_id, None, &city("ntré")).await.unwrap(), 1);
+        assert_eq!(db.count_activities(user_id, None, &city("%")).await.unwrap(), 0);
+        assert_eq!(db.count_activities(user_id, None, &city("_ontréal")).await.unwrap(), 0);
+        assert_eq!(db.count_activities(user_id, None, &city("\\")).await.unwrap(), 0);
+
+        let latest = db.get_latest_activity_date(user_id, "strava").await.unwrap().unwrap();
+        assert_eq!(latest.timestamp(), (base + chrono::Duration::days(4)).timestamp());
+
+        assert_eq!(db.delete_provider_activities(user_id, "strava").await.unwrap(), 3);
+        assert!(db.get_latest_activity_date(user_id, "strava").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_goal_crud() {
+        let db = create_test_db().await;
+        let user = User::new("goals@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+
+        let args = serde_json::json!({
+            "title": "Run 100km",
+            "goal_type": "distance",
+            "sport_type": "run",
+            "timeframe": "month",
+            "target_value": 100.0,
+            "target_date": "2026-12-31"
+        });
+        let mut goal = Goal::from_args(&user_id.to_string(), &args).unwrap();
+        let goal_id = db.create_goal(&goal).await.unwrap();
+
+        let stored = db.get_goal(user_id, &goal_id).await.unwrap().unwrap();
+        assert_eq!(stored.goal_type, goal.goal_type);
+        assert_eq!(stored.target_date, goal.target_date);
+
+        goal.current_value = 40.0;
+        goal.title = "Run 120km".to_string();
+        assert!(db.update_goal(&goal).await.unwrap());
+        assert!(db.update_goal_status(user_id, &goal_id, GoalStatus::Paused).await.unwrap());
+
+        let goals = db.get_user_goals(user_id).await.unwrap();
+        assert_eq!(goals.len(), 1);
+        assert_eq!(goals[0].title, "Run 120km");
+        assert_eq!(goals[0].current_value, 40.0);
+        assert_eq!(goals[0].status, GoalStatus::Paused);
+
+        // Another user's ID never matches
+        assert!(db.get_goal(Uuid::new_v4(), &goal_id).await.unwrap().is_none());
+
+        let invalid = Goal { target_value: 0.0, ..goal.clone() };
+        assert!(db.update_goal(&invalid).await.is_err());
+
+        assert!(db.delete_goal(user_id, &goal_id).await.unwrap());
+        assert!(!db.delete_goal(user_id, &goal_id).await.unwrap());
+        assert!(db.get_user_goals(user_id).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_legacy_goal_without_params_can_be_updated() {
+        let db = create_test_db().await;
+        let user = User::new("legacy@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+
+        // Stored before goal_params existed
+        let now = Utc::now().to_rfc3339();
+        sqlx::query(
+            r#"
+            INSERT INTO goals (id, user_id, title, goal_type, sport_type, target_value, target_date, created_at, updated_at)
+            VALUES ('legacy', ?1, 'Run 3 times a week', 'frequency', 'run', 3.0, '2026-12-31', ?2, ?2)
+            "#,
+        )
+        .bind(user_id.to_string())
+        .bind(&now)
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let mut goal = db.get_goal(user_id, "legacy").await.unwrap().unwrap();
+        assert_eq!(goal.goal_type, GoalType::Frequency { sport: "run".to_string(), sessions_per_week: 0 });
+
+        goal.apply_update(&serde_json::json!({ "title": "Run more", "status": "paused" })).unwrap();
+        assert!(db.update_goal(&goal).await.unwrap());
+        let stored = db.get_goal(user_id, "legacy").await.unwrap().unwrap();
+        assert_eq!(stored.title, "Run more");
+        assert_eq!(stored.status, GoalStatus::Paused);
+
+        // Replacing the parameters still checks them
+        assert!(goal
+            .apply_update(&serde_json::json!({ "goal_type": "frequency", "sport_type": "run" }))
+            .is_err());
+        goal.apply_update(&serde_json::json!({ "goal_type": "frequency", "sport_type": "run", "sessions_per_week": 3 }))
+            .unwrap();
+        assert!(db.update_goal(&goal).await.unwrap());
+        let stored = db.get_goal(user_id, "legacy").await.unwrap().unwrap();
+        assert_eq!(stored.goal_type, GoalType::Frequency { sport: "run".to_string(), sessions_per_week: 3 });
+    }
+
+    #[tokio::test]
+    async fn test_training_plan_storage() {
+        let db = create_test_db().await;
+        let user = User::new("plans@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+
+        db.upsert_user_profile(
+            user_id,
+            serde_json::json!({
+                "age": 35,
+                "fitness_level": "advanced",
+                "primary_sports": ["run"],
+                "hours_per_week": 6.0,
+                "preferred_days": ["Tuesday", "Thursday", "Sunday"],
+                "preferred_duration_minutes": 50
+            }),
+        )
+        .await
+        .unwrap();
+
+        let profile = db.get_user_fitness_profile(user_id).await.unwrap().unwrap();
+        assert!(matches!(profile.fitness_level, FitnessLevel::Advanced));
+        assert_eq!(profile.primary_sports, vec!["run".to_string()]);
+        assert_eq!(profile.preferences.time_availability.preferred_days.len(), 3);
+        assert_eq!(profile.preferences.time_availability.preferred_duration_minutes, Some(50));
+
+        assert_eq!(db.get_workout_thresholds(user_id).await.unwrap(), WorkoutThresholds::default());
+        db.upsert_user_profile(user_id, serde_json::json!({ "ftp_watts": 240.0, "threshold_pace_seconds_per_km": 265.0 }))
+            .await
+            .unwrap();
+        let thresholds = db.get_workout_thresholds(user_id).await.unwrap();
+        assert_eq!(thresholds.ftp_watts, Some(240.0));
+        assert_eq!(thresholds.threshold_pace_seconds_per_km, Some(265.0));
+
+        assert_eq!(db.get_user_timezone(user_id).await.unwrap(), None);
+        db.upsert_user_profile(user_id, serde_json::json!({ "timezone": "Europe/Paris" })).await.unwrap();
+        assert_eq!(db.get_user_timezone(user_id).await.unwrap().as_deref(), Some("Europe/Paris"));
+
+        // Partial upserts leave the fields they omit untouched
+        let thresholds = db.get_workout_thresholds(user_id).await.unwrap();
+        assert_eq!(thresholds.ftp_watts, Some(240.0));
+        assert_eq!(thresholds.threshold_pace_seconds_per_km, Some(265.0));
+        let stored = db.get_user_fitness_profile(user_id).await.unwrap().unwrap();
+        assert_eq!(stored.age, Some(35));
+        assert!(matches!(stored.fitness_level, FitnessLevel::Advanced));
+        assert_eq!(stored.primary_sports, vec!["run".to_string()]);
+        assert_eq!(stored.preferences.time_availability.preferred_days.len(), 3);
+        assert_eq!(stored.preferences.time_availability.preferred_duration_minutes, Some(50));
+
+        let event = crate::intelligence::TargetEvent::from_args(&serde_json::json!({
+            "sport_type": "run",
+            "distance_km": 42.2,
+            "event_date": (Utc::now() + chrono::Duration::weeks(16)).format("%Y-%m-%d").to_string()
+        }))
+        .unwrap();
+        let plan = TrainingPlan::generate(&user_id.to_string(), &profile, event, &[], Utc::now().date_naive()).unwrap();
+        let plan_id = db.create_training_plan(&plan).await.unwrap();
+
+        let stored = db.get_training_plan(user_id, &plan_id).await.unwrap().unwrap();
+        assert_eq!(stored.weeks.len(), plan.weeks.len());
+        assert_eq!(stored.event.event_date, plan.event.event_date);
+        assert!(db.get_training_plan(Uuid::new_v4(), &plan_id).await.unwrap().is_none());
+        assert_eq!(db.get_user_training_plans(user_id).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_activity_details_storage() {
+        let db = create_test_db().await;
+        let user = User::new("streams@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+
+        let details = ActivityDetails {
+            activity: Activity { id: "file_abc".to_string(), provider: "file".to_string(), ..Activity::default() },
+            streams: vec![crate::models::TrackPoint { heart_rate: Some(140), ..Default::default() }],
+            laps: vec![],
+            device: Some(crate::models::DeviceInfo { product: Some("Edge 530".to_string()), ..Default::default() }),
+        };
+        db.store_activity_details(user_id, &details).await.unwrap();
+
+        let stored = db.get_activity_details(user_id, "file", "file_abc").await.unwrap().unwrap();
+        assert_eq!(stored.activity.id, "file_abc");
+        assert_eq!(stored.streams, details.streams);
+        assert_eq!(stored.device, details.device);
+        assert_eq!(db.query_activities(user_id, Some("file"), &ActivityQuery::default()).await.unwrap().len(), 1);
+        assert!(db.get_activity_details(user_id, "strava", "file_abc").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_calendar_tokens() {
+        use crate::auth::{generate_calendar_token, hash_calendar_token};
+
+        let db = create_test_db().await;
+        let user = User::new("calendar@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+        assert!(!db.has_calendar_token(user_id).await.unwrap());
+
+        let token = generate_calendar_token();
+        assert_eq!(token.len(), 43);
+        db.set_calendar_token(user_id, &hash_calendar_token(&token)).await.unwrap();
+        assert!(db.has_calendar_token(user_id).await.unwrap());
+        assert_eq!(db.get_user_by_calendar_token_hash(&hash_calendar_token(&token)).await.unwrap(), Some(user_id));
+        // Only the hash is stored
+        assert!(db.get_user_by_calendar_token_hash(&token).await.unwrap().is_none());
+
+        let rotated = generate_calendar_token();
+        db.set_calendar_token(user_id, &hash_calendar_token(&rotated)).await.unwrap();
+        assert!(db.get_user_by_calendar_token_hash(&hash_calendar_token(&token)).await.unwrap().is_none());
+
+        assert!(db.revoke_calendar_token(user_id).await.unwrap());
+        assert!(!db.revoke_calendar_token(user_id).await.unwrap());
+        assert!(db.get_user_by_calendar_token_hash(&hash_calendar_token(&rotated)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_goal_milestones() {
+        let db = create_test_db().await;
+        let user = User::new("milestones@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = db.create_user(&user).await.unwrap();
+        let args = serde_json::json!({
+            "title": "Run 100km",
+            "goal_type": "distance",
+            "sport_type": "run",
+            "target_value": 100.0,
+            "target_date": "2026-12-31"
+        });
+        let goal = Goal::from_args(&user_id.to_string(), &args).unwrap();
+        let goal_id = db.create_goal(&goal).await.unwrap();
+        let goal_id = goal_id.as_str();
+        let milestone = |name: &str, target_value: f64, achieved: bool| Milestone {
+            name: name.to_string(),
+            target_value,
+            achieved_date: achieved.then(Utc::now),
+            achieved,
+        };
+
+        let first = db
+            .save_goal_milestones(goal_id, &[milestone("Halfway Point", 50.0, true), milestone("Goal Complete", 100.0, false)])
+            .await
+            .unwrap();
+        assert_eq!(first.len(), 1);
+        let achieved_date = db.get_goal_milestones(goal_id).await.unwrap()[0].achieved_date;
+        assert!(achieved_date.is_some());
+
+        // Re-saving keeps the first achieved date and reports nothing new
+        let again = db
+            .save_goal_milestones(goal_id, &[milestone("Halfway Point", 50.0, true), milestone("Goal Complete", 100.0, true)])
+            .await
+            .unwrap();
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].name, "Goal Complete");
+
+        let stored = db.get_goal_milestones(goal_id).await.unwrap();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(stored[0].achieved_date, achieved_date);
+        assert!(stored.iter().all(|m| m.achieved));
+
+        assert!(db.delete_goal(user_id, goal_id).await.unwrap());
+        assert!(db.get_goal_milestones(goal_id).await.unwrap().is_empty());
+    }
+}
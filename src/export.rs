@@ -0,0 +1,634 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Activity export as CSV summaries, GPX and TCX tracks, and Parquet tables
+//!
+//! CSV and Parquet hold one row per activity summary. GPX and TCX are written
+//! from recorded streams, so activities stored without them are left out and
+//! reported as skipped.
+
+use crate::activity_query::ActivityQuery;
+use crate::database::Database;
+use crate::models::{Activity, ActivityDetails, TrackPoint};
+use crate::workouts::WorkoutSport;
+use anyhow::Result;
+use arrow_array::{ArrayRef, Float64Array, RecordBatch, StringArray, TimestampMillisecondArray, UInt32Array, UInt64Array};
+use arrow_schema::{DataType, Field, Schema, TimeUnit};
+use chrono::{DateTime, SecondsFormat, Utc};
+use chrono_tz::Tz;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt::Write;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const CREATOR: &str = "Pierre MCP Server";
+
+/// File formats activities can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Gpx,
+    Tcx,
+    Parquet,
+}
+
+impl ExportFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().trim_start_matches('.').to_lowercase().as_str() {
+            "csv" => Some(Self::Csv),
+            "gpx" => Some(Self::Gpx),
+            "tcx" => Some(Self::Tcx),
+            "parquet" => Some(Self::Parquet),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Gpx => "gpx",
+            Self::Tcx => "tcx",
+            Self::Parquet => "parquet",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Gpx => "application/gpx+xml",
+            Self::Tcx => "application/vnd.garmin.tcx+xml",
+            Self::Parquet => "application/vnd.apache.parquet",
+        }
+    }
+
+    /// Whether the export is binary and has to be base64 encoded for transport
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::Parquet)
+    }
+
+    /// Whether the format is written from recorded streams rather than summaries
+    pub fn needs_streams(&self) -> bool {
+        matches!(self, Self::Gpx | Self::Tcx)
+    }
+
+    pub fn export(&self, activities: &[ActivityDetails]) -> Result<ActivityExport> {
+        let (included, skipped): (Vec<&ActivityDetails>, Vec<&ActivityDetails>) = activities
+            .iter()
+            .partition(|details| !self.needs_streams() || has_track(self, details));
+
+        let bytes = match self {
+            Self::Csv => to_csv(&included)?,
+            Self::Gpx => to_gpx(&included).into_bytes(),
+            Self::Tcx => to_tcx(&included).into_bytes(),
+            Self::Parquet => to_parquet(&included)?,
+        };
+
+        Ok(ActivityExport {
+            bytes,
+            exported: included.len(),
+            skipped: skipped.iter().map(|details| details.activity.id.clone()).collect(),
+        })
+    }
+}
+
+/// An export file and what went into it
+#[derive(Debug, Clone)]
+pub struct ActivityExport {
+    pub bytes: Vec<u8>,
+    pub exported: usize,
+    /// Ids of matching activities left out because they have no recorded streams
+    pub skipped: Vec<String>,
+}
+
+/// Build an export query from `query_activities` style arguments. Unlike a
+/// query, an export without a limit covers every matching activity.
+pub fn export_query(args: &Value, timezone: Tz) -> Result<ActivityQuery> {
+    let query = ActivityQuery::from_args(args, timezone)?;
+    Ok(ActivityQuery {
+        limit: args["limit"].as_u64().map(|n| n as usize),
+        ..query
+    })
+}
+
+/// Export a user's stored activities matching a query
+pub async fn export_user_activities(
+    database: &Database,
+    user_id: Uuid,
+    provider: Option<&str>,
+    query: &ActivityQuery,
+    format: ExportFormat,
+) -> Result<ActivityExport> {
+    let activities = database.query_activities(user_id, provider, query).await?;
+
+    let mut details = Vec::with_capacity(activities.len());
+    for activity in activities {
+        let stored = if format.needs_streams() {
+            database.get_activity_details(user_id, &activity.provider, &activity.id).await?
+        } else {
+            None
+        };
+        details.push(stored.unwrap_or(ActivityDetails {
+            activity,
+            streams: Vec::new(),
+            laps: Vec::new(),
+            device: None,
+        }));
+    }
+
+    format.export(&details)
+}
+
+/// GPX track points need a position; TCX also accepts time-only samples
+fn has_track(format: &ExportFormat, details: &ActivityDetails) -> bool {
+    match format {
+        ExportFormat::Gpx => details.streams.iter().any(has_position),
+        _ => !details.streams.is_empty(),
+    }
+}
+
+fn has_position(point: &TrackPoint) -> bool {
+    point.latitude.is_some() && point.longitude.is_some()
+}
+
+/// One CSV or Parquet row
+#[derive(Debug, Serialize)]
+struct SummaryRow<'a> {
+    id: &'a str,
+    provider: &'a str,
+    name: &'a str,
+    sport_type: String,
+    start_date: String,
+    duration_seconds: u64,
+    distance_meters: Option<f64>,
+    elevation_gain: Option<f64>,
+    average_heart_rate: Option<u32>,
+    max_heart_rate: Option<u32>,
+    average_speed: Option<f64>,
+    max_speed: Option<f64>,
+    calories: Option<u32>,
+    start_latitude: Option<f64>,
+    start_longitude: Option<f64>,
+    city: Option<&'a str>,
+    region: Option<&'a str>,
+    country: Option<&'a str>,
+    trail_name: Option<&'a str>,
+}
+
+impl<'a> From<&'a Activity> for SummaryRow<'a> {
+    fn from(activity: &'a Activity) -> Self {
+        Self {
+            id: &activity.id,
+            provider: &activity.provider,
+            name: &activity.name,
+            sport_type: activity.sport_type.key(),
+            start_date: timestamp(&activity.start_date),
+            duration_seconds: activity.duration_seconds,
+            distance_meters: activity.distance_meters,
+            elevation_gain: activity.elevation_gain,
+            average_heart_rate: activity.average_heart_rate,
+            max_heart_rate: activity.max_heart_rate,
+            average_speed: activity.average_speed,
+            max_speed: activity.max_speed,
+            calories: activity.calories,
+            start_latitude: activity.start_latitude,
+            start_longitude: activity.start_longitude,
+            city: activity.city.as_deref(),
+            region: activity.region.as_deref(),
+            country: activity.country.as_deref(),
+            trail_name: activity.trail_name.as_deref(),
+        }
+    }
+}
+
+fn to_csv(activities: &[&ActivityDetails]) -> Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    if activities.is_empty() {
+        // Serialized rows write the header, so an empty export writes it explicitly
+        writer.write_record(SUMMARY_COLUMNS)?;
+    }
+    for details in activities {
+        writer.serialize(SummaryRow::from(&details.activity))?;
+    }
+    Ok(writer.into_inner().map_err(|e| e.into_error())?)
+}
+
+const SUMMARY_COLUMNS: [&str; 19] = [
+    "id",
+    "provider",
+    "name",
+    "sport_type",
+    "start_date",
+    "duration_seconds",
+    "distance_meters",
+    "elevation_gain",
+    "average_heart_rate",
+    "max_heart_rate",
+    "average_speed",
+    "max_speed",
+    "calories",
+    "start_latitude",
+    "start_longitude",
+    "city",
+    "region",
+    "country",
+    "trail_name",
+];
+
+/// The summary columns as a typed Parquet table, start dates as UTC timestamps
+fn to_parquet(activities: &[&ActivityDetails]) -> Result<Vec<u8>> {
+    let rows: Vec<&Activity> = activities.iter().map(|details| &details.activity).collect();
+    let strings = |value: fn(&Activity) -> Option<&str>| -> ArrayRef {
+        Arc::new(rows.iter().map(|a| value(a)).collect::<StringArray>())
+    };
+    let floats = |value: fn(&Activity) -> Option<f64>| -> ArrayRef {
+        Arc::new(rows.iter().map(|a| value(a)).collect::<Float64Array>())
+    };
+    let integers = |value: fn(&Activity) -> Option<u32>| -> ArrayRef {
+        Arc::new(rows.iter().map(|a| value(a)).collect::<UInt32Array>())
+    };
+    let sport_types: Vec<String> = rows.iter().map(|a| a.sport_type.key()).collect();
+
+    let utc = DataType::Timestamp(TimeUnit::Millisecond, Some("UTC".into()));
+    let text = |name: &str, nullable: bool| Field::new(name, DataType::Utf8, nullable);
+    let schema = Arc::new(Schema::new(vec![
+        text("id", false),
+        text("provider", false),
+        text("name", false),
+        text("sport_type", false),
+        Field::new("start_date", utc, false),
+        Field::new("duration_seconds", DataType::UInt64, false),
+        Field::new("distance_meters", DataType::Float64, true),
+        Field::new("elevation_gain", DataType::Float64, true),
+        Field::new("average_heart_rate", DataType::UInt32, true),
+        Field::new("max_heart_rate", DataType::UInt32, true),
+        Field::new("average_speed", DataType::Float64, true),
+        Field::new("max_speed", DataType::Float64, true),
+        Field::new("calories", DataType::UInt32, true),
+        Field::new("start_latitude", DataType::Float64, true),
+        Field::new("start_longitude", DataType::Float64, true),
+        text("city", true),
+        text("region", true),
+        text("country", true),
+        text("trail_name", true),
+    ]));
+
+    let columns: Vec<ArrayRef> = vec![
+        strings(|a| Some(a.id.as_str())),
+        strings(|a| Some(a.provider.as_str())),
+        strings(|a| Some(a.name.as_str())),
+        Arc::new(StringArray::from(sport_types)),
+        Arc::new(
+            TimestampMillisecondArray::from(rows.iter().map(|a| a.start_date.timestamp_millis()).collect::<Vec<_>>())
+                .with_timezone("UTC"),
+        ),
+        Arc::new(UInt64Array::from(rows.iter().map(|a| a.duration_seconds).collect::<Vec<_>>())),
+        floats(|a| a.distance_meters),
+        floats(|a| a.elevation_gain),
+        integers(|a| a.average_heart_rate),
+        integers(|a| a.max_heart_rate),
+        floats(|a| a.average_speed),
+        floats(|a| a.max_speed),
+        integers(|a| a.calories),
+        floats(|a| a.start_latitude),
+        floats(|a| a.start_longitude),
+        strings(|a| a.city.as_deref()),
+        strings(|a| a.region.as_deref()),
+        strings(|a| a.country.as_deref()),
+        strings(|a| a.trail_name.as_deref()),
+    ];
+    let batch = RecordBatch::try_new(schema.clone(), columns)?;
+
+    let properties = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut bytes = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut bytes, schema, Some(properties))?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(bytes)
+}
+
+/// One GPX track per activity, with Garmin extensions for heart rate and cadence
+fn to_gpx(activities: &[&ActivityDetails]) -> String {
+    let mut gpx = String::new();
+    gpx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        gpx,
+        "<gpx version=\"1.1\" creator=\"{}\" xmlns=\"http://www.topografix.com/GPX/1/1\" \
+         xmlns:gpxtpx=\"http://www.garmin.com/xmlschemas/TrackPointExtension/v1\">",
+        CREATOR
+    );
+    if let Some(first) = activities.iter().map(|details| details.activity.start_date).min() {
+        let _ = writeln!(gpx, "  <metadata><time>{}</time></metadata>", timestamp(&first));
+    }
+
+    for details in activities {
+        let activity = &details.activity;
+        gpx.push_str("  <trk>\n");
+        let _ = writeln!(gpx, "    <name>{}</name>", escape(&activity.name));
+        let _ = writeln!(gpx, "    <type>{}</type>", activity.sport_type.key());
+        gpx.push_str("    <trkseg>\n");
+        for point in details.streams.iter().filter(|point| has_position(point)) {
+            let (Some(lat), Some(lon)) = (point.latitude, point.longitude) else { continue };
+            let _ = write!(gpx, "      <trkpt lat=\"{:.7}\" lon=\"{:.7}\">", lat, lon);
+            if let Some(altitude) = point.altitude {
+                let _ = write!(gpx, "<ele>{:.1}</ele>", altitude);
+            }
+            let _ = write!(gpx, "<time>{}</time>", timestamp(&point.time));
+
+            let mut extension = String::new();
+            if let Some(hr) = point.heart_rate {
+                let _ = write!(extension, "<gpxtpx:hr>{}</gpxtpx:hr>", hr);
+            }
+            if let Some(cadence) = point.cadence {
+                let _ = write!(extension, "<gpxtpx:cad>{}</gpxtpx:cad>", cadence);
+            }
+            let power = point.power.map(|watts| format!("<power>{}</power>", watts)).unwrap_or_default();
+            if !extension.is_empty() || !power.is_empty() {
+                gpx.push_str("<extensions>");
+                if !extension.is_empty() {
+                    let _ = write!(gpx, "<gpxtpx:TrackPointExtension>{}</gpxtpx:TrackPointExtension>", extension);
+                }
+                gpx.push_str(&power);
+                gpx.push_str("</extensions>");
+            }
+            gpx.push_str("</trkpt>\n");
+        }
+        gpx.push_str("    </trkseg>\n  </trk>\n");
+    }
+
+    gpx.push_str("</gpx>\n");
+    gpx
+}
+
+/// One TCX activity per activity, keeping laps. Activities without laps are
+/// written as a single lap carrying the activity totals.
+fn to_tcx(activities: &[&ActivityDetails]) -> String {
+    let mut tcx = String::new();
+    tcx.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tcx.push_str(
+        "<TrainingCenterDatabase xmlns=\"http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2\" \
+         xmlns:ns3=\"http://www.garmin.com/xmlschemas/ActivityExtension/v2\">\n",
+    );
+    tcx.push_str("  <Activities>\n");
+
+    for details in activities {
+        let activity = &details.activity;
+        let sport = match WorkoutSport::from_sport(&activity.sport_type) {
+            Some(WorkoutSport::Run) => "Running",
+            Some(WorkoutSport::Bike) => "Biking",
+            None => "Other",
+        };
+        let _ = writeln!(tcx, "    <Activity Sport=\"{}\">", sport);
+        let _ = writeln!(tcx, "      <Id>{}</Id>", timestamp(&activity.start_date));
+
+        let laps: Vec<TcxLap> = if details.laps.is_empty() {
+            vec![TcxLap {
+                start: activity.start_date,
+                duration_seconds: activity.duration_seconds,
+                distance_meters: activity.distance_meters,
+                calories: activity.calories,
+                average_heart_rate: activity.average_heart_rate,
+                max_heart_rate: activity.max_heart_rate,
+            }]
+        } else {
+            details
+                .laps
+                .iter()
+                .map(|lap| TcxLap {
+                    start: lap.start_date,
+                    duration_seconds: lap.duration_seconds,
+                    distance_meters: lap.distance_meters,
+                    calories: lap.calories,
+                    average_heart_rate: lap.average_heart_rate,
+                    max_heart_rate: lap.max_heart_rate,
+                })
+                .collect()
+        };
+
+        for (index, lap) in laps.iter().enumerate() {
+            let end = laps.get(index + 1).map(|next| next.start);
+            // Samples before the first lap start still belong to the first lap
+            let points = details.streams.iter().filter(|point| {
+                (index == 0 || point.time >= lap.start) && end.is_none_or(|end| point.time < end)
+            });
+            write_tcx_lap(&mut tcx, lap, points);
+        }
+
+        let _ = writeln!(tcx, "      <Notes>{}</Notes>", escape(&activity.name));
+        if let Some(product) = details.device.as_ref().and_then(|device| device.product.as_deref()) {
+            let _ = writeln!(tcx, "      <Creator><Name>{}</Name></Creator>", escape(product));
+        }
+        tcx.push_str("    </Activity>\n");
+    }
+
+    tcx.push_str("  </Activities>\n</TrainingCenterDatabase>\n");
+    tcx
+}
+
+struct TcxLap {
+    start: DateTime<Utc>,
+    duration_seconds: u64,
+    distance_meters: Option<f64>,
+    calories: Option<u32>,
+    average_heart_rate: Option<u32>,
+    max_heart_rate: Option<u32>,
+}
+
+fn write_tcx_lap<'a>(tcx: &mut String, lap: &TcxLap, points: impl Iterator<Item = &'a TrackPoint>) {
+    let _ = writeln!(tcx, "      <Lap StartTime=\"{}\">", timestamp(&lap.start));
+    let _ = writeln!(tcx, "        <TotalTimeSeconds>{}</TotalTimeSeconds>", lap.duration_seconds);
+    let _ = writeln!(tcx, "        <DistanceMeters>{:.1}</DistanceMeters>", lap.distance_meters.unwrap_or(0.0));
+    let _ = writeln!(tcx, "        <Calories>{}</Calories>", lap.calories.unwrap_or(0));
+    if let Some(hr) = lap.average_heart_rate {
+        let _ = writeln!(tcx, "        <AverageHeartRateBpm><Value>{}</Value></AverageHeartRateBpm>", hr);
+    }
+    if let Some(hr) = lap.max_heart_rate {
+        let _ = writeln!(tcx, "        <MaximumHeartRateBpm><Value>{}</Value></MaximumHeartRateBpm>", hr);
+    }
+    tcx.push_str("        <Intensity>Active</Intensity>\n");
+    tcx.push_str("        <TriggerMethod>Manual</TriggerMethod>\n");
+
+    tcx.push_str("        <Track>\n");
+    for point in points {
+        let _ = write!(tcx, "          <Trackpoint><Time>{}</Time>", timestamp(&point.time));
+        if let (Some(lat), Some(lon)) = (point.latitude, point.longitude) {
+            let _ = write!(
+                tcx,
+                "<Position><LatitudeDegrees>{:.7}</LatitudeDegrees><LongitudeDegrees>{:.7}</LongitudeDegrees></Position>",
+                lat, lon
+            );
+        }
+        if let Some(altitude) = point.altitude {
+            let _ = write!(tcx, "<AltitudeMeters>{:.1}</AltitudeMeters>", altitude);
+        }
+        if let Some(distance) = point.distance_meters {
+            let _ = write!(tcx, "<DistanceMeters>{:.1}</DistanceMeters>", distance);
+        }
+        if let Some(hr) = point.heart_rate {
+            let _ = write!(tcx, "<HeartRateBpm><Value>{}</Value></HeartRateBpm>", hr);
+        }
+        if let Some(cadence) = point.cadence {
+            let _ = write!(tcx, "<Cadence>{}</Cadence>", cadence);
+        }
+        if point.speed.is_some() || point.power.is_some() {
+            tcx.push_str("<Extensions><ns3:TPX>");
+            if let Some(speed) = point.speed {
+                let _ = write!(tcx, "<ns3:Speed>{:.3}</ns3:Speed>", speed);
+            }
+            if let Some(watts) = point.power {
+                let _ = write!(tcx, "<ns3:Watts>{}</ns3:Watts>", watts);
+            }
+            tcx.push_str("</ns3:TPX></Extensions>");
+        }
+        tcx.push_str("</Trackpoint>\n");
+    }
+    tcx.push_str("        </Track>\n");
+    tcx.push_str("      </Lap>\n");
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Lap, SportType};
+    use crate::providers::file_import::parse_activity_file;
+    use chrono::TimeZone;
+
+    fn sample_details() -> ActivityDetails {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 7, 0, 0).unwrap();
+        let streams: Vec<TrackPoint> = (0..5)
+            .map(|i| TrackPoint {
+                time: start + chrono::Duration::seconds(i * 30),
+                latitude: Some(45.5 + i as f64 * 0.001),
+                longitude: Some(-73.58),
+                altitude: Some(20.0 + i as f64),
+                distance_meters: Some(i as f64 * 111.2),
+                heart_rate: Some(130 + i as u32),
+                cadence: Some(85),
+                power: None,
+                speed: Some(3.7),
+            })
+            .collect();
+        ActivityDetails {
+            activity: Activity {
+                id: "file_0001".to_string(),
+                name: "Canal <easy> run".to_string(),
+                sport_type: SportType::Run,
+                start_date: start,
+                duration_seconds: 120,
+                distance_meters: Some(444.8),
+                elevation_gain: Some(4.0),
+                average_heart_rate: Some(132),
+                max_heart_rate: Some(134),
+                average_speed: Some(3.7),
+                max_speed: None,
+                calories: Some(30),
+                start_latitude: Some(45.5),
+                start_longitude: Some(-73.58),
+                city: Some("Montreal".to_string()),
+                region: None,
+                country: None,
+                trail_name: None,
+                provider: "file".to_string(),
+            },
+            laps: vec![
+                Lap {
+                    start_date: start,
+                    duration_seconds: 60,
+                    distance_meters: Some(222.4),
+                    average_heart_rate: Some(131),
+                    max_heart_rate: Some(132),
+                    average_speed: None,
+                    calories: Some(15),
+                },
+                Lap {
+                    start_date: start + chrono::Duration::seconds(60),
+                    duration_seconds: 60,
+                    distance_meters: Some(222.4),
+                    average_heart_rate: Some(133),
+                    max_heart_rate: Some(134),
+                    average_speed: None,
+                    calories: Some(15),
+                },
+            ],
+            streams,
+            device: None,
+        }
+    }
+
+    #[test]
+    fn test_csv_export() {
+        let mut manual = sample_details();
+        manual.activity.id = "manual".to_string();
+        manual.streams.clear();
+
+        let export = ExportFormat::Csv.export(&[sample_details(), manual]).unwrap();
+        assert_eq!(export.exported, 2);
+        assert!(export.skipped.is_empty());
+
+        let csv = String::from_utf8(export.bytes).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), SUMMARY_COLUMNS.join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "file_0001,file,Canal <easy> run,run,2024-06-01T07:00:00Z,120,444.8,4.0,132,134,3.7,,30,45.5,-73.58,Montreal,,,"
+        );
+
+        let empty = ExportFormat::Csv.export(&[]).unwrap();
+        assert_eq!(String::from_utf8(empty.bytes).unwrap().trim_end(), SUMMARY_COLUMNS.join(","));
+    }
+
+    #[test]
+    fn test_track_exports_round_trip() {
+        let mut summary_only = sample_details();
+        summary_only.activity.id = "summary".to_string();
+        summary_only.streams.clear();
+        let activities = [sample_details(), summary_only];
+
+        let gpx = ExportFormat::Gpx.export(&activities).unwrap();
+        assert_eq!((gpx.exported, gpx.skipped.clone()), (1, vec!["summary".to_string()]));
+        let parsed = parse_activity_file("export.gpx", &gpx.bytes).unwrap();
+        assert_eq!(parsed.activity.name, "Canal <easy> run");
+        assert_eq!(parsed.activity.sport_type, SportType::Run);
+        assert_eq!(parsed.streams.len(), 5);
+        assert_eq!(parsed.streams[4].heart_rate, Some(134));
+
+        let tcx = ExportFormat::Tcx.export(&activities).unwrap();
+        assert_eq!(tcx.exported, 1);
+        let parsed = parse_activity_file("export.tcx", &tcx.bytes).unwrap();
+        assert_eq!(parsed.laps.len(), 2);
+        assert_eq!(parsed.activity.duration_seconds, 120);
+        assert_eq!(parsed.activity.calories, Some(30));
+        assert_eq!(parsed.streams.len(), 5);
+        assert_eq!(parsed.streams[1].distance_meters, Some(111.2));
+        assert_eq!(parsed.streams[0].speed, Some(3.7));
+    }
+
+    #[test]
+    fn test_parquet_export() {
+        let export = ExportFormat::Parquet.export(&[sample_details()]).unwrap();
+        assert_eq!(export.exported, 1);
+        assert_eq!(&export.bytes[..4], b"PAR1");
+        assert_eq!(&export.bytes[export.bytes.len() - 4..], b"PAR1");
+
+        assert_eq!(ExportFormat::parse(".Parquet"), Some(ExportFormat::Parquet));
+        assert_eq!(ExportFormat::parse("fit"), None);
+    }
+}
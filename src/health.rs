@@ -7,6 +7,7 @@
 //! Health check endpoints and monitoring utilities
 
 use crate::database::Database;
+use crate::providers::quota::STRAVA_QUOTA;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
@@ -286,7 +287,11 @@ impl HealthChecker {
             }
         }
 
-        let status = if healthy_apis == total_apis {
+        // Remaining Strava quota, known once a Strava response has reported it
+        let strava_quota = STRAVA_QUOTA.status(chrono::Utc::now());
+        let quota_constrained = strava_quota.as_ref().is_some_and(|quota| quota.constrained);
+
+        let status = if healthy_apis == total_apis && !quota_constrained {
             HealthStatus::Healthy
         } else if healthy_apis > 0 {
             HealthStatus::Degraded
@@ -294,7 +299,10 @@ impl HealthChecker {
             HealthStatus::Unhealthy
         };
 
-        let message = format!("{}/{} external APIs accessible", healthy_apis, total_apis);
+        let mut message = format!("{}/{} external APIs accessible", healthy_apis, total_apis);
+        if quota_constrained {
+            message.push_str("; Strava rate limit nearly reached");
+        }
 
         ComponentHealth {
             name: "external_apis".to_string(),
@@ -303,7 +311,8 @@ impl HealthChecker {
             duration_ms: start.elapsed().as_millis() as u64,
             metadata: Some(serde_json::json!({
                 "apis_checked": total_apis,
-                "apis_healthy": healthy_apis
+                "apis_healthy": healthy_apis,
+                "strava_quota": strava_quota
             })),
         }
     }
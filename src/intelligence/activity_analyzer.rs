@@ -1,10 +1,22 @@
 //! Activity analysis engine for detailed activity insights
 
 use super::*;
+use super::weather::WeatherService;
 use crate::models::{Activity, SportType};
 use anyhow::Result;
 use std::collections::HashMap;
 
+/// Relative distance window used to find comparable activities
+const DISTANCE_BAND: f64 = 0.15;
+/// Maximum distance between start points for two activities to share a route
+const ROUTE_RADIUS_METERS: f64 = 250.0;
+/// Z-score at which a metric change is considered significant
+const SIGNIFICANCE_Z_SCORE: f64 = 2.0;
+/// Relative change treated as significant when the baseline is too small for a z-score
+const SIGNIFICANCE_PERCENT: f64 = 5.0;
+/// Minimum baseline size before z-scores are reported
+const MIN_SAMPLES_FOR_Z_SCORE: usize = 3;
+
 /// Trait for analyzing individual activities
 #[async_trait::async_trait]
 pub trait ActivityAnalyzerTrait {
@@ -19,6 +31,16 @@ pub trait ActivityAnalyzerTrait {
     
     /// Compare activity against user's historical data
     async fn compare_to_history(&self, activity: &Activity, historical_activities: &[Activity]) -> Result<Vec<AdvancedInsight>>;
+
+    /// Compare activity against a baseline selected from history, with normalized metric deltas.
+    /// `weather` maps activity IDs to the conditions they were recorded in.
+    async fn compare_activity(
+        &self,
+        activity: &Activity,
+        historical_activities: &[Activity],
+        comparison_type: &ComparisonType,
+        weather: &HashMap<String, WeatherConditions>,
+    ) -> Result<ActivityComparison>;
 }
 
 /// Advanced activity analyzer implementation
@@ -84,7 +106,7 @@ impl AdvancedActivityAnalyzer {
             score += 0.5;
         }
         
-        score.min(10.0).max(0.0)
+        score.clamp(0.0, 10.0)
     }
 
     /// Generate insights for activity performance
@@ -166,6 +188,161 @@ impl AdvancedActivityAnalyzer {
         insights
     }
 
+    /// Check whether a candidate activity falls within the distance band of the target.
+    /// Activities without distance are compared on duration instead.
+    fn in_distance_band(target: &Activity, candidate: &Activity) -> bool {
+        match (target.distance_meters, candidate.distance_meters) {
+            (Some(target_distance), Some(candidate_distance)) if target_distance > 0.0 => {
+                ((candidate_distance - target_distance) / target_distance).abs() <= DISTANCE_BAND
+            }
+            (None, _) if target.duration_seconds > 0 => {
+                let target_duration = target.duration_seconds as f64;
+                ((candidate.duration_seconds as f64 - target_duration) / target_duration).abs() <= DISTANCE_BAND
+            }
+            _ => false,
+        }
+    }
+
+    /// Check whether two activities were done on the same route
+    fn same_route(a: &Activity, b: &Activity) -> bool {
+        if let (Some(trail_a), Some(trail_b)) = (&a.trail_name, &b.trail_name) {
+            return trail_a.eq_ignore_ascii_case(trail_b);
+        }
+
+        match (a.start_latitude, a.start_longitude, b.start_latitude, b.start_longitude) {
+            (Some(lat1), Some(lon1), Some(lat2), Some(lon2)) => {
+                haversine_distance_meters(lat1, lon1, lat2, lon2) <= ROUTE_RADIUS_METERS
+            }
+            _ => false,
+        }
+    }
+
+    /// Select the baseline activities for a comparison.
+    /// Returns the baseline and whether it was narrowed to the same route.
+    pub fn select_baseline<'a>(
+        activity: &Activity,
+        historical_activities: &'a [Activity],
+        comparison_type: &ComparisonType,
+    ) -> (Vec<&'a Activity>, bool) {
+        let same_sport = historical_activities
+            .iter()
+            .filter(|a| a.id != activity.id && a.sport_type == activity.sport_type);
+
+        match comparison_type {
+            ComparisonType::SimilarActivities => {
+                let similar: Vec<_> = same_sport
+                    .filter(|a| Self::in_distance_band(activity, a))
+                    .collect();
+                let same_route: Vec<_> = similar
+                    .iter()
+                    .copied()
+                    .filter(|a| Self::same_route(activity, a))
+                    .collect();
+
+                if same_route.is_empty() {
+                    (similar, false)
+                } else {
+                    (same_route, true)
+                }
+            }
+            ComparisonType::PersonalBest => {
+                let best = same_sport
+                    .filter(|a| Self::in_distance_band(activity, a))
+                    .filter(|a| a.average_speed.is_some_and(|speed| speed > 0.0))
+                    .max_by(|a, b| {
+                        a.average_speed
+                            .partial_cmp(&b.average_speed)
+                            .unwrap_or(std::cmp::Ordering::Equal)
+                    });
+
+                (best.into_iter().collect(), false)
+            }
+            ComparisonType::LastNAverage { n } => {
+                let mut previous: Vec<_> = same_sport
+                    .filter(|a| a.start_date < activity.start_date)
+                    .collect();
+                previous.sort_by_key(|a| std::cmp::Reverse(a.start_date));
+                previous.truncate(*n);
+
+                (previous, false)
+            }
+        }
+    }
+
+    /// Pace in seconds per kilometre
+    fn pace_seconds_per_km(activity: &Activity) -> Option<f64> {
+        activity
+            .average_speed
+            .filter(|speed| *speed > 0.0)
+            .map(|speed| 1000.0 / speed)
+    }
+
+    /// Efficiency factor (speed per heart beat, scaled per minute)
+    fn efficiency_factor(&self, activity: &Activity) -> Option<f64> {
+        let metrics = self.metrics_calculator.calculate_metrics(activity).ok()?;
+        metrics
+            .efficiency_factor
+            .or_else(|| metrics.aerobic_efficiency.map(|efficiency| efficiency * 60.0))
+    }
+
+    /// Heart beats per kilometre, scaled down when the weather made the effort harder
+    fn weather_adjusted_effort(
+        activity: &Activity,
+        weather: Option<&WeatherConditions>,
+        weather_service: &WeatherService,
+    ) -> Option<f64> {
+        let avg_hr = activity.average_heart_rate? as f64;
+        let pace_minutes = Self::pace_seconds_per_km(activity)? / 60.0;
+        let beats_per_km = avg_hr * pace_minutes;
+
+        let adjustment = weather
+            .map(|conditions| weather_service.analyze_weather_impact(conditions).performance_adjustment as f64)
+            .unwrap_or(0.0);
+
+        Some(beats_per_km * (1.0 + adjustment / 100.0))
+    }
+
+    /// Build a normalized delta between a current value and the baseline samples.
+    /// `lower_is_better` is `None` for metrics without a favourable direction.
+    fn metric_delta(current: Option<f64>, baseline: &[f64], lower_is_better: Option<bool>) -> Option<MetricDelta> {
+        let current = current?;
+        if baseline.is_empty() {
+            return None;
+        }
+
+        let mean = baseline.iter().sum::<f64>() / baseline.len() as f64;
+        let delta = current - mean;
+        let delta_percent = if mean.abs() > f64::EPSILON {
+            delta / mean * 100.0
+        } else {
+            0.0
+        };
+
+        let z_score = if baseline.len() >= MIN_SAMPLES_FOR_Z_SCORE {
+            let variance = baseline.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
+                / (baseline.len() - 1) as f64;
+            let std_dev = variance.sqrt();
+            (std_dev > f64::EPSILON).then(|| delta / std_dev)
+        } else {
+            None
+        };
+
+        let significant = match z_score {
+            Some(z) => z.abs() >= SIGNIFICANCE_Z_SCORE,
+            None => delta_percent.abs() >= SIGNIFICANCE_PERCENT,
+        };
+
+        Some(MetricDelta {
+            current,
+            baseline: mean,
+            delta,
+            delta_percent,
+            z_score,
+            improved: lower_is_better.map(|lower| if lower { delta < 0.0 } else { delta > 0.0 }),
+            significant,
+        })
+    }
+
     /// Generate training recommendations based on activity
     fn generate_recommendations(&self, activity: &Activity, metrics: &AdvancedMetrics) -> Vec<String> {
         let mut recommendations = Vec::new();
@@ -286,7 +463,7 @@ impl ActivityAnalyzerTrait for AdvancedActivityAnalyzer {
                     confidence: Confidence::Medium,
                     affected_metric: "max_speed".to_string(),
                     expected_value: Some(expected_max_speed),
-                    actual_value: Some(max_speed as f64),
+                    actual_value: Some(max_speed),
                 });
             }
         }
@@ -347,13 +524,13 @@ impl ActivityAnalyzerTrait for AdvancedActivityAnalyzer {
         
         // Compare average speed/pace
         if let Some(current_speed) = activity.average_speed {
-            let historical_speeds: Vec<f32> = same_sport_activities
+            let historical_speeds: Vec<f64> = same_sport_activities
                 .iter()
                 .filter_map(|a| a.average_speed)
                 .collect();
             
             if !historical_speeds.is_empty() {
-                let avg_historical_speed = historical_speeds.iter().sum::<f32>() / historical_speeds.len() as f32;
+                let avg_historical_speed = historical_speeds.iter().sum::<f64>() / historical_speeds.len() as f64;
                 let improvement = ((current_speed - avg_historical_speed) / avg_historical_speed) * 100.0;
                 
                 if improvement > 5.0 {
@@ -385,22 +562,19 @@ impl ActivityAnalyzerTrait for AdvancedActivityAnalyzer {
         }
         
         // Compare heart rate efficiency
-        if let (Some(current_hr), Some(current_speed)) = (activity.average_heartrate, activity.average_speed) {
-            let current_efficiency = current_speed / current_hr;
+        if let (Some(current_hr), Some(current_speed)) = (activity.average_heart_rate, activity.average_speed) {
+            let current_efficiency = current_speed / current_hr as f64;
             
-            let historical_efficiencies: Vec<f32> = same_sport_activities
+            let historical_efficiencies: Vec<f64> = same_sport_activities
                 .iter()
-                .filter_map(|a| {
-                    if let (Some(hr), Some(speed)) = (a.average_heartrate, a.average_speed) {
-                        Some(speed / hr)
-                    } else {
-                        None
-                    }
+                .filter_map(|a| match (a.average_heart_rate, a.average_speed) {
+                    (Some(hr), Some(speed)) if hr > 0 => Some(speed / hr as f64),
+                    _ => None,
                 })
                 .collect();
             
             if !historical_efficiencies.is_empty() {
-                let avg_efficiency = historical_efficiencies.iter().sum::<f32>() / historical_efficiencies.len() as f32;
+                let avg_efficiency = historical_efficiencies.iter().sum::<f64>() / historical_efficiencies.len() as f64;
                 let efficiency_change = ((current_efficiency - avg_efficiency) / avg_efficiency) * 100.0;
                 
                 if efficiency_change > 3.0 {
@@ -420,22 +594,130 @@ impl ActivityAnalyzerTrait for AdvancedActivityAnalyzer {
         
         Ok(insights)
     }
+
+    async fn compare_activity(
+        &self,
+        activity: &Activity,
+        historical_activities: &[Activity],
+        comparison_type: &ComparisonType,
+        weather: &HashMap<String, WeatherConditions>,
+    ) -> Result<ActivityComparison> {
+        let (baseline, route_matched) = Self::select_baseline(activity, historical_activities, comparison_type);
+        let weather_service = WeatherService::with_default_config();
+
+        let collect = |extract: &dyn Fn(&Activity) -> Option<f64>| -> Vec<f64> {
+            baseline.iter().filter_map(|a| extract(a)).collect()
+        };
+
+        let pace_baseline = collect(&Self::pace_seconds_per_km);
+        let hr_baseline = collect(&|a: &Activity| a.average_heart_rate.map(f64::from));
+        let ef_baseline = collect(&|a: &Activity| self.efficiency_factor(a));
+        let elevation_baseline = collect(&|a: &Activity| a.elevation_gain);
+        let effort_baseline = collect(&|a: &Activity| {
+            Self::weather_adjusted_effort(a, weather.get(&a.id), &weather_service)
+        });
+
+        let deltas = ComparisonDeltas {
+            pace: Self::metric_delta(Self::pace_seconds_per_km(activity), &pace_baseline, Some(true)),
+            heart_rate: Self::metric_delta(activity.average_heart_rate.map(f64::from), &hr_baseline, None),
+            efficiency_factor: Self::metric_delta(self.efficiency_factor(activity), &ef_baseline, Some(false)),
+            elevation: Self::metric_delta(activity.elevation_gain, &elevation_baseline, None),
+            weather_adjusted_effort: Self::metric_delta(
+                Self::weather_adjusted_effort(activity, weather.get(&activity.id), &weather_service),
+                &effort_baseline,
+                Some(true),
+            ),
+        };
+
+        let significant_metrics: Vec<&str> = [
+            ("pace", &deltas.pace),
+            ("heart_rate", &deltas.heart_rate),
+            ("efficiency_factor", &deltas.efficiency_factor),
+            ("elevation", &deltas.elevation),
+            ("weather_adjusted_effort", &deltas.weather_adjusted_effort),
+        ]
+        .into_iter()
+        .filter(|(_, delta)| delta.as_ref().is_some_and(|d| d.significant))
+        .map(|(name, _)| name)
+        .collect();
+
+        let baseline_activities: Vec<Activity> = baseline.iter().map(|a| (*a).clone()).collect();
+        let mut insights = self.compare_to_history(activity, &baseline_activities).await?;
+
+        if !significant_metrics.is_empty() {
+            let mut metadata = HashMap::new();
+            metadata.insert("metrics".to_string(), serde_json::json!(significant_metrics));
+            metadata.insert("baseline_size".to_string(), serde_json::Value::from(baseline.len()));
+
+            insights.push(AdvancedInsight {
+                insight_type: "significant_change".to_string(),
+                message: format!("Significant change versus baseline in: {}", significant_metrics.join(", ")),
+                confidence: if baseline.len() >= MIN_SAMPLES_FOR_Z_SCORE { Confidence::High } else { Confidence::Medium },
+                severity: InsightSeverity::Info,
+                metadata,
+            });
+        }
+
+        Ok(ActivityComparison {
+            activity_id: activity.id.clone(),
+            comparison_type: comparison_type.clone(),
+            baseline_activity_ids: baseline.iter().map(|a| a.id.clone()).collect(),
+            route_matched,
+            deltas,
+            significant: !significant_metrics.is_empty(),
+            insights,
+        })
+    }
+}
+
+impl Default for AdvancedActivityAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Great-circle distance between two coordinates in metres
+fn haversine_distance_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+    let d_lat = (lat2 - lat1).to_radians();
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2)
+        + lat1.to_radians().cos() * lat2.to_radians().cos() * (d_lon / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::{Duration, Utc};
+
+    fn create_run(id: &str, days_ago: i64, distance_m: f64, speed: f64, hr: u32) -> Activity {
+        Activity {
+            id: id.to_string(),
+            start_date: Utc::now() - Duration::days(days_ago),
+            distance_meters: Some(distance_m),
+            duration_seconds: (distance_m / speed) as u64,
+            average_speed: Some(speed),
+            average_heart_rate: Some(hr),
+            elevation_gain: Some(50.0),
+            ..Activity::default()
+        }
+    }
 
     #[tokio::test]
     async fn test_activity_analysis() {
         let analyzer = AdvancedActivityAnalyzer::new();
         
-        let mut activity = Activity::default();
-        activity.sport_type = "Run".to_string();
-        activity.distance = Some(5000.0); // 5km
-        activity.moving_time = Some(1800); // 30 minutes
-        activity.average_heartrate = Some(150.0);
-        activity.average_speed = Some(2.78); // ~6 min/km pace
+        let activity = Activity {
+            sport_type: SportType::Run,
+            distance_meters: Some(5000.0), // 5km
+            duration_seconds: 1800, // 30 minutes
+            average_heart_rate: Some(150),
+            average_speed: Some(2.78), // ~6 min/km pace
+            ..Activity::default()
+        };
         
         let result = analyzer.analyze_activity(&activity).await;
         assert!(result.is_ok());
@@ -449,11 +731,142 @@ mod tests {
     async fn test_anomaly_detection() {
         let analyzer = AdvancedActivityAnalyzer::new();
         
-        let mut activity = Activity::default();
-        activity.max_heartrate = Some(250.0); // Unrealistic HR
-        activity.max_watts = Some(3000.0); // Unrealistic power
+        let activity = Activity {
+            max_heart_rate: Some(250), // Unrealistic HR
+            max_speed: Some(20.0),     // Unrealistic running speed
+            ..Activity::default()
+        };
         
         let anomalies = analyzer.detect_anomalies(&activity).await.unwrap();
         assert_eq!(anomalies.len(), 2); // Should detect both anomalies
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_comparison_type_parse() {
+        assert_eq!(ComparisonType::parse("similar_activities", None), Some(ComparisonType::SimilarActivities));
+        assert_eq!(ComparisonType::parse("personal_best", None), Some(ComparisonType::PersonalBest));
+        assert_eq!(
+            ComparisonType::parse("last_n_average", Some(3)),
+            Some(ComparisonType::LastNAverage { n: 3 })
+        );
+        assert_eq!(
+            ComparisonType::parse("last_n_average", None),
+            Some(ComparisonType::LastNAverage { n: ComparisonType::DEFAULT_LAST_N })
+        );
+        assert_eq!(ComparisonType::parse("unknown", None), None);
+    }
+
+    #[tokio::test]
+    async fn test_compare_similar_activities_uses_distance_band() {
+        let analyzer = AdvancedActivityAnalyzer::new();
+        let current = create_run("current", 0, 10_000.0, 3.3, 150);
+        let history = vec![
+            create_run("similar1", 3, 10_500.0, 3.0, 152),
+            create_run("similar2", 7, 9_600.0, 3.1, 151),
+            create_run("too_long", 10, 21_000.0, 2.9, 148),
+            Activity { sport_type: SportType::Ride, ..create_run("ride", 2, 10_000.0, 7.0, 130) },
+        ];
+
+        let comparison = analyzer
+            .compare_activity(&current, &history, &ComparisonType::SimilarActivities, &HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.baseline_activity_ids, vec!["similar1", "similar2"]);
+        let pace = comparison.deltas.pace.unwrap();
+        assert!(pace.delta < 0.0);
+        assert_eq!(pace.improved, Some(true));
+        assert!(comparison.deltas.weather_adjusted_effort.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_compare_similar_activities_prefers_same_route() {
+        let analyzer = AdvancedActivityAnalyzer::new();
+        let mut current = create_run("current", 0, 10_000.0, 3.3, 150);
+        current.trail_name = Some("Lachine Canal".to_string());
+        let mut same_route = create_run("same_route", 5, 10_100.0, 3.2, 150);
+        same_route.trail_name = Some("lachine canal".to_string());
+        let other_route = create_run("other_route", 6, 10_000.0, 3.0, 150);
+
+        let comparison = analyzer
+            .compare_activity(&current, &[same_route, other_route], &ComparisonType::SimilarActivities, &HashMap::new())
+            .await
+            .unwrap();
+
+        assert!(comparison.route_matched);
+        assert_eq!(comparison.baseline_activity_ids, vec!["same_route"]);
+    }
+
+    #[tokio::test]
+    async fn test_compare_personal_best_picks_fastest() {
+        let analyzer = AdvancedActivityAnalyzer::new();
+        let current = create_run("current", 0, 5_000.0, 3.5, 160);
+        let history = vec![
+            create_run("slow", 10, 5_000.0, 3.0, 150),
+            create_run("best", 20, 5_100.0, 3.8, 170),
+        ];
+
+        let comparison = analyzer
+            .compare_activity(&current, &history, &ComparisonType::PersonalBest, &HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.baseline_activity_ids, vec!["best"]);
+        let pace = comparison.deltas.pace.unwrap();
+        assert_eq!(pace.improved, Some(false));
+        assert!(pace.z_score.is_none());
+        assert!(pace.significant);
+    }
+
+    #[tokio::test]
+    async fn test_compare_last_n_average_flags_significance() {
+        let analyzer = AdvancedActivityAnalyzer::new();
+        let current = create_run("current", 0, 8_000.0, 4.0, 150);
+        let history: Vec<Activity> = (1..=6)
+            .map(|i| create_run(&format!("run{}", i), i, 8_000.0, 3.0 + i as f64 * 0.01, 150))
+            .collect();
+
+        let comparison = analyzer
+            .compare_activity(&current, &history, &ComparisonType::LastNAverage { n: 4 }, &HashMap::new())
+            .await
+            .unwrap();
+
+        assert_eq!(comparison.baseline_activity_ids, vec!["run1", "run2", "run3", "run4"]);
+        assert!(comparison.significant);
+        assert!(comparison.deltas.pace.unwrap().z_score.unwrap() < -SIGNIFICANCE_Z_SCORE);
+        assert!(!comparison.deltas.heart_rate.unwrap().significant);
+    }
+
+    #[tokio::test]
+    async fn test_weather_adjusted_effort_discounts_hard_conditions() {
+        let analyzer = AdvancedActivityAnalyzer::new();
+        let current = create_run("current", 0, 10_000.0, 3.0, 150);
+        let history = vec![create_run("previous", 3, 10_000.0, 3.0, 150)];
+        let mut weather = HashMap::new();
+        weather.insert("current".to_string(), WeatherConditions {
+            temperature_celsius: 33.0,
+            humidity_percentage: Some(85.0),
+            wind_speed_kmh: Some(5.0),
+            conditions: "sunny".to_string(),
+        });
+
+        let comparison = analyzer
+            .compare_activity(&current, &history, &ComparisonType::SimilarActivities, &weather)
+            .await
+            .unwrap();
+
+        let effort = comparison.deltas.weather_adjusted_effort.unwrap();
+        assert!(effort.current < effort.baseline);
+        assert_eq!(comparison.deltas.pace.unwrap().delta, 0.0);
+    }
+
+    #[test]
+    fn test_haversine_distance() {
+        let distance = haversine_distance_meters(45.5017, -73.5673, 45.5017, -73.5673);
+        assert!(distance.abs() < f64::EPSILON);
+
+        // Roughly 111 km per degree of latitude
+        let distance = haversine_distance_meters(45.0, -73.0, 46.0, -73.0);
+        assert!((distance - 111_195.0).abs() < 500.0);
+    }
+}
@@ -242,14 +242,7 @@ impl ActivityAnalyzer {
     fn determine_time_of_day(&self, start_date: &DateTime<Utc>) -> TimeOfDay {
         // Convert UTC to local time for proper categorization
         let local_time = start_date.with_timezone(&Local);
-        match local_time.hour() {
-            5..=6 => TimeOfDay::EarlyMorning,   // 5-7 AM
-            7..=10 => TimeOfDay::Morning,       // 7-11 AM  
-            11..=13 => TimeOfDay::Midday,       // 11 AM - 2 PM
-            14..=17 => TimeOfDay::Afternoon,    // 2-6 PM
-            18..=20 => TimeOfDay::Evening,      // 6-9 PM
-            _ => TimeOfDay::Night,              // 9 PM - 5 AM
-        }
+        TimeOfDay::from_hour(local_time.hour())
     }
 
     /// Generate natural language summary
@@ -1,29 +1,46 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
 //! Goal tracking and progress monitoring engine
 
 use super::*;
-use crate::models::Activity;
+use crate::models::{Activity, SportType};
 use anyhow::Result;
 use chrono::{DateTime, Utc, Duration};
 use std::collections::HashMap;
-use uuid::Uuid;
+
+/// Days of history before a goal was created used as its performance baseline
+pub const PERFORMANCE_BASELINE_DAYS: i64 = 90;
+
+/// Milestones at 25%, 50%, 75% and 100% of the target
+const MILESTONES: [(f64, &str); 4] = [
+    (25.0, "First Quarter"),
+    (50.0, "Halfway Point"),
+    (75.0, "Three Quarters"),
+    (100.0, "Goal Complete"),
+];
 
 /// Trait for goal management and progress tracking
 #[async_trait::async_trait]
 pub trait GoalEngineTrait {
     /// Suggest goals based on user profile and activity history
     async fn suggest_goals(&self, user_profile: &UserFitnessProfile, activities: &[Activity]) -> Result<Vec<GoalSuggestion>>;
-    
+
     /// Track progress toward a specific goal
     async fn track_progress(&self, goal: &Goal, activities: &[Activity]) -> Result<ProgressReport>;
-    
+
     /// Adjust goal based on current progress and performance
     async fn adjust_goal(&self, goal: &Goal, progress: &ProgressReport) -> Result<Option<GoalAdjustment>>;
-    
+
     /// Create milestone structure for a goal
     async fn create_milestones(&self, goal: &Goal) -> Result<Vec<Milestone>>;
 }
 
 /// Advanced goal engine implementation
+#[derive(Default)]
 pub struct AdvancedGoalEngine {
     user_profile: Option<UserFitnessProfile>,
 }
@@ -43,80 +60,67 @@ impl AdvancedGoalEngine {
         }
     }
 
+    /// User profile the engine was created with, if any
+    pub fn user_profile(&self) -> Option<&UserFitnessProfile> {
+        self.user_profile.as_ref()
+    }
+
     /// Calculate goal difficulty based on user's current performance
-    fn calculate_goal_difficulty(&self, goal: &Goal, activities: &[Activity]) -> GoalDifficulty {
+    pub fn calculate_goal_difficulty(&self, goal: &Goal, activities: &[Activity]) -> GoalDifficulty {
         let similar_activities: Vec<_> = activities
             .iter()
-            .filter(|a| a.sport_type == goal.goal_type.sport_type())
+            .filter(|a| goal.goal_type.matches(a))
             .collect();
 
         if similar_activities.is_empty() {
             return GoalDifficulty::Unknown;
         }
 
-        let current_performance = match &goal.goal_type {
-            GoalType::Distance { .. } => {
-                let avg_distance = similar_activities
+        let (current_performance, target) = match &goal.goal_type {
+            GoalType::Distance { timeframe, .. } => {
+                // Typical volume over one time frame, from the recent average per day
+                let since = Utc::now() - Duration::days(PERFORMANCE_BASELINE_DAYS);
+                let recent_km = similar_activities
                     .iter()
-                    .filter_map(|a| a.distance)
-                    .sum::<f32>() / similar_activities.len() as f32;
-                avg_distance as f64
+                    .filter(|a| a.start_date >= since)
+                    .filter_map(|a| a.distance_meters)
+                    .sum::<f64>() / 1000.0;
+                (recent_km / PERFORMANCE_BASELINE_DAYS as f64 * timeframe.to_days() as f64, goal.target_value)
             },
             GoalType::Time { distance, .. } => {
-                let similar_distance_activities: Vec<_> = similar_activities
+                let similar_distance_minutes: Vec<f64> = similar_activities
                     .iter()
-                    .filter(|a| {
-                        if let Some(d) = a.distance {
-                            (d as f64 - distance).abs() < distance * 0.2 // Within 20%
-                        } else {
-                            false
-                        }
-                    })
+                    .filter(|a| is_near_distance(a, *distance, 0.2))
+                    .map(|a| a.duration_seconds as f64 / 60.0)
                     .collect();
-                
-                if similar_distance_activities.is_empty() {
+
+                if similar_distance_minutes.is_empty() {
                     return GoalDifficulty::Unknown;
                 }
-                
-                let avg_time = similar_distance_activities
-                    .iter()
-                    .filter_map(|a| a.moving_time)
-                    .sum::<i32>() / similar_distance_activities.len() as i32;
-                avg_time as f64
-            },
-            GoalType::Performance { .. } => {
-                // Use average speed as performance metric
-                let avg_speed = similar_activities
-                    .iter()
-                    .filter_map(|a| a.average_speed)
-                    .sum::<f32>() / similar_activities.len() as f32;
-                avg_speed as f64
+
+                // Faster is harder, so compare the ratio the other way round
+                let avg_minutes = similar_distance_minutes.iter().sum::<f64>() / similar_distance_minutes.len() as f64;
+                (goal.target_value, avg_minutes)
             },
+            GoalType::Performance { improvement_percent, .. } => (100.0, 100.0 + improvement_percent),
             GoalType::Frequency { sessions_per_week, .. } => {
                 // Calculate current weekly frequency
                 let weeks = 4;
-                let recent_count = similar_activities
-                    .iter()
-                    .filter(|a| {
-                        if let Some(start_time) = &a.start_date_local {
-                            if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                                let activity_utc = activity_date.with_timezone(&Utc);
-                                let weeks_ago = (Utc::now() - activity_utc).num_weeks();
-                                return weeks_ago <= weeks;
-                            }
-                        }
-                        false
-                    })
-                    .count();
-                (recent_count as f64) / (weeks as f64)
+                let since = Utc::now() - Duration::weeks(weeks);
+                let recent_count = similar_activities.iter().filter(|a| a.start_date >= since).count();
+                ((recent_count as f64) / (weeks as f64), *sessions_per_week as f64)
             },
             GoalType::Custom { .. } => {
                 return GoalDifficulty::Unknown;
             },
         };
 
-        let improvement_ratio = goal.target_value / current_performance;
-        
+        if current_performance <= 0.0 {
+            return GoalDifficulty::Ambitious;
+        }
+
+        let improvement_ratio = target / current_performance;
+
         if improvement_ratio < 1.1 {
             GoalDifficulty::Easy
         } else if improvement_ratio < 1.3 {
@@ -128,37 +132,96 @@ impl AdvancedGoalEngine {
         }
     }
 
+    /// Current value of a goal in its own unit, from activities within the goal's period
+    fn current_value(&self, goal: &Goal, activities: &[Activity], now: DateTime<Utc>) -> f64 {
+        let period_end = goal.target_date.min(now);
+        let relevant_activities: Vec<&Activity> = activities
+            .iter()
+            .filter(|a| goal.goal_type.matches(a) && a.start_date >= goal.created_at && a.start_date <= period_end)
+            .collect();
+
+        match &goal.goal_type {
+            GoalType::Distance { timeframe, .. } => {
+                let timeframe_start = match timeframe {
+                    TimeFrame::Custom { start, .. } => *start,
+                    _ => now - Duration::days(timeframe.to_days()),
+                };
+
+                relevant_activities
+                    .iter()
+                    .filter(|a| a.start_date >= timeframe_start)
+                    .filter_map(|a| a.distance_meters)
+                    .sum::<f64>() / 1000.0
+            },
+            GoalType::Time { distance, .. } => {
+                // Best time in minutes over the target distance
+                relevant_activities
+                    .iter()
+                    .filter(|a| is_near_distance(a, *distance, 0.05))
+                    .map(|a| a.duration_seconds as f64 / 60.0)
+                    .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+                    .unwrap_or(0.0)
+            },
+            GoalType::Frequency { .. } => relevant_activities.len() as f64,
+            GoalType::Performance { metric, .. } => {
+                // Metrics the engine cannot measure are tracked by hand
+                let Some(metric_value) = performance_metric(metric) else {
+                    return goal.current_value;
+                };
+
+                let baseline_start = goal.created_at - Duration::days(PERFORMANCE_BASELINE_DAYS);
+                let baseline: Vec<f64> = activities
+                    .iter()
+                    .filter(|a| a.start_date >= baseline_start && a.start_date < goal.created_at)
+                    .filter_map(metric_value)
+                    .collect();
+                let current: Vec<f64> = relevant_activities
+                    .iter()
+                    .filter_map(|a| metric_value(a))
+                    .collect();
+
+                match (mean(&baseline), mean(&current)) {
+                    (Some(baseline), Some(current)) if baseline > 0.0 => ((current / baseline - 1.0) * 100.0).max(0.0),
+                    _ => 0.0,
+                }
+            },
+            GoalType::Custom { .. } => goal.current_value,
+        }
+    }
+
     /// Generate progress insights based on current status
     fn generate_progress_insights(&self, goal: &Goal, progress: &ProgressReport) -> Vec<AdvancedInsight> {
         let mut insights = Vec::new();
-        
+
         // Progress rate insight
         let days_elapsed = (Utc::now() - goal.created_at).num_days() as f64;
         let days_total = (goal.target_date - goal.created_at).num_days() as f64;
-        let time_progress = days_elapsed / days_total;
-        
-        if progress.progress_percentage > time_progress * 100.0 + 10.0 {
-            insights.push(AdvancedInsight {
-                insight_type: "ahead_of_schedule".to_string(),
-                message: "You're ahead of schedule! Excellent progress.".to_string(),
-                confidence: Confidence::High,
-                severity: InsightSeverity::Info,
-                metadata: HashMap::new(),
-            });
-        } else if progress.progress_percentage < time_progress * 100.0 - 10.0 {
-            insights.push(AdvancedInsight {
-                insight_type: "behind_schedule".to_string(),
-                message: "Progress is behind schedule - consider adjusting training plan.".to_string(),
-                confidence: Confidence::High,
-                severity: InsightSeverity::Warning,
-                metadata: HashMap::new(),
-            });
+        if days_total > 0.0 {
+            let time_progress = days_elapsed / days_total;
+
+            if progress.progress_percentage > time_progress * 100.0 + 10.0 {
+                insights.push(AdvancedInsight {
+                    insight_type: "ahead_of_schedule".to_string(),
+                    message: "You're ahead of schedule! Excellent progress.".to_string(),
+                    confidence: Confidence::High,
+                    severity: InsightSeverity::Info,
+                    metadata: HashMap::new(),
+                });
+            } else if progress.progress_percentage < time_progress * 100.0 - 10.0 {
+                insights.push(AdvancedInsight {
+                    insight_type: "behind_schedule".to_string(),
+                    message: "Progress is behind schedule - consider adjusting training plan.".to_string(),
+                    confidence: Confidence::High,
+                    severity: InsightSeverity::Warning,
+                    metadata: HashMap::new(),
+                });
+            }
         }
-        
+
         // Milestone achievement insight
         let achieved_milestones = progress.milestones_achieved.iter().filter(|m| m.achieved).count();
         let total_milestones = progress.milestones_achieved.len();
-        
+
         if achieved_milestones > total_milestones / 2 {
             insights.push(AdvancedInsight {
                 insight_type: "milestone_progress".to_string(),
@@ -168,7 +231,7 @@ impl AdvancedGoalEngine {
                 metadata: HashMap::new(),
             });
         }
-        
+
         insights
     }
 }
@@ -177,40 +240,27 @@ impl AdvancedGoalEngine {
 impl GoalEngineTrait for AdvancedGoalEngine {
     async fn suggest_goals(&self, user_profile: &UserFitnessProfile, activities: &[Activity]) -> Result<Vec<GoalSuggestion>> {
         let mut suggestions = Vec::new();
-        
-        // Analyze current activity patterns
-        let recent_activities: Vec<_> = activities
-            .iter()
-            .filter(|a| {
-                if let Some(start_time) = &a.start_date_local {
-                    if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                        let activity_utc = activity_date.with_timezone(&Utc);
-                        let weeks_ago = (Utc::now() - activity_utc).num_weeks();
-                        return weeks_ago <= 8; // Last 8 weeks
-                    }
-                }
-                false
-            })
-            .collect();
+
+        // Analyze the last 8 weeks of activity
+        let since = Utc::now() - Duration::weeks(8);
+        let recent_activities: Vec<_> = activities.iter().filter(|a| a.start_date >= since).collect();
 
         // Group activities by sport
         let mut sport_stats: HashMap<String, SportStats> = HashMap::new();
-        
+
         for activity in &recent_activities {
-            let sport = &activity.sport_type;
-            let stats = sport_stats.entry(sport.clone()).or_insert(SportStats::new());
-            
+            let stats = sport_stats.entry(activity.sport_type.key()).or_insert(SportStats::new());
+
             stats.activity_count += 1;
-            if let Some(distance) = activity.distance {
-                stats.total_distance += distance as f64;
-                stats.max_distance = stats.max_distance.max(distance as f64);
-            }
-            if let Some(duration) = activity.moving_time {
-                stats.total_duration += duration as f64;
-                stats.max_duration = stats.max_duration.max(duration as f64);
+            if let Some(distance) = activity.distance_meters {
+                stats.total_distance += distance / 1000.0;
+                stats.max_distance = stats.max_distance.max(distance / 1000.0);
             }
+            let duration = activity.duration_seconds as f64 / 60.0;
+            stats.total_duration += duration;
+            stats.max_duration = stats.max_duration.max(duration);
             if let Some(speed) = activity.average_speed {
-                stats.speeds.push(speed as f64);
+                stats.speeds.push(speed);
             }
         }
 
@@ -220,38 +270,31 @@ impl GoalEngineTrait for AdvancedGoalEngine {
                 continue; // Need more data
             }
 
-            let avg_distance = stats.total_distance / stats.activity_count as f64;
-            let avg_speed = if !stats.speeds.is_empty() {
-                stats.speeds.iter().sum::<f64>() / stats.speeds.len() as f64
-            } else {
-                0.0
-            };
-
-            // Distance goal suggestions
-            if avg_distance > 0.0 {
-                let target_distance = stats.max_distance * 1.2; // 20% increase
+            // Distance goal suggestions: 10% more than the recent monthly volume
+            if stats.total_distance > 0.0 {
+                let monthly_distance = stats.total_distance / 2.0;
                 suggestions.push(GoalSuggestion {
                     goal_type: GoalType::Distance {
                         sport: sport.clone(),
                         timeframe: TimeFrame::Month,
                     },
-                    suggested_target: target_distance,
-                    rationale: format!("Based on your recent {} activities, you could challenge yourself with a longer distance", sport),
+                    suggested_target: (monthly_distance * 1.1).round(),
+                    rationale: format!("Based on your recent {} activities, you could build up your monthly distance", sport),
                     difficulty: GoalDifficulty::Moderate,
                     estimated_timeline_days: 30,
                     success_probability: 0.75,
                 });
             }
 
-            // Performance goal suggestions  
-            if avg_speed > 0.0 {
+            // Performance goal suggestions
+            if !stats.speeds.is_empty() {
                 let target_improvement = 5.0; // 5% improvement
                 suggestions.push(GoalSuggestion {
                     goal_type: GoalType::Performance {
                         metric: "speed".to_string(),
                         improvement_percent: target_improvement,
                     },
-                    suggested_target: avg_speed * (1.0 + target_improvement / 100.0),
+                    suggested_target: target_improvement,
                     rationale: format!("Improve your average {} pace by {}%", sport, target_improvement),
                     difficulty: GoalDifficulty::Challenging,
                     estimated_timeline_days: 60,
@@ -268,7 +311,7 @@ impl GoalEngineTrait for AdvancedGoalEngine {
                         sport: sport.clone(),
                         sessions_per_week: target_frequency,
                     },
-                    suggested_target: target_frequency as f64,
+                    suggested_target: (target_frequency * 4) as f64,
                     rationale: format!("Increase {} training consistency", sport),
                     difficulty: GoalDifficulty::Moderate,
                     estimated_timeline_days: 28,
@@ -317,106 +360,24 @@ impl GoalEngineTrait for AdvancedGoalEngine {
     }
 
     async fn track_progress(&self, goal: &Goal, activities: &[Activity]) -> Result<ProgressReport> {
-        // Filter relevant activities since goal creation
-        let relevant_activities: Vec<_> = activities
-            .iter()
-            .filter(|a| {
-                // Must be same sport type
-                if a.sport_type != goal.goal_type.sport_type() {
-                    return false;
-                }
-                
-                // Must be after goal creation
-                if let Some(start_time) = &a.start_date_local {
-                    if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                        let activity_utc = activity_date.with_timezone(&Utc);
-                        return activity_utc >= goal.created_at;
-                    }
-                }
-                false
-            })
-            .collect();
+        let now = Utc::now();
 
         // Calculate current progress based on goal type
-        let current_value = match &goal.goal_type {
-            GoalType::Distance { timeframe, .. } => {
-                let timeframe_start = match timeframe {
-                    TimeFrame::Week => Utc::now() - Duration::weeks(1),
-                    TimeFrame::Month => Utc::now() - Duration::days(30),
-                    TimeFrame::Quarter => Utc::now() - Duration::days(90),
-                    _ => goal.created_at,
-                };
-                
-                relevant_activities
-                    .iter()
-                    .filter(|a| {
-                        if let Some(start_time) = &a.start_date_local {
-                            if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                                let activity_utc = activity_date.with_timezone(&Utc);
-                                return activity_utc >= timeframe_start;
-                            }
-                        }
-                        false
-                    })
-                    .filter_map(|a| a.distance)
-                    .sum::<f32>() as f64
-            },
-            GoalType::Time { distance, .. } => {
-                // Find best time for target distance
-                relevant_activities
-                    .iter()
-                    .filter(|a| {
-                        if let Some(d) = a.distance {
-                            (d as f64 - distance).abs() < distance * 0.05 // Within 5%
-                        } else {
-                            false
-                        }
-                    })
-                    .filter_map(|a| a.moving_time)
-                    .min()
-                    .unwrap_or(i32::MAX) as f64
-            },
-            GoalType::Frequency { sessions_per_week, .. } => {
-                let weeks_elapsed = (Utc::now() - goal.created_at).num_weeks().max(1);
-                relevant_activities.len() as f64 / weeks_elapsed as f64
-            },
-            GoalType::Performance { metric, .. } => {
-                match metric.as_str() {
-                    "speed" => {
-                        if let Some(latest_activity) = relevant_activities.last() {
-                            latest_activity.average_speed.unwrap_or(0.0) as f64
-                        } else {
-                            0.0
-                        }
-                    },
-                    _ => 0.0,
-                }
-            },
-            GoalType::Custom { .. } => goal.current_value,
-        };
-
-        // Calculate progress percentage
-        let progress_percentage = if goal.target_value > 0.0 {
-            (current_value / goal.target_value * 100.0).min(100.0)
-        } else {
-            0.0
-        };
+        let current_value = self.current_value(goal, activities, now);
+        let progress_percentage = Goal { current_value, ..goal.clone() }.progress_percentage();
 
-        // Create milestones
-        let milestones = self.create_milestones(goal).await?;
-
-        // Check milestone achievements
-        let mut achieved_milestones = milestones.clone();
-        for milestone in &mut achieved_milestones {
-            if current_value >= milestone.target_value {
+        // Mark the milestones the current progress has passed
+        let mut achieved_milestones = self.create_milestones(goal).await?;
+        for (milestone, (percentage, _)) in achieved_milestones.iter_mut().zip(MILESTONES) {
+            if progress_percentage >= percentage {
                 milestone.achieved = true;
-                milestone.achieved_date = Some(Utc::now());
+                milestone.achieved_date = Some(now);
             }
         }
 
         // Estimate completion date
         let completion_date_estimate = if progress_percentage > 0.0 {
-            let days_elapsed = (Utc::now() - goal.created_at).num_days();
+            let days_elapsed = (now - goal.created_at).num_days();
             let estimated_total_days = (days_elapsed as f64 / progress_percentage * 100.0) as i64;
             Some(goal.created_at + Duration::days(estimated_total_days))
         } else {
@@ -424,7 +385,7 @@ impl GoalEngineTrait for AdvancedGoalEngine {
         };
 
         // Determine if on track
-        let days_elapsed = (Utc::now() - goal.created_at).num_days() as f64;
+        let days_elapsed = (now - goal.created_at).num_days() as f64;
         let days_total = (goal.target_date - goal.created_at).num_days() as f64;
         let expected_progress = if days_total > 0.0 {
             (days_elapsed / days_total) * 100.0
@@ -435,17 +396,18 @@ impl GoalEngineTrait for AdvancedGoalEngine {
 
         let progress_report = ProgressReport {
             goal_id: goal.id.clone(),
+            current_value,
             progress_percentage,
             completion_date_estimate,
             milestones_achieved: achieved_milestones,
             insights: vec![], // Will be filled next
-            recommendations: vec![], // Will be filled next  
+            recommendations: vec![], // Will be filled next
             on_track,
         };
 
         let mut final_report = progress_report;
         final_report.insights = self.generate_progress_insights(goal, &final_report);
-        
+
         // Generate recommendations
         final_report.recommendations = if on_track {
             vec![
@@ -466,6 +428,9 @@ impl GoalEngineTrait for AdvancedGoalEngine {
     async fn adjust_goal(&self, goal: &Goal, progress: &ProgressReport) -> Result<Option<GoalAdjustment>> {
         let days_elapsed = (Utc::now() - goal.created_at).num_days() as f64;
         let days_total = (goal.target_date - goal.created_at).num_days() as f64;
+        if days_total <= 0.0 {
+            return Ok(None);
+        }
         let time_progress = days_elapsed / days_total;
 
         // Only suggest adjustments if we're past 25% of the timeline
@@ -473,13 +438,19 @@ impl GoalEngineTrait for AdvancedGoalEngine {
             return Ok(None);
         }
 
+        // Time goals get more ambitious as the target time drops
+        let (harder, easier) = match goal.goal_type {
+            GoalType::Time { .. } => (0.95, 1.1),
+            _ => (1.2, 0.8),
+        };
+
         let progress_ratio = progress.progress_percentage / (time_progress * 100.0);
 
         let adjustment = if progress_ratio > 1.3 {
             // Significantly ahead - suggest more ambitious goal
             Some(GoalAdjustment {
                 adjustment_type: AdjustmentType::IncreaseTarget,
-                new_target_value: goal.target_value * 1.2,
+                new_target_value: goal.target_value * harder,
                 rationale: "You're making excellent progress! Consider a more ambitious target.".to_string(),
                 confidence: Confidence::Medium,
             })
@@ -489,7 +460,7 @@ impl GoalEngineTrait for AdvancedGoalEngine {
                 // Enough time left - reduce target
                 Some(GoalAdjustment {
                     adjustment_type: AdjustmentType::DecreaseTarget,
-                    new_target_value: goal.target_value * 0.8,
+                    new_target_value: goal.target_value * easier,
                     rationale: "Consider adjusting to a more achievable target based on current progress.".to_string(),
                     confidence: Confidence::High,
                 })
@@ -510,21 +481,21 @@ impl GoalEngineTrait for AdvancedGoalEngine {
     }
 
     async fn create_milestones(&self, goal: &Goal) -> Result<Vec<Milestone>> {
-        let mut milestones = Vec::new();
-        
-        // Create 4 milestones at 25%, 50%, 75%, and 100%
-        let percentages = [25.0, 50.0, 75.0, 100.0];
-        let names = ["First Quarter", "Halfway Point", "Three Quarters", "Goal Complete"];
-        
-        for (i, &percentage) in percentages.iter().enumerate() {
-            milestones.push(Milestone {
-                name: names[i].to_string(),
-                target_value: goal.target_value * (percentage / 100.0),
+        let milestones = MILESTONES
+            .iter()
+            .map(|&(percentage, name)| Milestone {
+                name: name.to_string(),
+                // Progress on time goals is target / best time, so a quarter of
+                // the way there is four times the target time
+                target_value: match goal.goal_type {
+                    GoalType::Time { .. } => goal.target_value / (percentage / 100.0),
+                    _ => goal.target_value * (percentage / 100.0),
+                },
                 achieved_date: None,
                 achieved: false,
-            });
-        }
-        
+            })
+            .collect();
+
         Ok(milestones)
     }
 }
@@ -560,7 +531,8 @@ pub struct GoalAdjustment {
 }
 
 /// Types of goal adjustments
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum AdjustmentType {
     IncreaseTarget,
     DecreaseTarget,
@@ -572,8 +544,10 @@ pub enum AdjustmentType {
 #[derive(Debug)]
 struct SportStats {
     activity_count: usize,
+    /// Kilometres
     total_distance: f64,
     max_distance: f64,
+    /// Minutes
     total_duration: f64,
     max_duration: f64,
     speeds: Vec<f64>,
@@ -593,22 +567,87 @@ impl SportStats {
 }
 
 impl GoalType {
-    /// Get the sport type for this goal
-    pub fn sport_type(&self) -> String {
-        match self {
-            GoalType::Distance { sport, .. } => sport.clone(),
-            GoalType::Time { sport, .. } => sport.clone(),
-            GoalType::Frequency { sport, .. } => sport.clone(),
-            GoalType::Performance { .. } => "Any".to_string(),
-            GoalType::Custom { .. } => "Any".to_string(),
+    /// Whether an activity counts toward this goal. Goals without a sport,
+    /// or with sport 'any', count every activity.
+    pub fn matches(&self, activity: &Activity) -> bool {
+        match self.sport() {
+            Some(sport) if !sport.is_empty() && !sport.eq_ignore_ascii_case("any") => {
+                SportType::from_name(sport) == activity.sport_type
+            }
+            _ => true,
         }
     }
 }
 
+/// Name and due date of each milestone before the target, assuming steady
+/// progress from the goal's creation to its target date
+pub fn milestone_schedule(goal: &Goal) -> Vec<(&'static str, DateTime<Utc>)> {
+    let span = goal.target_date - goal.created_at;
+    MILESTONES
+        .iter()
+        .filter(|(percentage, _)| *percentage < 100.0)
+        .map(|&(percentage, name)| {
+            let offset = Duration::seconds((span.num_seconds() as f64 * percentage / 100.0) as i64);
+            (name, goal.created_at + offset)
+        })
+        .collect()
+}
+
+/// Whether an activity's distance is within `tolerance` of `distance_km`
+fn is_near_distance(activity: &Activity, distance_km: f64, tolerance: f64) -> bool {
+    activity
+        .distance_meters
+        .is_some_and(|d| (d / 1000.0 - distance_km).abs() < distance_km * tolerance)
+}
+
+/// Reads a performance goal metric from an activity
+type MetricValue = fn(&Activity) -> Option<f64>;
+
+/// Accessor for a performance goal metric, if the engine can measure it
+fn performance_metric(metric: &str) -> Option<MetricValue> {
+    let value: MetricValue = match metric.to_lowercase().as_str() {
+        "speed" | "pace" | "average_speed" => |a| a.average_speed,
+        "distance" => |a| a.distance_meters,
+        "duration" | "time" => |a| Some(a.duration_seconds as f64),
+        "elevation" | "elevation_gain" => |a| a.elevation_gain,
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn mean(values: &[f64]) -> Option<f64> {
+    (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn goal(goal_type: GoalType, target_value: f64) -> Goal {
+        Goal {
+            id: "goal".to_string(),
+            user_id: "test_user".to_string(),
+            title: "Test goal".to_string(),
+            description: String::new(),
+            goal_type,
+            target_value,
+            target_date: Utc::now() + Duration::days(30),
+            current_value: 0.0,
+            created_at: Utc::now() - Duration::days(10),
+            updated_at: Utc::now(),
+            status: GoalStatus::Active,
+        }
+    }
+
+    fn run(days_ago: i64, distance_meters: f64, duration_seconds: u64) -> Activity {
+        Activity {
+            start_date: Utc::now() - Duration::days(days_ago),
+            distance_meters: Some(distance_meters),
+            duration_seconds,
+            ..Activity::default()
+        }
+    }
+
     #[tokio::test]
     async fn test_goal_suggestions() {
         let profile = UserFitnessProfile {
@@ -631,67 +670,75 @@ mod tests {
                 },
             },
         };
-        
+
         let engine = AdvancedGoalEngine::with_profile(profile.clone());
-        
-        // Create sample activities
-        let mut activities = Vec::new();
-        for i in 0..10 {
-            let mut activity = Activity::default();
-            activity.sport_type = "Run".to_string();
-            activity.distance = Some(5000.0); // 5km runs
-            activity.moving_time = Some(1800); // 30 minutes
-            activity.start_date_local = Some(
-                (Utc::now() - chrono::Duration::days(i * 3)).to_rfc3339()
-            );
-            activities.push(activity);
-        }
-        
-        let result = engine.suggest_goals(&profile, &activities).await;
-        assert!(result.is_ok());
-        
-        let suggestions = result.unwrap();
+
+        // 5km runs every three days
+        let activities: Vec<Activity> = (0..10).map(|i| run(i * 3, 5000.0, 1800)).collect();
+
+        let suggestions = engine.suggest_goals(&profile, &activities).await.unwrap();
         assert!(!suggestions.is_empty());
+        assert!(suggestions.iter().all(|s| s.goal_type.validate().is_ok()));
     }
 
     #[tokio::test]
     async fn test_progress_tracking() {
-        let goal = Goal {
-            id: Uuid::new_v4().to_string(),
-            user_id: "test_user".to_string(),
-            title: "Run 100km this month".to_string(),
-            description: "Monthly distance goal".to_string(),
-            goal_type: GoalType::Distance {
-                sport: "Run".to_string(),
+        let goal = goal(
+            GoalType::Distance {
+                sport: "run".to_string(),
                 timeframe: TimeFrame::Month,
             },
-            target_value: 100000.0, // 100km in meters
-            target_date: Utc::now() + Duration::days(30),
-            current_value: 0.0,
-            created_at: Utc::now() - Duration::days(10),
-            updated_at: Utc::now(),
-            status: GoalStatus::Active,
-        };
-        
+            100.0,
+        );
+
         let engine = AdvancedGoalEngine::new();
-        
-        // Create activities that add up to 30km
-        let mut activities = Vec::new();
-        for i in 0..6 {
-            let mut activity = Activity::default();
-            activity.sport_type = "Run".to_string();
-            activity.distance = Some(5000.0); // 5km each
-            activity.start_date_local = Some(
-                (Utc::now() - Duration::days(5 - i)).to_rfc3339()
-            );
-            activities.push(activity);
-        }
-        
-        let result = engine.track_progress(&goal, &activities).await;
-        assert!(result.is_ok());
-        
-        let progress = result.unwrap();
+
+        // Six 5km runs since the goal was set, one ride and one run from before it
+        let mut activities: Vec<Activity> = (0..6).map(|i| run(5 - i, 5000.0, 1800)).collect();
+        activities.push(Activity { sport_type: SportType::Ride, ..run(2, 40000.0, 3600) });
+        activities.push(run(20, 21000.0, 7200));
+
+        let progress = engine.track_progress(&goal, &activities).await.unwrap();
+        assert_eq!(progress.current_value, 30.0);
         assert_eq!(progress.progress_percentage, 30.0); // 30km out of 100km
-        assert_eq!(progress.milestones_achieved[0].achieved, true); // 25% milestone
+        assert!(progress.milestones_achieved[0].achieved); // 25% milestone
+        assert!(!progress.milestones_achieved[1].achieved);
+    }
+
+    #[tokio::test]
+    async fn test_time_and_frequency_progress() {
+        let engine = AdvancedGoalEngine::new();
+        let activities = vec![run(1, 10_050.0, 3000), run(3, 9_980.0, 3300), run(5, 5_000.0, 1200)];
+
+        // Sub-50 10k: best 10k is exactly 50 minutes
+        let time_goal = goal(GoalType::Time { sport: "run".to_string(), distance: 10.0 }, 50.0);
+        let progress = engine.track_progress(&time_goal, &activities).await.unwrap();
+        assert_eq!(progress.current_value, 50.0);
+        assert_eq!(progress.progress_percentage, 100.0);
+        assert!(progress.milestones_achieved.iter().all(|m| m.achieved));
+
+        let frequency_goal = goal(GoalType::Frequency { sport: "run".to_string(), sessions_per_week: 3 }, 12.0);
+        let progress = engine.track_progress(&frequency_goal, &activities).await.unwrap();
+        assert_eq!(progress.current_value, 3.0);
+        assert_eq!(progress.progress_percentage, 25.0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_adjust_goal() {
+        let engine = AdvancedGoalEngine::new();
+        let mut behind = goal(GoalType::Distance { sport: "run".to_string(), timeframe: TimeFrame::Year }, 1000.0);
+        behind.created_at = Utc::now() - Duration::days(60);
+        behind.target_date = Utc::now() + Duration::days(60);
+
+        let progress = engine.track_progress(&behind, &[run(1, 5000.0, 1800)]).await.unwrap();
+        let adjustment = engine.adjust_goal(&behind, &progress).await.unwrap().unwrap();
+        assert_eq!(adjustment.adjustment_type, AdjustmentType::DecreaseTarget);
+        assert_eq!(adjustment.new_target_value, 800.0);
+
+        // Too early in the goal's timeline to judge
+        let mut fresh = goal(GoalType::Distance { sport: "run".to_string(), timeframe: TimeFrame::Year }, 1000.0);
+        fresh.created_at = Utc::now() - Duration::days(2);
+        let progress = engine.track_progress(&fresh, &[]).await.unwrap();
+        assert!(engine.adjust_goal(&fresh, &progress).await.unwrap().is_none());
+    }
+}
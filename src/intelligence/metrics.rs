@@ -1,12 +1,12 @@
 //! Advanced fitness metrics calculation and analysis
 
-use crate::models::Activity;
+use crate::models::{Activity, ActivityDetails};
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Advanced metrics for activity analysis
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct AdvancedMetrics {
     /// Training impulse (TRIMP) score
     pub trimp: Option<f64>,
@@ -28,22 +28,6 @@ pub struct AdvancedMetrics {
     pub custom_metrics: HashMap<String, f64>,
 }
 
-impl Default for AdvancedMetrics {
-    fn default() -> Self {
-        Self {
-            trimp: None,
-            aerobic_efficiency: None,
-            power_to_weight_ratio: None,
-            training_stress_score: None,
-            intensity_factor: None,
-            variability_index: None,
-            efficiency_factor: None,
-            decoupling_percentage: None,
-            custom_metrics: HashMap::new(),
-        }
-    }
-}
-
 /// Metrics calculator for activities
 pub struct MetricsCalculator {
     /// User's functional threshold power (FTP)
@@ -115,6 +99,30 @@ impl MetricsCalculator {
         Ok(metrics)
     }
 
+    /// Calculate all available metrics, including power-based ones from the
+    /// recorded power samples
+    pub fn calculate_detailed_metrics(&self, details: &ActivityDetails) -> Result<AdvancedMetrics> {
+        let mut metrics = self.calculate_metrics(&details.activity)?;
+
+        let power_data: Vec<f32> = details.streams.iter().filter_map(|point| point.power).map(|watts| watts as f32).collect();
+        if power_data.is_empty() {
+            return Ok(metrics);
+        }
+        let avg_power = power_data.iter().sum::<f32>() / power_data.len() as f32;
+
+        if let Some(weight_kg) = self.weight_kg.filter(|weight| *weight > 0.0) {
+            metrics.power_to_weight_ratio = Some(avg_power as f64 / weight_kg);
+        }
+        if let Some(ftp) = self.ftp.filter(|ftp| *ftp > 0.0) {
+            let duration_hours = details.activity.duration_seconds as f64 / 3600.0;
+            metrics.intensity_factor = Some(avg_power as f64 / ftp);
+            metrics.training_stress_score = self.calculate_tss(avg_power, ftp, duration_hours);
+        }
+        metrics.variability_index = self.calculate_variability_index(&power_data);
+
+        Ok(metrics)
+    }
+
     /// Calculate Training Impulse (TRIMP)
     fn calculate_trimp(&self, avg_hr: f32, duration_seconds: i32) -> Option<f64> {
         let (max_hr, resting_hr) = match (self.max_hr, self.resting_hr) {
@@ -127,7 +135,7 @@ impl MetricsCalculator {
         let duration_minutes = duration_seconds as f64 / 60.0;
 
         // Simplified TRIMP calculation
-        Some(duration_minutes * hr_ratio * 0.64 * (2.718_f64.powf(1.92 * hr_ratio)))
+        Some(duration_minutes * hr_ratio * 0.64 * (1.92 * hr_ratio).exp())
     }
 
     /// Calculate Training Stress Score (TSS)
@@ -178,6 +186,12 @@ impl MetricsCalculator {
     }
 }
 
+impl Default for MetricsCalculator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 /// Zone-based analysis for heart rate or power
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ZoneAnalysis {
@@ -264,10 +278,15 @@ mod tests {
         let calculator = MetricsCalculator::new()
             .with_user_data(Some(250.0), None, None, None, Some(70.0));
 
-        let mut activity = Activity::default();
-        activity.average_watts = Some(200.0);
+        // Power comes from the recorded samples rather than the activity summary
+        let details = ActivityDetails {
+            activity: Activity::default(),
+            streams: vec![crate::models::TrackPoint { power: Some(200), ..Default::default() }; 10],
+            laps: vec![],
+            device: None,
+        };
 
-        let metrics = calculator.calculate_metrics(&activity).unwrap();
+        let metrics = calculator.calculate_detailed_metrics(&details).unwrap();
         assert_eq!(metrics.power_to_weight_ratio, Some(200.0 / 70.0));
     }
 
@@ -16,6 +16,7 @@
 //! - Training recommendations
 //! - Advanced metrics calculation
 
+use crate::constants::status::*;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -23,20 +24,28 @@ pub mod analyzer;
 pub mod insights;
 pub mod weather;
 pub mod location;
+pub mod activity_analyzer;
+pub mod metrics;
+pub mod pattern_detector;
+pub mod streaks;
+pub mod period_report;
+pub mod goal_engine;
+pub mod training_plan;
 // Temporarily disable complex analyzers during compilation fix
-// pub mod activity_analyzer;
 // pub mod performance_analyzer; 
-// pub mod goal_engine;
-// pub mod recommendation_engine;
-// pub mod metrics;
+pub mod recommendation_engine;
 
 pub use analyzer::ActivityAnalyzer;
 pub use insights::Insight;
-// pub use activity_analyzer::*;
+pub use activity_analyzer::*;
+pub use metrics::*;
+pub use pattern_detector::*;
+pub use streaks::*;
+pub use period_report::*;
+pub use goal_engine::*;
+pub use training_plan::*;
 // pub use performance_analyzer::*;
-// pub use goal_engine::*;
-// pub use recommendation_engine::*;
-// pub use metrics::*;
+pub use recommendation_engine::*;
 
 /// Activity intelligence summary with insights and analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,7 +154,7 @@ pub struct LocationContext {
 }
 
 /// Time of day categorization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum TimeOfDay {
     EarlyMorning, // 5-7 AM
@@ -156,6 +165,32 @@ pub enum TimeOfDay {
     Night,        // 9 PM - 5 AM
 }
 
+impl TimeOfDay {
+    /// Categorize a local hour of day (0-23)
+    pub fn from_hour(hour: u32) -> Self {
+        match hour {
+            5..=6 => Self::EarlyMorning,
+            7..=10 => Self::Morning,
+            11..=13 => Self::Midday,
+            14..=17 => Self::Afternoon,
+            18..=20 => Self::Evening,
+            _ => Self::Night,
+        }
+    }
+
+    /// Human-readable label
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::EarlyMorning => "early morning",
+            Self::Morning => "morning",
+            Self::Midday => "midday",
+            Self::Afternoon => "afternoon",
+            Self::Evening => "evening",
+            Self::Night => "night",
+        }
+    }
+}
+
 /// Weekly training load summary
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WeeklyLoad {
@@ -184,11 +219,9 @@ impl ActivityIntelligence {
 }
 
 // === ADVANCED ANALYTICS TYPES === 
-// Temporarily disabled during compilation fixes
 
-/*
 /// Time frame for analysis
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum TimeFrame {
     Week,
     Month,
@@ -199,6 +232,18 @@ pub enum TimeFrame {
 }
 
 impl TimeFrame {
+    /// Parse a named time frame ('week', 'month', 'quarter', 'sixmonths', 'year')
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "week" => Some(Self::Week),
+            "month" => Some(Self::Month),
+            "quarter" => Some(Self::Quarter),
+            "sixmonths" | "six_months" => Some(Self::SixMonths),
+            "year" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
     /// Get the duration in days
     pub fn to_days(&self) -> i64 {
         match self {
@@ -266,17 +311,16 @@ impl Confidence {
     }
 }
 
-// Temporarily disabled - will re-enable after fixing field mismatches
-// /// Enhanced activity insights with advanced analytics
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// pub struct ActivityInsights {
-//     pub activity_id: String,
-//     pub overall_score: f64,
-//     pub insights: Vec<AdvancedInsight>,
-//     pub metrics: AdvancedMetrics,
-//     pub recommendations: Vec<String>,
-//     pub anomalies: Vec<Anomaly>,
-// }
+/// Enhanced activity insights with advanced analytics
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityInsights {
+    pub activity_id: String,
+    pub overall_score: f64,
+    pub insights: Vec<AdvancedInsight>,
+    pub metrics: AdvancedMetrics,
+    pub recommendations: Vec<String>,
+    pub anomalies: Vec<Anomaly>,
+}
 
 /// Advanced insight with confidence and metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -308,26 +352,77 @@ pub struct Anomaly {
     pub actual_value: Option<f64>,
 }
 
-/// Performance trend analysis results
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TrendAnalysis {
-    pub timeframe: TimeFrame,
-    pub metric: String,
-    pub trend_direction: TrendDirection,
-    pub trend_strength: f64,
-    pub statistical_significance: f64,
-    pub data_points: Vec<TrendDataPoint>,
-    pub insights: Vec<AdvancedInsight>,
+/// Baseline used when comparing an activity against history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ComparisonType {
+    /// Same sport, similar distance band and, when known, the same route
+    SimilarActivities,
+    /// Fastest same-sport activity within the distance band
+    PersonalBest,
+    /// Average of the most recent `n` same-sport activities
+    LastNAverage { n: usize },
+}
+
+impl ComparisonType {
+    /// Default window for `last_n_average` comparisons
+    pub const DEFAULT_LAST_N: usize = 5;
+
+    /// Parse a comparison type from its tool argument name
+    pub fn parse(value: &str, n: Option<usize>) -> Option<Self> {
+        match value {
+            "similar_activities" | "similar" => Some(Self::SimilarActivities),
+            "personal_best" | "pb" => Some(Self::PersonalBest),
+            "last_n_average" | "average" | "recent" => Some(Self::LastNAverage {
+                n: n.unwrap_or(Self::DEFAULT_LAST_N).max(1),
+            }),
+            _ => None,
+        }
+    }
 }
 
-/// Data point in a trend analysis
+/// Difference between an activity and its baseline for a single metric
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TrendDataPoint {
-    pub date: DateTime<Utc>,
-    pub value: f64,
-    pub smoothed_value: Option<f64>,
+pub struct MetricDelta {
+    pub current: f64,
+    pub baseline: f64,
+    pub delta: f64,
+    /// Delta relative to the baseline, in percent
+    pub delta_percent: f64,
+    /// Delta in baseline standard deviations, when the baseline has spread
+    pub z_score: Option<f64>,
+    /// Whether the change is favourable; `None` for direction-neutral metrics
+    pub improved: Option<bool>,
+    pub significant: bool,
+}
+
+/// Normalized deltas reported by an activity comparison
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ComparisonDeltas {
+    /// Pace in seconds per kilometre
+    pub pace: Option<MetricDelta>,
+    /// Average heart rate in bpm
+    pub heart_rate: Option<MetricDelta>,
+    pub efficiency_factor: Option<MetricDelta>,
+    /// Elevation gain in metres
+    pub elevation: Option<MetricDelta>,
+    /// Heart beats per kilometre, corrected for weather difficulty
+    pub weather_adjusted_effort: Option<MetricDelta>,
+}
+
+/// Result of comparing an activity against a historical baseline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityComparison {
+    pub activity_id: String,
+    pub comparison_type: ComparisonType,
+    pub baseline_activity_ids: Vec<String>,
+    /// True when the baseline was narrowed to activities on the same route
+    pub route_matched: bool,
+    pub deltas: ComparisonDeltas,
+    /// True when at least one metric changed significantly
+    pub significant: bool,
+    pub insights: Vec<AdvancedInsight>,
 }
-
 /// Fitness goal definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Goal {
@@ -336,6 +431,8 @@ pub struct Goal {
     pub title: String,
     pub description: String,
     pub goal_type: GoalType,
+    /// Target in the goal type's unit: km for distance, minutes for time,
+    /// sessions for frequency, percent for performance
     pub target_value: f64,
     pub target_date: DateTime<Utc>,
     pub current_value: f64,
@@ -344,18 +441,257 @@ pub struct Goal {
     pub status: GoalStatus,
 }
 
+impl Goal {
+    /// Build a new active goal from `set_goal` tool arguments
+    pub fn from_args(user_id: &str, args: &serde_json::Value) -> anyhow::Result<Self> {
+        let title = args["title"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: title"))?;
+        let kind = args["goal_type"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: goal_type"))?;
+        let target_value = args["target_value"]
+            .as_f64()
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: target_value"))?;
+        let target_date = args["target_date"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("Missing required parameter: target_date"))?;
+
+        let now = Utc::now();
+        let goal = Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            title: title.trim().to_string(),
+            description: args["description"].as_str().unwrap_or("").to_string(),
+            goal_type: GoalType::from_args(kind, args)?,
+            target_value,
+            target_date: Self::parse_date(target_date)?,
+            current_value: args["current_value"].as_f64().unwrap_or(0.0),
+            created_at: now,
+            updated_at: now,
+            status: GoalStatus::Active,
+        };
+
+        goal.validate()?;
+        Ok(goal)
+    }
+
+    /// Apply `update_goal` tool arguments. Fields not present are left unchanged;
+    /// the type parameters are rebuilt only when `goal_type` is given.
+    pub fn apply_update(&mut self, args: &serde_json::Value) -> anyhow::Result<()> {
+        if let Some(title) = args["title"].as_str() {
+            self.title = title.trim().to_string();
+        }
+        if let Some(description) = args["description"].as_str() {
+            self.description = description.to_string();
+        }
+        if let Some(kind) = args["goal_type"].as_str() {
+            self.goal_type = GoalType::from_args(kind, args)?;
+        }
+        if let Some(target_value) = args["target_value"].as_f64() {
+            self.target_value = target_value;
+        }
+        if let Some(target_date) = args["target_date"].as_str() {
+            self.target_date = Self::parse_date(target_date)?;
+        }
+        if let Some(current_value) = args["current_value"].as_f64() {
+            self.current_value = current_value;
+        }
+        if let Some(name) = args["status"].as_str() {
+            let status = GoalStatus::parse(name).ok_or_else(|| anyhow::anyhow!("Unknown goal status '{}'", name))?;
+            self.transition_to(status)?;
+        }
+
+        self.updated_at = Utc::now();
+        // Parameters were checked above if they changed; legacy goals keep theirs
+        self.validate_fields()
+    }
+
+    /// Move the goal to a new status, rejecting changes to completed or cancelled goals
+    pub fn transition_to(&mut self, status: GoalStatus) -> anyhow::Result<()> {
+        if status != self.status && !self.status.can_transition_to(status) {
+            anyhow::bail!(
+                "Cannot change a {} goal to {}",
+                self.status.as_str(),
+                status.as_str()
+            );
+        }
+        self.status = status;
+        Ok(())
+    }
+
+    /// Parse a goal date given as RFC 3339 or a bare YYYY-MM-DD (midnight UTC)
+    pub fn parse_date(value: &str) -> anyhow::Result<DateTime<Utc>> {
+        if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+            return Ok(timestamp.with_timezone(&Utc));
+        }
+
+        let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| anyhow::anyhow!("Invalid date '{}'. Use YYYY-MM-DD or RFC 3339", value))?;
+        Ok(date.and_time(chrono::NaiveTime::MIN).and_utc())
+    }
+
+    /// Check the goal is well formed before it is stored
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.validate_fields()?;
+        self.goal_type.validate()
+    }
+
+    /// Check the fields shared by every goal kind. Goals stored before
+    /// `goal_params` existed load with placeholder parameters, so updates only
+    /// check the parameters when they are replaced.
+    pub fn validate_fields(&self) -> anyhow::Result<()> {
+        if self.title.trim().is_empty() {
+            anyhow::bail!("Goal title must not be empty");
+        }
+        if !self.target_value.is_finite() || self.target_value <= 0.0 {
+            anyhow::bail!("Goal target_value must be a positive number");
+        }
+        if !self.current_value.is_finite() || self.current_value < 0.0 {
+            anyhow::bail!("Goal current_value must not be negative");
+        }
+        Ok(())
+    }
+
+    /// Progress toward the target, capped at 100. Time goals track the best
+    /// time, so progress grows as `current_value` drops toward the target.
+    pub fn progress_percentage(&self) -> f64 {
+        let ratio = match self.goal_type {
+            GoalType::Time { .. } if self.current_value <= 0.0 => 0.0,
+            GoalType::Time { .. } => self.target_value / self.current_value,
+            _ => self.current_value / self.target_value,
+        };
+        (ratio * 100.0).clamp(0.0, 100.0)
+    }
+}
+
 /// Type of fitness goal
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum GoalType {
+    /// Cover `target_value` km within the time frame
     Distance { sport: String, timeframe: TimeFrame },
+    /// Finish `distance` km in `target_value` minutes
     Time { sport: String, distance: f64 },
+    /// Train `sessions_per_week` times a week
     Frequency { sport: String, sessions_per_week: i32 },
+    /// Improve `metric` by `improvement_percent`
     Performance { metric: String, improvement_percent: f64 },
     Custom { metric: String, unit: String },
 }
 
+impl GoalType {
+    /// Goal kinds accepted by `from_args`
+    pub const KINDS: [&'static str; 5] = [
+        GOAL_TYPE_DISTANCE,
+        GOAL_TYPE_TIME,
+        GOAL_TYPE_FREQUENCY,
+        GOAL_TYPE_PERFORMANCE,
+        GOAL_TYPE_CUSTOM,
+    ];
+
+    /// Build a goal type from tool arguments. `kind` is one of [`GoalType::KINDS`];
+    /// the remaining fields come from `sport_type`, `timeframe`, `distance_km`,
+    /// `sessions_per_week`, `metric`, `improvement_percent` and `unit`.
+    pub fn from_args(kind: &str, args: &serde_json::Value) -> anyhow::Result<Self> {
+        let text = |field: &str| args[field].as_str().map(str::trim).unwrap_or("").to_string();
+        let sport = text("sport_type");
+
+        let goal_type = match kind.trim().to_lowercase().as_str() {
+            "distance" => {
+                let timeframe_name = args["timeframe"].as_str().unwrap_or("month");
+                let timeframe = TimeFrame::parse(timeframe_name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown timeframe '{}'", timeframe_name))?;
+                Self::Distance { sport, timeframe }
+            }
+            "time" => Self::Time {
+                sport,
+                distance: args["distance_km"].as_f64().unwrap_or(0.0),
+            },
+            "frequency" => Self::Frequency {
+                sport,
+                sessions_per_week: args["sessions_per_week"].as_i64().unwrap_or(0) as i32,
+            },
+            "performance" => Self::Performance {
+                metric: text("metric"),
+                improvement_percent: args["improvement_percent"].as_f64().unwrap_or(0.0),
+            },
+            "custom" => Self::Custom {
+                metric: text("metric"),
+                unit: text("unit"),
+            },
+            other => anyhow::bail!(
+                "Unknown goal_type '{}'. Use one of: {}",
+                other,
+                Self::KINDS.join(", ")
+            ),
+        };
+
+        goal_type.validate()?;
+        Ok(goal_type)
+    }
+
+    /// Check the fields required by each goal kind
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::Distance { sport, .. } if sport.is_empty() => {
+                anyhow::bail!("Distance goals require a sport_type")
+            }
+            Self::Time { sport, distance } => {
+                if sport.is_empty() {
+                    anyhow::bail!("Time goals require a sport_type");
+                }
+                if !distance.is_finite() || *distance <= 0.0 {
+                    anyhow::bail!("Time goals require a positive distance_km");
+                }
+            }
+            Self::Frequency { sport, sessions_per_week } => {
+                if sport.is_empty() {
+                    anyhow::bail!("Frequency goals require a sport_type");
+                }
+                if !(1..=14).contains(sessions_per_week) {
+                    anyhow::bail!("Frequency goals require sessions_per_week between 1 and 14");
+                }
+            }
+            Self::Performance { metric, improvement_percent } => {
+                if metric.is_empty() {
+                    anyhow::bail!("Performance goals require a metric");
+                }
+                if !improvement_percent.is_finite() || *improvement_percent <= 0.0 {
+                    anyhow::bail!("Performance goals require a positive improvement_percent");
+                }
+            }
+            Self::Custom { metric, unit } if metric.is_empty() || unit.is_empty() => {
+                anyhow::bail!("Custom goals require a metric and a unit")
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Kind name stored in the `goals.goal_type` column
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Distance { .. } => GOAL_TYPE_DISTANCE,
+            Self::Time { .. } => GOAL_TYPE_TIME,
+            Self::Frequency { .. } => GOAL_TYPE_FREQUENCY,
+            Self::Performance { .. } => GOAL_TYPE_PERFORMANCE,
+            Self::Custom { .. } => GOAL_TYPE_CUSTOM,
+        }
+    }
+
+    /// Sport the goal applies to, if any
+    pub fn sport(&self) -> Option<&str> {
+        match self {
+            Self::Distance { sport, .. } | Self::Time { sport, .. } | Self::Frequency { sport, .. } => Some(sport),
+            Self::Performance { .. } | Self::Custom { .. } => None,
+        }
+    }
+}
+
 /// Status of a goal
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum GoalStatus {
     Active,
     Completed,
@@ -363,10 +699,45 @@ pub enum GoalStatus {
     Cancelled,
 }
 
+impl GoalStatus {
+    /// Parse a status name ('active', 'completed', 'paused', 'cancelled')
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "active" => Some(Self::Active),
+            "completed" => Some(Self::Completed),
+            "paused" => Some(Self::Paused),
+            "cancelled" | "canceled" => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+
+    /// Name stored in the `goals.status` column
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Active => GOAL_STATUS_ACTIVE,
+            Self::Completed => GOAL_STATUS_COMPLETED,
+            Self::Paused => GOAL_STATUS_PAUSED,
+            Self::Cancelled => GOAL_STATUS_CANCELLED,
+        }
+    }
+
+    /// Completed and cancelled goals are final; active and paused goals can move
+    /// between each other or be closed
+    pub fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (Self::Active, Self::Paused | Self::Completed | Self::Cancelled)
+                | (Self::Paused, Self::Active | Self::Completed | Self::Cancelled)
+        )
+    }
+}
+
 /// Progress report for a goal
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProgressReport {
     pub goal_id: String,
+    /// Recomputed `Goal::current_value`
+    pub current_value: f64,
     pub progress_percentage: f64,
     pub completion_date_estimate: Option<DateTime<Utc>>,
     pub milestones_achieved: Vec<Milestone>,
@@ -384,39 +755,6 @@ pub struct Milestone {
     pub achieved: bool,
 }
 
-/// Training recommendation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct TrainingRecommendation {
-    pub recommendation_type: RecommendationType,
-    pub title: String,
-    pub description: String,
-    pub priority: RecommendationPriority,
-    pub confidence: Confidence,
-    pub rationale: String,
-    pub actionable_steps: Vec<String>,
-}
-
-/// Type of training recommendation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RecommendationType {
-    Intensity,
-    Volume,
-    Recovery,
-    Technique,
-    Nutrition,
-    Equipment,
-    Strategy,
-}
-
-/// Priority level for recommendations
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub enum RecommendationPriority {
-    Low,
-    Medium,
-    High,
-    Critical,
-}
-
 /// User fitness profile for personalized analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserFitnessProfile {
@@ -431,6 +769,32 @@ pub struct UserFitnessProfile {
     pub preferences: UserPreferences,
 }
 
+impl UserFitnessProfile {
+    /// Profile for a user who has not filled one in yet
+    pub fn new(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            age: None,
+            gender: None,
+            weight: None,
+            height: None,
+            fitness_level: FitnessLevel::Beginner,
+            primary_sports: Vec::new(),
+            training_history_months: 0,
+            preferences: UserPreferences {
+                preferred_units: "metric".to_string(),
+                training_focus: Vec::new(),
+                injury_history: Vec::new(),
+                time_availability: TimeAvailability {
+                    hours_per_week: 0.0,
+                    preferred_days: Vec::new(),
+                    preferred_duration_minutes: None,
+                },
+            },
+        }
+    }
+}
+
 /// Fitness level classification
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FitnessLevel {
@@ -440,6 +804,19 @@ pub enum FitnessLevel {
     Elite,
 }
 
+impl FitnessLevel {
+    /// Parse the level stored in `user_profiles.fitness_level`
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "beginner" => Some(Self::Beginner),
+            "intermediate" => Some(Self::Intermediate),
+            "advanced" => Some(Self::Advanced),
+            "elite" => Some(Self::Elite),
+            _ => None,
+        }
+    }
+}
+
 /// User preferences for training and analysis
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPreferences {
@@ -456,6 +833,63 @@ pub struct TimeAvailability {
     pub preferred_days: Vec<String>,
     pub preferred_duration_minutes: Option<i32>,
 }
+
+/// Training recommendation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingRecommendation {
+    pub recommendation_type: RecommendationType,
+    pub title: String,
+    pub description: String,
+    pub priority: RecommendationPriority,
+    pub confidence: Confidence,
+    pub rationale: String,
+    pub actionable_steps: Vec<String>,
+}
+
+/// Type of training recommendation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum RecommendationType {
+    Intensity,
+    Volume,
+    Recovery,
+    Technique,
+    Nutrition,
+    Equipment,
+    Strategy,
+}
+
+/// Priority level for recommendations
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum RecommendationPriority {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+// Temporarily disabled during compilation fixes
+
+/*
+/// Performance trend analysis results
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendAnalysis {
+    pub timeframe: TimeFrame,
+    pub metric: String,
+    pub trend_direction: TrendDirection,
+    pub trend_strength: f64,
+    pub statistical_significance: f64,
+    pub data_points: Vec<TrendDataPoint>,
+    pub insights: Vec<AdvancedInsight>,
+}
+
+/// Data point in a trend analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendDataPoint {
+    pub date: DateTime<Utc>,
+    pub value: f64,
+    pub smoothed_value: Option<f64>,
+}
+
 */
 
 #[cfg(test)]
@@ -509,4 +943,44 @@ mod tests {
                    zones.zone3_tempo + zones.zone4_threshold + zones.zone5_vo2max;
         assert_eq!(total, 100.0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_goal_type_validation() {
+        let goal_type = GoalType::from_args("frequency", &serde_json::json!({ "sport_type": "run", "sessions_per_week": 4 })).unwrap();
+        assert_eq!(goal_type, GoalType::Frequency { sport: "run".to_string(), sessions_per_week: 4 });
+        assert_eq!(goal_type.kind(), "frequency");
+
+        let goal_type = GoalType::from_args("distance", &serde_json::json!({ "sport_type": "ride" })).unwrap();
+        assert_eq!(goal_type, GoalType::Distance { sport: "ride".to_string(), timeframe: TimeFrame::Month });
+
+        assert!(GoalType::from_args("distance", &serde_json::json!({})).is_err());
+        assert!(GoalType::from_args("time", &serde_json::json!({ "sport_type": "run" })).is_err());
+        assert!(GoalType::from_args("frequency", &serde_json::json!({ "sport_type": "run", "sessions_per_week": 20 })).is_err());
+        assert!(GoalType::from_args("custom", &serde_json::json!({ "metric": "pushups" })).is_err());
+        assert!(GoalType::from_args("speed", &serde_json::json!({})).is_err());
+    }
+
+    #[test]
+    fn test_goal_lifecycle() {
+        let args = serde_json::json!({
+            "title": "Sub-2 half marathon",
+            "goal_type": "time",
+            "sport_type": "run",
+            "distance_km": 21.1,
+            "target_value": 120.0,
+            "target_date": "2026-10-04"
+        });
+        let mut goal = Goal::from_args("user", &args).unwrap();
+        assert_eq!(goal.status, GoalStatus::Active);
+        assert_eq!(goal.goal_type.sport(), Some("run"));
+
+        goal.apply_update(&serde_json::json!({ "status": "paused", "current_value": 240.0 })).unwrap();
+        assert_eq!(goal.status, GoalStatus::Paused);
+        assert_eq!(goal.progress_percentage(), 50.0);
+
+        goal.transition_to(GoalStatus::Completed).unwrap();
+        assert!(goal.transition_to(GoalStatus::Active).is_err());
+        assert!(goal.apply_update(&serde_json::json!({ "target_value": -1.0 })).is_err());
+        assert!(Goal::from_args("user", &serde_json::json!({ "title": "No type" })).is_err());
+    }
+}
@@ -0,0 +1,775 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Training pattern detection over an athlete's activity history
+
+use super::{Confidence, TimeOfDay};
+use crate::models::{Activity, SportType};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Minimum number of activities before any pattern is reported
+const MIN_ACTIVITIES: usize = 6;
+/// A run counts as a long run when it exceeds the median run by this factor
+const LONG_RUN_FACTOR: f64 = 1.5;
+/// Share of the athlete's observed max HR above which a session counts as hard
+const HARD_SESSION_HR_RATIO: f64 = 0.80;
+/// Minimum samples per group before comparing performance between groups
+const MIN_GROUP_SAMPLES: usize = 3;
+/// Relative performance gap (percent) treated as meaningful
+const PERFORMANCE_GAP_PERCENT: f64 = 2.0;
+
+/// Kinds of patterns the detector can look for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatternType {
+    /// Preferred training days of the week
+    WeekdayHabits,
+    /// Preferred time of day for training
+    TimeOfDayHabits,
+    /// How regularly long runs are scheduled
+    LongRunCadence,
+    /// Training volume cycles across the seasons
+    SeasonalVolume,
+    /// Whether hard sessions are followed by easy ones
+    HardEasyAlternation,
+    /// Performance differences between times of day
+    TimeOfDayPerformance,
+    /// Performance drop-off on the second of back-to-back days
+    BackToBackFatigue,
+}
+
+impl PatternType {
+    /// All supported pattern types
+    pub const ALL: [PatternType; 7] = [
+        Self::WeekdayHabits,
+        Self::TimeOfDayHabits,
+        Self::LongRunCadence,
+        Self::SeasonalVolume,
+        Self::HardEasyAlternation,
+        Self::TimeOfDayPerformance,
+        Self::BackToBackFatigue,
+    ];
+
+    /// Resolve a `pattern_type` tool argument into the detectors to run.
+    /// Accepts individual pattern names, `all`, and the broader legacy categories.
+    pub fn from_filter(filter: &str) -> Option<Vec<Self>> {
+        let patterns = match filter {
+            "all" | "" => Self::ALL.to_vec(),
+            "weekday_habits" => vec![Self::WeekdayHabits],
+            "time_of_day_habits" => vec![Self::TimeOfDayHabits],
+            "long_run_cadence" => vec![Self::LongRunCadence],
+            "seasonal_volume" => vec![Self::SeasonalVolume],
+            "hard_easy_alternation" => vec![Self::HardEasyAlternation],
+            "time_of_day_performance" => vec![Self::TimeOfDayPerformance],
+            "back_to_back_fatigue" => vec![Self::BackToBackFatigue],
+            "training_consistency" => vec![Self::WeekdayHabits, Self::TimeOfDayHabits, Self::LongRunCadence],
+            "seasonal_trends" => vec![Self::SeasonalVolume],
+            "performance_timing" => vec![Self::TimeOfDayPerformance],
+            "injury_risk" => vec![Self::HardEasyAlternation, Self::BackToBackFatigue],
+            _ => return None,
+        };
+        Some(patterns)
+    }
+}
+
+/// A pattern found in the activity history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DetectedPattern {
+    pub pattern_type: PatternType,
+    pub description: String,
+    pub confidence: Confidence,
+    /// Supporting numbers behind the pattern
+    pub evidence: HashMap<String, serde_json::Value>,
+    pub recommendation: Option<String>,
+}
+
+/// Result of running pattern detection
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatternAnalysis {
+    pub activities_analyzed: usize,
+    pub patterns_checked: Vec<PatternType>,
+    pub patterns: Vec<DetectedPattern>,
+}
+
+/// Detects habits and performance patterns across an activity history
+pub struct PatternDetector {
+    /// Timezone whose days and hours the user trains by
+    timezone: Tz,
+}
+
+impl PatternDetector {
+    /// Create a new pattern detector that reads times in UTC
+    pub fn new() -> Self {
+        Self { timezone: Tz::UTC }
+    }
+
+    /// Read days and times of day in the user's timezone
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Run the requested detectors over the activity history
+    pub fn detect_patterns(&self, activities: &[Activity], pattern_types: &[PatternType]) -> PatternAnalysis {
+        let mut sorted: Vec<&Activity> = activities.iter().collect();
+        sorted.sort_by_key(|a| a.start_date);
+
+        let patterns = if sorted.len() < MIN_ACTIVITIES {
+            Vec::new()
+        } else {
+            pattern_types
+                .iter()
+                .flat_map(|pattern_type| match pattern_type {
+                    PatternType::WeekdayHabits => Self::detect_weekday_habits(&sorted, self.timezone),
+                    PatternType::TimeOfDayHabits => Self::detect_time_of_day_habits(&sorted, self.timezone),
+                    PatternType::LongRunCadence => Self::detect_long_run_cadence(&sorted),
+                    PatternType::SeasonalVolume => Self::detect_seasonal_volume(&sorted, self.timezone),
+                    PatternType::HardEasyAlternation => Self::detect_hard_easy_alternation(&sorted),
+                    PatternType::TimeOfDayPerformance => Self::detect_time_of_day_performance(&sorted, self.timezone),
+                    PatternType::BackToBackFatigue => Self::detect_back_to_back_fatigue(&sorted, self.timezone),
+                })
+                .collect()
+        };
+
+        PatternAnalysis {
+            activities_analyzed: sorted.len(),
+            patterns_checked: pattern_types.to_vec(),
+            patterns,
+        }
+    }
+
+    /// Preferred days of the week
+    fn detect_weekday_habits(activities: &[&Activity], timezone: Tz) -> Vec<DetectedPattern> {
+        let mut counts: BTreeMap<u32, usize> = BTreeMap::new();
+        for activity in activities {
+            *counts.entry(local_time(&activity.start_date, timezone).weekday().num_days_from_monday()).or_default() += 1;
+        }
+
+        let total = activities.len() as f64;
+        let uniform_share = 1.0 / 7.0;
+        let favourite_days: Vec<(Weekday, f64)> = counts
+            .iter()
+            .map(|(day, count)| (weekday_from_index(*day), *count as f64 / total))
+            .filter(|(_, share)| *share >= uniform_share * 1.5)
+            .collect();
+
+        if favourite_days.is_empty() {
+            return Vec::new();
+        }
+
+        let rest_days: Vec<String> = (0..7)
+            .filter(|day| !counts.contains_key(day))
+            .map(|day| weekday_from_index(day).to_string())
+            .collect();
+        let favourite_share: f64 = favourite_days.iter().map(|(_, share)| share).sum();
+        let distribution: BTreeMap<String, usize> = counts
+            .iter()
+            .map(|(day, count)| (weekday_from_index(*day).to_string(), *count))
+            .collect();
+
+        let mut evidence = HashMap::new();
+        evidence.insert("distribution".to_string(), json!(distribution));
+        evidence.insert("favourite_days_share".to_string(), json!(favourite_share));
+        evidence.insert("rest_days".to_string(), json!(rest_days));
+
+        vec![DetectedPattern {
+            pattern_type: PatternType::WeekdayHabits,
+            description: format!(
+                "You train most often on {} ({:.0}% of sessions)",
+                favourite_days.iter().map(|(day, _)| day.to_string()).collect::<Vec<_>>().join(", "),
+                favourite_share * 100.0
+            ),
+            confidence: sample_confidence(activities.len(), favourite_share),
+            evidence,
+            recommendation: None,
+        }]
+    }
+
+    /// Preferred time of day
+    fn detect_time_of_day_habits(activities: &[&Activity], timezone: Tz) -> Vec<DetectedPattern> {
+        let mut counts: HashMap<TimeOfDay, usize> = HashMap::new();
+        for activity in activities {
+            *counts.entry(time_of_day(&activity.start_date, timezone)).or_default() += 1;
+        }
+
+        let Some((&slot, &count)) = counts.iter().max_by_key(|(_, count)| **count) else {
+            return Vec::new();
+        };
+        let share = count as f64 / activities.len() as f64;
+        if share < 0.5 {
+            return Vec::new();
+        }
+
+        let distribution: HashMap<&str, usize> = counts
+            .iter()
+            .map(|(slot, count)| (slot.display_name(), *count))
+            .collect();
+
+        let mut evidence = HashMap::new();
+        evidence.insert("time_of_day".to_string(), json!(slot));
+        evidence.insert("share".to_string(), json!(share));
+        evidence.insert("distribution".to_string(), json!(distribution));
+
+        vec![DetectedPattern {
+            pattern_type: PatternType::TimeOfDayHabits,
+            description: format!("{:.0}% of your sessions start in the {}", share * 100.0, slot.display_name()),
+            confidence: sample_confidence(activities.len(), share),
+            evidence,
+            recommendation: None,
+        }]
+    }
+
+    /// Regularity of long runs
+    fn detect_long_run_cadence(activities: &[&Activity]) -> Vec<DetectedPattern> {
+        let runs: Vec<&Activity> = activities
+            .iter()
+            .copied()
+            .filter(|a| a.sport_type == SportType::Run && a.distance_meters.is_some())
+            .collect();
+        let distances: Vec<f64> = runs.iter().filter_map(|a| a.distance_meters).collect();
+        let Some(median_distance) = median(&distances) else {
+            return Vec::new();
+        };
+
+        let threshold = median_distance * LONG_RUN_FACTOR;
+        let long_runs: Vec<&Activity> = runs
+            .into_iter()
+            .filter(|a| a.distance_meters.unwrap_or(0.0) >= threshold)
+            .collect();
+        if long_runs.len() < 3 {
+            return Vec::new();
+        }
+
+        let intervals: Vec<f64> = long_runs
+            .windows(2)
+            .map(|pair| (pair[1].start_date - pair[0].start_date).num_hours() as f64 / 24.0)
+            .collect();
+        let mean_interval = mean(&intervals);
+        let cv = std_dev(&intervals) / mean_interval.max(f64::EPSILON);
+        let last_long_run = long_runs[long_runs.len() - 1].start_date;
+        let days_since_last = (Utc::now() - last_long_run).num_days();
+
+        let mut evidence = HashMap::new();
+        evidence.insert("long_run_threshold_km".to_string(), json!(threshold / 1000.0));
+        evidence.insert("long_run_count".to_string(), json!(long_runs.len()));
+        evidence.insert("average_interval_days".to_string(), json!(mean_interval));
+        evidence.insert("interval_variation".to_string(), json!(cv));
+        evidence.insert("last_long_run".to_string(), json!(last_long_run));
+        evidence.insert("days_since_last_long_run".to_string(), json!(days_since_last));
+
+        let (description, recommendation) = if cv <= 0.35 {
+            (
+                format!("You do a long run (≥ {:.1} km) roughly every {:.0} days", threshold / 1000.0, mean_interval),
+                None,
+            )
+        } else {
+            (
+                format!(
+                    "Long runs (≥ {:.1} km) happen irregularly, on average every {:.0} days",
+                    threshold / 1000.0,
+                    mean_interval
+                ),
+                Some("Schedule long runs on a fixed weekly or fortnightly rhythm to build endurance steadily".to_string()),
+            )
+        };
+
+        vec![DetectedPattern {
+            pattern_type: PatternType::LongRunCadence,
+            description,
+            confidence: sample_confidence(long_runs.len() * 2, 1.0 - cv.min(1.0)),
+            evidence,
+            recommendation,
+        }]
+    }
+
+    /// Training volume cycles across seasons
+    fn detect_seasonal_volume(activities: &[&Activity], timezone: Tz) -> Vec<DetectedPattern> {
+        // Hours per (year, month), then averaged per calendar month
+        let mut monthly_hours: BTreeMap<(i32, u32), f64> = BTreeMap::new();
+        for activity in activities {
+            let local = local_time(&activity.start_date, timezone);
+            *monthly_hours.entry((local.year(), local.month())).or_default() +=
+                activity.duration_seconds as f64 / 3600.0;
+        }
+        if monthly_hours.len() < 6 {
+            return Vec::new();
+        }
+
+        let mut season_hours: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+        for ((_, month), hours) in &monthly_hours {
+            season_hours.entry(season_name(*month)).or_default().push(*hours);
+        }
+        let season_averages: BTreeMap<&str, f64> = season_hours
+            .iter()
+            .map(|(season, hours)| (*season, mean(hours)))
+            .collect();
+        if season_averages.len() < 2 {
+            return Vec::new();
+        }
+
+        let (peak_season, peak_hours) = season_averages
+            .iter()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(season, hours)| (*season, *hours))
+            .unwrap_or_default();
+        let (low_season, low_hours) = season_averages
+            .iter()
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(season, hours)| (*season, *hours))
+            .unwrap_or_default();
+        let ratio = peak_hours / low_hours.max(0.1);
+        if ratio < 1.5 {
+            return Vec::new();
+        }
+
+        let mut evidence = HashMap::new();
+        evidence.insert("average_monthly_hours_by_season".to_string(), json!(season_averages));
+        evidence.insert("months_covered".to_string(), json!(monthly_hours.len()));
+        evidence.insert("peak_to_low_ratio".to_string(), json!(ratio));
+
+        let coverage = (monthly_hours.len() as f64 / 12.0).min(1.0);
+        vec![DetectedPattern {
+            pattern_type: PatternType::SeasonalVolume,
+            description: format!(
+                "Training volume peaks in {} ({:.1} h/month) and drops in {} ({:.1} h/month)",
+                peak_season, peak_hours, low_season, low_hours
+            ),
+            confidence: Confidence::from_score(0.35 + 0.6 * coverage),
+            evidence,
+            recommendation: Some(format!(
+                "Plan a maintenance block for {} to limit fitness loss before the {} build",
+                low_season, peak_season
+            )),
+        }]
+    }
+
+    /// Whether hard sessions are followed by easy ones
+    fn detect_hard_easy_alternation(activities: &[&Activity]) -> Vec<DetectedPattern> {
+        let Some(max_hr) = activities.iter().filter_map(|a| a.max_heart_rate).max() else {
+            return Vec::new();
+        };
+        let intensities: Vec<bool> = activities
+            .iter()
+            .filter_map(|a| a.average_heart_rate)
+            .map(|hr| hr as f64 / max_hr as f64 >= HARD_SESSION_HR_RATIO)
+            .collect();
+        if intensities.len() < MIN_ACTIVITIES {
+            return Vec::new();
+        }
+
+        let hard_count = intensities.iter().filter(|hard| **hard).count();
+        let followed: Vec<bool> = intensities
+            .windows(2)
+            .filter(|pair| pair[0])
+            .map(|pair| pair[1])
+            .collect();
+        if hard_count == 0 || followed.is_empty() {
+            return Vec::new();
+        }
+
+        let hard_after_hard = followed.iter().filter(|hard| **hard).count();
+        let hard_after_hard_ratio = hard_after_hard as f64 / followed.len() as f64;
+
+        let mut evidence = HashMap::new();
+        evidence.insert("hard_sessions".to_string(), json!(hard_count));
+        evidence.insert("easy_sessions".to_string(), json!(intensities.len() - hard_count));
+        evidence.insert("hard_followed_by_hard".to_string(), json!(hard_after_hard));
+        evidence.insert("hard_followed_by_easy".to_string(), json!(followed.len() - hard_after_hard));
+        evidence.insert("hard_threshold_hr".to_string(), json!((max_hr as f64 * HARD_SESSION_HR_RATIO).round()));
+
+        let (description, recommendation) = if hard_after_hard_ratio <= 0.3 {
+            (
+                format!(
+                    "You consistently follow hard sessions with easy ones ({:.0}% of the time)",
+                    (1.0 - hard_after_hard_ratio) * 100.0
+                ),
+                None,
+            )
+        } else {
+            (
+                format!(
+                    "{:.0}% of your hard sessions are followed by another hard session",
+                    hard_after_hard_ratio * 100.0
+                ),
+                Some("Follow hard sessions with an easy day to absorb the training load".to_string()),
+            )
+        };
+
+        let strength = (hard_after_hard_ratio - 0.5).abs() * 2.0;
+        vec![DetectedPattern {
+            pattern_type: PatternType::HardEasyAlternation,
+            description,
+            confidence: sample_confidence(followed.len() * 2, strength),
+            evidence,
+            recommendation,
+        }]
+    }
+
+    /// Performance differences between times of day
+    fn detect_time_of_day_performance(activities: &[&Activity], timezone: Tz) -> Vec<DetectedPattern> {
+        let indices = relative_speed_index(activities);
+        let mut by_slot: HashMap<TimeOfDay, Vec<f64>> = HashMap::new();
+        for (activity, index) in activities.iter().zip(&indices) {
+            if let Some(index) = index {
+                by_slot.entry(time_of_day(&activity.start_date, timezone)).or_default().push(*index);
+            }
+        }
+
+        let slot_means: Vec<(TimeOfDay, f64, usize)> = by_slot
+            .iter()
+            .filter(|(_, values)| values.len() >= MIN_GROUP_SAMPLES)
+            .map(|(slot, values)| (*slot, mean(values), values.len()))
+            .collect();
+        if slot_means.len() < 2 {
+            return Vec::new();
+        }
+
+        let best = slot_means.iter().max_by(|a, b| a.1.total_cmp(&b.1)).copied().unwrap_or(slot_means[0]);
+        let worst = slot_means.iter().min_by(|a, b| a.1.total_cmp(&b.1)).copied().unwrap_or(slot_means[0]);
+        let gap_percent = (best.1 - worst.1) / worst.1 * 100.0;
+        if gap_percent < PERFORMANCE_GAP_PERCENT {
+            return Vec::new();
+        }
+
+        let relative_pace: HashMap<&str, f64> = slot_means
+            .iter()
+            .map(|(slot, value, _)| (slot.display_name(), *value))
+            .collect();
+
+        let mut evidence = HashMap::new();
+        evidence.insert("relative_speed_by_time_of_day".to_string(), json!(relative_pace));
+        evidence.insert("best_time_of_day".to_string(), json!(best.0));
+        evidence.insert("worst_time_of_day".to_string(), json!(worst.0));
+        evidence.insert("gap_percent".to_string(), json!(gap_percent));
+
+        vec![DetectedPattern {
+            pattern_type: PatternType::TimeOfDayPerformance,
+            description: format!(
+                "You are {:.1}% faster in the {} than in the {}",
+                gap_percent,
+                best.0.display_name(),
+                worst.0.display_name()
+            ),
+            confidence: sample_confidence(best.2.min(worst.2) * 2, (gap_percent / 10.0).min(1.0)),
+            evidence,
+            recommendation: Some(format!(
+                "Schedule key workouts in the {} when possible",
+                best.0.display_name()
+            )),
+        }]
+    }
+
+    /// Performance drop-off on the second of back-to-back training days
+    fn detect_back_to_back_fatigue(activities: &[&Activity], timezone: Tz) -> Vec<DetectedPattern> {
+        let indices = relative_speed_index(activities);
+        let training_days: HashSet<NaiveDate> = activities
+            .iter()
+            .map(|a| local_time(&a.start_date, timezone).date_naive())
+            .collect();
+
+        let mut back_to_back = Vec::new();
+        let mut rested = Vec::new();
+        for (activity, index) in activities.iter().zip(&indices) {
+            let Some(index) = index else { continue };
+            let day = local_time(&activity.start_date, timezone).date_naive();
+            let trained_yesterday = day.pred_opt().is_some_and(|yesterday| training_days.contains(&yesterday));
+            if trained_yesterday {
+                back_to_back.push(*index);
+            } else {
+                rested.push(*index);
+            }
+        }
+        if back_to_back.len() < MIN_GROUP_SAMPLES || rested.len() < MIN_GROUP_SAMPLES {
+            return Vec::new();
+        }
+
+        let back_to_back_mean = mean(&back_to_back);
+        let rested_mean = mean(&rested);
+        let drop_percent = (rested_mean - back_to_back_mean) / rested_mean * 100.0;
+        if drop_percent < PERFORMANCE_GAP_PERCENT {
+            return Vec::new();
+        }
+
+        let mut evidence = HashMap::new();
+        evidence.insert("back_to_back_sessions".to_string(), json!(back_to_back.len()));
+        evidence.insert("rested_sessions".to_string(), json!(rested.len()));
+        evidence.insert("back_to_back_relative_speed".to_string(), json!(back_to_back_mean));
+        evidence.insert("rested_relative_speed".to_string(), json!(rested_mean));
+        evidence.insert("drop_percent".to_string(), json!(drop_percent));
+
+        vec![DetectedPattern {
+            pattern_type: PatternType::BackToBackFatigue,
+            description: format!(
+                "Your pace drops by {:.1}% when training on consecutive days",
+                drop_percent
+            ),
+            confidence: sample_confidence(back_to_back.len().min(rested.len()) * 2, (drop_percent / 10.0).min(1.0)),
+            evidence,
+            recommendation: Some("Keep the second of back-to-back days easy, or add a rest day between key sessions".to_string()),
+        }]
+    }
+}
+
+impl Default for PatternDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Activity start time in the user's timezone
+fn local_time(start_date: &DateTime<Utc>, timezone: Tz) -> DateTime<Tz> {
+    start_date.with_timezone(&timezone)
+}
+
+fn time_of_day(start_date: &DateTime<Utc>, timezone: Tz) -> TimeOfDay {
+    TimeOfDay::from_hour(local_time(start_date, timezone).hour())
+}
+
+fn weekday_from_index(index: u32) -> Weekday {
+    Weekday::try_from(index as u8).unwrap_or(Weekday::Mon)
+}
+
+/// Meteorological season for a month (northern hemisphere)
+fn season_name(month: u32) -> &'static str {
+    match month {
+        3..=5 => "spring",
+        6..=8 => "summer",
+        9..=11 => "autumn",
+        _ => "winter",
+    }
+}
+
+/// Speed of each activity relative to the median speed for its sport.
+/// Lets runs and rides be compared on one scale; `None` when speed is unknown.
+fn relative_speed_index(activities: &[&Activity]) -> Vec<Option<f64>> {
+    let mut speeds_by_sport: HashMap<String, Vec<f64>> = HashMap::new();
+    for activity in activities {
+        if let Some(speed) = activity.average_speed.filter(|s| *s > 0.0) {
+            speeds_by_sport.entry(activity.sport_type.display_name().to_string()).or_default().push(speed);
+        }
+    }
+    let medians: HashMap<String, f64> = speeds_by_sport
+        .iter()
+        .filter_map(|(sport, speeds)| median(speeds).map(|m| (sport.clone(), m)))
+        .collect();
+
+    activities
+        .iter()
+        .map(|activity| {
+            let speed = activity.average_speed.filter(|s| *s > 0.0)?;
+            let median = medians.get(activity.sport_type.display_name())?;
+            Some(speed / median)
+        })
+        .collect()
+}
+
+/// Confidence from sample size and pattern strength (0-1)
+fn sample_confidence(samples: usize, strength: f64) -> Confidence {
+    let sample_score = samples as f64 / (samples as f64 + 10.0);
+    Confidence::from_score(0.6 * sample_score + 0.4 * strength.clamp(0.0, 1.0))
+}
+
+fn mean(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values.iter().sum::<f64>() / values.len() as f64
+    }
+}
+
+fn std_dev(values: &[f64]) -> f64 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let avg = mean(values);
+    (values.iter().map(|v| (v - avg).powi(2)).sum::<f64>() / (values.len() - 1) as f64).sqrt()
+}
+
+fn median(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    Some(if sorted.len().is_multiple_of(2) {
+        (sorted[mid - 1] + sorted[mid]) / 2.0
+    } else {
+        sorted[mid]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn activity_at(id: usize, year: i32, month: u32, day: u32, hour: u32) -> Activity {
+        Activity {
+            id: format!("a{}", id),
+            start_date: Tz::UTC
+                .with_ymd_and_hms(year, month, day, hour, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+            ..Activity::default()
+        }
+    }
+
+    fn activity_days_after(id: usize, start: NaiveDate, days: i64, hour: u32) -> Activity {
+        let date = start + chrono::Duration::days(days);
+        activity_at(id, date.year(), date.month(), date.day(), hour)
+    }
+
+    #[test]
+    fn test_pattern_type_filter() {
+        assert_eq!(PatternType::from_filter("all").unwrap().len(), PatternType::ALL.len());
+        assert_eq!(PatternType::from_filter("long_run_cadence"), Some(vec![PatternType::LongRunCadence]));
+        assert_eq!(PatternType::from_filter("training_consistency").unwrap().len(), 3);
+        assert!(PatternType::from_filter("unknown").is_none());
+    }
+
+    #[test]
+    fn test_insufficient_history_returns_no_patterns() {
+        let activities: Vec<Activity> = (0..3).map(|i| activity_at(i, 2024, 5, 1 + i as u32, 7)).collect();
+        let analysis = PatternDetector::new().detect_patterns(&activities, &PatternType::ALL);
+        assert_eq!(analysis.activities_analyzed, 3);
+        assert!(analysis.patterns.is_empty());
+    }
+
+    #[test]
+    fn test_weekday_and_time_of_day_habits() {
+        // Ten Saturday mornings in a row (2024-06-01 is a Saturday)
+        let start = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let activities: Vec<Activity> = (0..10)
+            .map(|i| activity_days_after(i, start, i as i64 * 7, 8))
+            .collect();
+
+        let analysis = PatternDetector::new()
+            .detect_patterns(&activities, &[PatternType::WeekdayHabits, PatternType::TimeOfDayHabits]);
+
+        let weekday = analysis.patterns.iter().find(|p| p.pattern_type == PatternType::WeekdayHabits).unwrap();
+        assert!(weekday.description.contains("Sat"));
+        let time = analysis.patterns.iter().find(|p| p.pattern_type == PatternType::TimeOfDayHabits).unwrap();
+        assert_eq!(time.evidence["time_of_day"], json!("morning"));
+        assert!(matches!(time.confidence, Confidence::High | Confidence::VeryHigh));
+    }
+
+    #[test]
+    fn test_long_run_cadence() {
+        let start = NaiveDate::from_ymd_opt(2024, 3, 3).unwrap();
+        let mut activities = Vec::new();
+        for week in 0..8i64 {
+            let mut long_run = activity_days_after(activities.len(), start, week * 7, 8);
+            long_run.distance_meters = Some(20_000.0);
+            activities.push(long_run);
+            for offset in 1..3 {
+                let mut easy = activity_days_after(activities.len(), start, week * 7 + offset * 2, 18);
+                easy.distance_meters = Some(8_000.0);
+                activities.push(easy);
+            }
+        }
+
+        let analysis = PatternDetector::new().detect_patterns(&activities, &[PatternType::LongRunCadence]);
+        let pattern = &analysis.patterns[0];
+        assert_eq!(pattern.evidence["long_run_count"], json!(8));
+        assert!((pattern.evidence["average_interval_days"].as_f64().unwrap() - 7.0).abs() < 0.1);
+        assert!(pattern.recommendation.is_none());
+    }
+
+    #[test]
+    fn test_seasonal_volume() {
+        let mut activities = Vec::new();
+        for month in 1..=12u32 {
+            let sessions = if (6..=8).contains(&month) { 12 } else { 4 };
+            for session in 0..sessions {
+                let mut activity = activity_at(activities.len(), 2024, month, 1 + session * 2, 7);
+                activity.duration_seconds = 3600;
+                activities.push(activity);
+            }
+        }
+
+        let analysis = PatternDetector::new().detect_patterns(&activities, &[PatternType::SeasonalVolume]);
+        let pattern = &analysis.patterns[0];
+        assert!(pattern.description.contains("peaks in summer"));
+        assert!(matches!(pattern.confidence, Confidence::VeryHigh));
+    }
+
+    #[test]
+    fn test_back_to_back_hard_sessions_flagged() {
+        let activities: Vec<Activity> = (0..10)
+            .map(|i| Activity {
+                average_heart_rate: Some(170),
+                max_heart_rate: Some(190),
+                ..activity_at(i, 2024, 4, 1 + i as u32, 7)
+            })
+            .collect();
+
+        let analysis = PatternDetector::new().detect_patterns(&activities, &[PatternType::HardEasyAlternation]);
+        let pattern = &analysis.patterns[0];
+        assert_eq!(pattern.evidence["hard_followed_by_easy"], json!(0));
+        assert!(pattern.recommendation.is_some());
+    }
+
+    #[test]
+    fn test_time_of_day_performance_and_back_to_back_fatigue() {
+        let mut activities = Vec::new();
+        // Rested morning runs are fast, evening runs the day after are slow
+        for i in 0..6u32 {
+            let mut morning = activity_at(activities.len(), 2024, 9, 1 + i * 3, 7);
+            morning.average_speed = Some(3.3);
+            activities.push(morning);
+            let mut evening = activity_at(activities.len(), 2024, 9, 2 + i * 3, 19);
+            evening.average_speed = Some(3.0);
+            activities.push(evening);
+        }
+
+        let analysis = PatternDetector::new().detect_patterns(
+            &activities,
+            &[PatternType::TimeOfDayPerformance, PatternType::BackToBackFatigue],
+        );
+
+        let timing = analysis.patterns.iter().find(|p| p.pattern_type == PatternType::TimeOfDayPerformance).unwrap();
+        assert_eq!(timing.evidence["best_time_of_day"], json!("morning"));
+        let fatigue = analysis.patterns.iter().find(|p| p.pattern_type == PatternType::BackToBackFatigue).unwrap();
+        assert!(fatigue.evidence["drop_percent"].as_f64().unwrap() > 5.0);
+    }
+
+    #[test]
+    fn test_habits_follow_the_users_timezone() {
+        // 23:30 on Saturdays in Montreal is early Sunday in UTC
+        let start = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let activities: Vec<Activity> = (0..10)
+            .map(|i| {
+                let day = start + chrono::Duration::days(i as i64 * 7);
+                Activity {
+                    id: format!("a{}", i),
+                    start_date: chrono_tz::America::Montreal
+                        .with_ymd_and_hms(day.year(), day.month(), day.day(), 23, 30, 0)
+                        .unwrap()
+                        .with_timezone(&Utc),
+                    ..Activity::default()
+                }
+            })
+            .collect();
+        let habits = [PatternType::WeekdayHabits, PatternType::TimeOfDayHabits];
+
+        let utc = PatternDetector::new().detect_patterns(&activities, &habits);
+        let weekday = utc.patterns.iter().find(|p| p.pattern_type == PatternType::WeekdayHabits).unwrap();
+        assert!(weekday.description.contains("Sun"));
+
+        let montreal = PatternDetector::new()
+            .with_timezone(chrono_tz::America::Montreal)
+            .detect_patterns(&activities, &habits);
+        let weekday = montreal.patterns.iter().find(|p| p.pattern_type == PatternType::WeekdayHabits).unwrap();
+        assert!(weekday.description.contains("Sat"));
+        let time = montreal.patterns.iter().find(|p| p.pattern_type == PatternType::TimeOfDayHabits).unwrap();
+        assert_eq!(time.evidence["time_of_day"], json!("night"));
+    }
+}
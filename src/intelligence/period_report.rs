@@ -0,0 +1,1053 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Week, month, season and year summary reports over an athlete's activity history
+
+use super::insights::{ActivityContext, InsightGenerator};
+use super::location::LocationData;
+use super::{Goal, GoalStatus, LocationContext, PersonalRecord, Streak, StreakCriteria, StreakEngine, StreakPeriod, WeatherConditions};
+use crate::activity_query::{group_activities, ActivityGroup, ActivityGroupBy};
+use crate::models::{Activity, SportType};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Write;
+
+/// Number of entries kept for ranked report sections
+const TOP_ENTRIES: usize = 5;
+/// Number of standout activities narrated by the insight generator
+const NARRATED_ACTIVITIES: usize = 3;
+/// Shortest activity considered for average speed records
+const MIN_SPEED_RECORD_DISTANCE_METERS: f64 = 5000.0;
+/// Share of the consistency score given to active weeks; the rest rewards even weekly volume
+const ACTIVE_WEEK_WEIGHT: f64 = 0.7;
+/// Daily streaks shorter than this are not worth a highlight
+const MIN_HIGHLIGHT_STREAK_DAYS: u32 = 3;
+
+/// Extracts a comparable metric from an activity
+type ActivityValue = fn(&Activity) -> Option<f64>;
+
+/// Length of a report period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReportPeriod {
+    Week,
+    Month,
+    /// Meteorological season (northern hemisphere): winter is December to February
+    Season,
+    Year,
+}
+
+impl ReportPeriod {
+    /// Parse a report period from a tool argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "week" | "weekly" => Some(Self::Week),
+            "month" | "monthly" => Some(Self::Month),
+            "season" | "seasonal" => Some(Self::Season),
+            "year" | "yearly" | "year_in_review" => Some(Self::Year),
+            _ => None,
+        }
+    }
+
+    /// Calendar window of this period containing the given date, with days
+    /// running midnight to midnight in `timezone`
+    pub fn window(&self, anchor: NaiveDate, timezone: Tz) -> ReportWindow {
+        let (start_date, end_date, label) = match self {
+            Self::Week => {
+                let start = anchor - Duration::days(i64::from(anchor.weekday().num_days_from_monday()));
+                let week = anchor.iso_week();
+                (start, start + Duration::days(6), format!("{}-W{:02}", week.year(), week.week()))
+            }
+            Self::Month => {
+                let start = first_of_month(anchor.year(), anchor.month());
+                (start, last_of_month(anchor.year(), anchor.month()), start.format("%B %Y").to_string())
+            }
+            Self::Season => {
+                let (name, start_month) = match anchor.month() {
+                    3..=5 => ("Spring", 3),
+                    6..=8 => ("Summer", 6),
+                    9..=11 => ("Autumn", 9),
+                    _ => ("Winter", 12),
+                };
+                // Winter starts in the December before January and February anchors
+                let start_year = if anchor.month() < 3 { anchor.year() - 1 } else { anchor.year() };
+                let start = first_of_month(start_year, start_month);
+                let end_anchor = start + Duration::days(62);
+                let end = last_of_month(end_anchor.year(), end_anchor.month());
+                let label = if start_month == 12 {
+                    format!("{} {}-{:02}", name, start_year, (start_year + 1) % 100)
+                } else {
+                    format!("{} {}", name, start_year)
+                };
+                (start, end, label)
+            }
+            Self::Year => (
+                first_of_month(anchor.year(), 1),
+                last_of_month(anchor.year(), 12),
+                anchor.year().to_string(),
+            ),
+        };
+
+        ReportWindow {
+            period: *self,
+            label,
+            start_date,
+            end_date,
+            timezone,
+        }
+    }
+
+    /// Capitalized name used in report titles
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            Self::Week => "Weekly",
+            Self::Month => "Monthly",
+            Self::Season => "Seasonal",
+            Self::Year => "Year in Review",
+        }
+    }
+}
+
+/// Calendar dates covered by a report, in the user's timezone
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportWindow {
+    pub period: ReportPeriod,
+    pub label: String,
+    pub start_date: NaiveDate,
+    /// Last day of the window, inclusive
+    pub end_date: NaiveDate,
+    /// Timezone the window's days and activity dates are read in
+    pub timezone: Tz,
+}
+
+impl ReportWindow {
+    /// First instant of the window
+    pub fn start(&self) -> DateTime<Utc> {
+        local_midnight(self.start_date, self.timezone)
+    }
+
+    /// First instant after the window
+    pub fn end(&self) -> DateTime<Utc> {
+        local_midnight(self.end_date + Duration::days(1), self.timezone)
+    }
+
+    /// Calendar date of a timestamp in the window's timezone
+    pub fn local_date(&self, date: DateTime<Utc>) -> NaiveDate {
+        date.with_timezone(&self.timezone).date_naive()
+    }
+
+    /// Whether a timestamp falls inside the window
+    pub fn contains(&self, date: DateTime<Utc>) -> bool {
+        date >= self.start() && date < self.end()
+    }
+}
+
+/// Kind of standout effort in a period
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EffortCategory {
+    LongestDistance,
+    LongestDuration,
+    MostElevation,
+}
+
+impl EffortCategory {
+    fn display_name(&self) -> &'static str {
+        match self {
+            Self::LongestDistance => "Longest distance",
+            Self::LongestDuration => "Longest duration",
+            Self::MostElevation => "Most elevation",
+        }
+    }
+}
+
+/// The biggest activity of the period in one category
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BiggestEffort {
+    pub category: EffortCategory,
+    pub activity_id: String,
+    pub activity_name: String,
+    pub sport_type: SportType,
+    pub date: DateTime<Utc>,
+    pub value: f64,
+    pub unit: String,
+}
+
+/// A personal record set during the period, relative to all earlier history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodPersonalRecord {
+    pub activity_id: String,
+    pub activity_name: String,
+    pub sport_type: SportType,
+    pub date: DateTime<Utc>,
+    pub record: PersonalRecord,
+}
+
+/// A place trained at during the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaceVisit {
+    pub name: String,
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    pub visits: usize,
+    pub total_distance_km: f64,
+}
+
+/// Weather recorded for one activity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherRecord {
+    pub activity_id: String,
+    pub activity_name: String,
+    pub date: DateTime<Utc>,
+    pub conditions: WeatherConditions,
+}
+
+/// Hottest, coldest and windiest sessions of the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeatherExtremes {
+    pub activities_with_weather: usize,
+    pub hottest: Option<WeatherRecord>,
+    pub coldest: Option<WeatherRecord>,
+    pub windiest: Option<WeatherRecord>,
+    /// Sessions in rain or snow
+    pub wet_activities: usize,
+}
+
+/// How regularly the athlete trained during the period
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConsistencyScore {
+    pub active_days: usize,
+    pub active_weeks: usize,
+    /// Weeks of the period elapsed so far
+    pub weeks_in_period: usize,
+    /// 0-100, weighting active weeks and how evenly sessions were spread across them
+    pub score: f64,
+}
+
+/// Longest streaks within the period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodStreaks {
+    pub longest_daily: Option<Streak>,
+    pub longest_weekly: Option<Streak>,
+}
+
+/// Result of a goal at the time of the report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalOutcomeStatus {
+    Achieved,
+    Missed,
+    InProgress,
+}
+
+/// A goal's progress and outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GoalOutcome {
+    pub goal_id: String,
+    pub title: String,
+    pub goal_type: String,
+    pub target_value: f64,
+    pub current_value: f64,
+    pub progress_percentage: f64,
+    pub target_date: Option<DateTime<Utc>>,
+    pub status: String,
+    pub outcome: GoalOutcomeStatus,
+}
+
+impl GoalOutcome {
+    /// Build an outcome from a stored goal
+    pub fn from_goal(goal: &Goal, now: DateTime<Utc>) -> Self {
+        let progress_percentage = goal.progress_percentage();
+
+        let outcome = if goal.status == GoalStatus::Completed || progress_percentage >= 100.0 {
+            GoalOutcomeStatus::Achieved
+        } else if goal.target_date < now {
+            GoalOutcomeStatus::Missed
+        } else {
+            GoalOutcomeStatus::InProgress
+        };
+
+        Self {
+            goal_id: goal.id.clone(),
+            title: goal.title.clone(),
+            goal_type: goal.goal_type.kind().to_string(),
+            target_value: goal.target_value,
+            current_value: goal.current_value,
+            progress_percentage,
+            target_date: Some(goal.target_date),
+            status: goal.status.as_str().to_string(),
+            outcome,
+        }
+    }
+}
+
+/// Summary report for one period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PeriodReport {
+    pub window: ReportWindow,
+    pub activity_count: usize,
+    pub total_distance_km: f64,
+    pub total_duration_hours: f64,
+    pub total_elevation_gain_m: f64,
+    pub totals_by_sport: Vec<ActivityGroup>,
+    pub biggest_efforts: Vec<BiggestEffort>,
+    pub personal_records: Vec<PeriodPersonalRecord>,
+    pub streaks: PeriodStreaks,
+    pub top_places: Vec<PlaceVisit>,
+    pub weather_extremes: Option<WeatherExtremes>,
+    pub consistency: ConsistencyScore,
+    pub goal_outcomes: Vec<GoalOutcome>,
+    /// Narrative lines for the report
+    pub highlights: Vec<String>,
+}
+
+/// Optional data enriching a report beyond the activities themselves
+#[derive(Debug, Clone, Default)]
+pub struct ReportContext {
+    /// Weather by activity ID
+    pub weather: HashMap<String, WeatherConditions>,
+    /// Reverse-geocoded locations by activity ID
+    pub locations: HashMap<String, LocationData>,
+    /// All of the user's goals
+    pub goals: Vec<GoalOutcome>,
+}
+
+/// Builds period reports from activity history
+pub struct PeriodReportGenerator {
+    insight_generator: InsightGenerator,
+}
+
+impl Default for PeriodReportGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PeriodReportGenerator {
+    pub fn new() -> Self {
+        Self {
+            insight_generator: InsightGenerator::new(),
+        }
+    }
+
+    /// Generate a report for `window`. `history` may include activities outside the
+    /// window; earlier ones are the baseline for personal records.
+    pub fn generate(
+        &self,
+        window: &ReportWindow,
+        history: &[Activity],
+        context: &ReportContext,
+        now: DateTime<Utc>,
+    ) -> PeriodReport {
+        let mut activities: Vec<Activity> = history
+            .iter()
+            .filter(|a| window.contains(a.start_date))
+            .cloned()
+            .collect();
+        activities.sort_by_key(|a| a.start_date);
+
+        let totals_by_sport = group_activities(&activities, ActivityGroupBy::Sport, window.timezone);
+        let biggest_efforts = biggest_efforts(&activities);
+        let personal_records = personal_records(window, history, &activities);
+        let streaks = period_streaks(window, &activities, now);
+        let top_places = top_places(&activities, &context.locations);
+        let weather_extremes = weather_extremes(&activities, &context.weather);
+        let consistency = consistency_score(window, &activities, now);
+        let goal_outcomes: Vec<GoalOutcome> = context
+            .goals
+            .iter()
+            .filter(|goal| {
+                goal.target_date.is_some_and(|date| window.contains(date))
+                    || (goal.outcome == GoalOutcomeStatus::InProgress && goal.status == "active")
+            })
+            .cloned()
+            .collect();
+
+        let mut report = PeriodReport {
+            window: window.clone(),
+            activity_count: activities.len(),
+            total_distance_km: totals_by_sport.iter().map(|g| g.total_distance_km).sum(),
+            total_duration_hours: totals_by_sport.iter().map(|g| g.total_duration_hours).sum(),
+            total_elevation_gain_m: totals_by_sport.iter().map(|g| g.total_elevation_gain_m).sum(),
+            totals_by_sport,
+            biggest_efforts,
+            personal_records,
+            streaks,
+            top_places,
+            weather_extremes,
+            consistency,
+            goal_outcomes,
+            highlights: Vec::new(),
+        };
+        report.highlights = self.highlights(&report, &activities, context);
+        report
+    }
+
+    /// Narrative lines: period summary, records, streaks, goals and the insight
+    /// generator's take on the standout activities
+    fn highlights(&self, report: &PeriodReport, activities: &[Activity], context: &ReportContext) -> Vec<String> {
+        let mut lines = Vec::new();
+        if activities.is_empty() {
+            lines.push(format!("No activities recorded for {}.", report.window.label));
+            return lines;
+        }
+
+        lines.push(format!(
+            "You logged {} activities covering {:.1} km over {:.1} hours in {}.",
+            report.activity_count, report.total_distance_km, report.total_duration_hours, report.window.label
+        ));
+
+        for pr in &report.personal_records {
+            lines.push(format!(
+                "New {} record: {} of {:.2} {} on {}.",
+                pr.sport_type.display_name(),
+                pr.record.record_type.replace('_', " "),
+                pr.record.value,
+                pr.record.unit,
+                report.window.local_date(pr.date)
+            ));
+        }
+
+        if let Some(streak) = report.streaks.longest_daily.as_ref().filter(|s| s.length >= MIN_HIGHLIGHT_STREAK_DAYS) {
+            lines.push(format!(
+                "Longest streak: {} consecutive days from {} to {}.",
+                streak.length, streak.start, streak.end
+            ));
+        }
+
+        for goal in report.goal_outcomes.iter().filter(|g| g.outcome == GoalOutcomeStatus::Achieved) {
+            lines.push(format!("Goal achieved: {}.", goal.title));
+        }
+
+        let mut narrated = BTreeSet::new();
+        for effort in &report.biggest_efforts {
+            if narrated.len() >= NARRATED_ACTIVITIES || !narrated.insert(effort.activity_id.clone()) {
+                continue;
+            }
+            let Some(activity) = activities.iter().find(|a| a.id == effort.activity_id) else {
+                continue;
+            };
+
+            let activity_context = ActivityContext {
+                weather: context.weather.get(&activity.id).cloned(),
+                location: context.locations.get(&activity.id).map(location_context),
+                recent_activities: None,
+                athlete_goals: None,
+                historical_data: None,
+            };
+            if let Some(insight) = self
+                .insight_generator
+                .generate_insights(activity, Some(&activity_context))
+                .into_iter()
+                .next()
+            {
+                lines.push(format!("{}: {}", activity.name, insight.message));
+            }
+        }
+
+        lines
+    }
+}
+
+impl PeriodReport {
+    /// Render the report as Markdown
+    pub fn to_markdown(&self) -> String {
+        let mut md = String::new();
+        let _ = writeln!(md, "# {}: {}", self.window.period.display_name(), self.window.label);
+        let _ = writeln!(md, "\n_{} to {}_\n", self.window.start_date, self.window.end_date);
+
+        let _ = writeln!(md, "## Summary\n");
+        let _ = writeln!(md, "- **Activities:** {}", self.activity_count);
+        let _ = writeln!(md, "- **Distance:** {:.1} km", self.total_distance_km);
+        let _ = writeln!(md, "- **Time:** {:.1} h", self.total_duration_hours);
+        let _ = writeln!(md, "- **Elevation gain:** {:.0} m", self.total_elevation_gain_m);
+        let _ = writeln!(
+            md,
+            "- **Consistency:** {:.0}/100 ({} of {} weeks active, {} active days)",
+            self.consistency.score, self.consistency.active_weeks, self.consistency.weeks_in_period, self.consistency.active_days
+        );
+
+        if !self.highlights.is_empty() {
+            let _ = writeln!(md, "\n## Highlights\n");
+            for line in &self.highlights {
+                let _ = writeln!(md, "- {}", line);
+            }
+        }
+
+        if !self.totals_by_sport.is_empty() {
+            let _ = writeln!(md, "\n## Totals by Sport\n");
+            let _ = writeln!(md, "| Sport | Activities | Distance (km) | Time (h) | Elevation (m) |");
+            let _ = writeln!(md, "|---|---:|---:|---:|---:|");
+            for group in &self.totals_by_sport {
+                let _ = writeln!(
+                    md,
+                    "| {} | {} | {:.1} | {:.1} | {:.0} |",
+                    group.key, group.activity_count, group.total_distance_km, group.total_duration_hours, group.total_elevation_gain_m
+                );
+            }
+        }
+
+        if !self.biggest_efforts.is_empty() {
+            let _ = writeln!(md, "\n## Biggest Efforts\n");
+            for effort in &self.biggest_efforts {
+                let _ = writeln!(
+                    md,
+                    "- **{}:** {} ({}) - {:.1} {}",
+                    effort.category.display_name(),
+                    effort.activity_name,
+                    self.window.local_date(effort.date),
+                    effort.value,
+                    effort.unit
+                );
+            }
+        }
+
+        if !self.personal_records.is_empty() {
+            let _ = writeln!(md, "\n## Personal Records\n");
+            for pr in &self.personal_records {
+                let previous = pr
+                    .record
+                    .previous_best
+                    .map(|best| format!(" (previous best {:.2})", best))
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    md,
+                    "- **{} {}:** {:.2} {}{} - {}",
+                    pr.sport_type.display_name(),
+                    pr.record.record_type.replace('_', " "),
+                    pr.record.value,
+                    pr.record.unit,
+                    previous,
+                    pr.activity_name
+                );
+            }
+        }
+
+        if self.streaks.longest_daily.is_some() || self.streaks.longest_weekly.is_some() {
+            let _ = writeln!(md, "\n## Streaks\n");
+            if let Some(streak) = &self.streaks.longest_daily {
+                let _ = writeln!(md, "- **Longest daily streak:** {} days ({} to {})", streak.length, streak.start, streak.end);
+            }
+            if let Some(streak) = &self.streaks.longest_weekly {
+                let _ = writeln!(md, "- **Longest weekly streak:** {} weeks ({} to {})", streak.length, streak.start, streak.end);
+            }
+        }
+
+        if !self.top_places.is_empty() {
+            let _ = writeln!(md, "\n## Most-Visited Places\n");
+            for place in &self.top_places {
+                let _ = writeln!(md, "- **{}:** {} visits, {:.1} km", place.name, place.visits, place.total_distance_km);
+            }
+        }
+
+        if let Some(weather) = &self.weather_extremes {
+            let _ = writeln!(md, "\n## Weather Extremes\n");
+            if let Some(record) = &weather.hottest {
+                let _ = writeln!(md, "- **Hottest:** {:.1}°C - {}", record.conditions.temperature_celsius, record.activity_name);
+            }
+            if let Some(record) = &weather.coldest {
+                let _ = writeln!(md, "- **Coldest:** {:.1}°C - {}", record.conditions.temperature_celsius, record.activity_name);
+            }
+            if let Some(record) = &weather.windiest {
+                let _ = writeln!(
+                    md,
+                    "- **Windiest:** {:.0} km/h - {}",
+                    record.conditions.wind_speed_kmh.unwrap_or(0.0),
+                    record.activity_name
+                );
+            }
+            let _ = writeln!(md, "- **Rain or snow sessions:** {}", weather.wet_activities);
+        }
+
+        if !self.goal_outcomes.is_empty() {
+            let _ = writeln!(md, "\n## Goals\n");
+            for goal in &self.goal_outcomes {
+                let outcome = match goal.outcome {
+                    GoalOutcomeStatus::Achieved => "achieved",
+                    GoalOutcomeStatus::Missed => "missed",
+                    GoalOutcomeStatus::InProgress => "in progress",
+                };
+                let _ = writeln!(md, "- **{}:** {} ({:.0}%)", goal.title, outcome, goal.progress_percentage);
+            }
+        }
+
+        md
+    }
+}
+
+fn biggest_efforts(activities: &[Activity]) -> Vec<BiggestEffort> {
+    let categories: [(EffortCategory, &str, ActivityValue); 3] = [
+        (EffortCategory::LongestDistance, "km", |a| a.distance_meters.map(|d| d / 1000.0)),
+        (EffortCategory::LongestDuration, "h", |a| Some(a.duration_seconds as f64 / 3600.0)),
+        (EffortCategory::MostElevation, "m", |a| a.elevation_gain),
+    ];
+
+    categories
+        .iter()
+        .filter_map(|(category, unit, value_of)| {
+            let (activity, value) = best_by(activities.iter(), *value_of)?;
+            (value > 0.0).then(|| BiggestEffort {
+                category: *category,
+                activity_id: activity.id.clone(),
+                activity_name: activity.name.clone(),
+                sport_type: activity.sport_type.clone(),
+                date: activity.start_date,
+                value,
+                unit: (*unit).to_string(),
+            })
+        })
+        .collect()
+}
+
+fn personal_records(window: &ReportWindow, history: &[Activity], activities: &[Activity]) -> Vec<PeriodPersonalRecord> {
+    let record_types: [(&str, &str, ActivityValue); 4] = [
+        ("longest_distance", "km", |a| a.distance_meters.map(|d| d / 1000.0)),
+        ("longest_duration", "h", |a| Some(a.duration_seconds as f64 / 3600.0)),
+        ("most_elevation", "m", |a| a.elevation_gain),
+        ("fastest_average_speed", "km/h", |a| {
+            a.average_speed
+                .filter(|_| a.distance_meters.unwrap_or(0.0) >= MIN_SPEED_RECORD_DISTANCE_METERS)
+                .map(|speed| speed * 3.6)
+        }),
+    ];
+
+    let mut sports: Vec<&SportType> = Vec::new();
+    for activity in activities {
+        if !sports.contains(&&activity.sport_type) {
+            sports.push(&activity.sport_type);
+        }
+    }
+
+    let mut records = Vec::new();
+    for sport in sports {
+        let in_period = activities.iter().filter(|a| &a.sport_type == sport);
+        let before = history
+            .iter()
+            .filter(|a| &a.sport_type == sport && a.start_date < window.start());
+
+        for (record_type, unit, value_of) in &record_types {
+            // Records need an earlier best to beat
+            let Some((_, previous_best)) = best_by(before.clone(), *value_of) else {
+                continue;
+            };
+            let Some((activity, value)) = best_by(in_period.clone(), *value_of) else {
+                continue;
+            };
+            if value <= previous_best {
+                continue;
+            }
+
+            records.push(PeriodPersonalRecord {
+                activity_id: activity.id.clone(),
+                activity_name: activity.name.clone(),
+                sport_type: activity.sport_type.clone(),
+                date: activity.start_date,
+                record: PersonalRecord {
+                    record_type: (*record_type).to_string(),
+                    value,
+                    unit: (*unit).to_string(),
+                    previous_best: Some(previous_best),
+                    improvement_percentage: (previous_best > 0.0)
+                        .then(|| ((value - previous_best) / previous_best * 100.0) as f32),
+                },
+            });
+        }
+    }
+    records
+}
+
+fn period_streaks(window: &ReportWindow, activities: &[Activity], now: DateTime<Utc>) -> PeriodStreaks {
+    // Evaluate as of the end of the window so past periods aren't judged from today
+    let as_of = now.min(window.end() - Duration::seconds(1));
+    let longest = |period| {
+        StreakEngine::new(StreakCriteria {
+            period,
+            ..StreakCriteria::default()
+        })
+        .with_timezone(window.timezone)
+        .analyze(activities, as_of)
+        .longest_streak
+    };
+
+    PeriodStreaks {
+        longest_daily: longest(StreakPeriod::Day),
+        longest_weekly: longest(StreakPeriod::Week),
+    }
+}
+
+fn top_places(activities: &[Activity], locations: &HashMap<String, LocationData>) -> Vec<PlaceVisit> {
+    let mut places: Vec<PlaceVisit> = Vec::new();
+
+    for activity in activities {
+        // Geocoded locations win over what the provider reported
+        let (trail_name, city, region, country) = match locations.get(&activity.id) {
+            Some(location) => (&location.trail_name, &location.city, &location.region, &location.country),
+            None => (&activity.trail_name, &activity.city, &activity.region, &activity.country),
+        };
+        let Some(name) = trail_name.as_ref().or(city.as_ref()).or(region.as_ref()).or(country.as_ref()) else {
+            continue;
+        };
+        let distance_km = activity.distance_meters.unwrap_or(0.0) / 1000.0;
+
+        match places.iter_mut().find(|place| &place.name == name) {
+            Some(place) => {
+                place.visits += 1;
+                place.total_distance_km += distance_km;
+            }
+            None => places.push(PlaceVisit {
+                name: name.clone(),
+                city: city.clone(),
+                region: region.clone(),
+                country: country.clone(),
+                visits: 1,
+                total_distance_km: distance_km,
+            }),
+        }
+    }
+
+    places.sort_by(|a, b| {
+        b.visits
+            .cmp(&a.visits)
+            .then(b.total_distance_km.partial_cmp(&a.total_distance_km).unwrap_or(std::cmp::Ordering::Equal))
+            .then(a.name.cmp(&b.name))
+    });
+    places.truncate(TOP_ENTRIES);
+    places
+}
+
+fn weather_extremes(activities: &[Activity], weather: &HashMap<String, WeatherConditions>) -> Option<WeatherExtremes> {
+    let observed: Vec<(&Activity, &WeatherConditions)> = activities
+        .iter()
+        .filter_map(|a| weather.get(&a.id).map(|conditions| (a, conditions)))
+        .collect();
+    if observed.is_empty() {
+        return None;
+    }
+
+    let record = |entry: Option<&(&Activity, &WeatherConditions)>| {
+        entry.map(|(activity, conditions)| WeatherRecord {
+            activity_id: activity.id.clone(),
+            activity_name: activity.name.clone(),
+            date: activity.start_date,
+            conditions: (*conditions).clone(),
+        })
+    };
+    let by_temperature = |a: &&(&Activity, &WeatherConditions), b: &&(&Activity, &WeatherConditions)| {
+        a.1.temperature_celsius
+            .partial_cmp(&b.1.temperature_celsius)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    };
+
+    Some(WeatherExtremes {
+        activities_with_weather: observed.len(),
+        hottest: record(observed.iter().max_by(by_temperature)),
+        coldest: record(observed.iter().min_by(by_temperature)),
+        windiest: record(
+            observed
+                .iter()
+                .filter(|(_, conditions)| conditions.wind_speed_kmh.is_some())
+                .max_by(|a, b| {
+                    a.1.wind_speed_kmh
+                        .partial_cmp(&b.1.wind_speed_kmh)
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                }),
+        ),
+        wet_activities: observed
+            .iter()
+            .filter(|(_, conditions)| {
+                let conditions = conditions.conditions.to_lowercase();
+                conditions.contains("rain") || conditions.contains("snow")
+            })
+            .count(),
+    })
+}
+
+fn consistency_score(window: &ReportWindow, activities: &[Activity], now: DateTime<Utc>) -> ConsistencyScore {
+    let today = window.local_date(now);
+    let last_day = window.end_date.min(today).max(window.start_date);
+    let week_start = |date: NaiveDate| date - Duration::days(i64::from(date.weekday().num_days_from_monday()));
+
+    let mut weeks: Vec<NaiveDate> = Vec::new();
+    let mut week = week_start(window.start_date);
+    while week <= last_day {
+        weeks.push(week);
+        week += Duration::days(7);
+    }
+
+    let days: Vec<NaiveDate> = activities
+        .iter()
+        .map(|a| window.local_date(a.start_date))
+        .collect();
+    let active_days: BTreeSet<NaiveDate> = days.iter().copied().collect();
+    let weekly_counts: Vec<f64> = weeks
+        .iter()
+        .map(|week| days.iter().filter(|day| week_start(**day) == *week).count() as f64)
+        .collect();
+    let active_weeks = weekly_counts.iter().filter(|count| **count > 0.0).count();
+
+    let score = if activities.is_empty() {
+        0.0
+    } else {
+        let active_ratio = active_weeks as f64 / weeks.len() as f64;
+        let mean = weekly_counts.iter().sum::<f64>() / weekly_counts.len() as f64;
+        let variance = weekly_counts.iter().map(|c| (c - mean).powi(2)).sum::<f64>() / weekly_counts.len() as f64;
+        let evenness = (1.0 - variance.sqrt() / mean).clamp(0.0, 1.0);
+        ((ACTIVE_WEEK_WEIGHT * active_ratio + (1.0 - ACTIVE_WEEK_WEIGHT) * evenness) * 1000.0).round() / 10.0
+    };
+
+    ConsistencyScore {
+        active_days: active_days.len(),
+        active_weeks,
+        weeks_in_period: weeks.len(),
+        score,
+    }
+}
+
+/// Activity with the highest value, ignoring activities without one
+fn best_by<'a>(
+    activities: impl Iterator<Item = &'a Activity>,
+    value_of: ActivityValue,
+) -> Option<(&'a Activity, f64)> {
+    activities
+        .filter_map(|a| value_of(a).map(|value| (a, value)))
+        .max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+fn location_context(location: &LocationData) -> LocationContext {
+    LocationContext {
+        city: location.city.clone(),
+        region: location.region.clone(),
+        country: location.country.clone(),
+        trail_name: location.trail_name.clone(),
+        terrain_type: location.natural.clone(),
+        display_name: location.display_name.clone(),
+    }
+}
+
+fn first_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).unwrap_or_default()
+}
+
+fn last_of_month(year: i32, month: u32) -> NaiveDate {
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    first_of_month(next_year, next_month) - Duration::days(1)
+}
+
+/// Start of `date` in `timezone`. Where a DST change skips midnight the day
+/// starts when the clocks resume, an hour later
+fn local_midnight(date: NaiveDate, timezone: Tz) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap_or_default();
+    timezone
+        .from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| timezone.from_local_datetime(&(midnight + Duration::hours(1))).earliest())
+        .map(|local| local.with_timezone(&Utc))
+        .unwrap_or_else(|| Utc.from_utc_datetime(&midnight))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intelligence::{GoalType, TimeFrame};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn goal(title: &str, target_value: f64, target_date: DateTime<Utc>) -> Goal {
+        Goal {
+            id: format!("goal-{}", title),
+            user_id: "user".to_string(),
+            title: title.to_string(),
+            description: String::new(),
+            goal_type: GoalType::Distance { sport: "run".to_string(), timeframe: TimeFrame::Month },
+            target_value,
+            target_date,
+            current_value: 0.0,
+            created_at: target_date,
+            updated_at: target_date,
+            status: GoalStatus::Active,
+        }
+    }
+
+    fn activity(id: &str, sport_type: SportType, day: NaiveDate, distance_km: f64) -> Activity {
+        Activity {
+            id: id.to_string(),
+            name: format!("Activity {id}"),
+            sport_type,
+            start_date: Utc.from_utc_datetime(&day.and_hms_opt(8, 0, 0).unwrap()),
+            duration_seconds: (distance_km * 360.0) as u64,
+            distance_meters: Some(distance_km * 1000.0),
+            ..Activity::default()
+        }
+    }
+
+    #[test]
+    fn test_report_windows() {
+        let month = ReportPeriod::Month.window(date(2024, 2, 14), Tz::UTC);
+        assert_eq!((month.start_date, month.end_date), (date(2024, 2, 1), date(2024, 2, 29)));
+        assert_eq!(month.label, "February 2024");
+
+        let winter = ReportPeriod::Season.window(date(2025, 1, 10), Tz::UTC);
+        assert_eq!((winter.start_date, winter.end_date), (date(2024, 12, 1), date(2025, 2, 28)));
+        assert_eq!(winter.label, "Winter 2024-25");
+
+        let autumn = ReportPeriod::Season.window(date(2024, 10, 1), Tz::UTC);
+        assert_eq!((autumn.start_date, autumn.end_date), (date(2024, 9, 1), date(2024, 11, 30)));
+
+        let week = ReportPeriod::Week.window(date(2024, 5, 16), Tz::UTC);
+        assert_eq!((week.start_date, week.end_date), (date(2024, 5, 13), date(2024, 5, 19)));
+
+        let year = ReportPeriod::Year.window(date(2024, 5, 16), Tz::UTC);
+        assert_eq!((year.start_date, year.end_date, year.label.as_str()), (date(2024, 1, 1), date(2024, 12, 31), "2024"));
+        assert!(year.contains(year.start()));
+        assert!(!year.contains(year.end()));
+    }
+
+    #[test]
+    fn test_report_window_follows_the_users_timezone() {
+        // Early morning of March 1st in Tokyo is still February in UTC
+        let late_february = Utc.with_ymd_and_hms(2024, 2, 29, 16, 0, 0).unwrap();
+
+        let utc = ReportPeriod::Month.window(date(2024, 3, 1), Tz::UTC);
+        assert!(!utc.contains(late_february));
+
+        let tokyo = ReportPeriod::Month.window(date(2024, 3, 1), chrono_tz::Asia::Tokyo);
+        assert!(tokyo.contains(late_february));
+        assert_eq!(tokyo.start(), Utc.with_ymd_and_hms(2024, 2, 29, 15, 0, 0).unwrap());
+        assert_eq!(tokyo.local_date(late_february), date(2024, 3, 1));
+    }
+
+    #[test]
+    fn test_report_window_starts_after_a_skipped_midnight() {
+        // Santiago skipped from 00:00 to 01:00 (-04:00 to -03:00) on 2022-09-11
+        let santiago = chrono_tz::America::Santiago;
+        assert!(santiago.from_local_datetime(&date(2022, 9, 11).and_hms_opt(0, 0, 0).unwrap()).earliest().is_none());
+        assert_eq!(local_midnight(date(2022, 9, 11), santiago), Utc.with_ymd_and_hms(2022, 9, 11, 4, 0, 0).unwrap());
+        assert_eq!(local_midnight(date(2022, 9, 12), santiago), Utc.with_ymd_and_hms(2022, 9, 12, 3, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_period_report_totals_records_and_places() {
+        let history = vec![
+            activity("old_run", SportType::Run, date(2024, 2, 10), 15.0),
+            activity("run1", SportType::Run, date(2024, 3, 4), 10.0),
+            Activity {
+                city: Some("Montreal".to_string()),
+                ..activity("run2", SportType::Run, date(2024, 3, 5), 21.1)
+            },
+            Activity {
+                city: Some("Montreal".to_string()),
+                ..activity("run3", SportType::Run, date(2024, 3, 6), 8.0)
+            },
+            Activity {
+                elevation_gain: Some(900.0),
+                ..activity("ride", SportType::Ride, date(2024, 3, 20), 60.0)
+            },
+            activity("april_run", SportType::Run, date(2024, 4, 2), 30.0),
+        ];
+        let window = ReportPeriod::Month.window(date(2024, 3, 1), Tz::UTC);
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+
+        let report = PeriodReportGenerator::new().generate(&window, &history, &ReportContext::default(), now);
+
+        assert_eq!(report.activity_count, 4);
+        assert!((report.total_distance_km - 99.1).abs() < 1e-6);
+        assert_eq!(report.totals_by_sport.len(), 2);
+
+        let longest = report
+            .biggest_efforts
+            .iter()
+            .find(|e| e.category == EffortCategory::LongestDistance)
+            .unwrap();
+        assert_eq!(longest.activity_id, "ride");
+
+        // The 21.1 km run beats February's 15 km; the ride has no earlier history
+        let distance_pr = report
+            .personal_records
+            .iter()
+            .find(|pr| pr.record.record_type == "longest_distance")
+            .unwrap();
+        assert_eq!(distance_pr.activity_id, "run2");
+        assert_eq!(distance_pr.record.previous_best, Some(15.0));
+        assert!(report.personal_records.iter().all(|pr| pr.sport_type == SportType::Run));
+
+        assert_eq!(report.top_places[0].name, "Montreal");
+        assert_eq!(report.top_places[0].visits, 2);
+
+        assert_eq!(report.streaks.longest_daily.as_ref().unwrap().length, 3);
+        assert_eq!(report.consistency.active_days, 4);
+        assert!(report.consistency.score > 0.0 && report.consistency.score <= 100.0);
+        assert!(report.weather_extremes.is_none());
+        assert!(report.highlights[0].contains("4 activities"));
+    }
+
+    #[test]
+    fn test_weather_extremes_and_markdown() {
+        let history = vec![
+            activity("hot", SportType::Run, date(2024, 7, 2), 10.0),
+            activity("wet", SportType::Run, date(2024, 7, 9), 12.0),
+        ];
+        let mut context = ReportContext::default();
+        context.weather.insert(
+            "hot".to_string(),
+            WeatherConditions {
+                temperature_celsius: 31.0,
+                humidity_percentage: Some(60.0),
+                wind_speed_kmh: Some(5.0),
+                conditions: "sunny".to_string(),
+            },
+        );
+        context.weather.insert(
+            "wet".to_string(),
+            WeatherConditions {
+                temperature_celsius: 18.0,
+                humidity_percentage: Some(95.0),
+                wind_speed_kmh: Some(25.0),
+                conditions: "light rain".to_string(),
+            },
+        );
+        let mut summer_goal = goal("Summer 100k", 100.0, Utc.with_ymd_and_hms(2024, 7, 31, 0, 0, 0).unwrap());
+        summer_goal.current_value = 100.0;
+        context.goals = vec![GoalOutcome::from_goal(&summer_goal, Utc::now())];
+
+        let window = ReportPeriod::Month.window(date(2024, 7, 1), Tz::UTC);
+        let report = PeriodReportGenerator::new().generate(&window, &history, &context, Utc::now());
+
+        let weather = report.weather_extremes.as_ref().unwrap();
+        assert_eq!(weather.hottest.as_ref().unwrap().activity_id, "hot");
+        assert_eq!(weather.coldest.as_ref().unwrap().activity_id, "wet");
+        assert_eq!(weather.windiest.as_ref().unwrap().activity_id, "wet");
+        assert_eq!(weather.wet_activities, 1);
+        assert_eq!(report.goal_outcomes[0].outcome, GoalOutcomeStatus::Achieved);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Monthly: July 2024"));
+        assert!(markdown.contains("## Totals by Sport"));
+        assert!(markdown.contains("## Weather Extremes"));
+        assert!(markdown.contains("**Summer 100k:** achieved"));
+    }
+
+    #[test]
+    fn test_goal_outcome_missed_and_empty_report() {
+        let now = Utc.with_ymd_and_hms(2024, 9, 1, 0, 0, 0).unwrap();
+        let mut marathon = goal("Marathon", 42.2, Utc.with_ymd_and_hms(2024, 8, 15, 0, 0, 0).unwrap());
+        marathon.current_value = 30.0;
+        assert_eq!(GoalOutcome::from_goal(&marathon, now).outcome, GoalOutcomeStatus::Missed);
+
+        marathon.status = GoalStatus::Completed;
+        assert_eq!(GoalOutcome::from_goal(&marathon, now).outcome, GoalOutcomeStatus::Achieved);
+
+        let window = ReportPeriod::Week.window(date(2024, 8, 12), Tz::UTC);
+        let report = PeriodReportGenerator::new().generate(&window, &[], &ReportContext::default(), now);
+        assert_eq!(report.activity_count, 0);
+        assert_eq!(report.consistency.score, 0.0);
+        assert!(report.highlights[0].starts_with("No activities"));
+    }
+}
@@ -1,9 +1,15 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
 //! Training recommendation engine for personalized insights
 
 use super::*;
-use crate::models::Activity;
+use crate::models::{Activity, SportType};
 use anyhow::Result;
-use chrono::{DateTime, Utc, Duration};
+use chrono::{Utc, Duration};
 use std::collections::HashMap;
 
 /// Trait for generating training recommendations
@@ -20,9 +26,13 @@ pub trait RecommendationEngineTrait {
     
     /// Generate equipment recommendations
     async fn generate_equipment_recommendations(&self, user_profile: &UserFitnessProfile, activities: &[Activity]) -> Result<Vec<TrainingRecommendation>>;
+
+    /// Generate a periodized plan from today towards a target event
+    async fn generate_training_plan(&self, user_profile: &UserFitnessProfile, event: TargetEvent, activities: &[Activity]) -> Result<TrainingPlan>;
 }
 
 /// Advanced recommendation engine implementation
+#[derive(Default)]
 pub struct AdvancedRecommendationEngine {
     user_profile: Option<UserFitnessProfile>,
 }
@@ -44,46 +54,32 @@ impl AdvancedRecommendationEngine {
 
     /// Analyze training patterns to identify areas for improvement
     fn analyze_training_patterns(&self, activities: &[Activity]) -> TrainingPatternAnalysis {
-        let recent_activities: Vec<_> = activities
+        let since = Utc::now() - Duration::weeks(4);
+        let recent_activities: Vec<Activity> = activities
             .iter()
-            .filter(|a| {
-                if let Some(start_time) = &a.start_date_local {
-                    if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                        let activity_utc = activity_date.with_timezone(&Utc);
-                        let weeks_ago = (Utc::now() - activity_utc).num_weeks();
-                        return weeks_ago <= 4; // Last 4 weeks
-                    }
-                }
-                false
-            })
+            .filter(|a| a.start_date >= since) // Last 4 weeks
+            .cloned()
             .collect();
 
         let mut sport_frequency: HashMap<String, usize> = HashMap::new();
         let mut weekly_load = 0.0;
         let mut high_intensity_count = 0;
-        let mut low_intensity_count = 0;
-        let mut total_duration = 0;
 
         for activity in &recent_activities {
-            *sport_frequency.entry(activity.sport_type.clone()).or_insert(0) += 1;
-            
-            if let Some(duration) = activity.moving_time {
-                weekly_load += duration as f64 / 3600.0; // Hours
-                total_duration += duration;
-            }
-            
-            if let Some(avg_hr) = activity.average_heartrate {
-                if avg_hr > 160.0 {
+            *sport_frequency.entry(activity.sport_type.key()).or_insert(0) += 1;
+
+            weekly_load += activity.duration_seconds as f64 / 3600.0; // Hours
+
+            if let Some(avg_hr) = activity.average_heart_rate {
+                if avg_hr > 160 {
                     high_intensity_count += 1;
-                } else if avg_hr < 130.0 {
-                    low_intensity_count += 1;
                 }
             }
         }
 
         weekly_load /= 4.0; // Average per week
         
-        let intensity_balance = if recent_activities.len() > 0 {
+        let intensity_balance = if !recent_activities.is_empty() {
             high_intensity_count as f64 / recent_activities.len() as f64
         } else {
             0.0
@@ -101,14 +97,8 @@ impl AdvancedRecommendationEngine {
 
         TrainingPatternAnalysis {
             weekly_load_hours: weekly_load,
-            sport_diversity: sport_frequency.len(),
             intensity_balance,
             consistency_score,
-            primary_sport: sport_frequency
-                .iter()
-                .max_by_key(|(_, &count)| count)
-                .map(|(sport, _)| sport.clone())
-                .unwrap_or_else(|| "Unknown".to_string()),
             training_gaps: self.identify_training_gaps(&recent_activities),
         }
     }
@@ -122,43 +112,31 @@ impl AdvancedRecommendationEngine {
             return gaps;
         }
         
-        let mut sorted_activities = activities.to_vec();
-        sorted_activities.sort_by(|a, b| {
-            let date_a = a.start_date_local.as_ref().and_then(|d| DateTime::parse_from_rfc3339(d).ok());
-            let date_b = b.start_date_local.as_ref().and_then(|d| DateTime::parse_from_rfc3339(d).ok());
-            date_a.cmp(&date_b)
-        });
+        let mut start_dates: Vec<_> = activities.iter().map(|a| a.start_date).collect();
+        start_dates.sort();
 
-        for i in 1..sorted_activities.len() {
-            if let (Some(prev_date_str), Some(curr_date_str)) = 
-                (&sorted_activities[i-1].start_date_local, &sorted_activities[i].start_date_local) {
-                if let (Ok(prev_date), Ok(curr_date)) = 
-                    (DateTime::parse_from_rfc3339(prev_date_str), DateTime::parse_from_rfc3339(curr_date_str)) {
-                    let gap_days = (curr_date - prev_date).num_days();
-                    
-                    if gap_days > 7 {
-                        gaps.push(TrainingGap {
-                            gap_type: GapType::LongRest,
-                            duration_days: gap_days,
-                            description: format!("{} days without training", gap_days),
-                            severity: if gap_days > 14 { InsightSeverity::Warning } else { InsightSeverity::Info },
-                        });
-                    }
-                }
+        for pair in start_dates.windows(2) {
+            let gap_days = (pair[1] - pair[0]).num_days();
+
+            if gap_days > 7 {
+                gaps.push(TrainingGap {
+                    gap_type: GapType::LongRest,
+                    duration_days: gap_days,
+                    description: format!("{} days without training", gap_days),
+                });
             }
         }
 
         // Check for missing training types
-        let sports: std::collections::HashSet<_> = activities.iter().map(|a| &a.sport_type).collect();
-        
+        let sports: std::collections::HashSet<_> = activities.iter().map(|a| a.sport_type.key()).collect();
+
         if let Some(profile) = &self.user_profile {
             for primary_sport in &profile.primary_sports {
-                if !sports.contains(&primary_sport) {
+                if !sports.contains(&SportType::from_name(primary_sport).key()) {
                     gaps.push(TrainingGap {
                         gap_type: GapType::MissingSport,
                         duration_days: 0,
                         description: format!("Missing {} training in recent activities", primary_sport),
-                        severity: InsightSeverity::Info,
                     });
                 }
             }
@@ -388,28 +366,21 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
         let mut recommendations = Vec::new();
         
         // Analyze recent training load
-        let recent_activities: Vec<_> = activities
+        let since = Utc::now() - Duration::days(7);
+        let recent_activities: Vec<Activity> = activities
             .iter()
-            .filter(|a| {
-                if let Some(start_time) = &a.start_date_local {
-                    if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                        let activity_utc = activity_date.with_timezone(&Utc);
-                        let days_ago = (Utc::now() - activity_utc).num_days();
-                        return days_ago <= 7; // Last week
-                    }
-                }
-                false
-            })
+            .filter(|a| a.start_date >= since) // Last week
+            .cloned()
             .collect();
 
-        let total_duration: i32 = recent_activities
+        let total_duration: u64 = recent_activities
             .iter()
-            .filter_map(|a| a.moving_time)
+            .map(|a| a.duration_seconds)
             .sum();
 
         let high_intensity_sessions = recent_activities
             .iter()
-            .filter(|a| a.average_heartrate.unwrap_or(0.0) > 160.0)
+            .filter(|a| a.average_heart_rate.unwrap_or(0) > 160)
             .count();
 
         // Check if recovery is needed
@@ -455,8 +426,8 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
     async fn generate_nutrition_recommendations(&self, activity: &Activity) -> Result<Vec<TrainingRecommendation>> {
         let mut recommendations = Vec::new();
         
-        let duration_hours = activity.moving_time.unwrap_or(0) as f64 / 3600.0;
-        let high_intensity = activity.average_heartrate.unwrap_or(0.0) > 150.0;
+        let duration_hours = activity.duration_seconds as f64 / 3600.0;
+        let high_intensity = activity.average_heart_rate.unwrap_or(0) > 150;
 
         // Pre-activity nutrition
         if duration_hours > 1.5 {
@@ -515,17 +486,17 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
         Ok(recommendations)
     }
 
-    async fn generate_equipment_recommendations(&self, user_profile: &UserFitnessProfile, activities: &[Activity]) -> Result<Vec<TrainingRecommendation>> {
+    async fn generate_equipment_recommendations(&self, _user_profile: &UserFitnessProfile, activities: &[Activity]) -> Result<Vec<TrainingRecommendation>> {
         let mut recommendations = Vec::new();
         
         // Analyze primary sports
         let mut sport_counts: HashMap<String, usize> = HashMap::new();
         for activity in activities {
-            *sport_counts.entry(activity.sport_type.clone()).or_insert(0) += 1;
+            *sport_counts.entry(activity.sport_type.key()).or_insert(0) += 1;
         }
 
         // Running-specific equipment
-        if sport_counts.get("Run").unwrap_or(&0) > &5 {
+        if sport_counts.get("run").unwrap_or(&0) > &5 {
             recommendations.push(TrainingRecommendation {
                 recommendation_type: RecommendationType::Equipment,
                 title: "Running Equipment Optimization".to_string(),
@@ -543,7 +514,7 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
         }
 
         // Cycling-specific equipment
-        if sport_counts.get("Ride").unwrap_or(&0) > &5 {
+        if sport_counts.get("ride").unwrap_or(&0) > &5 {
             recommendations.push(TrainingRecommendation {
                 recommendation_type: RecommendationType::Equipment,
                 title: "Cycling Equipment Optimization".to_string(),
@@ -561,7 +532,7 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
         }
 
         // General monitoring equipment
-        let has_hr_data = activities.iter().any(|a| a.average_heartrate.is_some());
+        let has_hr_data = activities.iter().any(|a| a.average_heart_rate.is_some());
         if !has_hr_data && activities.len() > 5 {
             recommendations.push(TrainingRecommendation {
                 recommendation_type: RecommendationType::Equipment,
@@ -581,6 +552,10 @@ impl RecommendationEngineTrait for AdvancedRecommendationEngine {
 
         Ok(recommendations)
     }
+
+    async fn generate_training_plan(&self, user_profile: &UserFitnessProfile, event: TargetEvent, activities: &[Activity]) -> Result<TrainingPlan> {
+        TrainingPlan::generate(&user_profile.user_id, user_profile, event, activities, Utc::now().date_naive())
+    }
 }
 
 impl AdvancedRecommendationEngine {
@@ -588,30 +563,21 @@ impl AdvancedRecommendationEngine {
     fn count_consecutive_training_days(&self, activities: &[Activity]) -> usize {
         let mut consecutive = 0;
         let mut current_date = Utc::now().date_naive();
-        
-        // Sort activities by date (most recent first)
-        let mut sorted_activities = activities.to_vec();
-        sorted_activities.sort_by(|a, b| {
-            let date_a = a.start_date_local.as_ref().and_then(|d| DateTime::parse_from_rfc3339(d).ok());
-            let date_b = b.start_date_local.as_ref().and_then(|d| DateTime::parse_from_rfc3339(d).ok());
-            date_b.cmp(&date_a) // Reverse order (newest first)
-        });
 
-        for activity in sorted_activities {
-            if let Some(start_time) = &activity.start_date_local {
-                if let Ok(activity_date) = DateTime::parse_from_rfc3339(start_time) {
-                    let activity_naive = activity_date.naive_utc().date();
-                    
-                    if activity_naive == current_date || activity_naive == current_date - chrono::naive::Days::new(1) {
-                        consecutive += 1;
-                        current_date = activity_naive - chrono::naive::Days::new(1);
-                    } else {
-                        break;
-                    }
-                }
+        // Distinct training days, most recent first
+        let mut training_days: Vec<_> = activities.iter().map(|a| a.start_date.date_naive()).collect();
+        training_days.sort_by(|a, b| b.cmp(a));
+        training_days.dedup();
+
+        for day in training_days {
+            if day == current_date || day == current_date - chrono::naive::Days::new(1) {
+                consecutive += 1;
+                current_date = day - chrono::naive::Days::new(1);
+            } else {
+                break;
             }
         }
-        
+
         consecutive
     }
 }
@@ -620,10 +586,8 @@ impl AdvancedRecommendationEngine {
 #[derive(Debug)]
 struct TrainingPatternAnalysis {
     weekly_load_hours: f64,
-    sport_diversity: usize,
     intensity_balance: f64,
     consistency_score: f64,
-    primary_sport: String,
     training_gaps: Vec<TrainingGap>,
 }
 
@@ -633,7 +597,6 @@ struct TrainingGap {
     gap_type: GapType,
     duration_days: i64,
     description: String,
-    severity: InsightSeverity,
 }
 
 /// Types of training gaps
@@ -675,14 +638,13 @@ mod tests {
         // Create sample activities with high intensity
         let mut activities = Vec::new();
         for i in 0..10 {
-            let mut activity = Activity::default();
-            activity.sport_type = "Run".to_string();
-            activity.average_heartrate = Some(170.0); // High intensity
-            activity.moving_time = Some(3600); // 1 hour
-            activity.start_date_local = Some(
-                (Utc::now() - Duration::days(i * 2)).to_rfc3339()
-            );
-            activities.push(activity);
+            activities.push(Activity {
+                sport_type: SportType::Run,
+                average_heart_rate: Some(170), // High intensity
+                duration_seconds: 3600, // 1 hour
+                start_date: Utc::now() - Duration::days(i * 2),
+                ..Activity::default()
+            });
         }
         
         let result = engine.generate_recommendations(&profile, &activities).await;
@@ -702,13 +664,12 @@ mod tests {
         // Create high load activities
         let mut activities = Vec::new();
         for i in 0..7 {
-            let mut activity = Activity::default();
-            activity.average_heartrate = Some(170.0);
-            activity.moving_time = Some(7200); // 2 hours each
-            activity.start_date_local = Some(
-                (Utc::now() - Duration::days(i)).to_rfc3339()
-            );
-            activities.push(activity);
+            activities.push(Activity {
+                average_heart_rate: Some(170),
+                duration_seconds: 7200, // 2 hours each
+                start_date: Utc::now() - Duration::days(i),
+                ..Activity::default()
+            });
         }
         
         let result = engine.generate_recovery_recommendations(&activities).await;
@@ -0,0 +1,471 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Streak and consistency analytics over an activity history
+
+use super::InsightSeverity;
+use crate::models::{Activity, SportType};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Calendar period a streak is counted in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreakPeriod {
+    Day,
+    Week,
+}
+
+impl StreakPeriod {
+    /// Parse a period from its tool argument name
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "day" | "daily" | "days" => Some(Self::Day),
+            "week" | "weekly" | "weeks" => Some(Self::Week),
+            _ => None,
+        }
+    }
+
+    /// First day of the period containing `date` (weeks start on Monday)
+    fn period_start(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Self::Day => date,
+            Self::Week => date - Duration::days(date.weekday().num_days_from_monday() as i64),
+        }
+    }
+
+    fn step(&self) -> Duration {
+        match self {
+            Self::Day => Duration::days(1),
+            Self::Week => Duration::weeks(1),
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Day => "day",
+            Self::Week => "week",
+        }
+    }
+}
+
+/// What counts towards a streak
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakCriteria {
+    pub period: StreakPeriod,
+    /// Only count activities of this sport
+    pub sport_type: Option<SportType>,
+    /// Per-activity minimum distance
+    pub min_distance_meters: Option<f64>,
+    /// Per-activity minimum duration
+    pub min_duration_seconds: Option<u64>,
+    /// Qualifying activities required per period
+    pub min_activities: u32,
+    /// Total distance required per period
+    pub min_total_distance_meters: Option<f64>,
+    /// Total duration required per period
+    pub min_total_duration_seconds: Option<u64>,
+}
+
+impl Default for StreakCriteria {
+    fn default() -> Self {
+        Self {
+            period: StreakPeriod::Day,
+            sport_type: None,
+            min_distance_meters: None,
+            min_duration_seconds: None,
+            min_activities: 1,
+            min_total_distance_meters: None,
+            min_total_duration_seconds: None,
+        }
+    }
+}
+
+impl StreakCriteria {
+    /// Check an individual activity against the sport and per-activity predicates
+    fn activity_qualifies(&self, activity: &Activity) -> bool {
+        if self.sport_type.as_ref().is_some_and(|sport| *sport != activity.sport_type) {
+            return false;
+        }
+        if self.min_distance_meters.is_some_and(|min| activity.distance_meters.unwrap_or(0.0) < min) {
+            return false;
+        }
+        if self.min_duration_seconds.is_some_and(|min| activity.duration_seconds < min) {
+            return false;
+        }
+        true
+    }
+
+    /// Check period totals against the per-period thresholds
+    fn period_qualifies(&self, totals: &PeriodTotals) -> bool {
+        totals.activity_count >= self.min_activities
+            && self.min_total_distance_meters.is_none_or(|min| totals.distance_meters >= min)
+            && self.min_total_duration_seconds.is_none_or(|min| totals.duration_seconds >= min)
+    }
+
+    /// Describe what is still missing for a period to qualify
+    fn remaining(&self, totals: &PeriodTotals) -> Vec<String> {
+        let mut remaining = Vec::new();
+        if totals.activity_count < self.min_activities {
+            remaining.push(format!("{} more activit{}", self.min_activities - totals.activity_count,
+                if self.min_activities - totals.activity_count == 1 { "y" } else { "ies" }));
+        }
+        if let Some(min) = self.min_total_distance_meters {
+            if totals.distance_meters < min {
+                remaining.push(format!("{:.1} km more", (min - totals.distance_meters) / 1000.0));
+            }
+        }
+        if let Some(min) = self.min_total_duration_seconds {
+            if totals.duration_seconds < min {
+                remaining.push(format!("{} more minutes", (min - totals.duration_seconds).div_ceil(60)));
+            }
+        }
+        remaining
+    }
+}
+
+/// Totals of qualifying activities within one period
+#[derive(Debug, Clone, Default)]
+struct PeriodTotals {
+    activity_count: u32,
+    distance_meters: f64,
+    duration_seconds: u64,
+    activity_ids: Vec<String>,
+}
+
+/// A run of consecutive qualifying periods
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Streak {
+    /// First day of the first period
+    pub start: NaiveDate,
+    /// First day of the last period
+    pub end: NaiveDate,
+    /// Number of consecutive periods
+    pub length: u32,
+    pub activity_count: u32,
+    pub total_distance_km: f64,
+    pub total_duration_hours: f64,
+    pub activity_ids: Vec<String>,
+}
+
+/// Warning about a streak that is about to end or a record within reach
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakAlert {
+    pub severity: InsightSeverity,
+    pub message: String,
+}
+
+/// Result of a streak analysis
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreakReport {
+    pub criteria: StreakCriteria,
+    /// Streak that is still alive as of the analysis date
+    pub current_streak: Option<Streak>,
+    pub longest_streak: Option<Streak>,
+    /// Number of periods that met the criteria
+    pub qualifying_periods: usize,
+    pub alerts: Vec<StreakAlert>,
+}
+
+/// Computes streaks of consecutive days or weeks meeting a set of criteria
+pub struct StreakEngine {
+    criteria: StreakCriteria,
+    /// Timezone whose calendar days the streak is counted in
+    timezone: Tz,
+}
+
+impl StreakEngine {
+    /// Create a streak engine for the given criteria, counting UTC days
+    pub fn new(criteria: StreakCriteria) -> Self {
+        Self { criteria, timezone: Tz::UTC }
+    }
+
+    /// Count days and weeks in the user's timezone
+    pub fn with_timezone(mut self, timezone: Tz) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// Analyze streaks in the activity history as of `now`
+    pub fn analyze(&self, activities: &[Activity], now: DateTime<Utc>) -> StreakReport {
+        let period = self.criteria.period;
+        let mut periods: BTreeMap<NaiveDate, PeriodTotals> = BTreeMap::new();
+
+        for activity in activities.iter().filter(|a| self.criteria.activity_qualifies(a)) {
+            let day = activity.start_date.with_timezone(&self.timezone).date_naive();
+            let totals = periods.entry(period.period_start(day)).or_default();
+            totals.activity_count += 1;
+            totals.distance_meters += activity.distance_meters.unwrap_or(0.0);
+            totals.duration_seconds += activity.duration_seconds;
+            totals.activity_ids.push(activity.id.clone());
+        }
+
+        let current_period = period.period_start(now.with_timezone(&self.timezone).date_naive());
+        let previous_period = current_period - period.step();
+
+        let streaks = self.build_streaks(&periods);
+        let longest_streak = streaks
+            .iter()
+            .max_by(|a, b| a.length.cmp(&b.length).then(a.end.cmp(&b.end)))
+            .cloned();
+        let current_streak = streaks
+            .last()
+            .filter(|streak| streak.end == current_period || streak.end == previous_period)
+            .cloned();
+
+        let alerts = self.build_alerts(
+            current_streak.as_ref(),
+            longest_streak.as_ref(),
+            periods.get(&current_period),
+            current_period,
+        );
+
+        StreakReport {
+            criteria: self.criteria.clone(),
+            current_streak,
+            longest_streak,
+            qualifying_periods: periods.values().filter(|t| self.criteria.period_qualifies(t)).count(),
+            alerts,
+        }
+    }
+
+    /// Group qualifying periods into runs of consecutive periods, oldest first
+    fn build_streaks(&self, periods: &BTreeMap<NaiveDate, PeriodTotals>) -> Vec<Streak> {
+        let step = self.criteria.period.step();
+        let mut streaks: Vec<Streak> = Vec::new();
+
+        for (start, totals) in periods.iter().filter(|(_, t)| self.criteria.period_qualifies(t)) {
+            match streaks.last_mut() {
+                Some(streak) if streak.end + step == *start => {
+                    streak.end = *start;
+                    streak.length += 1;
+                    streak.activity_count += totals.activity_count;
+                    streak.total_distance_km += totals.distance_meters / 1000.0;
+                    streak.total_duration_hours += totals.duration_seconds as f64 / 3600.0;
+                    streak.activity_ids.extend(totals.activity_ids.iter().cloned());
+                }
+                _ => streaks.push(Streak {
+                    start: *start,
+                    end: *start,
+                    length: 1,
+                    activity_count: totals.activity_count,
+                    total_distance_km: totals.distance_meters / 1000.0,
+                    total_duration_hours: totals.duration_seconds as f64 / 3600.0,
+                    activity_ids: totals.activity_ids.clone(),
+                }),
+            }
+        }
+
+        streaks
+    }
+
+    /// Generate streak-risk and record alerts
+    fn build_alerts(
+        &self,
+        current: Option<&Streak>,
+        longest: Option<&Streak>,
+        current_totals: Option<&PeriodTotals>,
+        current_period: NaiveDate,
+    ) -> Vec<StreakAlert> {
+        let mut alerts = Vec::new();
+        let Some(current) = current else {
+            return alerts;
+        };
+        let label = self.criteria.period.label();
+
+        // The streak is alive but the current period has not qualified yet
+        if current.end != current_period {
+            let remaining = self
+                .criteria
+                .remaining(&current_totals.cloned().unwrap_or_default());
+            let deadline = match self.criteria.period {
+                StreakPeriod::Day => "today".to_string(),
+                StreakPeriod::Week => format!("by {}", (current_period + Duration::days(6)).format("%A %Y-%m-%d")),
+            };
+
+            alerts.push(StreakAlert {
+                severity: if current.length >= 7 { InsightSeverity::Critical } else { InsightSeverity::Warning },
+                message: format!(
+                    "Your {}-{} streak ends unless you complete {} {}",
+                    current.length,
+                    label,
+                    remaining.join(" and "),
+                    deadline
+                ),
+            });
+        }
+
+        if let Some(longest) = longest {
+            let to_beat = longest.length.saturating_sub(current.length) + 1;
+            if longest.end != current.end && to_beat <= 3 {
+                alerts.push(StreakAlert {
+                    severity: InsightSeverity::Info,
+                    message: format!(
+                        "{} more qualifying {}{} to beat your longest streak of {}",
+                        to_beat,
+                        label,
+                        if to_beat == 1 { "" } else { "s" },
+                        longest.length
+                    ),
+                });
+            }
+        }
+
+        alerts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn run_on(id: &str, date: NaiveDate, distance_m: f64) -> Activity {
+        Activity {
+            id: id.to_string(),
+            sport_type: SportType::Run,
+            start_date: Tz::UTC
+                .with_ymd_and_hms(date.year(), date.month(), date.day(), 8, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc),
+            distance_meters: Some(distance_m),
+            duration_seconds: 3600,
+            ..Activity::default()
+        }
+    }
+
+    fn at_noon(date: NaiveDate) -> DateTime<Utc> {
+        Tz::UTC
+            .with_ymd_and_hms(date.year(), date.month(), date.day(), 12, 0, 0)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn day(n: i64) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 5, 1).unwrap() + Duration::days(n)
+    }
+
+    #[test]
+    fn test_period_parse() {
+        assert_eq!(StreakPeriod::parse("daily"), Some(StreakPeriod::Day));
+        assert_eq!(StreakPeriod::parse("week"), Some(StreakPeriod::Week));
+        assert_eq!(StreakPeriod::parse("month"), None);
+    }
+
+    #[test]
+    fn test_daily_distance_streak() {
+        let criteria = StreakCriteria {
+            sport_type: Some(SportType::Run),
+            min_distance_meters: Some(10_000.0),
+            ..StreakCriteria::default()
+        };
+        let activities = vec![
+            run_on("a", day(0), 10_500.0),
+            run_on("b", day(1), 12_000.0),
+            run_on("c", day(2), 11_000.0),
+            run_on("short", day(3), 5_000.0),
+            run_on("d", day(4), 10_000.0),
+            run_on("e", day(5), 10_000.0),
+        ];
+
+        let report = StreakEngine::new(criteria).analyze(&activities, at_noon(day(5)));
+
+        let longest = report.longest_streak.unwrap();
+        assert_eq!(longest.length, 3);
+        assert_eq!(longest.start, day(0));
+        assert_eq!(longest.activity_ids, vec!["a", "b", "c"]);
+
+        let current = report.current_streak.unwrap();
+        assert_eq!(current.length, 2);
+        assert_eq!(report.qualifying_periods, 5);
+        // Current period already qualified, so only the record alert is raised
+        assert_eq!(report.alerts.len(), 1);
+        assert!(report.alerts[0].message.contains("2 more qualifying days"));
+    }
+
+    #[test]
+    fn test_streak_at_risk_alert() {
+        let activities: Vec<Activity> = (0..4).map(|i| run_on(&format!("r{}", i), day(i), 5_000.0)).collect();
+
+        let report = StreakEngine::new(StreakCriteria::default()).analyze(&activities, at_noon(day(4)));
+
+        assert_eq!(report.current_streak.unwrap().length, 4);
+        assert!(matches!(report.alerts[0].severity, InsightSeverity::Warning));
+        assert!(report.alerts[0].message.contains("4-day streak ends"));
+        assert!(report.alerts[0].message.contains("today"));
+    }
+
+    #[test]
+    fn test_broken_streak_is_not_current() {
+        let activities = vec![run_on("a", day(0), 5_000.0), run_on("b", day(1), 5_000.0)];
+
+        let report = StreakEngine::new(StreakCriteria::default()).analyze(&activities, at_noon(day(5)));
+
+        assert!(report.current_streak.is_none());
+        assert_eq!(report.longest_streak.unwrap().length, 2);
+        assert!(report.alerts.is_empty());
+    }
+
+    #[test]
+    fn test_weekly_threshold_streak() {
+        let criteria = StreakCriteria {
+            period: StreakPeriod::Week,
+            min_activities: 3,
+            min_total_distance_meters: Some(20_000.0),
+            ..StreakCriteria::default()
+        };
+        // 2024-05-06 is a Monday
+        let monday = NaiveDate::from_ymd_opt(2024, 5, 6).unwrap();
+        let mut activities = Vec::new();
+        for week in 0..3 {
+            for session in 0..3 {
+                let date = monday + Duration::weeks(week) + Duration::days(session * 2);
+                activities.push(run_on(&format!("w{}s{}", week, session), date, 8_000.0));
+            }
+        }
+        // One session so far in the fourth week
+        activities.push(run_on("partial", monday + Duration::weeks(3), 8_000.0));
+
+        let now = at_noon(monday + Duration::weeks(3) + Duration::days(2));
+        let report = StreakEngine::new(criteria).analyze(&activities, now);
+
+        let current = report.current_streak.unwrap();
+        assert_eq!(current.length, 3);
+        assert_eq!(current.start, monday);
+        assert!((current.total_distance_km - 72.0).abs() < f64::EPSILON);
+        let alert = &report.alerts[0];
+        assert!(alert.message.contains("3-week streak"));
+        assert!(alert.message.contains("2 more activities and 12.0 km more"));
+    }
+
+    #[test]
+    fn test_days_follow_the_users_timezone() {
+        let los_angeles = chrono_tz::America::Los_Angeles;
+        let at = |date: NaiveDate, hour: u32| {
+            los_angeles
+                .with_ymd_and_hms(date.year(), date.month(), date.day(), hour, 0, 0)
+                .unwrap()
+                .with_timezone(&Utc)
+        };
+        // An evening run is already the next day in UTC
+        let activities = vec![
+            Activity { start_date: at(day(0), 20), ..run_on("evening", day(0), 5_000.0) },
+            Activity { start_date: at(day(2), 6), ..run_on("morning", day(2), 5_000.0) },
+        ];
+        let now = at(day(2), 12);
+
+        let utc = StreakEngine::new(StreakCriteria::default()).analyze(&activities, now);
+        assert_eq!(utc.longest_streak.unwrap().length, 2);
+
+        let local = StreakEngine::new(StreakCriteria::default())
+            .with_timezone(los_angeles)
+            .analyze(&activities, now);
+        assert_eq!(local.longest_streak.unwrap().length, 1);
+        assert_eq!(local.current_streak.unwrap().start, day(2));
+    }
+}
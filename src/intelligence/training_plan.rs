@@ -0,0 +1,775 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Periodized training plans towards a target event, and compliance tracking
+//! of a stored plan against the activities actually recorded
+
+use super::{FitnessLevel, Goal, TimeAvailability, UserFitnessProfile};
+use crate::models::{Activity, SportType};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashSet;
+
+/// Shortest plan that still fits every phase
+pub const MIN_PLAN_WEEKS: i64 = 4;
+
+/// Longer lead times are trimmed to the final weeks before the event
+pub const MAX_PLAN_WEEKS: i64 = 24;
+
+/// Every Nth week (outside the taper) drops volume to absorb the training
+const RECOVERY_WEEK_INTERVAL: usize = 4;
+
+/// Share of the weekly minutes given to the long session
+const LONG_SESSION_SHARE: f64 = 0.3;
+
+const MIN_SESSION_MINUTES: u32 = 20;
+
+/// Heart rate zones as (name, lower, upper) fractions of max heart rate
+const ZONE_BOUNDS: [(&str, f64, f64); 5] = [
+    ("Recovery", 0.50, 0.60),
+    ("Endurance", 0.60, 0.70),
+    ("Tempo", 0.70, 0.80),
+    ("Threshold", 0.80, 0.90),
+    ("VO2 max", 0.90, 1.00),
+];
+
+/// The race or event a plan builds towards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetEvent {
+    pub name: Option<String>,
+    pub sport: String,
+    pub distance_km: f64,
+    pub event_date: NaiveDate,
+    pub goal_time_minutes: Option<f64>,
+}
+
+impl TargetEvent {
+    /// Build an event from `generate_training_plan` tool arguments
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let Some(sport) = args["sport_type"].as_str() else {
+            bail!("sport_type is required");
+        };
+        let Some(distance_km) = args["distance_km"].as_f64() else {
+            bail!("distance_km is required");
+        };
+        let Some(event_date) = args["event_date"].as_str() else {
+            bail!("event_date is required");
+        };
+
+        let event = Self {
+            name: args["event_name"].as_str().map(str::to_string),
+            sport: sport.to_string(),
+            distance_km,
+            event_date: Goal::parse_date(event_date)?.date_naive(),
+            goal_time_minutes: args["goal_time_minutes"].as_f64(),
+        };
+        event.validate()?;
+        Ok(event)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.sport.trim().is_empty() {
+            bail!("sport_type must not be empty");
+        }
+        if self.distance_km <= 0.0 {
+            bail!("distance_km must be positive");
+        }
+        if self.goal_time_minutes.is_some_and(|minutes| minutes <= 0.0) {
+            bail!("goal_time_minutes must be positive");
+        }
+        Ok(())
+    }
+
+    /// Expected finishing time, from the goal time or a typical pace for the sport
+    fn expected_minutes(&self) -> f64 {
+        self.goal_time_minutes.unwrap_or_else(|| {
+            let minutes_per_km = match SportType::from_name(&self.sport) {
+                SportType::Run => 6.0,
+                SportType::Ride => 2.4,
+                SportType::Swim => 25.0,
+                _ => 10.0,
+            };
+            self.distance_km * minutes_per_km
+        })
+    }
+
+    /// Goal pace in the unit usual for the sport, if a goal time was given
+    fn target_pace(&self) -> Option<String> {
+        let minutes = self.goal_time_minutes?;
+        Some(match SportType::from_name(&self.sport) {
+            SportType::Ride => format!("{:.1} km/h", self.distance_km / (minutes / 60.0)),
+            SportType::Swim => format!("{} /100m", format_minutes(minutes / (self.distance_km * 10.0))),
+            _ => format!("{} /km", format_minutes(minutes / self.distance_km)),
+        })
+    }
+}
+
+impl TimeAvailability {
+    /// Override the stored availability with `hours_per_week`, `preferred_days`
+    /// and `preferred_duration_minutes` tool arguments when given
+    pub fn apply_args(&mut self, args: &Value) -> Result<()> {
+        if let Some(hours) = args["hours_per_week"].as_f64() {
+            if hours <= 0.0 || hours > 40.0 {
+                bail!("hours_per_week must be between 0 and 40");
+            }
+            self.hours_per_week = hours;
+        }
+
+        let days: Option<Vec<String>> = match &args["preferred_days"] {
+            Value::Array(days) => Some(days.iter().filter_map(|day| day.as_str()).map(str::to_string).collect()),
+            Value::String(days) => Some(days.split(',').map(|day| day.trim().to_string()).collect()),
+            _ => None,
+        };
+        if let Some(days) = days {
+            if let Some(day) = days.iter().find(|day| day.parse::<Weekday>().is_err()) {
+                bail!("Unknown day '{}' in preferred_days", day);
+            }
+            self.preferred_days = days;
+        }
+
+        if let Some(minutes) = args["preferred_duration_minutes"].as_i64() {
+            if minutes < MIN_SESSION_MINUTES as i64 {
+                bail!("preferred_duration_minutes must be at least {}", MIN_SESSION_MINUTES);
+            }
+            self.preferred_duration_minutes = Some(minutes as i32);
+        }
+
+        Ok(())
+    }
+}
+
+/// Periodization phase of a plan week
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TrainingPhase {
+    Base,
+    Build,
+    Peak,
+    Taper,
+}
+
+/// Kind of planned session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkoutType {
+    Easy,
+    Long,
+    Tempo,
+    Intervals,
+    Recovery,
+    RacePace,
+    Race,
+}
+
+impl WorkoutType {
+    fn zone(&self) -> u8 {
+        match self {
+            Self::Recovery => 1,
+            Self::Easy | Self::Long => 2,
+            Self::Tempo => 3,
+            Self::RacePace | Self::Race => 4,
+            Self::Intervals => 5,
+        }
+    }
+}
+
+/// Heart rate zone derived from the athlete's max heart rate
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HeartRateZone {
+    pub zone: u8,
+    pub name: String,
+    pub min_bpm: u32,
+    pub max_bpm: u32,
+}
+
+/// Target intensity of a workout
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkoutIntensity {
+    pub zone: u8,
+    pub name: String,
+    /// Only known when a max heart rate could be determined
+    pub heart_rate_bpm: Option<(u32, u32)>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlannedWorkout {
+    pub date: NaiveDate,
+    pub workout_type: WorkoutType,
+    pub sport: String,
+    pub duration_minutes: u32,
+    pub intensity: WorkoutIntensity,
+    pub description: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanWeek {
+    pub week_number: usize,
+    pub start_date: NaiveDate,
+    pub phase: TrainingPhase,
+    pub recovery_week: bool,
+    pub target_minutes: u32,
+    pub workouts: Vec<PlannedWorkout>,
+}
+
+/// Week-by-week plan leading up to a target event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingPlan {
+    pub id: String,
+    pub user_id: String,
+    pub event: TargetEvent,
+    pub created_at: DateTime<Utc>,
+    pub start_date: NaiveDate,
+    pub hours_per_week: f64,
+    pub zones: Vec<HeartRateZone>,
+    pub weeks: Vec<PlanWeek>,
+}
+
+/// How closely the recorded activities follow a plan so far
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanCompliance {
+    pub as_of: NaiveDate,
+    pub workouts_due: usize,
+    pub workouts_completed: usize,
+    pub planned_minutes: u32,
+    pub actual_minutes: u32,
+    /// 0-100, weighting completed sessions at 70% and trained minutes at 30%;
+    /// `None` until the first workout is due
+    pub score: Option<f64>,
+    pub weeks: Vec<WeekCompliance>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekCompliance {
+    pub week_number: usize,
+    pub phase: TrainingPhase,
+    pub workouts_due: usize,
+    pub workouts_completed: usize,
+    pub planned_minutes: u32,
+    pub actual_minutes: u32,
+    pub score: Option<f64>,
+}
+
+impl TrainingPlan {
+    /// Build a plan from today until the event for the profile's time availability
+    pub fn generate(
+        user_id: &str,
+        profile: &UserFitnessProfile,
+        event: TargetEvent,
+        activities: &[Activity],
+        today: NaiveDate,
+    ) -> Result<Self> {
+        event.validate()?;
+
+        let days_until_event = (event.event_date - today).num_days();
+        if days_until_event < MIN_PLAN_WEEKS * 7 {
+            bail!("The event must be at least {} weeks away to build a plan", MIN_PLAN_WEEKS);
+        }
+        let week_count = (days_until_event / 7).min(MAX_PLAN_WEEKS);
+        let start_date = event.event_date - Duration::days(week_count * 7 - 1);
+
+        let availability = &profile.preferences.time_availability;
+        let hours_per_week = if availability.hours_per_week > 0.0 {
+            availability.hours_per_week
+        } else {
+            default_hours_per_week(&profile.fitness_level)
+        };
+        let session_cap = availability.preferred_duration_minutes.filter(|minutes| *minutes > 0).map(|minutes| minutes as u32);
+        let training_days = training_days(&availability.preferred_days, hours_per_week, session_cap);
+        let zones = heart_rate_zones(profile, activities);
+
+        let phases = phase_layout(week_count as usize);
+        let taper_start = phases.iter().position(|phase| *phase == TrainingPhase::Taper).unwrap_or(phases.len());
+
+        let weeks = phases
+            .iter()
+            .enumerate()
+            .map(|(index, phase)| {
+                let week_number = index + 1;
+                let recovery_week = *phase != TrainingPhase::Taper && week_number % RECOVERY_WEEK_INTERVAL == 0;
+                let load = week_load(index, taper_start, phases.len(), recovery_week);
+                let target_minutes = (hours_per_week * 60.0 * load).round() as u32;
+                let week_start = start_date + Duration::weeks(index as i64);
+
+                PlanWeek {
+                    week_number,
+                    start_date: week_start,
+                    phase: *phase,
+                    recovery_week,
+                    target_minutes,
+                    workouts: plan_week_workouts(
+                        &event,
+                        week_start,
+                        *phase,
+                        recovery_week,
+                        target_minutes,
+                        &training_days,
+                        session_cap,
+                        &zones,
+                    ),
+                }
+            })
+            .collect();
+
+        Ok(Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            event,
+            created_at: Utc::now(),
+            start_date,
+            hours_per_week,
+            zones,
+            weeks,
+        })
+    }
+
+    /// Score the workouts due up to and including `today` against recorded activities.
+    /// A workout counts as done when an activity of the plan's sport exists on its date.
+    pub fn compliance(&self, activities: &[Activity], today: NaiveDate) -> PlanCompliance {
+        let sport = SportType::from_name(&self.event.sport).key();
+        let mut used = HashSet::new();
+        let mut weeks = Vec::new();
+
+        for week in self.weeks.iter().filter(|week| week.start_date <= today) {
+            let mut summary = WeekCompliance {
+                week_number: week.week_number,
+                phase: week.phase,
+                workouts_due: 0,
+                workouts_completed: 0,
+                planned_minutes: 0,
+                actual_minutes: 0,
+                score: None,
+            };
+
+            for workout in week.workouts.iter().filter(|workout| workout.date <= today) {
+                summary.workouts_due += 1;
+                summary.planned_minutes += workout.duration_minutes;
+
+                let matched = activities.iter().enumerate().find(|(index, activity)| {
+                    !used.contains(index)
+                        && activity.start_date.date_naive() == workout.date
+                        && activity.sport_type.key() == sport
+                });
+                if let Some((index, activity)) = matched {
+                    used.insert(index);
+                    summary.workouts_completed += 1;
+                    // Extra minutes on one day do not make up for a missed session
+                    summary.actual_minutes += ((activity.duration_seconds / 60) as u32).min(workout.duration_minutes);
+                }
+            }
+
+            summary.score = compliance_score(
+                summary.workouts_due,
+                summary.workouts_completed,
+                summary.planned_minutes,
+                summary.actual_minutes,
+            );
+            weeks.push(summary);
+        }
+
+        let workouts_due = weeks.iter().map(|week| week.workouts_due).sum();
+        let workouts_completed = weeks.iter().map(|week| week.workouts_completed).sum();
+        let planned_minutes = weeks.iter().map(|week| week.planned_minutes).sum();
+        let actual_minutes = weeks.iter().map(|week| week.actual_minutes).sum();
+
+        PlanCompliance {
+            as_of: today,
+            workouts_due,
+            workouts_completed,
+            planned_minutes,
+            actual_minutes,
+            score: compliance_score(workouts_due, workouts_completed, planned_minutes, actual_minutes),
+            weeks,
+        }
+    }
+}
+
+fn compliance_score(due: usize, completed: usize, planned_minutes: u32, actual_minutes: u32) -> Option<f64> {
+    if due == 0 {
+        return None;
+    }
+    let completion = completed as f64 / due as f64;
+    let volume = if planned_minutes > 0 {
+        (actual_minutes as f64 / planned_minutes as f64).min(1.0)
+    } else {
+        1.0
+    };
+    Some(((completion * 70.0 + volume * 30.0) * 10.0).round() / 10.0)
+}
+
+fn default_hours_per_week(level: &FitnessLevel) -> f64 {
+    match level {
+        FitnessLevel::Beginner => 3.0,
+        FitnessLevel::Intermediate => 5.0,
+        FitnessLevel::Advanced => 7.0,
+        FitnessLevel::Elite => 10.0,
+    }
+}
+
+/// Phase of each week: base, build (~35%), peak (~20%) and a one or two week taper
+fn phase_layout(week_count: usize) -> Vec<TrainingPhase> {
+    let taper = if week_count > 8 { 2 } else { 1 };
+    let remaining = week_count - taper;
+    let peak = ((remaining as f64 * 0.2).round() as usize).max(1);
+    let build = ((remaining as f64 * 0.35).round() as usize).max(1);
+    let base = remaining - peak - build;
+
+    std::iter::repeat_n(TrainingPhase::Base, base)
+        .chain(std::iter::repeat_n(TrainingPhase::Build, build))
+        .chain(std::iter::repeat_n(TrainingPhase::Peak, peak))
+        .chain(std::iter::repeat_n(TrainingPhase::Taper, taper))
+        .collect()
+}
+
+/// Fraction of the available weekly time to use in a given week
+fn week_load(index: usize, taper_start: usize, week_count: usize, recovery_week: bool) -> f64 {
+    if index >= taper_start {
+        // Race week is the lightest
+        return if index + 1 == week_count { 0.4 } else { 0.6 };
+    }
+
+    let progress = if taper_start > 1 { index as f64 / (taper_start - 1) as f64 } else { 1.0 };
+    let load = 0.7 + 0.3 * progress;
+    if recovery_week {
+        load * 0.7
+    } else {
+        load
+    }
+}
+
+/// Days of the week to train on, from the preferred days or a default split
+fn training_days(preferred_days: &[String], hours_per_week: f64, session_cap: Option<u32>) -> Vec<Weekday> {
+    let mut days: Vec<Weekday> = preferred_days.iter().filter_map(|day| day.trim().parse().ok()).collect();
+    days.sort_by_key(|day: &Weekday| day.num_days_from_monday());
+    days.dedup();
+    if !days.is_empty() {
+        return days;
+    }
+
+    let session_minutes = session_cap.unwrap_or(60) as f64;
+    let sessions = ((hours_per_week * 60.0) / session_minutes).ceil().clamp(3.0, 6.0) as usize;
+    match sessions {
+        3 => vec![Weekday::Tue, Weekday::Thu, Weekday::Sun],
+        4 => vec![Weekday::Tue, Weekday::Thu, Weekday::Sat, Weekday::Sun],
+        5 => vec![Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Sat, Weekday::Sun],
+        _ => vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Sat, Weekday::Sun],
+    }
+}
+
+/// Zones from the highest heart rate seen in the activities, else 220 minus age
+fn heart_rate_zones(profile: &UserFitnessProfile, activities: &[Activity]) -> Vec<HeartRateZone> {
+    let observed = activities.iter().filter_map(|a| a.max_heart_rate).filter(|hr| *hr >= 120).max();
+    let estimated = profile.age.filter(|age| *age > 0 && *age < 100).map(|age| (220 - age) as u32);
+    let Some(max_hr) = observed.or(estimated) else {
+        return Vec::new();
+    };
+
+    ZONE_BOUNDS
+        .iter()
+        .enumerate()
+        .map(|(index, (name, lower, upper))| HeartRateZone {
+            zone: index as u8 + 1,
+            name: name.to_string(),
+            min_bpm: (max_hr as f64 * lower).round() as u32,
+            max_bpm: (max_hr as f64 * upper).round() as u32,
+        })
+        .collect()
+}
+
+#[allow(clippy::too_many_arguments)]
+fn plan_week_workouts(
+    event: &TargetEvent,
+    week_start: NaiveDate,
+    phase: TrainingPhase,
+    recovery_week: bool,
+    target_minutes: u32,
+    training_days: &[Weekday],
+    session_cap: Option<u32>,
+    zones: &[HeartRateZone],
+) -> Vec<PlannedWorkout> {
+    let mut dates: Vec<NaiveDate> = (0..7)
+        .map(|offset| week_start + Duration::days(offset))
+        .filter(|date| training_days.contains(&date.weekday()) && *date < event.event_date)
+        .collect();
+    let race_week = week_start + Duration::days(6) >= event.event_date;
+
+    // The day before the race stays free
+    if race_week {
+        dates.retain(|date| *date + Duration::days(1) < event.event_date);
+    }
+    if dates.is_empty() && !race_week {
+        dates.push(week_start + Duration::days(6));
+    }
+
+    // Long session on the last weekend training day, or the last one of the week
+    let long_date = (!race_week).then(|| {
+        dates
+            .iter()
+            .rev()
+            .find(|date| matches!(date.weekday(), Weekday::Sat | Weekday::Sun))
+            .or(dates.last())
+            .copied()
+    });
+    let long_date = long_date.flatten();
+
+    let long_minutes = long_date.map(|_| (target_minutes as f64 * LONG_SESSION_SHARE).round() as u32).unwrap_or(0);
+    let other_count = dates.len() - usize::from(long_date.is_some());
+    let other_minutes = if other_count > 0 {
+        let share = (target_minutes - long_minutes) / other_count as u32;
+        session_cap.map_or(share, |cap| share.min(cap)).max(MIN_SESSION_MINUTES)
+    } else {
+        0
+    };
+
+    let mut quality = quality_sessions(phase, recovery_week).into_iter();
+    let mut workouts: Vec<PlannedWorkout> = dates
+        .iter()
+        .map(|date| {
+            if Some(*date) == long_date {
+                return workout(event, *date, WorkoutType::Long, long_minutes.max(MIN_SESSION_MINUTES), phase, zones);
+            }
+            let workout_type = quality.next().unwrap_or(if recovery_week {
+                WorkoutType::Recovery
+            } else {
+                WorkoutType::Easy
+            });
+            let minutes = match workout_type {
+                // Keep taper quality short and sharp
+                WorkoutType::RacePace if phase == TrainingPhase::Taper => (other_minutes * 2 / 3).max(MIN_SESSION_MINUTES),
+                _ => other_minutes,
+            };
+            workout(event, *date, workout_type, minutes, phase, zones)
+        })
+        .collect();
+
+    if race_week {
+        let minutes = event.expected_minutes().round() as u32;
+        workouts.push(workout(event, event.event_date, WorkoutType::Race, minutes, phase, zones));
+    }
+
+    workouts
+}
+
+/// Hard sessions of a normal week in each phase, in the order they are placed
+fn quality_sessions(phase: TrainingPhase, recovery_week: bool) -> Vec<WorkoutType> {
+    if recovery_week {
+        return Vec::new();
+    }
+    match phase {
+        TrainingPhase::Base => Vec::new(),
+        TrainingPhase::Build => vec![WorkoutType::Tempo],
+        TrainingPhase::Peak => vec![WorkoutType::Intervals, WorkoutType::RacePace],
+        TrainingPhase::Taper => vec![WorkoutType::RacePace],
+    }
+}
+
+fn workout(
+    event: &TargetEvent,
+    date: NaiveDate,
+    workout_type: WorkoutType,
+    duration_minutes: u32,
+    phase: TrainingPhase,
+    zones: &[HeartRateZone],
+) -> PlannedWorkout {
+    let zone = workout_type.zone();
+    let (name, _, _) = ZONE_BOUNDS[zone as usize - 1];
+    let heart_rate_bpm = zones.iter().find(|z| z.zone == zone).map(|z| (z.min_bpm, z.max_bpm));
+
+    let target = match heart_rate_bpm {
+        Some((min, max)) => format!("zone {} ({}-{} bpm)", zone, min, max),
+        None => format!("zone {} ({})", zone, name.to_lowercase()),
+    };
+    let pace = event.target_pace().map(|pace| format!(" at goal pace {}", pace)).unwrap_or_default();
+    let sport = SportType::from_name(&event.sport).display_name();
+
+    let description = match workout_type {
+        WorkoutType::Recovery => format!("{} min very easy {} in {} to recover", duration_minutes, sport, target),
+        WorkoutType::Easy => format!("{} min easy {} in {}, conversational effort", duration_minutes, sport, target),
+        WorkoutType::Long if phase == TrainingPhase::Peak => format!(
+            "{} min long {} in {}, finishing the last 20 min at race effort",
+            duration_minutes, sport, target
+        ),
+        WorkoutType::Long => format!("{} min long {} in {}, steady and relaxed", duration_minutes, sport, target),
+        WorkoutType::Tempo => format!(
+            "{} min {} with a continuous {} min tempo block in {}",
+            duration_minutes,
+            sport,
+            duration_minutes / 2,
+            target
+        ),
+        WorkoutType::Intervals => format!(
+            "{} min {}: warm up, then 5 x 3 min hard in {} with 2 min easy recoveries",
+            duration_minutes, sport, target
+        ),
+        WorkoutType::RacePace => format!(
+            "{} min {} including {} min in {}{}",
+            duration_minutes,
+            sport,
+            duration_minutes / 2,
+            target,
+            pace
+        ),
+        WorkoutType::Race => format!(
+            "{}: {:.1} km {}{}",
+            event.name.as_deref().unwrap_or("Race day"),
+            event.distance_km,
+            sport,
+            pace
+        ),
+    };
+
+    PlannedWorkout {
+        date,
+        workout_type,
+        sport: event.sport.clone(),
+        duration_minutes,
+        intensity: WorkoutIntensity { zone, name: name.to_string(), heart_rate_bpm },
+        description,
+    }
+}
+
+fn format_minutes(minutes: f64) -> String {
+    let seconds = (minutes * 60.0).round() as u64;
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intelligence::{TimeAvailability, UserPreferences};
+
+    fn profile(hours_per_week: f64, preferred_days: &[&str]) -> UserFitnessProfile {
+        UserFitnessProfile {
+            user_id: "user".to_string(),
+            age: Some(40),
+            gender: None,
+            weight: None,
+            height: None,
+            fitness_level: FitnessLevel::Intermediate,
+            primary_sports: vec!["run".to_string()],
+            training_history_months: 12,
+            preferences: UserPreferences {
+                preferred_units: "metric".to_string(),
+                training_focus: vec![],
+                injury_history: vec![],
+                time_availability: TimeAvailability {
+                    hours_per_week,
+                    preferred_days: preferred_days.iter().map(|day| day.to_string()).collect(),
+                    preferred_duration_minutes: Some(60),
+                },
+            },
+        }
+    }
+
+    fn event(event_date: NaiveDate) -> TargetEvent {
+        TargetEvent {
+            name: Some("City Half".to_string()),
+            sport: "run".to_string(),
+            distance_km: 21.1,
+            event_date,
+            goal_time_minutes: Some(105.0),
+        }
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_plan_phases_and_race_day() {
+        let today = date(2024, 1, 1);
+        let plan = TrainingPlan::generate("user", &profile(5.0, &[]), event(date(2024, 3, 25)), &[], today).unwrap();
+
+        assert_eq!(plan.weeks.len(), 12);
+        assert_eq!(plan.weeks[0].phase, TrainingPhase::Base);
+        assert_eq!(plan.weeks[10].phase, TrainingPhase::Taper);
+        assert_eq!(plan.weeks[11].phase, TrainingPhase::Taper);
+        assert!(plan.weeks.iter().any(|week| week.phase == TrainingPhase::Build));
+        assert!(plan.weeks.iter().any(|week| week.phase == TrainingPhase::Peak));
+        assert!(plan.weeks[3].recovery_week);
+        assert!(plan.weeks[3].target_minutes < plan.weeks[2].target_minutes);
+
+        let race_week = plan.weeks.last().unwrap();
+        let race = race_week.workouts.last().unwrap();
+        assert_eq!(race.workout_type, WorkoutType::Race);
+        assert_eq!(race.date, date(2024, 3, 25));
+        assert_eq!(race.duration_minutes, 105);
+        assert!(race.description.contains("4:59 /km"));
+
+        // 220 - 40 = 180 bpm max
+        assert_eq!(plan.zones.len(), 5);
+        assert_eq!(plan.zones[1].min_bpm, 108);
+        assert_eq!(plan.zones[1].max_bpm, 126);
+    }
+
+    #[test]
+    fn test_plan_uses_preferred_days_and_phase_workouts() {
+        let today = date(2024, 1, 1);
+        let plan = TrainingPlan::generate(
+            "user",
+            &profile(4.0, &["Tuesday", "thu", "Saturday"]),
+            event(date(2024, 3, 25)),
+            &[],
+            today,
+        )
+        .unwrap();
+
+        for week in &plan.weeks {
+            for workout in week.workouts.iter().filter(|w| w.workout_type != WorkoutType::Race) {
+                assert!(matches!(workout.date.weekday(), Weekday::Tue | Weekday::Thu | Weekday::Sat));
+            }
+            assert!(week.workouts.iter().all(|w| w.duration_minutes >= MIN_SESSION_MINUTES));
+        }
+
+        let types = |phase: TrainingPhase| -> Vec<WorkoutType> {
+            let week = plan.weeks.iter().find(|w| w.phase == phase && !w.recovery_week).unwrap();
+            week.workouts.iter().map(|w| w.workout_type).collect()
+        };
+        assert_eq!(types(TrainingPhase::Base), vec![WorkoutType::Easy, WorkoutType::Easy, WorkoutType::Long]);
+        assert!(types(TrainingPhase::Build).contains(&WorkoutType::Tempo));
+        assert!(types(TrainingPhase::Peak).contains(&WorkoutType::Intervals));
+    }
+
+    #[test]
+    fn test_plan_rejects_short_lead_time() {
+        let today = date(2024, 1, 1);
+        assert!(TrainingPlan::generate("user", &profile(5.0, &[]), event(date(2024, 1, 20)), &[], today).is_err());
+    }
+
+    #[test]
+    fn test_compliance_score() {
+        let today = date(2024, 1, 1);
+        let plan = TrainingPlan::generate("user", &profile(4.0, &["tue", "thu", "sat"]), event(date(2024, 3, 25)), &[], today)
+            .unwrap();
+        let first_week = &plan.weeks[0];
+
+        // Nothing due yet before the plan starts
+        assert_eq!(plan.compliance(&[], today).score, None);
+
+        // Complete the first two sessions at full length, skip the third
+        let activities: Vec<Activity> = first_week.workouts[..2]
+            .iter()
+            .map(|workout| Activity {
+                sport_type: SportType::Run,
+                start_date: workout.date.and_hms_opt(7, 0, 0).unwrap().and_utc(),
+                duration_seconds: workout.duration_minutes as u64 * 60,
+                ..Activity::default()
+            })
+            .collect();
+
+        let week_end = first_week.start_date + Duration::days(6);
+        let compliance = plan.compliance(&activities, week_end);
+        assert_eq!(compliance.workouts_due, 3);
+        assert_eq!(compliance.workouts_completed, 2);
+        assert_eq!(compliance.weeks.len(), 1);
+
+        let planned = first_week.workouts.iter().map(|w| w.duration_minutes).sum::<u32>() as f64;
+        let actual = first_week.workouts[..2].iter().map(|w| w.duration_minutes).sum::<u32>() as f64;
+        let expected = ((2.0 / 3.0 * 70.0 + actual / planned * 30.0) * 10.0).round() / 10.0;
+        assert_eq!(compliance.score, Some(expected));
+    }
+}
@@ -8,11 +8,11 @@
 //!
 //! A Model Context Protocol (MCP) server for fitness data aggregation and analysis.
 //! This server provides a unified interface to access fitness data from various providers
-//! like Strava, Garmin Connect, and RunKeeper through the MCP protocol.
+//! like Strava, Fitbit and Garmin Connect through the MCP protocol.
 //!
 //! ## Features
 //!
-//! - **Multi-provider support**: Connect to Strava, Garmin, RunKeeper, and more
+//! - **Multi-provider support**: Connect to Strava, Fitbit, Garmin Connect and local activity files
 //! - **OAuth2 authentication**: Secure authentication flow for fitness providers
 //! - **MCP protocol**: Standard interface for Claude and other AI assistants
 //! - **Real-time data**: Access to activities, athlete profiles, and statistics
@@ -90,9 +90,30 @@ pub mod intelligence;
 /// Multi-tenant database management
 pub mod database;
 
+/// Filters, sorting and grouping for the local activity store
+pub mod activity_query;
+
+/// Structured workouts and trainer/device file exporters
+pub mod workouts;
+
+/// iCalendar feeds of planned workouts and goal deadlines
+pub mod calendar;
+
+/// Activity export as CSV, GPX, TCX and Parquet
+pub mod export;
+
 /// Authentication and session management
 pub mod auth;
 
+/// Per-user, per-API-key and per-IP request rate limiting
+pub mod rate_limit;
+
+/// TLS termination for the HTTP and MCP listeners
+pub mod tls;
+
+/// Cross-origin access to the HTTP API
+pub mod cors;
+
 /// HTTP routes for user registration and OAuth flows
 pub mod routes;
 
@@ -158,6 +158,7 @@ async fn handle_tool_call(
                             refresh_token: auth_config.refresh_token.clone(),
                         },
                         "api_key" => AuthData::ApiKey(auth_config.api_key.clone().unwrap_or_default()),
+                        "directory" => AuthData::Directory(auth_config.directory.clone().unwrap_or_default().into()),
                         _ => {
                             return McpResponse {
                                 jsonrpc: JSONRPC_VERSION.to_string(),
@@ -9,36 +9,57 @@
 //! This module provides a multi-tenant MCP server that supports user authentication,
 //! secure token storage, and user-scoped data access.
 
-use crate::auth::{AuthManager, McpAuthMiddleware};
-use crate::constants::{protocol, protocol::*, errors::*, tools::*, json_fields::*};
+use crate::auth::{generate_calendar_token, hash_calendar_token, AuthManager, McpAuthMiddleware};
+use crate::constants::{env_config, protocol, protocol::*, errors::*, tools::*, json_fields::*, status::INSIGHT_TYPE_GOAL_ADJUSTMENT, messages::{CALENDAR_FEED_NOT_FOUND, CALENDAR_FEED_REVOKED, CALENDAR_FEED_URL_NOT_SHOWN, GOAL_CREATED, GOAL_DELETED, GOAL_NOT_FOUND, GOAL_UPDATED, PROFILE_UPDATED, TRAINING_PLAN_CREATED, TRAINING_PLAN_NOT_FOUND}, limits::{ACTIVITY_SYNC_MAX_PAGES, DEFAULT_ACTIVITIES_LIMIT, ACTIVITY_SYNC_PAGE_SIZE, MAX_GARMIN_WEBHOOK_BYTES, MAX_IMPORT_ARCHIVE_BYTES, MAX_IMPORT_FILE_BYTES, PROVIDER_CACHE_IDLE_SECS, REPORT_MAX_ENRICHMENT_LOOKUPS}};
+use crate::config::environment::{OAuthConfig, RateLimitConfig};
+use crate::cors::CorsPolicy;
 use crate::database::Database;
-use crate::models::AuthRequest;
-use crate::providers::{FitnessProvider, create_provider, AuthData};
+use crate::calendar::build_user_calendar;
+use crate::export::{export_query, export_user_activities, ExportFormat};
+use crate::activity_query::{group_activities, ActivityGroupBy, ActivityQuery};
+use crate::models::{Activity, ApiKeyScope, AuthRequest, SportType};
+use crate::providers::{FitnessProvider, cache::ProviderCache, quota, rate_limited, ProviderError};
+use crate::rate_limit::{RateLimitBucket, RateLimitExceeded, RateLimitKey, RateLimiter};
+use crate::tls;
 use crate::mcp::schema::InitializeResponse;
-use crate::intelligence::ActivityAnalyzer;
+use crate::intelligence::{ActivityAnalyzer, AdvancedGoalEngine, AdvancedMetrics, FitnessLevel, MetricsCalculator, AdvancedRecommendationEngine, RecommendationEngineTrait, TargetEvent, UserFitnessProfile, GoalEngineTrait, PERFORMANCE_BASELINE_DAYS, ActivityAnalyzerTrait, AdvancedActivityAnalyzer, ComparisonType, Goal, GoalOutcome, GoalStatus, GoalType, PatternDetector, PatternType, PeriodReportGenerator, ReportContext, ReportPeriod, StreakCriteria, StreakEngine, StreakPeriod, TimeFrame};
 use crate::intelligence::insights::ActivityContext;
 use crate::intelligence::weather::WeatherService;
 use crate::config::FitnessConfig;
-use crate::routes::{AuthRoutes, OAuthRoutes, RegisterRequest, LoginRequest};
+use crate::workouts::{StructuredWorkout, WorkoutFormat};
+use crate::routes::{ApiKeyRoutes, AuthRoutes, CreateApiKeyRequest, ExportRoutes, GarminWebhookRoutes, ImportRoutes, OAuthRoutes, RegisterRequest, LoginRequest, RefreshTokenRequest};
 
 use anyhow::Result;
+use chrono_tz::Tz;
+use base64::{Engine, engine::general_purpose};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::TlsAcceptor;
 use tracing::{info, warn};
 use uuid::Uuid;
 
 // Constants are now imported from the constants module
 
+/// Per-user provider instances, keyed by user ID and provider name
+type UserProviders = Arc<ProviderCache>;
+
 /// Multi-tenant MCP server supporting user authentication
 pub struct MultiTenantMcpServer {
     database: Arc<Database>,
     auth_manager: Arc<AuthManager>,
     auth_middleware: Arc<McpAuthMiddleware>,
     // Per-user provider instances
-    user_providers: Arc<RwLock<HashMap<String, HashMap<String, Box<dyn FitnessProvider>>>>>,
+    user_providers: UserProviders,
+    oauth_config: Arc<OAuthConfig>,
+    rate_limiter: Arc<RateLimiter>,
+    bind_address: IpAddr,
+    tls: Option<TlsAcceptor>,
+    cors: CorsPolicy,
 }
 
 impl MultiTenantMcpServer {
@@ -47,16 +68,53 @@ impl MultiTenantMcpServer {
         database: Database,
         auth_manager: AuthManager,
     ) -> Self {
-        let auth_middleware = McpAuthMiddleware::new(auth_manager.clone());
+        let auth_middleware = McpAuthMiddleware::new(auth_manager.clone(), database.clone());
+        let oauth_config = Arc::new(OAuthConfig::default());
         
         Self {
             database: Arc::new(database),
             auth_manager: Arc::new(auth_manager),
             auth_middleware: Arc::new(auth_middleware),
-            user_providers: Arc::new(RwLock::new(HashMap::new())),
+            user_providers: Arc::new(ProviderCache::new(Duration::from_secs(PROVIDER_CACHE_IDLE_SECS), oauth_config.clone())),
+            oauth_config,
+            rate_limiter: Arc::new(RateLimiter::disabled()),
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            tls: None,
+            cors: CorsPolicy::allow_any(),
         }
     }
 
+    /// Let users connect the providers with client credentials in `oauth_config`
+    pub fn with_oauth_config(mut self, oauth_config: OAuthConfig) -> Self {
+        self.oauth_config = Arc::new(oauth_config);
+        self.user_providers = Arc::new(ProviderCache::new(Duration::from_secs(PROVIDER_CACHE_IDLE_SECS), self.oauth_config.clone()));
+        self
+    }
+
+    /// Enforce request limits on both the MCP and HTTP endpoints
+    pub fn with_rate_limit(mut self, config: RateLimitConfig) -> Self {
+        self.rate_limiter = Arc::new(RateLimiter::new(config));
+        self
+    }
+
+    /// Listen on this address instead of localhost only
+    pub fn with_bind_address(mut self, bind_address: IpAddr) -> Self {
+        self.bind_address = bind_address;
+        self
+    }
+
+    /// Restrict which browser origins may call the HTTP API
+    pub fn with_cors(mut self, cors: CorsPolicy) -> Self {
+        self.cors = cors;
+        self
+    }
+
+    /// Terminate TLS on both the MCP and HTTP listeners
+    pub fn with_tls(mut self, acceptor: TlsAcceptor) -> Self {
+        self.tls = Some(acceptor);
+        self
+    }
+
     /// Run the multi-tenant server with both HTTP and MCP endpoints
     pub async fn run(self, port: u16) -> Result<()> {
         // Create HTTP + MCP server
@@ -68,15 +126,19 @@ impl MultiTenantMcpServer {
         
         // Create route handlers
         let _auth_routes = AuthRoutes::new((*database).clone(), (*auth_manager).clone());
-        let _oauth_routes = OAuthRoutes::new((*database).clone());
+        let _oauth_routes = OAuthRoutes::new((*database).clone(), (*auth_manager).clone(), self.oauth_config.clone());
         
         // Start HTTP server for auth endpoints in background
-        let http_port = port + 1; // Use port+1 for HTTP
+        let http_addr = SocketAddr::new(self.bind_address, port + 1); // Use port+1 for HTTP
         let database_http = database.clone();
         let auth_manager_http = auth_manager.clone();
+        let oauth_config_http = self.oauth_config.clone();
+        let rate_limiter_http = self.rate_limiter.clone();
+        let tls_http = self.tls.clone();
+        let cors = self.cors.clone();
         
         tokio::spawn(async move {
-            Self::run_http_server(http_port, database_http, auth_manager_http).await
+            Self::run_http_server(http_addr, tls_http, cors, database_http, auth_manager_http, oauth_config_http, rate_limiter_http).await
         });
         
         // Run MCP server on main port
@@ -85,22 +147,39 @@ impl MultiTenantMcpServer {
 
     /// Run HTTP server for authentication endpoints
     async fn run_http_server(
-        port: u16,
+        addr: SocketAddr,
+        tls: Option<TlsAcceptor>,
+        cors: CorsPolicy,
         database: Arc<Database>,
         auth_manager: Arc<AuthManager>,
+        oauth_config: Arc<OAuthConfig>,
+        rate_limiter: Arc<RateLimiter>,
     ) -> Result<()> {
         use warp::Filter;
         
-        info!("HTTP authentication server starting on port {}", port);
+        info!("HTTP authentication server starting on {}", addr);
         
-        let auth_routes = AuthRoutes::new((*database).clone(), (*auth_manager).clone());
-        let oauth_routes = OAuthRoutes::new(database.as_ref().clone());
+        // Every request counts against its IP here; routes count authenticated
+        // requests against the user and API key when they authenticate them
+        let rate_limit = tls::remote_addr()
+            .and_then({
+                let rate_limiter = rate_limiter.clone();
+                move |addr: Option<std::net::SocketAddr>| {
+                    let rate_limiter = rate_limiter.clone();
+                    async move {
+                        let keys: Vec<RateLimitKey> = addr.map(|addr| RateLimitKey::Ip(addr.ip())).into_iter().collect();
+                        rate_limiter
+                            .check(&keys, RateLimitBucket::Standard)
+                            .map_err(|exceeded| warp::reject::custom(RateLimited(exceeded)))
+                    }
+                }
+            })
+            .untuple_one();
         
-        // CORS configuration
-        let cors = warp::cors()
-            .allow_any_origin()
-            .allow_headers(vec!["content-type"])
-            .allow_methods(vec!["GET", "POST", "OPTIONS"]);
+        let auth_routes = AuthRoutes::new((*database).clone(), (*auth_manager).clone()).with_rate_limiter(rate_limiter.clone());
+        let garmin_webhook_routes = GarminWebhookRoutes::new(database.as_ref().clone(), oauth_config.clone());
+        let oauth_routes = OAuthRoutes::new(database.as_ref().clone(), auth_manager.as_ref().clone(), oauth_config)
+            .with_rate_limiter(rate_limiter.clone());
         
         // Registration endpoint
         let register = warp::path("auth")
@@ -144,30 +223,63 @@ impl MultiTenantMcpServer {
                 }
             });
         
-        // OAuth authorization URL endpoint
+        // Refresh endpoint; exchanges a refresh token for a new JWT and refresh token
+        let refresh = warp::path("auth")
+            .and(warp::path("refresh"))
+            .and(warp::post())
+            .and(warp::body::json())
+            .and_then({
+                let auth_routes = auth_routes.clone();
+                move |request: RefreshTokenRequest| {
+                    let auth_routes = auth_routes.clone();
+                    async move {
+                        match auth_routes.refresh(request).await {
+                            Ok(response) => Ok(warp::reply::json(&response)),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                Err(warp::reject::custom(ApiError(error)))
+                            }
+                        }
+                    }
+                }
+            });
+
+        // Logout endpoint; revokes the bearer JWT and the refresh tokens of its login
+        let logout = warp::path("auth")
+            .and(warp::path("logout"))
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then({
+                let auth_routes = auth_routes.clone();
+                move |auth_header: Option<String>| {
+                    let auth_routes = auth_routes.clone();
+                    async move {
+                        match auth_routes.logout(auth_header.as_deref()).await {
+                            Ok(()) => Ok(warp::reply::json(&serde_json::json!({"success": true}))),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                Err(route_rejection(&e, error))
+                            }
+                        }
+                    }
+                }
+            });
+        
+        // OAuth authorization URL endpoint; the account is the bearer token's user
         let oauth_auth = warp::path("oauth")
-            .and(warp::path!("auth" / String / String)) // /oauth/auth/{provider}/{user_id}
+            .and(warp::path!("auth" / String)) // /oauth/auth/{provider}
             .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
             .and_then({
                 let oauth_routes = oauth_routes.clone();
-                move |provider: String, user_id_str: String| {
+                move |provider: String, auth_header: Option<String>| {
                     let oauth_routes = oauth_routes.clone();
                     async move {
-                        match Uuid::parse_str(&user_id_str) {
-                            Ok(user_id) => {
-                                match oauth_routes.get_auth_url(user_id, &provider).await {
-                                    Ok(auth_response) => {
-                                        Ok(warp::reply::json(&auth_response))
-                                    }
-                                    Err(e) => {
-                                        let error = serde_json::json!({"error": e.to_string()});
-                                        Err(warp::reject::custom(ApiError(error)))
-                                    }
-                                }
-                            }
-                            Err(_) => {
-                                let error = serde_json::json!({"error": "Invalid user ID format"});
-                                Err(warp::reject::custom(ApiError(error)))
+                        match oauth_routes.get_auth_url(auth_header.as_deref(), &provider).await {
+                            Ok(auth_response) => Ok(warp::reply::json(&auth_response)),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                Err(route_rejection(&e, error))
                             }
                         }
                     }
@@ -227,6 +339,195 @@ impl MultiTenantMcpServer {
                 }
             });
 
+        // Activity file upload: raw GPX/TCX/FIT body, named by the filename query parameter
+        let import_routes = ImportRoutes::new((*database).clone(), (*auth_manager).clone()).with_rate_limiter(rate_limiter.clone());
+        let import_activity = warp::path("import")
+            .and(warp::path("activities"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<std::collections::HashMap<String, String>>())
+            .and(warp::body::content_length_limit(MAX_IMPORT_FILE_BYTES))
+            .and(warp::body::bytes())
+            .and_then(move |auth_header: Option<String>, params: std::collections::HashMap<String, String>, body: warp::hyper::body::Bytes| {
+                let import_routes = import_routes.clone();
+                async move {
+                    let file_name = params.get("filename").cloned().unwrap_or_default();
+                    match import_routes.upload_activity(auth_header.as_deref(), &file_name, &body).await {
+                        Ok(response) => Ok(warp::reply::json(&response)),
+                        Err(e) => {
+                            let error = serde_json::json!({"error": format!("{:#}", e)});
+                            Err(route_rejection(&e, error))
+                        }
+                    }
+                }
+            });
+
+        // Strava or Garmin account export ZIP as the raw body
+        let import_archive = warp::path("import")
+            .and(warp::path("archive"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::content_length_limit(MAX_IMPORT_ARCHIVE_BYTES))
+            .and(warp::body::stream())
+            .and_then({
+                let import_routes = ImportRoutes::new((*database).clone(), (*auth_manager).clone()).with_rate_limiter(rate_limiter.clone());
+                move |auth_header: Option<String>, body| {
+                    let import_routes = import_routes.clone();
+                    async move {
+                        match import_routes.import_archive(auth_header.as_deref(), body).await {
+                            Ok(response) => Ok(warp::reply::json(&response)),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": format!("{:#}", e)});
+                                Err(route_rejection(&e, error))
+                            }
+                        }
+                    }
+                }
+            });
+
+        // Activity export download, filtered by the export_activities tool arguments
+        let export_activities = warp::path("export")
+            .and(warp::path("activities"))
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::query::<std::collections::HashMap<String, String>>())
+            .and_then({
+                let export_routes = ExportRoutes::new((*database).clone(), (*auth_manager).clone()).with_rate_limiter(rate_limiter.clone());
+                move |auth_header: Option<String>, params: std::collections::HashMap<String, String>| {
+                    let export_routes = export_routes.clone();
+                    async move {
+                        match export_routes.export_activities(auth_header.as_deref(), &params).await {
+                            Ok((format, export)) => {
+                                let disposition = format!("attachment; filename=\"activities.{}\"", format.extension());
+                                let reply = warp::reply::with_header(export.bytes, "content-type", format.mime_type());
+                                let reply = warp::reply::with_header(reply, "content-disposition", disposition);
+                                Ok(warp::reply::with_header(reply, "x-skipped-activities", export.skipped.len().to_string()))
+                            }
+                            Err(e) => {
+                                let error = serde_json::json!({"error": format!("{:#}", e)});
+                                Err(route_rejection(&e, error))
+                            }
+                        }
+                    }
+                }
+            });
+
+        // Garmin Health API ping notifications, registered in the Garmin developer portal
+        let garmin_webhook = warp::path("webhooks")
+            .and(warp::path("garmin"))
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::body::content_length_limit(MAX_GARMIN_WEBHOOK_BYTES))
+            .and(warp::body::bytes())
+            .and_then(move |body: warp::hyper::body::Bytes| {
+                let garmin_webhook_routes = garmin_webhook_routes.clone();
+                async move {
+                    match garmin_webhook_routes.handle_notification(&body).await {
+                        Ok(response) => Ok(warp::reply::json(&response)),
+                        Err(e) => {
+                            let error = serde_json::json!({"error": format!("{:#}", e)});
+                            Err(warp::reject::custom(ApiError(error)))
+                        }
+                    }
+                }
+            });
+
+        // Calendar feed endpoint; the token in the URL is the only credential
+        let calendar = warp::path("calendar")
+            .and(warp::path!(String)) // /calendar/{token}.ics
+            .and(warp::get())
+            .and_then({
+                let database = database.clone();
+                move |file: String| {
+                    let database = database.clone();
+                    async move {
+                        let token = file.strip_suffix(".ics").unwrap_or(&file);
+                        let user_id = match database.get_user_by_calendar_token_hash(&hash_calendar_token(token)).await {
+                            Ok(Some(user_id)) => user_id,
+                            Ok(None) => return Err(warp::reject::not_found()),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                return Err(warp::reject::custom(ApiError(error)));
+                            }
+                        };
+
+                        match build_user_calendar(&database, user_id).await {
+                            Ok(ics) => Ok(warp::reply::with_header(
+                                ics,
+                                "content-type",
+                                "text/calendar; charset=utf-8",
+                            )),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": format!("Failed to build calendar: {}", e)});
+                                Err(warp::reject::custom(ApiError(error)))
+                            }
+                        }
+                    }
+                }
+            });
+
+        // API key management; the full key is only returned by the create call
+        let api_key_routes = ApiKeyRoutes::new((*database).clone(), (*auth_manager).clone()).with_rate_limiter(rate_limiter);
+        let create_api_key = warp::path("api-keys")
+            .and(warp::path::end())
+            .and(warp::post())
+            .and(warp::header::optional::<String>("authorization"))
+            .and(warp::body::json())
+            .and_then({
+                let api_key_routes = api_key_routes.clone();
+                move |auth_header: Option<String>, request: CreateApiKeyRequest| {
+                    let api_key_routes = api_key_routes.clone();
+                    async move {
+                        match api_key_routes.create_api_key(auth_header.as_deref(), request).await {
+                            Ok(response) => Ok(warp::reply::json(&response)),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                Err(route_rejection(&e, error))
+                            }
+                        }
+                    }
+                }
+            });
+
+        let list_api_keys = warp::path("api-keys")
+            .and(warp::path::end())
+            .and(warp::get())
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then({
+                let api_key_routes = api_key_routes.clone();
+                move |auth_header: Option<String>| {
+                    let api_key_routes = api_key_routes.clone();
+                    async move {
+                        match api_key_routes.list_api_keys(auth_header.as_deref()).await {
+                            Ok(keys) => Ok(warp::reply::json(&keys)),
+                            Err(e) => {
+                                let error = serde_json::json!({"error": e.to_string()});
+                                Err(route_rejection(&e, error))
+                            }
+                        }
+                    }
+                }
+            });
+
+        let revoke_api_key = warp::path!("api-keys" / String) // /api-keys/{key_id}
+            .and(warp::delete())
+            .and(warp::header::optional::<String>("authorization"))
+            .and_then(move |key_id: String, auth_header: Option<String>| {
+                let api_key_routes = api_key_routes.clone();
+                async move {
+                    match api_key_routes.revoke_api_key(auth_header.as_deref(), &key_id).await {
+                        Ok(()) => Ok(warp::reply::json(&serde_json::json!({"success": true, "key_id": key_id}))),
+                        Err(e) => {
+                            let error = serde_json::json!({"error": e.to_string()});
+                            Err(route_rejection(&e, error))
+                        }
+                    }
+                }
+            });
+
         // Health check endpoint
         let health = warp::path("health")
             .and(warp::get())
@@ -234,29 +535,34 @@ impl MultiTenantMcpServer {
                 warp::reply::json(&serde_json::json!({"status": "ok", "service": "pierre-mcp-server"}))
             });
         
-        let routes = register
+        let routes = rate_limit.and(register
             .or(login)
+            .or(refresh)
+            .or(logout)
             .or(oauth_auth)
             .or(oauth_callback)
-            .or(health)
-            .with(cors)
+            .or(import_activity)
+            .or(import_archive)
+            .or(garmin_webhook)
+            .or(export_activities)
+            .or(calendar)
+            .or(create_api_key)
+            .or(list_api_keys)
+            .or(revoke_api_key)
+            .or(health))
             .recover(handle_rejection);
+        let routes = cors.apply(routes);
         
-        info!("HTTP server ready on port {}", port);
-        warp::serve(routes)
-            .run(([127, 0, 0, 1], port))
-            .await;
-            
-        Ok(())
+        info!("HTTP server ready on {}", addr);
+        tls::serve_http(routes, addr, tls).await
     }
 
     /// Run MCP server for AI assistant connections
     async fn run_mcp_server(self, port: u16) -> Result<()> {
-        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
         use tokio::net::TcpListener;
         
-        let listener = TcpListener::bind(format!("127.0.0.1:{}", port)).await?;
-        info!("MCP server listening on port {}", port);
+        let listener = TcpListener::bind(SocketAddr::new(self.bind_address, port)).await?;
+        info!("MCP server listening on {}:{}{}", self.bind_address, port, if self.tls.is_some() { " (TLS)" } else { "" });
         
         loop {
             let (socket, addr) = listener.accept().await?;
@@ -266,40 +572,83 @@ impl MultiTenantMcpServer {
             let auth_manager = self.auth_manager.clone();
             let auth_middleware = self.auth_middleware.clone();
             let user_providers = self.user_providers.clone();
+            let oauth_config = self.oauth_config.clone();
+            let rate_limiter = self.rate_limiter.clone();
+            let tls = self.tls.clone();
             
             tokio::spawn(async move {
-                let (reader, mut writer) = socket.into_split();
-                let mut reader = BufReader::new(reader);
-                let mut line = String::new();
-                
-                while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
-                    if let Ok(request) = serde_json::from_str::<McpRequest>(&line) {
-                        let response = Self::handle_request(
-                            request,
-                            &database,
-                            &auth_manager,
-                            &auth_middleware,
-                            &user_providers,
-                        ).await;
-                        
-                        let response_str = serde_json::to_string(&response).unwrap();
-                        writer.write_all(response_str.as_bytes()).await.ok();
-                        writer.write_all(b"\n").await.ok();
+                let Some(tls) = tls else {
+                    Self::serve_mcp_connection(socket, addr.ip(), database, auth_manager, auth_middleware, user_providers, oauth_config, rate_limiter).await;
+                    return;
+                };
+                match tls::accept(&tls, socket).await {
+                    Ok(stream) => {
+                        Self::serve_mcp_connection(stream, addr.ip(), database, auth_manager, auth_middleware, user_providers, oauth_config, rate_limiter).await;
                     }
-                    line.clear();
+                    Err(e) => warn!("TLS handshake with MCP client {}: {:#}", addr, e),
                 }
             });
         }
     }
 
+    /// Answer newline-delimited MCP requests on one connection until the client closes it
+    #[allow(clippy::too_many_arguments)]
+    async fn serve_mcp_connection<S>(
+        stream: S,
+        client_ip: IpAddr,
+        database: Arc<Database>,
+        auth_manager: Arc<AuthManager>,
+        auth_middleware: Arc<McpAuthMiddleware>,
+        user_providers: UserProviders,
+        oauth_config: Arc<OAuthConfig>,
+        rate_limiter: Arc<RateLimiter>,
+    ) where
+        S: AsyncRead + AsyncWrite,
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        
+        let (reader, mut writer) = tokio::io::split(stream);
+        let mut reader = BufReader::new(reader);
+        let mut line = String::new();
+        
+        while reader.read_line(&mut line).await.unwrap_or(0) > 0 {
+            if let Ok(request) = serde_json::from_str::<McpRequest>(&line) {
+                let response = Self::handle_request(
+                    request,
+                    client_ip,
+                    &database,
+                    &auth_manager,
+                    &auth_middleware,
+                    &user_providers,
+                    &oauth_config,
+                    &rate_limiter,
+                ).await;
+                
+                let response_str = serde_json::to_string(&response).unwrap();
+                writer.write_all(response_str.as_bytes()).await.ok();
+                writer.write_all(b"\n").await.ok();
+                writer.flush().await.ok();
+            }
+            line.clear();
+        }
+    }
+
     /// Handle MCP request with authentication
+    #[allow(clippy::too_many_arguments)]
     async fn handle_request(
         request: McpRequest,
+        client_ip: IpAddr,
         database: &Arc<Database>,
         auth_manager: &Arc<AuthManager>,
         auth_middleware: &Arc<McpAuthMiddleware>,
-        user_providers: &Arc<RwLock<HashMap<String, HashMap<String, Box<dyn FitnessProvider>>>>>,
+        user_providers: &UserProviders,
+        oauth_config: &Arc<OAuthConfig>,
+        rate_limiter: &Arc<RateLimiter>,
     ) -> McpResponse {
+        if let Err(exceeded) = rate_limiter.check(&[RateLimitKey::Ip(client_ip)], RateLimitBucket::Standard) {
+            return Self::rate_limited_response(exceeded, request.id);
+        }
+
         match request.method.as_str() {
             "initialize" => {
                 let init_response = InitializeResponse::new(
@@ -322,16 +671,40 @@ impl MultiTenantMcpServer {
                 // Extract authorization header from request
                 let auth_token = request.auth_token.as_deref();
                 
-                match auth_middleware.authenticate_request(auth_token) {
-                    Ok(user_id) => {
+                match auth_middleware.authenticate_request(auth_token).await {
+                    Ok(auth) => {
+                        let tool_name = request.params.as_ref()
+                            .and_then(|params| params["name"].as_str())
+                            .unwrap_or("");
+                        let required_scope = Self::required_scope(tool_name);
+                        if !auth.allows(required_scope) {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_FORBIDDEN,
+                                    message: format!("API key lacks the {} scope required by {}", required_scope.as_str(), tool_name),
+                                    data: None,
+                                }),
+                                id: request.id,
+                            };
+                        }
+
+                        let keys = RateLimitKey::authenticated(auth.user_id, auth.api_key_id);
+                        if let Err(exceeded) = rate_limiter.check(&keys, RateLimitBucket::for_tool(tool_name)) {
+                            return Self::rate_limited_response(exceeded, request.id);
+                        }
+                        
                         // Update user's last active timestamp
-                        let _ = database.update_last_active(user_id).await;
+                        let _ = database.update_last_active(auth.user_id).await;
                         
                         Self::handle_authenticated_tool_call(
                             request,
-                            user_id,
+                            auth.user_id,
                             database,
+                            auth_manager,
                             user_providers,
+                            oauth_config,
                         ).await
                     }
                     Err(e) => {
@@ -364,6 +737,34 @@ impl MultiTenantMcpServer {
         }
     }
 
+    /// API key scope a tool needs. Reading is the default; goal changes need
+    /// `goals:write` and account changes need `admin`.
+    fn required_scope(tool_name: &str) -> ApiKeyScope {
+        match tool_name {
+            SET_GOAL | UPDATE_GOAL | PAUSE_GOAL | COMPLETE_GOAL | DELETE_GOAL | GENERATE_TRAINING_PLAN => {
+                ApiKeyScope::GoalsWrite
+            }
+            CONNECT_STRAVA | CONNECT_FITBIT | DISCONNECT_PROVIDER | GET_CALENDAR_FEED | REVOKE_CALENDAR_FEED => {
+                ApiKeyScope::Admin
+            }
+            _ => ApiKeyScope::ActivitiesRead,
+        }
+    }
+
+    fn rate_limited_response(exceeded: RateLimitExceeded, id: Value) -> McpResponse {
+        warn!("{}", exceeded);
+        McpResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            result: None,
+            error: Some(McpError {
+                code: ERROR_RATE_LIMITED,
+                message: exceeded.to_string(),
+                data: Some(exceeded.to_json()),
+            }),
+            id,
+        }
+    }
+
     /// Handle authentication request
     async fn handle_authenticate(
         request: McpRequest,
@@ -399,7 +800,9 @@ impl MultiTenantMcpServer {
         request: McpRequest,
         user_id: Uuid,
         database: &Arc<Database>,
-        user_providers: &Arc<RwLock<HashMap<String, HashMap<String, Box<dyn FitnessProvider>>>>>,
+        auth_manager: &Arc<AuthManager>,
+        user_providers: &UserProviders,
+        oauth_config: &Arc<OAuthConfig>,
     ) -> McpResponse {
         let params = request.params.unwrap_or_default();
         let tool_name = params["name"].as_str().unwrap_or("");
@@ -408,23 +811,26 @@ impl MultiTenantMcpServer {
         // Handle OAuth-related tools (don't require existing provider)
         match tool_name {
             CONNECT_STRAVA => {
-                return Self::handle_connect_strava(user_id, database, request.id).await;
+                return Self::handle_connect_strava(user_id, database, auth_manager, oauth_config, request.id).await;
             }
             CONNECT_FITBIT => {
-                return Self::handle_connect_fitbit(user_id, database, request.id).await;
+                return Self::handle_connect_fitbit(user_id, database, auth_manager, oauth_config, request.id).await;
             }
             GET_CONNECTION_STATUS => {
-                return Self::handle_get_connection_status(user_id, database, request.id).await;
+                return Self::handle_get_connection_status(user_id, database, auth_manager, oauth_config, request.id).await;
             }
             DISCONNECT_PROVIDER => {
                 let provider_name = args[PROVIDER].as_str().unwrap_or("");
-                return Self::handle_disconnect_provider(user_id, provider_name, database, request.id).await;
+                user_providers.invalidate(user_id, provider_name).await;
+                return Self::handle_disconnect_provider(user_id, provider_name, database, auth_manager, oauth_config, request.id).await;
             }
             // Tools that don't require providers
-            SET_GOAL | TRACK_PROGRESS | ANALYZE_GOAL_FEASIBILITY | SUGGEST_GOALS | 
-            CALCULATE_FITNESS_SCORE | GENERATE_RECOMMENDATIONS | ANALYZE_TRAINING_LOAD |
-            DETECT_PATTERNS | ANALYZE_PERFORMANCE_TRENDS => {
-                return Self::execute_tool_call_without_provider(tool_name, args, request.id, user_id, database).await;
+            SET_GOAL | TRACK_PROGRESS | LIST_GOALS | UPDATE_GOAL | PAUSE_GOAL | COMPLETE_GOAL | DELETE_GOAL |
+            ANALYZE_GOAL_FEASIBILITY | SUGGEST_GOALS | 
+            CALCULATE_FITNESS_SCORE | GENERATE_RECOMMENDATIONS | ANALYZE_TRAINING_LOAD | GENERATE_TRAINING_PLAN | GET_TRAINING_PLAN | EXPORT_WORKOUT |
+            GET_CALENDAR_FEED | REVOKE_CALENDAR_FEED | UPDATE_PROFILE |
+            DETECT_PATTERNS | FIND_STREAKS | QUERY_ACTIVITIES | EXPORT_ACTIVITIES | GENERATE_PERIOD_REPORT | ANALYZE_PERFORMANCE_TRENDS => {
+                return Self::execute_tool_call_without_provider(tool_name, args, request.id, user_id, database, user_providers).await;
             }
             _ => {
                 // Check if this is a known tool that requires a provider
@@ -487,119 +893,368 @@ impl MultiTenantMcpServer {
         user_id: Uuid,
         provider_name: &str,
         database: &Arc<Database>,
-        user_providers: &Arc<RwLock<HashMap<String, HashMap<String, Box<dyn FitnessProvider>>>>>,
-    ) -> Result<Box<dyn FitnessProvider>> {
-        let user_key = user_id.to_string();
-        
-        // Check if provider already exists for this user
-        {
-            let providers_read = user_providers.read().await;
-            if let Some(user_provider_map) = providers_read.get(&user_key) {
-                if let Some(_provider) = user_provider_map.get(provider_name) {
-                    // TODO: Clone provider - this is tricky with trait objects
-                    // For now, we'll recreate the provider each time
-                }
+        user_providers: &UserProviders,
+    ) -> Result<Arc<dyn FitnessProvider>> {
+        user_providers.get(user_id, provider_name, database).await
+    }
+
+    /// Pull new activities from the provider into the local activity store.
+    /// Paging stops once a page reaches activities that were already synced;
+    /// pages left over continue backfilling older history.
+    async fn sync_activity_store(
+        user_id: Uuid,
+        provider_name: &str,
+        database: &Arc<Database>,
+        user_providers: &UserProviders,
+    ) -> Result<usize> {
+        // Leave what's left of a shared upstream quota to requests that need it
+        if let Some(quota) = quota::for_provider(provider_name) {
+            if quota.is_constrained(chrono::Utc::now()) {
+                return Err(anyhow::anyhow!("{} API quota is nearly used up, skipping sync", provider_name));
             }
         }
 
-        // Create new provider instance for user
-        let mut provider = create_provider(provider_name)?;
-        
-        // Get user's decrypted token for this provider
-        let token = match provider_name {
-            "strava" => database.get_strava_token(user_id).await?,
-            "fitbit" => database.get_fitbit_token(user_id).await?,
-            _ => None,
-        };
+        let provider = Self::get_user_provider(user_id, provider_name, database, user_providers).await?;
+        let synced = Self::pull_provider_activities(user_id, provider_name, provider.as_ref(), database).await?;
 
-        if let Some(decrypted_token) = token {
-            // Authenticate provider with user's token
-            let auth_data = AuthData::OAuth2 {
-                client_id: String::new(), // Will be set from config
-                client_secret: String::new(), // Will be set from config
-                access_token: Some(decrypted_token.access_token),
-                refresh_token: Some(decrypted_token.refresh_token),
-            };
+        info!("Synced {} {} activities for user {}", synced, provider_name, user_id);
 
-            provider.authenticate(auth_data).await?;
-        } else {
-            return Err(anyhow::anyhow!("No valid token found for provider {}", provider_name));
+        if let Err(e) = Self::refresh_goal_progress(user_id, database).await {
+            warn!("Goal progress update failed for user {}: {}", user_id, e);
+        }
+
+        Ok(synced)
+    }
+
+    /// Page through the provider's activities, newest first, into the store.
+    /// At most `ACTIVITY_SYNC_MAX_PAGES` pages are read per sync; history older
+    /// than that is picked up from a stored offset on later syncs.
+    async fn pull_provider_activities(
+        user_id: Uuid,
+        provider_name: &str,
+        provider: &dyn FitnessProvider,
+        database: &Database,
+    ) -> Result<usize> {
+        let latest_synced = database.get_latest_activity_date(user_id, provider_name).await?;
+        let mut backfill = database.get_sync_backfill_offset(user_id, provider_name).await?;
+
+        let mut synced = 0;
+        let mut pages = 0;
+        let mut offset = 0;
+        loop {
+            let activities = provider.get_activities(Some(ACTIVITY_SYNC_PAGE_SIZE), Some(offset)).await?;
+            pages += 1;
+            offset += activities.len();
+            let reached_synced = latest_synced
+                .is_some_and(|latest| activities.iter().any(|a| a.start_date <= latest));
+
+            synced += database.upsert_activities(user_id, &activities).await?;
+
+            if activities.len() < ACTIVITY_SYNC_PAGE_SIZE {
+                // Nothing older left at the provider
+                backfill = None;
+                break;
+            }
+            if reached_synced {
+                backfill = backfill.map(|resume| resume.max(offset));
+                break;
+            }
+            if pages == ACTIVITY_SYNC_MAX_PAGES {
+                // More new activities than one sync reads; continue below them next time
+                backfill = Some(offset);
+                break;
+            }
         }
+        database.set_sync_backfill_offset(user_id, provider_name, backfill).await?;
+
+        while let Some(resume) = backfill.filter(|_| pages < ACTIVITY_SYNC_MAX_PAGES) {
+            let activities = provider.get_activities(Some(ACTIVITY_SYNC_PAGE_SIZE), Some(resume)).await?;
+            pages += 1;
+            synced += database.upsert_activities(user_id, &activities).await?;
 
-        // Store provider for reuse
-        {
-            let mut providers_write = user_providers.write().await;
-            providers_write
-                .entry(user_key)
-                .or_insert_with(HashMap::new)
-                .insert(provider_name.to_string(), provider);
+            backfill = (activities.len() == ACTIVITY_SYNC_PAGE_SIZE).then_some(resume + activities.len());
+            database.set_sync_backfill_offset(user_id, provider_name, backfill).await?;
         }
 
-        // Return a new instance (simplified for now)
-        let mut new_provider = create_provider(provider_name)?;
-        if let Some(decrypted_token) = database.get_strava_token(user_id).await? {
-            let auth_data = AuthData::OAuth2 {
-                client_id: String::new(),
-                client_secret: String::new(),
-                access_token: Some(decrypted_token.access_token),
-                refresh_token: Some(decrypted_token.refresh_token),
-            };
-            new_provider.authenticate(auth_data).await?;
+        if let Some(resume) = backfill {
+            info!("{} history for user {} continues from offset {} on the next sync", provider_name, user_id, resume);
         }
-        
-        Ok(new_provider)
+
+        Ok(synced)
     }
 
-    /// Handle connect_strava tool call
-    async fn handle_connect_strava(
-        user_id: Uuid,
-        database: &Arc<Database>,
-        id: Value,
-    ) -> McpResponse {
-        let oauth_routes = OAuthRoutes::new(database.as_ref().clone());
-        
-        match oauth_routes.get_auth_url(user_id, "strava").await {
-            Ok(auth_response) => {
-                McpResponse {
-                    jsonrpc: JSONRPC_VERSION.to_string(),
-                    result: serde_json::to_value(&auth_response).ok(),
-                    error: None,
-                    id,
-                }
+    /// Recompute progress on the user's active goals from the local activity store.
+    /// Milestones are persisted, goals that reach their target are completed and
+    /// goal adjustment suggestions are stored as insights.
+    async fn refresh_goal_progress(user_id: Uuid, database: &Arc<Database>) -> Result<()> {
+        let goals: Vec<Goal> = database
+            .get_user_goals(user_id)
+            .await?
+            .into_iter()
+            .filter(|goal| goal.status == GoalStatus::Active)
+            .collect();
+        let Some(earliest) = goals.iter().map(|goal| goal.created_at).min() else {
+            return Ok(());
+        };
+
+        let query = ActivityQuery {
+            start_date: Some(earliest - chrono::Duration::days(PERFORMANCE_BASELINE_DAYS)),
+            ..ActivityQuery::default()
+        }
+        .unpaged();
+        let history = database.query_activities(user_id, None, &query).await?;
+        let recent_insights = database.get_user_insights(user_id, Some(100)).await?;
+        let engine = AdvancedGoalEngine::new();
+
+        for goal in goals {
+            let report = engine.track_progress(&goal, &history).await?;
+
+            if report.current_value != goal.current_value {
+                database.update_goal_progress(&goal.id, report.current_value).await?;
             }
-            Err(e) => {
-                McpResponse {
-                    jsonrpc: JSONRPC_VERSION.to_string(),
-                    result: None,
-                    error: Some(McpError {
-                        code: ERROR_INTERNAL_ERROR,
-                        message: format!("Failed to generate Strava authorization URL: {}", e),
-                        data: None,
-                    }),
-                    id,
+
+            for milestone in database.save_goal_milestones(&goal.id, &report.milestones_achieved).await? {
+                info!("Goal {} reached milestone '{}'", goal.id, milestone.name);
+            }
+
+            if report.progress_percentage >= 100.0 {
+                database.update_goal_status(user_id, &goal.id, GoalStatus::Completed).await?;
+                info!("Goal {} completed for user {}", goal.id, user_id);
+                continue;
+            }
+
+            if let Some(adjustment) = engine.adjust_goal(&goal, &report).await? {
+                // Only store a suggestion when it differs from the last one for this goal
+                let latest = recent_insights.iter().find(|insight| {
+                    insight["insight_type"] == INSIGHT_TYPE_GOAL_ADJUSTMENT
+                        && insight["metadata"]["goal_id"] == goal.id.as_str()
+                });
+                let adjustment_type = serde_json::json!(adjustment.adjustment_type);
+                if latest.is_some_and(|insight| insight["metadata"]["adjustment_type"] == adjustment_type) {
+                    continue;
                 }
+
+                database
+                    .store_insight(
+                        user_id,
+                        serde_json::json!({
+                            "insight_type": INSIGHT_TYPE_GOAL_ADJUSTMENT,
+                            "title": format!("Adjust goal: {}", goal.title),
+                            "description": adjustment.rationale,
+                            "confidence": adjustment.confidence.as_score(),
+                            "severity": "info",
+                            "metadata": {
+                                "goal_id": goal.id,
+                                "adjustment_type": adjustment_type,
+                                "current_target_value": goal.target_value,
+                                "new_target_value": adjustment.new_target_value
+                            }
+                        }),
+                    )
+                    .await?;
             }
         }
+
+        Ok(())
     }
 
-    /// Handle connect_fitbit tool call
-    async fn handle_connect_fitbit(
+    /// Fetch a page of activities from the provider. The local activity store
+    /// answers instead while the provider's shared API quota is used up.
+    async fn fetch_activities(
+        provider: &Arc<dyn FitnessProvider>,
+        provider_name: &str,
+        limit: Option<usize>,
+        offset: Option<usize>,
         user_id: Uuid,
         database: &Arc<Database>,
-        id: Value,
-    ) -> McpResponse {
-        let oauth_routes = OAuthRoutes::new(database.as_ref().clone());
-        
-        match oauth_routes.get_auth_url(user_id, "fitbit").await {
-            Ok(auth_response) => {
-                McpResponse {
-                    jsonrpc: JSONRPC_VERSION.to_string(),
-                    result: serde_json::to_value(&auth_response).ok(),
-                    error: None,
-                    id,
-                }
+    ) -> Result<Vec<Activity>> {
+        let constrained = quota::for_provider(provider_name)
+            .is_some_and(|quota| quota.is_constrained(chrono::Utc::now()));
+        if !constrained {
+            match provider.get_activities(limit, offset).await {
+                Err(e) if rate_limited(&e).is_some() => warn!("{}, using stored activities", e),
+                result => return result,
             }
-            Err(e) => {
+        }
+
+        let query = ActivityQuery {
+            limit: Some(limit.unwrap_or(DEFAULT_ACTIVITIES_LIMIT)),
+            offset,
+            ..ActivityQuery::default()
+        };
+        database.query_activities(user_id, Some(provider_name), &query).await
+    }
+
+    /// Error response for a failed provider call, with retry data when the
+    /// provider's rate limit was reached
+    fn provider_error_response(action: &str, error: anyhow::Error, id: Value) -> McpResponse {
+        let (code, data) = match rate_limited(&error) {
+            Some(ProviderError::RateLimited { provider, retry_after_secs }) => (
+                ERROR_RATE_LIMITED,
+                Some(serde_json::json!({ "provider": provider, "retry_after_secs": retry_after_secs })),
+            ),
+            _ => (ERROR_INTERNAL_ERROR, None),
+        };
+        McpResponse {
+            jsonrpc: JSONRPC_VERSION.to_string(),
+            result: None,
+            error: Some(McpError {
+                code,
+                message: format!("Failed to {}: {}", action, error),
+                data,
+            }),
+            id,
+        }
+    }
+
+    /// Load the user's activity history for history-based analytics.
+    /// Syncs the local activity store first; when the sync fails the previously
+    /// stored history is used, and the sync error is returned if there is none.
+    async fn load_activity_history(
+        user_id: Uuid,
+        provider_name: &str,
+        database: &Arc<Database>,
+        user_providers: &UserProviders,
+    ) -> Result<Vec<Activity>> {
+        let sync_error = Self::sync_activity_store(user_id, provider_name, database, user_providers).await.err();
+
+        let activities = database
+            .query_activities(user_id, Some(provider_name), &ActivityQuery::default())
+            .await?;
+
+        match sync_error {
+            Some(e) if activities.is_empty() => Err(e),
+            Some(e) => {
+                warn!("Activity sync unavailable for user {}, using stored history: {}", user_id, e);
+                Ok(activities)
+            }
+            None => Ok(activities),
+        }
+    }
+
+    /// Timezone the user's training days and times are read in: the `timezone`
+    /// tool argument, then the user's profile, then UTC
+    async fn resolve_timezone(user_id: Uuid, args: &Value, database: &Arc<Database>) -> Result<Tz, McpError> {
+        let timezone = match args[TIMEZONE].as_str() {
+            Some(timezone) => Some(timezone.to_string()),
+            None => database.get_user_timezone(user_id).await.map_err(|e| McpError {
+                code: ERROR_INTERNAL_ERROR,
+                message: format!("Failed to load user timezone: {}", e),
+                data: None,
+            })?,
+        };
+
+        match timezone {
+            Some(timezone) => timezone.parse::<Tz>().map_err(|_| McpError {
+                code: ERROR_INVALID_PARAMS,
+                message: format!("Unknown timezone '{}'. Use an IANA name such as 'Europe/Paris'", timezone),
+                data: None,
+            }),
+            None => Ok(Tz::UTC),
+        }
+    }
+
+    /// Profile fields the update_profile tool may set, validated and ready for
+    /// `Database::upsert_user_profile`. Arguments it omits are left out so the
+    /// stored values are kept
+    fn profile_update_from_args(args: &Value) -> Result<serde_json::Map<String, Value>, McpError> {
+        const PROFILE_FIELDS: [&str; 10] = [
+            "age", "weight_kg", "fitness_level", "primary_sports", "hours_per_week", "preferred_days",
+            "preferred_duration_minutes", "ftp_watts", "threshold_pace_seconds_per_km", TIMEZONE,
+        ];
+        let invalid = |message: String| McpError { code: ERROR_INVALID_PARAMS, message, data: None };
+
+        let profile: serde_json::Map<String, Value> = PROFILE_FIELDS
+            .iter()
+            .filter_map(|field| args.get(*field).filter(|value| !value.is_null()).map(|value| (field.to_string(), value.clone())))
+            .collect();
+        if profile.is_empty() {
+            return Err(invalid(format!("Provide at least one profile field: {}", PROFILE_FIELDS.join(", "))));
+        }
+
+        if let Some(level) = profile.get("fitness_level") {
+            if level.as_str().and_then(FitnessLevel::parse).is_none() {
+                return Err(invalid("fitness_level must be one of 'beginner', 'intermediate', 'advanced', 'elite'".to_string()));
+            }
+        }
+        if let Some(timezone) = profile.get(TIMEZONE) {
+            if timezone.as_str().and_then(|timezone| timezone.parse::<Tz>().ok()).is_none() {
+                return Err(invalid(format!("Unknown timezone {}. Use an IANA name such as 'Europe/Paris'", timezone)));
+            }
+        }
+        for field in ["primary_sports", "preferred_days"] {
+            if profile.get(field).is_some_and(|value| !value.is_array()) {
+                return Err(invalid(format!("{} must be a list", field)));
+            }
+        }
+        for field in ["age", "preferred_duration_minutes"] {
+            if profile.get(field).is_some_and(|value| value.as_u64().is_none_or(|number| number == 0)) {
+                return Err(invalid(format!("{} must be a positive whole number", field)));
+            }
+        }
+        for field in ["weight_kg", "hours_per_week", "ftp_watts", "threshold_pace_seconds_per_km"] {
+            if profile.get(field).is_some_and(|value| value.as_f64().is_none_or(|number| number <= 0.0)) {
+                return Err(invalid(format!("{} must be a positive number", field)));
+            }
+        }
+
+        Ok(profile)
+    }
+
+    /// Handle connect_strava tool call
+    async fn handle_connect_strava(
+        user_id: Uuid,
+        database: &Arc<Database>,
+        auth_manager: &Arc<AuthManager>,
+        oauth_config: &Arc<OAuthConfig>,
+        id: Value,
+    ) -> McpResponse {
+        let oauth_routes = OAuthRoutes::new(database.as_ref().clone(), auth_manager.as_ref().clone(), oauth_config.clone());
+        
+        match oauth_routes.auth_url_for_user(user_id, "strava").await {
+            Ok(auth_response) => {
+                McpResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    result: serde_json::to_value(&auth_response).ok(),
+                    error: None,
+                    id,
+                }
+            }
+            Err(e) => {
+                McpResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    result: None,
+                    error: Some(McpError {
+                        code: ERROR_INTERNAL_ERROR,
+                        message: format!("Failed to generate Strava authorization URL: {}", e),
+                        data: None,
+                    }),
+                    id,
+                }
+            }
+        }
+    }
+
+    /// Handle connect_fitbit tool call
+    async fn handle_connect_fitbit(
+        user_id: Uuid,
+        database: &Arc<Database>,
+        auth_manager: &Arc<AuthManager>,
+        oauth_config: &Arc<OAuthConfig>,
+        id: Value,
+    ) -> McpResponse {
+        let oauth_routes = OAuthRoutes::new(database.as_ref().clone(), auth_manager.as_ref().clone(), oauth_config.clone());
+        
+        match oauth_routes.auth_url_for_user(user_id, "fitbit").await {
+            Ok(auth_response) => {
+                McpResponse {
+                    jsonrpc: JSONRPC_VERSION.to_string(),
+                    result: serde_json::to_value(&auth_response).ok(),
+                    error: None,
+                    id,
+                }
+            }
+            Err(e) => {
                 McpResponse {
                     jsonrpc: JSONRPC_VERSION.to_string(),
                     result: None,
@@ -618,9 +1273,11 @@ impl MultiTenantMcpServer {
     async fn handle_get_connection_status(
         user_id: Uuid,
         database: &Arc<Database>,
+        auth_manager: &Arc<AuthManager>,
+        oauth_config: &Arc<OAuthConfig>,
         id: Value,
     ) -> McpResponse {
-        let oauth_routes = OAuthRoutes::new(database.as_ref().clone());
+        let oauth_routes = OAuthRoutes::new(database.as_ref().clone(), auth_manager.as_ref().clone(), oauth_config.clone());
         
         match oauth_routes.get_connection_status(user_id).await {
             Ok(statuses) => {
@@ -651,9 +1308,11 @@ impl MultiTenantMcpServer {
         user_id: Uuid,
         provider: &str,
         database: &Arc<Database>,
+        auth_manager: &Arc<AuthManager>,
+        oauth_config: &Arc<OAuthConfig>,
         id: Value,
     ) -> McpResponse {
-        let oauth_routes = OAuthRoutes::new(database.as_ref().clone());
+        let oauth_routes = OAuthRoutes::new(database.as_ref().clone(), auth_manager.as_ref().clone(), oauth_config.clone());
         
         match oauth_routes.disconnect_provider(user_id, provider).await {
             Ok(()) => {
@@ -692,19 +1351,34 @@ impl MultiTenantMcpServer {
         id: Value,
         user_id: Uuid,
         database: &Arc<Database>,
+        user_providers: &UserProviders,
     ) -> McpResponse {
         let result = match tool_name {
             SET_GOAL => {
-                let goal_data = args.clone();
-                
-                // Store goal in database
-                match database.create_goal(user_id, goal_data).await {
+                let goal = match Goal::from_args(&user_id.to_string(), args) {
+                    Ok(goal) => goal,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                match database.create_goal(&goal).await {
                     Ok(goal_id) => {
                         let response = serde_json::json!({
                             "goal_created": {
                                 "goal_id": goal_id,
-                                "status": "active",
-                                "message": "Goal successfully created"
+                                "status": goal.status,
+                                "goal": goal,
+                                "message": GOAL_CREATED
                             }
                         });
                         Some(response)
@@ -725,35 +1399,132 @@ impl MultiTenantMcpServer {
             }
             TRACK_PROGRESS => {
                 let goal_id = args[GOAL_ID].as_str().unwrap_or("");
+
+                // Pick up activities stored since the last sync
+                if let Err(e) = Self::refresh_goal_progress(user_id, database).await {
+                    warn!("Goal progress update failed for user {}: {}", user_id, e);
+                }
                 
-                match database.get_user_goals(user_id).await {
-                    Ok(goals) => {
-                        if let Some(goal) = goals.iter().find(|g| g["id"] == goal_id) {
-                            let response = serde_json::json!({
-                                "progress_report": {
-                                    "goal_id": goal_id,
-                                    "goal": goal,
-                                    "progress_percentage": 65.0,
-                                    "on_track": true,
-                                    "insights": [
-                                        "Making good progress toward your goal",
-                                        "Maintain current training frequency"
-                                    ]
-                                }
+                match database.get_goal(user_id, goal_id).await {
+                    Ok(Some(goal)) => {
+                        let milestones = database.get_goal_milestones(&goal.id).await.unwrap_or_default();
+                        let adjustment = database
+                            .get_user_insights(user_id, Some(100))
+                            .await
+                            .unwrap_or_default()
+                            .into_iter()
+                            .find(|insight| {
+                                insight["insight_type"] == INSIGHT_TYPE_GOAL_ADJUSTMENT
+                                    && insight["metadata"]["goal_id"] == goal.id.as_str()
                             });
-                            Some(response)
+                        let remaining = match goal.goal_type {
+                            GoalType::Time { .. } if goal.current_value <= 0.0 => None,
+                            GoalType::Time { .. } => Some((goal.current_value - goal.target_value).max(0.0)),
+                            _ => Some((goal.target_value - goal.current_value).max(0.0)),
+                        };
+                        let now = chrono::Utc::now();
+                        let progress_percentage = goal.progress_percentage();
+                        let elapsed = (now - goal.created_at).num_seconds() as f64;
+                        let planned = (goal.target_date - goal.created_at).num_seconds() as f64;
+                        let expected_percentage = if planned > 0.0 {
+                            (elapsed / planned * 100.0).clamp(0.0, 100.0)
                         } else {
+                            100.0
+                        };
+                        let on_track = progress_percentage >= expected_percentage;
+
+                        let insight = match goal.status {
+                            GoalStatus::Completed => "Goal completed".to_string(),
+                            GoalStatus::Cancelled => "Goal was cancelled".to_string(),
+                            GoalStatus::Paused => "Goal is paused; resume it with update_goal".to_string(),
+                            GoalStatus::Active if on_track => "On track to reach the target by the target date".to_string(),
+                            GoalStatus::Active => format!(
+                                "Behind schedule: {:.0}% done, {:.0}% expected by now",
+                                progress_percentage, expected_percentage
+                            ),
+                        };
+
+                        let response = serde_json::json!({
+                            "progress_report": {
+                                "goal_id": goal.id,
+                                "goal": goal,
+                                "progress_percentage": progress_percentage,
+                                "expected_percentage": expected_percentage,
+                                "remaining": remaining,
+                                "days_remaining": (goal.target_date - now).num_days().max(0),
+                                "on_track": on_track,
+                                "milestones": milestones,
+                                "suggested_adjustment": adjustment,
+                                "insights": [insight]
+                            }
+                        });
+                        Some(response)
+                    }
+                    Ok(None) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: format!("Goal with ID '{}' not found", goal_id),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get goals: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            LIST_GOALS => {
+                let status = match args["status"].as_str() {
+                    Some(name) => match GoalStatus::parse(name) {
+                        Some(status) => Some(status),
+                        None => {
                             return McpResponse {
                                 jsonrpc: JSONRPC_VERSION.to_string(),
                                 result: None,
                                 error: Some(McpError {
                                     code: ERROR_INVALID_PARAMS,
-                                    message: format!("Goal with ID '{}' not found", goal_id),
+                                    message: format!("Unknown goal status '{}'", name),
                                     data: None,
                                 }),
                                 id,
                             };
                         }
+                    },
+                    None => None,
+                };
+                let kind = args["goal_type"].as_str().map(str::to_lowercase);
+
+                match database.get_user_goals(user_id).await {
+                    Ok(goals) => {
+                        let goals: Vec<Value> = goals
+                            .into_iter()
+                            .filter(|goal| status.is_none_or(|status| goal.status == status))
+                            .filter(|goal| kind.as_deref().is_none_or(|kind| goal.goal_type.kind() == kind))
+                            .map(|goal| {
+                                let progress_percentage = goal.progress_percentage();
+                                let mut value = serde_json::to_value(goal).unwrap_or_default();
+                                value["progress_percentage"] = serde_json::json!(progress_percentage);
+                                value
+                            })
+                            .collect();
+
+                        Some(serde_json::json!({
+                            "total": goals.len(),
+                            "goals": goals
+                        }))
                     }
                     Err(e) => {
                         return McpResponse {
@@ -769,6 +1540,115 @@ impl MultiTenantMcpServer {
                     }
                 }
             }
+            UPDATE_GOAL | PAUSE_GOAL | COMPLETE_GOAL => {
+                let goal_id = args[GOAL_ID].as_str().unwrap_or("");
+
+                let mut goal = match database.get_goal(user_id, goal_id).await {
+                    Ok(Some(goal)) => goal,
+                    Ok(None) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: format!("{}: '{}'", GOAL_NOT_FOUND, goal_id),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get goal: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let change = match tool_name {
+                    PAUSE_GOAL => goal.transition_to(GoalStatus::Paused),
+                    COMPLETE_GOAL => goal.transition_to(GoalStatus::Completed),
+                    _ => goal.apply_update(args),
+                };
+                if let Err(e) = change {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: e.to_string(),
+                            data: None,
+                        }),
+                        id,
+                    };
+                }
+                goal.updated_at = chrono::Utc::now();
+
+                match database.update_goal(&goal).await {
+                    Ok(_) => Some(serde_json::json!({
+                        "goal_updated": {
+                            "goal_id": goal.id,
+                            "status": goal.status,
+                            "goal": goal,
+                            "message": GOAL_UPDATED
+                        }
+                    })),
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to update goal: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            DELETE_GOAL => {
+                let goal_id = args[GOAL_ID].as_str().unwrap_or("");
+
+                match database.delete_goal(user_id, goal_id).await {
+                    Ok(true) => Some(serde_json::json!({
+                        "goal_deleted": {
+                            "goal_id": goal_id,
+                            "message": GOAL_DELETED
+                        }
+                    })),
+                    Ok(false) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: format!("{}: '{}'", GOAL_NOT_FOUND, goal_id),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to delete goal: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
             ANALYZE_GOAL_FEASIBILITY => {
                 let _goal_data = args.clone();
                 
@@ -858,42 +1738,945 @@ impl MultiTenantMcpServer {
                 });
                 Some(response)
             }
-            ANALYZE_TRAINING_LOAD => {
-                let response = serde_json::json!({
-                    "training_load_analysis": {
-                        "weekly_hours": 5.2,
-                        "weekly_distance_km": 35.0,
-                        "load_level": "moderate",
-                        "total_activities": 12,
-                        "insights": [
-                            "Current training load: moderate (5.2 hours/week)",
-                            "Training load is appropriate for current fitness level",
-                            "Consider periodization for optimal adaptation"
-                        ],
-                        "recommendations": [
-                            "Maintain current level",
-                            "Focus on consistency"
-                        ]
+            GENERATE_TRAINING_PLAN => {
+                let event = match TargetEvent::from_args(args) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                if let Some(provider_name) = args[PROVIDER].as_str().filter(|p| !p.is_empty()) {
+                    if let Err(e) = Self::sync_activity_store(user_id, provider_name, database, user_providers).await {
+                        warn!("Activity sync unavailable for user {}: {}", user_id, e);
+                    }
+                }
+
+                let mut profile = match database.get_user_fitness_profile(user_id).await {
+                    Ok(profile) => profile.unwrap_or_else(|| UserFitnessProfile::new(&user_id.to_string())),
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to load user profile: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+                if let Err(e) = profile.preferences.time_availability.apply_args(args) {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: e.to_string(),
+                            data: None,
+                        }),
+                        id,
+                    };
+                }
+
+                // Recent history is only used for heart rate zones
+                let query = ActivityQuery {
+                    start_date: Some(chrono::Utc::now() - chrono::Duration::days(PERFORMANCE_BASELINE_DAYS)),
+                    ..ActivityQuery::default()
+                }
+                .unpaged();
+                let history = match database.query_activities(user_id, None, &query).await {
+                    Ok(history) => history,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to load activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let engine = AdvancedRecommendationEngine::with_profile(profile.clone());
+                let plan = match engine.generate_training_plan(&profile, event, &history).await {
+                    Ok(plan) => plan,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                match database.create_training_plan(&plan).await {
+                    Ok(plan_id) => Some(serde_json::json!({
+                        "plan_id": plan_id,
+                        "training_plan": plan,
+                        "message": TRAINING_PLAN_CREATED
+                    })),
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to store training plan: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            GET_TRAINING_PLAN => {
+                let plan = match args["plan_id"].as_str() {
+                    Some(plan_id) => database.get_training_plan(user_id, plan_id).await,
+                    None => database
+                        .get_user_training_plans(user_id)
+                        .await
+                        .map(|plans| plans.into_iter().next()),
+                };
+                let plan = match plan {
+                    Ok(Some(plan)) => plan,
+                    Ok(None) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: TRAINING_PLAN_NOT_FOUND.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get training plan: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                if let Some(provider_name) = args[PROVIDER].as_str().filter(|p| !p.is_empty()) {
+                    if let Err(e) = Self::sync_activity_store(user_id, provider_name, database, user_providers).await {
+                        warn!("Activity sync unavailable for user {}: {}", user_id, e);
+                    }
+                }
+
+                let query = ActivityQuery {
+                    start_date: plan.start_date.and_hms_opt(0, 0, 0).map(|start| start.and_utc()),
+                    ..ActivityQuery::default()
+                }
+                .unpaged();
+                match database.query_activities(user_id, None, &query).await {
+                    Ok(activities) => {
+                        let compliance = plan.compliance(&activities, chrono::Utc::now().date_naive());
+                        Some(serde_json::json!({
+                            "training_plan": plan,
+                            "compliance": compliance
+                        }))
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to load activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            EXPORT_WORKOUT => {
+                let format_name = args["format"].as_str().unwrap_or("");
+                let Some(format) = WorkoutFormat::parse(format_name) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!("Unknown workout format '{}'. Use zwo, erg, mrc or fit", format_name),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+
+                // A custom workout is given as steps, otherwise one is taken from a stored plan
+                let (workout, resource_path) = if args["steps"].is_array() {
+                    match StructuredWorkout::from_args(args) {
+                        Ok(workout) => {
+                            let slug: String = workout
+                                .name
+                                .chars()
+                                .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+                                .collect();
+                            (workout, format!("custom/{}", slug))
+                        }
+                        Err(e) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: e.to_string(),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    }
+                } else {
+                    let date = match args["date"].as_str().map(Goal::parse_date) {
+                        Some(Ok(date)) => date.date_naive(),
+                        Some(Err(e)) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: e.to_string(),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                        None => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: "Either steps for a custom workout or the date of a planned workout is required".to_string(),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    };
+                    let plan = match args["plan_id"].as_str() {
+                        Some(plan_id) => database.get_training_plan(user_id, plan_id).await,
+                        None => database
+                            .get_user_training_plans(user_id)
+                            .await
+                            .map(|plans| plans.into_iter().next()),
+                    };
+                    let plan = match plan {
+                        Ok(Some(plan)) => plan,
+                        Ok(None) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: TRAINING_PLAN_NOT_FOUND.to_string(),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                        Err(e) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INTERNAL_ERROR,
+                                    message: format!("Failed to get training plan: {}", e),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    };
+                    let Some(planned) = plan
+                        .weeks
+                        .iter()
+                        .flat_map(|week| &week.workouts)
+                        .find(|workout| workout.date == date)
+                    else {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: format!("No workout planned on {}", date),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    };
+                    match StructuredWorkout::from_planned(planned) {
+                        Ok(workout) => (workout, format!("{}/{}", plan.id, date)),
+                        Err(e) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: e.to_string(),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    }
+                };
+
+                let mut thresholds = match database.get_workout_thresholds(user_id).await {
+                    Ok(thresholds) => thresholds,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to load workout thresholds: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+                if let Err(e) = thresholds.apply_args(args) {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: e.to_string(),
+                            data: None,
+                        }),
+                        id,
+                    };
+                }
+
+                let file = match format.export(&workout, &thresholds) {
+                    Ok(file) => file,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let mut resource = serde_json::json!({
+                    "uri": format!("workout://{}.{}", resource_path, format.extension()),
+                    "mimeType": format.mime_type()
+                });
+                if format.is_binary() {
+                    resource["blob"] = Value::String(general_purpose::STANDARD.encode(&file));
+                } else {
+                    resource["text"] = Value::String(String::from_utf8_lossy(&file).into_owned());
+                }
+
+                Some(serde_json::json!({
+                    "content": [{
+                        "type": "resource",
+                        "resource": resource
+                    }]
+                }))
+            }
+            GET_CALENDAR_FEED => {
+                // Only a hash of the token is stored, so the URL is shown when a token is issued
+                let rotate = args["rotate_token"].as_bool().unwrap_or(false);
+                let token = match database.has_calendar_token(user_id).await {
+                    Ok(true) if !rotate => Ok(None),
+                    Ok(_) => {
+                        let token = generate_calendar_token();
+                        database
+                            .set_calendar_token(user_id, &hash_calendar_token(&token))
+                            .await
+                            .map(|_| Some(token))
+                    }
+                    Err(e) => Err(e),
+                };
+                let token = match token {
+                    Ok(token) => token,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get calendar token: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+                let ics = match build_user_calendar(database, user_id).await {
+                    Ok(ics) => ics,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to build calendar: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let feed_url =
+                    token.map(|token| format!("{}/calendar/{}.ics", env_config::public_base_url(), token));
+                Some(serde_json::json!({
+                    "feed_url": feed_url,
+                    "message": if feed_url.is_none() { Some(CALENDAR_FEED_URL_NOT_SHOWN) } else { None },
+                    "content": [{
+                        "type": "resource",
+                        "resource": {
+                            "uri": feed_url.as_deref().unwrap_or("calendar://feed.ics"),
+                            "mimeType": "text/calendar",
+                            "text": ics
+                        }
+                    }]
+                }))
+            }
+            UPDATE_PROFILE => {
+                let profile = match Self::profile_update_from_args(args) {
+                    Ok(profile) => profile,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
+                    }
+                };
+                let updated_fields: Vec<String> = profile.keys().cloned().collect();
+
+                match database.upsert_user_profile(user_id, Value::Object(profile)).await {
+                    Ok(()) => Some(serde_json::json!({
+                        "updated_fields": updated_fields,
+                        "message": PROFILE_UPDATED
+                    })),
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to update profile: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            REVOKE_CALENDAR_FEED => match database.revoke_calendar_token(user_id).await {
+                Ok(revoked) => Some(serde_json::json!({
+                    "revoked": revoked,
+                    "message": if revoked { CALENDAR_FEED_REVOKED } else { CALENDAR_FEED_NOT_FOUND }
+                })),
+                Err(e) => {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INTERNAL_ERROR,
+                            message: format!("Failed to revoke calendar feed: {}", e),
+                            data: None,
+                        }),
+                        id,
+                    };
+                }
+            },
+            ANALYZE_TRAINING_LOAD => {
+                let response = serde_json::json!({
+                    "training_load_analysis": {
+                        "weekly_hours": 5.2,
+                        "weekly_distance_km": 35.0,
+                        "load_level": "moderate",
+                        "total_activities": 12,
+                        "insights": [
+                            "Current training load: moderate (5.2 hours/week)",
+                            "Training load is appropriate for current fitness level",
+                            "Consider periodization for optimal adaptation"
+                        ],
+                        "recommendations": [
+                            "Maintain current level",
+                            "Focus on consistency"
+                        ]
+                    }
+                });
+                Some(response)
+            }
+            DETECT_PATTERNS => {
+                let pattern_filter = args["pattern_type"].as_str().unwrap_or("all");
+                let Some(pattern_types) = PatternType::from_filter(pattern_filter) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!("Unknown pattern_type '{}'", pattern_filter),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+
+                let timezone = match Self::resolve_timezone(user_id, args, database).await {
+                    Ok(timezone) => timezone,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
+                    }
+                };
+
+                let provider_name = args[PROVIDER].as_str().unwrap_or("");
+                let activities = match Self::load_activity_history(user_id, provider_name, database, user_providers).await {
+                    Ok(activities) => activities,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let activities: Vec<Activity> = match args["timeframe"].as_str().and_then(TimeFrame::parse) {
+                    Some(timeframe) => {
+                        let start = timeframe.start_date();
+                        activities.into_iter().filter(|a| a.start_date >= start).collect()
+                    }
+                    None => activities,
+                };
+
+                let analysis = PatternDetector::new()
+                    .with_timezone(timezone)
+                    .detect_patterns(&activities, &pattern_types);
+                let recommendations: Vec<&String> = analysis
+                    .patterns
+                    .iter()
+                    .filter_map(|pattern| pattern.recommendation.as_ref())
+                    .collect();
+
+                Some(serde_json::json!({
+                    "pattern_analysis": {
+                        "pattern_type": pattern_filter,
+                        "total_activities": analysis.activities_analyzed,
+                        "patterns_checked": analysis.patterns_checked,
+                        "patterns_detected": analysis.patterns,
+                        "recommendations": recommendations
+                    }
+                }))
+            }
+            FIND_STREAKS => {
+                let period_name = args["period"].as_str().unwrap_or("day");
+                let Some(period) = StreakPeriod::parse(period_name) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!("Unknown period '{}'. Use 'day' or 'week'", period_name),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+
+                let criteria = StreakCriteria {
+                    period,
+                    sport_type: args["sport_type"].as_str().map(SportType::from_name),
+                    min_distance_meters: args["min_distance_km"].as_f64().map(|km| km * 1000.0),
+                    min_duration_seconds: args["min_duration_minutes"].as_f64().map(|m| (m * 60.0) as u64),
+                    min_activities: args["min_activities"].as_u64().map(|n| n.max(1) as u32).unwrap_or(1),
+                    min_total_distance_meters: args["min_total_distance_km"].as_f64().map(|km| km * 1000.0),
+                    min_total_duration_seconds: args["min_total_duration_minutes"].as_f64().map(|m| (m * 60.0) as u64),
+                };
+
+                let timezone = match Self::resolve_timezone(user_id, args, database).await {
+                    Ok(timezone) => timezone,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
+                    }
+                };
+
+                let provider_name = args[PROVIDER].as_str().unwrap_or("");
+                match Self::load_activity_history(user_id, provider_name, database, user_providers).await {
+                    Ok(activities) => {
+                        let report = StreakEngine::new(criteria)
+                            .with_timezone(timezone)
+                            .analyze(&activities, chrono::Utc::now());
+                        Some(serde_json::json!({ "streaks": report }))
+                    }
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            QUERY_ACTIVITIES => {
+                let timezone = match Self::resolve_timezone(user_id, args, database).await {
+                    Ok(timezone) => timezone,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
+                    }
+                };
+                let query = match ActivityQuery::from_args(args, timezone) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+                let group_by = match args["group_by"].as_str() {
+                    Some(value) => match ActivityGroupBy::parse(value) {
+                        Some(group_by) => Some(group_by),
+                        None => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: format!("Unknown group_by '{}'. Use week, month, year or sport", value),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    },
+                    None => None,
+                };
+
+                // Without a provider the whole store is queried as-is
+                let provider_name = args[PROVIDER].as_str().filter(|p| !p.is_empty());
+                if let Some(provider_name) = provider_name {
+                    if args["sync"].as_bool().unwrap_or(true) {
+                        if let Err(e) = Self::sync_activity_store(user_id, provider_name, database, user_providers).await {
+                            warn!("Activity sync unavailable for user {}: {}", user_id, e);
+                        }
+                    }
+                }
+
+                let query_result = async {
+                    let total = database.count_activities(user_id, provider_name, &query).await?;
+                    let activities = database.query_activities(user_id, provider_name, &query).await?;
+                    let groups = match group_by {
+                        Some(group_by) => {
+                            let matching = database.query_activities(user_id, provider_name, &query.unpaged()).await?;
+                            Some(group_activities(&matching, group_by, timezone))
+                        }
+                        None => None,
+                    };
+                    Ok::<_, anyhow::Error>((total, activities, groups))
+                }
+                .await;
+
+                match query_result {
+                    Ok((total, activities, groups)) => Some(serde_json::json!({
+                        "query": query,
+                        "total_matches": total,
+                        "returned": activities.len(),
+                        "activities": activities,
+                        "group_by": group_by,
+                        "groups": groups
+                    })),
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to query activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                }
+            }
+            EXPORT_ACTIVITIES => {
+                let format_name = args["format"].as_str().unwrap_or("csv");
+                let Some(format) = ExportFormat::parse(format_name) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!("Unknown export format '{}'. Use csv, gpx, tcx or parquet", format_name),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+                let timezone = match Self::resolve_timezone(user_id, args, database).await {
+                    Ok(timezone) => timezone,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
+                    }
+                };
+                let query = match export_query(args, timezone) {
+                    Ok(query) => query,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INVALID_PARAMS,
+                                message: e.to_string(),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let provider_name = args[PROVIDER].as_str().filter(|p| !p.is_empty());
+                if let Some(provider_name) = provider_name {
+                    if args["sync"].as_bool().unwrap_or(true) {
+                        if let Err(e) = Self::sync_activity_store(user_id, provider_name, database, user_providers).await {
+                            warn!("Activity sync unavailable for user {}: {}", user_id, e);
+                        }
+                    }
+                }
+
+                let export = match export_user_activities(database, user_id, provider_name, &query, format).await {
+                    Ok(export) => export,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to export activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
                     }
+                };
+
+                let mut resource = serde_json::json!({
+                    "uri": format!("activities://export.{}", format.extension()),
+                    "mimeType": format.mime_type()
                 });
-                Some(response)
+                if format.is_binary() {
+                    resource["blob"] = Value::String(general_purpose::STANDARD.encode(&export.bytes));
+                } else {
+                    resource["text"] = Value::String(String::from_utf8_lossy(&export.bytes).into_owned());
+                }
+
+                let mut summary = format!("Exported {} activities as {}", export.exported, format.extension());
+                if !export.skipped.is_empty() {
+                    summary.push_str(&format!(
+                        "; {} without recorded streams were left out: {}",
+                        export.skipped.len(),
+                        export.skipped.join(", ")
+                    ));
+                }
+
+                Some(serde_json::json!({
+                    "content": [
+                        { "type": "text", "text": summary },
+                        { "type": "resource", "resource": resource }
+                    ],
+                    "exported": export.exported,
+                    "skipped": export.skipped
+                }))
             }
-            DETECT_PATTERNS => {
-                let response = serde_json::json!({
-                    "pattern_analysis": {
-                        "pattern_type": args["pattern_type"].as_str().unwrap_or("weekly"),
-                        "total_activities": 25,
-                        "patterns_detected": [
-                            "Regular training frequency detected",
-                            "Consistent effort levels across activities"
-                        ],
-                        "recommendations": [
-                            "Continue current training consistency",
-                            "Consider adding variety to workout types"
-                        ]
+            GENERATE_PERIOD_REPORT => {
+                let period_name = args["period"].as_str().unwrap_or("month");
+                let Some(period) = ReportPeriod::parse(period_name) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!("Unknown period '{}'. Use 'week', 'month', 'season' or 'year'", period_name),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+                let timezone = match Self::resolve_timezone(user_id, args, database).await {
+                    Ok(timezone) => timezone,
+                    Err(error) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(error),
+                            id,
+                        };
                     }
-                });
-                Some(response)
+                };
+                let anchor = match args["date"].as_str() {
+                    Some(date) => match chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+                        Ok(anchor) => anchor,
+                        Err(_) => {
+                            return McpResponse {
+                                jsonrpc: JSONRPC_VERSION.to_string(),
+                                result: None,
+                                error: Some(McpError {
+                                    code: ERROR_INVALID_PARAMS,
+                                    message: format!("Invalid date '{}'. Use YYYY-MM-DD", date),
+                                    data: None,
+                                }),
+                                id,
+                            };
+                        }
+                    },
+                    None => chrono::Utc::now().with_timezone(&timezone).date_naive(),
+                };
+                let window = period.window(anchor, timezone);
+
+                let provider_name = args[PROVIDER].as_str().unwrap_or("");
+                let history = match Self::load_activity_history(user_id, provider_name, database, user_providers).await {
+                    Ok(history) => history,
+                    Err(e) => {
+                        return McpResponse {
+                            jsonrpc: JSONRPC_VERSION.to_string(),
+                            result: None,
+                            error: Some(McpError {
+                                code: ERROR_INTERNAL_ERROR,
+                                message: format!("Failed to get activities: {}", e),
+                                data: None,
+                            }),
+                            id,
+                        };
+                    }
+                };
+
+                let now = chrono::Utc::now();
+                let mut context = ReportContext {
+                    goals: database
+                        .get_user_goals(user_id)
+                        .await
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|goal| GoalOutcome::from_goal(goal, now))
+                        .collect(),
+                    ..ReportContext::default()
+                };
+
+                // Weather and geocoding are external calls, so only the longest
+                // activities of the period are enriched
+                let include_weather = args["include_weather"].as_bool().unwrap_or(false);
+                let include_locations = args["include_locations"].as_bool().unwrap_or(false);
+                if include_weather || include_locations {
+                    let mut candidates: Vec<&Activity> = history
+                        .iter()
+                        .filter(|a| window.contains(a.start_date) && a.start_latitude.is_some() && a.start_longitude.is_some())
+                        .collect();
+                    candidates.sort_by(|a, b| b.distance_meters.partial_cmp(&a.distance_meters).unwrap_or(std::cmp::Ordering::Equal));
+                    candidates.truncate(REPORT_MAX_ENRICHMENT_LOOKUPS);
+
+                    let weather_config = FitnessConfig::load(None).unwrap_or_default().weather_api.unwrap_or_default();
+                    let mut weather_service = WeatherService::new(weather_config);
+                    let mut location_service = crate::intelligence::location::LocationService::new();
+
+                    for a in candidates {
+                        if include_weather {
+                            if let Ok(Some(conditions)) = weather_service
+                                .get_weather_for_activity(a.start_latitude, a.start_longitude, a.start_date)
+                                .await
+                            {
+                                context.weather.insert(a.id.clone(), conditions);
+                            }
+                        }
+                        if let (true, Some(lat), Some(lon)) = (include_locations, a.start_latitude, a.start_longitude) {
+                            match location_service.get_location_from_coordinates(lat, lon).await {
+                                Ok(location) => {
+                                    context.locations.insert(a.id.clone(), location);
+                                }
+                                Err(e) => warn!("Failed to get location data: {}", e),
+                            }
+                        }
+                    }
+                }
+
+                let report = PeriodReportGenerator::new().generate(&window, &history, &context, now);
+                let markdown = report.to_markdown();
+                Some(serde_json::json!({
+                    "period_report": report,
+                    "markdown": markdown
+                }))
             }
             ANALYZE_PERFORMANCE_TRENDS => {
                 let response = serde_json::json!({
@@ -932,68 +2715,52 @@ impl MultiTenantMcpServer {
         }
     }
 
+    /// Power-based metrics from the activity's stored power samples, relative
+    /// to the FTP and weight in the user's profile. None without power samples
+    async fn power_metrics(user_id: Uuid, activity: &Activity, database: &Arc<Database>) -> Option<AdvancedMetrics> {
+        let details = database.get_activity_details(user_id, &activity.provider, &activity.id).await.ok().flatten()?;
+        if !details.streams.iter().any(|point| point.power.is_some()) {
+            return None;
+        }
+        let ftp = database.get_workout_thresholds(user_id).await.ok().and_then(|thresholds| thresholds.ftp_watts);
+        let weight_kg = database.get_user_fitness_profile(user_id).await.ok().flatten().and_then(|profile| profile.weight);
+
+        MetricsCalculator::new()
+            .with_user_data(ftp, None, None, None, weight_kg)
+            .calculate_detailed_metrics(&details)
+            .ok()
+    }
+
     /// Execute tool call with provider
     async fn execute_tool_call(
         tool_name: &str,
         args: &Value,
-        provider: &Box<dyn FitnessProvider>,
+        provider: &Arc<dyn FitnessProvider>,
         id: Value,
-        _user_id: Uuid,
-        _database: &Arc<Database>,
+        user_id: Uuid,
+        database: &Arc<Database>,
     ) -> McpResponse {
+        let provider_name = args[PROVIDER].as_str().unwrap_or("");
         let result = match tool_name {
             GET_ACTIVITIES => {
                 let limit = args[LIMIT].as_u64().map(|n| n as usize);
                 let offset = args[OFFSET].as_u64().map(|n| n as usize);
                 
-                match provider.get_activities(limit, offset).await {
+                match Self::fetch_activities(provider, provider_name, limit, offset, user_id, database).await {
                     Ok(activities) => serde_json::to_value(activities).ok(),
-                    Err(e) => {
-                        return McpResponse {
-                            jsonrpc: JSONRPC_VERSION.to_string(),
-                            result: None,
-                            error: Some(McpError {
-                                code: ERROR_INTERNAL_ERROR,
-                                message: format!("Failed to get activities: {}", e),
-                                data: None,
-                            }),
-                            id,
-                        };
-                    }
+                    Err(e) => return Self::provider_error_response("get activities", e, id),
                 }
             }
             GET_ATHLETE => {
                 match provider.get_athlete().await {
                     Ok(athlete) => serde_json::to_value(athlete).ok(),
-                    Err(e) => {
-                        return McpResponse {
-                            jsonrpc: JSONRPC_VERSION.to_string(),
-                            result: None,
-                            error: Some(McpError {
-                                code: ERROR_INTERNAL_ERROR,
-                                message: format!("Failed to get athlete: {}", e),
-                                data: None,
-                            }),
-                            id,
-                        };
-                    }
+                    Err(e) => return Self::provider_error_response("get athlete", e, id),
                 }
             }
             GET_STATS => {
                 match provider.get_stats().await {
                     Ok(stats) => serde_json::to_value(stats).ok(),
-                    Err(e) => {
-                        return McpResponse {
-                            jsonrpc: JSONRPC_VERSION.to_string(),
-                            result: None,
-                            error: Some(McpError {
-                                code: ERROR_INTERNAL_ERROR,
-                                message: format!("Failed to get stats: {}", e),
-                                data: None,
-                            }),
-                            id,
-                        };
-                    }
+                    Err(e) => return Self::provider_error_response("get stats", e, id),
                 }
             }
             GET_ACTIVITY_INTELLIGENCE => {
@@ -1002,7 +2769,7 @@ impl MultiTenantMcpServer {
                 let include_location = args["include_location"].as_bool().unwrap_or(true);
                 
                 // Get activities from provider
-                match provider.get_activities(Some(100), None).await {
+                match Self::fetch_activities(provider, provider_name, Some(100), None, user_id, database).await {
                     Ok(activities) => {
                         if let Some(activity) = activities.iter().find(|a| a.id == activity_id) {
                             // Create activity analyzer
@@ -1220,7 +2987,13 @@ impl MultiTenantMcpServer {
                                         })
                                     },
                                     "elevation_gain_m": activity.elevation_gain,
-                                    "calories_burned": activity.calories
+                                    "calories_burned": activity.calories,
+                                    "power_metrics": Self::power_metrics(user_id, activity, database).await.map(|metrics| serde_json::json!({
+                                        "power_to_weight_ratio": metrics.power_to_weight_ratio,
+                                        "intensity_factor": metrics.intensity_factor,
+                                        "training_stress_score": metrics.training_stress_score,
+                                        "variability_index": metrics.variability_index
+                                    }))
                                 }
                             });
                             Some(response)
@@ -1285,44 +3058,72 @@ impl MultiTenantMcpServer {
                     }
                 }
             }
-            "compare_activities" => {
-                let activity_id1 = args["activity_id1"].as_str().unwrap_or("");
-                let activity_id2 = args["activity_id2"].as_str().unwrap_or("");
-                
-                match provider.get_activities(Some(100), None).await {
+            COMPARE_ACTIVITIES => {
+                let activity_id = args[ACTIVITY_ID].as_str().unwrap_or("");
+                let comparison_name = args["comparison_type"].as_str().unwrap_or("similar_activities");
+                let last_n = args["n"].as_u64().map(|n| n as usize);
+                let include_weather = args["include_weather"].as_bool().unwrap_or(false);
+
+                let Some(comparison_type) = ComparisonType::parse(comparison_name, last_n) else {
+                    return McpResponse {
+                        jsonrpc: JSONRPC_VERSION.to_string(),
+                        result: None,
+                        error: Some(McpError {
+                            code: ERROR_INVALID_PARAMS,
+                            message: format!(
+                                "Unknown comparison_type '{}'. Use 'similar_activities', 'personal_best' or 'last_n_average'",
+                                comparison_name
+                            ),
+                            data: None,
+                        }),
+                        id,
+                    };
+                };
+
+                match provider.get_activities(Some(200), None).await {
                     Ok(activities) => {
-                        let activity1 = activities.iter().find(|a| a.id == activity_id1);
-                        let activity2 = activities.iter().find(|a| a.id == activity_id2);
-                        
-                        if let (Some(a1), Some(a2)) = (activity1, activity2) {
-                            let response = serde_json::json!({
-                                "comparison": {
-                                    "activity1": {
-                                        "id": a1.id,
-                                        "name": a1.name,
-                                        "duration_minutes": a1.duration_seconds / 60,
-                                        "distance_km": a1.distance_meters.map(|d| d / 1000.0)
-                                    },
-                                    "activity2": {
-                                        "id": a2.id,
-                                        "name": a2.name,
-                                        "duration_minutes": a2.duration_seconds / 60,
-                                        "distance_km": a2.distance_meters.map(|d| d / 1000.0)
-                                    },
-                                    "insights": [
-                                        "Activity comparison shows differences in duration and distance",
-                                        "For detailed analysis, consider pace, heart rate, and effort levels"
-                                    ]
+                        if let Some(activity) = activities.iter().find(|a| a.id == activity_id) {
+                            let (baseline, _) = AdvancedActivityAnalyzer::select_baseline(activity, &activities, &comparison_type);
+
+                            // Weather lookups are limited to the activity and its baseline
+                            let mut weather = HashMap::new();
+                            if include_weather {
+                                let weather_config = FitnessConfig::load(None).unwrap_or_default().weather_api.unwrap_or_default();
+                                let mut weather_service = WeatherService::new(weather_config);
+
+                                for a in std::iter::once(activity).chain(baseline) {
+                                    if let Ok(Some(conditions)) = weather_service
+                                        .get_weather_for_activity(a.start_latitude, a.start_longitude, a.start_date)
+                                        .await
+                                    {
+                                        weather.insert(a.id.clone(), conditions);
+                                    }
                                 }
-                            });
-                            Some(response)
+                            }
+
+                            let analyzer = AdvancedActivityAnalyzer::new();
+                            match analyzer.compare_activity(activity, &activities, &comparison_type, &weather).await {
+                                Ok(comparison) => Some(serde_json::json!({ "comparison": comparison })),
+                                Err(e) => {
+                                    return McpResponse {
+                                        jsonrpc: JSONRPC_VERSION.to_string(),
+                                        result: None,
+                                        error: Some(McpError {
+                                            code: ERROR_INTERNAL_ERROR,
+                                            message: format!("Failed to compare activity: {}", e),
+                                            data: None,
+                                        }),
+                                        id,
+                                    };
+                                }
+                            }
                         } else {
                             return McpResponse {
                                 jsonrpc: JSONRPC_VERSION.to_string(),
                                 result: None,
                                 error: Some(McpError {
                                     code: ERROR_INVALID_PARAMS,
-                                    message: "One or both activities not found".to_string(),
+                                    message: format!("Activity with ID '{}' not found", activity_id),
                                     data: None,
                                 }),
                                 id,
@@ -1343,41 +3144,6 @@ impl MultiTenantMcpServer {
                     }
                 }
             }
-            "detect_patterns" => {
-                let pattern_type = args["pattern_type"].as_str().unwrap_or("weekly");
-                
-                match provider.get_activities(Some(100), None).await {
-                    Ok(activities) => {
-                        let response = serde_json::json!({
-                            "pattern_analysis": {
-                                "pattern_type": pattern_type,
-                                "total_activities": activities.len(),
-                                "patterns_detected": [
-                                    "Regular training frequency detected",
-                                    "Consistent effort levels across activities"
-                                ],
-                                "recommendations": [
-                                    "Continue current training consistency",
-                                    "Consider adding variety to workout types"
-                                ]
-                            }
-                        });
-                        Some(response)
-                    }
-                    Err(e) => {
-                        return McpResponse {
-                            jsonrpc: JSONRPC_VERSION.to_string(),
-                            result: None,
-                            error: Some(McpError {
-                                code: ERROR_INTERNAL_ERROR,
-                                message: format!("Failed to get activities: {}", e),
-                                data: None,
-                            }),
-                            id,
-                        };
-                    }
-                }
-            }
             "suggest_goals" => {
                 match provider.get_activities(Some(50), None).await {
                     Ok(_activities) => {
@@ -1658,22 +3424,213 @@ struct ApiError(serde_json::Value);
 
 impl warp::reject::Reject for ApiError {}
 
+/// HTTP request over its rate limit
+#[derive(Debug)]
+struct RateLimited(RateLimitExceeded);
+
+impl warp::reject::Reject for RateLimited {}
+
+/// Rejection for a route error. A route that found the user or API key over
+/// its rate limit answers 429 like the per-IP limit does
+fn route_rejection(e: &anyhow::Error, error: serde_json::Value) -> warp::Rejection {
+    match e.downcast_ref::<RateLimitExceeded>() {
+        Some(exceeded) => warp::reject::custom(RateLimited(exceeded.clone())),
+        None => warp::reject::custom(ApiError(error)),
+    }
+}
+
 /// Handle HTTP rejections and errors
-async fn handle_rejection(err: warp::Rejection) -> Result<impl warp::Reply, std::convert::Infallible> {
+async fn handle_rejection(err: warp::Rejection) -> Result<warp::reply::Response, std::convert::Infallible> {
+    use warp::Reply;
+
     if let Some(api_error) = err.find::<ApiError>() {
         let json = warp::reply::json(&api_error.0);
-        Ok(warp::reply::with_status(json, warp::http::StatusCode::BAD_REQUEST))
+        Ok(warp::reply::with_status(json, warp::http::StatusCode::BAD_REQUEST).into_response())
+    } else if let Some(RateLimited(exceeded)) = err.find::<RateLimited>() {
+        let mut body = exceeded.to_json();
+        body["error"] = Value::String(exceeded.to_string());
+        let json = warp::reply::json(&body);
+        let reply = warp::reply::with_status(json, warp::http::StatusCode::TOO_MANY_REQUESTS);
+        Ok(warp::reply::with_header(reply, "retry-after", exceeded.retry_after_secs.to_string()).into_response())
     } else if err.is_not_found() {
         let json = warp::reply::json(&serde_json::json!({
             "error": "Not Found",
             "message": "The requested endpoint was not found"
         }));
-        Ok(warp::reply::with_status(json, warp::http::StatusCode::NOT_FOUND))
+        Ok(warp::reply::with_status(json, warp::http::StatusCode::NOT_FOUND).into_response())
     } else {
         let json = warp::reply::json(&serde_json::json!({
             "error": "Internal Server Error",
             "message": "Something went wrong"
         }));
-        Ok(warp::reply::with_status(json, warp::http::StatusCode::INTERNAL_SERVER_ERROR))
+        Ok(warp::reply::with_status(json, warp::http::StatusCode::INTERNAL_SERVER_ERROR).into_response())
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::generate_encryption_key;
+    use crate::models::{Athlete, PersonalRecord, Stats, User};
+    use crate::providers::AuthData;
+    use async_trait::async_trait;
+    use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+    use std::sync::Mutex;
+
+    /// Provider serving a fixed history, newest first, that counts page requests
+    struct PagedProvider {
+        activities: Mutex<Vec<Activity>>,
+        requests: Mutex<usize>,
+    }
+
+    impl PagedProvider {
+        fn with_history(count: usize) -> Self {
+            let newest = Utc.with_ymd_and_hms(2024, 6, 1, 8, 0, 0).unwrap();
+            Self {
+                activities: Mutex::new((0..count).map(|i| activity(i, newest - ChronoDuration::hours(i as i64))).collect()),
+                requests: Mutex::new(0),
+            }
+        }
+
+        fn requests(&self) -> usize {
+            std::mem::take(&mut *self.requests.lock().unwrap())
+        }
+    }
+
+    fn activity(index: usize, start_date: chrono::DateTime<Utc>) -> Activity {
+        Activity {
+            id: format!("activity_{}", index),
+            start_date,
+            provider: "strava".to_string(),
+            ..Activity::default()
+        }
+    }
+
+    #[async_trait]
+    impl FitnessProvider for PagedProvider {
+        async fn authenticate(&mut self, _auth_data: AuthData) -> Result<()> {
+            Ok(())
+        }
+
+        async fn get_athlete(&self) -> Result<Athlete> {
+            unimplemented!()
+        }
+
+        async fn get_activities(&self, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Activity>> {
+            *self.requests.lock().unwrap() += 1;
+            let activities = self.activities.lock().unwrap();
+            let start = offset.unwrap_or(0).min(activities.len());
+            let end = (start + limit.unwrap_or(activities.len())).min(activities.len());
+            Ok(activities[start..end].to_vec())
+        }
+
+        async fn get_activity(&self, _id: &str) -> Result<Activity> {
+            unimplemented!()
+        }
+
+        async fn get_stats(&self) -> Result<Stats> {
+            unimplemented!()
+        }
+
+        async fn get_personal_records(&self) -> Result<Vec<PersonalRecord>> {
+            unimplemented!()
+        }
+
+        fn provider_name(&self) -> &'static str {
+            "strava"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_backfills_history_beyond_one_sync() {
+        let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await.unwrap();
+        let user = User::new("sync@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = database.create_user(&user).await.unwrap();
+        let history = ACTIVITY_SYNC_PAGE_SIZE * ACTIVITY_SYNC_MAX_PAGES + 50;
+        let provider = PagedProvider::with_history(history);
+
+        // The first sync reads as many pages as it may and remembers where it stopped
+        let pull = || MultiTenantMcpServer::pull_provider_activities(user_id, "strava", &provider, &database);
+        assert_eq!(pull().await.unwrap(), history - 50);
+        assert_eq!(provider.requests(), ACTIVITY_SYNC_MAX_PAGES);
+        assert_eq!(
+            database.get_sync_backfill_offset(user_id, "strava").await.unwrap(),
+            Some(history - 50)
+        );
+
+        // The next sync checks for new activities, then finishes the older history
+        pull().await.unwrap();
+        assert_eq!(provider.requests(), 2);
+        let stored = database.count_activities(user_id, Some("strava"), &ActivityQuery::default()).await.unwrap();
+        assert_eq!(stored as usize, history);
+        assert_eq!(database.get_sync_backfill_offset(user_id, "strava").await.unwrap(), None);
+
+        // Once complete, syncs only read new activities
+        let newest = Utc.with_ymd_and_hms(2024, 6, 2, 8, 0, 0).unwrap();
+        provider.activities.lock().unwrap().insert(0, activity(history, newest));
+        pull().await.unwrap();
+        assert_eq!(provider.requests(), 1);
+        let stored = database.count_activities(user_id, Some("strava"), &ActivityQuery::default()).await.unwrap();
+        assert_eq!(stored as usize, history + 1);
+        assert_eq!(database.get_sync_backfill_offset(user_id, "strava").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_update_profile_keeps_omitted_fields() {
+        let database = Arc::new(Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await.unwrap());
+        let user = User::new("profile@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = database.create_user(&user).await.unwrap();
+        let user_providers = Arc::new(ProviderCache::new(std::time::Duration::from_secs(60), Arc::new(OAuthConfig::default())));
+        let update = |args: Value| {
+            let database = database.clone();
+            let user_providers = user_providers.clone();
+            async move {
+                MultiTenantMcpServer::execute_tool_call_without_provider(UPDATE_PROFILE, &args, Value::from(1), user_id, &database, &user_providers).await
+            }
+        };
+
+        let response = update(serde_json::json!({ "ftp_watts": 250.0 })).await;
+        assert!(response.error.is_none());
+        let response = update(serde_json::json!({ "timezone": "America/New_York" })).await;
+        assert!(response.error.is_none());
+
+        assert_eq!(database.get_workout_thresholds(user_id).await.unwrap().ftp_watts, Some(250.0));
+        assert_eq!(database.get_user_timezone(user_id).await.unwrap().as_deref(), Some("America/New_York"));
+
+        for args in [
+            serde_json::json!({}),
+            serde_json::json!({ "timezone": "Mars/Olympus_Mons" }),
+            serde_json::json!({ "fitness_level": "legendary" }),
+            serde_json::json!({ "ftp_watts": -10 }),
+            serde_json::json!({ "age": 35.5 }),
+        ] {
+            let response = update(args).await;
+            assert_eq!(response.error.unwrap().code, ERROR_INVALID_PARAMS);
+        }
+        assert_eq!(database.get_user_timezone(user_id).await.unwrap().as_deref(), Some("America/New_York"));
+    }
+
+    #[tokio::test]
+    async fn test_power_metrics_from_stored_samples() {
+        let database = Arc::new(Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await.unwrap());
+        let user = User::new("power@example.com".to_string(), "hashed_password".to_string(), None);
+        let user_id = database.create_user(&user).await.unwrap();
+        database.upsert_user_profile(user_id, serde_json::json!({ "ftp_watts": 250.0, "weight_kg": 70.0 })).await.unwrap();
+
+        let activity = Activity { id: "ride".to_string(), provider: "file".to_string(), duration_seconds: 3600, ..Activity::default() };
+        let details = crate::models::ActivityDetails {
+            activity: activity.clone(),
+            streams: vec![crate::models::TrackPoint { power: Some(200), ..Default::default() }; 10],
+            laps: vec![],
+            device: None,
+        };
+        database.store_activity_details(user_id, &details).await.unwrap();
+
+        let metrics = MultiTenantMcpServer::power_metrics(user_id, &activity, &database).await.unwrap();
+        assert_eq!(metrics.power_to_weight_ratio, Some(200.0 / 70.0));
+        assert_eq!(metrics.intensity_factor, Some(0.8));
+        assert_eq!(metrics.training_stress_score, Some(64.0));
+
+        let unrecorded = Activity { id: "run".to_string(), ..activity };
+        assert!(MultiTenantMcpServer::power_metrics(user_id, &unrecorded, &database).await.is_none());
     }
-}
\ No newline at end of file
+}
@@ -99,6 +99,8 @@ fn create_fitness_tools() -> Vec<ToolSchema> {
     vec![
         // Original tools
         create_get_activities_tool(),
+        create_query_activities_tool(),
+        create_export_activities_tool(),
         create_get_athlete_tool(), 
         create_get_stats_tool(),
         create_get_activity_intelligence_tool(),
@@ -113,11 +115,24 @@ fn create_fitness_tools() -> Vec<ToolSchema> {
         create_analyze_performance_trends_tool(),
         create_compare_activities_tool(),
         create_detect_patterns_tool(),
+        create_find_streaks_tool(),
+        create_generate_period_report_tool(),
         create_set_goal_tool(),
         create_track_progress_tool(),
+        create_list_goals_tool(),
+        create_update_goal_tool(),
+        create_pause_goal_tool(),
+        create_complete_goal_tool(),
+        create_delete_goal_tool(),
         create_suggest_goals_tool(),
         create_analyze_goal_feasibility_tool(),
         create_generate_recommendations_tool(),
+        create_generate_training_plan_tool(),
+        create_get_training_plan_tool(),
+        create_export_workout_tool(),
+        create_get_calendar_feed_tool(),
+        create_revoke_calendar_feed_tool(),
+        create_update_profile_tool(),
         create_calculate_fitness_score_tool(),
         create_predict_performance_tool(),
         create_analyze_training_load_tool(),
@@ -154,6 +169,153 @@ fn create_get_activities_tool() -> ToolSchema {
     }
 }
 
+/// Create the query_activities tool schema
+fn create_query_activities_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+    
+    properties.insert(PROVIDER.to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness provider to sync and query (omit to query all stored activities)".to_string()),
+    });
+    
+    properties.insert("sport_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Only activities of this sport (e.g., 'run', 'ride')".to_string()),
+    });
+    
+    properties.insert("start_date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Earliest start date (YYYY-MM-DD or RFC 3339, inclusive)".to_string()),
+    });
+    
+    properties.insert("end_date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Latest start date (YYYY-MM-DD or RFC 3339, inclusive)".to_string()),
+    });
+
+    properties.insert("timezone".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("IANA timezone for plain dates and week/month/year groups, e.g. 'Europe/Paris' (default: the user's profile timezone, else UTC)".to_string()),
+    });
+    
+    properties.insert("min_distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Minimum distance in kilometers".to_string()),
+    });
+    
+    properties.insert("max_distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Maximum distance in kilometers".to_string()),
+    });
+    
+    properties.insert("min_duration_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Minimum duration in minutes".to_string()),
+    });
+    
+    properties.insert("max_duration_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Maximum duration in minutes".to_string()),
+    });
+    
+    properties.insert("min_elevation_m".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Minimum elevation gain in meters".to_string()),
+    });
+    
+    properties.insert("max_elevation_m".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Maximum elevation gain in meters".to_string()),
+    });
+    
+    properties.insert("city".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("City name to match (case-insensitive, partial match)".to_string()),
+    });
+    
+    properties.insert("region".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Region or state to match (case-insensitive, partial match)".to_string()),
+    });
+    
+    properties.insert("trail_name".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Trail name to match (case-insensitive, partial match)".to_string()),
+    });
+    
+    properties.insert("has_heart_rate".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Only activities with (true) or without (false) heart rate data".to_string()),
+    });
+    
+    properties.insert("sort_by".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Sort field ('date', 'distance', 'duration', 'elevation', 'heart_rate', default: 'date')".to_string()),
+    });
+    
+    properties.insert("sort_order".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Sort order ('asc' or 'desc', default: 'desc')".to_string()),
+    });
+    
+    properties.insert(LIMIT.to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Maximum number of activities to return (default: 20)".to_string()),
+    });
+    
+    properties.insert(OFFSET.to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Number of matching activities to skip (for pagination)".to_string()),
+    });
+    
+    properties.insert("group_by".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Aggregate matching activities by 'week', 'month', 'year' or 'sport'".to_string()),
+    });
+    
+    properties.insert("sync".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Sync new activities from the provider before querying (default: true)".to_string()),
+    });
+
+    ToolSchema {
+        name: QUERY_ACTIVITIES.to_string(),
+        description: "Query the local activity store with filters on sport, dates, distance, duration, elevation, location and heart rate, with sorting and optional grouping".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
+/// Create the export_activities tool schema
+fn create_export_activities_tool() -> ToolSchema {
+    // Same filters as query_activities; grouping does not apply to a file
+    let mut properties = create_query_activities_tool().input_schema.properties.unwrap_or_default();
+    properties.remove("group_by");
+
+    properties.insert("format".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("File format ('csv', 'parquet', or 'gpx'/'tcx' for activities with recorded streams, default: 'csv')".to_string()),
+    });
+
+    properties.insert(LIMIT.to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Maximum number of activities to export (default: all matching)".to_string()),
+    });
+
+    ToolSchema {
+        name: EXPORT_ACTIVITIES.to_string(),
+        description: "Export stored activities matching filters as a CSV or Parquet table of summaries, or as GPX/TCX tracks".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
 /// Create the get_athlete tool schema
 fn create_get_athlete_tool() -> ToolSchema {
     let mut properties = HashMap::new();
@@ -315,13 +477,15 @@ mod tests {
         assert!(json["capabilities"]["tools"].is_array());
         
         let tools = json["capabilities"]["tools"].as_array().unwrap();
-        assert_eq!(tools.len(), 21);
+        assert_eq!(tools.len(), 36);
         
         let tool_names: Vec<&str> = tools.iter()
             .filter_map(|t| t["name"].as_str())
             .collect();
         
         assert!(tool_names.contains(&"get_activities"));
+        assert!(tool_names.contains(&"query_activities"));
+        assert!(tool_names.contains(&"export_activities"));
         assert!(tool_names.contains(&"get_athlete"));
         assert!(tool_names.contains(&"get_stats"));
         assert!(tool_names.contains(&"get_activity_intelligence"));
@@ -477,12 +641,22 @@ fn create_compare_activities_tool() -> ToolSchema {
     
     properties.insert("comparison_type".to_string(), PropertySchema {
         property_type: "string".to_string(),
-        description: Some("Type of comparison ('similar_activities', 'personal_best', 'average', 'recent')".to_string()),
+        description: Some("Type of comparison ('similar_activities', 'personal_best', 'last_n_average')".to_string()),
+    });
+    
+    properties.insert("n".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Number of recent activities averaged for 'last_n_average' (default: 5)".to_string()),
+    });
+    
+    properties.insert("include_weather".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Fetch weather to adjust effort for conditions (default: false)".to_string()),
     });
 
     ToolSchema {
         name: "compare_activities".to_string(),
-        description: "Compare an activity against similar activities, personal bests, or historical averages".to_string(),
+        description: "Compare an activity against similar activities, a personal best, or the average of recent activities. Returns normalized deltas for pace, heart rate, efficiency factor, elevation and weather-adjusted effort with a significance flag".to_string(),
         input_schema: JsonSchema {
             schema_type: "object".to_string(),
             properties: Some(properties),
@@ -502,17 +676,22 @@ fn create_detect_patterns_tool() -> ToolSchema {
     
     properties.insert("pattern_type".to_string(), PropertySchema {
         property_type: "string".to_string(),
-        description: Some("Type of pattern to detect ('training_consistency', 'seasonal_trends', 'performance_plateaus', 'injury_risk')".to_string()),
+        description: Some("Pattern to detect ('all', 'weekday_habits', 'time_of_day_habits', 'long_run_cadence', 'seasonal_volume', 'hard_easy_alternation', 'time_of_day_performance', 'back_to_back_fatigue') or a group ('training_consistency', 'seasonal_trends', 'performance_timing', 'injury_risk')".to_string()),
     });
     
     properties.insert("timeframe".to_string(), PropertySchema {
         property_type: "string".to_string(),
-        description: Some("Time period for pattern analysis".to_string()),
+        description: Some("Time period for pattern analysis ('week', 'month', 'quarter', 'sixmonths', 'year'; default: all history)".to_string()),
+    });
+
+    properties.insert("timezone".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("IANA timezone that training days and times are read in, e.g. 'Europe/Paris' (default: the user's profile timezone, else UTC)".to_string()),
     });
 
     ToolSchema {
         name: "detect_patterns".to_string(),
-        description: "Detect patterns in training data such as consistency trends, seasonal variations, or performance plateaus".to_string(),
+        description: "Detect patterns in training history such as weekday and time-of-day habits, long-run cadence, seasonal volume, hard/easy alternation and fatigue after back-to-back days. Each pattern includes evidence and a confidence level".to_string(),
         input_schema: JsonSchema {
             schema_type: "object".to_string(),
             properties: Some(properties),
@@ -521,6 +700,111 @@ fn create_detect_patterns_tool() -> ToolSchema {
     }
 }
 
+/// Create the find_streaks tool schema
+fn create_find_streaks_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+    
+    properties.insert("provider".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness provider name".to_string()),
+    });
+    
+    properties.insert("period".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Streak period ('day' or 'week', default: 'day')".to_string()),
+    });
+    
+    properties.insert("sport_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Only count activities of this sport (e.g., 'run', 'ride')".to_string()),
+    });
+    
+    properties.insert("min_distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Minimum distance for an activity to count".to_string()),
+    });
+    
+    properties.insert("min_duration_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Minimum duration for an activity to count".to_string()),
+    });
+    
+    properties.insert("min_activities".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Qualifying activities required per period (default: 1)".to_string()),
+    });
+    
+    properties.insert("min_total_distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Total distance required per period".to_string()),
+    });
+    
+    properties.insert("min_total_duration_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Total duration required per period".to_string()),
+    });
+
+    properties.insert("timezone".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("IANA timezone whose days and weeks are counted, e.g. 'Europe/Paris' (default: the user's profile timezone, else UTC)".to_string()),
+    });
+
+    ToolSchema {
+        name: "find_streaks".to_string(),
+        description: "Find current and longest streaks of consecutive days or weeks meeting activity criteria, with alerts when a streak is at risk".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["provider".to_string()]),
+        },
+    }
+}
+
+/// Create the generate_period_report tool schema
+fn create_generate_period_report_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+    
+    properties.insert("provider".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness provider name".to_string()),
+    });
+    
+    properties.insert("period".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Report period ('week', 'month', 'season', 'year', default: 'month')".to_string()),
+    });
+    
+    properties.insert("date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Any date within the period to report on (YYYY-MM-DD, default: today)".to_string()),
+    });
+
+    properties.insert("timezone".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("IANA timezone the period's days are counted in, e.g. 'Europe/Paris' (default: the user's profile timezone, else UTC)".to_string()),
+    });
+    
+    properties.insert("include_weather".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Look up weather for the period's longest activities to report extremes (default: false)".to_string()),
+    });
+    
+    properties.insert("include_locations".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Reverse-geocode the period's longest activities for most-visited places (default: false)".to_string()),
+    });
+
+    ToolSchema {
+        name: "generate_period_report".to_string(),
+        description: "Generate a week, month, season or year-in-review report with totals per sport, biggest efforts, PRs, streaks, places, weather extremes, consistency and goal outcomes, as JSON and Markdown".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["provider".to_string()]),
+        },
+    }
+}
+
 /// Create the set_goal tool schema
 fn create_set_goal_tool() -> ToolSchema {
     let mut properties = HashMap::new();
@@ -542,19 +826,21 @@ fn create_set_goal_tool() -> ToolSchema {
     
     properties.insert("target_value".to_string(), PropertySchema {
         property_type: "number".to_string(),
-        description: Some("Target value to achieve".to_string()),
+        description: Some("Target value to achieve: km for distance, minutes for time, sessions for frequency, percent for performance".to_string()),
     });
     
     properties.insert("target_date".to_string(), PropertySchema {
         property_type: "string".to_string(),
         description: Some("Target completion date (ISO format)".to_string()),
     });
-    
-    properties.insert("sport_type".to_string(), PropertySchema {
-        property_type: "string".to_string(),
-        description: Some("Sport type for the goal".to_string()),
+
+    properties.insert("current_value".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Progress already made toward the target (default 0)".to_string()),
     });
 
+    insert_goal_type_properties(&mut properties);
+
     ToolSchema {
         name: "set_goal".to_string(),
         description: "Create and manage fitness goals with tracking and progress monitoring".to_string(),
@@ -586,6 +872,173 @@ fn create_track_progress_tool() -> ToolSchema {
     }
 }
 
+/// Add the per-type goal parameters shared by set_goal and update_goal
+fn insert_goal_type_properties(properties: &mut HashMap<String, PropertySchema>) {
+    properties.insert("sport_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Sport type for the goal (required for distance, time and frequency goals)".to_string()),
+    });
+
+    properties.insert("timeframe".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Time frame for distance goals ('week', 'month', 'quarter', 'sixmonths', 'year'; default month)".to_string()),
+    });
+
+    properties.insert("distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Race distance in km for time goals".to_string()),
+    });
+
+    properties.insert("sessions_per_week".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Weekly sessions for frequency goals (1-14)".to_string()),
+    });
+
+    properties.insert("metric".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Metric for performance and custom goals".to_string()),
+    });
+
+    properties.insert("improvement_percent".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Improvement in percent for performance goals".to_string()),
+    });
+
+    properties.insert("unit".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Unit for custom goals".to_string()),
+    });
+}
+
+/// Create a schema for a tool that only takes a goal ID
+fn create_goal_id_tool(name: &str, description: &str, goal_id_description: &str) -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("goal_id".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some(goal_id_description.to_string()),
+    });
+
+    ToolSchema {
+        name: name.to_string(),
+        description: description.to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["goal_id".to_string()]),
+        },
+    }
+}
+
+/// Create the list_goals tool schema
+fn create_list_goals_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("status".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Only list goals with this status ('active', 'paused', 'completed', 'cancelled')".to_string()),
+    });
+
+    properties.insert("goal_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Only list goals of this type ('distance', 'time', 'frequency', 'performance', 'custom')".to_string()),
+    });
+
+    ToolSchema {
+        name: "list_goals".to_string(),
+        description: "List the user's fitness goals with status and progress, optionally filtered by status or type".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
+/// Create the update_goal tool schema
+fn create_update_goal_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("goal_id".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("ID of the goal to update".to_string()),
+    });
+
+    properties.insert("title".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("New goal title".to_string()),
+    });
+
+    properties.insert("description".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("New goal description".to_string()),
+    });
+
+    properties.insert("goal_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("New goal type; the goal's type parameters are rebuilt from this call's arguments".to_string()),
+    });
+
+    properties.insert("target_value".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("New target value".to_string()),
+    });
+
+    properties.insert("target_date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("New target completion date (ISO format)".to_string()),
+    });
+
+    properties.insert("current_value".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Corrected progress toward the target".to_string()),
+    });
+
+    properties.insert("status".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("New status ('active', 'paused', 'completed', 'cancelled'); completed and cancelled goals cannot change status".to_string()),
+    });
+
+    insert_goal_type_properties(&mut properties);
+
+    ToolSchema {
+        name: "update_goal".to_string(),
+        description: "Edit an existing goal's title, target, date, type parameters or status".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["goal_id".to_string()]),
+        },
+    }
+}
+
+/// Create the pause_goal tool schema
+fn create_pause_goal_tool() -> ToolSchema {
+    create_goal_id_tool(
+        "pause_goal",
+        "Pause an active goal; resume it later with update_goal and status 'active'",
+        "ID of the goal to pause",
+    )
+}
+
+/// Create the complete_goal tool schema
+fn create_complete_goal_tool() -> ToolSchema {
+    create_goal_id_tool(
+        "complete_goal",
+        "Mark an active or paused goal as completed",
+        "ID of the goal to complete",
+    )
+}
+
+/// Create the delete_goal tool schema
+fn create_delete_goal_tool() -> ToolSchema {
+    create_goal_id_tool(
+        "delete_goal",
+        "Permanently delete a goal and its milestones",
+        "ID of the goal to delete",
+    )
+}
+
 /// Create the suggest_goals tool schema
 fn create_suggest_goals_tool() -> ToolSchema {
     let mut properties = HashMap::new();
@@ -661,6 +1114,246 @@ fn create_generate_recommendations_tool() -> ToolSchema {
     }
 }
 
+/// Create the generate_training_plan tool schema
+fn create_generate_training_plan_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("provider".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness provider to sync recent activities from before planning (optional)".to_string()),
+    });
+
+    properties.insert("sport_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Sport of the target event (e.g., 'run', 'ride', 'swim')".to_string()),
+    });
+
+    properties.insert("distance_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Event distance in kilometers".to_string()),
+    });
+
+    properties.insert("event_date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Event date (YYYY-MM-DD), at least 4 weeks away".to_string()),
+    });
+
+    properties.insert("goal_time_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Target finishing time in minutes, used for race pace workouts (optional)".to_string()),
+    });
+
+    properties.insert("event_name".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Name of the event (optional)".to_string()),
+    });
+
+    properties.insert("hours_per_week".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Available training hours per week (default: from the user profile)".to_string()),
+    });
+
+    properties.insert("preferred_days".to_string(), PropertySchema {
+        property_type: "array".to_string(),
+        description: Some("Days to train on, e.g. ['tuesday', 'thursday', 'sunday'] (default: from the user profile)".to_string()),
+    });
+
+    properties.insert("preferred_duration_minutes".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Longest regular session in minutes; the long session may exceed it (default: from the user profile)".to_string()),
+    });
+
+    ToolSchema {
+        name: "generate_training_plan".to_string(),
+        description: "Generate and store a week-by-week training plan with base, build, peak and taper phases towards a target event, with workouts in the user's heart rate zones".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["sport_type".to_string(), "distance_km".to_string(), "event_date".to_string()]),
+        },
+    }
+}
+
+/// Create the get_training_plan tool schema
+fn create_get_training_plan_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("provider".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness provider to sync activities from before scoring compliance (optional)".to_string()),
+    });
+
+    properties.insert("plan_id".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("ID of the plan (default: the most recently generated plan)".to_string()),
+    });
+
+    ToolSchema {
+        name: "get_training_plan".to_string(),
+        description: "Get a stored training plan with a compliance score comparing planned workouts to recorded activities".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
+/// Create the export_workout tool schema
+fn create_export_workout_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("format".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("File format ('zwo' for Zwift, 'erg', 'mrc', 'fit')".to_string()),
+    });
+
+    properties.insert("date".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Date of the planned workout to export (YYYY-MM-DD)".to_string()),
+    });
+
+    properties.insert("plan_id".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Training plan to take the workout from (default: the most recent plan)".to_string()),
+    });
+
+    properties.insert("name".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Name of a custom workout given as steps".to_string()),
+    });
+
+    properties.insert("sport_type".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Sport of a custom workout ('ride' or 'run', default: 'ride')".to_string()),
+    });
+
+    properties.insert("steps".to_string(), PropertySchema {
+        property_type: "array".to_string(),
+        description: Some("Custom workout steps: {type: 'warmup'|'cooldown', duration_seconds, start_intensity, end_intensity}, {type: 'steady', duration_seconds, intensity} or {type: 'repeat', count, steps}; intensities are fractions of FTP or threshold pace".to_string()),
+    });
+
+    properties.insert("ftp_watts".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Functional threshold power in watts (default: from the user profile)".to_string()),
+    });
+
+    properties.insert("threshold_pace".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Threshold running pace per km as 'm:ss' or seconds (default: from the user profile)".to_string()),
+    });
+
+    ToolSchema {
+        name: "export_workout".to_string(),
+        description: "Export a planned or custom structured workout as a Zwift .zwo, .erg, .mrc or FIT workout file for trainers and devices, returned as an embedded resource".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec!["format".to_string()]),
+        },
+    }
+}
+
+/// Create the get_calendar_feed tool schema
+fn create_get_calendar_feed_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("rotate_token".to_string(), PropertySchema {
+        property_type: "boolean".to_string(),
+        description: Some("Issue a new feed URL, invalidating the previous one. The URL is only shown when it is issued (default: false)".to_string()),
+    });
+
+    ToolSchema {
+        name: "get_calendar_feed".to_string(),
+        description: "Get a subscribable iCalendar (.ics) feed URL with planned workouts, goal target dates and milestone checkpoints, plus the current feed as an embedded resource".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
+/// Create the revoke_calendar_feed tool schema
+fn create_revoke_calendar_feed_tool() -> ToolSchema {
+    let properties = HashMap::new(); // No parameters needed - uses user's JWT context
+
+    ToolSchema {
+        name: "revoke_calendar_feed".to_string(),
+        description: "Revoke the calendar feed URL so calendar apps can no longer read it".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
+/// Create the update_profile tool schema
+fn create_update_profile_tool() -> ToolSchema {
+    let mut properties = HashMap::new();
+
+    properties.insert("age".to_string(), PropertySchema {
+        property_type: "integer".to_string(),
+        description: Some("Age in years".to_string()),
+    });
+
+    properties.insert("weight_kg".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Body weight in kilograms".to_string()),
+    });
+
+    properties.insert("fitness_level".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("Fitness level ('beginner', 'intermediate', 'advanced', 'elite')".to_string()),
+    });
+
+    properties.insert("primary_sports".to_string(), PropertySchema {
+        property_type: "array".to_string(),
+        description: Some("Sports the user mainly trains for, e.g. ['run', 'ride']".to_string()),
+    });
+
+    properties.insert("hours_per_week".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Hours available for training each week".to_string()),
+    });
+
+    properties.insert("preferred_days".to_string(), PropertySchema {
+        property_type: "array".to_string(),
+        description: Some("Days the user prefers to train, e.g. ['Tuesday', 'Saturday']".to_string()),
+    });
+
+    properties.insert("preferred_duration_minutes".to_string(), PropertySchema {
+        property_type: "integer".to_string(),
+        description: Some("Preferred session length in minutes".to_string()),
+    });
+
+    properties.insert("ftp_watts".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Functional threshold power in watts, used for cycling workout targets".to_string()),
+    });
+
+    properties.insert("threshold_pace_seconds_per_km".to_string(), PropertySchema {
+        property_type: "number".to_string(),
+        description: Some("Threshold running pace in seconds per km, used for running workout targets".to_string()),
+    });
+
+    properties.insert("timezone".to_string(), PropertySchema {
+        property_type: "string".to_string(),
+        description: Some("IANA timezone training days and reports are read in, e.g. 'Europe/Paris'".to_string()),
+    });
+
+    ToolSchema {
+        name: "update_profile".to_string(),
+        description: "Update the user's fitness profile. Only the fields given are changed".to_string(),
+        input_schema: JsonSchema {
+            schema_type: "object".to_string(),
+            properties: Some(properties),
+            required: Some(vec![]),
+        },
+    }
+}
+
 /// Create the calculate_fitness_score tool schema
 fn create_calculate_fitness_score_tool() -> ToolSchema {
     let mut properties = HashMap::new();
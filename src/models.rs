@@ -8,7 +8,7 @@
 //!
 //! This module contains the core data structures used throughout the Pierre MCP Server.
 //! These models provide a unified representation of fitness data from various providers
-//! like Strava, Garmin Connect, and RunKeeper.
+//! like Strava, Fitbit and Garmin Connect.
 //!
 //! ## Design Principles
 //!
@@ -23,6 +23,7 @@
 //! - [`Athlete`]: User profile information
 //! - [`Stats`]: Aggregated fitness statistics
 //! - [`PersonalRecord`]: Individual performance records
+//! - [`ActivityDetails`]: An activity with its streams, laps and device info
 //! - [`SportType`]: Enumeration of supported activity types
 
 use chrono::{DateTime, Utc};
@@ -130,6 +131,77 @@ impl Default for Activity {
     }
 }
 
+/// A single sample of an activity's recorded streams
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TrackPoint {
+    /// When the sample was recorded (UTC)
+    pub time: DateTime<Utc>,
+    /// Latitude in degrees
+    pub latitude: Option<f64>,
+    /// Longitude in degrees
+    pub longitude: Option<f64>,
+    /// Altitude in meters
+    pub altitude: Option<f64>,
+    /// Cumulative distance from the start in meters
+    pub distance_meters: Option<f64>,
+    /// Heart rate (BPM)
+    pub heart_rate: Option<u32>,
+    /// Cadence (RPM, or steps per minute per leg for running)
+    pub cadence: Option<u32>,
+    /// Power in watts
+    pub power: Option<u32>,
+    /// Instantaneous speed in meters per second
+    pub speed: Option<f64>,
+}
+
+/// A lap or split recorded by the device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lap {
+    /// When the lap started (UTC)
+    pub start_date: DateTime<Utc>,
+    /// Lap duration in seconds
+    pub duration_seconds: u64,
+    /// Lap distance in meters
+    pub distance_meters: Option<f64>,
+    /// Average heart rate during the lap (BPM)
+    pub average_heart_rate: Option<u32>,
+    /// Maximum heart rate during the lap (BPM)
+    pub max_heart_rate: Option<u32>,
+    /// Average speed in meters per second
+    pub average_speed: Option<f64>,
+    /// Calories burned during the lap
+    pub calories: Option<u32>,
+}
+
+/// The device or application that recorded an activity
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Manufacturer name (e.g., "Garmin") or numeric FIT manufacturer id
+    pub manufacturer: Option<String>,
+    /// Product name or numeric product id
+    pub product: Option<String>,
+    /// Device serial number
+    pub serial_number: Option<String>,
+    /// Firmware or application version
+    pub software_version: Option<String>,
+}
+
+/// An activity together with its recorded streams, laps and device
+///
+/// Activity files carry far more than the summary in [`Activity`]; this keeps
+/// the sample-level data for analysis and re-export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActivityDetails {
+    /// Summary of the activity
+    pub activity: Activity,
+    /// Recorded samples in time order
+    pub streams: Vec<TrackPoint>,
+    /// Laps in time order
+    pub laps: Vec<Lap>,
+    /// Recording device, if known
+    pub device: Option<DeviceInfo>,
+}
+
 /// Enumeration of supported sport/activity types
 ///
 /// This enum covers the most common fitness activities across all providers.
@@ -319,6 +391,26 @@ impl SportType {
             other => SportType::Other(other.to_string()),
         }
     }
+
+    /// Parse a sport type from a user-supplied name such as a tool argument.
+    /// Accepts serialized names (`run`, `virtual_ride`) as well as internal config names.
+    pub fn from_name(name: &str) -> Self {
+        let normalized = name.trim().to_lowercase().replace([' ', '-'], "_");
+        serde_json::from_value(serde_json::Value::String(normalized.clone()))
+            .unwrap_or_else(|_| Self::from_internal_string(&normalized))
+    }
+
+    /// Stable lowercase key used for storage and grouping (`run`, `virtual_ride`).
+    /// `Other` types use their lowercased provider name.
+    pub fn key(&self) -> String {
+        match self {
+            SportType::Other(name) => name.to_lowercase(),
+            sport => serde_json::to_value(sport)
+                .ok()
+                .and_then(|value| value.as_str().map(str::to_string))
+                .unwrap_or_default(),
+        }
+    }
     
     /// Get the human-readable name for this sport type
     pub fn display_name(&self) -> &'static str {
@@ -478,10 +570,8 @@ pub struct User {
     pub display_name: Option<String>,
     /// Hashed password for authentication
     pub password_hash: String,
-    /// Encrypted Strava tokens
-    pub strava_token: Option<EncryptedToken>,
-    /// Encrypted Fitbit tokens
-    pub fitbit_token: Option<EncryptedToken>,
+    /// Linked fitness provider accounts with their encrypted tokens
+    pub provider_connections: Vec<ProviderConnection>,
     /// When the user account was created
     pub created_at: DateTime<Utc>,
     /// Last time user accessed the system
@@ -490,6 +580,21 @@ pub struct User {
     pub is_active: bool,
 }
 
+/// A user's linked account at a fitness provider
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProviderConnection {
+    /// Provider name (e.g., "strava")
+    pub provider: String,
+    /// Encrypted OAuth tokens
+    pub token: EncryptedToken,
+    /// The user's athlete or user id at the provider, once known
+    pub external_athlete_id: Option<String>,
+    /// When the account was first connected
+    pub connected_at: DateTime<Utc>,
+    /// When the tokens were last stored
+    pub updated_at: DateTime<Utc>,
+}
+
 /// Encrypted OAuth token storage
 ///
 /// Tokens are encrypted at rest using AES-256-GCM encryption.
@@ -523,6 +628,13 @@ pub struct DecryptedToken {
     pub scope: String,
 }
 
+impl DecryptedToken {
+    /// Whether the access token expires within the refresh margin
+    pub fn will_expire_soon(&self) -> bool {
+        self.expires_at <= Utc::now() + chrono::Duration::minutes(crate::constants::limits::TOKEN_REFRESH_MARGIN_MINUTES)
+    }
+}
+
 /// User session for MCP protocol authentication
 ///
 /// Contains JWT token and user context for secure MCP communication.
@@ -540,6 +652,107 @@ pub struct UserSession {
     pub available_providers: Vec<String>,
 }
 
+/// A pending OAuth authorization, valid for a single provider callback
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthState {
+    /// Opaque state parameter sent to the provider
+    pub state: String,
+    /// User who started the authorization
+    pub user_id: Uuid,
+    /// Provider the authorization was started for
+    pub provider: String,
+    /// PKCE code verifier to send with the code exchange
+    pub code_verifier: String,
+    /// When the authorization stops being accepted
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Permission granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiKeyScope {
+    /// Read activities, stats, analytics and goals
+    #[serde(rename = "activities:read")]
+    ActivitiesRead,
+    /// Create, update and delete goals and training plans
+    #[serde(rename = "goals:write")]
+    GoalsWrite,
+    /// Everything, including provider connections and calendar feeds
+    #[serde(rename = "admin")]
+    Admin,
+}
+
+impl ApiKeyScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiKeyScope::ActivitiesRead => "activities:read",
+            ApiKeyScope::GoalsWrite => "goals:write",
+            ApiKeyScope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(scope: &str) -> Option<Self> {
+        match scope {
+            "activities:read" => Some(ApiKeyScope::ActivitiesRead),
+            "goals:write" => Some(ApiKeyScope::GoalsWrite),
+            "admin" => Some(ApiKeyScope::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Long-lived credential for MCP clients that can't log in interactively
+///
+/// Only a hash of the secret is stored; the full key is shown once, when it is created.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// Label chosen by the user
+    pub name: String,
+    /// Start of the key, shown so users can tell their keys apart
+    pub key_prefix: String,
+    pub scopes: Vec<ApiKeyScope>,
+    pub created_at: DateTime<Utc>,
+    /// Keys without an expiry stay valid until revoked
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+impl ApiKey {
+    /// Whether the key can still be used to authenticate
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at.is_none_or(|expires_at| expires_at > Utc::now())
+    }
+}
+
+/// A server-side record of one issued JWT and its refresh token
+///
+/// Every refresh replaces the session with a new one in the same family, so
+/// the sessions of one login form a chain. Revoking a session rejects its JWT
+/// even before the token expires.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthSession {
+    /// The `jti` of the JWT issued with this session
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// The session created at login, shared by every session refreshed from it
+    pub family_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    /// When the refresh token, or the JWT if refresh tokens are disabled, expires
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// The session this one was rotated into by a refresh
+    pub replaced_by: Option<Uuid>,
+}
+
+impl AuthSession {
+    /// Whether the session's refresh token can still be used
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none() && self.expires_at > Utc::now()
+    }
+}
+
 /// Authentication request for MCP protocol
 ///
 /// Clients send this to authenticate with the MCP server.
@@ -573,38 +786,27 @@ impl User {
             email,
             display_name,
             password_hash,
-            strava_token: None,
-            fitbit_token: None,
+            provider_connections: Vec::new(),
             created_at: now,
             last_active: now,
             is_active: true,
         }
     }
 
-    /// Check if user has valid Strava token
-    pub fn has_strava_access(&self) -> bool {
-        self.strava_token.as_ref()
-            .map(|token| token.expires_at > Utc::now())
-            .unwrap_or(false)
-    }
-
-    /// Check if user has valid Fitbit token
-    pub fn has_fitbit_access(&self) -> bool {
-        self.fitbit_token.as_ref()
-            .map(|token| token.expires_at > Utc::now())
-            .unwrap_or(false)
+    /// Check if user has a valid token for the given provider
+    pub fn has_provider_access(&self, provider: &str) -> bool {
+        self.provider_connections
+            .iter()
+            .any(|connection| connection.provider == provider && connection.token.expires_at > Utc::now())
     }
 
     /// Get list of available providers for this user
     pub fn available_providers(&self) -> Vec<String> {
-        let mut providers = Vec::new();
-        if self.has_strava_access() {
-            providers.push("strava".to_string());
-        }
-        if self.has_fitbit_access() {
-            providers.push("fitbit".to_string());
-        }
-        providers
+        self.provider_connections
+            .iter()
+            .filter(|connection| connection.token.expires_at > Utc::now())
+            .map(|connection| connection.provider.clone())
+            .collect()
     }
 
     /// Update last active timestamp
@@ -793,6 +995,23 @@ mod tests {
         assert!(matches!(sport, SportType::Run));
     }
 
+    #[test]
+    fn test_sport_type_from_name() {
+        assert_eq!(SportType::from_name("Run"), SportType::Run);
+        assert_eq!(SportType::from_name("ride"), SportType::Ride);
+        assert_eq!(SportType::from_name("bike_ride"), SportType::Ride);
+        assert_eq!(SportType::from_name("Virtual Run"), SportType::VirtualRun);
+        assert_eq!(SportType::from_name("parkour"), SportType::Other("parkour".to_string()));
+    }
+
+    #[test]
+    fn test_sport_type_key() {
+        assert_eq!(SportType::Run.key(), "run");
+        assert_eq!(SportType::VirtualRide.key(), "virtual_ride");
+        assert_eq!(SportType::Other("Parkour".to_string()).key(), "parkour");
+        assert_eq!(SportType::from_name("Parkour").key(), SportType::Other("Parkour".to_string()).key());
+    }
+
     #[test]
     fn test_athlete_creation() {
         let athlete = create_sample_athlete();
@@ -11,6 +11,8 @@ use url::Url;
 use sha2::{Sha256, Digest};
 use base64::{Engine as _, engine::general_purpose::URL_SAFE_NO_PAD};
 use rand::Rng;
+use crate::config::environment::OAuthConfig;
+use crate::constants::{env_config, limits};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[allow(dead_code)]
@@ -35,7 +37,6 @@ pub struct PkceParams {
 
 impl PkceParams {
     /// Generate PKCE parameters with S256 challenge method
-    #[allow(dead_code)]
     pub fn generate() -> Self {
         // Generate a cryptographically secure random code verifier (43-128 characters)
         let mut rng = rand::thread_rng();
@@ -46,6 +47,11 @@ impl PkceParams {
             })
             .collect::<String>();
 
+        Self::from_verifier(code_verifier)
+    }
+
+    /// Rebuild PKCE parameters from a stored code verifier
+    pub fn from_verifier(code_verifier: String) -> Self {
         // Create S256 code challenge
         let mut hasher = Sha256::new();
         hasher.update(code_verifier.as_bytes());
@@ -83,7 +89,7 @@ impl OAuth2Token {
     #[allow(dead_code)]
     pub fn will_expire_soon(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
-            expires_at <= Utc::now() + Duration::minutes(5)
+            expires_at <= Utc::now() + Duration::minutes(limits::TOKEN_REFRESH_MARGIN_MINUTES)
         } else {
             false
         }
@@ -237,6 +243,24 @@ struct TokenResponse {
     scope: Option<String>,
 }
 
+/// Exchange a provider refresh token for a new token, using the configured client credentials
+pub async fn refresh_provider_token(
+    client: &reqwest::Client,
+    oauth_config: &OAuthConfig,
+    provider: &str,
+    refresh_token: &str,
+) -> Result<OAuth2Token> {
+    let (client_id, client_secret) = oauth_config.credentials(provider)
+        .ok_or_else(|| anyhow::anyhow!("No OAuth client credentials configured for {}", provider))?;
+    
+    match provider {
+        "strava" => strava::refresh_strava_token(client, &client_id, &client_secret, refresh_token).await,
+        "fitbit" => fitbit::refresh_fitbit_token(client, &client_id, &client_secret, refresh_token).await,
+        "garmin" => garmin::refresh_garmin_token(client, &client_id, &client_secret, refresh_token).await,
+        _ => Err(anyhow::anyhow!("Token refresh is not supported for provider {}", provider)),
+    }
+}
+
 // Strava-specific OAuth2 extensions
 pub mod strava {
     use super::*;
@@ -344,14 +368,20 @@ pub mod strava {
             ("grant_type", "refresh_token"),
         ];
         
-        let response: StravaTokenResponse = client
-            .post("https://www.strava.com/oauth/token")
+        let response = client
+            .post(env_config::strava_token_url())
             .form(&params)
             .send()
-            .await?
-            .json()
             .await?;
         
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Strava token refresh failed: {}", error_text));
+        }
+        
+        let response: StravaTokenResponse = response.json().await
+            .context("Failed to parse Strava token response")?;
+        
         Ok(OAuth2Token {
             access_token: response.access_token,
             token_type: response.token_type,
@@ -467,7 +497,6 @@ pub mod fitbit {
     }
     
     /// Refresh Fitbit access token
-    #[allow(dead_code)]
     pub async fn refresh_fitbit_token(
         client: &reqwest::Client,
         client_id: &str,
@@ -481,14 +510,20 @@ pub mod fitbit {
             ("grant_type", "refresh_token"),
         ];
         
-        let response: FitbitTokenResponse = client
+        let response = client
             .post("https://api.fitbit.com/oauth2/token")
             .form(&params)
             .send()
-            .await?
-            .json()
             .await?;
         
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Fitbit token refresh failed: {}", error_text));
+        }
+        
+        let response: FitbitTokenResponse = response.json().await
+            .context("Failed to parse Fitbit token response")?;
+        
         Ok(OAuth2Token {
             access_token: response.access_token,
             token_type: response.token_type,
@@ -497,4 +532,86 @@ pub mod fitbit {
             scope: Some(response.scope),
         })
     }
-}
\ No newline at end of file
+}
+// Garmin Connect OAuth2 extensions
+//
+// Garmin only issues tokens to PKCE flows, so there is no plain code exchange.
+pub mod garmin {
+    use super::*;
+    use crate::constants::env_config;
+    
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    pub struct GarminTokenResponse {
+        pub access_token: String,
+        pub token_type: String,
+        pub expires_in: i64,
+        pub refresh_token: String,
+        #[serde(default)]
+        pub scope: Option<String>,
+        #[serde(default)]
+        pub refresh_token_expires_in: Option<i64>,
+    }
+    
+    /// Exchange Garmin authorization code using the verifier from the authorization request
+    pub async fn exchange_garmin_code_with_pkce(
+        client: &reqwest::Client,
+        client_id: &str,
+        client_secret: &str,
+        code: &str,
+        redirect_uri: &str,
+        pkce: &PkceParams,
+    ) -> Result<OAuth2Token> {
+        let params = [
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("code", code),
+            ("grant_type", "authorization_code"),
+            ("redirect_uri", redirect_uri),
+            ("code_verifier", &pkce.code_verifier),
+        ];
+        
+        request_garmin_token(client, &params).await
+    }
+    
+    /// Refresh Garmin access token
+    pub async fn refresh_garmin_token(
+        client: &reqwest::Client,
+        client_id: &str,
+        client_secret: &str,
+        refresh_token: &str,
+    ) -> Result<OAuth2Token> {
+        let params = [
+            ("client_id", client_id),
+            ("client_secret", client_secret),
+            ("refresh_token", refresh_token),
+            ("grant_type", "refresh_token"),
+        ];
+        
+        request_garmin_token(client, &params).await
+    }
+    
+    async fn request_garmin_token(client: &reqwest::Client, params: &[(&str, &str)]) -> Result<OAuth2Token> {
+        let response = client
+            .post(env_config::garmin_token_url())
+            .form(params)
+            .send()
+            .await?;
+        
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            return Err(anyhow::anyhow!("Garmin token request failed: {}", error_text));
+        }
+        
+        let response: GarminTokenResponse = response.json().await
+            .context("Failed to parse Garmin token response")?;
+        
+        Ok(OAuth2Token {
+            access_token: response.access_token,
+            token_type: response.token_type,
+            expires_at: Some(Utc::now() + Duration::seconds(response.expires_in)),
+            refresh_token: Some(response.refresh_token),
+            scope: response.scope,
+        })
+    }
+}
@@ -0,0 +1,182 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Per-user provider instance cache
+//!
+//! Authenticated providers are reused across requests for the same user and
+//! provider. A cached instance is only handed out while it still holds the
+//! token stored in the database, so disconnecting, reconnecting or a refresh
+//! made elsewhere replaces it on the next request. Instances left unused for
+//! the idle timeout are dropped.
+
+use super::token_refresh::RefreshingProvider;
+use super::FitnessProvider;
+use crate::config::environment::OAuthConfig;
+use crate::database::Database;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::debug;
+use uuid::Uuid;
+
+struct CachedProvider {
+    provider: Arc<RefreshingProvider>,
+    last_used: Instant,
+}
+
+/// Authenticated providers keyed by user ID and provider name
+pub struct ProviderCache {
+    entries: RwLock<HashMap<(Uuid, String), CachedProvider>>,
+    idle_timeout: Duration,
+    /// Client credentials for refreshing tokens
+    oauth_config: Arc<OAuthConfig>,
+}
+
+impl ProviderCache {
+    pub fn new(idle_timeout: Duration, oauth_config: Arc<OAuthConfig>) -> Self {
+        Self {
+            entries: RwLock::new(HashMap::new()),
+            idle_timeout,
+            oauth_config,
+        }
+    }
+
+    /// Get the user's provider, reusing the cached instance while its token is current
+    pub async fn get(&self, user_id: Uuid, provider_name: &str, database: &Arc<Database>) -> Result<Arc<dyn FitnessProvider>> {
+        let key = (user_id, provider_name.to_string());
+        let stored = database.get_provider_token(user_id, provider_name).await?;
+
+        {
+            let mut entries = self.entries.write().await;
+            let now = Instant::now();
+            entries.retain(|_, entry| now.duration_since(entry.last_used) < self.idle_timeout);
+
+            if let Some(entry) = entries.get_mut(&key) {
+                // A token that expires soon is refreshed by reconnecting
+                let current = match &stored {
+                    Some(token) => !token.will_expire_soon() && entry.provider.access_token().await == token.access_token,
+                    None => false,
+                };
+                if current {
+                    entry.last_used = now;
+                    return Ok(entry.provider.clone());
+                }
+
+                debug!("Dropping cached {} provider for user {}: token changed", provider_name, user_id);
+                entries.remove(&key);
+            }
+        }
+
+        let provider = Arc::new(RefreshingProvider::connect(user_id, provider_name, database.clone(), self.oauth_config.clone()).await?);
+        self.entries.write().await.insert(
+            key,
+            CachedProvider {
+                provider: provider.clone(),
+                last_used: Instant::now(),
+            },
+        );
+        Ok(provider)
+    }
+
+    /// Drop the cached instance for a user's provider
+    pub async fn invalidate(&self, user_id: Uuid, provider_name: &str) {
+        self.entries.write().await.remove(&(user_id, provider_name.to_string()));
+    }
+
+    /// Number of cached instances, including idle ones not yet dropped
+    pub async fn len(&self) -> usize {
+        self.entries.read().await.len()
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::generate_encryption_key;
+    use crate::models::User;
+    use chrono::Utc;
+
+    async fn connected_user(database: &Database, email: &str) -> Uuid {
+        let user = User::new(email.to_string(), "hashed_password".to_string(), None);
+        let user_id = database.create_user(&user).await.unwrap();
+        database
+            .update_provider_token(user_id, "strava", "access", "refresh", Utc::now() + chrono::Duration::hours(6), "read".to_string())
+            .await
+            .unwrap();
+        user_id
+    }
+
+    fn strava_config() -> Arc<OAuthConfig> {
+        Arc::new(OAuthConfig {
+            strava: crate::config::environment::OAuthProviderConfig {
+                client_id: Some("strava_client".to_string()),
+                client_secret: Some("strava_secret".to_string()),
+                enabled: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        })
+    }
+
+    async fn test_database() -> Arc<Database> {
+        Arc::new(Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_provider_reused_until_token_changes() {
+        let database = test_database().await;
+        let user_id = connected_user(&database, "cache@example.com").await;
+        let cache = ProviderCache::new(Duration::from_secs(60), strava_config());
+
+        let first = cache.get(user_id, "strava", &database).await.unwrap();
+        let second = cache.get(user_id, "strava", &database).await.unwrap();
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // Reconnecting stores a new token, so the cached instance is replaced
+        database
+            .update_provider_token(user_id, "strava", "access_2", "refresh_2", Utc::now() + chrono::Duration::hours(6), "read".to_string())
+            .await
+            .unwrap();
+        let third = cache.get(user_id, "strava", &database).await.unwrap();
+        assert!(!Arc::ptr_eq(&second, &third));
+        assert_eq!(cache.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_disconnected_provider_is_not_served() {
+        let database = test_database().await;
+        let user_id = connected_user(&database, "disconnect@example.com").await;
+        let cache = ProviderCache::new(Duration::from_secs(60), strava_config());
+
+        cache.get(user_id, "strava", &database).await.unwrap();
+        database.delete_provider_connection(user_id, "strava").await.unwrap();
+
+        assert!(cache.get(user_id, "strava", &database).await.is_err());
+        assert!(cache.is_empty().await);
+    }
+
+    #[tokio::test]
+    async fn test_idle_and_invalidated_providers_are_dropped() {
+        let database = test_database().await;
+        let first_user = connected_user(&database, "idle@example.com").await;
+        let second_user = connected_user(&database, "active@example.com").await;
+        let cache = ProviderCache::new(Duration::from_millis(50), strava_config());
+
+        cache.get(first_user, "strava", &database).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        cache.get(second_user, "strava", &database).await.unwrap();
+        assert_eq!(cache.len().await, 1);
+
+        cache.invalidate(second_user, "strava").await;
+        assert!(cache.is_empty().await);
+    }
+}
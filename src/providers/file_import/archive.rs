@@ -0,0 +1,602 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Strava and Garmin account export archives
+//!
+//! Strava's "download your data" ZIP lists every activity in `activities.csv`
+//! and keeps the recorded files, often gzipped, under `activities/`. Garmin's
+//! export nests ZIPs of uploaded FIT files under `DI_CONNECT` next to a
+//! `summarizedActivities.json` holding names and totals. Activities keep the
+//! provider and id they have on the platform they came from, so they line up
+//! with activities synced over that platform's API.
+
+use super::{parse_activity_file, sport_from_name, ActivityFileFormat};
+use crate::config::FitnessConfig;
+use crate::constants::limits::{
+    ARCHIVE_DUPLICATE_DURATION_TOLERANCE, ARCHIVE_DUPLICATE_WINDOW_SECS, MAX_IMPORT_ARCHIVE_ENTRIES,
+    MAX_IMPORT_ARCHIVE_EXPANDED_BYTES, MAX_IMPORT_FILE_BYTES,
+};
+use crate::models::{Activity, ActivityDetails, SportType};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{Read, Seek, Write};
+use zip::ZipArchive;
+
+/// Platform an export archive was downloaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveSource {
+    Strava,
+    Garmin,
+}
+
+impl ArchiveSource {
+    /// Provider name the archive's activities are stored under
+    pub fn provider(&self) -> &'static str {
+        match self {
+            ArchiveSource::Strava => "strava",
+            ArchiveSource::Garmin => "garmin",
+        }
+    }
+}
+
+/// Activities read from an export archive
+#[derive(Debug)]
+pub struct ArchiveContents {
+    pub source: ArchiveSource,
+    pub activities: Vec<ActivityDetails>,
+    /// Entries that could not be read, with the reason. Their activities are
+    /// still imported from the summary when the archive has one.
+    pub failed: Vec<String>,
+}
+
+/// Read every activity from a Strava or Garmin export ZIP
+pub fn read_archive<R: Read + Seek>(reader: R) -> Result<ArchiveContents> {
+    let mut archive = ZipArchive::new(reader).context("Archive is not a valid ZIP file")?;
+    let mut budget = ReadBudget::new();
+    budget.add_entries(archive.len())?;
+    let names: Vec<String> = archive.file_names().map(str::to_string).collect();
+
+    if let Some(csv_name) = names.iter().find(|name| base_name(name) == "activities.csv") {
+        read_strava(&mut archive, csv_name, &mut budget)
+    } else if names.iter().any(|name| name.starts_with("DI_CONNECT/") || name.contains("summarizedActivities")) {
+        read_garmin(&mut archive, &names, &mut budget)
+    } else {
+        bail!("Not a Strava or Garmin export: no activities.csv or DI_CONNECT folder found")
+    }
+}
+
+/// Find an activity in the store that an archive activity duplicates: the same
+/// provider and id, or one from another provider starting within
+/// `ARCHIVE_DUPLICATE_WINDOW_SECS` with a similar duration, which catches a
+/// Garmin recording that was also synced to Strava. Separate activities from
+/// one provider are never merged, however close together they start.
+pub fn find_duplicate<'a>(activity: &Activity, existing: &'a [Activity]) -> Option<&'a Activity> {
+    existing.iter().find(|other| {
+        if other.provider == activity.provider {
+            return other.id == activity.id;
+        }
+        let longer = other.duration_seconds.max(activity.duration_seconds) as f64;
+        (other.start_date - activity.start_date).num_seconds().abs() <= ARCHIVE_DUPLICATE_WINDOW_SECS
+            && other.duration_seconds.abs_diff(activity.duration_seconds) as f64 <= longer * ARCHIVE_DUPLICATE_DURATION_TOLERANCE
+    })
+}
+
+/// Decompressed bytes and entries an import may still read. Shared by an
+/// archive and the ZIPs nested in it, so a small upload can't expand without bound.
+struct ReadBudget {
+    bytes: u64,
+    entries: usize,
+    exhausted: bool,
+}
+
+impl ReadBudget {
+    fn new() -> Self {
+        Self { bytes: MAX_IMPORT_ARCHIVE_EXPANDED_BYTES, entries: MAX_IMPORT_ARCHIVE_ENTRIES, exhausted: false }
+    }
+
+    fn add_entries(&mut self, count: usize) -> Result<()> {
+        match self.entries.checked_sub(count) {
+            Some(left) => {
+                self.entries = left;
+                Ok(())
+            }
+            None => {
+                self.exhausted = true;
+                bail!("Archive has more than {} entries", MAX_IMPORT_ARCHIVE_ENTRIES)
+            }
+        }
+    }
+
+    /// Copy an entry into `writer`, failing when it is larger than `cap` or
+    /// than what is left of the budget
+    fn copy<W: Write>(&mut self, name: &str, reader: impl Read, writer: &mut W, cap: u64) -> Result<()> {
+        let available = self.bytes;
+        let copied = std::io::copy(&mut reader.take(cap.min(available) + 1), writer)
+            .with_context(|| format!("Failed to read '{}'", name))?;
+        self.bytes = available.saturating_sub(copied);
+        if copied > available {
+            self.exhausted = true;
+            bail!("Archive expands to more than {} bytes", MAX_IMPORT_ARCHIVE_EXPANDED_BYTES);
+        }
+        if copied > cap {
+            bail!("'{}' is larger than {} bytes", name, cap);
+        }
+        Ok(())
+    }
+
+    /// Abort the import once the budget has run out. Entry errors are recorded
+    /// and skipped, so this is checked after each one.
+    fn check(&self) -> Result<()> {
+        if self.exhausted {
+            bail!(
+                "Archive exceeds the import limit of {} bytes or {} entries after decompression",
+                MAX_IMPORT_ARCHIVE_EXPANDED_BYTES,
+                MAX_IMPORT_ARCHIVE_ENTRIES
+            );
+        }
+        Ok(())
+    }
+}
+
+fn read_strava<R: Read + Seek>(archive: &mut ZipArchive<R>, csv_name: &str, budget: &mut ReadBudget) -> Result<ArchiveContents> {
+    let (_, csv) = read_entry(archive, csv_name, budget)?;
+    let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(csv.as_slice());
+    let columns = StravaColumns::new(reader.headers()?)?;
+    let fitness_config = FitnessConfig::default();
+
+    let mut contents = ArchiveContents { source: ArchiveSource::Strava, activities: Vec::new(), failed: Vec::new() };
+    for record in reader.records() {
+        let record = record?;
+        let field = |index: Option<usize>| index.and_then(|i| record.get(i)).map(str::trim).filter(|v| !v.is_empty());
+
+        let Some(id) = field(Some(columns.id)) else { continue };
+        let name = field(columns.name).unwrap_or_default();
+        let sport = field(columns.activity_type)
+            .map(|kind| SportType::from_provider_string(&kind.replace(' ', ""), &fitness_config))
+            .unwrap_or(SportType::Workout);
+
+        // Activities with a recorded file keep its streams; manual entries only have the CSV row
+        let recorded = field(columns.file_name).and_then(|file_name| match read_activity(archive, file_name, budget) {
+            Ok(details) => Some(details),
+            Err(e) => {
+                contents.failed.push(format!("{}: {:#}", file_name, e));
+                None
+            }
+        });
+        budget.check()?;
+        let mut details = match recorded {
+            Some(details) => details,
+            None => {
+                let Some(start_date) = field(Some(columns.date)).and_then(parse_strava_date) else {
+                    contents.failed.push(format!("Activity {}: missing or invalid date", id));
+                    continue;
+                };
+                let duration_seconds = field(columns.elapsed_time).and_then(|v| v.parse::<f64>().ok()).unwrap_or(0.0);
+                let distance_meters = field(columns.distance)
+                    .and_then(|v| v.replace(',', "").parse::<f64>().ok())
+                    .map(|distance| distance * columns.distance_scale);
+                summary_details(start_date, duration_seconds, distance_meters)
+            }
+        };
+
+        let activity = &mut details.activity;
+        activity.id = id.to_string();
+        activity.provider = ArchiveSource::Strava.provider().to_string();
+        activity.sport_type = sport;
+        if !name.is_empty() {
+            activity.name = name.to_string();
+        }
+        if activity.max_heart_rate.is_none() {
+            activity.max_heart_rate = field(columns.max_heart_rate).and_then(|v| v.parse::<f64>().ok()).map(|hr| hr as u32);
+        }
+        contents.activities.push(details);
+    }
+
+    Ok(contents)
+}
+
+/// Column positions in Strava's `activities.csv`
+struct StravaColumns {
+    id: usize,
+    date: usize,
+    name: Option<usize>,
+    activity_type: Option<usize>,
+    elapsed_time: Option<usize>,
+    distance: Option<usize>,
+    /// Meters per unit of the distance column
+    distance_scale: f64,
+    max_heart_rate: Option<usize>,
+    file_name: Option<usize>,
+}
+
+impl StravaColumns {
+    fn new(headers: &csv::StringRecord) -> Result<Self> {
+        let headers: Vec<&str> = headers.iter().map(str::trim).collect();
+        let first = |name: &str| headers.iter().position(|header| *header == name);
+        // Newer exports repeat some columns; the later copy holds raw units (meters)
+        let last = |name: &str| headers.iter().rposition(|header| *header == name);
+        let distance_columns = headers.iter().filter(|header| **header == "Distance").count();
+
+        Ok(Self {
+            id: first("Activity ID").context("activities.csv has no 'Activity ID' column")?,
+            date: first("Activity Date").context("activities.csv has no 'Activity Date' column")?,
+            name: first("Activity Name"),
+            activity_type: first("Activity Type"),
+            elapsed_time: last("Elapsed Time"),
+            distance: last("Distance"),
+            distance_scale: if distance_columns > 1 { 1.0 } else { 1000.0 },
+            max_heart_rate: first("Max Heart Rate"),
+            file_name: first("Filename"),
+        })
+    }
+}
+
+/// Strava writes dates in UTC as e.g. "Jun 1, 2024, 11:30:00 AM"
+fn parse_strava_date(text: &str) -> Option<DateTime<Utc>> {
+    ["%b %d, %Y, %I:%M:%S %p", "%b %d, %Y %I:%M:%S %p", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .map(|date| date.and_utc())
+}
+
+fn read_garmin<R: Read + Seek>(archive: &mut ZipArchive<R>, names: &[String], budget: &mut ReadBudget) -> Result<ArchiveContents> {
+    let mut contents = ArchiveContents { source: ArchiveSource::Garmin, activities: Vec::new(), failed: Vec::new() };
+
+    let mut summaries: HashMap<String, Value> = HashMap::new();
+    for name in names.iter().filter(|name| name.contains("summarizedActivities") && name.ends_with(".json")) {
+        let (_, json) = read_entry(archive, name, budget)?;
+        let value: Value = serde_json::from_slice(&json).with_context(|| format!("Failed to parse {}", name))?;
+        collect_summaries(&value, &mut summaries);
+    }
+
+    // Uploaded files sit in nested ZIPs, or directly in the archive when re-zipped by hand
+    let mut recorded: HashMap<String, ActivityDetails> = HashMap::new();
+    for name in names {
+        let lower = name.to_lowercase();
+        if lower.ends_with(".zip") {
+            if let Err(e) = read_nested_garmin(archive, name, budget, &mut recorded, &mut contents.failed) {
+                contents.failed.push(format!("{}: {:#}", name, e));
+            }
+            budget.check()?;
+        } else if is_activity_file(name) {
+            read_garmin_file(archive, name, budget, &mut recorded, &mut contents.failed)?;
+        }
+    }
+
+    for (id, summary) in &summaries {
+        let details = match recorded.remove(id) {
+            Some(details) => details,
+            None => match garmin_summary_details(summary) {
+                Some(details) => details,
+                None => {
+                    contents.failed.push(format!("Activity {}: summary has no start time", id));
+                    continue;
+                }
+            },
+        };
+        contents.activities.push(apply_garmin_summary(id, details, summary));
+    }
+    // Files without a summary entry keep what the file itself records
+    contents.activities.extend(recorded.into_values());
+    contents.activities.sort_by_key(|details| details.activity.start_date);
+
+    Ok(contents)
+}
+
+/// Read the activity files of a ZIP nested in a Garmin export. Nested archives
+/// bundle many uploads, so they are spooled to a temporary file and only the
+/// archive-wide budget applies, not the per-file cap.
+fn read_nested_garmin<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    budget: &mut ReadBudget,
+    recorded: &mut HashMap<String, ActivityDetails>,
+    failed: &mut Vec<String>,
+) -> Result<()> {
+    let mut file = tempfile::tempfile().context("Failed to create a temporary file")?;
+    let entry = archive
+        .by_name(name)
+        .with_context(|| format!("Archive has no entry '{}'", name))?;
+    budget.copy(name, entry, &mut file, u64::MAX)?;
+    file.rewind()?;
+
+    let mut nested = ZipArchive::new(file).context("Not a valid ZIP file")?;
+    budget.add_entries(nested.len())?;
+    let nested_names: Vec<String> = nested.file_names().map(str::to_string).collect();
+    for nested_name in nested_names.iter().filter(|name| is_activity_file(name)) {
+        read_garmin_file(&mut nested, nested_name, budget, recorded, failed)?;
+    }
+    Ok(())
+}
+
+fn read_garmin_file<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+    name: &str,
+    budget: &mut ReadBudget,
+    recorded: &mut HashMap<String, ActivityDetails>,
+    failed: &mut Vec<String>,
+) -> Result<()> {
+    match read_activity(archive, name, budget) {
+        Ok(mut details) => {
+            // Uploaded files are named `<account>_<activity id>.fit`
+            let id = garmin_activity_id(name).unwrap_or_else(|| details.activity.id.clone());
+            details.activity.id = id.clone();
+            details.activity.provider = ArchiveSource::Garmin.provider().to_string();
+            recorded.insert(id, details);
+        }
+        Err(e) => failed.push(format!("{}: {:#}", name, e)),
+    }
+    budget.check()
+}
+
+fn garmin_activity_id(name: &str) -> Option<String> {
+    let name = base_name(name);
+    let stem = std::path::Path::new(name.strip_suffix(".gz").unwrap_or(name)).file_stem()?.to_str()?;
+    let id = stem.rsplit('_').next()?;
+    (!id.is_empty() && id.chars().all(|c| c.is_ascii_digit())).then(|| id.to_string())
+}
+
+/// Collect `summarizedActivitiesExport` entries by activity id, wherever they are nested
+fn collect_summaries(value: &Value, summaries: &mut HashMap<String, Value>) {
+    match value {
+        Value::Array(items) => items.iter().for_each(|item| collect_summaries(item, summaries)),
+        Value::Object(object) => match object.get("activityId") {
+            Some(id) => {
+                let id = id.as_u64().map(|id| id.to_string()).or_else(|| id.as_str().map(str::to_string));
+                if let Some(id) = id {
+                    summaries.insert(id, value.clone());
+                }
+            }
+            None => object.values().for_each(|item| collect_summaries(item, summaries)),
+        },
+        _ => {}
+    }
+}
+
+/// Activity from a Garmin summary alone. Garmin exports durations in
+/// milliseconds and distances in centimeters.
+fn garmin_summary_details(summary: &Value) -> Option<ActivityDetails> {
+    let start_date = DateTime::from_timestamp_millis(summary["beginTimestamp"].as_i64()?)?;
+    let duration_seconds = summary["duration"].as_f64().unwrap_or(0.0) / 1000.0;
+    let distance_meters = summary["distance"].as_f64().map(|distance| distance / 100.0);
+    let mut details = summary_details(start_date, duration_seconds, distance_meters);
+
+    let activity = &mut details.activity;
+    activity.elevation_gain = summary["elevationGain"].as_f64().map(|gain| gain / 100.0);
+    activity.average_heart_rate = summary["avgHr"].as_f64().map(|hr| hr.round() as u32);
+    activity.max_heart_rate = summary["maxHr"].as_f64().map(|hr| hr.round() as u32);
+    activity.calories = summary["calories"].as_f64().map(|calories| calories.round() as u32);
+    activity.start_latitude = summary["startLatitude"].as_f64();
+    activity.start_longitude = summary["startLongitude"].as_f64();
+    Some(details)
+}
+
+fn apply_garmin_summary(id: &str, mut details: ActivityDetails, summary: &Value) -> ActivityDetails {
+    let activity = &mut details.activity;
+    activity.id = id.to_string();
+    activity.provider = ArchiveSource::Garmin.provider().to_string();
+    if let Some(name) = summary["name"].as_str().filter(|name| !name.trim().is_empty()) {
+        activity.name = name.to_string();
+    }
+    if let Some(kind) = summary["activityType"].as_str() {
+        activity.sport_type = sport_from_name(&kind.replace('_', " "));
+    }
+    details
+}
+
+/// Summary-only activity for entries without a recorded file
+fn summary_details(start_date: DateTime<Utc>, duration_seconds: f64, distance_meters: Option<f64>) -> ActivityDetails {
+    let duration_seconds = duration_seconds.max(0.0).round() as u64;
+    ActivityDetails {
+        activity: Activity {
+            id: String::new(),
+            name: String::new(),
+            sport_type: SportType::Workout,
+            start_date,
+            duration_seconds,
+            distance_meters,
+            elevation_gain: None,
+            average_heart_rate: None,
+            max_heart_rate: None,
+            average_speed: distance_meters
+                .filter(|_| duration_seconds > 0)
+                .map(|distance| distance / duration_seconds as f64),
+            max_speed: None,
+            calories: None,
+            start_latitude: None,
+            start_longitude: None,
+            city: None,
+            region: None,
+            country: None,
+            trail_name: None,
+            provider: String::new(),
+        },
+        streams: Vec::new(),
+        laps: Vec::new(),
+        device: None,
+    }
+}
+
+fn is_activity_file(name: &str) -> bool {
+    let name = base_name(name);
+    ActivityFileFormat::detect(name.strip_suffix(".gz").unwrap_or(name), &[]).is_some()
+}
+
+/// Read and parse an activity file entry, decompressing `.gz` files
+fn read_activity<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str, budget: &mut ReadBudget) -> Result<ActivityDetails> {
+    let (file_name, bytes) = read_entry(archive, name, budget)?;
+    parse_activity_file(&file_name, &bytes)
+}
+
+/// Read an archive entry, capped at `MAX_IMPORT_FILE_BYTES` after decompression.
+/// Returns the file name without any `.gz` suffix and the content.
+fn read_entry<R: Read + Seek>(archive: &mut ZipArchive<R>, name: &str, budget: &mut ReadBudget) -> Result<(String, Vec<u8>)> {
+    let entry = archive
+        .by_name(name)
+        .with_context(|| format!("Archive has no entry '{}'", name))?;
+
+    let file_name = base_name(name);
+    let mut bytes = Vec::new();
+    let file_name = match file_name.strip_suffix(".gz") {
+        Some(inner) => {
+            budget.copy(name, GzDecoder::new(entry), &mut bytes, MAX_IMPORT_FILE_BYTES)?;
+            inner
+        }
+        None => {
+            budget.copy(name, entry, &mut bytes, MAX_IMPORT_FILE_BYTES)?;
+            file_name
+        }
+    };
+
+    Ok((file_name.to_string(), bytes))
+}
+
+fn base_name(name: &str) -> &str {
+    name.rsplit('/').next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::io::Cursor;
+    use zip::write::SimpleFileOptions;
+
+    fn zip_of(entries: &[(&str, &[u8])]) -> Vec<u8> {
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, bytes) in entries {
+            writer.start_file(*name, SimpleFileOptions::default()).unwrap();
+            writer.write_all(bytes).unwrap();
+        }
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_strava_manual_activities() {
+        let csv = "Activity ID,Activity Date,Activity Name,Activity Type,Elapsed Time,Distance,Filename,Elapsed Time,Distance\n\
+                   101,\"Jan 5, 2020, 3:04:05 PM\",Treadmill,Run,1800,5.00,,1800,5000.0\n\
+                   102,not a date,Broken,Ride,60,1.0,,60,1000.0\n";
+        let contents = read_archive(Cursor::new(zip_of(&[("export_1/activities.csv", csv.as_bytes())]))).unwrap();
+
+        assert_eq!(contents.source, ArchiveSource::Strava);
+        assert_eq!(contents.activities.len(), 1);
+        assert_eq!(contents.failed.len(), 1);
+        let activity = &contents.activities[0].activity;
+        assert_eq!(activity.id, "101");
+        assert_eq!(activity.provider, "strava");
+        assert_eq!(activity.name, "Treadmill");
+        assert_eq!(activity.sport_type, SportType::Run);
+        assert_eq!(activity.start_date, Utc.with_ymd_and_hms(2020, 1, 5, 15, 4, 5).unwrap());
+        assert_eq!(activity.duration_seconds, 1800);
+        assert_eq!(activity.distance_meters, Some(5000.0));
+    }
+
+    #[test]
+    fn test_garmin_summaries() {
+        let json = r#"[{"summarizedActivitiesExport": [
+            {"activityId": 9001, "name": "Lunch swim", "activityType": "lap_swimming",
+             "beginTimestamp": 1717243200000, "duration": 1800000.0, "distance": 150000.0, "avgHr": 131.6}
+        ]}]"#;
+        let contents = read_archive(Cursor::new(zip_of(&[(
+            "DI_CONNECT/DI-Connect-Fitness/me_0_summarizedActivities.json",
+            json.as_bytes(),
+        )])))
+        .unwrap();
+
+        assert_eq!(contents.source, ArchiveSource::Garmin);
+        let activity = &contents.activities[0].activity;
+        assert_eq!(activity.id, "9001");
+        assert_eq!(activity.provider, "garmin");
+        assert_eq!(activity.sport_type, SportType::Swim);
+        assert_eq!(activity.duration_seconds, 1800);
+        assert_eq!(activity.distance_meters, Some(1500.0));
+        assert_eq!(activity.average_heart_rate, Some(132));
+    }
+
+    #[test]
+    fn test_garmin_nested_uploads() {
+        // Upload bundles routinely exceed the per-file cap; only the archive budget applies
+        let padding = vec![0u8; MAX_IMPORT_FILE_BYTES as usize + 1024];
+        let stored = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        writer.start_file("me_555.fit", stored).unwrap();
+        writer.write_all(include_bytes!("../../../tests/fixtures/activities/threshold_ride.fit")).unwrap();
+        writer.start_file("padding.bin", stored).unwrap();
+        writer.write_all(&padding).unwrap();
+        let uploads = writer.finish().unwrap().into_inner();
+        assert!(uploads.len() as u64 > MAX_IMPORT_FILE_BYTES);
+
+        let contents = read_archive(Cursor::new(zip_of(&[
+            ("DI_CONNECT/DI-Connect-Uploaded-Files/UploadedFiles_0-_Part1.zip", &uploads),
+            ("DI_CONNECT/DI-Connect-Uploaded-Files/UploadedFiles_0-_Part2.zip", b"not a zip"),
+        ])))
+        .unwrap();
+
+        assert_eq!(contents.activities.len(), 1);
+        assert_eq!(contents.activities[0].activity.id, "555");
+        assert_eq!(contents.activities[0].activity.provider, "garmin");
+        assert_eq!(contents.failed.len(), 1);
+        assert!(contents.failed[0].starts_with("DI_CONNECT/DI-Connect-Uploaded-Files/UploadedFiles_0-_Part2.zip"));
+    }
+
+    #[test]
+    fn test_read_budget() {
+        let mut budget = ReadBudget { bytes: 10, entries: 2, exhausted: false };
+        let mut out = Vec::new();
+        assert!(budget.copy("a", &[0u8; 6][..], &mut out, 5).is_err());
+        budget.check().unwrap();
+        budget.copy("b", &[0u8; 3][..], &mut out, 5).unwrap();
+        assert!(budget.copy("c", &[0u8; 5][..], &mut out, 5).is_err());
+        assert!(budget.check().is_err());
+
+        let mut budget = ReadBudget::new();
+        budget.add_entries(MAX_IMPORT_ARCHIVE_ENTRIES).unwrap();
+        assert!(budget.add_entries(1).is_err());
+        assert!(budget.check().is_err());
+    }
+
+    #[test]
+    fn test_find_duplicate() {
+        let start = Utc.with_ymd_and_hms(2024, 6, 1, 11, 30, 0).unwrap();
+        let mut synced = summary_details(start, 600.0, None).activity;
+        synced.id = "555".to_string();
+        synced.provider = "strava".to_string();
+
+        let mut same_id = synced.clone();
+        same_id.start_date = start + chrono::Duration::hours(1);
+        assert!(find_duplicate(&same_id, std::slice::from_ref(&synced)).is_some());
+
+        let mut from_garmin = synced.clone();
+        from_garmin.provider = "garmin".to_string();
+        from_garmin.id = "9001".to_string();
+        from_garmin.start_date = start + chrono::Duration::seconds(2);
+        assert!(find_duplicate(&from_garmin, std::slice::from_ref(&synced)).is_some());
+
+        // A different activity starting at the same time is kept
+        from_garmin.duration_seconds = 1800;
+        assert!(find_duplicate(&from_garmin, std::slice::from_ref(&synced)).is_none());
+
+        from_garmin.duration_seconds = 630;
+        from_garmin.start_date = start + chrono::Duration::minutes(30);
+        assert!(find_duplicate(&from_garmin, std::slice::from_ref(&synced)).is_none());
+
+        // Two activities from one provider are never merged by start time
+        let mut back_to_back = synced.clone();
+        back_to_back.id = "556".to_string();
+        back_to_back.start_date = start + chrono::Duration::seconds(30);
+        assert!(find_duplicate(&back_to_back, std::slice::from_ref(&synced)).is_none());
+
+        assert_eq!(garmin_activity_id("UploadedFiles/me@example.com_12345678901.fit"), Some("12345678901".to_string()));
+        assert_eq!(garmin_activity_id("me_987.fit.gz"), Some("987".to_string()));
+        assert_eq!(garmin_activity_id("ride.fit"), None);
+        assert!(read_archive(Cursor::new(b"not a zip")).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! FIT activity files (`file_id`, `device_info`, `session`, `lap` and `record` messages)
+//!
+//! A minimal decoder for the FIT binary protocol: definition and data messages
+//! in either byte order, compressed timestamp headers, and developer fields,
+//! which are skipped. Array fields are reduced to their first element.
+
+use super::{FileTotals, ParsedFile};
+use crate::models::{DeviceInfo, Lap, SportType, TrackPoint};
+use crate::workouts::fit::{crc16, FIT_EPOCH_OFFSET};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+const MESG_FILE_ID: u16 = 0;
+const MESG_SESSION: u16 = 18;
+const MESG_LAP: u16 = 19;
+const MESG_RECORD: u16 = 20;
+const MESG_DEVICE_INFO: u16 = 23;
+
+const FIELD_TIMESTAMP: u8 = 253;
+
+/// Size in bytes of each base type, indexed by base type number
+const BASE_TYPE_SIZES: [usize; 17] = [1, 1, 1, 2, 2, 4, 4, 1, 4, 8, 1, 2, 4, 1, 8, 8, 8];
+const BASE_TYPE_STRING: u8 = 7;
+
+/// Degrees per semicircle, the FIT unit for positions
+const SEMICIRCLE_DEGREES: f64 = 180.0 / 2_147_483_648.0;
+
+#[derive(Debug, Clone)]
+enum FieldValue {
+    Integer(i64),
+    Float(f64),
+    Text(String),
+}
+
+/// A decoded data message with its valid fields
+#[derive(Debug)]
+struct Message {
+    global: u16,
+    fields: HashMap<u8, FieldValue>,
+}
+
+impl Message {
+    fn integer(&self, field: u8) -> Option<i64> {
+        match self.fields.get(&field)? {
+            FieldValue::Integer(value) => Some(*value),
+            FieldValue::Float(value) => Some(*value as i64),
+            FieldValue::Text(_) => None,
+        }
+    }
+
+    /// Numeric field with the profile's scale and offset applied
+    fn scaled(&self, field: u8, scale: f64, offset: f64) -> Option<f64> {
+        let value = match self.fields.get(&field)? {
+            FieldValue::Integer(value) => *value as f64,
+            FieldValue::Float(value) => *value,
+            FieldValue::Text(_) => return None,
+        };
+        Some(value / scale - offset)
+    }
+
+    fn text(&self, field: u8) -> Option<String> {
+        match self.fields.get(&field)? {
+            FieldValue::Text(text) => Some(text.clone()),
+            FieldValue::Integer(value) => Some(value.to_string()),
+            FieldValue::Float(value) => Some(value.to_string()),
+        }
+    }
+
+    fn time(&self, field: u8) -> Option<DateTime<Utc>> {
+        self.integer(field)
+            .and_then(|seconds| DateTime::from_timestamp(seconds + FIT_EPOCH_OFFSET, 0))
+    }
+}
+
+struct FieldDefinition {
+    number: u8,
+    size: usize,
+    base_type: u8,
+}
+
+struct Definition {
+    global: u16,
+    big_endian: bool,
+    fields: Vec<FieldDefinition>,
+    developer_size: usize,
+}
+
+pub(crate) fn parse(bytes: &[u8]) -> Result<ParsedFile> {
+    let messages = decode(bytes)?;
+    let mut parsed = ParsedFile::default();
+    let mut session_seen = false;
+
+    for message in &messages {
+        match message.global {
+            MESG_FILE_ID => {
+                parsed.device = Some(DeviceInfo {
+                    manufacturer: message.integer(1).map(manufacturer_name),
+                    product: message.text(2),
+                    serial_number: message.text(3),
+                    software_version: None,
+                });
+            }
+            // Device index 0 is the device that created the file
+            MESG_DEVICE_INFO if message.integer(0).unwrap_or(0) == 0 => {
+                let device = parsed.device.get_or_insert_with(DeviceInfo::default);
+                device.manufacturer = device.manufacturer.take().or_else(|| message.integer(2).map(manufacturer_name));
+                device.product = device.product.take().or_else(|| message.text(4));
+                device.serial_number = device.serial_number.take().or_else(|| message.text(3));
+                device.software_version = message
+                    .scaled(5, 100.0, 0.0)
+                    .map(|version| format!("{:.2}", version));
+            }
+            // Multisport files have one session per leg; the first describes the activity
+            MESG_SESSION if !session_seen => {
+                session_seen = true;
+                parsed.sport = message.integer(5).map(|sport| fit_sport(sport, message.integer(6).unwrap_or(0)));
+                parsed.start_date = message.time(2);
+                parsed.totals = FileTotals {
+                    duration_seconds: message.scaled(7, 1000.0, 0.0),
+                    distance_meters: message.scaled(9, 100.0, 0.0),
+                    elevation_gain: message.scaled(22, 1.0, 0.0),
+                    calories: message.integer(11).map(|calories| calories as u32),
+                    average_heart_rate: message.integer(16).map(|hr| hr as u32),
+                    max_heart_rate: message.integer(17).map(|hr| hr as u32),
+                    average_speed: message.scaled(124, 1000.0, 0.0).or_else(|| message.scaled(14, 1000.0, 0.0)),
+                    max_speed: message.scaled(125, 1000.0, 0.0).or_else(|| message.scaled(15, 1000.0, 0.0)),
+                };
+            }
+            MESG_LAP => {
+                let Some(start_date) = message.time(2) else { continue };
+                parsed.laps.push(Lap {
+                    start_date,
+                    duration_seconds: message.scaled(7, 1000.0, 0.0).unwrap_or(0.0).round() as u64,
+                    distance_meters: message.scaled(9, 100.0, 0.0),
+                    average_heart_rate: message.integer(15).map(|hr| hr as u32),
+                    max_heart_rate: message.integer(16).map(|hr| hr as u32),
+                    average_speed: message.scaled(110, 1000.0, 0.0).or_else(|| message.scaled(13, 1000.0, 0.0)),
+                    calories: message.integer(11).map(|calories| calories as u32),
+                });
+            }
+            MESG_RECORD => {
+                let Some(time) = message.time(FIELD_TIMESTAMP) else { continue };
+                parsed.points.push(TrackPoint {
+                    time,
+                    latitude: message.integer(0).map(|lat| lat as f64 * SEMICIRCLE_DEGREES),
+                    longitude: message.integer(1).map(|lon| lon as f64 * SEMICIRCLE_DEGREES),
+                    altitude: message.scaled(78, 5.0, 500.0).or_else(|| message.scaled(2, 5.0, 500.0)),
+                    distance_meters: message.scaled(5, 100.0, 0.0),
+                    heart_rate: message.integer(3).map(|hr| hr as u32),
+                    cadence: message.integer(4).map(|cadence| cadence as u32),
+                    power: message.integer(7).map(|power| power as u32),
+                    speed: message.scaled(73, 1000.0, 0.0).or_else(|| message.scaled(6, 1000.0, 0.0)),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    Ok(parsed)
+}
+
+/// Decode every data message in a FIT file
+fn decode(bytes: &[u8]) -> Result<Vec<Message>> {
+    if bytes.len() < 12 || &bytes[8..12] != b".FIT" {
+        bail!("Not a FIT file");
+    }
+    let header_size = bytes[0] as usize;
+    let data_size = u32::from_le_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as usize;
+    let end = header_size + data_size;
+    if header_size < 12 || bytes.len() < end + 2 {
+        bail!("FIT file is truncated");
+    }
+    let stored_crc = u16::from_le_bytes([bytes[end], bytes[end + 1]]);
+    if stored_crc != 0 && crc16(&bytes[..end]) != stored_crc {
+        bail!("FIT file failed its CRC check");
+    }
+
+    let mut definitions: HashMap<u8, Definition> = HashMap::new();
+    let mut messages = Vec::new();
+    let mut last_timestamp: Option<i64> = None;
+    let mut pos = header_size;
+
+    let take = |pos: &mut usize, len: usize| -> Result<&[u8]> {
+        let slice = bytes.get(*pos..*pos + len).filter(|_| *pos + len <= end).context("FIT file is truncated")?;
+        *pos += len;
+        Ok(slice)
+    };
+
+    while pos < end {
+        let header = take(&mut pos, 1)?[0];
+
+        if header & 0x40 != 0 && header & 0x80 == 0 {
+            let local = header & 0x0F;
+            let fixed = take(&mut pos, 5)?;
+            let big_endian = fixed[1] == 1;
+            let global = if big_endian {
+                u16::from_be_bytes([fixed[2], fixed[3]])
+            } else {
+                u16::from_le_bytes([fixed[2], fixed[3]])
+            };
+            let field_count = fixed[4] as usize;
+            let fields = take(&mut pos, field_count * 3)?
+                .chunks(3)
+                .map(|field| FieldDefinition { number: field[0], size: field[1] as usize, base_type: field[2] })
+                .collect();
+            let developer_size = if header & 0x20 != 0 {
+                let count = take(&mut pos, 1)?[0] as usize;
+                take(&mut pos, count * 3)?.chunks(3).map(|field| field[1] as usize).sum()
+            } else {
+                0
+            };
+            definitions.insert(local, Definition { global, big_endian, fields, developer_size });
+            continue;
+        }
+
+        // Compressed timestamp headers carry the low 5 bits of the time since the last timestamp
+        let (local, compressed_time) = if header & 0x80 != 0 {
+            let offset = (header & 0x1F) as i64;
+            let time = last_timestamp.map(|last| {
+                let time = (last & !0x1F) | offset;
+                if offset < (last & 0x1F) { time + 0x20 } else { time }
+            });
+            ((header >> 5) & 0x03, time)
+        } else {
+            (header & 0x0F, None)
+        };
+
+        let definition = definitions
+            .get(&local)
+            .with_context(|| format!("FIT data message for undefined local type {}", local))?;
+        let mut fields = HashMap::new();
+        for field in &definition.fields {
+            let raw = take(&mut pos, field.size)?;
+            if let Some(value) = decode_field(raw, field.base_type, definition.big_endian) {
+                fields.insert(field.number, value);
+            }
+        }
+        take(&mut pos, definition.developer_size)?;
+
+        let mut message = Message { global: definition.global, fields };
+        if let Some(time) = compressed_time {
+            message.fields.entry(FIELD_TIMESTAMP).or_insert(FieldValue::Integer(time));
+        }
+        if let Some(time) = message.integer(FIELD_TIMESTAMP) {
+            last_timestamp = Some(time);
+        }
+        messages.push(message);
+    }
+
+    Ok(messages)
+}
+
+/// Decode the first element of a field, or None for the base type's invalid value
+fn decode_field(raw: &[u8], base_type: u8, big_endian: bool) -> Option<FieldValue> {
+    let base = base_type & 0x1F;
+    if base == BASE_TYPE_STRING {
+        let text = raw.split(|&byte| byte == 0).next().unwrap_or_default();
+        let text = String::from_utf8_lossy(text).trim().to_string();
+        return (!text.is_empty()).then_some(FieldValue::Text(text));
+    }
+
+    let size = *BASE_TYPE_SIZES.get(base as usize)?;
+    let element = raw.get(..size)?;
+    let mut unsigned: u64 = 0;
+    for i in 0..size {
+        let byte = if big_endian { element[i] } else { element[size - 1 - i] };
+        unsigned = (unsigned << 8) | byte as u64;
+    }
+    let all_ones = if size == 8 { u64::MAX } else { (1u64 << (size * 8)) - 1 };
+    let signed_max = all_ones >> 1;
+
+    match base {
+        // enum, uint8, uint16, uint32, byte, uint64
+        0 | 2 | 4 | 6 | 13 | 15 => (unsigned != all_ones).then_some(FieldValue::Integer(unsigned as i64)),
+        // sint8, sint16, sint32, sint64
+        1 | 3 | 5 | 14 => {
+            if unsigned == signed_max {
+                return None;
+            }
+            let shift = 64 - size * 8;
+            Some(FieldValue::Integer(((unsigned << shift) as i64) >> shift))
+        }
+        // uint8z, uint16z, uint32z, uint64z
+        10 | 11 | 12 | 16 => (unsigned != 0).then_some(FieldValue::Integer(unsigned as i64)),
+        8 => (unsigned != all_ones).then(|| FieldValue::Float(f32::from_bits(unsigned as u32) as f64)),
+        9 => (unsigned != all_ones).then(|| FieldValue::Float(f64::from_bits(unsigned))),
+        _ => None,
+    }
+}
+
+fn fit_sport(sport: i64, sub_sport: i64) -> SportType {
+    match (sport, sub_sport) {
+        (1, 1) => SportType::VirtualRun,
+        (1, 3) => SportType::TrailRunning,
+        (1, _) => SportType::Run,
+        (2, 6) | (2, 58) => SportType::VirtualRide,
+        (2, 8) => SportType::MountainBike,
+        (2, 46) => SportType::GravelRide,
+        (2, _) => SportType::Ride,
+        (5, _) => SportType::Swim,
+        (10, 20) => SportType::StrengthTraining,
+        (11, _) => SportType::Walk,
+        (12, _) => SportType::CrossCountrySkiing,
+        (13, _) => SportType::AlpineSkiing,
+        (14, _) => SportType::Snowboarding,
+        (15, _) => SportType::Rowing,
+        (17, _) => SportType::Hike,
+        (21, _) => SportType::EbikeRide,
+        _ => SportType::Workout,
+    }
+}
+
+fn manufacturer_name(id: i64) -> String {
+    match id {
+        1 => "Garmin".to_string(),
+        23 => "Suunto".to_string(),
+        32 => "Wahoo Fitness".to_string(),
+        123 => "Polar".to_string(),
+        255 => "Development".to_string(),
+        260 => "Zwift".to_string(),
+        265 => "Strava".to_string(),
+        294 => "Coros".to_string(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_field_invalid_values() {
+        assert!(decode_field(&[0xFF], 2, false).is_none());
+        assert!(matches!(decode_field(&[0x34, 0x12], 0x84, false), Some(FieldValue::Integer(0x1234))));
+        assert!(matches!(decode_field(&[0x12, 0x34], 0x84, true), Some(FieldValue::Integer(0x1234))));
+        assert!(matches!(decode_field(&[0xFE, 0xFF, 0xFF, 0xFF], 0x85, false), Some(FieldValue::Integer(-2))));
+        assert!(decode_field(&[0xFF, 0xFF, 0xFF, 0x7F], 0x85, false).is_none());
+        assert!(decode_field(&[0, 0], 0x8B, false).is_none());
+        assert!(matches!(decode_field(b"Edge\0\0", 7, false), Some(FieldValue::Text(text)) if text == "Edge"));
+    }
+
+    #[test]
+    fn test_rejects_corrupt_files() {
+        assert!(decode(b"not a fit file").is_err());
+
+        let mut file = vec![12, 0x10, 0, 0, 1, 0, 0, 0, b'.', b'F', b'I', b'T', 0x40];
+        let crc = crc16(&file);
+        file.extend_from_slice(&crc.to_le_bytes());
+        // A definition header with nothing after it
+        assert!(decode(&file).unwrap_err().to_string().contains("truncated"));
+
+        let last = file.len() - 1;
+        file[last] ^= 0xFF;
+        assert!(decode(&file).unwrap_err().to_string().contains("CRC"));
+    }
+}
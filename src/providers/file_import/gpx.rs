@@ -0,0 +1,146 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! GPX 1.1 tracks, including Garmin `TrackPointExtension` heart rate and cadence
+//!
+//! GPX has no laps; each track segment is reported as one so paused recordings
+//! keep their structure.
+
+use super::{sport_from_name, ParsedFile};
+use crate::models::{DeviceInfo, Lap, TrackPoint};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use roxmltree::{Document, Node};
+
+pub(crate) fn parse(xml: &str) -> Result<ParsedFile> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "gpx" {
+        bail!("Not a GPX document");
+    }
+
+    let Some(track) = child(root, "trk") else {
+        bail!("GPX file has no track");
+    };
+
+    let mut parsed = ParsedFile {
+        name: child_text(track, "name").map(str::to_string),
+        sport: child_text(track, "type").map(sport_from_name),
+        start_date: child(root, "metadata").and_then(|metadata| child_text(metadata, "time")).and_then(parse_time),
+        device: root.attribute("creator").map(|creator| DeviceInfo {
+            product: Some(creator.to_string()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+
+    for segment in children(track, "trkseg") {
+        let points: Vec<TrackPoint> = children(segment, "trkpt").filter_map(track_point).collect();
+        if let (Some(first), Some(last)) = (points.first(), points.last()) {
+            let heart_rates: Vec<u32> = points.iter().filter_map(|point| point.heart_rate).collect();
+            parsed.laps.push(Lap {
+                start_date: first.time,
+                duration_seconds: (last.time - first.time).num_seconds().max(0) as u64,
+                distance_meters: None,
+                average_heart_rate: (!heart_rates.is_empty())
+                    .then(|| heart_rates.iter().sum::<u32>() / heart_rates.len() as u32),
+                max_heart_rate: heart_rates.iter().copied().max(),
+                average_speed: None,
+                calories: None,
+            });
+        }
+        parsed.points.extend(points);
+    }
+
+    // A single segment is the whole activity, not a lap
+    if parsed.laps.len() == 1 {
+        parsed.laps.clear();
+    }
+
+    Ok(parsed)
+}
+
+/// A `trkpt`; points without a timestamp cannot be placed in a stream and are dropped
+fn track_point(node: Node) -> Option<TrackPoint> {
+    let time = child_text(node, "time").and_then(parse_time)?;
+    let extension = |name: &str| {
+        child(node, "extensions")
+            .and_then(|extensions| extensions.descendants().find(|n| n.tag_name().name() == name))
+            .and_then(|n| n.text())
+            .and_then(|text| text.trim().parse::<f64>().ok())
+    };
+
+    Some(TrackPoint {
+        time,
+        latitude: node.attribute("lat").and_then(|lat| lat.parse().ok()),
+        longitude: node.attribute("lon").and_then(|lon| lon.parse().ok()),
+        altitude: child_text(node, "ele").and_then(|ele| ele.parse().ok()),
+        distance_meters: None,
+        heart_rate: extension("hr").map(|hr| hr as u32),
+        cadence: extension("cad").map(|cad| cad as u32),
+        power: extension("power").map(|power| power as u32),
+        speed: extension("speed"),
+    })
+}
+
+pub(super) fn parse_time(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text.trim()).ok().map(|time| time.with_timezone(&Utc))
+}
+
+pub(super) fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|n| n.tag_name().name() == name)
+}
+
+pub(super) fn children<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children().filter(move |n| n.tag_name().name() == name)
+}
+
+pub(super) fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|n| n.text()).map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SportType;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_gpx_track() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx creator="StravaGPX" version="1.1" xmlns="http://www.topografix.com/GPX/1/1"
+     xmlns:gpxtpx="http://www.garmin.com/xmlschemas/TrackPointExtension/v1">
+  <metadata><time>2024-06-01T07:00:00Z</time></metadata>
+  <trk>
+    <name>Lachine canal</name>
+    <type>running</type>
+    <trkseg>
+      <trkpt lat="45.4800" lon="-73.5800"><ele>20.0</ele><time>2024-06-01T07:00:00Z</time>
+        <extensions><gpxtpx:TrackPointExtension><gpxtpx:hr>130</gpxtpx:hr><gpxtpx:cad>84</gpxtpx:cad></gpxtpx:TrackPointExtension></extensions>
+      </trkpt>
+      <trkpt lat="45.4810" lon="-73.5800"><ele>22.5</ele><time>2024-06-01T07:00:30Z</time></trkpt>
+      <trkpt lat="45.4820" lon="-73.5800"><time>bad</time></trkpt>
+    </trkseg>
+  </trk>
+</gpx>"#;
+
+        let parsed = parse(xml).unwrap();
+        assert_eq!(parsed.name.as_deref(), Some("Lachine canal"));
+        assert_eq!(parsed.sport, Some(SportType::Run));
+        assert_eq!(parsed.start_date, Some(Utc.with_ymd_and_hms(2024, 6, 1, 7, 0, 0).unwrap()));
+        assert_eq!(parsed.device.unwrap().product.as_deref(), Some("StravaGPX"));
+        assert_eq!(parsed.points.len(), 2);
+        assert_eq!(parsed.points[0].heart_rate, Some(130));
+        assert_eq!(parsed.points[0].cadence, Some(84));
+        assert_eq!(parsed.points[1].altitude, Some(22.5));
+        assert!(parsed.laps.is_empty());
+
+        assert!(parse("<kml/>").is_err());
+    }
+}
@@ -0,0 +1,516 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Activity file import (GPX, TCX and FIT)
+//!
+//! For devices whose platforms we cannot reach over OAuth, activities are read
+//! straight from the files they record. Files are parsed into an
+//! [`ActivityDetails`] keeping streams, laps and device info; the summary
+//! [`Activity`] is derived from device totals where present and from the
+//! recorded samples otherwise.
+
+pub mod archive;
+pub mod fit;
+pub mod gpx;
+pub mod tcx;
+
+use super::{AuthData, FitnessProvider};
+use crate::constants::limits::IMPORT_WATCH_INTERVAL_SECS;
+use crate::models::{Activity, ActivityDetails, Athlete, DeviceInfo, Lap, PersonalRecord, SportType, Stats, TrackPoint};
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Timelike, Utc};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+pub const PROVIDER_NAME: &str = "file";
+
+/// Mean Earth radius used for distances between GPS points
+const EARTH_RADIUS_METERS: f64 = 6_371_000.0;
+
+/// Supported activity file formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ActivityFileFormat {
+    Gpx,
+    Tcx,
+    Fit,
+}
+
+impl ActivityFileFormat {
+    /// Detect the format from the file extension, falling back to the content
+    pub fn detect(file_name: &str, bytes: &[u8]) -> Option<Self> {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase);
+        match extension.as_deref() {
+            Some("gpx") => return Some(Self::Gpx),
+            Some("tcx") => return Some(Self::Tcx),
+            Some("fit") => return Some(Self::Fit),
+            _ => {}
+        }
+
+        if bytes.len() >= 12 && &bytes[8..12] == b".FIT" {
+            return Some(Self::Fit);
+        }
+        let head = String::from_utf8_lossy(&bytes[..bytes.len().min(1024)]);
+        if head.contains("<gpx") {
+            Some(Self::Gpx)
+        } else if head.contains("<TrainingCenterDatabase") {
+            Some(Self::Tcx)
+        } else {
+            None
+        }
+    }
+}
+
+/// Totals recorded by the device, preferred over values derived from samples
+#[derive(Debug, Default)]
+pub(crate) struct FileTotals {
+    pub duration_seconds: Option<f64>,
+    pub distance_meters: Option<f64>,
+    pub elevation_gain: Option<f64>,
+    pub calories: Option<u32>,
+    pub average_heart_rate: Option<u32>,
+    pub max_heart_rate: Option<u32>,
+    pub average_speed: Option<f64>,
+    pub max_speed: Option<f64>,
+}
+
+/// What a format parser extracts from one file
+#[derive(Debug, Default)]
+pub(crate) struct ParsedFile {
+    pub name: Option<String>,
+    pub sport: Option<SportType>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub points: Vec<TrackPoint>,
+    pub laps: Vec<Lap>,
+    pub device: Option<DeviceInfo>,
+    pub totals: FileTotals,
+}
+
+/// Parse a GPX, TCX or FIT file into an activity with its streams, laps and device
+pub fn parse_activity_file(file_name: &str, bytes: &[u8]) -> Result<ActivityDetails> {
+    let format = ActivityFileFormat::detect(file_name, bytes)
+        .with_context(|| format!("Unsupported activity file '{}'; expected GPX, TCX or FIT", file_name))?;
+
+    let parsed = match format {
+        ActivityFileFormat::Gpx => xml_text(bytes).and_then(gpx::parse),
+        ActivityFileFormat::Tcx => xml_text(bytes).and_then(tcx::parse),
+        ActivityFileFormat::Fit => fit::parse(bytes),
+    }
+    .with_context(|| format!("Failed to parse '{}'", file_name))?;
+
+    summarize(file_id(bytes), parsed)
+}
+
+fn xml_text(bytes: &[u8]) -> Result<&str> {
+    let text = std::str::from_utf8(bytes).context("Activity file is not valid UTF-8")?;
+    // Some exporters write a byte order mark, which the XML parser rejects
+    Ok(text.trim_start_matches('\u{feff}').trim_start())
+}
+
+/// Stable id from the file content, so importing the same file twice updates one activity
+fn file_id(bytes: &[u8]) -> String {
+    let digest = Sha256::digest(bytes);
+    let hex: String = digest[..8].iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("file_{}", hex)
+}
+
+/// Build the activity summary from a parsed file
+fn summarize(id: String, parsed: ParsedFile) -> Result<ActivityDetails> {
+    let ParsedFile { name, sport, start_date, mut points, laps, device, totals } = parsed;
+    points.sort_by_key(|point| point.time);
+    fill_distances(&mut points);
+
+    let Some(start_date) = start_date
+        .or_else(|| points.first().map(|point| point.time))
+        .or_else(|| laps.first().map(|lap| lap.start_date))
+    else {
+        bail!("Activity file has no timestamps");
+    };
+    let sport = sport.unwrap_or(SportType::Workout);
+
+    let duration_seconds = totals.duration_seconds.unwrap_or_else(|| match (points.first(), points.last()) {
+        (Some(first), Some(last)) => (last.time - first.time).num_milliseconds() as f64 / 1000.0,
+        _ => laps.iter().map(|lap| lap.duration_seconds as f64).sum(),
+    });
+    let distance_meters = totals
+        .distance_meters
+        .or_else(|| points.iter().rev().find_map(|point| point.distance_meters))
+        .or_else(|| laps.iter().map(|lap| lap.distance_meters).sum::<Option<f64>>().filter(|_| !laps.is_empty()));
+    let elevation_gain = totals.elevation_gain.or_else(|| elevation_gain(&points));
+
+    let heart_rates: Vec<u32> = points.iter().filter_map(|point| point.heart_rate).collect();
+    let average_heart_rate = totals.average_heart_rate.or_else(|| {
+        (!heart_rates.is_empty())
+            .then(|| (heart_rates.iter().map(|&hr| hr as f64).sum::<f64>() / heart_rates.len() as f64).round() as u32)
+    });
+    let max_heart_rate = totals.max_heart_rate.or_else(|| heart_rates.iter().copied().max());
+
+    let average_speed = totals.average_speed.or_else(|| {
+        distance_meters
+            .filter(|_| duration_seconds > 0.0)
+            .map(|distance| distance / duration_seconds)
+    });
+    let max_speed = totals
+        .max_speed
+        .or_else(|| points.iter().filter_map(|point| point.speed).reduce(f64::max));
+    let calories = totals
+        .calories
+        .or_else(|| laps.iter().map(|lap| lap.calories).sum::<Option<u32>>().filter(|_| !laps.is_empty()));
+
+    let start = points.iter().find(|point| point.latitude.is_some() && point.longitude.is_some());
+
+    let activity = Activity {
+        id,
+        name: name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| default_name(&sport, start_date)),
+        sport_type: sport,
+        start_date,
+        duration_seconds: duration_seconds.max(0.0).round() as u64,
+        distance_meters,
+        elevation_gain,
+        average_heart_rate,
+        max_heart_rate,
+        average_speed,
+        max_speed,
+        calories,
+        start_latitude: start.and_then(|point| point.latitude),
+        start_longitude: start.and_then(|point| point.longitude),
+        city: None,
+        region: None,
+        country: None,
+        trail_name: None,
+        provider: PROVIDER_NAME.to_string(),
+    };
+
+    Ok(ActivityDetails { activity, streams: points, laps, device })
+}
+
+/// Fill in cumulative distance from GPS positions when the file does not record it
+fn fill_distances(points: &mut [TrackPoint]) {
+    if points.iter().any(|point| point.distance_meters.is_some()) {
+        return;
+    }
+
+    let mut total = 0.0;
+    let mut previous: Option<(f64, f64)> = None;
+    for point in points.iter_mut() {
+        let (Some(lat), Some(lon)) = (point.latitude, point.longitude) else {
+            continue;
+        };
+        if let Some((prev_lat, prev_lon)) = previous {
+            total += haversine_meters(prev_lat, prev_lon, lat, lon);
+        }
+        point.distance_meters = Some(total);
+        previous = Some((lat, lon));
+    }
+}
+
+/// Great-circle distance between two coordinates in meters
+pub(crate) fn haversine_meters(lat1: f64, lon1: f64, lat2: f64, lon2: f64) -> f64 {
+    let (lat1, lat2) = (lat1.to_radians(), lat2.to_radians());
+    let d_lat = lat2 - lat1;
+    let d_lon = (lon2 - lon1).to_radians();
+    let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_METERS * a.sqrt().asin()
+}
+
+/// Sum of climbs between consecutive altitude samples
+fn elevation_gain(points: &[TrackPoint]) -> Option<f64> {
+    let altitudes: Vec<f64> = points.iter().filter_map(|point| point.altitude).collect();
+    if altitudes.len() < 2 {
+        return None;
+    }
+    Some(altitudes.windows(2).map(|pair| (pair[1] - pair[0]).max(0.0)).sum())
+}
+
+/// Name an untitled activity by time of day, e.g. "Morning run"
+pub(crate) fn default_name(sport: &SportType, start: DateTime<Utc>) -> String {
+    let part_of_day = match start.hour() {
+        5..=11 => "Morning",
+        12..=16 => "Afternoon",
+        17..=20 => "Evening",
+        _ => "Night",
+    };
+    format!("{} {}", part_of_day, sport.display_name())
+}
+
+/// Map the sport names used by GPX and TCX exporters
+pub(crate) fn sport_from_name(name: &str) -> SportType {
+    let lower = name.trim().to_lowercase();
+    match lower.as_str() {
+        // Older Strava GPX exports use numeric activity types
+        "1" => SportType::Ride,
+        "9" => SportType::Run,
+        "10" => SportType::Walk,
+        _ if lower.contains("trail") => SportType::TrailRunning,
+        _ if lower.contains("run") => SportType::Run,
+        _ if lower.contains("mountain") => SportType::MountainBike,
+        _ if lower.contains("cycl") || lower.contains("bik") || lower.contains("ride") => SportType::Ride,
+        _ if lower.contains("swim") => SportType::Swim,
+        _ if lower.contains("walk") => SportType::Walk,
+        _ if lower.contains("hik") => SportType::Hike,
+        "" | "other" => SportType::Workout,
+        _ => SportType::from_name(&lower),
+    }
+}
+
+/// Activities imported from uploaded files or a watched directory
+#[derive(Default)]
+struct ImportStore {
+    activities: HashMap<String, ActivityDetails>,
+    /// Modification time of each file already read from a watched directory
+    seen: HashMap<PathBuf, SystemTime>,
+}
+
+/// Provider backed by activity files rather than a remote API
+#[derive(Default)]
+pub struct FileImportProvider {
+    store: Arc<RwLock<ImportStore>>,
+    watcher: Option<JoinHandle<()>>,
+}
+
+impl FileImportProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse and add one file. Importing the same file again replaces it.
+    pub fn import_file(&self, file_name: &str, bytes: &[u8]) -> Result<Activity> {
+        let details = parse_activity_file(file_name, bytes)?;
+        let activity = details.activity.clone();
+        self.store
+            .write()
+            .map_err(|_| anyhow::anyhow!("Import store lock poisoned"))?
+            .activities
+            .insert(activity.id.clone(), details);
+        Ok(activity)
+    }
+
+    /// Streams, laps and device info of an imported activity
+    pub fn get_activity_details(&self, id: &str) -> Option<ActivityDetails> {
+        self.store.read().ok()?.activities.get(id).cloned()
+    }
+
+    /// Import new or changed files from a directory. Returns the number imported.
+    pub fn scan_directory(&self, dir: &Path) -> Result<usize> {
+        scan_directory(&self.store, dir)
+    }
+
+    /// Import a directory now and keep importing files added to it
+    pub async fn watch_directory(&mut self, dir: PathBuf, interval: Duration) -> Result<()> {
+        let store = self.store.clone();
+        let initial_dir = dir.clone();
+        let imported = tokio::task::spawn_blocking(move || scan_directory(&store, &initial_dir)).await??;
+        info!("Imported {} activity files from {}", imported, dir.display());
+
+        if let Some(previous) = self.watcher.take() {
+            previous.abort();
+        }
+        let store = self.store.clone();
+        self.watcher = Some(tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let store = store.clone();
+                let dir = dir.clone();
+                match tokio::task::spawn_blocking(move || scan_directory(&store, &dir)).await {
+                    Ok(Ok(0)) => {}
+                    Ok(Ok(count)) => info!("Imported {} new activity files", count),
+                    Ok(Err(e)) => warn!("Failed to scan import directory: {}", e),
+                    Err(e) => warn!("Import directory scan panicked: {}", e),
+                }
+            }
+        }));
+        Ok(())
+    }
+
+    fn activities(&self) -> Result<Vec<Activity>> {
+        let store = self
+            .store
+            .read()
+            .map_err(|_| anyhow::anyhow!("Import store lock poisoned"))?;
+        let mut activities: Vec<Activity> = store.activities.values().map(|details| details.activity.clone()).collect();
+        activities.sort_by_key(|activity| std::cmp::Reverse(activity.start_date));
+        Ok(activities)
+    }
+}
+
+impl Drop for FileImportProvider {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            watcher.abort();
+        }
+    }
+}
+
+fn scan_directory(store: &RwLock<ImportStore>, dir: &Path) -> Result<usize> {
+    let entries = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read import directory {}", dir.display()))?;
+
+    let mut imported = 0;
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(metadata) = entry.metadata() else { continue };
+        let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else { continue };
+        if !metadata.is_file() || ActivityFileFormat::detect(file_name, &[]).is_none() {
+            continue;
+        }
+
+        let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+        let unchanged = store
+            .read()
+            .map_err(|_| anyhow::anyhow!("Import store lock poisoned"))?
+            .seen
+            .get(&path)
+            .is_some_and(|seen| *seen >= modified);
+        if unchanged {
+            continue;
+        }
+
+        let parsed = std::fs::read(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|bytes| parse_activity_file(file_name, &bytes));
+        let mut store = store
+            .write()
+            .map_err(|_| anyhow::anyhow!("Import store lock poisoned"))?;
+        // Files that fail are skipped until they change, e.g. once a copy completes
+        store.seen.insert(path.clone(), modified);
+        match parsed {
+            Ok(details) => {
+                store.activities.insert(details.activity.id.clone(), details);
+                imported += 1;
+            }
+            Err(e) => warn!("Skipping {}: {:#}", path.display(), e),
+        }
+    }
+
+    Ok(imported)
+}
+
+#[async_trait]
+impl FitnessProvider for FileImportProvider {
+    async fn authenticate(&mut self, auth_data: AuthData) -> Result<()> {
+        match auth_data {
+            AuthData::Directory(dir) => {
+                self.watch_directory(dir, Duration::from_secs(IMPORT_WATCH_INTERVAL_SECS)).await
+            }
+            _ => Err(anyhow::anyhow!("File import reads a local directory and needs no credentials")),
+        }
+    }
+
+    async fn get_athlete(&self) -> Result<Athlete> {
+        Ok(Athlete {
+            id: "local".to_string(),
+            username: "local".to_string(),
+            firstname: None,
+            lastname: None,
+            profile_picture: None,
+            provider: PROVIDER_NAME.to_string(),
+        })
+    }
+
+    async fn get_activities(&self, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Activity>> {
+        Ok(self
+            .activities()?
+            .into_iter()
+            .skip(offset.unwrap_or(0))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn get_activity(&self, id: &str) -> Result<Activity> {
+        self.get_activity_details(id)
+            .map(|details| details.activity)
+            .with_context(|| format!("Activity {} not found", id))
+    }
+
+    async fn get_stats(&self) -> Result<Stats> {
+        let activities = self.activities()?;
+        Ok(Stats {
+            total_activities: activities.len() as u64,
+            total_distance: activities.iter().filter_map(|a| a.distance_meters).sum(),
+            total_duration: activities.iter().map(|a| a.duration_seconds).sum(),
+            total_elevation_gain: activities.iter().filter_map(|a| a.elevation_gain).sum(),
+        })
+    }
+
+    async fn get_personal_records(&self) -> Result<Vec<PersonalRecord>> {
+        Ok(vec![])
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "File Import"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn point(seconds: i64, lat: f64, altitude: f64, heart_rate: u32) -> TrackPoint {
+        TrackPoint {
+            time: Utc.with_ymd_and_hms(2024, 6, 1, 7, 0, 0).unwrap() + chrono::Duration::seconds(seconds),
+            latitude: Some(lat),
+            longitude: Some(-73.6),
+            altitude: Some(altitude),
+            heart_rate: Some(heart_rate),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_summary_from_samples() {
+        let parsed = ParsedFile {
+            sport: Some(SportType::Run),
+            points: vec![point(600, 45.52, 110.0, 160), point(0, 45.50, 100.0, 140), point(300, 45.51, 95.0, 150)],
+            ..Default::default()
+        };
+
+        let details = summarize("file_test".to_string(), parsed).unwrap();
+        let activity = &details.activity;
+        assert_eq!(activity.name, "Morning run");
+        assert_eq!(activity.duration_seconds, 600);
+        assert_eq!(activity.average_heart_rate, Some(150));
+        assert_eq!(activity.max_heart_rate, Some(160));
+        assert_eq!(activity.elevation_gain, Some(15.0));
+        assert_eq!(activity.start_latitude, Some(45.50));
+
+        // 0.02 degrees of latitude is about 2.22 km
+        let distance = activity.distance_meters.unwrap();
+        assert!((distance - 2224.0).abs() < 5.0, "distance {}", distance);
+        assert_eq!(details.streams[0].distance_meters, Some(0.0));
+        assert!((activity.average_speed.unwrap() - distance / 600.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_format_detection() {
+        assert_eq!(ActivityFileFormat::detect("Run.GPX", b""), Some(ActivityFileFormat::Gpx));
+        assert_eq!(ActivityFileFormat::detect("upload", b"<?xml version=\"1.0\"?><TrainingCenterDatabase>"), Some(ActivityFileFormat::Tcx));
+        assert_eq!(ActivityFileFormat::detect("upload", b"\x0e\x10\x00\x00\x00\x00\x00\x00.FIT"), Some(ActivityFileFormat::Fit));
+        assert_eq!(ActivityFileFormat::detect("notes.txt", b"hello"), None);
+        assert!(parse_activity_file("notes.txt", b"hello").is_err());
+    }
+
+    #[test]
+    fn test_sport_names() {
+        assert_eq!(sport_from_name("running"), SportType::Run);
+        assert_eq!(sport_from_name("Biking"), SportType::Ride);
+        assert_eq!(sport_from_name("9"), SportType::Run);
+        assert_eq!(sport_from_name("trail_running"), SportType::TrailRunning);
+        assert_eq!(sport_from_name("Other"), SportType::Workout);
+    }
+}
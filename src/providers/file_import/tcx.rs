@@ -0,0 +1,182 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Garmin Training Center (TCX) activities
+//!
+//! Only the first `Activity` of a file is read. Lap totals are summed into the
+//! activity totals; speed and power come from the `TPX` activity extension.
+
+use super::gpx::{child, child_text, children, parse_time};
+use super::{sport_from_name, FileTotals, ParsedFile};
+use crate::models::{DeviceInfo, Lap, TrackPoint};
+use anyhow::{bail, Result};
+use roxmltree::{Document, Node};
+
+pub(crate) fn parse(xml: &str) -> Result<ParsedFile> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "TrainingCenterDatabase" {
+        bail!("Not a TCX document");
+    }
+
+    let Some(activity) = child(root, "Activities").and_then(|activities| child(activities, "Activity")) else {
+        bail!("TCX file has no activity");
+    };
+
+    let mut parsed = ParsedFile {
+        name: child_text(activity, "Notes").map(str::to_string),
+        sport: activity.attribute("Sport").map(sport_from_name),
+        start_date: child_text(activity, "Id").and_then(parse_time),
+        device: child(activity, "Creator").map(device_info),
+        ..Default::default()
+    };
+
+    for lap in children(activity, "Lap") {
+        let Some(start_date) = lap.attribute("StartTime").and_then(parse_time) else {
+            continue;
+        };
+        parsed.laps.push(Lap {
+            start_date,
+            duration_seconds: number(lap, "TotalTimeSeconds").unwrap_or(0.0).round() as u64,
+            distance_meters: number(lap, "DistanceMeters"),
+            average_heart_rate: value(lap, "AverageHeartRateBpm").map(|hr| hr as u32),
+            max_heart_rate: value(lap, "MaximumHeartRateBpm").map(|hr| hr as u32),
+            average_speed: child(lap, "Extensions").and_then(|extensions| extension(extensions, "AvgSpeed")),
+            calories: number(lap, "Calories").map(|calories| calories as u32),
+        });
+
+        for track in children(lap, "Track") {
+            parsed.points.extend(children(track, "Trackpoint").filter_map(track_point));
+        }
+    }
+
+    parsed.totals = lap_totals(&parsed.laps, children(activity, "Lap").filter_map(|lap| number(lap, "MaximumSpeed")));
+    Ok(parsed)
+}
+
+fn lap_totals(laps: &[Lap], max_speeds: impl Iterator<Item = f64>) -> FileTotals {
+    if laps.is_empty() {
+        return FileTotals::default();
+    }
+
+    let sum = |value: fn(&Lap) -> Option<f64>| {
+        let values: Vec<f64> = laps.iter().filter_map(value).collect();
+        (!values.is_empty()).then(|| values.iter().sum::<f64>())
+    };
+    FileTotals {
+        duration_seconds: sum(|lap| Some(lap.duration_seconds as f64)),
+        distance_meters: sum(|lap| lap.distance_meters),
+        calories: sum(|lap| lap.calories.map(f64::from)).map(|calories| calories as u32),
+        max_heart_rate: laps.iter().filter_map(|lap| lap.max_heart_rate).max(),
+        max_speed: max_speeds.reduce(f64::max),
+        ..Default::default()
+    }
+}
+
+fn track_point(node: Node) -> Option<TrackPoint> {
+    let time = child_text(node, "Time").and_then(parse_time)?;
+    let position = child(node, "Position");
+    let extensions = child(node, "Extensions");
+
+    Some(TrackPoint {
+        time,
+        latitude: position.and_then(|position| number(position, "LatitudeDegrees")),
+        longitude: position.and_then(|position| number(position, "LongitudeDegrees")),
+        altitude: number(node, "AltitudeMeters"),
+        distance_meters: number(node, "DistanceMeters"),
+        heart_rate: value(node, "HeartRateBpm").map(|hr| hr as u32),
+        cadence: number(node, "Cadence")
+            .or_else(|| extensions.and_then(|extensions| extension(extensions, "RunCadence")))
+            .map(|cadence| cadence as u32),
+        power: extensions
+            .and_then(|extensions| extension(extensions, "Watts"))
+            .map(|watts| watts as u32),
+        speed: extensions.and_then(|extensions| extension(extensions, "Speed")),
+    })
+}
+
+fn device_info(creator: Node) -> DeviceInfo {
+    let version = child(creator, "Version").and_then(|version| {
+        let major = child_text(version, "VersionMajor")?;
+        let minor = child_text(version, "VersionMinor").unwrap_or("0");
+        Some(format!("{}.{}", major, minor))
+    });
+
+    DeviceInfo {
+        manufacturer: None,
+        product: child_text(creator, "Name").map(str::to_string),
+        serial_number: child_text(creator, "UnitId").map(str::to_string),
+        software_version: version,
+    }
+}
+
+fn number(node: Node, name: &str) -> Option<f64> {
+    child_text(node, name).and_then(|text| text.parse().ok())
+}
+
+/// Heart rates are wrapped in a `<Value>` element
+fn value(node: Node, name: &str) -> Option<f64> {
+    child(node, name).and_then(|wrapper| number(wrapper, "Value"))
+}
+
+/// Extension fields live one level down, inside a namespaced `TPX`/`LX` element
+fn extension(extensions: Node, name: &str) -> Option<f64> {
+    extensions
+        .descendants()
+        .find(|n| n.tag_name().name() == name)
+        .and_then(|n| n.text())
+        .and_then(|text| text.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SportType;
+
+    #[test]
+    fn test_tcx_laps() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<TrainingCenterDatabase xmlns="http://www.garmin.com/xmlschemas/TrainingCenterDatabase/v2"
+    xmlns:ns3="http://www.garmin.com/xmlschemas/ActivityExtension/v2">
+  <Activities>
+    <Activity Sport="Biking">
+      <Id>2024-05-04T13:00:00Z</Id>
+      <Lap StartTime="2024-05-04T13:00:00Z">
+        <TotalTimeSeconds>600</TotalTimeSeconds><DistanceMeters>5000</DistanceMeters>
+        <MaximumSpeed>12.5</MaximumSpeed><Calories>150</Calories>
+        <MaximumHeartRateBpm><Value>150</Value></MaximumHeartRateBpm>
+        <Track>
+          <Trackpoint><Time>2024-05-04T13:00:00Z</Time><HeartRateBpm><Value>120</Value></HeartRateBpm>
+            <Extensions><ns3:TPX><ns3:Speed>8.1</ns3:Speed><ns3:Watts>180</ns3:Watts></ns3:TPX></Extensions></Trackpoint>
+        </Track>
+      </Lap>
+      <Lap StartTime="2024-05-04T13:10:00Z">
+        <TotalTimeSeconds>300.4</TotalTimeSeconds><DistanceMeters>2500</DistanceMeters>
+        <MaximumSpeed>13.0</MaximumSpeed><Calories>80</Calories>
+      </Lap>
+      <Creator><Name>Edge 530</Name><UnitId>3312345678</UnitId>
+        <Version><VersionMajor>9</VersionMajor><VersionMinor>20</VersionMinor></Version></Creator>
+    </Activity>
+  </Activities>
+</TrainingCenterDatabase>"#;
+
+        let parsed = parse(xml).unwrap();
+        assert_eq!(parsed.sport, Some(SportType::Ride));
+        assert_eq!(parsed.laps.len(), 2);
+        assert_eq!(parsed.laps[1].duration_seconds, 300);
+        assert_eq!(parsed.totals.distance_meters, Some(7500.0));
+        assert_eq!(parsed.totals.calories, Some(230));
+        assert_eq!(parsed.totals.max_speed, Some(13.0));
+        assert_eq!(parsed.totals.max_heart_rate, Some(150));
+        assert_eq!(parsed.points[0].power, Some(180));
+        assert_eq!(parsed.points[0].speed, Some(8.1));
+
+        let device = parsed.device.unwrap();
+        assert_eq!(device.product.as_deref(), Some("Edge 530"));
+        assert_eq!(device.serial_number.as_deref(), Some("3312345678"));
+        assert_eq!(device.software_version.as_deref(), Some("9.20"));
+    }
+}
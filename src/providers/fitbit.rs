@@ -23,7 +23,7 @@ use serde::Deserialize;
 use chrono::{DateTime, Utc};
 use crate::models::{Activity, Athlete, Stats, PersonalRecord, SportType};
 use crate::oauth2_client::PkceParams;
-use super::{FitnessProvider, AuthData};
+use super::{check_response, FitnessProvider, AuthData};
 use tracing::info;
 
 const FITBIT_API_BASE: &str = "https://api.fitbit.com/1";
@@ -83,7 +83,6 @@ impl FitbitProvider {
     /// * `redirect_uri` - The redirect URI registered with your Fitbit app
     /// * `state` - A unique state parameter for CSRF protection
     /// * `pkce` - PKCE parameters for enhanced security
-    #[allow(dead_code)]
     pub fn get_auth_url_with_pkce(&self, redirect_uri: &str, state: &str, pkce: &PkceParams) -> Result<String> {
         let client_id = self.client_id.as_ref()
             .context("Client ID not configured")?;
@@ -194,7 +193,7 @@ impl FitbitProvider {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
-        let response: FitbitActivitiesResponse = self.client
+        let response = self.client
             .get(format!("{}/user/-/activities/list.json", FITBIT_API_BASE))
             .bearer_auth(token)
             .query(&[
@@ -205,7 +204,8 @@ impl FitbitProvider {
                 ("offset", "0")
             ])
             .send()
-            .await?
+            .await?;
+        let response: FitbitActivitiesResponse = check_response("Fitbit", response).await?
             .json()
             .await?;
         
@@ -232,11 +232,12 @@ impl FitnessProvider for FitbitProvider {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
-        let response: FitbitUser = self.client
+        let response = self.client
             .get(format!("{}/user/-/profile.json", FITBIT_API_BASE))
             .bearer_auth(token)
             .send()
-            .await?
+            .await?;
+        let response: FitbitUser = check_response("Fitbit", response).await?
             .json()
             .await?;
         
@@ -276,11 +277,12 @@ impl FitnessProvider for FitbitProvider {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
-        let response: FitbitActivityDetail = self.client
+        let response = self.client
             .get(format!("{}/user/-/activities/{}.json", FITBIT_API_BASE, id))
             .bearer_auth(token)
             .send()
-            .await?
+            .await?;
+        let response: FitbitActivityDetail = check_response("Fitbit", response).await?
             .json()
             .await?;
         
@@ -292,11 +294,12 @@ impl FitnessProvider for FitbitProvider {
             .context("Not authenticated")?;
         
         // Get lifetime stats from Fitbit
-        let response: FitbitLifetimeStats = self.client
+        let response = self.client
             .get(format!("{}/user/-/activities.json", FITBIT_API_BASE))
             .bearer_auth(token)
             .send()
-            .await?
+            .await?;
+        let response: FitbitLifetimeStats = check_response("Fitbit", response).await?
             .json()
             .await?;
         
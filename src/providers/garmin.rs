@@ -0,0 +1,758 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Garmin Connect provider implementation for fitness data retrieval.
+//!
+//! This module integrates with the Garmin Health and Activity APIs, supporting:
+//! - OAuth2 authentication with PKCE (the only flow Garmin offers)
+//! - Pull requests for activity summaries, activity details and dailies
+//! - Push and ping notification payloads sent by Garmin when a device syncs;
+//!   the `/webhooks/garmin` route acts on pings only
+//!
+//! Garmin has no "list my activities" endpoint: every pull request covers a
+//! window of at most one day of *upload* time, so longer ranges are fetched
+//! one window at a time.
+//!
+//! # API Documentation
+//! - [Garmin Connect Developer Program](https://developer.garmin.com/gc-developer-program/)
+
+use async_trait::async_trait;
+use anyhow::{Result, Context};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use crate::constants::{env_config, limits};
+use crate::models::{Activity, ActivityDetails, Athlete, DeviceInfo, Lap, PersonalRecord, Stats, TrackPoint};
+use crate::oauth2_client::PkceParams;
+use super::file_import::sport_from_name;
+use super::{check_response, FitnessProvider, AuthData};
+use tracing::info;
+
+pub const PROVIDER_NAME: &str = "garmin";
+
+/// Garmin Connect provider supporting OAuth2 with PKCE
+pub struct GarminProvider {
+    client: Client,
+    access_token: Option<String>,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    refresh_token: Option<String>,
+}
+
+impl Default for GarminProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GarminProvider {
+    /// Create a new Garmin provider instance
+    pub fn new() -> Self {
+        Self {
+            client: Client::new(),
+            access_token: None,
+            client_id: None,
+            client_secret: None,
+            refresh_token: None,
+        }
+    }
+
+    /// Get OAuth2 authorization URL with PKCE
+    ///
+    /// # Arguments
+    /// * `redirect_uri` - The redirect URI registered with your Garmin app
+    /// * `state` - A unique state parameter for CSRF protection
+    /// * `pkce` - PKCE parameters; the verifier is needed again for the code exchange
+    pub fn get_auth_url_with_pkce(&self, redirect_uri: &str, state: &str, pkce: &PkceParams) -> Result<String> {
+        let client_id = self.client_id.as_ref()
+            .context("Client ID not configured")?;
+
+        let mut url = url::Url::parse(&env_config::garmin_auth_url())?;
+        url.query_pairs_mut()
+            .append_pair("client_id", client_id)
+            .append_pair("redirect_uri", redirect_uri)
+            .append_pair("response_type", "code")
+            .append_pair("state", state)
+            .append_pair("code_challenge", &pkce.code_challenge)
+            .append_pair("code_challenge_method", &pkce.code_challenge_method);
+
+        Ok(url.to_string())
+    }
+
+    /// Exchange authorization code for access tokens
+    ///
+    /// # Arguments
+    /// * `code` - Authorization code received from Garmin
+    /// * `redirect_uri` - The same redirect URI used in authorization
+    /// * `pkce` - PKCE parameters used in authorization
+    pub async fn exchange_code_with_pkce(&mut self, code: &str, redirect_uri: &str, pkce: &PkceParams) -> Result<(String, String)> {
+        let client_id = self.client_id.as_ref()
+            .context("Client ID not set")?;
+        let client_secret = self.client_secret.as_ref()
+            .context("Client secret not set")?;
+
+        let token = crate::oauth2_client::garmin::exchange_garmin_code_with_pkce(
+            &self.client,
+            client_id,
+            client_secret,
+            code,
+            redirect_uri,
+            pkce
+        ).await?;
+
+        self.access_token = Some(token.access_token.clone());
+        self.refresh_token = token.refresh_token.clone();
+
+        info!("Garmin authentication with PKCE successful");
+
+        // Return tokens for storage
+        Ok((token.access_token, token.refresh_token.unwrap_or_default()))
+    }
+
+    /// Refresh access token using refresh token
+    pub async fn refresh_access_token(&mut self) -> Result<(String, String)> {
+        let refresh_token = self.refresh_token.as_ref()
+            .context("No refresh token available")?;
+
+        let client_id = self.client_id.as_ref()
+            .context("Client ID not set")?;
+        let client_secret = self.client_secret.as_ref()
+            .context("Client secret not set")?;
+
+        let new_token = crate::oauth2_client::garmin::refresh_garmin_token(
+            &self.client,
+            client_id,
+            client_secret,
+            refresh_token
+        ).await?;
+
+        self.access_token = Some(new_token.access_token.clone());
+        self.refresh_token = new_token.refresh_token.clone();
+
+        info!("Garmin token refreshed successfully");
+
+        // Return tokens for storage
+        Ok((new_token.access_token, new_token.refresh_token.unwrap_or_default()))
+    }
+
+    /// Activity summaries uploaded between `start` and `end`
+    pub async fn get_activity_summaries(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<Activity>> {
+        let summaries: Vec<GarminActivitySummary> = self.pull_range("activities", start, end).await?;
+        Ok(summaries.into_iter().map(Activity::from).collect())
+    }
+
+    /// Activities with their samples and laps, uploaded between `start` and `end`
+    pub async fn get_activity_details(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<ActivityDetails>> {
+        let details: Vec<GarminActivityDetail> = self.pull_range("activityDetails", start, end).await?;
+        Ok(details.into_iter().map(ActivityDetails::from).collect())
+    }
+
+    /// Daily wellness summaries uploaded between `start` and `end`
+    pub async fn get_dailies(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<DailySummary>> {
+        let dailies: Vec<GarminDaily> = self.pull_range("dailies", start, end).await?;
+        Ok(dailies.into_iter().map(DailySummary::from).collect())
+    }
+
+    /// Fetch the summaries a ping notification points at
+    ///
+    /// Ping notifications carry only a callback URL per upload; the callback
+    /// returns the same records a push notification would have included.
+    /// Anyone can post a ping, so the user's token is only sent to callbacks
+    /// on the configured Garmin API host.
+    pub async fn fetch_ping(&self, ping: &GarminPing) -> Result<GarminNotification> {
+        let token = self.access_token.as_ref()
+            .context("Not authenticated")?;
+
+        let callback_url = url::Url::parse(&ping.callback_url).context("Invalid Garmin callback URL")?;
+        let api_base = url::Url::parse(&env_config::garmin_api_base()).context("Invalid Garmin API base URL")?;
+        if callback_url.origin() != api_base.origin() {
+            anyhow::bail!("Garmin callback URL {} is not on {}", callback_url, api_base.origin().ascii_serialization());
+        }
+
+        let records: serde_json::Value = self.client
+            .get(callback_url)
+            .bearer_auth(token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut payload = serde_json::Map::new();
+        payload.insert(ping.summary_type.clone(), records);
+        GarminNotification::from_json(&serde_json::to_vec(&payload)?)
+    }
+
+    /// Pull one summary type across a range, one upload window at a time
+    async fn pull_range<T: DeserializeOwned>(&self, summary_type: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<T>> {
+        let mut records = Vec::new();
+        let mut window_start = start;
+        while window_start < end {
+            let window_end = (window_start + Duration::seconds(limits::GARMIN_PULL_WINDOW_SECS)).min(end);
+            records.extend(self.pull_window(summary_type, window_start, window_end).await?);
+            window_start = window_end;
+        }
+        Ok(records)
+    }
+
+    async fn pull_window<T: DeserializeOwned>(&self, summary_type: &str, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<T>> {
+        let token = self.access_token.as_ref()
+            .context("Not authenticated")?;
+
+        let response = self.client
+            .get(format!("{}/{}", env_config::garmin_api_base(), summary_type))
+            .bearer_auth(token)
+            .query(&[
+                ("uploadStartTimeInSeconds", start.timestamp()),
+                ("uploadEndTimeInSeconds", end.timestamp()),
+            ])
+            .send()
+            .await?;
+
+        check_response("Garmin", response).await
+            .with_context(|| format!("Garmin {} request failed", summary_type))?
+            .json()
+            .await
+            .with_context(|| format!("Failed to parse Garmin {} response", summary_type))
+    }
+
+    /// Activities from the most recent upload windows, newest first, stopping once `wanted` are found
+    async fn recent_activities(&self, wanted: Option<usize>) -> Result<Vec<Activity>> {
+        let now = Utc::now();
+        let oldest = now - Duration::days(limits::GARMIN_DEFAULT_LOOKBACK_DAYS);
+        let mut activities = Vec::new();
+        let mut window_end = now;
+        while window_end > oldest {
+            let window_start = (window_end - Duration::seconds(limits::GARMIN_PULL_WINDOW_SECS)).max(oldest);
+            let summaries: Vec<GarminActivitySummary> = self.pull_window("activities", window_start, window_end).await?;
+            activities.extend(summaries.into_iter().map(Activity::from));
+            if wanted.is_some_and(|wanted| activities.len() >= wanted) {
+                break;
+            }
+            window_end = window_start;
+        }
+        activities.sort_by_key(|activity| std::cmp::Reverse(activity.start_date));
+        Ok(activities)
+    }
+}
+
+#[async_trait]
+impl FitnessProvider for GarminProvider {
+    async fn authenticate(&mut self, auth_data: AuthData) -> Result<()> {
+        match auth_data {
+            AuthData::OAuth2 { client_id, client_secret, access_token, refresh_token } => {
+                self.client_id = Some(client_id);
+                self.client_secret = Some(client_secret);
+                self.access_token = access_token;
+                self.refresh_token = refresh_token;
+                Ok(())
+            }
+            _ => Err(anyhow::anyhow!("Garmin requires OAuth2 authentication")),
+        }
+    }
+
+    async fn get_athlete(&self) -> Result<Athlete> {
+        let token = self.access_token.as_ref()
+            .context("Not authenticated")?;
+
+        // Garmin shares no profile details, only a stable user id
+        let response: GarminUserId = self.client
+            .get(format!("{}/user/id", env_config::garmin_api_base()))
+            .bearer_auth(token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        Ok(Athlete {
+            id: response.user_id.clone(),
+            username: response.user_id,
+            firstname: None,
+            lastname: None,
+            profile_picture: None,
+            provider: PROVIDER_NAME.to_string(),
+        })
+    }
+
+    async fn get_activities(&self, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Activity>> {
+        let offset = offset.unwrap_or(0);
+        let activities = self.recent_activities(limit.map(|limit| limit + offset)).await?;
+
+        Ok(activities
+            .into_iter()
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .collect())
+    }
+
+    async fn get_activity(&self, id: &str) -> Result<Activity> {
+        // There is no lookup by id, so search the recent upload windows
+        self.recent_activities(None).await?
+            .into_iter()
+            .find(|activity| activity.id == id)
+            .with_context(|| format!(
+                "Garmin activity {} not found in the last {} days of uploads",
+                id,
+                limits::GARMIN_DEFAULT_LOOKBACK_DAYS
+            ))
+    }
+
+    async fn get_stats(&self) -> Result<Stats> {
+        // Garmin has no lifetime totals, so these cover the recent upload windows
+        let activities = self.recent_activities(None).await?;
+
+        Ok(Stats {
+            total_activities: activities.len() as u64,
+            total_distance: activities.iter().filter_map(|a| a.distance_meters).sum(),
+            total_duration: activities.iter().map(|a| a.duration_seconds).sum(),
+            total_elevation_gain: activities.iter().filter_map(|a| a.elevation_gain).sum(),
+        })
+    }
+
+    async fn get_personal_records(&self) -> Result<Vec<PersonalRecord>> {
+        // Garmin doesn't expose personal records through the Health API
+        Ok(vec![])
+    }
+
+    fn provider_name(&self) -> &'static str {
+        "Garmin"
+    }
+}
+
+/// One day of wellness data from a Garmin device
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DailySummary {
+    /// Local calendar date the summary covers
+    pub calendar_date: NaiveDate,
+    /// Start of the summary period (UTC)
+    pub start_date: DateTime<Utc>,
+    /// Length of the summary period in seconds
+    pub duration_seconds: u64,
+    pub steps: Option<u32>,
+    pub distance_meters: Option<f64>,
+    pub active_calories: Option<u32>,
+    pub bmr_calories: Option<u32>,
+    pub resting_heart_rate: Option<u32>,
+    pub average_heart_rate: Option<u32>,
+    pub max_heart_rate: Option<u32>,
+    pub floors_climbed: Option<u32>,
+    /// Average stress level (0-100)
+    pub average_stress_level: Option<i32>,
+}
+
+/// A ping notification: Garmin has new data, fetch it from the callback URL
+#[derive(Debug, Clone, PartialEq)]
+pub struct GarminPing {
+    /// Summary type, such as `activities` or `dailies`
+    pub summary_type: String,
+    /// Garmin user the data belongs to
+    pub user_id: String,
+    /// Short-lived URL returning the new records
+    pub callback_url: String,
+}
+
+/// Records delivered by a push notification or fetched for a ping
+#[derive(Debug, Clone, Default)]
+pub struct GarminNotification {
+    /// Activities, with samples when the notification carried activity details
+    pub activities: Vec<ActivityDetails>,
+    pub dailies: Vec<DailySummary>,
+    pub pings: Vec<GarminPing>,
+    /// Garmin user ids the records belong to
+    pub user_ids: Vec<String>,
+}
+
+impl GarminNotification {
+    /// Parse the body Garmin POSTs to a push or ping endpoint
+    ///
+    /// Both kinds of notification key their entries by summary type; ping
+    /// entries are told apart by their callback URL. Summary types this
+    /// server doesn't use (sleeps, body composition, ...) are ignored.
+    pub fn from_json(bytes: &[u8]) -> Result<Self> {
+        let payload: HashMap<String, Vec<serde_json::Value>> = serde_json::from_slice(bytes)
+            .context("Invalid Garmin notification payload")?;
+
+        let mut notification = Self::default();
+        let mut details = Vec::new();
+        for (summary_type, entries) in payload {
+            for entry in entries {
+                if let Ok(ping) = serde_json::from_value::<GarminPingEntry>(entry.clone()) {
+                    notification.add_user(&ping.user_id);
+                    notification.pings.push(GarminPing {
+                        summary_type: summary_type.clone(),
+                        user_id: ping.user_id,
+                        callback_url: ping.callback_url,
+                    });
+                    continue;
+                }
+
+                match summary_type.as_str() {
+                    "activities" => {
+                        let summary: GarminActivitySummary = serde_json::from_value(entry)
+                            .context("Invalid Garmin activity summary")?;
+                        notification.add_optional_user(&summary.user_id);
+                        notification.activities.push(GarminActivityDetail::from(summary).into());
+                    }
+                    "activityDetails" => {
+                        let detail: GarminActivityDetail = serde_json::from_value(entry)
+                            .context("Invalid Garmin activity detail")?;
+                        notification.add_optional_user(&detail.summary.user_id);
+                        details.push(ActivityDetails::from(detail));
+                    }
+                    "dailies" => {
+                        let daily: GarminDaily = serde_json::from_value(entry)
+                            .context("Invalid Garmin daily summary")?;
+                        notification.add_optional_user(&daily.user_id);
+                        notification.dailies.push(daily.into());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // A detail supersedes the bare summary of the same activity
+        for detail in details {
+            notification.activities.retain(|existing| existing.activity.id != detail.activity.id);
+            notification.activities.push(detail);
+        }
+        notification.activities.sort_by_key(|details| details.activity.start_date);
+        notification.dailies.sort_by_key(|daily| daily.calendar_date);
+
+        Ok(notification)
+    }
+
+    fn add_user(&mut self, user_id: &str) {
+        if !self.user_ids.iter().any(|known| known == user_id) {
+            self.user_ids.push(user_id.to_string());
+        }
+    }
+
+    fn add_optional_user(&mut self, user_id: &Option<String>) {
+        if let Some(user_id) = user_id {
+            self.add_user(user_id);
+        }
+    }
+}
+
+// Garmin Health API response structures
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminUserId {
+    user_id: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminPingEntry {
+    user_id: String,
+    #[serde(rename = "callbackURL")]
+    callback_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminActivitySummary {
+    summary_id: String,
+    activity_id: Option<u64>,
+    /// Only present in push notifications
+    user_id: Option<String>,
+    activity_name: Option<String>,
+    activity_type: Option<String>,
+    start_time_in_seconds: i64,
+    duration_in_seconds: u64,
+    distance_in_meters: Option<f64>,
+    total_elevation_gain_in_meters: Option<f64>,
+    average_heart_rate_in_beats_per_minute: Option<u32>,
+    max_heart_rate_in_beats_per_minute: Option<u32>,
+    average_speed_in_meters_per_second: Option<f64>,
+    max_speed_in_meters_per_second: Option<f64>,
+    active_kilocalories: Option<u32>,
+    starting_latitude_in_degree: Option<f64>,
+    starting_longitude_in_degree: Option<f64>,
+    device_name: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminActivityDetail {
+    summary: GarminActivitySummary,
+    #[serde(default)]
+    samples: Vec<GarminSample>,
+    #[serde(default)]
+    laps: Vec<GarminLap>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminSample {
+    start_time_in_seconds: i64,
+    latitude_in_degree: Option<f64>,
+    longitude_in_degree: Option<f64>,
+    elevation_in_meters: Option<f64>,
+    heart_rate: Option<u32>,
+    speed_meters_per_second: Option<f64>,
+    total_distance_in_meters: Option<f64>,
+    power_in_watts: Option<f64>,
+    bike_cadence_in_rpm: Option<f64>,
+    /// Steps per minute for both legs
+    steps_per_minute: Option<f64>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminLap {
+    start_time_in_seconds: i64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GarminDaily {
+    user_id: Option<String>,
+    calendar_date: NaiveDate,
+    start_time_in_seconds: i64,
+    duration_in_seconds: u64,
+    steps: Option<u32>,
+    distance_in_meters: Option<f64>,
+    active_kilocalories: Option<u32>,
+    bmr_kilocalories: Option<u32>,
+    resting_heart_rate_in_beats_per_minute: Option<u32>,
+    average_heart_rate_in_beats_per_minute: Option<u32>,
+    max_heart_rate_in_beats_per_minute: Option<u32>,
+    floors_climbed: Option<u32>,
+    average_stress_level: Option<i32>,
+}
+
+fn timestamp(seconds: i64) -> DateTime<Utc> {
+    DateTime::from_timestamp(seconds, 0).unwrap_or_default()
+}
+
+impl From<GarminActivitySummary> for Activity {
+    fn from(garmin: GarminActivitySummary) -> Self {
+        let sport_type = sport_from_name(&garmin.activity_type.unwrap_or_default().replace('_', " "));
+        let start_date = timestamp(garmin.start_time_in_seconds);
+        let name = garmin.activity_name
+            .unwrap_or_else(|| super::file_import::default_name(&sport_type, start_date));
+
+        Activity {
+            // Activity ids match those in Garmin account exports
+            id: garmin.activity_id.map(|id| id.to_string()).unwrap_or(garmin.summary_id),
+            name,
+            sport_type,
+            start_date,
+            duration_seconds: garmin.duration_in_seconds,
+            distance_meters: garmin.distance_in_meters,
+            elevation_gain: garmin.total_elevation_gain_in_meters,
+            average_heart_rate: garmin.average_heart_rate_in_beats_per_minute,
+            max_heart_rate: garmin.max_heart_rate_in_beats_per_minute,
+            average_speed: garmin.average_speed_in_meters_per_second,
+            max_speed: garmin.max_speed_in_meters_per_second,
+            calories: garmin.active_kilocalories,
+            start_latitude: garmin.starting_latitude_in_degree,
+            start_longitude: garmin.starting_longitude_in_degree,
+            city: None,
+            region: None,
+            country: None,
+            trail_name: None,
+            provider: PROVIDER_NAME.to_string(),
+        }
+    }
+}
+
+impl From<GarminActivitySummary> for GarminActivityDetail {
+    fn from(summary: GarminActivitySummary) -> Self {
+        Self {
+            summary,
+            samples: Vec::new(),
+            laps: Vec::new(),
+        }
+    }
+}
+
+impl From<GarminActivityDetail> for ActivityDetails {
+    fn from(detail: GarminActivityDetail) -> Self {
+        let device = detail.summary.device_name.clone().map(|product| DeviceInfo {
+            manufacturer: Some("Garmin".to_string()),
+            product: Some(product),
+            ..DeviceInfo::default()
+        });
+        let activity = Activity::from(detail.summary);
+
+        let streams: Vec<TrackPoint> = detail.samples
+            .into_iter()
+            .map(|sample| TrackPoint {
+                time: timestamp(sample.start_time_in_seconds),
+                latitude: sample.latitude_in_degree,
+                longitude: sample.longitude_in_degree,
+                altitude: sample.elevation_in_meters,
+                distance_meters: sample.total_distance_in_meters,
+                heart_rate: sample.heart_rate,
+                cadence: sample.bike_cadence_in_rpm
+                    .or(sample.steps_per_minute.map(|steps| steps / 2.0))
+                    .map(|cadence| cadence.round() as u32),
+                power: sample.power_in_watts.map(|watts| watts.round() as u32),
+                speed: sample.speed_meters_per_second,
+            })
+            .collect();
+
+        // Garmin only reports where laps start; the rest comes from the samples
+        let activity_end = activity.start_date + Duration::seconds(activity.duration_seconds as i64);
+        let lap_starts: Vec<DateTime<Utc>> = detail.laps.iter().map(|lap| timestamp(lap.start_time_in_seconds)).collect();
+        let laps = lap_starts
+            .iter()
+            .enumerate()
+            .map(|(index, &start)| {
+                let end = lap_starts.get(index + 1).copied().unwrap_or(activity_end);
+                lap_from_samples(&streams, start, end)
+            })
+            .collect();
+
+        ActivityDetails {
+            activity,
+            streams,
+            laps,
+            device,
+        }
+    }
+}
+
+fn lap_from_samples(streams: &[TrackPoint], start: DateTime<Utc>, end: DateTime<Utc>) -> Lap {
+    let samples: Vec<&TrackPoint> = streams
+        .iter()
+        .filter(|point| point.time >= start && point.time < end)
+        .collect();
+    let duration_seconds = (end - start).num_seconds().max(0) as u64;
+
+    // Distance runs up to the first sample of the next lap when there is one
+    let first_distance = samples.first().and_then(|point| point.distance_meters);
+    let last_distance = streams
+        .iter()
+        .find(|point| point.time >= end)
+        .or(samples.last().copied())
+        .and_then(|point| point.distance_meters);
+    let distance_meters = first_distance.zip(last_distance).map(|(first, last)| last - first);
+
+    let heart_rates: Vec<u32> = samples.iter().filter_map(|point| point.heart_rate).collect();
+    let average_heart_rate = (!heart_rates.is_empty())
+        .then(|| (heart_rates.iter().sum::<u32>() as f64 / heart_rates.len() as f64).round() as u32);
+
+    Lap {
+        start_date: start,
+        duration_seconds,
+        distance_meters,
+        average_heart_rate,
+        max_heart_rate: heart_rates.iter().max().copied(),
+        average_speed: distance_meters
+            .filter(|_| duration_seconds > 0)
+            .map(|distance| distance / duration_seconds as f64),
+        calories: None,
+    }
+}
+
+impl From<GarminDaily> for DailySummary {
+    fn from(garmin: GarminDaily) -> Self {
+        DailySummary {
+            calendar_date: garmin.calendar_date,
+            start_date: timestamp(garmin.start_time_in_seconds),
+            duration_seconds: garmin.duration_in_seconds,
+            steps: garmin.steps,
+            distance_meters: garmin.distance_in_meters,
+            active_calories: garmin.active_kilocalories,
+            bmr_calories: garmin.bmr_kilocalories,
+            resting_heart_rate: garmin.resting_heart_rate_in_beats_per_minute,
+            average_heart_rate: garmin.average_heart_rate_in_beats_per_minute,
+            max_heart_rate: garmin.max_heart_rate_in_beats_per_minute,
+            floors_climbed: garmin.floors_climbed,
+            // Garmin reports -1 when there wasn't enough data
+            average_stress_level: garmin.average_stress_level.filter(|level| *level >= 0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::SportType;
+
+    #[test]
+    fn test_activity_detail_conversion() {
+        let detail: GarminActivityDetail = serde_json::from_value(serde_json::json!({
+            "summaryId": "5001968355-detail",
+            "summary": {
+                "summaryId": "5001968355",
+                "activityId": 5001968355u64,
+                "activityType": "TRAIL_RUNNING",
+                "startTimeInSeconds": 1_700_000_000,
+                "durationInSeconds": 20,
+                "distanceInMeters": 80.0,
+                "deviceName": "forerunner965"
+            },
+            "samples": [
+                {"startTimeInSeconds": 1_700_000_000, "heartRate": 140, "totalDistanceInMeters": 0.0, "stepsPerMinute": 170.0},
+                {"startTimeInSeconds": 1_700_000_010, "heartRate": 150, "totalDistanceInMeters": 40.0},
+                {"startTimeInSeconds": 1_700_000_020, "heartRate": 160, "totalDistanceInMeters": 80.0}
+            ],
+            "laps": [{"startTimeInSeconds": 1_700_000_000}, {"startTimeInSeconds": 1_700_000_010}]
+        }))
+        .unwrap();
+
+        let details = ActivityDetails::from(detail);
+        assert_eq!(details.activity.id, "5001968355");
+        assert_eq!(details.activity.provider, "garmin");
+        assert_eq!(details.activity.sport_type, SportType::TrailRunning);
+        assert_eq!(details.streams.len(), 3);
+        assert_eq!(details.streams[0].cadence, Some(85));
+        assert_eq!(details.device.unwrap().product.as_deref(), Some("forerunner965"));
+
+        assert_eq!(details.laps.len(), 2);
+        assert_eq!(details.laps[0].duration_seconds, 10);
+        assert_eq!(details.laps[0].distance_meters, Some(40.0));
+        assert_eq!(details.laps[0].average_heart_rate, Some(140));
+        assert_eq!(details.laps[1].distance_meters, Some(40.0));
+        assert_eq!(details.laps[1].max_heart_rate, Some(150));
+    }
+
+    #[test]
+    fn test_push_and_ping_notifications() {
+        let push = serde_json::json!({
+            "activities": [
+                {"userId": "garmin-user", "summaryId": "1", "activityId": 1, "activityType": "RUNNING",
+                 "startTimeInSeconds": 1_700_000_000, "durationInSeconds": 600}
+            ],
+            "activityDetails": [
+                {"summary": {"userId": "garmin-user", "summaryId": "1", "activityId": 1, "activityType": "RUNNING",
+                             "startTimeInSeconds": 1_700_000_000, "durationInSeconds": 600},
+                 "samples": [{"startTimeInSeconds": 1_700_000_000, "heartRate": 120}]}
+            ],
+            "dailies": [
+                {"userId": "garmin-user", "calendarDate": "2023-11-14", "startTimeInSeconds": 1_699_920_000,
+                 "durationInSeconds": 86400, "steps": 12000, "averageStressLevel": -1}
+            ],
+            "sleeps": [{"userId": "garmin-user", "summaryId": "x"}]
+        });
+        let notification = GarminNotification::from_json(&serde_json::to_vec(&push).unwrap()).unwrap();
+        assert_eq!(notification.activities.len(), 1);
+        assert_eq!(notification.activities[0].streams.len(), 1);
+        assert_eq!(notification.dailies[0].steps, Some(12000));
+        assert_eq!(notification.dailies[0].average_stress_level, None);
+        assert_eq!(notification.user_ids, vec!["garmin-user".to_string()]);
+        assert!(notification.pings.is_empty());
+
+        let ping = serde_json::json!({
+            "activities": [{"userId": "garmin-user", "callbackURL": "https://apis.garmin.com/callback?token=abc"}]
+        });
+        let notification = GarminNotification::from_json(&serde_json::to_vec(&ping).unwrap()).unwrap();
+        assert!(notification.activities.is_empty());
+        assert_eq!(notification.pings, vec![GarminPing {
+            summary_type: "activities".to_string(),
+            user_id: "garmin-user".to_string(),
+            callback_url: "https://apis.garmin.com/callback?token=abc".to_string(),
+        }]);
+    }
+}
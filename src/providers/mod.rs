@@ -7,9 +7,15 @@
 use async_trait::async_trait;
 use anyhow::Result;
 use crate::models::{Activity, Athlete, Stats, PersonalRecord};
+use std::path::PathBuf;
 
 pub mod strava;
 pub mod fitbit;
+pub mod garmin;
+pub mod file_import;
+pub mod cache;
+pub mod token_refresh;
+pub mod quota;
 
 
 #[async_trait]
@@ -42,12 +48,59 @@ pub enum AuthData {
     },
     #[allow(dead_code)]
     ApiKey(String),
+    /// Local directory of activity files, for providers without a remote API
+    Directory(PathBuf),
+}
+
+/// Provider failures that callers can act on
+#[derive(Debug, thiserror::Error)]
+pub enum ProviderError {
+    /// The provider rejected the access token, which a token refresh may fix
+    #[error("{provider} API rejected the access token: {message}")]
+    Unauthorized { provider: &'static str, message: String },
+    /// The provider's shared API quota is used up for now
+    #[error("{provider} API rate limit reached; retry after {retry_after_secs}s")]
+    RateLimited { provider: &'static str, retry_after_secs: u64 },
+}
+
+/// Whether an error means the provider rejected the access token
+pub fn is_unauthorized(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        matches!(cause.downcast_ref::<ProviderError>(), Some(ProviderError::Unauthorized { .. }))
+            || cause.downcast_ref::<reqwest::Error>().and_then(reqwest::Error::status)
+                == Some(reqwest::StatusCode::UNAUTHORIZED)
+    })
+}
+
+/// The rate limit error behind an error, if the provider's quota is used up
+pub fn rate_limited(error: &anyhow::Error) -> Option<&ProviderError> {
+    error.chain().find_map(|cause| match cause.downcast_ref::<ProviderError>() {
+        Some(rate_limited @ ProviderError::RateLimited { .. }) => Some(rate_limited),
+        _ => None,
+    })
+}
+
+/// Turn an unsuccessful API response into an error, reporting 401s as
+/// [`ProviderError::Unauthorized`]
+pub(crate) async fn check_response(provider: &'static str, response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let message = response.text().await.unwrap_or_else(|_| "Unable to read error response".to_string());
+    if status == reqwest::StatusCode::UNAUTHORIZED {
+        return Err(ProviderError::Unauthorized { provider, message }.into());
+    }
+    Err(anyhow::anyhow!("{} API returned error: {} - {}", provider, status, message))
 }
 
 pub fn create_provider(provider_type: &str) -> Result<Box<dyn FitnessProvider>> {
     match provider_type.to_lowercase().as_str() {
         "strava" => Ok(Box::new(strava::StravaProvider::new())),
         "fitbit" => Ok(Box::new(fitbit::FitbitProvider::new())),
-        _ => Err(anyhow::anyhow!("Unknown provider: {}. Currently supported: strava, fitbit", provider_type)),
+        garmin::PROVIDER_NAME => Ok(Box::new(garmin::GarminProvider::new())),
+        file_import::PROVIDER_NAME => Ok(Box::new(file_import::FileImportProvider::new())),
+        _ => Err(anyhow::anyhow!("Unknown provider: {}. Currently supported: strava, fitbit, garmin, file", provider_type)),
     }
 }
\ No newline at end of file
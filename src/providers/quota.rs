@@ -0,0 +1,278 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Upstream API quota tracking
+//!
+//! Strava limits each application, not each athlete, to a number of requests
+//! per 15 minutes and per day, and reports the limits and current usage in
+//! the `X-RateLimit-Limit` and `X-RateLimit-Usage` headers of every response.
+//! All users of the server share that quota, so it is tracked process-wide.
+//! Near the limit, requests wait for the next 15-minute window if it opens
+//! soon and are shed otherwise, leaving a small reserve unused.
+
+use super::ProviderError;
+use crate::constants::limits::{STRAVA_QUOTA_MAX_WAIT_SECS, STRAVA_QUOTA_RESERVE_PERCENT, STRAVA_SHORT_WINDOW_SECS};
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use serde::Serialize;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// The Strava quota, shared by every user and server in the process
+pub static STRAVA_QUOTA: LazyLock<ApiQuota> = LazyLock::new(|| ApiQuota::new("Strava"));
+
+/// The shared quota of a provider, if it has one
+pub fn for_provider(provider_name: &str) -> Option<&'static ApiQuota> {
+    match provider_name.to_lowercase().as_str() {
+        "strava" => Some(&STRAVA_QUOTA),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct QuotaState {
+    short_limit: u32,
+    short_usage: u32,
+    daily_limit: u32,
+    daily_usage: u32,
+    /// When usage was last reported; no limits are enforced before the first report
+    updated_at: Option<DateTime<Utc>>,
+}
+
+impl QuotaState {
+    /// Start fresh counts for any window that has rolled over since the last report
+    fn roll_over(&mut self, now: DateTime<Utc>) {
+        let Some(updated_at) = self.updated_at else {
+            return;
+        };
+        if window_start(updated_at, STRAVA_SHORT_WINDOW_SECS) < window_start(now, STRAVA_SHORT_WINDOW_SECS) {
+            self.short_usage = 0;
+        }
+        if window_start(updated_at, SECONDS_PER_DAY) < window_start(now, SECONDS_PER_DAY) {
+            self.daily_usage = 0;
+        }
+        self.updated_at = Some(now);
+    }
+}
+
+/// Remaining upstream quota, as reported by health checks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct QuotaStatus {
+    pub short_limit: u32,
+    pub short_remaining: u32,
+    pub short_resets_in_secs: u64,
+    pub daily_limit: u32,
+    pub daily_remaining: u32,
+    pub daily_resets_in_secs: u64,
+    /// Whether requests are currently being queued or shed
+    pub constrained: bool,
+}
+
+/// Request usage against a provider's 15-minute and daily limits
+#[derive(Debug)]
+pub struct ApiQuota {
+    provider: &'static str,
+    state: Mutex<QuotaState>,
+}
+
+impl ApiQuota {
+    pub fn new(provider: &'static str) -> Self {
+        Self {
+            provider,
+            state: Mutex::new(QuotaState::default()),
+        }
+    }
+
+    /// Record the limits and usage reported in a response's headers
+    pub fn record(&self, headers: &HeaderMap, now: DateTime<Utc>) {
+        let (Some(limits), Some(usage)) = (
+            parse_pair(headers, "x-ratelimit-limit"),
+            parse_pair(headers, "x-ratelimit-usage"),
+        ) else {
+            return;
+        };
+
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        *state = QuotaState {
+            short_limit: limits.0,
+            short_usage: usage.0,
+            daily_limit: limits.1,
+            daily_usage: usage.1,
+            updated_at: Some(now),
+        };
+    }
+
+    /// Count a request against the quota. Returns how long to wait first when
+    /// the 15-minute window is used up but opens again soon, or a
+    /// [`ProviderError::RateLimited`] when the request should be shed.
+    pub fn admit(&self, now: DateTime<Utc>) -> Result<Option<Duration>, ProviderError> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.roll_over(now);
+
+        if exhausted(state.daily_usage, state.daily_limit) {
+            return Err(self.rate_limited(secs_until_reset(now, SECONDS_PER_DAY)));
+        }
+        if exhausted(state.short_usage, state.short_limit) {
+            let wait = secs_until_reset(now, STRAVA_SHORT_WINDOW_SECS);
+            if wait > STRAVA_QUOTA_MAX_WAIT_SECS {
+                return Err(self.rate_limited(wait));
+            }
+            return Ok(Some(Duration::from_secs(wait)));
+        }
+
+        // Count the request now so concurrent requests see it before Strava reports it
+        state.short_usage += 1;
+        state.daily_usage += 1;
+        Ok(None)
+    }
+
+    /// Seconds until the quota that is currently used up opens again
+    pub fn retry_after_secs(&self, now: DateTime<Utc>) -> u64 {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.roll_over(now);
+        if exhausted(state.daily_usage, state.daily_limit) {
+            secs_until_reset(now, SECONDS_PER_DAY)
+        } else {
+            secs_until_reset(now, STRAVA_SHORT_WINDOW_SECS)
+        }
+    }
+
+    /// Whether requests would currently be queued or shed
+    pub fn is_constrained(&self, now: DateTime<Utc>) -> bool {
+        self.status(now).is_some_and(|status| status.constrained)
+    }
+
+    /// Remaining quota, once the provider has reported its usage
+    pub fn status(&self, now: DateTime<Utc>) -> Option<QuotaStatus> {
+        let mut state = self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        state.updated_at?;
+        state.roll_over(now);
+        Some(QuotaStatus {
+            short_limit: state.short_limit,
+            short_remaining: state.short_limit.saturating_sub(state.short_usage),
+            short_resets_in_secs: secs_until_reset(now, STRAVA_SHORT_WINDOW_SECS),
+            daily_limit: state.daily_limit,
+            daily_remaining: state.daily_limit.saturating_sub(state.daily_usage),
+            daily_resets_in_secs: secs_until_reset(now, SECONDS_PER_DAY),
+            constrained: exhausted(state.short_usage, state.short_limit)
+                || exhausted(state.daily_usage, state.daily_limit),
+        })
+    }
+
+    fn rate_limited(&self, retry_after_secs: u64) -> ProviderError {
+        ProviderError::RateLimited {
+            provider: self.provider,
+            retry_after_secs,
+        }
+    }
+}
+
+/// Whether usage has reached the limit less the reserve. A zero limit means none was reported.
+fn exhausted(usage: u32, limit: u32) -> bool {
+    // Widened so a huge reported limit can't overflow
+    let reserve = u64::from(limit) * u64::from(STRAVA_QUOTA_RESERVE_PERCENT) / 100;
+    limit > 0 && u64::from(usage) >= u64::from(limit) - reserve
+}
+
+/// Strava windows start on the quarter hour and at midnight UTC
+fn window_start(time: DateTime<Utc>, window_secs: i64) -> i64 {
+    time.timestamp() - time.timestamp().rem_euclid(window_secs)
+}
+
+fn secs_until_reset(now: DateTime<Utc>, window_secs: i64) -> u64 {
+    (window_start(now, window_secs) + window_secs - now.timestamp()) as u64
+}
+
+/// Parse a `short,daily` header value
+fn parse_pair(headers: &HeaderMap, name: &str) -> Option<(u32, u32)> {
+    let (short, daily) = headers.get(name)?.to_str().ok()?.split_once(',')?;
+    Some((short.trim().parse().ok()?, daily.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn headers(limit: &str, usage: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-ratelimit-limit", limit.parse().unwrap());
+        headers.insert("x-ratelimit-usage", usage.parse().unwrap());
+        headers
+    }
+
+    fn at(hour: u32, min: u32, sec: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 3, 14, hour, min, sec).unwrap()
+    }
+
+    #[test]
+    fn test_unreported_quota_is_not_enforced() {
+        let quota = ApiQuota::new("Strava");
+        for _ in 0..1000 {
+            assert_eq!(quota.admit(at(10, 0, 0)).unwrap(), None);
+        }
+        assert!(quota.status(at(10, 0, 0)).is_none());
+    }
+
+    #[test]
+    fn test_huge_reported_limit_does_not_overflow() {
+        assert!(!exhausted(1000, u32::MAX));
+        assert!(exhausted(u32::MAX, u32::MAX));
+
+        let quota = ApiQuota::new("Strava");
+        quota.record(&headers("4294967295,4294967295", "10,10"), at(10, 5, 0));
+        assert_eq!(quota.admit(at(10, 5, 1)).unwrap(), None);
+        assert!(!quota.is_constrained(at(10, 5, 1)));
+    }
+
+    #[test]
+    fn test_headers_are_recorded_and_requests_counted() {
+        let quota = ApiQuota::new("Strava");
+        quota.record(&headers("600,30000", "314,27536"), at(10, 5, 0));
+        assert_eq!(quota.admit(at(10, 5, 1)).unwrap(), None);
+
+        let status = quota.status(at(10, 5, 1)).unwrap();
+        assert_eq!(status.short_remaining, 600 - 315);
+        assert_eq!(status.daily_remaining, 30000 - 27537);
+        assert_eq!(status.short_resets_in_secs, 9 * 60 + 59);
+        assert!(!status.constrained);
+    }
+
+    #[test]
+    fn test_waits_for_window_opening_soon() {
+        let quota = ApiQuota::new("Strava");
+        // 570 of 600 leaves only the 5% reserve
+        quota.record(&headers("600,30000", "570,1000"), at(10, 14, 40));
+        assert_eq!(quota.admit(at(10, 14, 40)).unwrap(), Some(Duration::from_secs(20)));
+        assert!(quota.is_constrained(at(10, 14, 40)));
+
+        // The next window starts with a fresh count
+        assert_eq!(quota.admit(at(10, 15, 0)).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sheds_when_window_opens_late() {
+        let quota = ApiQuota::new("Strava");
+        quota.record(&headers("600,30000", "600,1000"), at(10, 1, 0));
+        match quota.admit(at(10, 1, 0)) {
+            Err(ProviderError::RateLimited { retry_after_secs, .. }) => assert_eq!(retry_after_secs, 14 * 60),
+            other => panic!("expected the request to be shed, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_daily_limit_sheds_until_midnight() {
+        let quota = ApiQuota::new("Strava");
+        quota.record(&headers("600,30000", "10,29999"), at(23, 0, 0));
+        match quota.admit(at(23, 0, 0)) {
+            Err(ProviderError::RateLimited { retry_after_secs, .. }) => assert_eq!(retry_after_secs, 3600),
+            other => panic!("expected the request to be shed, got {:?}", other),
+        }
+        assert_eq!(quota.retry_after_secs(at(23, 0, 0)), 3600);
+    }
+}
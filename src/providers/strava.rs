@@ -13,8 +13,9 @@ use crate::models::{Activity, Athlete, Stats, PersonalRecord, SportType};
 use crate::config::FitnessConfig;
 use crate::oauth2_client::PkceParams;
 use crate::constants::env_config;
-use super::{FitnessProvider, AuthData};
-use tracing::{info, error};
+use super::{check_response, rate_limited, FitnessProvider, AuthData, ProviderError};
+use super::quota::STRAVA_QUOTA;
+use tracing::{info, error, warn};
 
 pub struct StravaProvider {
     client: Client,
@@ -52,7 +53,6 @@ impl StravaProvider {
     }
 
     /// Get authorization URL with PKCE support for enhanced security
-    #[allow(dead_code)]
     pub fn get_auth_url_with_pkce(&self, redirect_uri: &str, state: &str, pkce: &PkceParams) -> Result<String> {
         let client_id = self.client_id.as_ref()
             .context("Client ID not configured")?;
@@ -172,11 +172,11 @@ impl FitnessProvider for StravaProvider {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
-        let response: StravaAthlete = self.client
+        let request = self.client
             .get(format!("{}/athlete", env_config::strava_api_base()))
-            .bearer_auth(token)
-            .send()
-            .await?
+            .bearer_auth(token);
+        let response = self.send(request).await?;
+        let response: StravaAthlete = check_response("Strava", response).await?
             .json()
             .await?;
         
@@ -205,33 +205,24 @@ impl FitnessProvider for StravaProvider {
         let url = format!("{}/athlete/activities", env_config::strava_api_base());
         info!("Fetching activities from: {} with query: {:?}", url, query);
         
-        let response = self.client
+        let request = self.client
             .get(&url)
             .bearer_auth(token)
-            .query(&query)
-            .send()
-            .await
+            .query(&query);
+        let response = self.send(request).await
             .context("Failed to send request to Strava API")?;
         
         let status = response.status();
         info!("Strava API response status: {}", status);
         
         if !status.is_success() {
-            let error_text = response.text().await.unwrap_or_else(|_| "Unable to read error response".to_string());
-            error!("Strava API error response: {} - {}", status, error_text);
-            
-            // Check if it's an authentication error and we have a refresh token
-            if status == 401 && self.refresh_token.is_some() {
-                info!("Access token expired, attempting to refresh...");
-                // Note: This would require mutable self to refresh the token
-                return Err(anyhow::anyhow!("Access token expired. Strava API error: {} - {}", status, error_text));
-            }
-            
-            return Err(anyhow::anyhow!("Strava API returned error: {} - {}", status, error_text));
+            error!("Strava API error response: {}", status);
         }
         
         // Get response text first for debugging
-        let response_text = response.text().await
+        let response_text = check_response("Strava", response).await?
+            .text()
+            .await
             .context("Failed to read response body")?;
         
         info!("Strava API response length: {} bytes", response_text.len());
@@ -256,11 +247,11 @@ impl FitnessProvider for StravaProvider {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
-        let response: StravaActivity = self.client
+        let request = self.client
             .get(format!("{}/activities/{}", env_config::strava_api_base(), id))
-            .bearer_auth(token)
-            .send()
-            .await?
+            .bearer_auth(token);
+        let response = self.send(request).await?;
+        let response: StravaActivity = check_response("Strava", response).await?
             .json()
             .await?;
         
@@ -269,8 +260,11 @@ impl FitnessProvider for StravaProvider {
 
     async fn get_stats(&self) -> Result<Stats> {
         // Try Strava's athlete stats endpoint first
-        if let Ok(strava_stats) = self.get_strava_athlete_stats().await {
-            return Ok(strava_stats);
+        match self.get_strava_athlete_stats().await {
+            Ok(strava_stats) => return Ok(strava_stats),
+            // Paging through activities would only spend more of the quota
+            Err(e) if rate_limited(&e).is_some() => return Err(e),
+            Err(_) => {}
         }
         
         // Fallback: Calculate from recent activities (limited to avoid rate limits)
@@ -307,26 +301,46 @@ impl FitnessProvider for StravaProvider {
 }
 
 impl StravaProvider {
+    /// Send an API request within the shared Strava quota, recording the usage
+    /// Strava reports. Requests wait briefly for the next 15-minute window or are
+    /// shed with [`ProviderError::RateLimited`] when the quota is used up.
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        while let Some(wait) = STRAVA_QUOTA.admit(Utc::now())? {
+            warn!("Strava rate limit nearly reached, waiting {}s for the next window", wait.as_secs());
+            tokio::time::sleep(wait).await;
+        }
+
+        let response = request.send().await?;
+        STRAVA_QUOTA.record(response.headers(), Utc::now());
+        if response.status() == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Err(ProviderError::RateLimited {
+                provider: "Strava",
+                retry_after_secs: STRAVA_QUOTA.retry_after_secs(Utc::now()),
+            }.into());
+        }
+        Ok(response)
+    }
+
     // Try to get stats from Strava's athlete stats endpoint
     async fn get_strava_athlete_stats(&self) -> Result<Stats> {
         let token = self.access_token.as_ref()
             .context("Not authenticated")?;
         
         // Get athlete ID first
-        let athlete: StravaAthlete = self.client
+        let request = self.client
             .get(format!("{}/athlete", env_config::strava_api_base()))
-            .bearer_auth(token)
-            .send()
-            .await?
+            .bearer_auth(token);
+        let athlete = self.send(request).await?;
+        let athlete: StravaAthlete = check_response("Strava", athlete).await?
             .json()
             .await?;
         
         // Get athlete stats
-        let response: StravaAthleteStats = self.client
+        let request = self.client
             .get(format!("{}/athletes/{}/stats", env_config::strava_api_base(), athlete.id))
-            .bearer_auth(token)
-            .send()
-            .await?
+            .bearer_auth(token);
+        let response = self.send(request).await?;
+        let response: StravaAthleteStats = check_response("Strava", response).await?
             .json()
             .await?;
         
@@ -0,0 +1,224 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Provider access token refresh
+//!
+//! Stored tokens are refreshed shortly before they expire, and again when a
+//! provider rejects one anyway. Rotated tokens are written back to the
+//! database. Refreshes for the same user and provider run one at a time:
+//! providers like Strava revoke a refresh token once it has been used, so two
+//! concurrent refreshes would leave one request holding a dead token.
+
+use super::{create_provider, is_unauthorized, AuthData, FitnessProvider};
+use crate::config::environment::OAuthConfig;
+use crate::database::Database;
+use crate::models::{Activity, Athlete, DecryptedToken, PersonalRecord, Stats};
+use crate::oauth2_client;
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex as StdMutex};
+use tokio::sync::{Mutex, RwLock};
+use tracing::info;
+use uuid::Uuid;
+
+type RefreshLocks = StdMutex<HashMap<(Uuid, String), Arc<Mutex<()>>>>;
+
+/// One lock per user and provider, shared by every server in the process
+static REFRESH_LOCKS: LazyLock<RefreshLocks> = LazyLock::new(Default::default);
+
+fn refresh_lock(user_id: Uuid, provider: &str) -> Arc<Mutex<()>> {
+    let mut locks = REFRESH_LOCKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    locks.entry((user_id, provider.to_string())).or_default().clone()
+}
+
+/// Get the user's stored token for a provider, refreshing it first if it expires soon
+pub async fn valid_token(database: &Database, oauth_config: &OAuthConfig, user_id: Uuid, provider: &str) -> Result<DecryptedToken> {
+    let token = stored_token(database, user_id, provider).await?;
+    if !token.will_expire_soon() {
+        return Ok(token);
+    }
+
+    info!("{} token for user {} expires at {}, refreshing", provider, user_id, token.expires_at);
+    refresh_token(database, oauth_config, user_id, provider, &token.access_token).await
+}
+
+/// Replace a stale access token with a refreshed one and store it
+///
+/// If another request already replaced `stale_access_token` while this one
+/// waited for the lock, the stored token is returned without refreshing again.
+pub async fn refresh_token(
+    database: &Database,
+    oauth_config: &OAuthConfig,
+    user_id: Uuid,
+    provider: &str,
+    stale_access_token: &str,
+) -> Result<DecryptedToken> {
+    let lock = refresh_lock(user_id, provider);
+    let _guard = lock.lock().await;
+
+    let token = stored_token(database, user_id, provider).await?;
+    if token.access_token != stale_access_token {
+        return Ok(token);
+    }
+
+    let refreshed = oauth2_client::refresh_provider_token(&reqwest::Client::new(), oauth_config, provider, &token.refresh_token).await?;
+    let token = DecryptedToken {
+        access_token: refreshed.access_token,
+        refresh_token: refreshed.refresh_token.unwrap_or(token.refresh_token),
+        expires_at: refreshed.expires_at.unwrap_or_else(chrono::Utc::now),
+        scope: refreshed.scope.unwrap_or(token.scope),
+    };
+    database
+        .update_provider_token(
+            user_id,
+            provider,
+            &token.access_token,
+            &token.refresh_token,
+            token.expires_at,
+            token.scope.clone(),
+        )
+        .await?;
+
+    info!("Refreshed {} token for user {}", provider, user_id);
+    Ok(token)
+}
+
+async fn stored_token(database: &Database, user_id: Uuid, provider: &str) -> Result<DecryptedToken> {
+    database
+        .get_provider_token(user_id, provider)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("No valid token found for provider {}", provider))
+}
+
+async fn authenticate(
+    provider: &mut dyn FitnessProvider,
+    oauth_config: &OAuthConfig,
+    provider_name: &str,
+    token: &DecryptedToken,
+) -> Result<()> {
+    let (client_id, client_secret) = oauth_config
+        .credentials(provider_name)
+        .ok_or_else(|| anyhow::anyhow!("{} OAuth is not configured: no client ID and secret to authenticate with", provider_name))?;
+    provider
+        .authenticate(AuthData::OAuth2 {
+            client_id,
+            client_secret,
+            access_token: Some(token.access_token.clone()),
+            refresh_token: Some(token.refresh_token.clone()),
+        })
+        .await
+}
+
+struct Session {
+    provider: Box<dyn FitnessProvider>,
+    access_token: String,
+}
+
+/// A user's provider that refreshes its token and retries once when the
+/// provider rejects the current one
+pub struct RefreshingProvider {
+    user_id: Uuid,
+    provider_name: String,
+    display_name: &'static str,
+    database: Arc<Database>,
+    oauth_config: Arc<OAuthConfig>,
+    session: RwLock<Session>,
+}
+
+impl RefreshingProvider {
+    /// Authenticate a provider with the user's stored token, refreshed first if it expires soon
+    pub async fn connect(
+        user_id: Uuid,
+        provider_name: &str,
+        database: Arc<Database>,
+        oauth_config: Arc<OAuthConfig>,
+    ) -> Result<Self> {
+        let token = valid_token(&database, &oauth_config, user_id, provider_name).await?;
+        let mut provider = create_provider(provider_name)?;
+        authenticate(provider.as_mut(), &oauth_config, provider_name, &token).await?;
+
+        Ok(Self {
+            user_id,
+            provider_name: provider_name.to_string(),
+            display_name: provider.provider_name(),
+            database,
+            oauth_config,
+            session: RwLock::new(Session {
+                provider,
+                access_token: token.access_token,
+            }),
+        })
+    }
+
+    /// The access token the provider is currently authenticated with
+    pub async fn access_token(&self) -> String {
+        self.session.read().await.access_token.clone()
+    }
+
+    /// Refresh the rejected token and authenticate the provider with its replacement
+    async fn reauthenticate(&self) -> Result<()> {
+        let rejected = self.access_token().await;
+        let token = refresh_token(&self.database, &self.oauth_config, self.user_id, &self.provider_name, &rejected).await?;
+
+        let mut session = self.session.write().await;
+        authenticate(session.provider.as_mut(), &self.oauth_config, &self.provider_name, &token).await?;
+        session.access_token = token.access_token;
+        Ok(())
+    }
+}
+
+/// Run a provider call, refreshing the token and retrying once if it was rejected
+macro_rules! with_refresh {
+    ($self:ident, |$provider:ident| $call:expr) => {{
+        let result = {
+            let session = $self.session.read().await;
+            let $provider = &session.provider;
+            $call
+        };
+        match result {
+            Err(e) if is_unauthorized(&e) => {
+                info!("{} rejected the token for user {}, refreshing", $self.provider_name, $self.user_id);
+                $self.reauthenticate().await?;
+                let session = $self.session.read().await;
+                let $provider = &session.provider;
+                $call
+            }
+            result => result,
+        }
+    }};
+}
+
+#[async_trait]
+impl FitnessProvider for RefreshingProvider {
+    async fn authenticate(&mut self, auth_data: AuthData) -> Result<()> {
+        self.session.get_mut().provider.authenticate(auth_data).await
+    }
+
+    async fn get_athlete(&self) -> Result<Athlete> {
+        with_refresh!(self, |provider| provider.get_athlete().await)
+    }
+
+    async fn get_activities(&self, limit: Option<usize>, offset: Option<usize>) -> Result<Vec<Activity>> {
+        with_refresh!(self, |provider| provider.get_activities(limit, offset).await)
+    }
+
+    async fn get_activity(&self, id: &str) -> Result<Activity> {
+        with_refresh!(self, |provider| provider.get_activity(id).await)
+    }
+
+    async fn get_stats(&self) -> Result<Stats> {
+        with_refresh!(self, |provider| provider.get_stats().await)
+    }
+
+    async fn get_personal_records(&self) -> Result<Vec<PersonalRecord>> {
+        with_refresh!(self, |provider| provider.get_personal_records().await)
+    }
+
+    fn provider_name(&self) -> &'static str {
+        self.display_name
+    }
+}
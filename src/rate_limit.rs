@@ -0,0 +1,278 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Rate Limiting
+//!
+//! Fixed-window request limits for the MCP and HTTP servers, counted
+//! separately per client IP, per user and per API key. Tools that fan out to
+//! providers or external services draw from a smaller expensive bucket, so a
+//! client can't spend its whole allowance on them.
+
+use crate::config::environment::RateLimitConfig;
+use crate::constants::limits::EXPENSIVE_TOOL_LIMIT_DIVISOR;
+use crate::constants::tools::{
+    ANALYZE_PERFORMANCE_TRENDS, EXPORT_ACTIVITIES, GENERATE_PERIOD_REPORT, GENERATE_TRAINING_PLAN,
+    GET_ACTIVITY_INTELLIGENCE, PREDICT_PERFORMANCE,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Who a request is counted against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitKey {
+    Ip(IpAddr),
+    User(Uuid),
+    ApiKey(Uuid),
+}
+
+impl RateLimitKey {
+    /// Keys of an authenticated request: its user, and its API key if it used one
+    pub fn authenticated(user_id: Uuid, api_key_id: Option<Uuid>) -> Vec<Self> {
+        std::iter::once(Self::User(user_id)).chain(api_key_id.map(Self::ApiKey)).collect()
+    }
+
+    /// Kind of key, as reported to clients
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Ip(_) => "ip",
+            Self::User(_) => "user",
+            Self::ApiKey(_) => "api_key",
+        }
+    }
+}
+
+impl fmt::Display for RateLimitKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip(ip) => write!(f, "IP {}", ip),
+            Self::User(id) => write!(f, "user {}", id),
+            Self::ApiKey(id) => write!(f, "API key {}", id),
+        }
+    }
+}
+
+/// Separately counted request allowances
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitBucket {
+    Standard,
+    /// Tools that are slow or call out to providers and external services
+    Expensive,
+}
+
+impl RateLimitBucket {
+    /// The bucket a tool call draws from
+    pub fn for_tool(tool_name: &str) -> Self {
+        match tool_name {
+            GET_ACTIVITY_INTELLIGENCE | ANALYZE_PERFORMANCE_TRENDS | GENERATE_PERIOD_REPORT | PREDICT_PERFORMANCE
+            | GENERATE_TRAINING_PLAN | EXPORT_ACTIVITIES => Self::Expensive,
+            _ => Self::Standard,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Expensive => "expensive",
+        }
+    }
+}
+
+/// A request that went over one of its limits
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Rate limit of {limit} {} requests per {window_secs}s exceeded for {key}; retry after {retry_after_secs}s", bucket.as_str())]
+pub struct RateLimitExceeded {
+    pub key: RateLimitKey,
+    pub bucket: RateLimitBucket,
+    pub limit: u32,
+    pub window_secs: u64,
+    pub retry_after_secs: u64,
+}
+
+impl RateLimitExceeded {
+    /// Details for the `data` of an MCP error or the body of an HTTP 429
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "retry_after_secs": self.retry_after_secs,
+            "limit": self.limit,
+            "window_secs": self.window_secs,
+            "key_type": self.key.kind(),
+            "bucket": self.bucket.as_str(),
+        })
+    }
+}
+
+struct Window {
+    started: Instant,
+    count: u32,
+}
+
+struct Windows {
+    counts: HashMap<(RateLimitKey, RateLimitBucket), Window>,
+    last_pruned: Instant,
+}
+
+/// Request counters shared by every connection of a server
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    windows: Mutex<Windows>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            windows: Mutex::new(Windows {
+                counts: HashMap::new(),
+                last_pruned: Instant::now(),
+            }),
+        }
+    }
+
+    /// A limiter that lets every request through
+    pub fn disabled() -> Self {
+        Self::new(RateLimitConfig {
+            enabled: false,
+            requests_per_window: 0,
+            window_seconds: 0,
+        })
+    }
+
+    /// Requests allowed per window in a bucket
+    pub fn limit(&self, bucket: RateLimitBucket) -> u32 {
+        match bucket {
+            RateLimitBucket::Standard => self.config.requests_per_window,
+            RateLimitBucket::Expensive => (self.config.requests_per_window / EXPENSIVE_TOOL_LIMIT_DIVISOR).max(1),
+        }
+    }
+
+    /// Count a request against every key. If any key is over its limit,
+    /// nothing is counted and the key that ran out first is reported.
+    pub fn check(&self, keys: &[RateLimitKey], bucket: RateLimitBucket) -> Result<(), RateLimitExceeded> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+
+        let window = Duration::from_secs(self.config.window_seconds);
+        let limit = self.limit(bucket);
+        let now = Instant::now();
+        let mut windows = self.windows.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+        if now.duration_since(windows.last_pruned) >= window {
+            windows.counts.retain(|_, counter| now.duration_since(counter.started) < window);
+            windows.last_pruned = now;
+        }
+
+        let exceeded = keys
+            .iter()
+            .filter_map(|key| {
+                let counter = windows.counts.get(&(*key, bucket))?;
+                let elapsed = now.duration_since(counter.started);
+                (elapsed < window && counter.count >= limit).then(|| (*key, window - elapsed))
+            })
+            .max_by_key(|(_, remaining)| *remaining);
+        if let Some((key, remaining)) = exceeded {
+            return Err(RateLimitExceeded {
+                key,
+                bucket,
+                limit,
+                window_secs: self.config.window_seconds,
+                // Round up so a client retrying on time isn't a moment early
+                retry_after_secs: remaining.as_secs() + u64::from(remaining.subsec_nanos() > 0),
+            });
+        }
+
+        for key in keys {
+            let counter = windows.counts.entry((*key, bucket)).or_insert(Window { started: now, count: 0 });
+            if now.duration_since(counter.started) >= window {
+                *counter = Window { started: now, count: 0 };
+            }
+            counter.count += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+
+    fn limiter(requests_per_window: u32, window_seconds: u64) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            enabled: true,
+            requests_per_window,
+            window_seconds,
+        })
+    }
+
+    #[test]
+    fn test_keys_are_limited_separately() {
+        let limiter = limiter(2, 60);
+        let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let user = RateLimitKey::User(Uuid::new_v4());
+
+        assert!(limiter.check(&[ip], RateLimitBucket::Standard).is_ok());
+        assert!(limiter.check(&[ip], RateLimitBucket::Standard).is_ok());
+        let exceeded = limiter.check(&[ip], RateLimitBucket::Standard).unwrap_err();
+        assert_eq!(exceeded.key, ip);
+        assert_eq!(exceeded.limit, 2);
+        assert!(exceeded.retry_after_secs > 0 && exceeded.retry_after_secs <= 60);
+
+        assert!(limiter.check(&[user], RateLimitBucket::Standard).is_ok());
+    }
+
+    #[test]
+    fn test_rejected_requests_are_not_counted() {
+        let limiter = limiter(1, 60);
+        let user = RateLimitKey::User(Uuid::new_v4());
+        let api_key = RateLimitKey::ApiKey(Uuid::new_v4());
+
+        assert!(limiter.check(&[user], RateLimitBucket::Standard).is_ok());
+        // The user is out of requests, so the API key's request isn't counted either
+        assert!(limiter.check(&[user, api_key], RateLimitBucket::Standard).is_err());
+        assert!(limiter.check(&[api_key], RateLimitBucket::Standard).is_ok());
+    }
+
+    #[test]
+    fn test_expensive_bucket_is_separate_and_smaller() {
+        let limiter = limiter(20, 60);
+        let user = RateLimitKey::User(Uuid::new_v4());
+        assert_eq!(RateLimitBucket::for_tool(GET_ACTIVITY_INTELLIGENCE), RateLimitBucket::Expensive);
+        assert_eq!(RateLimitBucket::for_tool("list_goals"), RateLimitBucket::Standard);
+
+        assert!(limiter.check(&[user], RateLimitBucket::Expensive).is_ok());
+        assert!(limiter.check(&[user], RateLimitBucket::Expensive).is_ok());
+        let exceeded = limiter.check(&[user], RateLimitBucket::Expensive).unwrap_err();
+        assert_eq!(exceeded.bucket, RateLimitBucket::Expensive);
+        assert_eq!(exceeded.to_json()["key_type"], "user");
+
+        assert!(limiter.check(&[user], RateLimitBucket::Standard).is_ok());
+    }
+
+    #[test]
+    fn test_window_resets() {
+        let limiter = limiter(1, 1);
+        let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+        assert!(limiter.check(&[ip], RateLimitBucket::Standard).is_ok());
+        assert!(limiter.check(&[ip], RateLimitBucket::Standard).is_err());
+        std::thread::sleep(Duration::from_millis(1100));
+        assert!(limiter.check(&[ip], RateLimitBucket::Standard).is_ok());
+    }
+
+    #[test]
+    fn test_disabled_limiter_allows_everything() {
+        let limiter = RateLimiter::disabled();
+        let ip = RateLimitKey::Ip(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        for _ in 0..100 {
+            assert!(limiter.check(&[ip], RateLimitBucket::Expensive).is_ok());
+        }
+    }
+}
@@ -4,17 +4,29 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-//! HTTP routes for user authentication and OAuth flows in multi-tenant mode
+//! HTTP routes for user authentication, API keys, OAuth flows, activity uploads and exports in multi-tenant mode
 
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use tracing::{info, error};
+use std::sync::Arc;
+use tracing::{info, error, warn};
 use uuid::Uuid;
 use base64::{Engine, engine::general_purpose};
+use futures_util::{Stream, StreamExt};
+use warp::hyper::body::Buf;
 use crate::{
-    auth::AuthManager,
+    auth::{generate_api_key, generate_oauth_state, generate_refresh_token, hash_api_key, hash_refresh_token, AuthManager, McpAuthMiddleware},
+    config::environment::OAuthConfig,
     database::Database,
-    models::User,
+    export::{export_query, export_user_activities, ActivityExport, ExportFormat},
+    constants::{api_keys, env_config, limits, oauth},
+    models::{ApiKey, ApiKeyScope, AuthSession, OAuthState, User},
+    oauth2_client::PkceParams,
+    rate_limit::{RateLimitBucket, RateLimitKey, RateLimiter},
+    providers::{
+        file_import::archive, fitbit::FitbitProvider, garmin::{GarminNotification, GarminPing, GarminProvider}, strava::StravaProvider, token_refresh, AuthData,
+        FitnessProvider,
+    },
 };
 
 #[derive(Debug, Clone, Deserialize)]
@@ -40,9 +52,17 @@ pub struct LoginRequest {
 pub struct LoginResponse {
     pub jwt_token: String,
     pub expires_at: String,
+    /// Single-use token for `/auth/refresh`, present when refresh tokens are enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
     pub user: UserInfo,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct UserInfo {
     pub user_id: String,
@@ -80,6 +100,47 @@ pub struct OAuthCallbackResponse {
     pub scopes: String,
 }
 
+#[derive(Debug, Serialize)]
+pub struct ImportResponse {
+    pub activity: crate::models::Activity,
+    pub stream_points: usize,
+    pub laps: usize,
+    pub device: Option<crate::models::DeviceInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveImportResponse {
+    pub source: crate::providers::file_import::archive::ArchiveSource,
+    pub imported: usize,
+    /// Activities already in the store, synced over the API or imported before
+    pub duplicates: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GarminWebhookResponse {
+    /// Activities fetched from ping callbacks and stored
+    pub stored: usize,
+    /// Records pushed inline or of a summary type the server doesn't keep
+    pub ignored: usize,
+    pub failed: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Days until the key expires, at most `MAX_EXPIRY_DAYS`; omit for a key that lasts until revoked
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreateApiKeyResponse {
+    /// The full key. Only its hash is stored, so it can't be shown again.
+    pub api_key: String,
+    pub key: ApiKey,
+}
+
 #[derive(Debug, Deserialize)]
 struct StravaTokenResponse {
     access_token: String,
@@ -91,7 +152,6 @@ struct StravaTokenResponse {
     token_type: String,
     #[serde(default)]
     scope: Option<String>,
-    #[allow(dead_code)]
     #[serde(default)]
     athlete: serde_json::Value,
 }
@@ -104,7 +164,6 @@ struct FitbitTokenResponse {
     #[allow(dead_code)]
     token_type: String,
     scope: String,
-    #[allow(dead_code)]
     user_id: String,
 }
 
@@ -112,6 +171,7 @@ struct FitbitTokenResponse {
 pub struct AuthRoutes {
     database: Database,
     auth_manager: AuthManager,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 impl AuthRoutes {
@@ -119,9 +179,16 @@ impl AuthRoutes {
         Self {
             database,
             auth_manager,
+            rate_limiter: Arc::new(RateLimiter::disabled()),
         }
     }
 
+    /// Count authenticated requests against their user
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
     /// Handle user registration
     pub async fn register(&self, request: RegisterRequest) -> Result<RegisterResponse> {
         info!("User registration attempt for email: {}", request.email);
@@ -179,23 +246,134 @@ impl AuthRoutes {
         // Update last active timestamp
         self.database.update_last_active(user.id).await?;
 
-        // Generate JWT token
-        let jwt_token = self.auth_manager.generate_token(&user)?;
-        let expires_at = chrono::Utc::now() + chrono::Duration::hours(24); // TODO: use auth_manager config
+        let session_id = Uuid::new_v4();
+        let response = self.start_session(&user, session_id, session_id, None).await?;
 
         info!("User logged in successfully: {} ({})", request.email, user.id);
+        Ok(response)
+    }
+
+    /// Exchange a refresh token for a new JWT and refresh token
+    ///
+    /// Each refresh token works once. Presenting one that was already
+    /// exchanged means it leaked, so every session of that login is revoked.
+    pub async fn refresh(&self, request: RefreshTokenRequest) -> Result<LoginResponse> {
+        if !self.auth_manager.refresh_tokens_enabled() {
+            return Err(anyhow::anyhow!("Refresh tokens are disabled"));
+        }
+
+        let session = self.database.get_session_by_refresh_hash(&hash_refresh_token(&request.refresh_token)).await?
+            .ok_or_else(|| anyhow::anyhow!("Invalid refresh token"))?;
+        if session.replaced_by.is_some() {
+            return Err(self.revoke_reused_session(&session).await);
+        }
+        if !session.is_active() {
+            return Err(anyhow::anyhow!("Refresh token has expired or been revoked"));
+        }
+
+        let user = self.database.get_user(session.user_id).await?
+            .ok_or_else(|| anyhow::anyhow!("User not found"))?;
+        let response = self.start_session(&user, Uuid::new_v4(), session.family_id, Some(session.id)).await?;
+
+        info!("Refreshed session for user {}", user.id);
+        Ok(response)
+    }
+
+    /// Revoke every session of the login the bearer JWT belongs to
+    pub async fn logout(&self, auth_header: Option<&str>) -> Result<()> {
+        let token = auth_header
+            .and_then(|h| h.strip_prefix("Bearer "))
+            .ok_or_else(|| anyhow::anyhow!("Missing or invalid authorization header"))?;
+        let claims = self.auth_manager.validate_active_token(token, &self.database).await?;
+        let user_id = Uuid::parse_str(&claims.sub)?;
+        let jti = Uuid::parse_str(&claims.jti)?;
+        count_authenticated(&self.rate_limiter, user_id, None)?;
+
+        match self.database.get_session(jti).await? {
+            Some(session) => {
+                self.database.revoke_session_family(session.family_id).await?;
+            }
+            None => {
+                // A token issued without a session; record it as revoked
+                let now = chrono::Utc::now();
+                let session = AuthSession {
+                    id: jti,
+                    user_id,
+                    family_id: jti,
+                    created_at: now,
+                    expires_at: now + self.auth_manager.token_lifetime(),
+                    revoked_at: Some(now),
+                    replaced_by: None,
+                };
+                self.database.create_session(&session, None).await?;
+            }
+        }
+
+        info!("User logged out: {}", user_id);
+        Ok(())
+    }
+
+    /// Issue a JWT, plus a refresh token if enabled, and store their session.
+    /// With `replaces`, the new session rotates out that one.
+    async fn start_session(
+        &self,
+        user: &User,
+        session_id: Uuid,
+        family_id: Uuid,
+        replaces: Option<Uuid>,
+    ) -> Result<LoginResponse> {
+        let now = chrono::Utc::now();
+        let jwt_token = self.auth_manager.generate_token_with_id(user, session_id)?;
+        let jwt_expires_at = now + self.auth_manager.token_lifetime();
+        let refresh_token = self.auth_manager.refresh_tokens_enabled().then(generate_refresh_token);
+
+        let session = AuthSession {
+            id: session_id,
+            user_id: user.id,
+            family_id,
+            created_at: now,
+            expires_at: if refresh_token.is_some() {
+                now + chrono::Duration::days(limits::REFRESH_TOKEN_EXPIRY_DAYS)
+            } else {
+                jwt_expires_at
+            },
+            revoked_at: None,
+            replaced_by: None,
+        };
+        let refresh_token_hash = refresh_token.as_deref().map(hash_refresh_token);
+        match (replaces, refresh_token_hash.as_deref()) {
+            (Some(previous), Some(hash)) => {
+                if !self.database.rotate_session(previous, &session, hash).await? {
+                    // Another request exchanged the same token first
+                    let previous = self.database.get_session(previous).await?
+                        .ok_or_else(|| anyhow::anyhow!("Invalid refresh token"))?;
+                    return Err(self.revoke_reused_session(&previous).await);
+                }
+            }
+            _ => self.database.create_session(&session, refresh_token_hash.as_deref()).await?,
+        }
 
         Ok(LoginResponse {
             jwt_token,
-            expires_at: expires_at.to_rfc3339(),
+            expires_at: jwt_expires_at.to_rfc3339(),
+            refresh_token,
             user: UserInfo {
                 user_id: user.id.to_string(),
-                email: user.email,
-                display_name: user.display_name,
+                email: user.email.clone(),
+                display_name: user.display_name.clone(),
             },
         })
     }
 
+    /// Revoke the login of a refresh token that was used twice, returning the error to report
+    async fn revoke_reused_session(&self, session: &AuthSession) -> anyhow::Error {
+        warn!("Refresh token reuse for user {}, revoking all sessions of that login", session.user_id);
+        if let Err(e) = self.database.revoke_session_family(session.family_id).await {
+            error!("Failed to revoke sessions after refresh token reuse: {}", e);
+        }
+        anyhow::anyhow!("Refresh token has already been used; sign in again")
+    }
+
     /// Validate email format
     fn is_valid_email(&self, email: &str) -> bool {
         // Simple email validation
@@ -220,123 +398,239 @@ impl AuthRoutes {
     }
 }
 
+/// Creation, listing and revocation of a user's API keys
+#[derive(Clone)]
+pub struct ApiKeyRoutes {
+    database: Database,
+    auth_middleware: McpAuthMiddleware,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl ApiKeyRoutes {
+    pub fn new(database: Database, auth_manager: AuthManager) -> Self {
+        Self {
+            auth_middleware: McpAuthMiddleware::new(auth_manager, database.clone()),
+            database,
+            rate_limiter: Arc::new(RateLimiter::disabled()),
+        }
+    }
+
+    /// Count authenticated requests against their user and API key
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Create an API key for the authenticated user
+    pub async fn create_api_key(&self, auth_header: Option<&str>, request: CreateApiKeyRequest) -> Result<CreateApiKeyResponse> {
+        let user_id = self.authenticate(auth_header).await?;
+
+        if request.name.trim().is_empty() {
+            return Err(anyhow::anyhow!("API key name is required"));
+        }
+        if request.scopes.is_empty() {
+            return Err(anyhow::anyhow!("API key needs at least one scope: activities:read, goals:write or admin"));
+        }
+        if request.expires_in_days.is_some_and(|days| !(1..=api_keys::MAX_EXPIRY_DAYS).contains(&days)) {
+            return Err(anyhow::anyhow!("expires_in_days must be between 1 and {}", api_keys::MAX_EXPIRY_DAYS));
+        }
+
+        let (api_key, key_prefix) = generate_api_key();
+        let now = chrono::Utc::now();
+        let key = ApiKey {
+            id: Uuid::new_v4(),
+            user_id,
+            name: request.name.trim().to_string(),
+            key_prefix,
+            scopes: request.scopes,
+            created_at: now,
+            expires_at: request.expires_in_days.map(|days| now + chrono::Duration::days(days)),
+            last_used_at: None,
+            revoked_at: None,
+        };
+        self.database.create_api_key(&key, &hash_api_key(&api_key)).await?;
+
+        info!("Created API key {} for user {}", key.key_prefix, user_id);
+        Ok(CreateApiKeyResponse { api_key, key })
+    }
+
+    /// List the authenticated user's API keys, without their secrets
+    pub async fn list_api_keys(&self, auth_header: Option<&str>) -> Result<Vec<ApiKey>> {
+        let user_id = self.authenticate(auth_header).await?;
+        self.database.get_api_keys(user_id).await
+    }
+
+    /// Revoke one of the authenticated user's API keys
+    pub async fn revoke_api_key(&self, auth_header: Option<&str>, key_id: &str) -> Result<()> {
+        let user_id = self.authenticate(auth_header).await?;
+        let key_id = Uuid::parse_str(key_id).map_err(|_| anyhow::anyhow!("Invalid API key ID format"))?;
+
+        if !self.database.revoke_api_key(user_id, key_id).await? {
+            return Err(anyhow::anyhow!("API key not found or already revoked"));
+        }
+        info!("Revoked API key {} for user {}", key_id, user_id);
+        Ok(())
+    }
+
+    /// Keys are managed with a login token or an admin API key
+    async fn authenticate(&self, auth_header: Option<&str>) -> Result<Uuid> {
+        let auth = self.auth_middleware.authenticate_request(auth_header).await?;
+        count_authenticated(&self.rate_limiter, auth.user_id, auth.api_key_id)?;
+        if !auth.allows(ApiKeyScope::Admin) {
+            return Err(anyhow::anyhow!("Managing API keys requires a login token or an admin API key"));
+        }
+        Ok(auth.user_id)
+    }
+}
+
 /// OAuth flow routes for connecting fitness providers
 #[derive(Clone)]
 pub struct OAuthRoutes {
-    #[allow(dead_code)]
     database: Database,
+    auth_manager: AuthManager,
+    oauth_config: Arc<OAuthConfig>,
+    rate_limiter: Arc<RateLimiter>,
 }
 
 impl OAuthRoutes {
-    pub fn new(database: Database) -> Self {
-        Self { database }
+    pub fn new(database: Database, auth_manager: AuthManager, oauth_config: Arc<OAuthConfig>) -> Self {
+        Self { database, auth_manager, oauth_config, rate_limiter: Arc::new(RateLimiter::disabled()) }
     }
 
-    /// Get OAuth authorization URL for a provider with real configuration
-    pub async fn get_auth_url(&self, user_id: uuid::Uuid, provider: &str) -> Result<OAuthAuthorizationResponse> {
-        // Store state in database for CSRF protection
-        let state = format!("{}:{}", user_id, uuid::Uuid::new_v4());
-        self.store_oauth_state(user_id, provider, &state).await?;
+    /// Count authenticated requests against their user
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Client ID, secret and redirect URI of a provider users can connect
+    fn client(&self, provider: &str) -> Result<(String, String, String)> {
+        let config = self.oauth_config.provider(provider)
+            .filter(|config| config.enabled)
+            .ok_or_else(|| anyhow::anyhow!("{} OAuth is not enabled", provider))?;
+        let (client_id, client_secret) = config.credentials()
+            .ok_or_else(|| anyhow::anyhow!(
+                "{} OAuth is not configured: set {}_CLIENT_ID and {}_CLIENT_SECRET",
+                provider,
+                provider.to_uppercase(),
+                provider.to_uppercase()
+            ))?;
+        let redirect_uri = config.redirect_uri.clone()
+            .ok_or_else(|| anyhow::anyhow!("{} OAuth has no redirect URI configured", provider))?;
+        Ok((client_id, client_secret, redirect_uri))
+    }
+
+    /// Get OAuth authorization URL for the authenticated user to connect a provider
+    pub async fn get_auth_url(&self, auth_header: Option<&str>, provider: &str) -> Result<OAuthAuthorizationResponse> {
+        let user_id = authenticate_bearer(&self.auth_manager, &self.database, &self.rate_limiter, auth_header).await?;
+        self.auth_url_for_user(user_id, provider).await
+    }
+
+    /// Authorization URL for a user the caller has already authenticated, such as an MCP session
+    pub(crate) async fn auth_url_for_user(&self, user_id: uuid::Uuid, provider: &str) -> Result<OAuthAuthorizationResponse> {
+        let state = generate_oauth_state();
+        let pkce = PkceParams::generate();
+        let client = |client_id: String| AuthData::OAuth2 {
+            client_id,
+            client_secret: String::new(),
+            access_token: None,
+            refresh_token: None,
+        };
         
-        match provider {
+        if !oauth::OAUTH_PROVIDERS.contains(&provider) {
+            return Err(anyhow::anyhow!("Unsupported provider: {}", provider));
+        }
+        let (client_id, _, redirect_uri) = self.client(provider)?;
+        
+        let (authorization_url, display_name) = match provider {
             "strava" => {
-                let client_id = std::env::var("STRAVA_CLIENT_ID")
-                    .or_else(|_| std::env::var("strava_client_id"))
-                    .unwrap_or_else(|_| "YOUR_STRAVA_CLIENT_ID".to_string());
-                
-                let redirect_uri = std::env::var("STRAVA_REDIRECT_URI")
-                    .or_else(|_| std::env::var("strava_redirect_uri"))
-                    .unwrap_or_else(|_| "http://localhost:8081/oauth/callback/strava".to_string());
-                
-                let scope = "read,activity:read_all";
-                
-                let auth_url = format!(
-                    "https://www.strava.com/oauth/authorize?client_id={}&redirect_uri={}&response_type=code&scope={}&state={}",
-                    urlencoding::encode(&client_id),
-                    urlencoding::encode(&redirect_uri),
-                    urlencoding::encode(scope),
-                    urlencoding::encode(&state)
-                );
-                
-                Ok(OAuthAuthorizationResponse {
-                    authorization_url: auth_url,
-                    state: state.clone(),
-                    instructions: "Visit the URL above to authorize access to your Strava account. You'll be redirected back after authorization.".to_string(),
-                    expires_in_minutes: 10,
-                })
+                let mut strava = StravaProvider::new();
+                strava.authenticate(client(client_id)).await?;
+                (strava.get_auth_url_with_pkce(&redirect_uri, &state, &pkce)?, "Strava")
             }
             "fitbit" => {
-                let client_id = std::env::var("FITBIT_CLIENT_ID")
-                    .or_else(|_| std::env::var("fitbit_client_id"))
-                    .unwrap_or_else(|_| "YOUR_FITBIT_CLIENT_ID".to_string());
-                
-                let redirect_uri = std::env::var("FITBIT_REDIRECT_URI")
-                    .or_else(|_| std::env::var("fitbit_redirect_uri"))
-                    .unwrap_or_else(|_| "http://localhost:8081/oauth/callback/fitbit".to_string());
-                
-                let scope = "activity%20profile";
-                
-                let auth_url = format!(
-                    "https://www.fitbit.com/oauth2/authorize?client_id={}&redirect_uri={}&response_type=code&scope={}&state={}",
-                    urlencoding::encode(&client_id),
-                    urlencoding::encode(&redirect_uri),
-                    scope,
-                    urlencoding::encode(&state)
-                );
-                
-                Ok(OAuthAuthorizationResponse {
-                    authorization_url: auth_url,
-                    state: state.clone(),
-                    instructions: "Visit the URL above to authorize access to your Fitbit account. You'll be redirected back after authorization.".to_string(),
-                    expires_in_minutes: 10,
-                })
+                let mut fitbit = FitbitProvider::new();
+                fitbit.authenticate(client(client_id)).await?;
+                (fitbit.get_auth_url_with_pkce(&redirect_uri, &state, &pkce)?, "Fitbit")
             }
-            _ => Err(anyhow::anyhow!("Unsupported provider: {}", provider))
-        }
+            "garmin" => {
+                let mut garmin = GarminProvider::new();
+                garmin.authenticate(client(client_id)).await?;
+                (garmin.get_auth_url_with_pkce(&redirect_uri, &state, &pkce)?, "Garmin Connect")
+            }
+            _ => return Err(anyhow::anyhow!("Unsupported provider: {}", provider)),
+        };
+        
+        // Store state in database for CSRF protection
+        self.store_oauth_state(user_id, provider, &state, &pkce).await?;
+        
+        Ok(OAuthAuthorizationResponse {
+            authorization_url,
+            state,
+            instructions: format!(
+                "Visit the URL above to authorize access to your {} account. You'll be redirected back after authorization.",
+                display_name
+            ),
+            expires_in_minutes: limits::OAUTH_STATE_EXPIRY_MINUTES,
+        })
     }
     
-    /// Store OAuth state for CSRF protection
-    async fn store_oauth_state(&self, user_id: uuid::Uuid, provider: &str, state: &str) -> Result<()> {
-        // Store state with expiration (10 minutes)
-        let _expires_at = chrono::Utc::now() + chrono::Duration::minutes(10);
-        
-        // In a production system, you'd store this in a cache/database
-        // For now, we'll store it in memory or use a simple approach
-        info!("Storing OAuth state for user {} provider {}: {}", user_id, provider, state);
+    /// Store OAuth state for CSRF protection, with the PKCE verifier the callback needs
+    async fn store_oauth_state(&self, user_id: uuid::Uuid, provider: &str, state: &str, pkce: &PkceParams) -> Result<()> {
+        self.database.store_oauth_state(&OAuthState {
+            state: state.to_string(),
+            user_id,
+            provider: provider.to_string(),
+            code_verifier: pkce.code_verifier.clone(),
+            expires_at: chrono::Utc::now() + chrono::Duration::minutes(limits::OAUTH_STATE_EXPIRY_MINUTES),
+        }).await?;
         
-        // TODO: Implement proper state storage
+        info!("Stored OAuth state for user {} provider {}", user_id, provider);
         Ok(())
     }
 
     /// Handle OAuth callback and store tokens
     pub async fn handle_callback(&self, code: &str, state: &str, provider: &str) -> Result<OAuthCallbackResponse> {
-        // Parse user ID from state
-        let parts: Vec<&str> = state.split(':').collect();
-        if parts.len() != 2 {
-            return Err(anyhow::anyhow!("Invalid state parameter"));
+        if !oauth::OAUTH_PROVIDERS.contains(&provider) {
+            return Err(anyhow::anyhow!("Unsupported provider: {}", provider));
         }
         
-        let user_id = uuid::Uuid::parse_str(parts[0])?;
+        // The stored state, not the state string, says whose account is being connected
+        let oauth_state = self.database.consume_oauth_state(state).await?
+            .ok_or_else(|| anyhow::anyhow!("Invalid state parameter: unknown, expired or already used"))?;
+        if oauth_state.provider != provider {
+            return Err(anyhow::anyhow!(
+                "Invalid state parameter: issued for {}, not {}",
+                oauth_state.provider,
+                provider
+            ));
+        }
+        
+        let user_id = oauth_state.user_id;
+        let pkce = PkceParams::from_verifier(oauth_state.code_verifier);
         
-        // Validate state (in production, check against stored state)
         info!("Processing OAuth callback for user {} provider {}", user_id, provider);
         
         // Exchange code for tokens (implementation depends on provider)
         match provider {
             "strava" => {
-                let token_response = self.exchange_strava_code(code).await?;
+                let token_response = self.exchange_strava_code(code, &pkce).await?;
                 
                 // Store encrypted tokens in database
                 let expires_at = chrono::DateTime::<chrono::Utc>::from_timestamp(token_response.expires_at, 0)
                     .unwrap_or_else(|| chrono::Utc::now() + chrono::Duration::hours(6));
                 
-                self.database.update_strava_token(
+                self.database.update_provider_token(
                     user_id,
+                    "strava",
                     &token_response.access_token,
                     &token_response.refresh_token,
                     expires_at,
                     token_response.scope.clone().unwrap_or_else(|| "read,activity:read_all".to_string()),
                 ).await?;
+                if let Some(athlete_id) = token_response.athlete["id"].as_i64() {
+                    self.database.update_provider_athlete_id(user_id, "strava", &athlete_id.to_string()).await?;
+                }
                 
                 info!("Strava tokens stored successfully for user: {}", user_id);
                 
@@ -348,18 +642,20 @@ impl OAuthRoutes {
                 })
             }
             "fitbit" => {
-                let token_response = self.exchange_fitbit_code(code).await?;
+                let token_response = self.exchange_fitbit_code(code, &pkce).await?;
                 
                 // Store encrypted tokens in database
                 let expires_at = chrono::Utc::now() + chrono::Duration::seconds(token_response.expires_in);
                 
-                self.database.update_fitbit_token(
+                self.database.update_provider_token(
                     user_id,
+                    "fitbit",
                     &token_response.access_token,
                     &token_response.refresh_token,
                     expires_at,
                     token_response.scope.clone(),
                 ).await?;
+                self.database.update_provider_athlete_id(user_id, "fitbit", &token_response.user_id).await?;
                 
                 info!("Fitbit tokens stored successfully for user: {}", user_id);
                 
@@ -370,30 +666,65 @@ impl OAuthRoutes {
                     scopes: token_response.scope,
                 })
             }
+            "garmin" => {
+                let token = self.exchange_garmin_code(code, &pkce).await?;
+                
+                // Store encrypted tokens in database
+                let expires_at = token.expires_at
+                    .unwrap_or_else(|| chrono::Utc::now() + chrono::Duration::hours(24));
+                let scope = token.scope.clone()
+                    .unwrap_or_else(|| oauth::GARMIN_DEFAULT_SCOPES.to_string());
+                
+                self.database.update_provider_token(
+                    user_id,
+                    "garmin",
+                    &token.access_token,
+                    token.refresh_token.as_deref().unwrap_or_default(),
+                    expires_at,
+                    scope.clone(),
+                ).await?;
+                
+                // Push notifications name the Garmin user, not ours
+                let mut garmin = GarminProvider::new();
+                garmin.authenticate(AuthData::OAuth2 {
+                    client_id: String::new(),
+                    client_secret: String::new(),
+                    access_token: Some(token.access_token.clone()),
+                    refresh_token: token.refresh_token.clone(),
+                }).await?;
+                match garmin.get_athlete().await {
+                    Ok(athlete) => self.database.update_provider_athlete_id(user_id, "garmin", &athlete.id).await?,
+                    Err(e) => warn!("Could not look up Garmin user id for user {}: {}", user_id, e),
+                }
+                
+                info!("Garmin tokens stored successfully for user: {}", user_id);
+                
+                Ok(OAuthCallbackResponse {
+                    user_id: user_id.to_string(),
+                    provider: "garmin".to_string(),
+                    expires_at: expires_at.to_rfc3339(),
+                    scopes: scope,
+                })
+            }
             _ => Err(anyhow::anyhow!("Unsupported provider: {}", provider))
         }
     }
     
     /// Exchange Strava authorization code for tokens
-    async fn exchange_strava_code(&self, code: &str) -> Result<StravaTokenResponse> {
-        let client_id = std::env::var("STRAVA_CLIENT_ID")
-            .or_else(|_| std::env::var("strava_client_id"))
-            .unwrap_or_else(|_| "163846".to_string()); // Default for testing
-        
-        let client_secret = std::env::var("STRAVA_CLIENT_SECRET")
-            .or_else(|_| std::env::var("strava_client_secret"))
-            .unwrap_or_else(|_| "1dfc45ad0a1f6983b835e4495aa9473d111d03bc".to_string()); // Default for testing
+    async fn exchange_strava_code(&self, code: &str, pkce: &PkceParams) -> Result<StravaTokenResponse> {
+        let (client_id, client_secret, _) = self.client("strava")?;
         
         let params = [
             ("client_id", client_id.as_str()),
             ("client_secret", client_secret.as_str()),
             ("code", code),
             ("grant_type", "authorization_code"),
+            ("code_verifier", pkce.code_verifier.as_str()),
         ];
         
         let client = reqwest::Client::new();
         let response = client
-            .post("https://www.strava.com/oauth/token")
+            .post(env_config::strava_token_url())
             .form(&params)
             .send()
             .await?;
@@ -415,24 +746,15 @@ impl OAuthRoutes {
     }
     
     /// Exchange Fitbit authorization code for tokens
-    async fn exchange_fitbit_code(&self, code: &str) -> Result<FitbitTokenResponse> {
-        let client_id = std::env::var("FITBIT_CLIENT_ID")
-            .or_else(|_| std::env::var("fitbit_client_id"))
-            .unwrap_or_else(|_| "YOUR_FITBIT_CLIENT_ID".to_string());
-        
-        let client_secret = std::env::var("FITBIT_CLIENT_SECRET")
-            .or_else(|_| std::env::var("fitbit_client_secret"))
-            .unwrap_or_else(|_| "YOUR_FITBIT_CLIENT_SECRET".to_string());
-        
-        let redirect_uri = std::env::var("FITBIT_REDIRECT_URI")
-            .or_else(|_| std::env::var("fitbit_redirect_uri"))
-            .unwrap_or_else(|_| "http://localhost:8081/oauth/callback/fitbit".to_string());
+    async fn exchange_fitbit_code(&self, code: &str, pkce: &PkceParams) -> Result<FitbitTokenResponse> {
+        let (client_id, client_secret, redirect_uri) = self.client("fitbit")?;
         
         let params = [
             ("client_id", client_id.as_str()),
             ("grant_type", "authorization_code"),
             ("redirect_uri", redirect_uri.as_str()),
             ("code", code),
+            ("code_verifier", pkce.code_verifier.as_str()),
         ];
         
         let auth_header = general_purpose::STANDARD.encode(format!("{}:{}", client_id, client_secret));
@@ -457,65 +779,338 @@ impl OAuthRoutes {
         Ok(token_response)
     }
     
+    /// Exchange Garmin authorization code for tokens
+    async fn exchange_garmin_code(&self, code: &str, pkce: &PkceParams) -> Result<crate::oauth2_client::OAuth2Token> {
+        let (client_id, client_secret, redirect_uri) = self.client("garmin")?;
+        
+        let token = crate::oauth2_client::garmin::exchange_garmin_code_with_pkce(
+            &reqwest::Client::new(),
+            &client_id,
+            &client_secret,
+            code,
+            &redirect_uri,
+            pkce,
+        ).await?;
+        info!("Garmin token exchange successful");
+        
+        Ok(token)
+    }
+    
     /// Get connection status for all providers for a user
     pub async fn get_connection_status(&self, user_id: Uuid) -> Result<Vec<ConnectionStatus>> {
-        let mut statuses = Vec::new();
+        let connections = self.database.get_provider_connections(user_id).await.unwrap_or_default();
         
-        // Check Strava connection
-        if let Ok(Some(strava_token)) = self.database.get_strava_token(user_id).await {
-            statuses.push(ConnectionStatus {
-                provider: "strava".to_string(),
-                connected: true,
-                expires_at: Some(strava_token.expires_at.to_rfc3339()),
-                scopes: Some(strava_token.scope),
-            });
-        } else {
-            statuses.push(ConnectionStatus {
-                provider: "strava".to_string(),
-                connected: false,
-                expires_at: None,
-                scopes: None,
-            });
-        }
-        
-        // Check Fitbit connection
-        if let Ok(Some(fitbit_token)) = self.database.get_fitbit_token(user_id).await {
-            statuses.push(ConnectionStatus {
-                provider: "fitbit".to_string(),
-                connected: true,
-                expires_at: Some(fitbit_token.expires_at.to_rfc3339()),
-                scopes: Some(fitbit_token.scope),
-            });
-        } else {
-            statuses.push(ConnectionStatus {
-                provider: "fitbit".to_string(),
-                connected: false,
-                expires_at: None,
-                scopes: None,
-            });
-        }
+        let statuses = oauth::OAUTH_PROVIDERS
+            .iter()
+            .map(|&provider| match connections.iter().find(|c| c.provider == provider) {
+                Some(connection) => ConnectionStatus {
+                    provider: provider.to_string(),
+                    connected: true,
+                    expires_at: Some(connection.token.expires_at.to_rfc3339()),
+                    scopes: Some(connection.token.scope.clone()),
+                },
+                None => ConnectionStatus {
+                    provider: provider.to_string(),
+                    connected: false,
+                    expires_at: None,
+                    scopes: None,
+                },
+            })
+            .collect();
         
         Ok(statuses)
     }
     
     /// Disconnect a provider by removing stored tokens
     pub async fn disconnect_provider(&self, user_id: Uuid, provider: &str) -> Result<()> {
-        match provider {
-            "strava" => {
-                // TODO: Implement token revocation by clearing the tokens in database
-                // For now, we'd need to add a method to clear specific provider tokens
-                info!("Disconnecting Strava for user {}", user_id);
-                // self.database.clear_strava_token(user_id).await?;
-                Ok(())
+        if !oauth::OAUTH_PROVIDERS.contains(&provider) {
+            return Err(anyhow::anyhow!("Unsupported provider: {}", provider));
+        }
+        
+        // Tokens are dropped locally; they stay valid at the provider until they expire
+        if self.database.delete_provider_connection(user_id, provider).await? {
+            info!("Disconnected {} for user {}", provider, user_id);
+        }
+        Ok(())
+    }
+}
+
+/// Upload of GPX, TCX and FIT files and account export archives into a user's activity store
+#[derive(Clone)]
+pub struct ImportRoutes {
+    database: Database,
+    auth_manager: AuthManager,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl ImportRoutes {
+    pub fn new(database: Database, auth_manager: AuthManager) -> Self {
+        Self {
+            database,
+            auth_manager,
+            rate_limiter: Arc::new(RateLimiter::disabled()),
+        }
+    }
+
+    /// Count authenticated requests against their user
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Parse an uploaded activity file and store it for the authenticated user
+    pub async fn upload_activity(
+        &self,
+        auth_header: Option<&str>,
+        file_name: &str,
+        bytes: &[u8],
+    ) -> Result<ImportResponse> {
+        let user_id = self.authenticate(auth_header).await?;
+
+        let details = crate::providers::file_import::parse_activity_file(file_name, bytes)?;
+        self.database.store_activity_details(user_id, &details).await?;
+        info!("Imported activity {} from {} for user {}", details.activity.id, file_name, user_id);
+
+        Ok(ImportResponse {
+            stream_points: details.streams.len(),
+            laps: details.laps.len(),
+            device: details.device,
+            activity: details.activity,
+        })
+    }
+
+    /// Import a Strava or Garmin account export, skipping activities already in the store.
+    /// The body is only read once the caller is authenticated, and is spooled to a
+    /// temporary file rather than held in memory.
+    pub async fn import_archive<S, B, E>(&self, auth_header: Option<&str>, body: S) -> Result<ArchiveImportResponse>
+    where
+        S: Stream<Item = std::result::Result<B, E>>,
+        B: Buf,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let user_id = self.authenticate(auth_header).await?;
+
+        let file = spool_body(body, limits::MAX_IMPORT_ARCHIVE_BYTES).await?;
+        let contents = tokio::task::spawn_blocking(move || archive::read_archive(file)).await??;
+        let query = crate::activity_query::ActivityQuery::default().unpaged();
+        let mut existing = self.database.query_activities(user_id, None, &query).await?;
+
+        let mut imported = 0;
+        let mut duplicates = 0;
+        for details in contents.activities {
+            if archive::find_duplicate(&details.activity, &existing).is_some() {
+                duplicates += 1;
+                continue;
             }
-            "fitbit" => {
-                info!("Disconnecting Fitbit for user {}", user_id);
-                // self.database.clear_fitbit_token(user_id).await?;
-                Ok(())
+
+            if details.streams.is_empty() && details.laps.is_empty() && details.device.is_none() {
+                self.database.upsert_activities(user_id, std::slice::from_ref(&details.activity)).await?;
+            } else {
+                self.database.store_activity_details(user_id, &details).await?;
             }
-            _ => Err(anyhow::anyhow!("Unsupported provider: {}", provider))
+            existing.push(details.activity);
+            imported += 1;
+        }
+
+        info!(
+            "Imported {} activities from a {:?} archive for user {} ({} duplicates, {} unreadable entries)",
+            imported, contents.source, user_id, duplicates, contents.failed.len()
+        );
+        Ok(ArchiveImportResponse { source: contents.source, imported, duplicates, failed: contents.failed })
+    }
+
+    async fn authenticate(&self, auth_header: Option<&str>) -> Result<Uuid> {
+        authenticate_bearer(&self.auth_manager, &self.database, &self.rate_limiter, auth_header).await
+    }
+}
+
+/// Write a request body to an anonymous temporary file, failing once it passes `max_bytes`
+async fn spool_body<S, B, E>(body: S, max_bytes: u64) -> Result<std::fs::File>
+where
+    S: Stream<Item = std::result::Result<B, E>>,
+    B: Buf,
+    E: std::error::Error + Send + Sync + 'static,
+{
+    use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+
+    let mut file = tokio::fs::File::from_std(tempfile::tempfile()?);
+    let mut body = std::pin::pin!(body);
+    let mut written = 0u64;
+    while let Some(mut chunk) = body.next().await.transpose()? {
+        written += chunk.remaining() as u64;
+        if written > max_bytes {
+            return Err(anyhow::anyhow!("Upload is larger than {} bytes", max_bytes));
+        }
+        file.write_all(&chunk.copy_to_bytes(chunk.remaining())).await?;
+    }
+    file.rewind().await?;
+    Ok(file.into_std().await)
+}
+
+/// Notifications Garmin posts when a connected user's device syncs
+///
+/// Garmin doesn't sign these requests, so records pushed inline are not
+/// trusted. Only ping notifications are acted on: their data is fetched from
+/// Garmin with the user's own token, so the server should be registered for
+/// ping rather than push delivery.
+#[derive(Clone)]
+pub struct GarminWebhookRoutes {
+    database: Database,
+    oauth_config: Arc<OAuthConfig>,
+}
+
+impl GarminWebhookRoutes {
+    pub fn new(database: Database, oauth_config: Arc<OAuthConfig>) -> Self {
+        Self { database, oauth_config }
+    }
+
+    /// Fetch and store the activities behind each ping in a notification
+    pub async fn handle_notification(&self, body: &[u8]) -> Result<GarminWebhookResponse> {
+        let notification = GarminNotification::from_json(body)?;
+        let mut response = GarminWebhookResponse {
+            stored: 0,
+            ignored: notification.activities.len() + notification.dailies.len(),
+            failed: Vec::new(),
+        };
+        if response.ignored > 0 {
+            warn!("Ignoring {} records pushed inline to the Garmin webhook; only ping notifications are supported", response.ignored);
+        }
+
+        for ping in &notification.pings {
+            if !matches!(ping.summary_type.as_str(), "activities" | "activityDetails") {
+                response.ignored += 1;
+                continue;
+            }
+            match self.store_ping(ping).await {
+                Ok(stored) => response.stored += stored,
+                Err(e) => {
+                    warn!("Garmin {} ping for Garmin user {} failed: {:#}", ping.summary_type, ping.user_id, e);
+                    response.failed.push(format!("{} for {}: {:#}", ping.summary_type, ping.user_id, e));
+                }
+            }
+        }
+        Ok(response)
+    }
+
+    async fn store_ping(&self, ping: &GarminPing) -> Result<usize> {
+        let mut stored = 0;
+        for user_id in self.database.get_users_by_provider_athlete_id("garmin", &ping.user_id).await? {
+            let token = token_refresh::valid_token(&self.database, &self.oauth_config, user_id, "garmin").await?;
+            let mut garmin = GarminProvider::new();
+            garmin.authenticate(AuthData::OAuth2 {
+                client_id: String::new(),
+                client_secret: String::new(),
+                access_token: Some(token.access_token),
+                refresh_token: Some(token.refresh_token),
+            }).await?;
+
+            let fetched = garmin.fetch_ping(ping).await?;
+            for details in &fetched.activities {
+                // A bare summary must not wipe the samples of an activity already stored with details
+                if details.streams.is_empty() && details.laps.is_empty() {
+                    self.database.upsert_activities(user_id, std::slice::from_ref(&details.activity)).await?;
+                } else {
+                    self.database.store_activity_details(user_id, details).await?;
+                }
+            }
+            info!("Stored {} Garmin activities for user {} from a {} ping", fetched.activities.len(), user_id, ping.summary_type);
+            stored += fetched.activities.len();
+        }
+        Ok(stored)
+    }
+}
+
+/// Download of a user's stored activities as CSV, GPX, TCX or Parquet
+#[derive(Clone)]
+pub struct ExportRoutes {
+    database: Database,
+    auth_manager: AuthManager,
+    rate_limiter: Arc<RateLimiter>,
+}
+
+impl ExportRoutes {
+    pub fn new(database: Database, auth_manager: AuthManager) -> Self {
+        Self {
+            database,
+            auth_manager,
+            rate_limiter: Arc::new(RateLimiter::disabled()),
         }
     }
+
+    /// Count authenticated requests against their user
+    pub fn with_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.rate_limiter = rate_limiter;
+        self
+    }
+
+    /// Export the authenticated user's activities. Query parameters are the
+    /// `export_activities` tool arguments.
+    pub async fn export_activities(
+        &self,
+        auth_header: Option<&str>,
+        params: &std::collections::HashMap<String, String>,
+    ) -> Result<(ExportFormat, ActivityExport)> {
+        let user_id = authenticate_bearer(&self.auth_manager, &self.database, &self.rate_limiter, auth_header).await?;
+
+        let format_name = params.get("format").map_or("csv", String::as_str);
+        let format = ExportFormat::parse(format_name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown export format '{}'. Use csv, gpx, tcx or parquet", format_name))?;
+
+        // Numbers and booleans arrive as text; type them the way tool arguments are
+        let args: serde_json::Map<String, serde_json::Value> = params
+            .iter()
+            .map(|(key, value)| {
+                let typed = match value.as_str() {
+                    "true" => serde_json::Value::Bool(true),
+                    "false" => serde_json::Value::Bool(false),
+                    _ => serde_json::from_str::<serde_json::Number>(value)
+                        .map_or_else(|_| serde_json::Value::String(value.clone()), serde_json::Value::Number),
+                };
+                (key.clone(), typed)
+            })
+            .collect();
+        let args = serde_json::Value::Object(args);
+        let timezone = match args["timezone"].as_str() {
+            Some(timezone) => Some(timezone.to_string()),
+            None => self.database.get_user_timezone(user_id).await?,
+        };
+        let timezone = match timezone {
+            Some(timezone) => timezone
+                .parse::<chrono_tz::Tz>()
+                .map_err(|_| anyhow::anyhow!("Unknown timezone '{}'", timezone))?,
+            None => chrono_tz::Tz::UTC,
+        };
+        let query = export_query(&args, timezone)?;
+        let provider = args["provider"].as_str().filter(|p| !p.is_empty());
+
+        let export = export_user_activities(&self.database, user_id, provider, &query, format).await?;
+        info!("Exported {} activities as {} for user {}", export.exported, format.extension(), user_id);
+        Ok((format, export))
+    }
+}
+
+/// Resolve the user from an `Authorization: Bearer <jwt>` header
+async fn authenticate_bearer(
+    auth_manager: &AuthManager,
+    database: &Database,
+    rate_limiter: &RateLimiter,
+    auth_header: Option<&str>,
+) -> Result<Uuid> {
+    let token = auth_header
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .ok_or_else(|| anyhow::anyhow!("Missing or invalid authorization header"))?;
+    let claims = auth_manager.validate_active_token(token, database).await?;
+    let user_id = Uuid::parse_str(&claims.sub)?;
+    count_authenticated(rate_limiter, user_id, None)?;
+    Ok(user_id)
+}
+
+/// Count an authenticated HTTP request against its user and API key. The
+/// server counts every request against its IP before it reaches a route; the
+/// user and key are only known once the route has authenticated it.
+fn count_authenticated(rate_limiter: &RateLimiter, user_id: Uuid, api_key_id: Option<Uuid>) -> Result<()> {
+    rate_limiter.check(&RateLimitKey::authenticated(user_id, api_key_id), RateLimitBucket::Standard)?;
+    Ok(())
 }
 
 #[cfg(test)]
@@ -0,0 +1,280 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # TLS Termination
+//!
+//! rustls-based TLS for the HTTP and MCP listeners, for deployments without a
+//! reverse proxy in front. The certificate and key files are checked for
+//! changes periodically, so a renewed certificate is served without a restart.
+//! A renewal that leaves the files unreadable or mismatched keeps the previous
+//! certificate in use until the files are fixed.
+
+use crate::config::environment::TlsConfig;
+use crate::constants::limits::{TLS_HANDSHAKE_TIMEOUT_SECS, TLS_RELOAD_INTERVAL_SECS};
+use anyhow::{Context, Result};
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::crypto::{ring, CryptoProvider};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::TlsAcceptor;
+use tracing::{debug, info, warn};
+use warp::hyper::service::Service;
+use warp::Filter;
+
+type FileTimes = (Option<SystemTime>, Option<SystemTime>);
+
+/// Serves the certificate on disk, reloading it when the files change
+#[derive(Debug)]
+pub struct ReloadingCertResolver {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    current: RwLock<Arc<CertifiedKey>>,
+    /// Modification times of the files the current certificate was loaded from
+    loaded_from: Mutex<FileTimes>,
+}
+
+impl ReloadingCertResolver {
+    pub fn load(cert_path: &Path, key_path: &Path, provider: Arc<CryptoProvider>) -> Result<Self> {
+        let loaded_from = modified_times(cert_path, key_path);
+        let current = load_certified_key(cert_path, key_path, &provider)?;
+        Ok(Self {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            provider,
+            current: RwLock::new(Arc::new(current)),
+            loaded_from: Mutex::new(loaded_from),
+        })
+    }
+
+    /// Reload the certificate if either file changed since it was loaded.
+    /// Returns whether a new certificate is now being served.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let mut loaded_from = self.loaded_from.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let modified = modified_times(&self.cert_path, &self.key_path);
+        if *loaded_from == modified {
+            return Ok(false);
+        }
+
+        // On failure the file times aren't recorded, so the next check tries again
+        let reloaded = load_certified_key(&self.cert_path, &self.key_path, &self.provider)?;
+        *self.current.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = Arc::new(reloaded);
+        *loaded_from = modified;
+        Ok(true)
+    }
+
+    /// The certificate chain and key being served
+    pub fn current(&self) -> Arc<CertifiedKey> {
+        self.current.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+}
+
+impl ResolvesServerCert for ReloadingCertResolver {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(self.current())
+    }
+}
+
+fn modified_times(cert_path: &Path, key_path: &Path) -> FileTimes {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    (modified(cert_path), modified(key_path))
+}
+
+/// Load a PEM certificate chain and the private key that belongs to it
+pub fn load_certified_key(cert_path: &Path, key_path: &Path, provider: &CryptoProvider) -> Result<CertifiedKey> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read TLS certificate {}", cert_path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow::anyhow!("No certificates found in {}", cert_path.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key_path)
+        .with_context(|| format!("Failed to read TLS private key {}", key_path.display()))?;
+
+    CertifiedKey::from_der(certs, key, provider)
+        .with_context(|| format!("TLS private key {} doesn't match certificate {}", key_path.display(), cert_path.display()))
+}
+
+/// Build the acceptor for both listeners, or `None` when TLS is disabled.
+/// Must be called within a Tokio runtime, which runs the reload checks.
+pub fn acceptor(config: &TlsConfig) -> Result<Option<TlsAcceptor>> {
+    if !config.enabled {
+        return Ok(None);
+    }
+    let (Some(cert_path), Some(key_path)) = (&config.cert_path, &config.key_path) else {
+        return Err(anyhow::anyhow!("TLS is enabled but cert_path or key_path is missing"));
+    };
+
+    let provider = Arc::new(ring::default_provider());
+    let resolver = Arc::new(ReloadingCertResolver::load(cert_path, key_path, provider.clone())?);
+    watch(&resolver, Duration::from_secs(TLS_RELOAD_INTERVAL_SECS));
+
+    let server_config = ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()?
+        .with_no_client_auth()
+        .with_cert_resolver(resolver);
+    info!("TLS enabled with certificate {}", cert_path.display());
+    Ok(Some(TlsAcceptor::from(Arc::new(server_config))))
+}
+
+/// Check the certificate files for changes every interval, for as long as the
+/// resolver is in use
+pub fn watch(resolver: &Arc<ReloadingCertResolver>, interval: Duration) -> JoinHandle<()> {
+    let resolver = Arc::downgrade(resolver);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+        loop {
+            ticker.tick().await;
+            let Some(resolver) = resolver.upgrade() else {
+                break;
+            };
+            match resolver.reload_if_changed() {
+                Ok(true) => info!("Reloaded TLS certificate from {}", resolver.cert_path.display()),
+                Ok(false) => {}
+                Err(e) => warn!("Failed to reload TLS certificate, still serving the previous one: {:#}", e),
+            }
+        }
+    })
+}
+
+/// Address of the client at the other end of a connection that warp didn't accept itself
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+/// The client's address, over plain HTTP or TLS
+pub fn remote_addr() -> impl Filter<Extract = (Option<SocketAddr>,), Error = Infallible> + Clone {
+    warp::addr::remote()
+        .and(warp::ext::optional::<ClientAddr>())
+        .map(|remote: Option<SocketAddr>, client: Option<ClientAddr>| client.map(|client| client.0).or(remote))
+}
+
+/// Complete the TLS handshake on an accepted connection, giving up after
+/// `TLS_HANDSHAKE_TIMEOUT_SECS` so idle clients can't hold tasks and sockets open
+pub async fn accept<S>(acceptor: &TlsAcceptor, socket: S) -> Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    accept_within(acceptor, socket, Duration::from_secs(TLS_HANDSHAKE_TIMEOUT_SECS)).await
+}
+
+async fn accept_within<S>(acceptor: &TlsAcceptor, socket: S, timeout: Duration) -> Result<TlsStream<S>>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    tokio::time::timeout(timeout, acceptor.accept(socket))
+        .await
+        .context("TLS handshake timed out")?
+        .context("TLS handshake failed")
+}
+
+/// Serve warp routes on an address, over TLS when an acceptor is given
+pub async fn serve_http<F, R>(routes: F, addr: SocketAddr, tls: Option<TlsAcceptor>) -> Result<()>
+where
+    F: Filter<Extract = (R,), Error = Infallible> + Clone + Send + Sync + 'static,
+    R: warp::Reply,
+{
+    let Some(tls) = tls else {
+        warp::serve(routes).run(addr).await;
+        return Ok(());
+    };
+
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (socket, peer) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(e) => {
+                warn!("Failed to accept HTTPS connection: {}", e);
+                continue;
+            }
+        };
+        let tls = tls.clone();
+        let routes = routes.clone();
+
+        tokio::spawn(async move {
+            let stream = match accept(&tls, socket).await {
+                Ok(stream) => stream,
+                Err(e) => {
+                    debug!("TLS handshake with {}: {:#}", peer, e);
+                    return;
+                }
+            };
+            let service = warp::hyper::service::service_fn(move |mut request: warp::hyper::Request<warp::hyper::Body>| {
+                request.extensions_mut().insert(ClientAddr(peer));
+                warp::service(routes.clone()).call(request)
+            });
+            if let Err(e) = warp::hyper::server::conn::Http::new().serve_connection(stream, service).await {
+                debug!("HTTPS connection from {} ended with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+    }
+
+    #[test]
+    fn test_mismatched_key_is_rejected() {
+        let provider = ring::default_provider();
+        assert!(load_certified_key(&fixture("server.pem"), &fixture("server.key"), &provider).is_ok());
+        assert!(load_certified_key(&fixture("server.pem"), &fixture("renewed.key"), &provider).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stalled_handshake_times_out() {
+        let acceptor = acceptor(&TlsConfig {
+            enabled: true,
+            cert_path: Some(fixture("server.pem")),
+            key_path: Some(fixture("server.key")),
+        })
+        .unwrap()
+        .unwrap();
+
+        // The client connects but never sends a ClientHello
+        let (_client, server) = tokio::io::duplex(1024);
+        let error = accept_within(&acceptor, server, Duration::from_millis(50)).await.unwrap_err();
+        assert!(error.to_string().contains("timed out"));
+    }
+
+    #[test]
+    fn test_changed_certificate_is_reloaded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("cert.pem");
+        let key_path = dir.path().join("key.pem");
+        std::fs::copy(fixture("server.pem"), &cert_path).unwrap();
+        std::fs::copy(fixture("server.key"), &key_path).unwrap();
+
+        let resolver = ReloadingCertResolver::load(&cert_path, &key_path, Arc::new(ring::default_provider())).unwrap();
+        let original = resolver.current().cert[0].clone();
+        assert!(!resolver.reload_if_changed().unwrap());
+
+        // Halfway through a renewal the key no longer matches; keep serving the old certificate
+        std::thread::sleep(Duration::from_millis(10));
+        std::fs::copy(fixture("renewed.pem"), &cert_path).unwrap();
+        assert!(resolver.reload_if_changed().is_err());
+        assert_eq!(resolver.current().cert[0], original);
+
+        std::fs::copy(fixture("renewed.key"), &key_path).unwrap();
+        assert!(resolver.reload_if_changed().unwrap());
+        assert_ne!(resolver.current().cert[0], original);
+    }
+}
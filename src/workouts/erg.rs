@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! `.erg` (absolute watts) and `.mrc` (percent of FTP) course files
+//!
+//! Both list the workout as points of (minutes, target); each segment adds a
+//! point at its start and end so steady blocks stay flat and ramps interpolate.
+
+use super::{StructuredWorkout, WorkoutSport, WorkoutThresholds};
+use anyhow::{bail, Result};
+use std::fmt::Write;
+
+pub fn to_erg(workout: &StructuredWorkout, thresholds: &WorkoutThresholds) -> Result<String> {
+    ensure_bike(workout)?;
+    let Some(ftp) = thresholds.ftp_watts else {
+        bail!("ERG files need an FTP; set ftp_watts or use the MRC format for percent targets");
+    };
+
+    let header = format!("FTP = {:.0}\nMINUTES WATTS", ftp);
+    Ok(course_file(workout, &header, |intensity| format!("{:.0}", ftp * intensity)))
+}
+
+pub fn to_mrc(workout: &StructuredWorkout) -> Result<String> {
+    ensure_bike(workout)?;
+    Ok(course_file(workout, "MINUTES PERCENT", |intensity| format!("{:.0}", intensity * 100.0)))
+}
+
+fn ensure_bike(workout: &StructuredWorkout) -> Result<()> {
+    if workout.sport != WorkoutSport::Bike {
+        bail!("ERG and MRC files only describe bike workouts");
+    }
+    Ok(())
+}
+
+fn course_file(workout: &StructuredWorkout, header: &str, target: impl Fn(f64) -> String) -> String {
+    let mut file = String::new();
+    file.push_str("[COURSE HEADER]\n");
+    file.push_str("VERSION = 2\n");
+    file.push_str("UNITS = ENGLISH\n");
+    let _ = writeln!(file, "DESCRIPTION = {}", single_line(&workout.description));
+    let _ = writeln!(file, "FILE NAME = {}", single_line(&workout.name));
+    let _ = writeln!(file, "{}", header);
+    file.push_str("[END COURSE HEADER]\n");
+    file.push_str("[COURSE DATA]\n");
+
+    let mut elapsed = 0.0;
+    for segment in workout.segments() {
+        let end = elapsed + segment.duration_seconds as f64 / 60.0;
+        let _ = writeln!(file, "{:.2}\t{}", elapsed, target(segment.start_intensity));
+        let _ = writeln!(file, "{:.2}\t{}", end, target(segment.end_intensity));
+        elapsed = end;
+    }
+
+    file.push_str("[END COURSE DATA]\n");
+    file
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workouts::WorkoutStep;
+
+    fn workout(sport: WorkoutSport) -> StructuredWorkout {
+        StructuredWorkout {
+            name: "Sweet spot".to_string(),
+            description: "2 x 10 min\nsweet spot".to_string(),
+            sport,
+            steps: vec![
+                WorkoutStep::Warmup { duration_seconds: 300, start_intensity: 0.5, end_intensity: 0.7 },
+                WorkoutStep::Repeat {
+                    count: 2,
+                    steps: vec![
+                        WorkoutStep::Steady { duration_seconds: 600, intensity: 0.9 },
+                        WorkoutStep::Steady { duration_seconds: 150, intensity: 0.5 },
+                    ],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_erg_and_mrc_export() {
+        let thresholds = WorkoutThresholds { ftp_watts: Some(200.0), threshold_pace_seconds_per_km: None };
+        let erg = to_erg(&workout(WorkoutSport::Bike), &thresholds).unwrap();
+        assert!(erg.contains("DESCRIPTION = 2 x 10 min sweet spot\n"));
+        assert!(erg.contains("FTP = 200\nMINUTES WATTS\n"));
+        assert!(erg.contains("0.00\t100\n5.00\t140\n5.00\t180\n15.00\t180\n"));
+        assert!(erg.ends_with("27.50\t100\n30.00\t100\n[END COURSE DATA]\n"));
+
+        let mrc = to_mrc(&workout(WorkoutSport::Bike)).unwrap();
+        assert!(mrc.contains("MINUTES PERCENT\n"));
+        assert!(mrc.contains("5.00\t90\n15.00\t90\n"));
+
+        assert!(to_erg(&workout(WorkoutSport::Bike), &WorkoutThresholds::default()).is_err());
+        assert!(to_mrc(&workout(WorkoutSport::Run)).is_err());
+    }
+}
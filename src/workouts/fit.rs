@@ -0,0 +1,365 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! FIT workout files (`file_id`, `workout` and `workout_step` messages)
+//!
+//! Bike steps target power, in watts when FTP is known and as percent of FTP
+//! otherwise. Run steps target speed and need a threshold pace.
+
+use super::{StructuredWorkout, WorkoutSport, WorkoutStep, WorkoutThresholds};
+use anyhow::{bail, Result};
+use chrono::Utc;
+
+const PROTOCOL_VERSION: u8 = 0x10;
+const PROFILE_VERSION: u16 = 2132;
+
+/// Seconds between the Unix epoch and the FIT epoch (1989-12-31T00:00:00Z)
+pub(crate) const FIT_EPOCH_OFFSET: i64 = 631_065_600;
+
+const MESG_FILE_ID: u16 = 0;
+const MESG_WORKOUT: u16 = 26;
+const MESG_WORKOUT_STEP: u16 = 27;
+
+const BASE_ENUM: u8 = 0x00;
+const BASE_STRING: u8 = 0x07;
+const BASE_UINT16: u8 = 0x84;
+const BASE_UINT32: u8 = 0x86;
+
+const FILE_TYPE_WORKOUT: u8 = 5;
+const MANUFACTURER_DEVELOPMENT: u16 = 255;
+
+const SPORT_RUNNING: u8 = 1;
+const SPORT_CYCLING: u8 = 2;
+
+const DURATION_TIME: u8 = 0;
+const DURATION_REPEAT_UNTIL_STEPS_COMPLETE: u8 = 6;
+
+const TARGET_SPEED: u8 = 0;
+const TARGET_POWER: u8 = 4;
+
+const INTENSITY_ACTIVE: u8 = 0;
+const INTENSITY_REST: u8 = 1;
+const INTENSITY_WARMUP: u8 = 2;
+const INTENSITY_COOLDOWN: u8 = 3;
+
+/// Power targets above this are absolute watts offset by 1000, below it percent of FTP
+const POWER_WATTS_OFFSET: u32 = 1000;
+
+/// Half width of the target band around a steady intensity
+const TARGET_BAND: f64 = 0.03;
+
+const STEP_NAME_SIZE: u8 = 16;
+const WORKOUT_NAME_MAX: usize = 40;
+
+const INVALID_ENUM: u8 = 0xFF;
+const INVALID_UINT32: u32 = 0xFFFF_FFFF;
+
+/// One `workout_step` message
+struct FitStep {
+    name: &'static str,
+    duration_type: u8,
+    duration_value: u32,
+    target_type: u8,
+    target_value: u32,
+    target_low: u32,
+    target_high: u32,
+    intensity: u8,
+}
+
+/// Encode a workout as a FIT workout file: one `workout_step` per step, with
+/// repeats closed by a repeat-until-steps-complete step. Each step gets a
+/// target band around its intensity, as power for bike workouts and as speed
+/// for run workouts, taken from `thresholds`. Fails for run workouts when no
+/// threshold pace is known.
+pub fn to_fit(workout: &StructuredWorkout, thresholds: &WorkoutThresholds) -> Result<Vec<u8>> {
+    if workout.sport == WorkoutSport::Run && thresholds.threshold_pace_seconds_per_km.is_none() {
+        bail!("FIT run workouts need a threshold pace; set threshold_pace");
+    }
+
+    let mut steps = Vec::new();
+    for step in &workout.steps {
+        match step {
+            WorkoutStep::Repeat { count, steps: repeated } => {
+                let first_index = steps.len() as u32;
+                for step in repeated {
+                    steps.push(fit_step(step, workout.sport, thresholds));
+                }
+                steps.push(FitStep {
+                    name: "Repeat",
+                    duration_type: DURATION_REPEAT_UNTIL_STEPS_COMPLETE,
+                    duration_value: first_index,
+                    target_type: INVALID_ENUM,
+                    target_value: *count,
+                    target_low: INVALID_UINT32,
+                    target_high: INVALID_UINT32,
+                    intensity: INVALID_ENUM,
+                });
+            }
+            step => steps.push(fit_step(step, workout.sport, thresholds)),
+        }
+    }
+
+    let name = truncate(&workout.name, WORKOUT_NAME_MAX);
+    let sport = match workout.sport {
+        WorkoutSport::Bike => SPORT_CYCLING,
+        WorkoutSport::Run => SPORT_RUNNING,
+    };
+
+    let mut data = Vec::new();
+
+    define(&mut data, 0, MESG_FILE_ID, &[(0, 1, BASE_ENUM), (1, 2, BASE_UINT16), (2, 2, BASE_UINT16), (4, 4, BASE_UINT32)]);
+    data.push(0);
+    data.push(FILE_TYPE_WORKOUT);
+    data.extend_from_slice(&MANUFACTURER_DEVELOPMENT.to_le_bytes());
+    data.extend_from_slice(&0u16.to_le_bytes());
+    data.extend_from_slice(&((Utc::now().timestamp() - FIT_EPOCH_OFFSET) as u32).to_le_bytes());
+
+    let name_size = name.len() as u8 + 1;
+    define(&mut data, 1, MESG_WORKOUT, &[(4, 1, BASE_ENUM), (6, 2, BASE_UINT16), (8, name_size, BASE_STRING)]);
+    data.push(1);
+    data.push(sport);
+    data.extend_from_slice(&(steps.len() as u16).to_le_bytes());
+    push_string(&mut data, name, name_size);
+
+    define(
+        &mut data,
+        2,
+        MESG_WORKOUT_STEP,
+        &[
+            (254, 2, BASE_UINT16),
+            (0, STEP_NAME_SIZE, BASE_STRING),
+            (1, 1, BASE_ENUM),
+            (2, 4, BASE_UINT32),
+            (3, 1, BASE_ENUM),
+            (4, 4, BASE_UINT32),
+            (5, 4, BASE_UINT32),
+            (6, 4, BASE_UINT32),
+            (7, 1, BASE_ENUM),
+        ],
+    );
+    for (index, step) in steps.iter().enumerate() {
+        data.push(2);
+        data.extend_from_slice(&(index as u16).to_le_bytes());
+        push_string(&mut data, step.name, STEP_NAME_SIZE);
+        data.push(step.duration_type);
+        data.extend_from_slice(&step.duration_value.to_le_bytes());
+        data.push(step.target_type);
+        data.extend_from_slice(&step.target_value.to_le_bytes());
+        data.extend_from_slice(&step.target_low.to_le_bytes());
+        data.extend_from_slice(&step.target_high.to_le_bytes());
+        data.push(step.intensity);
+    }
+
+    let mut file = Vec::with_capacity(data.len() + 16);
+    file.push(14);
+    file.push(PROTOCOL_VERSION);
+    file.extend_from_slice(&PROFILE_VERSION.to_le_bytes());
+    file.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    file.extend_from_slice(b".FIT");
+    let header_crc = crc16(&file);
+    file.extend_from_slice(&header_crc.to_le_bytes());
+    file.extend_from_slice(&data);
+    let file_crc = crc16(&file);
+    file.extend_from_slice(&file_crc.to_le_bytes());
+
+    Ok(file)
+}
+
+fn fit_step(step: &WorkoutStep, sport: WorkoutSport, thresholds: &WorkoutThresholds) -> FitStep {
+    let (name, duration_seconds, low, high, intensity) = match step {
+        WorkoutStep::Warmup { duration_seconds, start_intensity, end_intensity } => (
+            "Warmup",
+            *duration_seconds,
+            start_intensity.min(*end_intensity),
+            start_intensity.max(*end_intensity),
+            INTENSITY_WARMUP,
+        ),
+        WorkoutStep::Cooldown { duration_seconds, start_intensity, end_intensity } => (
+            "Cooldown",
+            *duration_seconds,
+            start_intensity.min(*end_intensity),
+            start_intensity.max(*end_intensity),
+            INTENSITY_COOLDOWN,
+        ),
+        WorkoutStep::Steady { duration_seconds, intensity } => {
+            let (name, kind) = if *intensity < 0.6 { ("Recover", INTENSITY_REST) } else { ("Work", INTENSITY_ACTIVE) };
+            (name, *duration_seconds, (intensity - TARGET_BAND).max(0.0), intensity + TARGET_BAND, kind)
+        }
+        WorkoutStep::Repeat { .. } => unreachable!("repeats are expanded by the caller"),
+    };
+
+    let (target_type, target_low, target_high) = match sport {
+        WorkoutSport::Bike => match thresholds.ftp_watts {
+            Some(_) => (
+                TARGET_POWER,
+                thresholds.watts(low).unwrap_or_default().round() as u32 + POWER_WATTS_OFFSET,
+                thresholds.watts(high).unwrap_or_default().round() as u32 + POWER_WATTS_OFFSET,
+            ),
+            None => (TARGET_POWER, (low * 100.0).round() as u32, (high * 100.0).round() as u32),
+        },
+        // Speed in mm/s
+        WorkoutSport::Run => (
+            TARGET_SPEED,
+            (thresholds.speed(low).unwrap_or_default() * 1000.0).round() as u32,
+            (thresholds.speed(high).unwrap_or_default() * 1000.0).round() as u32,
+        ),
+    };
+
+    FitStep {
+        name,
+        duration_type: DURATION_TIME,
+        duration_value: duration_seconds * 1000,
+        target_type,
+        target_value: 0,
+        target_low,
+        target_high,
+        intensity,
+    }
+}
+
+/// Append a little-endian definition message for `(field number, size, base type)` fields
+fn define(data: &mut Vec<u8>, local_type: u8, global: u16, fields: &[(u8, u8, u8)]) {
+    data.push(0x40 | local_type);
+    data.push(0); // reserved
+    data.push(0); // little-endian
+    data.extend_from_slice(&global.to_le_bytes());
+    data.push(fields.len() as u8);
+    for (number, size, base_type) in fields {
+        data.extend_from_slice(&[*number, *size, *base_type]);
+    }
+}
+
+/// Null-terminated string padded to `size` bytes
+fn push_string(data: &mut Vec<u8>, value: &str, size: u8) {
+    let value = truncate(value, size as usize - 1);
+    data.extend_from_slice(value.as_bytes());
+    data.resize(data.len() + size as usize - value.len(), 0);
+}
+
+fn truncate(value: &str, max_bytes: usize) -> &str {
+    let mut end = value.len().min(max_bytes);
+    while !value.is_char_boundary(end) {
+        end -= 1;
+    }
+    &value[..end]
+}
+
+pub(crate) fn crc16(bytes: &[u8]) -> u16 {
+    const TABLE: [u16; 16] = [
+        0x0000, 0xCC01, 0xD801, 0x1400, 0xF001, 0x3C00, 0x2800, 0xE401, 0xA001, 0x6C00, 0x7800, 0xB401, 0x5000,
+        0x9C01, 0x8801, 0x4400,
+    ];
+
+    bytes.iter().fold(0u16, |mut crc, byte| {
+        let tmp = TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc = crc ^ tmp ^ TABLE[(byte & 0xF) as usize];
+        let tmp = TABLE[(crc & 0xF) as usize];
+        crc = (crc >> 4) & 0x0FFF;
+        crc ^ tmp ^ TABLE[((byte >> 4) & 0xF) as usize]
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode the workout_step messages of a file written by `to_fit`
+    fn decode_steps(file: &[u8]) -> Vec<Vec<u8>> {
+        let data = &file[14..file.len() - 2];
+        let mut sizes = std::collections::HashMap::new();
+        let mut globals = std::collections::HashMap::new();
+        let mut steps = Vec::new();
+        let mut pos = 0;
+
+        while pos < data.len() {
+            let header = data[pos];
+            let local = header & 0x0F;
+            pos += 1;
+            if header & 0x40 != 0 {
+                let global = u16::from_le_bytes([data[pos + 2], data[pos + 3]]);
+                let count = data[pos + 4] as usize;
+                let size: usize = (0..count).map(|i| data[pos + 5 + i * 3 + 1] as usize).sum();
+                sizes.insert(local, size);
+                globals.insert(local, global);
+                pos += 5 + count * 3;
+            } else {
+                let size = sizes[&local];
+                if globals[&local] == MESG_WORKOUT_STEP {
+                    steps.push(data[pos..pos + size].to_vec());
+                }
+                pos += size;
+            }
+        }
+        steps
+    }
+
+    fn workout(sport: WorkoutSport) -> StructuredWorkout {
+        StructuredWorkout {
+            name: "Threshold".to_string(),
+            description: String::new(),
+            sport,
+            steps: vec![
+                WorkoutStep::Warmup { duration_seconds: 600, start_intensity: 0.5, end_intensity: 0.75 },
+                WorkoutStep::Repeat {
+                    count: 3,
+                    steps: vec![
+                        WorkoutStep::Steady { duration_seconds: 480, intensity: 1.0 },
+                        WorkoutStep::Steady { duration_seconds: 120, intensity: 0.5 },
+                    ],
+                },
+                WorkoutStep::Cooldown { duration_seconds: 300, start_intensity: 0.7, end_intensity: 0.45 },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_fit_file_structure() {
+        let thresholds = WorkoutThresholds { ftp_watts: Some(200.0), threshold_pace_seconds_per_km: None };
+        let file = to_fit(&workout(WorkoutSport::Bike), &thresholds).unwrap();
+
+        assert_eq!(file[0], 14);
+        assert_eq!(&file[8..12], b".FIT");
+        let data_size = u32::from_le_bytes([file[4], file[5], file[6], file[7]]) as usize;
+        assert_eq!(file.len(), 14 + data_size + 2);
+        assert_eq!(crc16(&file[..12]), u16::from_le_bytes([file[12], file[13]]));
+        // The CRC over a file including its trailing CRC is zero
+        assert_eq!(crc16(&file), 0);
+
+        let steps = decode_steps(&file);
+        assert_eq!(steps.len(), 5);
+
+        // Work step: index 1, 8 minutes at 97-103% of 200 W as watts + 1000
+        let work = &steps[1];
+        assert_eq!(&work[2..8], b"Work\0\0");
+        assert_eq!(u32::from_le_bytes(work[19..23].try_into().unwrap()), 480_000);
+        assert_eq!(work[23], TARGET_POWER);
+        assert_eq!(u32::from_le_bytes(work[28..32].try_into().unwrap()), 1194);
+        assert_eq!(u32::from_le_bytes(work[32..36].try_into().unwrap()), 1206);
+
+        // Repeat step points back at the work step, three times
+        let repeat = &steps[3];
+        assert_eq!(repeat[18], DURATION_REPEAT_UNTIL_STEPS_COMPLETE);
+        assert_eq!(u32::from_le_bytes(repeat[19..23].try_into().unwrap()), 1);
+        assert_eq!(u32::from_le_bytes(repeat[24..28].try_into().unwrap()), 3);
+    }
+
+    #[test]
+    fn test_fit_targets_without_ftp_and_for_runs() {
+        let file = to_fit(&workout(WorkoutSport::Bike), &WorkoutThresholds::default()).unwrap();
+        let work = &decode_steps(&file)[1];
+        assert_eq!(u32::from_le_bytes(work[28..32].try_into().unwrap()), 97);
+
+        assert!(to_fit(&workout(WorkoutSport::Run), &WorkoutThresholds::default()).is_err());
+
+        let thresholds = WorkoutThresholds { ftp_watts: None, threshold_pace_seconds_per_km: Some(250.0) };
+        let file = to_fit(&workout(WorkoutSport::Run), &thresholds).unwrap();
+        let work = &decode_steps(&file)[1];
+        assert_eq!(work[23], TARGET_SPEED);
+        // 4:10 /km is 4 m/s, so 97% is 3.88 m/s
+        assert_eq!(u32::from_le_bytes(work[28..32].try_into().unwrap()), 3880);
+    }
+}
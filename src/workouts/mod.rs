@@ -0,0 +1,422 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Structured workouts and exporters for indoor trainers and devices
+//!
+//! Step intensities are fractions of the athlete's threshold: FTP for bike
+//! workouts and threshold pace (as speed) for runs. The exporters turn them
+//! into absolute targets where the format needs them.
+
+pub mod erg;
+pub mod fit;
+pub mod zwo;
+
+use crate::intelligence::{PlannedWorkout, WorkoutType};
+use crate::models::SportType;
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Warmup and cooldown length added to structured sessions
+const RAMP_SECONDS: u32 = 600;
+
+/// Sessions shorter than this are exported without a warmup and cooldown
+const MIN_RAMPED_SECONDS: u32 = 40 * 60;
+
+/// Sports the workout formats can describe
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkoutSport {
+    Bike,
+    Run,
+}
+
+impl WorkoutSport {
+    pub fn from_sport(sport: &SportType) -> Option<Self> {
+        match sport {
+            SportType::Ride | SportType::VirtualRide | SportType::EbikeRide | SportType::GravelRide => Some(Self::Bike),
+            SportType::Run | SportType::VirtualRun | SportType::TrailRunning => Some(Self::Run),
+            _ => None,
+        }
+    }
+}
+
+/// One step of a structured workout; intensities are fractions of threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WorkoutStep {
+    Warmup { duration_seconds: u32, start_intensity: f64, end_intensity: f64 },
+    Steady { duration_seconds: u32, intensity: f64 },
+    Cooldown { duration_seconds: u32, start_intensity: f64, end_intensity: f64 },
+    Repeat { count: u32, steps: Vec<WorkoutStep> },
+}
+
+impl WorkoutStep {
+    pub fn duration_seconds(&self) -> u32 {
+        match self {
+            Self::Warmup { duration_seconds, .. }
+            | Self::Steady { duration_seconds, .. }
+            | Self::Cooldown { duration_seconds, .. } => *duration_seconds,
+            Self::Repeat { count, steps } => count * steps.iter().map(Self::duration_seconds).sum::<u32>(),
+        }
+    }
+
+    fn validate(&self) -> Result<()> {
+        let intensities = match self {
+            Self::Warmup { start_intensity, end_intensity, .. }
+            | Self::Cooldown { start_intensity, end_intensity, .. } => vec![*start_intensity, *end_intensity],
+            Self::Steady { intensity, .. } => vec![*intensity],
+            Self::Repeat { count, steps } => {
+                if *count == 0 || steps.is_empty() {
+                    bail!("Repeat steps need a positive count and at least one step");
+                }
+                if steps.iter().any(|step| matches!(step, Self::Repeat { .. })) {
+                    bail!("Repeats cannot be nested");
+                }
+                return steps.iter().try_for_each(Self::validate);
+            }
+        };
+
+        if self.duration_seconds() == 0 {
+            bail!("Workout steps must have a positive duration");
+        }
+        if intensities.iter().any(|intensity| !(0.0..=3.0).contains(intensity)) {
+            bail!("Step intensities are fractions of threshold between 0 and 3");
+        }
+        Ok(())
+    }
+}
+
+/// Continuous stretch of a workout with repeats unrolled
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WorkoutSegment {
+    pub duration_seconds: u32,
+    pub start_intensity: f64,
+    pub end_intensity: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StructuredWorkout {
+    pub name: String,
+    pub description: String,
+    pub sport: WorkoutSport,
+    pub steps: Vec<WorkoutStep>,
+}
+
+impl StructuredWorkout {
+    /// Build a workout from `name`, `sport_type`, `description` and `steps` tool arguments
+    pub fn from_args(args: &Value) -> Result<Self> {
+        let Some(name) = args["name"].as_str() else {
+            bail!("name is required for a custom workout");
+        };
+        let sport_name = args["sport_type"].as_str().unwrap_or("ride");
+        let Some(sport) = WorkoutSport::from_sport(&SportType::from_name(sport_name)) else {
+            bail!("Structured workouts can only be exported for rides and runs, not '{}'", sport_name);
+        };
+        let steps: Vec<WorkoutStep> = serde_json::from_value(args["steps"].clone())
+            .map_err(|e| anyhow::anyhow!("Invalid workout steps: {}", e))?;
+
+        let workout = Self {
+            name: name.to_string(),
+            description: args["description"].as_str().unwrap_or_default().to_string(),
+            sport,
+            steps,
+        };
+        workout.validate()?;
+        Ok(workout)
+    }
+
+    /// Structure a training plan session into warmup, main set and cooldown
+    pub fn from_planned(workout: &PlannedWorkout) -> Result<Self> {
+        let Some(sport) = WorkoutSport::from_sport(&SportType::from_name(&workout.sport)) else {
+            bail!("Structured workouts can only be exported for rides and runs, not '{}'", workout.sport);
+        };
+
+        let total = workout.duration_minutes * 60;
+        let ramped = total >= MIN_RAMPED_SECONDS;
+        let main = if ramped { total - 2 * RAMP_SECONDS } else { total };
+
+        let mut main_set = match workout.workout_type {
+            WorkoutType::Recovery => vec![steady(main, 0.55)],
+            WorkoutType::Easy => vec![steady(main, 0.65)],
+            WorkoutType::Long => vec![steady(main, 0.7)],
+            WorkoutType::Tempo => with_filler(main, vec![steady(main / 2, 0.85)], 0.65),
+            WorkoutType::RacePace => with_filler(main, vec![steady(main / 2, 0.95)], 0.65),
+            WorkoutType::Race => vec![steady(main, 1.0)],
+            WorkoutType::Intervals => {
+                let repeat = WorkoutStep::Repeat { count: 5, steps: vec![steady(180, 1.1), steady(120, 0.55)] };
+                if repeat.duration_seconds() <= main {
+                    with_filler(main, vec![repeat], 0.6)
+                } else {
+                    vec![steady(main, 0.9)]
+                }
+            }
+        };
+
+        let mut steps = Vec::new();
+        if ramped {
+            steps.push(WorkoutStep::Warmup { duration_seconds: RAMP_SECONDS, start_intensity: 0.5, end_intensity: 0.75 });
+        }
+        steps.append(&mut main_set);
+        if ramped {
+            steps.push(WorkoutStep::Cooldown { duration_seconds: RAMP_SECONDS, start_intensity: 0.7, end_intensity: 0.45 });
+        }
+
+        Ok(Self {
+            name: format!("{} {:?}", workout.date, workout.workout_type),
+            description: workout.description.clone(),
+            sport,
+            steps,
+        })
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.steps.is_empty() {
+            bail!("A workout needs at least one step");
+        }
+        self.steps.iter().try_for_each(WorkoutStep::validate)
+    }
+
+    pub fn duration_seconds(&self) -> u32 {
+        self.steps.iter().map(WorkoutStep::duration_seconds).sum()
+    }
+
+    /// All steps in order with repeats unrolled
+    pub fn segments(&self) -> Vec<WorkoutSegment> {
+        fn push(step: &WorkoutStep, segments: &mut Vec<WorkoutSegment>) {
+            match step {
+                WorkoutStep::Warmup { duration_seconds, start_intensity, end_intensity }
+                | WorkoutStep::Cooldown { duration_seconds, start_intensity, end_intensity } => {
+                    segments.push(WorkoutSegment {
+                        duration_seconds: *duration_seconds,
+                        start_intensity: *start_intensity,
+                        end_intensity: *end_intensity,
+                    })
+                }
+                WorkoutStep::Steady { duration_seconds, intensity } => segments.push(WorkoutSegment {
+                    duration_seconds: *duration_seconds,
+                    start_intensity: *intensity,
+                    end_intensity: *intensity,
+                }),
+                WorkoutStep::Repeat { count, steps } => {
+                    for _ in 0..*count {
+                        steps.iter().for_each(|step| push(step, segments));
+                    }
+                }
+            }
+        }
+
+        let mut segments = Vec::new();
+        self.steps.iter().for_each(|step| push(step, &mut segments));
+        segments
+    }
+}
+
+fn steady(duration_seconds: u32, intensity: f64) -> WorkoutStep {
+    WorkoutStep::Steady { duration_seconds, intensity }
+}
+
+/// Pad a main set with easy riding or running up to `total` seconds
+fn with_filler(total: u32, mut steps: Vec<WorkoutStep>, filler_intensity: f64) -> Vec<WorkoutStep> {
+    let used: u32 = steps.iter().map(WorkoutStep::duration_seconds).sum();
+    if total > used {
+        steps.push(steady(total - used, filler_intensity));
+    }
+    steps
+}
+
+/// The athlete's thresholds that step intensities are relative to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WorkoutThresholds {
+    pub ftp_watts: Option<f64>,
+    pub threshold_pace_seconds_per_km: Option<f64>,
+}
+
+impl WorkoutThresholds {
+    /// Override stored thresholds with `ftp_watts` and `threshold_pace` tool arguments.
+    /// The pace is seconds per km or a "m:ss" string.
+    pub fn apply_args(&mut self, args: &Value) -> Result<()> {
+        if let Some(ftp) = args["ftp_watts"].as_f64() {
+            if ftp <= 0.0 {
+                bail!("ftp_watts must be positive");
+            }
+            self.ftp_watts = Some(ftp);
+        }
+
+        let pace = match &args["threshold_pace"] {
+            Value::Number(seconds) => seconds.as_f64(),
+            Value::String(pace) => Some(parse_pace(pace)?),
+            _ => None,
+        };
+        if let Some(pace) = pace {
+            if pace <= 0.0 {
+                bail!("threshold_pace must be positive");
+            }
+            self.threshold_pace_seconds_per_km = Some(pace);
+        }
+
+        Ok(())
+    }
+
+    /// Absolute power for an intensity, if FTP is known
+    pub fn watts(&self, intensity: f64) -> Option<f64> {
+        self.ftp_watts.map(|ftp| ftp * intensity)
+    }
+
+    /// Speed in m/s for an intensity, if threshold pace is known
+    pub fn speed(&self, intensity: f64) -> Option<f64> {
+        self.threshold_pace_seconds_per_km.map(|pace| 1000.0 / pace * intensity)
+    }
+}
+
+fn parse_pace(pace: &str) -> Result<f64> {
+    let parsed = match pace.trim().split_once(':') {
+        Some((minutes, seconds)) => minutes
+            .parse::<u32>()
+            .ok()
+            .zip(seconds.parse::<u32>().ok().filter(|seconds| *seconds < 60))
+            .map(|(minutes, seconds)| (minutes * 60 + seconds) as f64),
+        None => pace.trim().parse().ok(),
+    };
+    parsed.ok_or_else(|| anyhow::anyhow!("Invalid threshold_pace '{}'. Use seconds per km or m:ss", pace))
+}
+
+/// Supported export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WorkoutFormat {
+    /// Zwift workout XML
+    Zwo,
+    /// Absolute watts over time
+    Erg,
+    /// Percent of FTP over time
+    Mrc,
+    /// Garmin FIT workout file
+    Fit,
+}
+
+impl WorkoutFormat {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().trim_start_matches('.').to_lowercase().as_str() {
+            "zwo" | "zwift" => Some(Self::Zwo),
+            "erg" => Some(Self::Erg),
+            "mrc" => Some(Self::Mrc),
+            "fit" => Some(Self::Fit),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Zwo => "zwo",
+            Self::Erg => "erg",
+            Self::Mrc => "mrc",
+            Self::Fit => "fit",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Self::Zwo => "application/xml",
+            Self::Erg | Self::Mrc => "text/plain",
+            Self::Fit => "application/vnd.ant.fit",
+        }
+    }
+
+    /// Whether the export is binary and has to be base64 encoded for transport
+    pub fn is_binary(&self) -> bool {
+        matches!(self, Self::Fit)
+    }
+
+    pub fn export(&self, workout: &StructuredWorkout, thresholds: &WorkoutThresholds) -> Result<Vec<u8>> {
+        workout.validate()?;
+        match self {
+            Self::Zwo => Ok(zwo::to_zwo(workout, thresholds).into_bytes()),
+            Self::Erg => Ok(erg::to_erg(workout, thresholds)?.into_bytes()),
+            Self::Mrc => Ok(erg::to_mrc(workout)?.into_bytes()),
+            Self::Fit => fit::to_fit(workout, thresholds),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::intelligence::WorkoutIntensity;
+    use chrono::NaiveDate;
+
+    fn planned(workout_type: WorkoutType, duration_minutes: u32) -> PlannedWorkout {
+        PlannedWorkout {
+            date: NaiveDate::from_ymd_opt(2024, 5, 7).unwrap(),
+            workout_type,
+            sport: "ride".to_string(),
+            duration_minutes,
+            intensity: WorkoutIntensity { zone: 5, name: "VO2 max".to_string(), heart_rate_bpm: None },
+            description: "Intervals".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_from_planned_keeps_duration() {
+        for (workout_type, minutes) in [
+            (WorkoutType::Intervals, 60),
+            (WorkoutType::Tempo, 45),
+            (WorkoutType::Easy, 30),
+            (WorkoutType::Intervals, 25),
+        ] {
+            let workout = StructuredWorkout::from_planned(&planned(workout_type, minutes)).unwrap();
+            assert_eq!(workout.duration_seconds(), minutes * 60);
+            assert!(workout.validate().is_ok());
+        }
+
+        let intervals = StructuredWorkout::from_planned(&planned(WorkoutType::Intervals, 60)).unwrap();
+        assert!(matches!(intervals.steps[0], WorkoutStep::Warmup { .. }));
+        assert!(matches!(intervals.steps[1], WorkoutStep::Repeat { count: 5, .. }));
+        assert!(matches!(intervals.steps.last(), Some(WorkoutStep::Cooldown { .. })));
+        // 5 x (on + off) unrolled, plus warmup, filler and cooldown
+        assert_eq!(intervals.segments().len(), 13);
+
+        let mut swim = planned(WorkoutType::Easy, 30);
+        swim.sport = "swim".to_string();
+        assert!(StructuredWorkout::from_planned(&swim).is_err());
+    }
+
+    #[test]
+    fn test_custom_workout_args() {
+        let args = serde_json::json!({
+            "name": "Over-unders",
+            "sport_type": "ride",
+            "steps": [
+                {"type": "warmup", "duration_seconds": 600, "start_intensity": 0.5, "end_intensity": 0.8},
+                {"type": "repeat", "count": 3, "steps": [
+                    {"type": "steady", "duration_seconds": 120, "intensity": 0.95},
+                    {"type": "steady", "duration_seconds": 60, "intensity": 1.05}
+                ]}
+            ]
+        });
+        let workout = StructuredWorkout::from_args(&args).unwrap();
+        assert_eq!(workout.duration_seconds(), 600 + 3 * 180);
+
+        let mut nested = args.clone();
+        nested["steps"][1]["steps"][0] = serde_json::json!({"type": "repeat", "count": 2, "steps": []});
+        assert!(StructuredWorkout::from_args(&nested).is_err());
+
+        let mut zero = args;
+        zero["steps"][0]["duration_seconds"] = serde_json::json!(0);
+        assert!(StructuredWorkout::from_args(&zero).is_err());
+    }
+
+    #[test]
+    fn test_threshold_args() {
+        let mut thresholds = WorkoutThresholds { ftp_watts: Some(200.0), threshold_pace_seconds_per_km: None };
+        thresholds.apply_args(&serde_json::json!({ "threshold_pace": "4:30" })).unwrap();
+        assert_eq!(thresholds.threshold_pace_seconds_per_km, Some(270.0));
+        assert_eq!(thresholds.watts(0.5), Some(100.0));
+        assert!((thresholds.speed(1.0).unwrap() - 3.7037).abs() < 0.001);
+
+        assert!(thresholds.apply_args(&serde_json::json!({ "threshold_pace": "4:75" })).is_err());
+        assert!(thresholds.apply_args(&serde_json::json!({ "ftp_watts": -5 })).is_err());
+    }
+}
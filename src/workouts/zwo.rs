@@ -0,0 +1,136 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Zwift `.zwo` workout XML
+//!
+//! Zwift reads power as a fraction of FTP (of threshold pace for runs), which
+//! is exactly how step intensities are stored, so no thresholds are needed.
+
+use super::{StructuredWorkout, WorkoutSport, WorkoutStep, WorkoutThresholds};
+use std::fmt::Write;
+
+pub fn to_zwo(workout: &StructuredWorkout, thresholds: &WorkoutThresholds) -> String {
+    let sport = match workout.sport {
+        WorkoutSport::Bike => "bike",
+        WorkoutSport::Run => "run",
+    };
+
+    let mut xml = String::new();
+    xml.push_str("<workout_file>\n");
+    xml.push_str("    <author>Pierre</author>\n");
+    let _ = writeln!(xml, "    <name>{}</name>", escape(&workout.name));
+    let _ = writeln!(xml, "    <description>{}</description>", escape(&description(workout, thresholds)));
+    let _ = writeln!(xml, "    <sportType>{}</sportType>", sport);
+    xml.push_str("    <tags/>\n");
+    xml.push_str("    <workout>\n");
+    for step in &workout.steps {
+        push_step(&mut xml, step);
+    }
+    xml.push_str("    </workout>\n");
+    xml.push_str("</workout_file>\n");
+    xml
+}
+
+fn push_step(xml: &mut String, step: &WorkoutStep) {
+    match step {
+        WorkoutStep::Warmup { duration_seconds, start_intensity, end_intensity } => {
+            let _ = writeln!(
+                xml,
+                r#"        <Warmup Duration="{}" PowerLow="{:.2}" PowerHigh="{:.2}"/>"#,
+                duration_seconds, start_intensity, end_intensity
+            );
+        }
+        WorkoutStep::Cooldown { duration_seconds, start_intensity, end_intensity } => {
+            let _ = writeln!(
+                xml,
+                r#"        <Cooldown Duration="{}" PowerLow="{:.2}" PowerHigh="{:.2}"/>"#,
+                duration_seconds, start_intensity, end_intensity
+            );
+        }
+        WorkoutStep::Steady { duration_seconds, intensity } => {
+            let _ = writeln!(xml, r#"        <SteadyState Duration="{}" Power="{:.2}"/>"#, duration_seconds, intensity);
+        }
+        // Zwift's on/off block covers the common case; anything else is unrolled
+        WorkoutStep::Repeat { count, steps } => match steps.as_slice() {
+            [WorkoutStep::Steady { duration_seconds: on, intensity: on_power }, WorkoutStep::Steady { duration_seconds: off, intensity: off_power }] => {
+                let _ = writeln!(
+                    xml,
+                    r#"        <IntervalsT Repeat="{}" OnDuration="{}" OffDuration="{}" OnPower="{:.2}" OffPower="{:.2}"/>"#,
+                    count, on, off, on_power, off_power
+                );
+            }
+            _ => {
+                for _ in 0..*count {
+                    steps.iter().for_each(|step| push_step(xml, step));
+                }
+            }
+        },
+    }
+}
+
+/// Workout description with the threshold the percentages refer to, when known
+fn description(workout: &StructuredWorkout, thresholds: &WorkoutThresholds) -> String {
+    let reference = match workout.sport {
+        WorkoutSport::Bike => thresholds.ftp_watts.map(|ftp| format!("Targets based on an FTP of {:.0} W.", ftp)),
+        WorkoutSport::Run => thresholds.threshold_pace_seconds_per_km.map(|pace| {
+            let seconds = pace.round() as u32;
+            format!("Targets based on a threshold pace of {}:{:02} /km.", seconds / 60, seconds % 60)
+        }),
+    };
+
+    match reference {
+        Some(reference) if workout.description.is_empty() => reference,
+        Some(reference) => format!("{} {}", workout.description, reference),
+        None => workout.description.clone(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_zwo_export() {
+        let workout = StructuredWorkout {
+            name: "VO2 <max> & more".to_string(),
+            description: String::new(),
+            sport: WorkoutSport::Bike,
+            steps: vec![
+                WorkoutStep::Warmup { duration_seconds: 600, start_intensity: 0.5, end_intensity: 0.75 },
+                WorkoutStep::Repeat {
+                    count: 5,
+                    steps: vec![
+                        WorkoutStep::Steady { duration_seconds: 180, intensity: 1.1 },
+                        WorkoutStep::Steady { duration_seconds: 120, intensity: 0.55 },
+                    ],
+                },
+                WorkoutStep::Repeat {
+                    count: 2,
+                    steps: vec![WorkoutStep::Steady { duration_seconds: 60, intensity: 1.2 }],
+                },
+                WorkoutStep::Cooldown { duration_seconds: 300, start_intensity: 0.7, end_intensity: 0.45 },
+            ],
+        };
+        let thresholds = WorkoutThresholds { ftp_watts: Some(250.0), threshold_pace_seconds_per_km: None };
+
+        let xml = to_zwo(&workout, &thresholds);
+        assert!(xml.contains("<name>VO2 &lt;max&gt; &amp; more</name>"));
+        assert!(xml.contains("<description>Targets based on an FTP of 250 W.</description>"));
+        assert!(xml.contains("<sportType>bike</sportType>"));
+        assert!(xml.contains(r#"<Warmup Duration="600" PowerLow="0.50" PowerHigh="0.75"/>"#));
+        assert!(xml.contains(r#"<IntervalsT Repeat="5" OnDuration="180" OffDuration="120" OnPower="1.10" OffPower="0.55"/>"#));
+        assert_eq!(xml.matches(r#"<SteadyState Duration="60" Power="1.20"/>"#).count(), 2);
+        assert!(xml.contains(r#"<Cooldown Duration="300" PowerLow="0.70" PowerHigh="0.45"/>"#));
+    }
+}
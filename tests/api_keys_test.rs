@@ -0,0 +1,168 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for API key management and API key authentication over MCP
+
+use anyhow::Result;
+use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::mcp::multitenant::MultiTenantMcpServer;
+use pierre_mcp_server::models::ApiKeyScope;
+use pierre_mcp_server::routes::{ApiKeyRoutes, AuthRoutes, CreateApiKeyRequest, LoginRequest, RegisterRequest};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+async fn setup() -> Result<(Database, AuthManager, String)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+
+    auth_routes.register(RegisterRequest {
+        email: "agent-owner@example.com".to_string(),
+        password: "password123".to_string(),
+        display_name: None,
+    }).await?;
+    let login = auth_routes.login(LoginRequest {
+        email: "agent-owner@example.com".to_string(),
+        password: "password123".to_string(),
+    }).await?;
+
+    Ok((database, auth_manager, format!("Bearer {}", login.jwt_token)))
+}
+
+fn create_request(name: &str, scopes: Vec<ApiKeyScope>) -> CreateApiKeyRequest {
+    CreateApiKeyRequest {
+        name: name.to_string(),
+        scopes,
+        expires_in_days: None,
+    }
+}
+
+#[tokio::test]
+async fn test_create_list_and_revoke_api_keys() -> Result<()> {
+    let (database, auth_manager, jwt) = setup().await?;
+    let routes = ApiKeyRoutes::new(database, auth_manager);
+
+    let created = routes.create_api_key(Some(&jwt), CreateApiKeyRequest {
+        name: "training agent".to_string(),
+        scopes: vec![ApiKeyScope::ActivitiesRead, ApiKeyScope::GoalsWrite],
+        expires_in_days: Some(90),
+    }).await?;
+    assert!(created.api_key.starts_with(&created.key.key_prefix));
+    assert!(created.key.expires_at.is_some());
+
+    let keys = routes.list_api_keys(Some(&jwt)).await?;
+    assert_eq!(keys.len(), 1);
+    assert_eq!(keys[0].key_prefix, created.key.key_prefix);
+    assert_eq!(keys[0].scopes, vec![ApiKeyScope::ActivitiesRead, ApiKeyScope::GoalsWrite]);
+
+    // The listing never includes the secret
+    let listing = serde_json::to_string(&keys)?;
+    assert!(!listing.contains(&created.api_key));
+
+    routes.revoke_api_key(Some(&jwt), &created.key.id.to_string()).await?;
+    assert!(routes.list_api_keys(Some(&jwt)).await?[0].revoked_at.is_some());
+    assert!(routes.revoke_api_key(Some(&jwt), &created.key.id.to_string()).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_api_key_requests_are_validated() -> Result<()> {
+    let (database, auth_manager, jwt) = setup().await?;
+    let routes = ApiKeyRoutes::new(database, auth_manager);
+
+    assert!(routes.create_api_key(Some(&jwt), create_request("", vec![ApiKeyScope::Admin])).await.is_err());
+    assert!(routes.create_api_key(Some(&jwt), create_request("no scopes", vec![])).await.is_err());
+    let mut expired = create_request("expired", vec![ApiKeyScope::Admin]);
+    expired.expires_in_days = Some(0);
+    assert!(routes.create_api_key(Some(&jwt), expired).await.is_err());
+    // Far-future expiries are refused rather than overflowing the expiry date
+    for days in [3651, i64::MAX] {
+        let mut distant = create_request("distant", vec![ApiKeyScope::Admin]);
+        distant.expires_in_days = Some(days);
+        let error = routes.create_api_key(Some(&jwt), distant).await.unwrap_err();
+        assert!(error.to_string().contains("between 1 and 3650"));
+    }
+    assert!(routes.create_api_key(None, create_request("anonymous", vec![ApiKeyScope::Admin])).await.is_err());
+
+    let scopes: Vec<ApiKeyScope> = serde_json::from_value(json!(["activities:read", "goals:write", "admin"]))?;
+    assert_eq!(scopes, vec![ApiKeyScope::ActivitiesRead, ApiKeyScope::GoalsWrite, ApiKeyScope::Admin]);
+    assert!(serde_json::from_value::<ApiKeyScope>(json!("superuser")).is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_only_admin_keys_manage_keys() -> Result<()> {
+    let (database, auth_manager, jwt) = setup().await?;
+    let routes = ApiKeyRoutes::new(database, auth_manager);
+
+    let read_key = routes.create_api_key(Some(&jwt), create_request("reader", vec![ApiKeyScope::ActivitiesRead])).await?;
+    let admin_key = routes.create_api_key(Some(&jwt), create_request("admin", vec![ApiKeyScope::Admin])).await?;
+
+    let read_auth = format!("Bearer {}", read_key.api_key);
+    let error = routes.list_api_keys(Some(&read_auth)).await.unwrap_err();
+    assert!(error.to_string().contains("admin API key"));
+
+    let admin_auth = format!("Bearer {}", admin_key.api_key);
+    assert_eq!(routes.list_api_keys(Some(&admin_auth)).await?.len(), 2);
+    Ok(())
+}
+
+async fn call_tool(stream: &mut TcpStream, auth: &str, tool_name: &str, arguments: Value) -> Result<Value> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": tool_name, "arguments": arguments },
+        "auth": auth
+    });
+    stream.write_all(format!("{}\n", request).as_bytes()).await?;
+
+    let mut line = String::new();
+    BufReader::new(&mut *stream).read_line(&mut line).await?;
+    Ok(serde_json::from_str(line.trim())?)
+}
+
+#[tokio::test]
+async fn test_mcp_tools_enforce_api_key_scopes() -> Result<()> {
+    let (database, auth_manager, jwt) = setup().await?;
+    let routes = ApiKeyRoutes::new(database.clone(), auth_manager.clone());
+    let read_key = routes.create_api_key(Some(&jwt), create_request("reader", vec![ApiKeyScope::ActivitiesRead])).await?;
+    let read_auth = format!("Bearer {}", read_key.api_key);
+
+    let port = 11000 + rand::random::<u16>() % 1000;
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move { server.run(port).await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+
+    // Reading is allowed
+    let response = call_tool(&mut stream, &read_auth, "list_goals", json!({})).await?;
+    assert!(response["error"].is_null(), "unexpected error: {}", response["error"]);
+
+    // Writing goals needs goals:write
+    let response = call_tool(&mut stream, &read_auth, "set_goal", json!({
+        "title": "Run 100km",
+        "goal_type": "distance",
+        "target_value": 100000.0,
+        "target_date": "2030-01-01"
+    })).await?;
+    assert_eq!(response["error"]["code"], -32001);
+    assert!(response["error"]["message"].as_str().unwrap().contains("goals:write"));
+
+    // Connecting providers needs admin
+    let response = call_tool(&mut stream, &read_auth, "connect_strava", json!({})).await?;
+    assert_eq!(response["error"]["code"], -32001);
+
+    // A revoked key stops authenticating
+    routes.revoke_api_key(Some(&jwt), &read_key.key.id.to_string()).await?;
+    let response = call_tool(&mut stream, &read_auth, "list_goals", json!({})).await?;
+    assert_eq!(response["error"]["code"], -32000);
+
+    server_handle.abort();
+    Ok(())
+}
@@ -0,0 +1,90 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for CORS on the HTTP API
+
+use anyhow::Result;
+use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::cors::CorsPolicy;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::mcp::multitenant::MultiTenantMcpServer;
+use reqwest::{Method, StatusCode};
+
+/// Start a server that lets the dashboard's subdomains call it with credentials, returning its HTTP base URL
+async fn start_server() -> Result<(String, tokio::task::JoinHandle<Result<()>>)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let cors = CorsPolicy::new(&["https://*.example.com".to_string()], true)?;
+
+    let port = 16000 + rand::random::<u16>() % 1000 * 2;
+    let server = MultiTenantMcpServer::new(database, auth_manager).with_cors(cors);
+    let handle = tokio::spawn(async move { server.run(port).await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    Ok((format!("http://127.0.0.1:{}", port + 1), handle))
+}
+
+fn preflight(client: &reqwest::Client, url: &str, origin: &str) -> reqwest::RequestBuilder {
+    client
+        .request(Method::OPTIONS, url)
+        .header("origin", origin)
+        .header("access-control-request-method", "POST")
+        .header("access-control-request-headers", "authorization, content-type")
+}
+
+#[tokio::test]
+async fn test_preflight_from_allowed_origin() -> Result<()> {
+    let (base_url, handle) = start_server().await?;
+    let client = reqwest::Client::new();
+
+    let response = preflight(&client, &format!("{}/api/keys", base_url), "https://dashboard.example.com").send().await?;
+    assert_eq!(response.status(), StatusCode::NO_CONTENT);
+    let headers = response.headers();
+    assert_eq!(headers["access-control-allow-origin"], "https://dashboard.example.com");
+    assert_eq!(headers["access-control-allow-credentials"], "true");
+    assert!(headers["access-control-allow-headers"].to_str()?.contains("authorization"));
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_preflight_from_other_origin_is_refused() -> Result<()> {
+    let (base_url, handle) = start_server().await?;
+    let client = reqwest::Client::new();
+
+    for origin in ["https://example.com", "https://dashboard.example.org", "http://dashboard.example.com"] {
+        let response = preflight(&client, &format!("{}/api/keys", base_url), origin).send().await?;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", origin);
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+    }
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_responses_only_carry_headers_for_allowed_origins() -> Result<()> {
+    let (base_url, handle) = start_server().await?;
+    let client = reqwest::Client::new();
+    let url = format!("{}/health", base_url);
+
+    let response = client.get(&url).header("origin", "https://app.example.com").send().await?;
+    assert!(response.status().is_success());
+    assert_eq!(response.headers()["access-control-allow-origin"], "https://app.example.com");
+    assert_eq!(response.headers()["vary"], "origin");
+
+    let response = client.get(&url).header("origin", "https://evil.com").send().await?;
+    assert!(response.status().is_success());
+    assert!(!response.headers().contains_key("access-control-allow-origin"));
+
+    // Errors are readable by allowed origins too
+    let response = client.get(format!("{}/missing", base_url)).header("origin", "https://app.example.com").send().await?;
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()["access-control-allow-origin"], "https://app.example.com");
+
+    handle.abort();
+    Ok(())
+}
@@ -229,6 +229,7 @@ async fn test_config_persistence_workflow() -> Result<()> {
         api_key: Some("test_key_12345".to_string()),
         redirect_uri: None,
         scopes: None,
+        directory: None,
     });
     
     let original_config = Config { providers };
@@ -0,0 +1,321 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Activity file and export archive import against the GPX, TCX and FIT fixtures in `tests/fixtures/activities`
+
+use anyhow::Result;
+use chrono::{TimeZone, Utc};
+use pierre_mcp_server::{
+    auth::AuthManager,
+    database::{Database, generate_encryption_key},
+    mcp::multitenant::MultiTenantMcpServer,
+    models::{Activity, SportType},
+    providers::{
+        create_provider,
+        file_import::{archive::ArchiveSource, parse_activity_file, FileImportProvider},
+        FitnessProvider,
+    },
+    routes::{AuthRoutes, ImportRoutes, LoginRequest, RegisterRequest},
+};
+use std::io::{Cursor, Write};
+use std::path::Path;
+use std::time::Duration;
+use tempfile::TempDir;
+use uuid::Uuid;
+
+fn fixture(name: &str) -> Vec<u8> {
+    std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/activities").join(name))
+        .expect("fixture file")
+}
+
+#[test]
+fn test_parse_gpx_fixture() {
+    let details = parse_activity_file("mount_royal_run.gpx", &fixture("mount_royal_run.gpx")).unwrap();
+    let activity = &details.activity;
+
+    assert_eq!(activity.name, "Mount Royal loop");
+    assert_eq!(activity.sport_type, SportType::Run);
+    assert_eq!(activity.provider, "file");
+    assert!(activity.id.starts_with("file_"));
+    assert_eq!(activity.start_date, Utc.with_ymd_and_hms(2024, 6, 1, 11, 30, 0).unwrap());
+    assert_eq!(activity.duration_seconds, 600);
+    assert_eq!(activity.start_latitude, Some(45.5017));
+    assert_eq!(activity.max_heart_rate, Some(149));
+    assert!(activity.distance_meters.unwrap() > 1_500.0);
+    assert!(activity.elevation_gain.unwrap() > 0.0);
+
+    assert_eq!(details.streams.len(), 31);
+    assert_eq!(details.streams[0].cadence, Some(86));
+    assert_eq!(details.device.unwrap().product.as_deref(), Some("StravaGPX Android"));
+}
+
+#[test]
+fn test_parse_tcx_fixture() {
+    let details = parse_activity_file("track_intervals.tcx", &fixture("track_intervals.tcx")).unwrap();
+    let activity = &details.activity;
+
+    assert_eq!(activity.name, "Track intervals");
+    assert_eq!(activity.sport_type, SportType::Run);
+    assert_eq!(activity.duration_seconds, 720);
+    assert_eq!(activity.distance_meters, Some(2_520.0));
+    assert_eq!(activity.calories, Some(180));
+    assert_eq!(activity.max_heart_rate, Some(164));
+    assert_eq!(activity.max_speed, Some(4.2));
+
+    assert_eq!(details.laps.len(), 3);
+    assert_eq!(details.laps[1].average_heart_rate, Some(152));
+    assert_eq!(details.streams.len(), 27);
+    assert_eq!(details.streams[0].cadence, Some(88));
+    assert_eq!(details.streams[0].speed, Some(3.5));
+
+    let device = details.device.unwrap();
+    assert_eq!(device.product.as_deref(), Some("Forerunner 255"));
+    assert_eq!(device.serial_number.as_deref(), Some("3456789012"));
+    assert_eq!(device.software_version.as_deref(), Some("19.18"));
+}
+
+#[test]
+fn test_parse_fit_fixture() {
+    let details = parse_activity_file("threshold_ride.fit", &fixture("threshold_ride.fit")).unwrap();
+    let activity = &details.activity;
+
+    // Session totals take precedence over values derived from records
+    assert_eq!(activity.name, "Afternoon bike ride");
+    assert_eq!(activity.sport_type, SportType::Ride);
+    assert_eq!(activity.start_date, Utc.with_ymd_and_hms(2024, 5, 4, 13, 0, 0).unwrap());
+    assert_eq!(activity.duration_seconds, 600);
+    assert_eq!(activity.distance_meters, Some(5_340.0));
+    assert_eq!(activity.calories, Some(200));
+    assert_eq!(activity.average_heart_rate, Some(138));
+    assert_eq!(activity.max_heart_rate, Some(150));
+    assert_eq!(activity.elevation_gain, Some(42.0));
+    assert_eq!(activity.average_speed, Some(8.9));
+
+    // Big-endian records and compressed-timestamp records with developer fields
+    assert_eq!(details.streams.len(), 61);
+    let first = &details.streams[0];
+    assert!((first.latitude.unwrap() - 45.5).abs() < 1e-6);
+    assert!((first.altitude.unwrap() - 30.0).abs() < 1e-9);
+    assert_eq!(first.power, Some(200));
+    assert_eq!(first.cadence, Some(88));
+    assert_eq!(first.speed, Some(8.9));
+    let compressed = &details.streams[2];
+    assert_eq!(compressed.time, Utc.with_ymd_and_hms(2024, 5, 4, 13, 0, 20).unwrap());
+    assert_eq!(compressed.heart_rate, Some(121));
+    assert_eq!(compressed.distance_meters, Some(178.0));
+
+    assert_eq!(details.laps.len(), 2);
+    assert_eq!(details.laps[1].distance_meters, Some(2_670.0));
+    let device = details.device.unwrap();
+    assert_eq!(device.manufacturer.as_deref(), Some("Garmin"));
+    assert_eq!(device.serial_number.as_deref(), Some("3312345678"));
+    assert_eq!(device.software_version.as_deref(), Some("9.20"));
+}
+
+#[tokio::test]
+async fn test_directory_watch() -> Result<()> {
+    let dir = TempDir::new()?;
+    std::fs::write(dir.path().join("mount_royal_run.gpx"), fixture("mount_royal_run.gpx"))?;
+    std::fs::write(dir.path().join("broken.gpx"), b"<gpx><trk>")?;
+    std::fs::write(dir.path().join("notes.txt"), b"not an activity")?;
+
+    let mut provider = FileImportProvider::new();
+    provider.watch_directory(dir.path().to_path_buf(), Duration::from_millis(50)).await?;
+    assert_eq!(provider.get_activities(None, None).await?.len(), 1);
+
+    // Files dropped in later are picked up by the watcher
+    std::fs::write(dir.path().join("threshold_ride.fit"), fixture("threshold_ride.fit"))?;
+    std::fs::write(dir.path().join("track_intervals.tcx"), fixture("track_intervals.tcx"))?;
+    tokio::time::sleep(Duration::from_millis(300)).await;
+
+    let activities = provider.get_activities(None, None).await?;
+    assert_eq!(activities.len(), 3);
+    assert_eq!(activities[0].name, "Mount Royal loop");
+    assert_eq!(provider.get_activities(Some(1), Some(2)).await?[0].sport_type, SportType::Ride);
+
+    let stats = provider.get_stats().await?;
+    assert_eq!(stats.total_activities, 3);
+    assert_eq!(stats.total_duration, 600 + 720 + 600);
+
+    let details = provider.get_activity_details(&activities[1].id).unwrap();
+    assert_eq!(details.laps.len(), 3);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_file_provider_registration() -> Result<()> {
+    let provider = create_provider("file")?;
+    assert_eq!(provider.get_athlete().await?.provider, "file");
+    assert!(provider.get_activities(None, None).await?.is_empty());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload_endpoint() -> Result<()> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+    let registered = auth_routes
+        .register(RegisterRequest {
+            email: "upload@example.com".to_string(),
+            password: "testpass123".to_string(),
+            display_name: None,
+        })
+        .await?;
+    let user_id = Uuid::parse_str(&registered.user_id)?;
+    let jwt_token = auth_routes
+        .login(LoginRequest { email: "upload@example.com".to_string(), password: "testpass123".to_string() })
+        .await?
+        .jwt_token;
+
+    let test_port = 9000 + rand::random::<u16>() % 1000;
+    let server = MultiTenantMcpServer::new(database.clone(), auth_manager);
+    let server_handle = tokio::spawn(async move { server.run(test_port).await });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    let url = format!("http://127.0.0.1:{}/import/activities?filename=threshold_ride.fit", test_port + 1);
+    let client = reqwest::Client::new();
+    let response = client
+        .post(&url)
+        .bearer_auth(&jwt_token)
+        .body(fixture("threshold_ride.fit"))
+        .send()
+        .await?;
+    assert_eq!(response.status(), 200);
+    let body: serde_json::Value = response.json().await?;
+    assert_eq!(body["activity"]["sport_type"], "ride");
+    assert_eq!(body["stream_points"], 61);
+    assert_eq!(body["laps"], 2);
+    assert_eq!(body["device"]["manufacturer"], "Garmin");
+
+    let activity_id = body["activity"]["id"].as_str().unwrap();
+    let stored = database.get_activity_details(user_id, "file", activity_id).await?.unwrap();
+    assert_eq!(stored.streams.len(), 61);
+
+    // Uploads need a valid token and a supported file
+    let unauthorized = client.post(&url).body(fixture("threshold_ride.fit")).send().await?;
+    assert_eq!(unauthorized.status(), 400);
+    let unsupported = client
+        .post(format!("http://127.0.0.1:{}/import/activities?filename=notes.txt", test_port + 1))
+        .bearer_auth(&jwt_token)
+        .body("hello")
+        .send()
+        .await?;
+    assert!(unsupported.json::<serde_json::Value>().await?["error"].as_str().unwrap().contains("Unsupported"));
+
+    server_handle.abort();
+    Ok(())
+}
+
+fn zip_of(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    let mut writer = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    for (name, bytes) in entries {
+        writer.start_file(*name, zip::write::SimpleFileOptions::default()).unwrap();
+        writer.write_all(bytes).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+fn gzip(bytes: &[u8]) -> Vec<u8> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(bytes).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Request body delivered as a single chunk, as warp's body stream yields it
+fn body(bytes: Vec<u8>) -> impl futures_util::Stream<Item = std::io::Result<warp::hyper::body::Bytes>> {
+    futures_util::stream::iter([Ok(warp::hyper::body::Bytes::from(bytes))])
+}
+
+#[tokio::test]
+async fn test_archive_import_deduplicates() -> Result<()> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+    let user_id = Uuid::parse_str(
+        &auth_routes
+            .register(RegisterRequest {
+                email: "archive@example.com".to_string(),
+                password: "testpass123".to_string(),
+                display_name: None,
+            })
+            .await?
+            .user_id,
+    )?;
+    let jwt_token = auth_routes
+        .login(LoginRequest { email: "archive@example.com".to_string(), password: "testpass123".to_string() })
+        .await?
+        .jwt_token;
+    let auth_header = format!("Bearer {}", jwt_token);
+
+    // The run was already synced over the Strava API
+    let mut synced: Activity = parse_activity_file("mount_royal_run.gpx", &fixture("mount_royal_run.gpx"))?.activity;
+    synced.id = "4001".to_string();
+    synced.provider = "strava".to_string();
+    database.upsert_activities(user_id, &[synced]).await?;
+
+    let csv = "Activity ID,Activity Date,Activity Name,Activity Type,Elapsed Time,Distance,Max Heart Rate,Filename,Elapsed Time,Distance\n\
+               4001,\"Jun 1, 2024, 11:30:00 AM\",Mount Royal loop,Run,600,1.80,149,activities/4001.gpx.gz,600,1800.0\n\
+               4002,\"Jan 5, 2020, 3:04:05 PM\",Treadmill,Run,1800,5.00,,,1800,5000.0\n\
+               4003,\"May 4, 2024, 1:00:00 PM\",Threshold ride,Ride,600,5.34,150,activities/4003.fit.gz,600,5340.0\n\
+               4004,\"May 5, 2024, 9:00:00 AM\",Missing file,Ride,3600,30.0,,activities/4004.fit.gz,3600,30000.0\n";
+    let strava = zip_of(&[
+        ("activities.csv", csv.as_bytes().to_vec()),
+        ("activities/4001.gpx.gz", gzip(&fixture("mount_royal_run.gpx"))),
+        ("activities/4003.fit.gz", gzip(&fixture("threshold_ride.fit"))),
+    ]);
+
+    let import_routes = ImportRoutes::new(database.clone(), auth_manager);
+    let response = import_routes.import_archive(Some(&auth_header), body(strava.clone())).await?;
+    assert_eq!(response.source, ArchiveSource::Strava);
+    assert_eq!(response.imported, 3);
+    assert_eq!(response.duplicates, 1);
+    assert_eq!(response.failed.len(), 1);
+    assert!(response.failed[0].starts_with("activities/4004.fit.gz"));
+
+    let ride = database.get_activity_details(user_id, "strava", "4003").await?.unwrap();
+    assert_eq!(ride.activity.name, "Threshold ride");
+    assert_eq!(ride.activity.sport_type, SportType::Ride);
+    assert_eq!(ride.streams.len(), 61);
+
+    // Importing the same archive again adds nothing
+    let again = import_routes.import_archive(Some(&auth_header), body(strava)).await?;
+    assert_eq!((again.imported, again.duplicates), (0, 4));
+
+    // The Garmin export holds the ride Strava already has, plus a swim only Garmin knows about
+    let summaries = serde_json::json!([{"summarizedActivitiesExport": [
+        {"activityId": 12345678901_u64, "name": "Threshold ride", "activityType": "road_biking",
+         "beginTimestamp": 1714827600000_i64, "duration": 600000.0, "distance": 534000.0},
+        {"activityId": 12345678902_u64, "name": "Pool swim", "activityType": "lap_swimming",
+         "beginTimestamp": 1714905000000_i64, "duration": 1800000.0, "distance": 150000.0}
+    ]}]);
+    let uploads = zip_of(&[("me@example.com_12345678901.fit", fixture("threshold_ride.fit"))]);
+    let garmin = zip_of(&[
+        ("DI_CONNECT/DI-Connect-Fitness/me@example.com_0_summarizedActivities.json", summaries.to_string().into_bytes()),
+        ("DI_CONNECT/DI-Connect-Uploaded-Files/UploadedFiles_0-_Part1.zip", uploads),
+    ]);
+    let response = import_routes.import_archive(Some(&auth_header), body(garmin)).await?;
+    assert_eq!(response.source, ArchiveSource::Garmin);
+    assert_eq!((response.imported, response.duplicates), (1, 1));
+
+    let query = pierre_mcp_server::activity_query::ActivityQuery::default().unpaged();
+    let swims: Vec<Activity> = database
+        .query_activities(user_id, Some("garmin"), &query)
+        .await?;
+    assert_eq!(swims.len(), 1);
+    assert_eq!(swims[0].id, "12345678902");
+    assert_eq!(swims[0].distance_meters, Some(1500.0));
+    assert_eq!(database.count_activities(user_id, None, &query).await?, 5);
+
+    // Unauthenticated uploads are rejected before the body is read
+    let unread = futures_util::stream::poll_fn(|_| -> std::task::Poll<Option<std::io::Result<warp::hyper::body::Bytes>>> {
+        panic!("archive body read before authentication")
+    });
+    assert!(import_routes.import_archive(None, unread).await.is_err());
+    assert!(import_routes.import_archive(Some(&auth_header), body(b"plain text".to_vec())).await.is_err());
+    Ok(())
+}
@@ -0,0 +1,372 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for the Garmin Connect provider against a mocked Health API
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use mockito::{Matcher, Server, ServerGuard};
+use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::config::environment::OAuthConfig;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::oauth2_client::PkceParams;
+use pierre_mcp_server::providers::garmin::{GarminPing, GarminProvider};
+use pierre_mcp_server::providers::{create_provider, AuthData, FitnessProvider};
+use pierre_mcp_server::routes::{GarminWebhookRoutes, OAuthRoutes};
+use pierre_mcp_server::models::{SportType, User};
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// The Garmin endpoints come from the environment, which all tests in this binary share
+static GARMIN_ENV: Mutex<()> = Mutex::const_new(());
+
+async fn mock_garmin() -> (ServerGuard, MutexGuard<'static, ()>) {
+    let guard = GARMIN_ENV.lock().await;
+    let server = Server::new_async().await;
+    std::env::set_var("GARMIN_API_BASE", server.url());
+    std::env::set_var("GARMIN_TOKEN_URL", format!("{}/oauth/token", server.url()));
+    std::env::set_var("GARMIN_CLIENT_ID", "garmin_client");
+    std::env::set_var("GARMIN_CLIENT_SECRET", "garmin_secret");
+    (server, guard)
+}
+
+async fn authenticated_provider() -> Result<GarminProvider> {
+    let mut provider = GarminProvider::new();
+    provider.authenticate(AuthData::OAuth2 {
+        client_id: "garmin_client".to_string(),
+        client_secret: "garmin_secret".to_string(),
+        access_token: Some("garmin_access".to_string()),
+        refresh_token: Some("garmin_refresh".to_string()),
+    }).await?;
+    Ok(provider)
+}
+
+fn activity_summary(activity_id: u64, start: DateTime<Utc>) -> serde_json::Value {
+    json!({
+        "summaryId": activity_id.to_string(),
+        "activityId": activity_id,
+        "activityName": "Lunch Run",
+        "activityType": "RUNNING",
+        "startTimeInSeconds": start.timestamp(),
+        "durationInSeconds": 1800,
+        "distanceInMeters": 5000.0,
+        "averageHeartRateInBeatsPerMinute": 148,
+        "activeKilocalories": 380,
+        "deviceName": "fenix7"
+    })
+}
+
+fn token_response() -> serde_json::Value {
+    json!({
+        "access_token": "new_garmin_access",
+        "token_type": "bearer",
+        "expires_in": 86400,
+        "refresh_token": "new_garmin_refresh",
+        "scope": "ACTIVITY_EXPORT HEALTH_EXPORT",
+        "refresh_token_expires_in": 7775998
+    })
+}
+
+#[tokio::test]
+async fn test_garmin_pulls_summaries_in_daily_windows() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let end = Utc::now();
+    let start = end - Duration::hours(36);
+
+    let activities = server.mock("GET", "/activities")
+        .match_header("authorization", "Bearer garmin_access")
+        .match_query(Matcher::AllOf(vec![
+            Matcher::Regex("uploadStartTimeInSeconds=\\d+".to_string()),
+            Matcher::Regex("uploadEndTimeInSeconds=\\d+".to_string()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(json!([activity_summary(42, start)]).to_string())
+        .expect(2)
+        .create_async()
+        .await;
+
+    let provider = authenticated_provider().await?;
+    let summaries = provider.get_activity_summaries(start, end).await?;
+    activities.assert_async().await;
+
+    assert_eq!(summaries.len(), 2);
+    let activity = &summaries[0];
+    assert_eq!(activity.id, "42");
+    assert_eq!(activity.provider, "garmin");
+    assert_eq!(activity.sport_type, SportType::Run);
+    assert_eq!(activity.distance_meters, Some(5000.0));
+    assert_eq!(activity.average_heart_rate, Some(148));
+    assert_eq!(activity.calories, Some(380));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_activity_details_and_dailies() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let end = Utc::now();
+    let start = end - Duration::hours(6);
+
+    let _details = server.mock("GET", "/activityDetails")
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(json!([{
+            "summaryId": "42-detail",
+            "activityId": 42,
+            "summary": activity_summary(42, start),
+            "samples": [
+                {"startTimeInSeconds": start.timestamp(), "latitudeInDegree": 45.5, "longitudeInDegree": -73.6,
+                 "elevationInMeters": 30.0, "heartRate": 140, "totalDistanceInMeters": 0.0, "powerInWatts": 250.0},
+                {"startTimeInSeconds": start.timestamp() + 5, "latitudeInDegree": 45.5001, "longitudeInDegree": -73.6,
+                 "elevationInMeters": 31.0, "heartRate": 142, "totalDistanceInMeters": 14.0, "powerInWatts": 260.0}
+            ],
+            "laps": [{"startTimeInSeconds": start.timestamp()}]
+        }]).to_string())
+        .create_async()
+        .await;
+
+    let _dailies = server.mock("GET", "/dailies")
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(json!([{
+            "summaryId": "daily-1",
+            "calendarDate": "2024-03-10",
+            "startTimeInSeconds": 1_710_028_800,
+            "durationInSeconds": 86400,
+            "steps": 10432,
+            "distanceInMeters": 8120.0,
+            "activeKilocalories": 610,
+            "bmrKilocalories": 1720,
+            "restingHeartRateInBeatsPerMinute": 52,
+            "floorsClimbed": 7,
+            "averageStressLevel": 31
+        }]).to_string())
+        .create_async()
+        .await;
+
+    let provider = authenticated_provider().await?;
+
+    let details = provider.get_activity_details(start, end).await?;
+    assert_eq!(details.len(), 1);
+    assert_eq!(details[0].activity.id, "42");
+    assert_eq!(details[0].streams.len(), 2);
+    assert_eq!(details[0].streams[1].power, Some(260));
+    assert_eq!(details[0].laps.len(), 1);
+    assert_eq!(details[0].laps[0].duration_seconds, 1800);
+    assert_eq!(details[0].device.as_ref().and_then(|d| d.product.as_deref()), Some("fenix7"));
+
+    let dailies = provider.get_dailies(start, end).await?;
+    assert_eq!(dailies.len(), 1);
+    assert_eq!(dailies[0].calendar_date.to_string(), "2024-03-10");
+    assert_eq!(dailies[0].steps, Some(10432));
+    assert_eq!(dailies[0].resting_heart_rate, Some(52));
+    assert_eq!(dailies[0].average_stress_level, Some(31));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_athlete_and_recent_activities() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+
+    let _user = server.mock("GET", "/user/id")
+        .with_header("content-type", "application/json")
+        .with_body(json!({"userId": "d3315b1072421d0dd7c8f6b8e1de4df8"}).to_string())
+        .create_async()
+        .await;
+    let _activities = server.mock("GET", "/activities")
+        .match_query(Matcher::Any)
+        .with_header("content-type", "application/json")
+        .with_body(json!([
+            activity_summary(1, Utc::now() - Duration::hours(3)),
+            activity_summary(2, Utc::now() - Duration::hours(1)),
+        ]).to_string())
+        .create_async()
+        .await;
+
+    let provider = create_provider("garmin")?;
+    assert_eq!(provider.provider_name(), "Garmin");
+
+    let provider = authenticated_provider().await?;
+    let athlete = provider.get_athlete().await?;
+    assert_eq!(athlete.id, "d3315b1072421d0dd7c8f6b8e1de4df8");
+    assert_eq!(athlete.provider, "garmin");
+
+    // Enough activities arrive in the first window, newest first
+    let activities = provider.get_activities(Some(1), Some(1)).await?;
+    assert_eq!(activities.len(), 1);
+    assert_eq!(activities[0].id, "1");
+
+    let activity = provider.get_activity("2").await?;
+    assert_eq!(activity.name, "Lunch Run");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_token_exchange_and_refresh() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let pkce = PkceParams::generate();
+
+    let exchange = server.mock("POST", "/oauth/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".to_string(), "authorization_code".to_string()),
+            Matcher::UrlEncoded("code".to_string(), "auth_code".to_string()),
+            Matcher::UrlEncoded("code_verifier".to_string(), pkce.code_verifier.clone()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(token_response().to_string())
+        .create_async()
+        .await;
+    let refresh = server.mock("POST", "/oauth/token")
+        .match_body(Matcher::AllOf(vec![
+            Matcher::UrlEncoded("grant_type".to_string(), "refresh_token".to_string()),
+            Matcher::UrlEncoded("refresh_token".to_string(), "new_garmin_refresh".to_string()),
+        ]))
+        .with_header("content-type", "application/json")
+        .with_body(token_response().to_string())
+        .create_async()
+        .await;
+
+    let mut provider = authenticated_provider().await?;
+    let auth_url = provider.get_auth_url_with_pkce("http://localhost/callback", "state-1", &pkce)?;
+    assert!(auth_url.contains("code_challenge_method=S256"));
+    assert!(auth_url.contains(&format!("code_challenge={}", pkce.code_challenge)));
+
+    let (access, refresh_token) = provider.exchange_code_with_pkce("auth_code", "http://localhost/callback", &pkce).await?;
+    assert_eq!(access, "new_garmin_access");
+    assert_eq!(refresh_token, "new_garmin_refresh");
+    exchange.assert_async().await;
+
+    provider.refresh_access_token().await?;
+    refresh.assert_async().await;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_oauth_callback_stores_tokens() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let _token = server.mock("POST", "/oauth/token")
+        .match_body(Matcher::Regex("code_verifier=".to_string()))
+        .with_header("content-type", "application/json")
+        .with_body(token_response().to_string())
+        .create_async()
+        .await;
+    let _user = server.mock("GET", "/user/id")
+        .match_header("authorization", "Bearer new_garmin_access")
+        .with_header("content-type", "application/json")
+        .with_body(json!({"userId": "garmin-user-7"}).to_string())
+        .create_async()
+        .await;
+
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let user = User::new("garmin@example.com".to_string(), "hash".to_string(), None);
+    let user_id = database.create_user(&user).await?;
+
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_header = format!("Bearer {}", auth_manager.generate_token(&user)?);
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager, Arc::new(OAuthConfig::from_env()?));
+    let authorization = oauth_routes.get_auth_url(Some(&auth_header), "garmin").await?;
+    assert!(authorization.authorization_url.contains("code_challenge="));
+
+    let callback = oauth_routes.handle_callback("auth_code", &authorization.state, "garmin").await?;
+    assert_eq!(callback.provider, "garmin");
+    assert_eq!(callback.scopes, "ACTIVITY_EXPORT HEALTH_EXPORT");
+
+    let token = database.get_provider_token(user_id, "garmin").await?.unwrap();
+    assert_eq!(token.access_token, "new_garmin_access");
+    assert_eq!(token.refresh_token, "new_garmin_refresh");
+    let connections = database.get_provider_connections(user_id).await?;
+    assert_eq!(connections[0].external_athlete_id.as_deref(), Some("garmin-user-7"));
+
+    let statuses = oauth_routes.get_connection_status(user_id).await?;
+    assert!(statuses.iter().any(|status| status.provider == "garmin" && status.connected));
+
+    // The PKCE verifier is used once
+    assert!(oauth_routes.handle_callback("auth_code", &authorization.state, "garmin").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_ping_callback() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let start = Utc::now() - Duration::hours(2);
+
+    let _callback = server.mock("GET", "/callback/activities")
+        .match_query(Matcher::UrlEncoded("token".to_string(), "abc".to_string()))
+        .match_header("authorization", "Bearer garmin_access")
+        .with_header("content-type", "application/json")
+        .with_body(json!([activity_summary(77, start)]).to_string())
+        .create_async()
+        .await;
+
+    let provider = authenticated_provider().await?;
+    let ping = GarminPing {
+        summary_type: "activities".to_string(),
+        user_id: "garmin-user".to_string(),
+        callback_url: format!("{}/callback/activities?token=abc", server.url()),
+    };
+    let notification = provider.fetch_ping(&ping).await?;
+
+    assert_eq!(notification.activities.len(), 1);
+    assert_eq!(notification.activities[0].activity.id, "77");
+    assert!(notification.pings.is_empty());
+
+    // The token is never sent to a callback on another host
+    let ping = GarminPing {
+        callback_url: "https://attacker.example.com/callback/activities?token=abc".to_string(),
+        ..ping
+    };
+    assert!(provider.fetch_ping(&ping).await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_garmin_webhook_stores_pinged_activities() -> Result<()> {
+    let (mut server, _env) = mock_garmin().await;
+    let start = Utc::now() - Duration::hours(2);
+    let callback = server.mock("GET", "/callback/activities")
+        .match_header("authorization", "Bearer garmin_access")
+        .with_header("content-type", "application/json")
+        .with_body(json!([activity_summary(88, start)]).to_string())
+        .create_async()
+        .await;
+
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let user_id = database.create_user(&User::new("webhook@example.com".to_string(), "hash".to_string(), None)).await?;
+    database.update_provider_token(user_id, "garmin", "garmin_access", "garmin_refresh", Utc::now() + Duration::hours(8), "ACTIVITY_EXPORT".to_string()).await?;
+    database.update_provider_athlete_id(user_id, "garmin", "garmin-user-9").await?;
+
+    let webhook = GarminWebhookRoutes::new(database.clone(), Arc::new(OAuthConfig::from_env()?));
+    let notification = json!({
+        "activities": [
+            {"userId": "garmin-user-9", "callbackURL": format!("{}/callback/activities", server.url())},
+            {"userId": "garmin-user-9", "callbackURL": "https://attacker.example.com/callback/activities"},
+            // Pushed inline, so it can't be trusted
+            {"userId": "garmin-user-9", "summaryId": "99", "activityId": 99, "startTimeInSeconds": start.timestamp(), "durationInSeconds": 60}
+        ],
+        "dailies": [{"userId": "garmin-user-9", "callbackURL": format!("{}/callback/dailies", server.url())}]
+    });
+    let response = webhook.handle_notification(notification.to_string().as_bytes()).await?;
+    callback.assert_async().await;
+
+    assert_eq!(response.stored, 1);
+    assert_eq!(response.ignored, 2);
+    assert_eq!(response.failed.len(), 1);
+    assert!(response.failed[0].contains("attacker.example.com"));
+
+    let query = pierre_mcp_server::activity_query::ActivityQuery::default().unpaged();
+    let stored = database.query_activities(user_id, Some("garmin"), &query).await?;
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0].id, "88");
+    assert!(webhook.handle_notification(b"not json").await.is_err());
+
+    Ok(())
+}
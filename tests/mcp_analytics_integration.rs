@@ -13,9 +13,12 @@
 //! 4. Comprehensive fitness reporting
 
 use anyhow::Result;
+use chrono::{Duration, TimeZone, Utc};
 use pierre_mcp_server::auth::AuthManager;
 use pierre_mcp_server::database::{Database, generate_encryption_key};
+use pierre_mcp_server::intelligence::{Goal, TargetEvent, TrainingPlan, UserFitnessProfile};
 use pierre_mcp_server::mcp::multitenant::MultiTenantMcpServer;
+use pierre_mcp_server::models::{Activity, SportType};
 use pierre_mcp_server::routes::{AuthRoutes, RegisterRequest, LoginRequest};
 use serde_json::{json, Value};
 use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
@@ -151,9 +154,9 @@ async fn test_mcp_server_initialization() -> Result<()> {
     assert_eq!(init_response["jsonrpc"], "2.0");
     assert!(init_response["result"]["capabilities"]["tools"].is_array());
     
-    // Check that we have all 21 expected tools
+    // Check that we have all 36 expected tools
     let tools = init_response["result"]["capabilities"]["tools"].as_array().unwrap();
-    assert_eq!(tools.len(), 21);
+    assert_eq!(tools.len(), 36);
     
     // Verify key analytics tools are present
     let tool_names: Vec<&str> = tools.iter()
@@ -223,6 +226,21 @@ async fn test_analytics_workflow_without_provider() -> Result<()> {
     
     assert!(feasibility["result"]["feasibility_analysis"]["feasible"].is_boolean());
     assert!(feasibility["result"]["feasibility_analysis"]["confidence"].is_number());
+
+    // History-based tools report the failed sync when nothing is stored yet
+    let patterns = client.call_tool("detect_patterns", json!({
+        "provider": "strava",
+        "pattern_type": "all"
+    })).await?;
+    assert!(patterns["result"].is_null());
+    assert!(patterns["error"]["message"].as_str().unwrap().contains("Failed to get activities"));
+
+    let patterns = client.call_tool("detect_patterns", json!({
+        "provider": "strava",
+        "pattern_type": "all",
+        "timezone": "Mars/Olympus_Mons"
+    })).await?;
+    assert_eq!(patterns["error"]["code"], -32602);
     
     server_handle.abort();
     Ok(())
@@ -278,6 +296,603 @@ async fn test_goal_management_workflow() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_goal_lifecycle_tools() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (_user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    // Frequency goals need a valid sessions_per_week
+    let invalid = client.call_tool("set_goal", json!({
+        "title": "Run more",
+        "goal_type": "frequency",
+        "sport_type": "run",
+        "target_value": 12.0,
+        "target_date": "2030-01-31",
+        "sessions_per_week": 0
+    })).await?;
+    assert!(invalid["error"]["message"].as_str().unwrap().contains("sessions_per_week"));
+
+    let created = client.call_tool("set_goal", json!({
+        "title": "Run 4 times a week",
+        "goal_type": "frequency",
+        "sport_type": "run",
+        "target_value": 12.0,
+        "target_date": "2030-01-31",
+        "sessions_per_week": 4
+    })).await?;
+    let goal_id = created["result"]["goal_created"]["goal_id"].as_str().unwrap().to_string();
+    assert_eq!(created["result"]["goal_created"]["goal"]["goal_type"]["sessions_per_week"], 4);
+
+    let updated = client.call_tool("update_goal", json!({
+        "goal_id": goal_id,
+        "title": "Run 3 times a week",
+        "current_value": 6.0
+    })).await?;
+    assert_eq!(updated["result"]["goal_updated"]["goal"]["title"], "Run 3 times a week");
+
+    let paused = client.call_tool("pause_goal", json!({ "goal_id": goal_id })).await?;
+    assert_eq!(paused["result"]["goal_updated"]["status"], "paused");
+
+    let listed = client.call_tool("list_goals", json!({ "status": "paused" })).await?;
+    assert_eq!(listed["result"]["total"], 1);
+    assert_eq!(listed["result"]["goals"][0]["progress_percentage"], 50.0);
+
+    let completed = client.call_tool("complete_goal", json!({ "goal_id": goal_id })).await?;
+    assert_eq!(completed["result"]["goal_updated"]["status"], "completed");
+
+    // Completed goals are final
+    let resumed = client.call_tool("update_goal", json!({ "goal_id": goal_id, "status": "active" })).await?;
+    assert!(resumed["error"].is_object());
+
+    let deleted = client.call_tool("delete_goal", json!({ "goal_id": goal_id })).await?;
+    assert_eq!(deleted["result"]["goal_deleted"]["goal_id"], goal_id.as_str());
+
+    let listed = client.call_tool("list_goals", json!({})).await?;
+    assert_eq!(listed["result"]["total"], 0);
+
+    let missing = client.call_tool("delete_goal", json!({ "goal_id": goal_id })).await?;
+    assert!(missing["error"].is_object());
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_goal_progress_from_activity_store() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    // Goals set three weeks ago, followed by five 6km runs
+    let now = Utc::now();
+    let mut distance_goal = Goal::from_args(&user_id.to_string(), &json!({
+        "title": "Run 50km this month",
+        "goal_type": "distance",
+        "sport_type": "run",
+        "target_value": 50.0,
+        "target_date": (now + Duration::days(40)).to_rfc3339()
+    }))?;
+    distance_goal.created_at = now - Duration::days(20);
+    let mut frequency_goal = Goal::from_args(&user_id.to_string(), &json!({
+        "title": "Three runs",
+        "goal_type": "frequency",
+        "sport_type": "run",
+        "sessions_per_week": 2,
+        "target_value": 3.0,
+        "target_date": (now + Duration::days(40)).to_rfc3339()
+    }))?;
+    frequency_goal.created_at = now - Duration::days(20);
+    database.create_goal(&distance_goal).await?;
+    database.create_goal(&frequency_goal).await?;
+
+    let runs: Vec<Activity> = (0..5)
+        .map(|i| Activity {
+            id: format!("run_{}", i),
+            start_date: now - Duration::days(15 - i * 3),
+            distance_meters: Some(6000.0),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        })
+        .collect();
+    database.upsert_activities(user_id, &runs).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    let progress = client.call_tool("track_progress", json!({ "goal_id": distance_goal.id })).await?;
+    let report = &progress["result"]["progress_report"];
+    assert_eq!(report["goal"]["current_value"], 30.0);
+    assert_eq!(report["progress_percentage"], 60.0);
+
+    let milestones = report["milestones"].as_array().unwrap();
+    assert_eq!(milestones.len(), 4);
+    assert_eq!(milestones.iter().filter(|m| m["achieved"] == true).count(), 2);
+    assert!(milestones[0]["achieved_date"].is_string());
+
+    // Well ahead of schedule, so a higher target is suggested
+    assert_eq!(report["suggested_adjustment"]["metadata"]["adjustment_type"], "increase_target");
+
+    // The frequency goal reached its target and completed itself
+    let completed = client.call_tool("list_goals", json!({ "status": "completed" })).await?;
+    assert_eq!(completed["result"]["total"], 1);
+    assert_eq!(completed["result"]["goals"][0]["id"], frequency_goal.id.as_str());
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_training_plan_tools() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    // A plan generated ten days ago whose first week ends today, with every session done
+    let today = Utc::now().date_naive();
+    let event = TargetEvent::from_args(&json!({
+        "sport_type": "run",
+        "distance_km": 10.0,
+        "event_date": (today + Duration::weeks(8)).to_string()
+    }))?;
+    let plan = TrainingPlan::generate(
+        &user_id.to_string(),
+        &UserFitnessProfile::new(&user_id.to_string()),
+        event,
+        &[],
+        today - Duration::days(10),
+    )?;
+    let first_week: Vec<Activity> = plan.weeks[0]
+        .workouts
+        .iter()
+        .enumerate()
+        .map(|(i, workout)| Activity {
+            id: format!("plan_run_{}", i),
+            sport_type: SportType::Run,
+            start_date: workout.date.and_hms_opt(7, 0, 0).unwrap().and_utc(),
+            duration_seconds: u64::from(workout.duration_minutes) * 60,
+            provider: "strava".to_string(),
+            ..Activity::default()
+        })
+        .collect();
+    database.create_training_plan(&plan).await?;
+    database.upsert_activities(user_id, &first_week).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    let stored = client.call_tool("get_training_plan", json!({ "plan_id": plan.id })).await?;
+    let compliance = &stored["result"]["compliance"];
+    assert_eq!(compliance["weeks"][0]["workouts_completed"], first_week.len());
+    assert_eq!(compliance["weeks"][0]["score"], 100.0);
+    assert_eq!(compliance["workouts_completed"], first_week.len());
+
+    let created = client.call_tool("generate_training_plan", json!({
+        "sport_type": "run",
+        "distance_km": 21.1,
+        "event_date": (today + Duration::weeks(12)).to_string(),
+        "goal_time_minutes": 110,
+        "hours_per_week": 5,
+        "preferred_days": ["tuesday", "thursday", "saturday", "sunday"]
+    })).await?;
+    let new_plan = &created["result"]["training_plan"];
+    assert_eq!(new_plan["weeks"].as_array().unwrap().len(), 12);
+    assert_eq!(new_plan["weeks"][0]["phase"], "base");
+    assert_eq!(new_plan["weeks"][11]["phase"], "taper");
+    let race = new_plan["weeks"][11]["workouts"].as_array().unwrap().last().unwrap();
+    assert_eq!(race["workout_type"], "race");
+
+    // Without an ID the newest plan is returned
+    let latest = client.call_tool("get_training_plan", json!({})).await?;
+    assert_eq!(latest["result"]["training_plan"]["id"], created["result"]["plan_id"]);
+    assert!(latest["result"]["compliance"]["score"].is_null());
+
+    let too_soon = client.call_tool("generate_training_plan", json!({
+        "sport_type": "run",
+        "distance_km": 5.0,
+        "event_date": (today + Duration::weeks(2)).to_string()
+    })).await?;
+    assert!(too_soon["error"]["message"].as_str().unwrap().contains("at least 4 weeks"));
+
+    let bad_day = client.call_tool("generate_training_plan", json!({
+        "sport_type": "run",
+        "distance_km": 5.0,
+        "event_date": (today + Duration::weeks(6)).to_string(),
+        "preferred_days": ["someday"]
+    })).await?;
+    assert!(bad_day["error"].is_object());
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_workout() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+    database.upsert_user_profile(user_id, json!({ "ftp_watts": 250.0 })).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    let created = client.call_tool("generate_training_plan", json!({
+        "sport_type": "ride",
+        "distance_km": 100.0,
+        "event_date": (Utc::now().date_naive() + Duration::weeks(10)).to_string(),
+        "hours_per_week": 6
+    })).await?;
+    let plan = &created["result"]["training_plan"];
+    let workout = &plan["weeks"][0]["workouts"][0];
+
+    let zwo = client.call_tool("export_workout", json!({
+        "format": "zwo",
+        "date": workout["date"]
+    })).await?;
+    let resource = &zwo["result"]["content"][0]["resource"];
+    assert_eq!(zwo["result"]["content"][0]["type"], "resource");
+    assert_eq!(resource["mimeType"], "application/xml");
+    assert_eq!(
+        resource["uri"],
+        format!("workout://{}/{}.zwo", plan["id"].as_str().unwrap(), workout["date"].as_str().unwrap()).as_str()
+    );
+    let xml = resource["text"].as_str().unwrap();
+    assert!(xml.contains("<sportType>bike</sportType>"));
+    assert!(xml.contains("FTP of 250 W"));
+
+    // ERG targets use the stored FTP unless one is passed in
+    let erg = client.call_tool("export_workout", json!({
+        "format": "erg",
+        "date": workout["date"],
+        "ftp_watts": 300
+    })).await?;
+    assert!(erg["result"]["content"][0]["resource"]["text"].as_str().unwrap().contains("FTP = 300"));
+
+    let fit = client.call_tool("export_workout", json!({
+        "format": "fit",
+        "name": "Threshold 3x8",
+        "steps": [
+            {"type": "warmup", "duration_seconds": 600, "start_intensity": 0.5, "end_intensity": 0.75},
+            {"type": "repeat", "count": 3, "steps": [
+                {"type": "steady", "duration_seconds": 480, "intensity": 1.0},
+                {"type": "steady", "duration_seconds": 120, "intensity": 0.5}
+            ]}
+        ]
+    })).await?;
+    let resource = &fit["result"]["content"][0]["resource"];
+    assert_eq!(resource["uri"], "workout://custom/threshold-3x8.fit");
+    assert_eq!(resource["mimeType"], "application/vnd.ant.fit");
+    let blob = resource["blob"].as_str().unwrap();
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, blob)?;
+    assert_eq!(&bytes[8..12], b".FIT");
+
+    let missing = client.call_tool("export_workout", json!({
+        "format": "zwo",
+        "date": "1999-01-01"
+    })).await?;
+    assert!(missing["error"]["message"].as_str().unwrap().contains("No workout planned"));
+
+    let bad_format = client.call_tool("export_workout", json!({ "format": "pdf", "date": workout["date"] })).await?;
+    assert!(bad_format["error"].is_object());
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_calendar_feed() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (_user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    client.call_tool("set_goal", json!({
+        "title": "Spring century",
+        "goal_type": "distance",
+        "target_value": 400.0,
+        "target_date": (Utc::now().date_naive() + Duration::weeks(8)).to_string(),
+        "sport_type": "ride"
+    })).await?;
+    client.call_tool("generate_training_plan", json!({
+        "sport_type": "run",
+        "distance_km": 10.0,
+        "event_date": (Utc::now().date_naive() + Duration::weeks(6)).to_string()
+    })).await?;
+
+    let feed = client.call_tool("get_calendar_feed", json!({})).await?;
+    let feed_url = feed["result"]["feed_url"].as_str().unwrap().to_string();
+    assert!(feed_url.ends_with(".ics"));
+    let resource = &feed["result"]["content"][0]["resource"];
+    assert_eq!(resource["mimeType"], "text/calendar");
+    let ics = resource["text"].as_str().unwrap();
+    assert!(ics.starts_with("BEGIN:VCALENDAR\r\n"));
+    assert!(ics.contains("SUMMARY:Goal deadline: Spring century"));
+    assert!(ics.contains("SUMMARY:Milestone checkpoint: Halfway Point"));
+    assert!(ics.contains("CATEGORIES:WORKOUT"));
+
+    // The URL is only shown when issued; later calls return the calendar without it
+    let again = client.call_tool("get_calendar_feed", json!({})).await?;
+    assert!(again["result"]["feed_url"].is_null());
+    assert!(again["result"]["message"].as_str().unwrap().contains("rotate_token"));
+    assert!(again["result"]["content"][0]["resource"]["text"].as_str().unwrap().contains("Spring century"));
+
+    // Calendar apps fetch the feed over HTTP with only the token
+    let token = feed_url.rsplit('/').next().unwrap();
+    let url = format!("http://127.0.0.1:{}/calendar/{}", test_port + 1, token);
+    let response = reqwest::get(&url).await?;
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"].to_str()?.starts_with("text/calendar"));
+    assert!(response.text().await?.contains("Spring century"));
+
+    let rotated = client.call_tool("get_calendar_feed", json!({ "rotate_token": true })).await?;
+    assert_ne!(rotated["result"]["feed_url"], feed_url.as_str());
+    assert_eq!(reqwest::get(&url).await?.status(), 404);
+
+    let revoked = client.call_tool("revoke_calendar_feed", json!({})).await?;
+    assert_eq!(revoked["result"]["revoked"], true);
+    let rotated_token = rotated["result"]["feed_url"].as_str().unwrap().rsplit('/').next().unwrap();
+    let rotated_url = format!("http://127.0.0.1:{}/calendar/{}", test_port + 1, rotated_token);
+    assert_eq!(reqwest::get(&rotated_url).await?.status(), 404);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_query_activities_from_local_store() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    // Seed the activity store as a previous sync would have
+    let activities = vec![
+        Activity {
+            id: "run_2024".to_string(),
+            start_date: Utc.with_ymd_and_hms(2024, 5, 12, 12, 0, 0).unwrap(),
+            distance_meters: Some(21_100.0),
+            city: Some("Montreal".to_string()),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+        Activity {
+            id: "short_run_2024".to_string(),
+            start_date: Utc.with_ymd_and_hms(2024, 5, 20, 12, 0, 0).unwrap(),
+            distance_meters: Some(5_000.0),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+        Activity {
+            id: "ride_2024".to_string(),
+            sport_type: SportType::Ride,
+            start_date: Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap(),
+            distance_meters: Some(60_000.0),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+        Activity {
+            id: "run_2025".to_string(),
+            start_date: Utc.with_ymd_and_hms(2025, 2, 1, 12, 0, 0).unwrap(),
+            distance_meters: Some(30_000.0),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+    ];
+    database.upsert_activities(user_id, &activities).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    // Longest 2024 run without a provider connection
+    let longest = client.call_tool("query_activities", json!({
+        "sport_type": "run",
+        "start_date": "2024-01-01",
+        "end_date": "2024-12-31",
+        "sort_by": "distance",
+        "limit": 1
+    })).await?;
+
+    assert_eq!(longest["result"]["total_matches"], 2);
+    assert_eq!(longest["result"]["activities"][0]["id"], "run_2024");
+    assert_eq!(longest["result"]["activities"][0]["city"], "Montreal");
+
+    // Group everything by sport
+    let grouped = client.call_tool("query_activities", json!({
+        "group_by": "sport"
+    })).await?;
+
+    let groups = grouped["result"]["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[1]["key"], "run");
+    assert_eq!(groups[1]["activity_count"], 3);
+
+    // Invalid sort fields are rejected
+    let invalid = client.call_tool("query_activities", json!({
+        "sort_by": "cadence"
+    })).await?;
+    assert!(invalid["error"]["message"].as_str().unwrap().contains("sort_by"));
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_export_activities() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    // A synced summary and an imported file with streams
+    database.upsert_activities(user_id, &[Activity {
+        id: "ride_2024".to_string(),
+        name: "Lakeshore ride".to_string(),
+        sport_type: SportType::Ride,
+        start_date: Utc.with_ymd_and_hms(2024, 6, 2, 12, 0, 0).unwrap(),
+        distance_meters: Some(60_000.0),
+        provider: "strava".to_string(),
+        ..Activity::default()
+    }]).await?;
+    let gpx = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/activities/mount_royal_run.gpx"))?;
+    let run = pierre_mcp_server::providers::file_import::parse_activity_file("mount_royal_run.gpx", &gpx)?;
+    database.store_activity_details(user_id, &run).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token.clone());
+
+    let csv = client.call_tool("export_activities", json!({})).await?;
+    assert_eq!(csv["result"]["exported"], 2);
+    let resource = &csv["result"]["content"][1]["resource"];
+    assert_eq!(resource["uri"], "activities://export.csv");
+    assert_eq!(resource["mimeType"], "text/csv");
+    let text = resource["text"].as_str().unwrap();
+    assert!(text.starts_with("id,provider,name,sport_type,start_date"));
+    assert!(text.contains("ride_2024,strava,Lakeshore ride,ride,2024-06-02T12:00:00Z"));
+
+    // Track formats only include activities with recorded streams
+    let tcx = client.call_tool("export_activities", json!({ "format": "tcx" })).await?;
+    assert_eq!(tcx["result"]["exported"], 1);
+    assert_eq!(tcx["result"]["skipped"], json!(["ride_2024"]));
+    assert!(tcx["result"]["content"][0]["text"].as_str().unwrap().contains("left out: ride_2024"));
+    assert!(tcx["result"]["content"][1]["resource"]["text"].as_str().unwrap().contains("<Notes>Mount Royal loop</Notes>"));
+
+    let parquet = client.call_tool("export_activities", json!({ "format": "parquet", "sport_type": "run" })).await?;
+    assert_eq!(parquet["result"]["exported"], 1);
+    let blob = parquet["result"]["content"][1]["resource"]["blob"].as_str().unwrap();
+    let bytes = base64::Engine::decode(&base64::engine::general_purpose::STANDARD, blob)?;
+    assert_eq!(&bytes[..4], b"PAR1");
+
+    let bad_format = client.call_tool("export_activities", json!({ "format": "pdf" })).await?;
+    assert!(bad_format["error"]["message"].as_str().unwrap().contains("Unknown export format"));
+
+    // The same export as an HTTP download
+    let url = format!("http://127.0.0.1:{}/export/activities?format=gpx&min_distance_km=1", test_port + 1);
+    let http = reqwest::Client::new();
+    let response = http.get(&url).bearer_auth(&jwt_token).send().await?;
+    assert_eq!(response.status(), 200);
+    assert_eq!(response.headers()["content-type"], "application/gpx+xml");
+    assert_eq!(response.headers()["content-disposition"], "attachment; filename=\"activities.gpx\"");
+    assert_eq!(response.headers()["x-skipped-activities"], "1");
+    assert!(response.text().await?.contains("<name>Mount Royal loop</name>"));
+
+    assert_eq!(http.get(&url).send().await?.status(), 400);
+
+    server_handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_generate_period_report() -> Result<()> {
+    let (database, auth_manager, test_port) = setup_test_environment().await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    let activities = vec![
+        Activity {
+            id: "spring_run".to_string(),
+            name: "Spring Half".to_string(),
+            start_date: Utc.with_ymd_and_hms(2024, 4, 14, 12, 0, 0).unwrap(),
+            distance_meters: Some(21_100.0),
+            city: Some("Montreal".to_string()),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+        Activity {
+            id: "summer_ride".to_string(),
+            sport_type: SportType::Ride,
+            start_date: Utc.with_ymd_and_hms(2024, 7, 6, 12, 0, 0).unwrap(),
+            distance_meters: Some(80_000.0),
+            provider: "strava".to_string(),
+            ..Activity::default()
+        },
+    ];
+    database.upsert_activities(user_id, &activities).await?;
+
+    let server = MultiTenantMcpServer::new(database, auth_manager);
+    let server_handle = tokio::spawn(async move {
+        server.run(test_port).await
+    });
+
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    let mut client = McpTestClient::connect(test_port).await?;
+    client.initialize().await?;
+    client.set_token(jwt_token);
+
+    let report = client.call_tool("generate_period_report", json!({
+        "provider": "strava",
+        "period": "year",
+        "date": "2024-06-01"
+    })).await?;
+
+    let period_report = &report["result"]["period_report"];
+    assert_eq!(period_report["window"]["label"], "2024");
+    assert_eq!(period_report["activity_count"], 2);
+    assert_eq!(period_report["totals_by_sport"].as_array().unwrap().len(), 2);
+    assert_eq!(period_report["top_places"][0]["name"], "Montreal");
+    assert!(report["result"]["markdown"].as_str().unwrap().starts_with("# Year in Review: 2024"));
+
+    let invalid = client.call_tool("generate_period_report", json!({
+        "provider": "strava",
+        "period": "fortnight"
+    })).await?;
+    assert!(invalid["error"]["message"].as_str().unwrap().contains("fortnight"));
+
+    server_handle.abort();
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_analytics_tools_comprehensive() -> Result<()> {
     let (database, auth_manager, test_port) = setup_test_environment().await?;
@@ -379,7 +994,21 @@ async fn test_error_handling_invalid_tool() -> Result<()> {
 #[tokio::test] 
 async fn test_fitness_report_generation_workflow() -> Result<()> {
     let (database, auth_manager, test_port) = setup_test_environment().await?;
-    let (_user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+    let (user_id, jwt_token) = create_test_user(&database, &auth_manager).await?;
+
+    // History synced before Strava was disconnected
+    let now = Utc::now();
+    let runs: Vec<Activity> = (0..6)
+        .map(|i| Activity {
+            id: format!("run_{}", i),
+            start_date: now - Duration::days(i * 4),
+            distance_meters: Some(8000.0),
+            duration_seconds: 2700,
+            provider: "strava".to_string(),
+            ..Activity::default()
+        })
+        .collect();
+    database.upsert_activities(user_id, &runs).await?;
     
     let server = MultiTenantMcpServer::new(database, auth_manager);
     let server_handle = tokio::spawn(async move {
@@ -32,6 +32,7 @@ fn create_test_config() -> Config {
         api_key: Some("test_api_key".to_string()),
         redirect_uri: None,
         scopes: None,
+        directory: None,
     });
     
     Config { providers }
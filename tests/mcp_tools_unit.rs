@@ -13,14 +13,16 @@ fn test_mcp_tool_schemas() {
     // Test that all analytics tools are properly defined
     let tools = get_tools();
     
-    // Should have all 21 tools
-    assert_eq!(tools.len(), 21);
+    // Should have all 36 tools
+    assert_eq!(tools.len(), 36);
     
     // Check key analytics tools are present
     let tool_names: Vec<&str> = tools.iter().map(|t| t.name.as_str()).collect();
     
     // Core functionality
     assert!(tool_names.contains(&"get_activities"));
+    assert!(tool_names.contains(&"query_activities"));
+    assert!(tool_names.contains(&"export_activities"));
     assert!(tool_names.contains(&"get_athlete"));
     assert!(tool_names.contains(&"get_stats"));
     assert!(tool_names.contains(&"get_activity_intelligence"));
@@ -37,18 +39,29 @@ fn test_mcp_tool_schemas() {
     assert!(tool_names.contains(&"analyze_performance_trends"));
     assert!(tool_names.contains(&"compare_activities"));
     assert!(tool_names.contains(&"detect_patterns"));
+    assert!(tool_names.contains(&"generate_period_report"));
     
     // Goal management
     assert!(tool_names.contains(&"set_goal"));
     assert!(tool_names.contains(&"track_progress"));
     assert!(tool_names.contains(&"suggest_goals"));
     assert!(tool_names.contains(&"analyze_goal_feasibility"));
+    assert!(tool_names.contains(&"list_goals"));
+    assert!(tool_names.contains(&"update_goal"));
+    assert!(tool_names.contains(&"pause_goal"));
+    assert!(tool_names.contains(&"complete_goal"));
+    assert!(tool_names.contains(&"delete_goal"));
     
     // Advanced analytics
     assert!(tool_names.contains(&"generate_recommendations"));
     assert!(tool_names.contains(&"calculate_fitness_score"));
     assert!(tool_names.contains(&"predict_performance"));
     assert!(tool_names.contains(&"analyze_training_load"));
+    assert!(tool_names.contains(&"generate_training_plan"));
+    assert!(tool_names.contains(&"get_training_plan"));
+    assert!(tool_names.contains(&"export_workout"));
+    assert!(tool_names.contains(&"get_calendar_feed"));
+    assert!(tool_names.contains(&"revoke_calendar_feed"));
 }
 
 #[test]
@@ -126,7 +139,7 @@ fn test_initialize_response() {
     assert_eq!(response.protocol_version, "2024-11-05");
     assert_eq!(response.server_info.name, "pierre-mcp-server-multitenant");
     assert_eq!(response.server_info.version, "0.1.0");
-    assert_eq!(response.capabilities.tools.len(), 21);
+    assert_eq!(response.capabilities.tools.len(), 36);
 }
 
 #[test]
@@ -190,7 +203,10 @@ fn test_goal_tools_consistency() {
     let tools = get_tools();
     
     // Goal-related tools should have consistent parameter naming
-    let goal_tools = ["set_goal", "track_progress", "analyze_goal_feasibility"];
+    let goal_tools = [
+        "set_goal", "track_progress", "analyze_goal_feasibility",
+        "list_goals", "update_goal", "pause_goal", "complete_goal", "delete_goal"
+    ];
     
     for tool_name in &goal_tools {
         let tool = tools.iter()
@@ -101,8 +101,9 @@ async fn test_database_encryption() -> Result<()> {
     
     // Store encrypted Strava token
     let expires_at = chrono::Utc::now() + chrono::Duration::hours(6);
-    database.update_strava_token(
+    database.update_provider_token(
         user_id,
+        "strava",
         "secret_access_token_123",
         "secret_refresh_token_456",
         expires_at,
@@ -110,7 +111,7 @@ async fn test_database_encryption() -> Result<()> {
     ).await?;
     
     // Retrieve and decrypt token
-    let decrypted_token = database.get_strava_token(user_id).await?.unwrap();
+    let decrypted_token = database.get_provider_token(user_id, "strava").await?.unwrap();
     assert_eq!(decrypted_token.access_token, "secret_access_token_123");
     assert_eq!(decrypted_token.refresh_token, "secret_refresh_token_456");
     assert_eq!(decrypted_token.scope, "read,activity:read_all");
@@ -135,11 +136,11 @@ async fn test_jwt_edge_cases() -> Result<()> {
     assert_eq!(claims.email, "jwt@test.com");
     assert_eq!(claims.sub, user.id.to_string());
     
-    // Test token refresh
-    let refreshed_token = auth_manager.refresh_token(&token, &user)?;
-    let refreshed_claims = auth_manager.validate_token(&refreshed_token)?;
-    assert_eq!(refreshed_claims.email, claims.email);
-    assert_eq!(refreshed_claims.sub, claims.sub);
+    // Every token gets its own ID
+    let second_token = auth_manager.generate_token(&user)?;
+    let second_claims = auth_manager.validate_token(&second_token)?;
+    assert_eq!(second_claims.sub, claims.sub);
+    assert_ne!(second_claims.jti, claims.jti);
     
     // Test invalid token
     let invalid_token = "invalid.token.here";
@@ -182,16 +183,18 @@ async fn test_user_isolation() -> Result<()> {
     // Store tokens for each user
     let expires_at = chrono::Utc::now() + chrono::Duration::hours(6);
     
-    database.update_strava_token(
+    database.update_provider_token(
         user1_id,
+        "strava",
         "user1_access_token",
         "user1_refresh_token",
         expires_at,
         "read,activity:read_all".to_string(),
     ).await?;
     
-    database.update_strava_token(
+    database.update_provider_token(
         user2_id,
+        "strava",
         "user2_access_token",
         "user2_refresh_token",
         expires_at,
@@ -199,10 +202,10 @@ async fn test_user_isolation() -> Result<()> {
     ).await?;
     
     // Verify user isolation - each user can only access their own tokens
-    let user1_token = database.get_strava_token(user1_id).await?.unwrap();
+    let user1_token = database.get_provider_token(user1_id, "strava").await?.unwrap();
     assert_eq!(user1_token.access_token, "user1_access_token");
     
-    let user2_token = database.get_strava_token(user2_id).await?.unwrap();
+    let user2_token = database.get_provider_token(user2_id, "strava").await?.unwrap();
     assert_eq!(user2_token.access_token, "user2_access_token");
     
     // Verify users cannot access each other's data
@@ -7,13 +7,29 @@
 //! End-to-end tests for OAuth flow with MCP integration
 
 use pierre_mcp_server::{
+    config::environment::{OAuthConfig, OAuthProviderConfig},
     mcp::multitenant::MultiTenantMcpServer,
     database::{Database, generate_encryption_key},
     auth::AuthManager,
 };
 use serde_json::json;
+use std::sync::Arc;
 use tokio::time::{sleep, Duration};
 
+/// Strava client credentials, as `ServerConfig` would load them
+fn oauth_config() -> Arc<OAuthConfig> {
+    Arc::new(OAuthConfig {
+        strava: OAuthProviderConfig {
+            client_id: Some("strava_client".to_string()),
+            client_secret: Some("strava_secret".to_string()),
+            redirect_uri: Some("http://localhost:8081/oauth/callback/strava".to_string()),
+            scopes: vec![],
+            enabled: true,
+        },
+        ..Default::default()
+    })
+}
+
 /// Test the complete OAuth flow through MCP tools
 #[tokio::test]
 async fn test_oauth_flow_through_mcp() {
@@ -91,7 +107,7 @@ async fn test_oauth_flow_through_mcp() {
 async fn test_oauth_callback_error_handling() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database);
+    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database, AuthManager::new(vec![0u8; 64], 24), oauth_config());
     
     // Test invalid state parameter
     let result = oauth_routes.handle_callback("test_code", "invalid_state", "strava").await;
@@ -114,25 +130,35 @@ async fn test_oauth_callback_error_handling() {
 async fn test_oauth_state_csrf_protection() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database);
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database, auth_manager.clone(), oauth_config());
     
-    let user_id = uuid::Uuid::new_v4();
+    let user = pierre_mcp_server::models::User::new("csrf@example.com".to_string(), "hash".to_string(), None);
+    let auth_header = format!("Bearer {}", auth_manager.generate_token(&user).unwrap());
     
     // Generate OAuth URL and get state
-    let auth_response = oauth_routes.get_auth_url(user_id, "strava").await.unwrap();
-    
-    // Verify state contains user ID
-    assert!(auth_response.state.contains(&user_id.to_string()));
+    let auth_response = oauth_routes.get_auth_url(Some(&auth_header), "strava").await.unwrap();
     
-    // Verify state format is UUID:UUID
-    let state_parts: Vec<&str> = auth_response.state.split(':').collect();
-    assert_eq!(state_parts.len(), 2);
-    assert_eq!(state_parts[0], user_id.to_string());
-    assert!(uuid::Uuid::parse_str(state_parts[1]).is_ok());
+    // The state is opaque: nothing in it names the user
+    assert!(!auth_response.state.contains(&user.id.to_string()));
+    assert!(auth_response.state.len() >= 43);
     
     // Verify each request generates unique state
-    let auth_response2 = oauth_routes.get_auth_url(user_id, "strava").await.unwrap();
+    let auth_response2 = oauth_routes.get_auth_url(Some(&auth_header), "strava").await.unwrap();
     assert_ne!(auth_response.state, auth_response2.state);
+    
+    // A well-formed state that was never issued is rejected
+    let forged_state = pierre_mcp_server::auth::generate_oauth_state();
+    let result = oauth_routes.handle_callback("test_code", &forged_state, "strava").await;
+    assert!(result.unwrap_err().to_string().contains("Invalid state parameter"));
+    
+    // A state issued for one provider can't complete another provider's callback
+    let result = oauth_routes.handle_callback("test_code", &auth_response.state, "fitbit").await;
+    assert!(result.unwrap_err().to_string().contains("issued for strava"));
+    
+    // States are single-use, so the rejected attempt above used it up
+    let result = oauth_routes.handle_callback("test_code", &auth_response.state, "strava").await;
+    assert!(result.unwrap_err().to_string().contains("Invalid state parameter"));
 }
 
 /// Test provider connection status tracking
@@ -143,7 +169,7 @@ async fn test_connection_status_tracking() {
     let auth_manager = AuthManager::new(vec![0u8; 64], 24);
     
     // Register a test user
-    let auth_routes = pierre_mcp_server::routes::AuthRoutes::new(database.clone(), auth_manager);
+    let auth_routes = pierre_mcp_server::routes::AuthRoutes::new(database.clone(), auth_manager.clone());
     let register_request = pierre_mcp_server::routes::RegisterRequest {
         email: "status_test@example.com".to_string(),
         password: "password123".to_string(),
@@ -154,11 +180,11 @@ async fn test_connection_status_tracking() {
     let user_id = uuid::Uuid::parse_str(&register_response.user_id).unwrap();
     
     // Check initial connection status
-    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database.clone());
+    let oauth_routes = pierre_mcp_server::routes::OAuthRoutes::new(database.clone(), auth_manager, oauth_config());
     let statuses = oauth_routes.get_connection_status(user_id).await.unwrap();
     
     // Verify initial state
-    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses.len(), 3);
     for status in &statuses {
         assert!(!status.connected);
         assert!(status.expires_at.is_none());
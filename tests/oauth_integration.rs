@@ -7,12 +7,39 @@
 //! Integration tests for OAuth flow in multi-tenant mode
 
 use pierre_mcp_server::{
-    routes::{AuthRoutes, OAuthRoutes, RegisterRequest},
+    config::environment::{OAuthConfig, OAuthProviderConfig},
+    routes::{AuthRoutes, LoginRequest, OAuthRoutes, RegisterRequest},
     database::{Database, generate_encryption_key},
     auth::AuthManager,
+    mcp::multitenant::MultiTenantMcpServer,
+    models::User,
 };
+use reqwest::StatusCode;
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Client credentials for every provider, as `ServerConfig` would load them
+fn oauth_config() -> Arc<OAuthConfig> {
+    let provider = |name: &str| OAuthProviderConfig {
+        client_id: Some(format!("{}_client", name)),
+        client_secret: Some(format!("{}_secret", name)),
+        redirect_uri: Some(format!("http://localhost:8081/oauth/callback/{}", name)),
+        scopes: vec![],
+        enabled: true,
+    };
+    Arc::new(OAuthConfig {
+        strava: provider("strava"),
+        fitbit: provider("fitbit"),
+        garmin: provider("garmin"),
+    })
+}
+
+/// An `Authorization` header for a new user, who need not be stored
+fn bearer(auth_manager: &AuthManager) -> (Uuid, String) {
+    let user = User::new(format!("{}@example.com", Uuid::new_v4()), "hash".to_string(), None);
+    (user.id, format!("Bearer {}", auth_manager.generate_token(&user).unwrap()))
+}
+
 #[tokio::test]
 async fn test_oauth_authorization_url_generation() {
     // Setup
@@ -21,7 +48,7 @@ async fn test_oauth_authorization_url_generation() {
     let auth_manager = AuthManager::new(vec![0u8; 64], 24);
     
     let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager.clone(), oauth_config());
     
     // Register and login user
     let register_request = RegisterRequest {
@@ -32,57 +59,60 @@ async fn test_oauth_authorization_url_generation() {
     
     let register_response = auth_routes.register(register_request).await.unwrap();
     let user_id = Uuid::parse_str(&register_response.user_id).unwrap();
+    let login_response = auth_routes.login(LoginRequest {
+        email: "oauth_test@example.com".to_string(),
+        password: "password123".to_string(),
+    }).await.unwrap();
+    let auth_header = format!("Bearer {}", login_response.jwt_token);
     
     // Test Strava OAuth URL generation
-    let strava_auth = oauth_routes.get_auth_url(user_id, "strava").await.unwrap();
+    let strava_auth = oauth_routes.get_auth_url(Some(&auth_header), "strava").await.unwrap();
     
     assert!(strava_auth.authorization_url.contains("https://www.strava.com/oauth/authorize"));
     assert!(strava_auth.authorization_url.contains("client_id="));
     assert!(strava_auth.authorization_url.contains("redirect_uri="));
     assert!(strava_auth.authorization_url.contains("scope=read%2Cactivity%3Aread_all"));
-    assert!(strava_auth.state.contains(&user_id.to_string()));
+    assert!(!strava_auth.state.contains(&user_id.to_string()));
     assert_eq!(strava_auth.expires_in_minutes, 10);
     
     // Test Fitbit OAuth URL generation
-    let fitbit_auth = oauth_routes.get_auth_url(user_id, "fitbit").await.unwrap();
+    let fitbit_auth = oauth_routes.get_auth_url(Some(&auth_header), "fitbit").await.unwrap();
     
     assert!(fitbit_auth.authorization_url.contains("https://www.fitbit.com/oauth2/authorize"));
     assert!(fitbit_auth.authorization_url.contains("client_id="));
     assert!(fitbit_auth.authorization_url.contains("redirect_uri="));
-    assert!(fitbit_auth.authorization_url.contains("scope=activity%20profile"));
-    assert!(fitbit_auth.state.contains(&user_id.to_string()));
+    assert!(fitbit_auth.authorization_url.contains("scope=activity+profile"));
+    assert!(!fitbit_auth.state.contains(&user_id.to_string()));
 }
 
 #[tokio::test]
 async fn test_oauth_state_validation() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let _oauth_routes = OAuthRoutes::new(database.clone());
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager.clone(), oauth_config());
     
-    // Test valid state format
-    let user_id = Uuid::new_v4();
-    let state_id = Uuid::new_v4();
-    let valid_state = format!("{}:{}", user_id, state_id);
-    
-    // This should parse correctly (we can't test the full callback without mocking the HTTP client)
-    // But we can verify the state format is what we expect
-    assert!(valid_state.contains(':'));
-    let parts: Vec<&str> = valid_state.split(':').collect();
-    assert_eq!(parts.len(), 2);
-    assert!(Uuid::parse_str(parts[0]).is_ok());
-    assert!(Uuid::parse_str(parts[1]).is_ok());
+    // The state is random and says nothing about the user; the stored state does
+    let (user_id, auth_header) = bearer(&auth_manager);
+    let auth = oauth_routes.get_auth_url(Some(&auth_header), "strava").await.unwrap();
+    assert!(!auth.state.contains(':'));
+    assert!(!auth.state.contains(&user_id.to_string()));
+    
+    let stored = database.consume_oauth_state(&auth.state).await.unwrap().unwrap();
+    assert_eq!(stored.user_id, user_id);
+    assert_eq!(stored.provider, "strava");
 }
 
 #[tokio::test]
 async fn test_connection_status_no_providers() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    let oauth_routes = OAuthRoutes::new(database.clone(), AuthManager::new(vec![0u8; 64], 24), oauth_config());
     
     let user_id = Uuid::new_v4();
     let statuses = oauth_routes.get_connection_status(user_id).await.unwrap();
     
-    assert_eq!(statuses.len(), 2);
+    assert_eq!(statuses.len(), 3);
     
     let strava_status = statuses.iter().find(|s| s.provider == "strava").unwrap();
     assert!(!strava_status.connected);
@@ -93,16 +123,22 @@ async fn test_connection_status_no_providers() {
     assert!(!fitbit_status.connected);
     assert!(fitbit_status.expires_at.is_none());
     assert!(fitbit_status.scopes.is_none());
+    
+    let garmin_status = statuses.iter().find(|s| s.provider == "garmin").unwrap();
+    assert!(!garmin_status.connected);
+    assert!(garmin_status.expires_at.is_none());
+    assert!(garmin_status.scopes.is_none());
 }
 
 #[tokio::test]
 async fn test_invalid_provider_error() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager.clone(), oauth_config());
     
-    let user_id = Uuid::new_v4();
-    let result = oauth_routes.get_auth_url(user_id, "invalid_provider").await;
+    let (_, auth_header) = bearer(&auth_manager);
+    let result = oauth_routes.get_auth_url(Some(&auth_header), "invalid_provider").await;
     
     assert!(result.is_err());
     assert!(result.unwrap_err().to_string().contains("Unsupported provider"));
@@ -112,7 +148,7 @@ async fn test_invalid_provider_error() {
 async fn test_disconnect_provider() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    let oauth_routes = OAuthRoutes::new(database.clone(), AuthManager::new(vec![0u8; 64], 24), oauth_config());
     
     let user_id = Uuid::new_v4();
     
@@ -130,12 +166,13 @@ async fn test_disconnect_provider() {
 async fn test_oauth_urls_contain_required_parameters() {
     let encryption_key = generate_encryption_key().to_vec();
     let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
-    let oauth_routes = OAuthRoutes::new(database.clone());
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let oauth_routes = OAuthRoutes::new(database.clone(), auth_manager.clone(), oauth_config());
     
-    let user_id = Uuid::new_v4();
+    let (_, auth_header) = bearer(&auth_manager);
     
     // Test Strava URL parameters
-    let strava_auth = oauth_routes.get_auth_url(user_id, "strava").await.unwrap();
+    let strava_auth = oauth_routes.get_auth_url(Some(&auth_header), "strava").await.unwrap();
     let strava_url = url::Url::parse(&strava_auth.authorization_url).unwrap();
     let strava_params: std::collections::HashMap<_, _> = strava_url.query_pairs().collect();
     
@@ -147,7 +184,7 @@ async fn test_oauth_urls_contain_required_parameters() {
     assert!(strava_params.contains_key("state"));
     
     // Test Fitbit URL parameters
-    let fitbit_auth = oauth_routes.get_auth_url(user_id, "fitbit").await.unwrap();
+    let fitbit_auth = oauth_routes.get_auth_url(Some(&auth_header), "fitbit").await.unwrap();
     let fitbit_url = url::Url::parse(&fitbit_auth.authorization_url).unwrap();
     let fitbit_params: std::collections::HashMap<_, _> = fitbit_url.query_pairs().collect();
     
@@ -157,4 +194,76 @@ async fn test_oauth_urls_contain_required_parameters() {
     assert_eq!(fitbit_params.get("response_type").unwrap(), "code");
     assert!(fitbit_params.contains_key("scope"));
     assert!(fitbit_params.contains_key("state"));
-}
\ No newline at end of file
+    
+    // Both providers use PKCE
+    for params in [&strava_params, &fitbit_params] {
+        assert_eq!(params.get("code_challenge_method").unwrap(), "S256");
+        assert!(params.contains_key("code_challenge"));
+    }
+}
+
+#[tokio::test]
+async fn test_unconfigured_provider_is_refused() {
+    let encryption_key = generate_encryption_key().to_vec();
+    let database = Database::new("sqlite::memory:", encryption_key).await.unwrap();
+    let mut config = (*oauth_config()).clone();
+    config.strava.client_secret = None;
+    config.fitbit.enabled = false;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let oauth_routes = OAuthRoutes::new(database, auth_manager.clone(), Arc::new(config));
+    
+    let (_, auth_header) = bearer(&auth_manager);
+    
+    // No authorization URL is built from missing credentials
+    let result = oauth_routes.get_auth_url(Some(&auth_header), "strava").await;
+    assert!(result.unwrap_err().to_string().contains("STRAVA_CLIENT_SECRET"));
+    
+    let result = oauth_routes.get_auth_url(Some(&auth_header), "fitbit").await;
+    assert!(result.unwrap_err().to_string().contains("not enabled"));
+    
+    assert!(oauth_routes.get_auth_url(Some(&auth_header), "garmin").await.is_ok());
+}
+
+#[tokio::test]
+async fn test_auth_url_requires_the_users_own_token() {
+    let temp_dir = tempfile::TempDir::new().unwrap();
+    let db_path = temp_dir.path().join("oauth.db");
+    let database = Database::new(&format!("sqlite:{}", db_path.display()), generate_encryption_key().to_vec()).await.unwrap();
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    
+    let port = 17000 + rand::random::<u16>() % 1000 * 2;
+    let server = MultiTenantMcpServer::new(database.clone(), auth_manager.clone())
+        .with_oauth_config((*oauth_config()).clone());
+    let handle = tokio::spawn(async move { server.run(port).await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    let base_url = format!("http://127.0.0.1:{}", port + 1);
+    let client = reqwest::Client::new();
+    
+    // No token, or a token that isn't ours, starts no flow
+    let response = client.get(format!("{}/oauth/auth/strava", base_url)).send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let response = client.get(format!("{}/oauth/auth/strava", base_url))
+        .header("authorization", "Bearer forged.jwt.token")
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    
+    // Naming another user in the path, as the old route allowed, is not a route any more
+    let (user_id, auth_header) = bearer(&auth_manager);
+    let (victim_id, _) = bearer(&auth_manager);
+    let response = client.get(format!("{}/oauth/auth/strava/{}", base_url, victim_id))
+        .header("authorization", &auth_header)
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    
+    // The flow is always for the token's user
+    let response = client.get(format!("{}/oauth/auth/strava", base_url))
+        .header("authorization", &auth_header)
+        .send().await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body: serde_json::Value = response.json().await.unwrap();
+    let state = body["state"].as_str().unwrap();
+    let stored = database.consume_oauth_state(state).await.unwrap().unwrap();
+    assert_eq!(stored.user_id, user_id);
+    
+    handle.abort();
+}
@@ -0,0 +1,160 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for rate limiting on the MCP and HTTP endpoints
+
+use anyhow::Result;
+use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::config::environment::RateLimitConfig;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::mcp::multitenant::MultiTenantMcpServer;
+use pierre_mcp_server::rate_limit::{RateLimitExceeded, RateLimitKey, RateLimiter};
+use pierre_mcp_server::routes::{ApiKeyRoutes, AuthRoutes, LoginRequest, RegisterRequest};
+use std::sync::Arc;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+/// Start a server with the given limit and return its MCP port and a user's JWT
+async fn start_server(requests_per_window: u32) -> Result<(u16, String, tokio::task::JoinHandle<Result<()>>)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+    auth_routes.register(RegisterRequest {
+        email: "limited@example.com".to_string(),
+        password: "password123".to_string(),
+        display_name: None,
+    }).await?;
+    let login = auth_routes.login(LoginRequest {
+        email: "limited@example.com".to_string(),
+        password: "password123".to_string(),
+    }).await?;
+
+    let port = 12000 + rand::random::<u16>() % 1000 * 2;
+    let server = MultiTenantMcpServer::new(database, auth_manager).with_rate_limit(RateLimitConfig {
+        enabled: true,
+        requests_per_window,
+        window_seconds: 60,
+    });
+    let handle = tokio::spawn(async move { server.run(port).await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+
+    Ok((port, format!("Bearer {}", login.jwt_token), handle))
+}
+
+async fn send(stream: &mut TcpStream, request: Value) -> Result<Value> {
+    stream.write_all(format!("{}\n", request).as_bytes()).await?;
+    let mut line = String::new();
+    BufReader::new(&mut *stream).read_line(&mut line).await?;
+    Ok(serde_json::from_str(line.trim())?)
+}
+
+fn tool_call(auth: &str, tool_name: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "tools/call",
+        "params": { "name": tool_name, "arguments": {} },
+        "auth": auth
+    })
+}
+
+#[tokio::test]
+async fn test_mcp_requests_are_limited_per_ip() -> Result<()> {
+    let (port, _, handle) = start_server(2).await?;
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+    let initialize = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"});
+
+    assert!(send(&mut stream, initialize.clone()).await?["error"].is_null());
+    assert!(send(&mut stream, initialize.clone()).await?["error"].is_null());
+
+    let response = send(&mut stream, initialize).await?;
+    assert_eq!(response["error"]["code"], -32002);
+    assert_eq!(response["error"]["data"]["key_type"], "ip");
+    let retry_after = response["error"]["data"]["retry_after_secs"].as_u64().unwrap();
+    assert!(retry_after > 0 && retry_after <= 60);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_expensive_tools_have_their_own_bucket() -> Result<()> {
+    // 20 requests per window leaves 2 for expensive tools
+    let (port, jwt, handle) = start_server(20).await?;
+    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
+
+    for _ in 0..2 {
+        let response = send(&mut stream, tool_call(&jwt, "get_activity_intelligence")).await?;
+        assert_ne!(response["error"]["code"], -32002);
+    }
+    let response = send(&mut stream, tool_call(&jwt, "get_activity_intelligence")).await?;
+    assert_eq!(response["error"]["code"], -32002);
+    assert_eq!(response["error"]["data"]["bucket"], "expensive");
+    assert_eq!(response["error"]["data"]["key_type"], "user");
+
+    // Cheap tools are still allowed
+    let response = send(&mut stream, tool_call(&jwt, "list_goals")).await?;
+    assert!(response["error"].is_null(), "unexpected error: {}", response["error"]);
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_requests_get_429_with_retry_after() -> Result<()> {
+    let (port, _, handle) = start_server(2).await?;
+    let url = format!("http://127.0.0.1:{}/health", port + 1);
+    let client = reqwest::Client::new();
+
+    for _ in 0..2 {
+        assert!(client.get(&url).send().await?.status().is_success());
+    }
+    let response = client.get(&url).send().await?;
+    assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = response.headers()["retry-after"].to_str()?.parse()?;
+    assert!(retry_after > 0 && retry_after <= 60);
+    let body: Value = response.json().await?;
+    assert_eq!(body["key_type"], "ip");
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_routes_count_requests_against_the_user() -> Result<()> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+    let registered = auth_routes.register(RegisterRequest {
+        email: "routes@example.com".to_string(),
+        password: "password123".to_string(),
+        display_name: None,
+    }).await?;
+    let login = auth_routes.login(LoginRequest {
+        email: "routes@example.com".to_string(),
+        password: "password123".to_string(),
+    }).await?;
+    let jwt = format!("Bearer {}", login.jwt_token);
+
+    let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+        enabled: true,
+        requests_per_window: 2,
+        window_seconds: 60,
+    }));
+    let routes = ApiKeyRoutes::new(database, auth_manager).with_rate_limiter(rate_limiter);
+
+    // Requests that fail authentication aren't counted against anyone
+    assert!(routes.list_api_keys(Some("Bearer invalid")).await.is_err());
+    routes.list_api_keys(Some(&jwt)).await?;
+    routes.list_api_keys(Some(&jwt)).await?;
+
+    let error = routes.list_api_keys(Some(&jwt)).await.unwrap_err();
+    let exceeded = error.downcast_ref::<RateLimitExceeded>().expect("rate limit error");
+    assert_eq!(exceeded.key, RateLimitKey::User(registered.user_id.parse()?));
+
+    Ok(())
+}
@@ -0,0 +1,103 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for refresh token rotation, logout and JWT revocation
+
+use anyhow::Result;
+use pierre_mcp_server::auth::{AuthManager, McpAuthMiddleware};
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::routes::{AuthRoutes, LoginRequest, LoginResponse, RefreshTokenRequest, RegisterRequest};
+
+async fn setup(enable_refresh_tokens: bool) -> Result<(AuthRoutes, McpAuthMiddleware, LoginResponse)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 1).with_refresh_tokens(enable_refresh_tokens);
+    let auth_routes = AuthRoutes::new(database.clone(), auth_manager.clone());
+    let middleware = McpAuthMiddleware::new(auth_manager, database);
+
+    auth_routes.register(RegisterRequest {
+        email: "session@example.com".to_string(),
+        password: "password123".to_string(),
+        display_name: None,
+    }).await?;
+    let login = auth_routes.login(LoginRequest {
+        email: "session@example.com".to_string(),
+        password: "password123".to_string(),
+    }).await?;
+
+    Ok((auth_routes, middleware, login))
+}
+
+fn refresh_request(login: &LoginResponse) -> RefreshTokenRequest {
+    RefreshTokenRequest {
+        refresh_token: login.refresh_token.clone().expect("login should issue a refresh token"),
+    }
+}
+
+fn bearer(login: &LoginResponse) -> String {
+    format!("Bearer {}", login.jwt_token)
+}
+
+#[tokio::test]
+async fn test_refresh_rotates_tokens() -> Result<()> {
+    let (auth_routes, middleware, login) = setup(true).await?;
+
+    let refreshed = auth_routes.refresh(refresh_request(&login)).await?;
+    assert_ne!(refreshed.jwt_token, login.jwt_token);
+    assert_ne!(refreshed.refresh_token, login.refresh_token);
+    assert_eq!(refreshed.user.email, "session@example.com");
+
+    // The new JWT works and the one it replaced doesn't
+    assert!(middleware.authenticate_request(Some(&bearer(&refreshed))).await.is_ok());
+    assert!(middleware.authenticate_request(Some(&bearer(&login))).await.is_err());
+
+    // The new refresh token can be exchanged in turn
+    let again = auth_routes.refresh(refresh_request(&refreshed)).await?;
+    assert!(middleware.authenticate_request(Some(&bearer(&again))).await.is_ok());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_reused_refresh_token_revokes_login() -> Result<()> {
+    let (auth_routes, middleware, login) = setup(true).await?;
+    let refreshed = auth_routes.refresh(refresh_request(&login)).await?;
+
+    // Replaying the first refresh token ends every session of the login
+    let error = auth_routes.refresh(refresh_request(&login)).await.unwrap_err();
+    assert!(error.to_string().contains("already been used"));
+    assert!(middleware.authenticate_request(Some(&bearer(&refreshed))).await.is_err());
+    assert!(auth_routes.refresh(refresh_request(&refreshed)).await.is_err());
+
+    assert!(auth_routes.refresh(RefreshTokenRequest { refresh_token: "forged".to_string() }).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_logout_revokes_jwt_and_refresh_token() -> Result<()> {
+    let (auth_routes, middleware, login) = setup(true).await?;
+    assert!(middleware.authenticate_request(Some(&bearer(&login))).await.is_ok());
+
+    auth_routes.logout(Some(&bearer(&login))).await?;
+
+    let error = middleware.authenticate_request(Some(&bearer(&login))).await.unwrap_err();
+    assert!(error.to_string().contains("revoked"));
+    assert!(auth_routes.refresh(refresh_request(&login)).await.is_err());
+    assert!(auth_routes.logout(Some(&bearer(&login))).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refresh_tokens_disabled() -> Result<()> {
+    let (auth_routes, middleware, login) = setup(false).await?;
+    assert!(login.refresh_token.is_none());
+
+    let error = auth_routes.refresh(RefreshTokenRequest { refresh_token: "anything".to_string() }).await.unwrap_err();
+    assert!(error.to_string().contains("disabled"));
+
+    // Logging out still revokes the JWT
+    auth_routes.logout(Some(&bearer(&login))).await?;
+    assert!(middleware.authenticate_request(Some(&bearer(&login))).await.is_err());
+    Ok(())
+}
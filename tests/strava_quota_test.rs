@@ -0,0 +1,102 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for Strava quota tracking against a mocked Strava API
+
+use anyhow::Result;
+use chrono::Utc;
+use mockito::{Server, ServerGuard};
+use pierre_mcp_server::providers::quota::STRAVA_QUOTA;
+use pierre_mcp_server::providers::strava::StravaProvider;
+use pierre_mcp_server::providers::{rate_limited, AuthData, FitnessProvider, ProviderError};
+use reqwest::header::HeaderMap;
+use serde_json::json;
+use tokio::sync::{Mutex, MutexGuard};
+
+/// The Strava endpoint and quota are process-wide, which all tests in this binary share
+static STRAVA_ENV: Mutex<()> = Mutex::const_new(());
+
+async fn mock_strava() -> Result<(ServerGuard, StravaProvider, MutexGuard<'static, ()>)> {
+    let guard = STRAVA_ENV.lock().await;
+    let server = Server::new_async().await;
+    std::env::set_var("STRAVA_API_BASE", server.url());
+
+    // Start every test with an unused quota
+    let mut headers = HeaderMap::new();
+    headers.insert("x-ratelimit-limit", "600,30000".parse()?);
+    headers.insert("x-ratelimit-usage", "0,0".parse()?);
+    STRAVA_QUOTA.record(&headers, Utc::now());
+
+    let mut provider = StravaProvider::new();
+    provider.authenticate(AuthData::OAuth2 {
+        client_id: "strava_client".to_string(),
+        client_secret: "strava_secret".to_string(),
+        access_token: Some("access".to_string()),
+        refresh_token: None,
+    }).await?;
+    Ok((server, provider, guard))
+}
+
+fn athlete_response() -> String {
+    json!({ "id": 1234, "username": "runner", "firstname": "Ada", "lastname": "Lovelace" }).to_string()
+}
+
+#[tokio::test]
+async fn test_reported_usage_is_tracked() -> Result<()> {
+    let (mut server, provider, _env) = mock_strava().await?;
+    server.mock("GET", "/athlete")
+        .with_header("X-RateLimit-Limit", "600,30000")
+        .with_header("X-RateLimit-Usage", "100,5000")
+        .with_body(athlete_response())
+        .create_async().await;
+
+    provider.get_athlete().await?;
+
+    let status = STRAVA_QUOTA.status(Utc::now()).unwrap();
+    assert_eq!(status.short_remaining, 500);
+    assert_eq!(status.daily_remaining, 25000);
+    assert!(!status.constrained);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_requests_are_shed_once_daily_quota_is_used() -> Result<()> {
+    let (mut server, provider, _env) = mock_strava().await?;
+    let athlete = server.mock("GET", "/athlete")
+        .with_header("X-RateLimit-Limit", "600,30000")
+        .with_header("X-RateLimit-Usage", "10,30000")
+        .with_body(athlete_response())
+        .expect(1)
+        .create_async().await;
+
+    provider.get_athlete().await?;
+    assert!(STRAVA_QUOTA.is_constrained(Utc::now()));
+
+    // Shed without reaching Strava
+    let error = provider.get_athlete().await.unwrap_err();
+    match rate_limited(&error) {
+        Some(ProviderError::RateLimited { retry_after_secs, .. }) => assert!(*retry_after_secs > 0),
+        _ => panic!("expected a rate limit error, got {}", error),
+    }
+    athlete.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_too_many_requests_response_is_rate_limited() -> Result<()> {
+    let (mut server, provider, _env) = mock_strava().await?;
+    server.mock("GET", "/athlete/activities")
+        .match_query(mockito::Matcher::Any)
+        .with_status(429)
+        .with_header("X-RateLimit-Limit", "600,30000")
+        .with_header("X-RateLimit-Usage", "601,12000")
+        .with_body(r#"{"message":"Rate Limit Exceeded"}"#)
+        .create_async().await;
+
+    let error = provider.get_activities(Some(10), None).await.unwrap_err();
+    assert!(rate_limited(&error).is_some(), "expected a rate limit error, got {}", error);
+    Ok(())
+}
@@ -0,0 +1,104 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for TLS on the MCP and HTTP listeners
+
+use anyhow::Result;
+use pierre_mcp_server::auth::AuthManager;
+use pierre_mcp_server::config::environment::TlsConfig;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::mcp::multitenant::MultiTenantMcpServer;
+use pierre_mcp_server::tls;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+fn fixture(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/tls").join(name)
+}
+
+/// Start a TLS-enabled server and return its MCP port
+async fn start_server() -> Result<(u16, tokio::task::JoinHandle<Result<()>>)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let auth_manager = AuthManager::new(vec![0u8; 64], 24);
+    let acceptor = tls::acceptor(&TlsConfig {
+        enabled: true,
+        cert_path: Some(fixture("server.pem")),
+        key_path: Some(fixture("server.key")),
+    })?
+    .expect("TLS is enabled");
+
+    let port = 14000 + rand::random::<u16>() % 1000 * 2;
+    let server = MultiTenantMcpServer::new(database, auth_manager).with_tls(acceptor);
+    let handle = tokio::spawn(async move { server.run(port).await });
+    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
+    Ok((port, handle))
+}
+
+fn connector() -> Result<TlsConnector> {
+    let mut roots = RootCertStore::empty();
+    roots.add(CertificateDer::from_pem_file(fixture("ca.pem"))?)?;
+    let config = ClientConfig::builder_with_provider(Arc::new(tokio_rustls::rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+#[tokio::test]
+async fn test_mcp_over_tls() -> Result<()> {
+    let (port, handle) = start_server().await?;
+
+    let socket = TcpStream::connect(("127.0.0.1", port)).await?;
+    let mut stream = connector()?.connect(ServerName::try_from("localhost")?, socket).await?;
+    stream.write_all(format!("{}\n", json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"})).as_bytes()).await?;
+    stream.flush().await?;
+
+    let mut line = String::new();
+    BufReader::new(&mut stream).read_line(&mut line).await?;
+    let response: Value = serde_json::from_str(line.trim())?;
+    assert!(response["error"].is_null());
+    assert!(response["result"]["serverInfo"].is_object());
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_plaintext_mcp_is_refused() -> Result<()> {
+    let (port, handle) = start_server().await?;
+
+    let mut socket = TcpStream::connect(("127.0.0.1", port)).await?;
+    socket.write_all(format!("{}\n", json!({"jsonrpc": "2.0", "id": 1, "method": "initialize"})).as_bytes()).await?;
+    let mut line = String::new();
+    let read = BufReader::new(&mut socket).read_line(&mut line).await;
+    assert!(read.is_err() || !line.contains("jsonrpc"));
+
+    handle.abort();
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_http_over_tls() -> Result<()> {
+    let (port, handle) = start_server().await?;
+    let client = reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(&std::fs::read(fixture("ca.pem"))?)?)
+        .build()?;
+
+    let response = client.get(format!("https://localhost:{}/health", port + 1)).send().await?;
+    assert!(response.status().is_success());
+    let body: Value = response.json().await?;
+    assert_eq!(body["status"], "ok");
+
+    handle.abort();
+    Ok(())
+}
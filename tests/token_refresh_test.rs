@@ -0,0 +1,197 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Integration tests for provider token refresh against a mocked Strava API
+
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use mockito::{Server, ServerGuard};
+use pierre_mcp_server::config::environment::OAuthConfig;
+use pierre_mcp_server::database::{generate_encryption_key, Database};
+use pierre_mcp_server::models::User;
+use pierre_mcp_server::providers::token_refresh::{self, RefreshingProvider};
+use pierre_mcp_server::providers::FitnessProvider;
+use serde_json::json;
+use std::sync::Arc;
+use tokio::sync::{Mutex, MutexGuard};
+use uuid::Uuid;
+
+/// The Strava endpoints come from the environment, which all tests in this binary share
+static STRAVA_ENV: Mutex<()> = Mutex::const_new(());
+
+async fn mock_strava() -> (ServerGuard, MutexGuard<'static, ()>) {
+    let guard = STRAVA_ENV.lock().await;
+    let server = Server::new_async().await;
+    std::env::set_var("STRAVA_API_BASE", server.url());
+    std::env::set_var("STRAVA_TOKEN_URL", format!("{}/oauth/token", server.url()));
+    std::env::set_var("STRAVA_CLIENT_ID", "strava_client");
+    std::env::set_var("STRAVA_CLIENT_SECRET", "strava_secret");
+    (server, guard)
+}
+
+/// A user with a stored Strava token that expires after `expires_in`
+async fn connected_user(expires_in: Duration) -> Result<(Arc<Database>, Uuid)> {
+    let database = Database::new("sqlite::memory:", generate_encryption_key().to_vec()).await?;
+    let user = User::new("refresh@example.com".to_string(), "hashed_password".to_string(), None);
+    let user_id = database.create_user(&user).await?;
+    database.update_provider_token(
+        user_id,
+        "strava",
+        "old_access",
+        "old_refresh",
+        Utc::now() + expires_in,
+        "read,activity:read_all".to_string(),
+    ).await?;
+    Ok((Arc::new(database), user_id))
+}
+
+fn token_response() -> String {
+    json!({
+        "token_type": "Bearer",
+        "access_token": "new_access",
+        "refresh_token": "new_refresh",
+        "expires_at": (Utc::now() + Duration::hours(6)).timestamp(),
+        "expires_in": 21600
+    }).to_string()
+}
+
+fn athlete_response() -> String {
+    json!({ "id": 1234, "username": "runner", "firstname": "Ada", "lastname": "Lovelace" }).to_string()
+}
+
+#[tokio::test]
+async fn test_expiring_token_is_refreshed_before_use() -> Result<()> {
+    let (mut server, _env) = mock_strava().await;
+    let (database, user_id) = connected_user(Duration::minutes(1)).await?;
+
+    let refresh = server.mock("POST", "/oauth/token")
+        .match_body(mockito::Matcher::UrlEncoded("refresh_token".into(), "old_refresh".into()))
+        .with_body(token_response())
+        .expect(1)
+        .create_async().await;
+    let athlete = server.mock("GET", "/athlete")
+        .match_header("authorization", "Bearer new_access")
+        .with_body(athlete_response())
+        .create_async().await;
+
+    let provider = RefreshingProvider::connect(user_id, "strava", database.clone(), Arc::new(OAuthConfig::from_env()?)).await?;
+    assert_eq!(provider.get_athlete().await?.id, "1234");
+
+    refresh.assert_async().await;
+    athlete.assert_async().await;
+
+    // The rotated tokens are persisted
+    let stored = database.get_provider_token(user_id, "strava").await?.unwrap();
+    assert_eq!(stored.access_token, "new_access");
+    assert_eq!(stored.refresh_token, "new_refresh");
+    assert!(!stored.will_expire_soon());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rejected_token_is_refreshed_and_retried_once() -> Result<()> {
+    let (mut server, _env) = mock_strava().await;
+    let (database, user_id) = connected_user(Duration::hours(4)).await?;
+
+    let rejected = server.mock("GET", "/athlete")
+        .match_header("authorization", "Bearer old_access")
+        .with_status(401)
+        .with_body(r#"{"message":"Authorization Error"}"#)
+        .expect(1)
+        .create_async().await;
+    let refresh = server.mock("POST", "/oauth/token")
+        .with_body(token_response())
+        .expect(1)
+        .create_async().await;
+    let accepted = server.mock("GET", "/athlete")
+        .match_header("authorization", "Bearer new_access")
+        .with_body(athlete_response())
+        .expect(1)
+        .create_async().await;
+
+    let provider = RefreshingProvider::connect(user_id, "strava", database.clone(), Arc::new(OAuthConfig::from_env()?)).await?;
+    assert_eq!(provider.get_athlete().await?.username, "runner");
+
+    rejected.assert_async().await;
+    refresh.assert_async().await;
+    accepted.assert_async().await;
+    assert_eq!(database.get_provider_token(user_id, "strava").await?.unwrap().access_token, "new_access");
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_token_rejected_after_refresh_is_not_retried_again() -> Result<()> {
+    let (mut server, _env) = mock_strava().await;
+    let (database, user_id) = connected_user(Duration::hours(4)).await?;
+
+    let athlete = server.mock("GET", "/athlete")
+        .with_status(401)
+        .with_body(r#"{"message":"Authorization Error"}"#)
+        .expect(2)
+        .create_async().await;
+    let refresh = server.mock("POST", "/oauth/token")
+        .with_body(token_response())
+        .expect(1)
+        .create_async().await;
+
+    let provider = RefreshingProvider::connect(user_id, "strava", database, Arc::new(OAuthConfig::from_env()?)).await?;
+    let error = provider.get_athlete().await.unwrap_err();
+    assert!(pierre_mcp_server::providers::is_unauthorized(&error));
+
+    athlete.assert_async().await;
+    refresh.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_concurrent_refreshes_use_the_refresh_token_once() -> Result<()> {
+    let (mut server, _env) = mock_strava().await;
+    let (database, user_id) = connected_user(Duration::minutes(1)).await?;
+
+    let refresh = server.mock("POST", "/oauth/token")
+        .with_body(token_response())
+        .expect(1)
+        .create_async().await;
+
+    let oauth_config = OAuthConfig::from_env()?;
+    let (first, second) = tokio::join!(
+        token_refresh::valid_token(&database, &oauth_config, user_id, "strava"),
+        token_refresh::valid_token(&database, &oauth_config, user_id, "strava"),
+    );
+    assert_eq!(first?.access_token, "new_access");
+    assert_eq!(second?.access_token, "new_access");
+
+    refresh.assert_async().await;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_provider_without_credentials_is_refused() -> Result<()> {
+    let (database, user_id) = connected_user(Duration::hours(6)).await?;
+
+    let result = RefreshingProvider::connect(user_id, "strava", database, Arc::new(OAuthConfig::default())).await;
+    assert!(result.err().unwrap().to_string().contains("strava OAuth is not configured"));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_failed_refresh_keeps_the_stored_token() -> Result<()> {
+    let (mut server, _env) = mock_strava().await;
+    let (database, user_id) = connected_user(Duration::minutes(1)).await?;
+
+    server.mock("POST", "/oauth/token")
+        .with_status(400)
+        .with_body(r#"{"message":"Bad Request","errors":[{"field":"refresh_token","code":"invalid"}]}"#)
+        .create_async().await;
+
+    let result = RefreshingProvider::connect(user_id, "strava", database.clone(), Arc::new(OAuthConfig::from_env()?)).await;
+    assert!(result.is_err());
+
+    let stored = database.get_provider_token(user_id, "strava").await?.unwrap();
+    assert_eq!(stored.access_token, "old_access");
+    assert_eq!(stored.refresh_token, "old_refresh");
+    Ok(())
+}